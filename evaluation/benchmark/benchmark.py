@@ -48,6 +48,9 @@ prusti/tests/verify/pass-overflow/rosetta/Towers_of_Hanoi_spec.rs
 
 prusti/tests/verify/pass-overflow/nll-rfc/borrow_first.rs
 prusti/tests/verify/fail/nll-rfc/message.rs
+
+prusti/tests/verify/pass/fold-unfold-stress/deep-branching-fields.rs
+prusti/tests/verify/pass/fold-unfold-stress/deep-branching-enums.rs
 """.strip().split()
 
 
@@ -18,3 +18,56 @@ pub fn old<T>(arg: T) -> T {
 pub fn before_expiry<T>(arg: T) -> T {
     arg
 }
+
+/// This function is used to mark a subexpression of a specification that
+/// cannot be encoded. The subexpression is replaced by an unknown value, so
+/// the rest of the specification has to hold for any value of it.
+pub fn unsafe_spec<T>(arg: T) -> T {
+    arg
+}
+
+/// This function is used to compare two values structurally, by comparing
+/// their fields, whatever the `PartialEq` implementation of their type.
+/// It is what the `left === right` operator of the specifications is
+/// rewritten to. It is never evaluated, only encoded.
+pub fn snap_eq<T>(_left: &T, _right: &T) -> bool {
+    unreachable!()
+}
+
+/// This function is used to state that a specification owns the `len`
+/// consecutive values that the raw pointer `ptr` points to, i.e. the values
+/// at `ptr.add(0)` to `ptr.add(len - 1)`. It is never evaluated, only
+/// encoded.
+pub fn deref_spec<P>(_ptr: P, _len: usize) -> bool {
+    unreachable!()
+}
+
+/// This function is used to state that a specification owns the fraction
+/// `numerator / denominator` of the `len` consecutive values that the raw
+/// pointer `ptr` points to, e.g. `deref_spec_frac(ptr, len, 1, 2)` for half
+/// of them. The values can be read but not written. The fraction is given by
+/// integer literals. It is never evaluated, only encoded.
+pub fn deref_spec_frac<P>(_ptr: P, _len: usize, _numerator: u32, _denominator: u32) -> bool {
+    unreachable!()
+}
+
+/// The raw pointers, whose targets can be read by `deref_value`.
+pub trait RawPtr {
+    /// The type of the target of the pointer.
+    type Target;
+}
+
+impl<T> RawPtr for *const T {
+    type Target = T;
+}
+
+impl<T> RawPtr for *mut T {
+    type Target = T;
+}
+
+/// This function is used to read the value at `ptr.add(index)` in a
+/// specification, in a memory region owned by a `deref_spec`. It is never
+/// evaluated, only encoded.
+pub fn deref_value<P: RawPtr>(_ptr: P, _index: usize) -> P::Target {
+    unreachable!()
+}
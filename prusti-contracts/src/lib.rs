@@ -10,3 +10,47 @@
 #![warn(missing_docs)]
 
 pub mod internal;
+
+/// Assume an assertion in the body of a procedure, for example
+/// `prusti_assume!(x > 0)`. Prusti does not check the assertion, but reports
+/// it among the assumptions of the verification. Without Prusti, the macro
+/// does nothing.
+#[macro_export]
+macro_rules! prusti_assume {
+    ($($tokens:tt)*) => {};
+}
+
+/// Assert an assertion in the body of a procedure, for example
+/// `prusti_assert!(x > 0)`. Prusti reports an error if the assertion might not
+/// hold at that point. Without Prusti, the macro does nothing.
+#[macro_export]
+macro_rules! prusti_assert {
+    ($($tokens:tt)*) => {};
+}
+
+/// Refute an assertion in the body of a procedure, for example
+/// `prusti_refute!(x > 0)`. Prusti reports an error if the assertion holds in
+/// all the executions that reach that point. In particular, `prusti_refute!(false)`
+/// reports an error if the point is unreachable, which helps to spot vacuous proofs.
+/// Without Prusti, the macro does nothing.
+#[macro_export]
+macro_rules! prusti_refute {
+    ($($tokens:tt)*) => {};
+}
+
+/// Fold a `#[predicate]` function, for example `fold!(region(p))`: Prusti
+/// exchanges the permissions of the body of the predicate for the predicate,
+/// and reports an error if they are not held. Without Prusti, the macro does
+/// nothing.
+#[macro_export]
+macro_rules! fold {
+    ($($tokens:tt)*) => {};
+}
+
+/// Unfold a `#[predicate]` function, for example `unfold!(region(p))`: Prusti
+/// exchanges the predicate for the permissions of its body, and reports an
+/// error if the predicate is not held. Without Prusti, the macro does nothing.
+#[macro_export]
+macro_rules! unfold {
+    ($($tokens:tt)*) => {};
+}
@@ -24,5 +24,6 @@ extern crate serde_json;
 extern crate serde_derive;
 
 extern crate prusti_interface;
+extern crate prusti_std_models;
 
 pub mod validators;
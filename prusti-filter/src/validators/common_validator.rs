@@ -4,7 +4,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use prusti_std_models::is_modelled_type;
 use rustc::hir;
+use rustc::hir::def_id::DefId;
 use rustc::hir::map::Node;
 use rustc::middle::const_val::ConstVal;
 use rustc::mir;
@@ -38,6 +40,14 @@ macro_rules! interesting {
     };
 }
 
+/// Is `ty` the type `&str` of the string literals?
+fn is_str_ref(ty: ty::Ty) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyRef(_, inner_ty, _) => inner_ty.sty == ty::TypeVariants::TyStr,
+        _ => false,
+    }
+}
+
 /// Methods that are common to ProcedureValidator and PureFunctionValidator
 pub trait CommonValidator<'a, 'tcx: 'a> {
     fn support(&mut self) -> &mut SupportStatus;
@@ -146,7 +156,7 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
 
             ty::TypeVariants::TyForeign(..) => unsupported!(self, span, "uses foreign types"),
 
-            ty::TypeVariants::TyStr => partially!(self, span, "uses `str` types"),
+            ty::TypeVariants::TyStr => {} // OK, modelled by the sequence of its bytes
 
             ty::TypeVariants::TyArray(inner_ty, ..) => {
                 self.check_inner_ty(inner_ty, span);
@@ -154,8 +164,8 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
             }
 
             ty::TypeVariants::TySlice(inner_ty, ..) => {
+                // OK, modelled by the length and the elements, like a `Vec`
                 self.check_inner_ty(inner_ty, span);
-                unsupported!(self, span, "uses `slice` types");
             }
 
             ty::TypeVariants::TyRawPtr(..) => {
@@ -170,9 +180,14 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
                 unsupported!(self, span, "uses function pointer types")
             }
 
-            ty::TypeVariants::TyDynamic(..) => unsupported!(self, span, "uses dynamic trait types"),
+            ty::TypeVariants::TyDynamic(..) => {
+                // Trait objects are encoded abstractly, only their trait is known.
+                partially!(self, span, "uses dynamic trait types");
+            }
 
-            ty::TypeVariants::TyClosure(..) => unsupported!(self, span, "uses closures"),
+            ty::TypeVariants::TyClosure(def_id, closure_substs) => {
+                self.check_closure_upvars(def_id, closure_substs, span)
+            }
 
             ty::TypeVariants::TyGenerator(..) => unsupported!(self, span, "uses generators"),
 
@@ -186,9 +201,21 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
                 }
             }
 
-            ty::TypeVariants::TyProjection(..) => unsupported!(self, span, "uses associated types"),
+            ty::TypeVariants::TyProjection(ty::ProjectionTy { substs, .. }) => {
+                // Associated types that cannot be normalized are encoded abstractly.
+                partially!(self, span, "uses associated types");
+                for inner_ty in substs.types() {
+                    self.check_inner_ty(inner_ty, span);
+                }
+            }
 
-            ty::TypeVariants::TyAnon(..) => unsupported!(self, span, "uses anonymized types"),
+            ty::TypeVariants::TyAnon(_, substs) => {
+                // `impl Trait` types are encoded abstractly, only their trait is known.
+                partially!(self, span, "uses anonymized types");
+                for inner_ty in substs.types() {
+                    self.check_inner_ty(inner_ty, span);
+                }
+            }
 
             ty::TypeVariants::TyParam(..) => {} // OK
 
@@ -211,6 +238,9 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
         if adt_def.is_box() {
             let boxed_ty = substs.type_at(0);
             self.check_inner_ty(boxed_ty, span);
+        } else if is_modelled_type(&self.tcx().absolute_item_path_str(adt_def.did)) {
+            // The fields of the collections modelled by `prusti_std_models` are not encoded.
+            interesting!(self, "uses modelled collections of the standard library");
         } else {
             for field_def in adt_def.all_fields() {
                 let field_ty = field_def.ty(self.tcx(), substs);
@@ -219,6 +249,29 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
         }
     }
 
+    /// A closure that captures variables by value owns them, like a struct, e.g. a closure
+    /// passed to `std::thread::spawn`. A closure that captures nothing has no state, and is only
+    /// encoded when it is passed to a modelled search, e.g. `Iterator::any`.
+    fn check_closure_upvars(
+        &mut self,
+        def_id: DefId,
+        closure_substs: ty::ClosureSubsts<'tcx>,
+        span: Span,
+    ) {
+        let tcx = self.tcx();
+        for upvar_ty in closure_substs.upvar_tys(def_id, tcx) {
+            let captures_reference = upvar_ty.walk().any(|inner_ty| match inner_ty.sty {
+                ty::TypeVariants::TyRef(..) => true,
+                _ => false,
+            });
+            if captures_reference {
+                unsupported!(self, span, "uses closures that capture references");
+            } else {
+                self.check_inner_ty(upvar_ty, span);
+            }
+        }
+    }
+
     fn check_mir_stmt(&mut self, mir: &mir::Mir<'tcx>, stmt: &mir::Statement<'tcx>) {
         trace!("check_mir_stmt {:?}", stmt);
         let span = stmt.source_info.span;
@@ -688,8 +741,8 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
         match literal {
             mir::Literal::Value { value } => {
                 match value.val {
-                    ConstVal::Value(ref value) => {
-                        if value.to_scalar().is_none() {
+                    ConstVal::Value(ref const_value) => {
+                        if const_value.to_scalar().is_none() && !is_str_ref(value.ty) {
                             unsupported!(self, span, "uses non-scalar literals");
                         }
                     }
@@ -727,10 +780,12 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
                     | ty::TypeVariants::TyUint(_)
                     | ty::TypeVariants::TyChar => {} // OK
 
+                    _ if is_str_ref(value.ty) => {} // OK, a string literal
+
                     _ => unsupported!(
                         self,
                         span,
-                        "uses literals of type non-boolean, non-integer or non-char"
+                        "uses literals of type non-boolean, non-integer, non-char or non-string"
                     ),
                 };
             }
@@ -768,7 +823,9 @@ pub trait CommonValidator<'a, 'tcx: 'a> {
                 }
             }
 
-            mir::AggregateKind::Closure(..) => unsupported!(self, span, "uses closures"),
+            mir::AggregateKind::Closure(def_id, closure_substs) => {
+                self.check_closure_upvars(*def_id, *closure_substs, span)
+            }
 
             mir::AggregateKind::Generator(..) => unsupported!(self, span, "uses generators"),
         }
@@ -258,31 +258,6 @@ impl<'a, 'tcx: 'a> ProcedureValidator<'a, 'tcx> {
                     _ => {} // OK
                 }
             }
-
-            // Detect return, break, continue inside loops
-            for successor in basic_block_data.terminator().successors() {
-                if !procedure.is_reachable_block(*successor) || procedure.is_spec_block(*successor)
-                {
-                    continue;
-                }
-                let _successor_data = &mir.basic_blocks()[*successor];
-                // TODO: enable only after issue #149 is solved
-                //if let mir::TerminatorKind::Unreachable = successor_data.terminator.as_ref().unwrap().kind {
-                //    continue;
-                //}
-                //if !procedure.is_panic_block(successor) {
-                //    continue;
-                //}
-                if loops.is_out_edge(bbi, *successor) && !loops.is_loop_head(bbi) {
-                    let span = basic_block_data
-                        .terminator
-                        .as_ref()
-                        .unwrap()
-                        .source_info
-                        .span;
-                    partially!(self, span, "causes abrupt loop terminations");
-                }
-            }
         }
 
         for (_, local_levels) in &reborrowing_hints {
@@ -106,6 +106,25 @@ impl<'a, 'tcx: 'a> PureFunctionValidator<'a, 'tcx> {
 
             ty::TypeVariants::TyUint(_) => {} // OK
 
+            // Encoded as one function for each component
+            ty::TypeVariants::TyTuple(component_tys) if !component_tys.is_empty() => {
+                for component_ty in component_tys {
+                    match component_ty.sty {
+                        ty::TypeVariants::TyBool
+                        | ty::TypeVariants::TyChar
+                        | ty::TypeVariants::TyInt(_)
+                        | ty::TypeVariants::TyUint(_) => {} // OK
+
+                        _ => unsupported!(
+                            self,
+                            span,
+                            "has a tuple return value with a component of type non-integer, \
+                             non-boolean or non-char"
+                        ),
+                    }
+                }
+            }
+
             _ => unsupported!(
                 self,
                 span,
@@ -16,29 +16,56 @@ lazy_static! {
         // 1. Default values
         settings.set_default("VIPER_BACKEND", "Silicon").unwrap();
         settings.set_default("CHECK_FOLDUNFOLD_STATE", false).unwrap();
+        settings.set_default("CHECK_VIR_TYPES", cfg!(debug_assertions)).unwrap();
+        settings.set_default("CHECK_REPEATED_ENCODING", false).unwrap();
         settings.set_default("CHECK_BINARY_OPERATIONS", false).unwrap();
         settings.set_default("CHECK_PANICS", true).unwrap();
+        settings.set_default("CHECK_DEBUG_ASSERTIONS", true).unwrap();
+        settings.set_default("CHECK_UNREACHABILITY", false).unwrap();
+        settings.set_default("VERIFICATION_PROFILE", "").unwrap();
+        settings.set_default("SELECTED_VERIFICATION_PROFILE", "").unwrap();
         settings.set_default("ENCODE_UNSIGNED_NUM_CONSTRAINT", false).unwrap();
         settings.set_default("SIMPLIFY_ENCODING", true).unwrap();
         settings.set_default("ENABLE_WHITELIST", false).unwrap();
         settings.set_default::<Vec<String>>("WHITELIST", vec![]).unwrap();
+        settings.set_default("VERIFY_ONLY", "").unwrap();
         settings.set_default("LOG_DIR", "./log/").unwrap();
         settings.set_default("DUMP_DEBUG_INFO", false).unwrap();
         settings.set_default("DUMP_BRANCH_CTXT_IN_DEBUG_INFO", false).unwrap();
         settings.set_default("DUMP_REBORROWING_DAG_IN_DEBUG_INFO", false).unwrap();
         settings.set_default("DUMP_BORROWCK_INFO", false).unwrap();
+        settings.set_default("DUMP_FOLD_UNFOLD_ACTIONS", false).unwrap();
         settings.set_default("DUMP_VIPER_PROGRAM", false).unwrap();
+        settings.set_default("EMIT_VIPER_TEXT", false).unwrap();
+        settings.set_default("DUMP_VIPER_DIR", "").unwrap();
+        settings.set_default("DUMP_CFG_DIR", "").unwrap();
+        settings.set_default("DUMP_VIR_AFTER", "").unwrap();
         settings.set_default("NUM_PARENTS_FOR_DUMPS", 0).unwrap();
         settings.set_default("CONTRACTS_LIB", "").unwrap();
         settings.set_default::<Vec<String>>("EXTRA_JVM_ARGS", vec![]).unwrap();
         settings.set_default::<Vec<String>>("EXTRA_VERIFIER_ARGS", vec![]).unwrap();
         settings.set_default("QUIET", false).unwrap();
+        settings.set_default("MESSAGE_FORMAT", "human").unwrap();
         settings.set_default("ASSERT_TIMEOUT", 10_000).unwrap();
+        settings.set_default("Z3_RANDOM_SEED", 0).unwrap();
+        settings.set_default("QUANTIFIER_INSTANTIATION_BOUND", 0).unwrap();
         settings.set_default("USE_MORE_COMPLETE_EXHALE", true).unwrap();
         // TODO: Check before enabling that pure variable havoc works properly after the
         // purification optimisation.
         settings.set_default("USE_ASSUME_FALSE_BACK_EDGES", false).unwrap();
         settings.set_default("REPORT_SUPPORT_STATUS", true).unwrap();
+        settings.set_default("REPORT_TRUSTED_SURFACE", false).unwrap();
+        settings.set_default("REPORT_VERIFICATION_TIMES", false).unwrap();
+        settings.set_default("INFER_PURITY", false).unwrap();
+        settings.set_default("USE_LIMITED_RECURSIVE_FUNCTIONS", false).unwrap();
+        settings.set_default("NUM_THREADS", 1).unwrap();
+        settings.set_default("SPLIT_OBLIGATIONS", false).unwrap();
+        settings.set_default("LAZY_PREDICATE_BODIES", false).unwrap();
+        settings.set_default("USE_LOOKUP_TRIGGERS", false).unwrap();
+        settings.set_default("SMT_EXPORT", false).unwrap();
+        settings.set_default("SMT_SOLVER_PATH", "z3").unwrap();
+        settings.set_default("INFER_ACCESSOR_PLEDGES", true).unwrap();
+        settings.set_default("RELAXED_DYNAMIC_BORROWS", false).unwrap();
 
         // Flags for debugging Prusti that can change verification results.
         settings.set_default("DISABLE_NAME_MANGLING", false).unwrap();
@@ -46,6 +73,7 @@ lazy_static! {
         settings.set_default("ENABLE_VERIFY_ONLY_BASIC_BLOCK_PATH", false).unwrap();
         settings.set_default::<Vec<String>>("VERIFY_ONLY_BASIC_BLOCK_PATH", vec![]).unwrap();
         settings.set_default::<Vec<String>>("DELETE_BASIC_BLOCKS", vec![]).unwrap();
+        settings.set_default("AUDIT_LAZY_PREDICATE_BODIES", false).unwrap();
 
         // 2. Override with the optional TOML file "Prusti.toml" (if there is any)
         settings.merge(
@@ -80,6 +108,26 @@ pub fn check_foldunfold_state() -> bool {
         .unwrap()
 }
 
+/// Should we type check the VIR program before sending it to Viper? Enabled by default
+/// in debug builds of Prusti.
+pub fn check_vir_types() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("CHECK_VIR_TYPES")
+        .unwrap()
+}
+
+/// Should we encode the crate a second time in a new encoding session, and check that the
+/// program is the same? This detects state that leaks from one session into the next one.
+pub fn check_repeated_encoding() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("CHECK_REPEATED_ENCODING")
+        .unwrap()
+}
+
 /// The Viper backend that should be used for the verification
 pub fn viper_backend() -> String {
     SETTINGS
@@ -101,6 +149,73 @@ pub fn check_panics() -> bool {
         .unwrap()
 }
 
+/// Should we check that the body of each procedure, each of its branches and its end are
+/// reachable? Code that is unreachable, for example because of contradictory preconditions,
+/// verifies vacuously and is reported with a warning.
+pub fn check_unreachability() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("CHECK_UNREACHABILITY")
+        .unwrap()
+}
+
+/// Should we check that the assertions of `debug_assert!` and its variants hold?
+pub fn check_debug_assertions() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("CHECK_DEBUG_ASSERTIONS")
+        .unwrap()
+}
+
+/// The spec profiles under which the crate should be verified: `debug`, `release` or
+/// `both`. When no profile is given, `CHECK_BINARY_OPERATIONS` and
+/// `CHECK_DEBUG_ASSERTIONS` are used as they are.
+pub fn verification_profiles() -> Vec<String> {
+    let profile = SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("VERIFICATION_PROFILE")
+        .unwrap()
+        .to_lowercase();
+    match profile.trim() {
+        "" => vec![],
+        "both" => vec!["debug".to_string(), "release".to_string()],
+        "debug" | "release" => vec![profile.trim().to_string()],
+        other => panic!("Unknown verification profile '{}'", other),
+    }
+}
+
+/// Select the spec profile of the next verification, overriding the flags whose
+/// semantics differ between debug and release builds: under `debug` overflows and
+/// failing `debug_assert!`s are errors, under `release` they are not checked.
+pub fn select_verification_profile(profile: &str) {
+    let is_debug = match profile {
+        "debug" => true,
+        "release" => false,
+        other => panic!("Unknown verification profile '{}'", other),
+    };
+    let mut settings = SETTINGS.write().unwrap();
+    settings.set("CHECK_BINARY_OPERATIONS", is_debug).unwrap();
+    settings.set("CHECK_DEBUG_ASSERTIONS", is_debug).unwrap();
+    settings.set("SELECTED_VERIFICATION_PROFILE", profile).unwrap();
+}
+
+/// The spec profile selected by `select_verification_profile`, if any.
+pub fn selected_verification_profile() -> Option<String> {
+    let profile = SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("SELECTED_VERIFICATION_PROFILE")
+        .unwrap();
+    if profile.is_empty() {
+        None
+    } else {
+        Some(profile)
+    }
+}
+
 /// Should we simplify the encoding before passing it to Viper?
 pub fn simplify_encoding() -> bool {
     SETTINGS
@@ -128,6 +243,20 @@ pub fn verification_whitelist() -> Vec<String> {
         .unwrap()
 }
 
+/// Get the comma-separated filters of `VERIFY_ONLY`, e.g. `path::to::fn,path::to::module`.
+/// If there is any filter, only the procedures whose path matches a filter are verified.
+pub fn verify_only_items() -> Vec<String> {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("VERIFY_ONLY")
+        .unwrap()
+        .split(',')
+        .map(|filter| filter.trim().to_string())
+        .filter(|filter| !filter.is_empty())
+        .collect()
+}
+
 /// Should we dump debug files?
 pub fn dump_debug_info() -> bool {
     SETTINGS
@@ -164,6 +293,15 @@ pub fn dump_borrowck_info() -> bool {
         .unwrap()
 }
 
+/// Should we dump the fold-unfold statements generated in each basic block?
+pub fn dump_fold_unfold_actions() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("DUMP_FOLD_UNFOLD_ACTIONS")
+        .unwrap()
+}
+
 /// Should we dump the Viper program?
 pub fn dump_viper_program() -> bool {
     SETTINGS
@@ -173,6 +311,60 @@ pub fn dump_viper_program() -> bool {
         .unwrap()
 }
 
+/// Should we build the Viper program by letting the backend parse its source text, instead
+/// of constructing it object by object through JNI?
+pub fn emit_viper_text() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("EMIT_VIPER_TEXT")
+        .unwrap()
+}
+
+/// In which folder should we dump one Viper program (`.vpr` file) for each
+/// verified item? If empty, no program is dumped.
+pub fn dump_viper_dir() -> Option<String> {
+    let dir = SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("DUMP_VIPER_DIR")
+        .unwrap();
+    if dir.is_empty() {
+        None
+    } else {
+        Some(dir)
+    }
+}
+
+/// In which folder should we dump the CFG (`.dot` file) of each encoded
+/// method, after the fold-unfold algorithm? If empty, no CFG is dumped.
+pub fn dump_cfg_dir() -> Option<String> {
+    let dir = SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("DUMP_CFG_DIR")
+        .unwrap();
+    if dir.is_empty() {
+        None
+    } else {
+        Some(dir)
+    }
+}
+
+/// Get the comma-separated names of the VIR passes after which each encoded method is dumped,
+/// e.g. `simplify,optimiser`, or `all` to dump it after each pass.
+pub fn dump_vir_after() -> Vec<String> {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("DUMP_VIR_AFTER")
+        .unwrap()
+        .split(',')
+        .map(|pass| pass.trim().to_string())
+        .filter(|pass| !pass.is_empty())
+        .collect()
+}
+
 /// How many parent folders should be used to disambiguate the Viper dumps (and other debug files)?
 pub fn num_parents_for_dumps() -> u64 {
     SETTINGS
@@ -237,6 +429,17 @@ pub fn quiet() -> bool {
     SETTINGS.read().unwrap().get::<bool>("QUIET").unwrap()
 }
 
+/// The format of the verification results: either `human` or `json`.
+/// With `json`, each error, warning and timing datum is also printed to
+/// stdout as a JSON object.
+pub fn message_format() -> String {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("MESSAGE_FORMAT")
+        .unwrap()
+}
+
 /// The assert timeout (in miliseconds) passed to Silicon.
 pub fn assert_timeout() -> u64 {
     SETTINGS
@@ -246,6 +449,25 @@ pub fn assert_timeout() -> u64 {
         .unwrap()
 }
 
+/// The random seed of Z3. With `0`, Z3 uses its default seed.
+pub fn z3_random_seed() -> u64 {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<u64>("Z3_RANDOM_SEED")
+        .unwrap()
+}
+
+/// The maximum number of instances of each quantifier that Z3 creates when used by Silicon.
+/// With `0`, the number of instances is not bounded.
+pub fn quantifier_instantiation_bound() -> u64 {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<u64>("QUANTIFIER_INSTANTIATION_BOUND")
+        .unwrap()
+}
+
 /// Use the Silicon configuration option `--enableMoreCompleteExhale`.
 pub fn use_more_complete_exhale() -> bool {
     SETTINGS
@@ -273,6 +495,137 @@ pub fn report_support_status() -> bool {
         .unwrap()
 }
 
+/// Should we report the items whose correctness is assumed and not verified?
+pub fn report_trusted_surface() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("REPORT_TRUSTED_SURFACE")
+        .unwrap()
+}
+
+/// Should we report the time spent to encode and verify each procedure? This verifies
+/// each procedure with a separate Viper program, even if `NUM_THREADS` is 1.
+pub fn report_verification_times() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("REPORT_VERIFICATION_TIMES")
+        .unwrap()
+}
+
+/// Should we encode the recursive calls of all the recursive pure functions with a limited
+/// version of the function, which only unrolls the definition once? The functions that have a
+/// termination measure (`#[decreases="..."]`) are always encoded in this way.
+pub fn use_limited_recursive_functions() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("USE_LIMITED_RECURSIVE_FUNCTIONS")
+        .unwrap()
+}
+
+/// Should we encode as pure functions the private procedures that are inferred to be
+/// pure, even without a `#[pure]` annotation?
+pub fn infer_purity() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("INFER_PURITY")
+        .unwrap()
+}
+
+/// Should we emit abstract predicates for the types whose predicate is never
+/// folded or unfolded?
+pub fn lazy_predicate_bodies() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("LAZY_PREDICATE_BODIES")
+        .unwrap()
+}
+
+/// Should we only report the predicates that `LAZY_PREDICATE_BODIES` would make
+/// abstract, keeping their bodies so that the verification results can be compared?
+pub fn audit_lazy_predicate_bodies() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("AUDIT_LAZY_PREDICATE_BODIES")
+        .unwrap()
+}
+
+/// Should we check the pure functions and the methods with an SMT solver instead of Viper?
+/// This experimental mode does not need a JVM, but it only supports heap-independent pure
+/// functions and loop-free methods. The other items make the verification fail.
+pub fn smt_export() -> bool {
+    SETTINGS.read().unwrap().get::<bool>("SMT_EXPORT").unwrap()
+}
+
+/// The SMT solver used by `SMT_EXPORT`. It has to accept the `-smt2 -in` arguments.
+pub fn smt_solver_path() -> String {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<String>("SMT_SOLVER_PATH")
+        .unwrap()
+}
+
+/// Should we infer the pledge of procedures that return a mutable reference
+/// to a field of a mutable reference argument, when no pledge is given?
+pub fn infer_accessor_pledges() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("INFER_ACCESSOR_PLEDGES")
+        .unwrap()
+}
+
+/// Should a call of `RefCell::borrow` or `RefCell::borrow_mut` that might violate
+/// the dynamic borrow rules be reported as a possible panic, like a `panic!()`,
+/// instead of as an error?
+pub fn relaxed_dynamic_borrows() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("RELAXED_DYNAMIC_BORROWS")
+        .unwrap()
+}
+
+/// The number of Viper verifiers that verify methods in parallel.
+/// With a value of `1` the whole program is verified at once. Otherwise, each
+/// method is verified in a program of its own, which repeats the verification
+/// of the functions and predicates that the methods share.
+pub fn num_threads() -> u64 {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<u64>("NUM_THREADS")
+        .unwrap()
+}
+
+/// Verify each assertion of a method in a separate Viper method, which assumes the other
+/// assertions of the method. A timeout is then reported only for the assertions that cause
+/// it, and the verification time of each assertion is part of the verification times report.
+pub fn split_obligations() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("SPLIT_OBLIGATIONS")
+        .unwrap()
+}
+
+/// Trigger the quantifiers of the specifications that have no user-given triggers by the
+/// lookups of the memory regions in their body, i.e. the `deref_value` expressions, instead of
+/// letting Viper infer their triggers. Experimental.
+pub fn use_lookup_triggers() -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get::<bool>("USE_LOOKUP_TRIGGERS")
+        .unwrap()
+}
+
 /// Disable mangling of generated Viper names.
 ///
 /// **Note:** This is very likely to result in invalid programs being
@@ -20,6 +20,8 @@ pub struct CollectPrustiSpecVisitor<'r, 'a: 'r, 'tcx: 'a> {
     result: &'r mut Vec<DefId>,
     use_whitelist: bool,
     whitelist: HashSet<String>,
+    /// The filters of `VERIFY_ONLY`; if there is any, only the matching items are verified.
+    verify_only: Vec<String>,
 }
 
 impl<'r, 'a, 'tcx> CollectPrustiSpecVisitor<'r, 'a, 'tcx> {
@@ -30,38 +32,79 @@ impl<'r, 'a, 'tcx> CollectPrustiSpecVisitor<'r, 'a, 'tcx> {
             result,
             use_whitelist: config::enable_whitelist(),
             whitelist: HashSet::from_iter(config::verification_whitelist()),
+            verify_only: config::verify_only_items(),
         }
     }
+
+    /// Add the procedure to the result, unless it is excluded by the whitelist or by the
+    /// `VERIFY_ONLY` filters.
+    fn select(&mut self, def_id: DefId, item_kind: &str) {
+        let item_def_path = self.env.get_item_def_path(def_id);
+        if self.use_whitelist && !self.whitelist.contains(&item_def_path) {
+            debug!(
+                "Skip verification of {} '{}': not in the whitelist",
+                item_kind, item_def_path
+            );
+            return;
+        }
+        if !self.verify_only.is_empty() {
+            let item_name = self.env.get_item_name(def_id);
+            if !self
+                .verify_only
+                .iter()
+                .any(|filter| matches_item_filter(&item_name, filter))
+            {
+                debug!(
+                    "Skip verification of {} '{}': it does not match VERIFY_ONLY",
+                    item_kind, item_def_path
+                );
+                return;
+            }
+        }
+        trace!("Add {} to result", item_def_path);
+        self.result.push(def_id);
+    }
+}
+
+/// Whether the path of an item matches a filter of `VERIFY_ONLY`, which is either the path of
+/// the item (possibly without its leading modules) or the path of a module that contains it.
+fn matches_item_filter(item_path: &str, filter: &str) -> bool {
+    let item_path = item_path.trim_left_matches("::");
+    let filter = filter.trim_left_matches("::");
+    item_path == filter
+        || item_path.ends_with(&format!("::{}", filter))
+        || item_path.starts_with(&format!("{}::", filter))
 }
 
 impl<'r, 'a, 'tcx> ItemLikeVisitor<'tcx> for CollectPrustiSpecVisitor<'r, 'a, 'tcx> {
     fn visit_item(&mut self, item: &hir::Item) {
         if attr::contains_name(&item.attrs, "__PRUSTI_LOOP_SPEC_ID")
+            || attr::contains_name(&item.attrs, "__PRUSTI_ASSUMPTION_SPEC_ID")
+            || attr::contains_name(&item.attrs, "__PRUSTI_ASSERTION_SPEC_ID")
+            || attr::contains_name(&item.attrs, "__PRUSTI_REFUTATION_SPEC_ID")
+            || attr::contains_name(&item.attrs, "__PRUSTI_FOLD_SPEC_ID")
+            || attr::contains_name(&item.attrs, "__PRUSTI_UNFOLD_SPEC_ID")
             || attr::contains_name(&item.attrs, "__PRUSTI_EXPR_ID")
             || attr::contains_name(&item.attrs, "__PRUSTI_FORALL_ID")
             || attr::contains_name(&item.attrs, "__PRUSTI_SPEC_ONLY")
             || attr::contains_name(&item.attrs, "trusted")
+            || attr::contains_name(&item.attrs, "pure_spec")
+            || attr::contains_name(&item.attrs, "predicate")
+            || attr::contains_name(&item.attrs, "__PRUSTI_SKIP")
         {
             return;
         }
         if let hir::Item_::ItemFn(..) = item.node {
             let def_id = self.tcx.hir.local_def_id(item.id);
-            let item_def_path = self.env.get_item_def_path(def_id);
-            if !self.use_whitelist || self.whitelist.contains(&item_def_path) {
-                trace!("Add {} to result", item_def_path);
-                self.result.push(def_id);
-            } else {
-                debug!(
-                    "Skip verification of item '{}': not in the whitelist",
-                    item_def_path
-                )
-            }
+            self.select(def_id, "item");
         }
     }
 
     fn visit_trait_item(&mut self, trait_item: &hir::TraitItem) {
         if attr::contains_name(&trait_item.attrs, "__PRUSTI_SPEC_ONLY")
             || attr::contains_name(&trait_item.attrs, "trusted")
+            || attr::contains_name(&trait_item.attrs, "pure_spec")
+            || attr::contains_name(&trait_item.attrs, "__PRUSTI_SKIP")
         {
             return;
         }
@@ -78,21 +121,14 @@ impl<'r, 'a, 'tcx> ItemLikeVisitor<'tcx> for CollectPrustiSpecVisitor<'r, 'a, 't
             return;
         }
         let def_id = self.tcx.hir.local_def_id(trait_item.id);
-        let item_def_path = self.env.get_item_def_path(def_id);
-        if !self.use_whitelist || self.whitelist.contains(&item_def_path) {
-            trace!("Add {} to result", item_def_path);
-            self.result.push(def_id);
-        } else {
-            debug!(
-                "Skip verification of trait item '{}': not in the whitelist",
-                item_def_path
-            )
-        }
+        self.select(def_id, "trait item");
     }
 
     fn visit_impl_item(&mut self, impl_item: &hir::ImplItem) {
         if attr::contains_name(&impl_item.attrs, "__PRUSTI_SPEC_ONLY")
             || attr::contains_name(&impl_item.attrs, "trusted")
+            || attr::contains_name(&impl_item.attrs, "pure_spec")
+            || attr::contains_name(&impl_item.attrs, "__PRUSTI_SKIP")
         {
             return;
         }
@@ -105,15 +141,6 @@ impl<'r, 'a, 'tcx> ItemLikeVisitor<'tcx> for CollectPrustiSpecVisitor<'r, 'a, 't
         }
 
         let def_id = self.tcx.hir.local_def_id(impl_item.id);
-        let item_def_path = self.env.get_item_def_path(def_id);
-        if !self.use_whitelist || self.whitelist.contains(&item_def_path) {
-            trace!("Add {} to result", item_def_path);
-            self.result.push(def_id);
-        } else {
-            debug!(
-                "Skip verification of impl item '{}': not in the whitelist",
-                item_def_path
-            )
-        }
+        self.select(def_id, "impl item");
     }
 }
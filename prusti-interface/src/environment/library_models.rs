@@ -0,0 +1,119 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Models of the procedures of the standard library that are encoded in a
+//! special way.
+//!
+//! Each model belongs to a tier of the standard library (`core`, `alloc` or
+//! `std`) and it is used only if the verified crate links the crate of that
+//! tier. This way, `no_std` crates are verified with the models of `core`
+//! (and of `alloc`, if they use it), while the other procedures of the
+//! standard library are encoded as normal calls.
+
+use rustc::ty::TyCtxt;
+
+/// A tier of the standard library.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LibraryTier {
+    Core,
+    Alloc,
+    Std,
+}
+
+impl LibraryTier {
+    fn crate_name(&self) -> &'static str {
+        match self {
+            LibraryTier::Core => "core",
+            LibraryTier::Alloc => "alloc",
+            LibraryTier::Std => "std",
+        }
+    }
+}
+
+/// A procedure of the standard library with a special encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LibraryModel {
+    /// A procedure that panics, called when a Rust assertion fails.
+    BeginPanic,
+    /// `Deref::deref` or `DerefMut::deref_mut`.
+    Deref,
+    /// `Box::new`.
+    BoxNew,
+    /// `RefCell::borrow`.
+    RefCellBorrow,
+    /// `RefCell::borrow_mut`.
+    RefCellBorrowMut,
+    /// `Try::into_result`, called by the `?` operator.
+    TryIntoResult,
+    /// `Try::from_error`, called by the `?` operator to return an error.
+    TryFromError,
+    /// `From::from`, called by the `?` operator to convert an error.
+    ConvertFrom,
+    /// `<*const T>::add`, `<*const T>::offset` and their `*mut T` versions.
+    PtrOffset,
+}
+
+/// The paths of the modelled procedures, as printed by `absolute_item_path_str`.
+const MODELS: &[(LibraryTier, &str, LibraryModel)] = &[
+    (LibraryTier::Core, "core::panicking::panic", LibraryModel::BeginPanic),
+    (LibraryTier::Core, "core::panicking::panic_fmt", LibraryModel::BeginPanic),
+    (LibraryTier::Core, "core::ops::Deref::deref", LibraryModel::Deref),
+    (LibraryTier::Core, "core::ops::DerefMut::deref_mut", LibraryModel::Deref),
+    (LibraryTier::Core, "<core::cell::RefCell<T>>::borrow", LibraryModel::RefCellBorrow),
+    (LibraryTier::Core, "<core::cell::RefCell<T>>::borrow_mut", LibraryModel::RefCellBorrowMut),
+    (LibraryTier::Core, "core::ops::Try::into_result", LibraryModel::TryIntoResult),
+    (LibraryTier::Core, "core::ops::Try::from_error", LibraryModel::TryFromError),
+    (LibraryTier::Core, "core::convert::From::from", LibraryModel::ConvertFrom),
+    (LibraryTier::Core, "<*const T>::add", LibraryModel::PtrOffset),
+    (LibraryTier::Core, "<*const T>::offset", LibraryModel::PtrOffset),
+    (LibraryTier::Core, "<*mut T>::add", LibraryModel::PtrOffset),
+    (LibraryTier::Core, "<*mut T>::offset", LibraryModel::PtrOffset),
+    (LibraryTier::Alloc, "<alloc::boxed::Box<T>>::new", LibraryModel::BoxNew),
+    (LibraryTier::Std, "std::rt::begin_panic", LibraryModel::BeginPanic),
+    (LibraryTier::Std, "std::panicking::begin_panic", LibraryModel::BeginPanic),
+    (LibraryTier::Std, "std::ops::Deref::deref", LibraryModel::Deref),
+    (LibraryTier::Std, "std::ops::DerefMut::deref_mut", LibraryModel::Deref),
+    (LibraryTier::Std, "<std::boxed::Box<T>>::new", LibraryModel::BoxNew),
+    (LibraryTier::Std, "<std::cell::RefCell<T>>::borrow", LibraryModel::RefCellBorrow),
+    (LibraryTier::Std, "<std::cell::RefCell<T>>::borrow_mut", LibraryModel::RefCellBorrowMut),
+    (LibraryTier::Std, "std::ops::Try::into_result", LibraryModel::TryIntoResult),
+    (LibraryTier::Std, "std::ops::Try::from_error", LibraryModel::TryFromError),
+    (LibraryTier::Std, "std::convert::From::from", LibraryModel::ConvertFrom),
+];
+
+/// The models of the tiers of the standard library linked by a crate.
+#[derive(Clone, Debug)]
+pub struct LibraryModels {
+    linked_tiers: Vec<LibraryTier>,
+}
+
+impl LibraryModels {
+    /// Collect the tiers of the standard library linked by the crate being compiled.
+    pub fn new<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>) -> Self {
+        let linked_crates: Vec<_> = tcx
+            .crates()
+            .iter()
+            .map(|&cnum| tcx.crate_name(cnum).as_str().to_string())
+            .collect();
+        let linked_tiers = [LibraryTier::Core, LibraryTier::Alloc, LibraryTier::Std]
+            .iter()
+            .filter(|tier| linked_crates.iter().any(|name| name == tier.crate_name()))
+            .cloned()
+            .collect();
+        debug!("Linked tiers of the standard library: {:?}", linked_tiers);
+        LibraryModels { linked_tiers }
+    }
+
+    /// The model of the procedure with the given path, if its tier is linked.
+    pub fn resolve(&self, path: &str) -> Option<LibraryModel> {
+        MODELS
+            .iter()
+            .find(|(tier, model_path, _)| {
+                *model_path == path && self.linked_tiers.contains(tier)
+            })
+            .map(|&(_, _, model)| model)
+    }
+}
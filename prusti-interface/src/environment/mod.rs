@@ -19,6 +19,7 @@ use syntax_pos::MultiSpan;
 pub mod borrowck;
 mod collect_prusti_spec_visitor;
 mod dump_borrowck_info;
+pub mod library_models;
 mod loops;
 mod loops_utils;
 pub mod mir_analyses;
@@ -32,6 +33,7 @@ pub use self::loops_utils::*;
 pub use self::procedure::{BasicBlockIndex, Procedure};
 use config;
 use data::ProcedureDefId;
+use report::json;
 use syntax::codemap::CodeMap;
 use syntax::codemap::Span;
 use utils::get_attr_value;
@@ -80,11 +82,14 @@ impl<'r, 'a, 'tcx> Environment<'r, 'a, 'tcx> {
 
     /// Emits a warning message
     pub fn warn(&self, msg: &str) {
+        json::warning(self.codemap(), None, msg);
         self.state.session.warn(msg);
     }
 
     /// Emits an warning message.
     pub fn span_warn<S: Into<MultiSpan>>(&self, sp: S, msg: &str) {
+        let sp = sp.into();
+        json::warning(self.codemap(), sp.primary_span(), msg);
         self.state.session.span_warn(sp, msg);
     }
 
@@ -98,13 +103,15 @@ impl<'r, 'a, 'tcx> Environment<'r, 'a, 'tcx> {
         self.state.session.span_err(sp, msg);
     }
 
-    /// Emits an error message.
+    /// Emits an error message, with an optional structured suggestion
+    /// given as message, span to replace and replacement.
     pub fn span_err_with_help_and_note<S: Into<MultiSpan> + Clone>(
         &self,
         sp: S,
         msg: &str,
         help: &Option<String>,
-        note: &Option<(String, S)>
+        note: &Option<(String, S)>,
+        suggestion: &Option<(String, Span, String)>,
     ) {
         let mut diagnostic = self.state.session.struct_err(msg);
         diagnostic.set_span(sp);
@@ -114,6 +121,9 @@ impl<'r, 'a, 'tcx> Environment<'r, 'a, 'tcx> {
         if let Some((note_msg, note_sp)) = note {
             diagnostic.span_note(note_sp.clone(), note_msg);
         }
+        if let Some((suggestion_msg, suggestion_sp, code)) = suggestion {
+            diagnostic.span_suggestion(*suggestion_sp, suggestion_msg, code.clone());
+        }
         diagnostic.emit();
     }
 
@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use super::library_models::{LibraryModel, LibraryModels};
 use super::loops;
 use data::ProcedureDefId;
 use rustc::mir;
@@ -28,6 +29,7 @@ pub struct Procedure<'a, 'tcx: 'a> {
     nonspec_basic_blocks: HashSet<BasicBlock>,
     predecessors: HashMap<BasicBlockIndex, HashSet<BasicBlockIndex>>,
     ordered_basic_blocks: Vec<BasicBlockIndex>,
+    library_models: LibraryModels,
 }
 
 impl<'a, 'tcx> Procedure<'a, 'tcx> {
@@ -60,6 +62,7 @@ impl<'a, 'tcx> Procedure<'a, 'tcx> {
             nonspec_basic_blocks,
             predecessors,
             ordered_basic_blocks,
+            library_models: LibraryModels::new(tcx),
         }
     }
 
@@ -241,8 +244,7 @@ impl<'a, 'tcx> Procedure<'a, 'tcx> {
         } = self.mir[bbi].terminator.as_ref().unwrap().kind
         {
             let func_proc_name = self.tcx.absolute_item_path_str(def_id);
-            &func_proc_name == "std::panicking::begin_panic"
-                || &func_proc_name == "std::rt::begin_panic"
+            self.library_models.resolve(&func_proc_name) == Some(LibraryModel::BeginPanic)
         } else {
             false
         }
@@ -352,41 +354,35 @@ fn build_nonspec_basic_blocks<'tcx>(mir: &Mir<'tcx>) -> HashSet<BasicBlock> {
         if is_loop_head {
             trace!("MIR block {:?} is a loop head", source);
         }
+        // Skip the following "if false". The guard is checked on the block itself, because the
+        // block of a `prusti_assume!` might be the first block of the procedure.
+        let span = term.source_info.span;
+        if let TerminatorKind::SwitchInt {
+            ref discr,
+            ref values,
+            ref targets,
+            ..
+        } = term.kind
+        {
+            trace!("term is a SwitchInt");
+            trace!("discr: '{:?}'", discr);
+            // Specification guard has its span set to (0, 0) position.
+            if format!("{:?}", discr) == "const false" && span.lo().0 == 0 && span.hi().0 == 0 {
+                // Some assumptions
+                assert_eq!(values[0], 0 as u128);
+                assert_eq!(values.len(), 1);
+
+                // Do not visit the 'then' branch.
+                // So, it will not be put into nonspec_basic_blocks.
+                debug!(
+                    "MIR block {:?} is the head of a specification branch",
+                    targets[1]
+                );
+                visited.insert(targets[1]);
+            }
+        }
         for target in get_normal_targets(term) {
             trace!("Try target {:?} of {:?}", target, source);
-            // Skip the following "if false"
-            let target_term = &mir[target].terminator.as_ref().unwrap();
-            let span = target_term.source_info.span;
-            trace!(
-                "target_term {:?} span=({:?}, {:?})",
-                target_term,
-                span.lo(),
-                span.hi()
-            );
-            if let TerminatorKind::SwitchInt {
-                ref discr,
-                ref values,
-                ref targets,
-                ..
-            } = target_term.kind
-            {
-                trace!("target_term is a SwitchInt");
-                trace!("discr: '{:?}'", discr);
-                // Specification guard has its span set to (0, 0) position.
-                if format!("{:?}", discr) == "const false" && span.lo().0 == 0 && span.hi().0 == 0 {
-                    // Some assumptions
-                    assert_eq!(values[0], 0 as u128);
-                    assert_eq!(values.len(), 1);
-
-                    // Do not visit the 'then' branch.
-                    // So, it will not be put into nonspec_basic_blocks.
-                    debug!(
-                        "MIR block {:?} is the head of a specification branch",
-                        targets[1]
-                    );
-                    visited.insert(targets[1]);
-                }
-            }
             if !visited.contains(&target) {
                 to_visit.push(target);
             }
@@ -37,6 +37,7 @@ extern crate rustc_target;
 extern crate serde_derive;
 extern crate config as config_crate;
 extern crate serde;
+extern crate serde_json;
 extern crate syntax;
 extern crate syntax_pos;
 #[macro_use]
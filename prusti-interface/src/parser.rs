@@ -36,15 +36,83 @@
 //! assertion := assertion && assertion
 //!            | expression ==> assertion
 //!            | (forall variable_name :: {expression} expression ==> expression)
+//!            | forall(|variable_name: type| {expression} expression ==> expression)
+//!            | exists(|variable_name: type| {expression} expression)
+//!            | let variable_name = expression in assertion
 //! ```
 //!
+//! The body of a `let` extends as far to the right as possible, like in
+//! Viper. It is type-checked as a closure taking the bound variable that
+//! is immediately applied to the value, so that the type of the variable
+//! is inferred; the variable must have an integer or boolean type.
+//!
 //! Here `expression` is a Rust expression that contains only elements
 //! that are considered expressions in Viper, plus `match` expressions.
+//! The active variant of an enum can be tested with `matches!(x, Some(_))`
+//! or `is_variant!(x, Some)`, which are rewritten to `match` expressions.
+//! Two values can be compared structurally with `left === right`, which
+//! is rewritten to `snap_eq(&left, &right)`: unlike `==`, it compares the
+//! fields of structs whatever the `PartialEq` implementation of their type.
+//! The operator binds tighter than `&&` and `||`, and looser than the
+//! other operators of Rust; it can be nested in parentheses, possibly
+//! negated with `!`, but not in other expressions.
 //! The parsed specification is stored in the structure
 //! `specifications::UntypedSpecification` and type-checked
 //! specification is stored in the structure
 //! `specifications::TypedSpecification`.
 //!
+//! An assertion can also be assumed in the body of a procedure with
+//! `prusti_assume!(assertion)`. The assumption is not checked; it is
+//! rewritten to an `if false` block like the one of a loop invariant
+//! (see below), whose marker closure has the `__PRUSTI_ASSUMPTION_SPEC_ID`
+//! attribute instead of `__PRUSTI_LOOP_SPEC_ID`. In the same way,
+//! `prusti_assert!(assertion)` checks the assertion at that point of the
+//! body (`__PRUSTI_ASSERTION_SPEC_ID`), while `prusti_refute!(assertion)`
+//! checks that the assertion does *not* hold in all the executions that
+//! reach that point (`__PRUSTI_REFUTATION_SPEC_ID`). A failing refutation
+//! of `false` shows that the point is unreachable, for example because of
+//! contradictory assumptions.
+//!
+//! A function with the `#[predicate]` attribute, which takes a single
+//! argument of a boolean, integer, character or raw pointer type and
+//! returns a `bool`, declares a predicate whose body is the assertion
+//! returned by the function, for example a `deref_spec` of the pointer.
+//! An application of the function in a specification is the permission
+//! to the predicate, which is never folded or unfolded automatically:
+//! `fold!(predicate(x))` exchanges the body for the predicate and
+//! `unfold!(predicate(x))` exchanges the predicate for its body. Like the
+//! other statements above, they are rewritten to `if false` blocks, whose
+//! marker closures have the `__PRUSTI_FOLD_SPEC_ID` and
+//! `__PRUSTI_UNFOLD_SPEC_ID` attributes. The specification functions are
+//! imported at the beginning of the body of a predicate.
+//!
+//! The attribute `#[prusti::skip]` is rewritten to `#[__PRUSTI_SKIP]`, which
+//! excludes the item from the verification (its contract is still used at
+//! the call sites).
+//!
+//! Multiple `requires` (or `ensures`) attributes of a procedure are
+//! evaluated from top to bottom, like a conjunction: a clause may assume
+//! the clauses written before it, both when checking that it is
+//! well-defined and when it is encoded. For example, in
+//!
+//! ```rust,ignore
+//! #[requires="n > 0"]
+//! #[requires="100 % n == 0"]
+//! fn divisor(n: u32) -> u32 { n }
+//! ```
+//!
+//! the second precondition is well-defined because of the first one.
+//! The type invariants of the arguments (and of the result, in
+//! postconditions) hold before the first clause.
+//!
+//! A recursive pure function can declare a termination measure with
+//! `#[decreases="expression"]`, where the expression is an integer of the
+//! arguments that must decrease (while staying non-negative) at each
+//! recursive call. The measure is registered as a separate
+//! `SpecificationSet::Termination`, whose ID is stored in the
+//! `__PRUSTI_TERMINATION_SPEC_ID` attribute of the procedure, and it is
+//! type-checked together with the preconditions.
+//!
 //! The general workflow for parsing and type-checking specifications is
 //! as follows:
 //!
@@ -201,9 +269,29 @@ pub fn register_attributes(state: &mut driver::CompileState) {
     let registry = state.registry.as_mut().unwrap();
     registry.register_attribute(String::from("trusted"), AttributeType::Whitelisted);
     registry.register_attribute(String::from("pure"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("impure"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("pure_spec"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("predicate"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("readonly"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("model"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("lock_invariant"), AttributeType::Whitelisted);
     registry.register_attribute(String::from("invariant"), AttributeType::Whitelisted);
     registry.register_attribute(String::from("requires"), AttributeType::Whitelisted);
     registry.register_attribute(String::from("ensures"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("decreases"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("bit_precise"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("assert_timeout"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("z3_random_seed"), AttributeType::Whitelisted);
+    registry.register_attribute(
+        String::from("quantifier_instantiation_bound"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(String::from("arithmetic_newtype"), AttributeType::Whitelisted);
+    registry.register_attribute(String::from("domain"), AttributeType::Whitelisted);
+    registry.register_attribute(
+        String::from("trusted_if_unsupported"),
+        AttributeType::Whitelisted,
+    );
     registry.register_attribute(PRUSTI_SPEC_ATTR.to_string(), AttributeType::Whitelisted);
     registry.register_attribute(
         String::from("__PRUSTI_SPEC_ONLY"),
@@ -213,6 +301,31 @@ pub fn register_attributes(state: &mut driver::CompileState) {
         String::from("__PRUSTI_LOOP_SPEC_ID"),
         AttributeType::Whitelisted,
     );
+    registry.register_attribute(
+        String::from("__PRUSTI_ASSUMPTION_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(
+        String::from("__PRUSTI_ASSERTION_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(
+        String::from("__PRUSTI_REFUTATION_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(
+        String::from("__PRUSTI_FOLD_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(
+        String::from("__PRUSTI_UNFOLD_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(
+        String::from("__PRUSTI_TERMINATION_SPEC_ID"),
+        AttributeType::Whitelisted,
+    );
+    registry.register_attribute(String::from("__PRUSTI_SKIP"), AttributeType::Whitelisted);
     registry.register_attribute(String::from("__PRUSTI_EXPR_ID"), AttributeType::Whitelisted);
     registry.register_attribute(
         String::from("__PRUSTI_FORALL_ID"),
@@ -294,6 +407,34 @@ impl<'tcx> SpecParser<'tcx> {
         new_id
     }
 
+    /// Register the termination measure of a procedure and return the
+    /// attribute that links it to the procedure. At most one measure is
+    /// allowed, and it has to be an expression.
+    fn register_termination_measures(
+        &mut self,
+        span: Span,
+        measures: &[UntypedSpecification],
+    ) -> Option<ast::Attribute> {
+        if measures.is_empty() {
+            return None;
+        }
+        if measures.len() > 1 {
+            self.report_error(span, "only one termination measure allowed for procedure");
+            return None;
+        }
+        if let AssertionKind::Expr(_) = *measures[0].assertion.kind {
+        } else {
+            self.report_error(span, "the termination measure must be an expression");
+            return None;
+        }
+        let id = self.register_specification(SpecificationSet::Termination(measures.to_vec()));
+        Some(self.ast_builder.attribute_name_value(
+            span,
+            "__PRUSTI_TERMINATION_SPEC_ID",
+            &id.to_string(),
+        ))
+    }
+
     fn report_error(&self, span: Span, message: &str) {
         let mut err = self.session.struct_span_err(span, message);
         err.emit();
@@ -305,8 +446,9 @@ impl<'tcx> SpecParser<'tcx> {
         warn.emit();
     }
 
-    /// Construct a lambda function with an attribute that identifies the spec id of the loop
-    fn build_loop_mark(&self, spec_id: SpecID) -> ast::Stmt {
+    /// Construct a lambda function with an attribute `attr_name` that identifies the spec id
+    /// of a loop or of a specification statement
+    fn build_spec_mark(&self, attr_name: &str, spec_id: SpecID) -> ast::Stmt {
         let builder = &self.ast_builder;
         let span = DUMMY_SP;
         let mut lambda_fn = builder
@@ -319,13 +461,38 @@ impl<'tcx> SpecParser<'tcx> {
             .into_inner();
         lambda_fn.attrs = vec![self.ast_builder.attribute_name_value(
             span,
-            "__PRUSTI_LOOP_SPEC_ID",
+            attr_name,
             &spec_id.to_string(),
         )]
         .into();
         builder.stmt_semi(ptr::P(lambda_fn))
     }
 
+    /// Construct the `if false { statements }` block that is used only for type-checking the
+    /// specification `spec_id`. The zero span of the `if` allows to recognize the block in MIR.
+    fn build_spec_only_block(
+        &self,
+        span: Span,
+        spec_id: SpecID,
+        statements: Vec<ast::Stmt>,
+    ) -> ast::Stmt {
+        let builder = &self.ast_builder;
+        let expr = builder.expr_if(
+            Span::new(BytePos(0), BytePos(0), SyntaxContext::empty()),
+            builder.expr_bool(span, false),
+            builder.expr_block(builder.block(span, statements)),
+            None,
+        );
+        let mut expr = expr.into_inner();
+        expr.attrs = vec![self.ast_builder.attribute_name_value(
+            span,
+            "__PRUSTI_SPEC_ONLY",
+            &spec_id.to_string(),
+        )]
+        .into();
+        builder.stmt_expr(ptr::P(expr))
+    }
+
     /// Construct a lambda function with given expression to make that expression
     /// to be type-checked by the Rust compiler.
     fn build_typeck_call(
@@ -333,6 +500,15 @@ impl<'tcx> SpecParser<'tcx> {
         expression: &UntypedExpression,
         expected_ty: Option<ptr::P<ast::Ty>>,
     ) -> ast::Stmt {
+        let lambda_fn = self.build_typeck_lambda(expression, expected_ty);
+        self.ast_builder.stmt_semi(lambda_fn)
+    }
+
+    fn build_typeck_lambda(
+        &self,
+        expression: &UntypedExpression,
+        expected_ty: Option<ptr::P<ast::Ty>>,
+    ) -> ptr::P<ast::Expr> {
         let builder = &self.ast_builder;
         let expr_id = expression.id;
         let rust_expr = expression.expr.clone();
@@ -353,7 +529,7 @@ impl<'tcx> SpecParser<'tcx> {
             self.ast_builder.attribute_word(span, "pure"),
         ]
         .into();
-        builder.stmt_semi(ptr::P(lambda_fn))
+        ptr::P(lambda_fn)
     }
 
     fn build_assertion(&self, expression: &UntypedExpression) -> ast::Stmt {
@@ -421,7 +597,8 @@ impl<'tcx> SpecParser<'tcx> {
                 self.populate_statements(assertion, statements);
             }
             // encode generics typecond as forallvargs (hack!)
-            AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => {
+            AssertionKind::ForAll(ref vars, ref trigger_set, ref body) |
+            AssertionKind::Exists(ref vars, ref trigger_set, ref body) => {
                 let mut stmts = self.convert_trigger_set_to_statements(trigger_set);
                 self.populate_statements(body, &mut stmts);
                 let builder = &self.ast_builder;
@@ -447,6 +624,36 @@ impl<'tcx> SpecParser<'tcx> {
                 let statement = builder.stmt_semi(ptr::P(lambda_fn));
                 statements.push(statement);
             }
+            AssertionKind::Let(ref vars, ref value, ref body) => {
+                // The lambda function of the body is applied to the value, so that the
+                // type of the variable is inferred.
+                let mut stmts = vec![];
+                self.populate_statements(body, &mut stmts);
+                let builder = &self.ast_builder;
+
+                // TODO: use a proper span
+                let span = DUMMY_SP;
+
+                let mut lambda_fn = builder
+                    .lambda_fn_decl(
+                        span,
+                        builder.fn_decl(vars.vars.clone(), ast::FunctionRetTy::Default(span)),
+                        builder.expr_block(builder.block(span, stmts)),
+                        span,
+                    )
+                    .into_inner();
+
+                lambda_fn.attrs = vec![
+                    builder.attribute_name_value(span, "__PRUSTI_FORALL_ID", &vars.id.to_string()),
+                    builder.attribute_word(span, "pure"),
+                ]
+                .into();
+
+                let value_call =
+                    builder.expr_call(span, self.build_typeck_lambda(value, None), vec![]);
+                let call = builder.expr_call(span, ptr::P(lambda_fn), vec![value_call]);
+                statements.push(builder.stmt_semi(call));
+            }
             AssertionKind::Pledge(ref reference, ref lhs, ref rhs) => {
                 if let Some(ref reference) = reference {
                     let statement = self.build_typeck_call(reference, None);
@@ -465,7 +672,14 @@ impl<'tcx> SpecParser<'tcx> {
         trace!("[convert_to_statements] enter");
         let mut statements = Vec::new();
         for specification in specifications {
-            self.populate_statements(&specification.assertion, &mut statements);
+            if specification.typ == SpecType::Decreases {
+                // A termination measure is an integer, not a boolean.
+                if let AssertionKind::Expr(ref expression) = *specification.assertion.kind {
+                    statements.push(self.build_typeck_call(expression, None));
+                }
+            } else {
+                self.populate_statements(&specification.assertion, &mut statements);
+            }
         }
         trace!("[convert_to_statements] exit");
         statements
@@ -501,6 +715,29 @@ impl<'tcx> SpecParser<'tcx> {
             })
     }
 
+    /// Replace the `impl Trait` types of the arguments of a function with fresh type
+    /// parameters that have the same bounds, like rustc does when it lowers the function.
+    /// The specification items, which copy the arguments and the generics of the function,
+    /// then refer to the same type parameters, and the contracts of the bounds apply to
+    /// them like to any other type parameter.
+    fn desugar_impl_trait_args(
+        &self,
+        decl: &mut ptr::P<ast::FnDecl>,
+        generics: &mut ast::Generics,
+    ) {
+        let mut desugarer = ImplTraitDesugarer {
+            ast_builder: &self.ast_builder,
+            params: vec![],
+        };
+        *decl = decl.clone().map(|mut decl| {
+            for arg in &mut decl.inputs {
+                arg.ty = desugarer.fold_ty(arg.ty.clone());
+            }
+            decl
+        });
+        generics.params.extend(desugarer.params);
+    }
+
     /// Generate a function that contains only the precondition and postcondition
     /// for type-checking.
     fn generate_spec_item(
@@ -623,7 +860,7 @@ impl<'tcx> SpecParser<'tcx> {
     ) -> ast::Item {
         let mut name = item.ident.to_string();
         match item.node {
-            ast::ItemKind::Struct(ref _variantdata, ref generics) => {
+            ast::ItemKind::Struct(_, ref generics) | ast::ItemKind::Enum(_, ref generics) => {
                 let mut statements = vec![];
 
                 // Add invariants.
@@ -901,26 +1138,73 @@ impl<'tcx> SpecParser<'tcx> {
         if !statements.is_empty() {
             statements.insert(0, self.build_prusti_contract_import(span));
         }
-        statements.insert(0, self.build_loop_mark(spec_id));
-        let builder = &self.ast_builder;
-        let expr = builder.expr_if(
-            Span::new(BytePos(0), BytePos(0), SyntaxContext::empty()),
-            builder.expr_bool(span, false),
-            builder.expr_block(builder.block(span, statements)),
-            None,
-        );
-        let mut expr = expr.into_inner();
-        expr.attrs = vec![self.ast_builder.attribute_name_value(
-            span,
-            "__PRUSTI_SPEC_ONLY",
-            &spec_id.to_string(),
-        )]
-        .into();
-        block.stmts.insert(0, builder.stmt_expr(ptr::P(expr)));
+        statements.insert(0, self.build_spec_mark("__PRUSTI_LOOP_SPEC_ID", spec_id));
+        let spec_block = self.build_spec_only_block(span, spec_id, statements);
+        block.stmts.insert(0, spec_block);
         trace!("[rewrite_loop_block] exit");
         ptr::P(block)
     }
 
+    /// Rewrite `prusti_assume!(assertion)`, `prusti_assert!(assertion)`,
+    /// `prusti_refute!(assertion)`, `fold!(predicate)` or `unfold!(predicate)` to a block that
+    /// type-checks the assertion and that marks the position of the statement. Returns `None` if
+    /// the assertion cannot be parsed.
+    fn rewrite_spec_statement(
+        &mut self,
+        mac: &ast::Mac,
+        span: Span,
+        typ: SpecType,
+    ) -> Option<ast::Stmt> {
+        trace!("[rewrite_spec_statement] enter typ={:?}", typ);
+        let (kind, attr_name) = match typ {
+            SpecType::Assumption => ("assumption", "__PRUSTI_ASSUMPTION_SPEC_ID"),
+            SpecType::Assertion => ("assertion", "__PRUSTI_ASSERTION_SPEC_ID"),
+            SpecType::Refutation => ("refutation", "__PRUSTI_REFUTATION_SPEC_ID"),
+            SpecType::Fold => ("fold", "__PRUSTI_FOLD_SPEC_ID"),
+            SpecType::Unfold => ("unfold", "__PRUSTI_UNFOLD_SPEC_ID"),
+            _ => unreachable!("{:?} is not a specification statement", typ),
+        };
+        let snippet = match self.session.codemap().span_to_snippet(mac.span) {
+            Ok(snippet) => snippet,
+            Err(_) => {
+                self.report_error(
+                    span,
+                    &format!("the source code of the {} is not available", kind),
+                );
+                return None;
+            }
+        };
+        let start = snippet.find(|c: char| c == '(' || c == '[' || c == '{');
+        let end = snippet.rfind(|c: char| c == ')' || c == ']' || c == '}');
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => {
+                self.report_error(span, &format!("malformed {} (expected an assertion)", kind));
+                return None;
+            }
+        };
+        let spec_string = &snippet[start + 1..end];
+        let spec_span = shift_resize_span(mac.span, start as u32, spec_string.len() as u32 + 2);
+        let assertion = self.parse_assertion_wrap(spec_span, spec_string)?;
+        let spec = UntypedSpecification {
+            typ: typ,
+            assertion: assertion,
+        };
+        let spec_set = match typ {
+            SpecType::Assumption => SpecificationSet::Assumption(vec![spec.clone()]),
+            SpecType::Assertion => SpecificationSet::Assertion(vec![spec.clone()]),
+            SpecType::Fold => SpecificationSet::Fold(vec![spec.clone()]),
+            SpecType::Unfold => SpecificationSet::Unfold(vec![spec.clone()]),
+            _ => SpecificationSet::Refutation(vec![spec.clone()]),
+        };
+        let id = self.register_specification(spec_set);
+        let mut statements = self.convert_to_statements(&[spec]);
+        statements.insert(0, self.build_prusti_contract_import(span));
+        statements.insert(0, self.build_spec_mark(attr_name, id));
+        trace!("[rewrite_spec_statement] exit");
+        Some(self.build_spec_only_block(span, id, statements))
+    }
+
     fn rewrite_loop(&mut self, expr: ptr::P<ast::Expr>) -> ptr::P<ast::Expr> {
         trace!("[rewrite_loop] enter");
         let mut expr = expr.into_inner();
@@ -988,6 +1272,11 @@ impl<'tcx> SpecParser<'tcx> {
             self.report_error(item.span, "invariant not allowed for procedure");
             return SmallVector::one(ptr::P(item));
         }
+        let measures: Vec<_> = specs
+            .iter()
+            .filter(|spec| spec.typ == SpecType::Decreases)
+            .cloned()
+            .collect();
         let preconditions: Vec<_> = specs
             .clone()
             .into_iter()
@@ -1006,9 +1295,23 @@ impl<'tcx> SpecParser<'tcx> {
             PRUSTI_SPEC_ATTR,
             &id.to_string(),
         ));
+        if let Some(attr) = self.register_termination_measures(item.span, &measures) {
+            item.attrs.push(attr);
+        }
+
+        // The body of a predicate is an assertion, which may use the specification functions
+        if item.attrs.iter().any(|attr| attr.check_name("predicate")) {
+            let import = self.build_prusti_contract_import(item.span);
+            if let ast::ItemKind::Fn(_, _, _, ref mut body) = item.node {
+                *body = body.clone().map(|mut block| {
+                    block.stmts.insert(0, import);
+                    block
+                });
+            }
+        }
 
         // Early returns
-        if spec_set.is_empty() {
+        if spec_set.is_empty() && measures.is_empty() {
             trace!("[rewrite_fn_item] exit");
             return SmallVector::one(ptr::P(item));
         }
@@ -1018,6 +1321,9 @@ impl<'tcx> SpecParser<'tcx> {
                 return SmallVector::one(ptr::P(item));
             }
         }
+        if let ast::ItemKind::Fn(ref mut decl, _, ref mut generics, _) = item.node {
+            self.desugar_impl_trait_args(decl, generics);
+        }
 
         // Dump modified item
         let new_item_str = syntax::print::pprust::item_to_string(&item);
@@ -1025,16 +1331,26 @@ impl<'tcx> SpecParser<'tcx> {
         self.log_modified_program(new_item_str);
 
         // Create spec item
-        let mut spec_item = self.generate_spec_item(&item, id, &preconditions, &postconditions);
+        let mut spec_item = self.generate_spec_item(
+            &item,
+            id,
+            &[&preconditions[..], &measures[..]].concat(),
+            &postconditions,
+        );
         spec_item
             .attrs
             .extend(item.attrs.iter().cloned().filter(|attr| {
                 !attr.check_name("trusted")
                     && !attr.check_name("pure")
+                    && !attr.check_name("pure_spec")
+                    && !attr.check_name("predicate")
+                    && !attr.check_name("lock_invariant")
                     && !attr.check_name("invariant")
                     && !attr.check_name("requires")
                     && !attr.check_name("ensures")
+                    && !attr.check_name("decreases")
                     && !attr.check_name(PRUSTI_SPEC_ATTR)
+                    && !attr.check_name("__PRUSTI_TERMINATION_SPEC_ID")
             }));
 
         // Dump spec item
@@ -1053,6 +1369,7 @@ impl<'tcx> SpecParser<'tcx> {
     fn rewrite_impl_item_method(
         &mut self,
         mut impl_item: ast::ImplItem,
+        is_trait_impl: bool,
     ) -> (SmallVector<ast::ImplItem>, SmallVector<ast::ImplItem>) {
         trace!("[rewrite_impl_item_method] enter");
 
@@ -1062,6 +1379,11 @@ impl<'tcx> SpecParser<'tcx> {
             self.report_error(impl_item.span, "invariant not allowed for procedure");
             return (SmallVector::one(impl_item), SmallVector::new());
         }
+        let measures: Vec<_> = specs
+            .iter()
+            .filter(|spec| spec.typ == SpecType::Decreases)
+            .cloned()
+            .collect();
         let preconditions: Vec<_> = specs
             .clone()
             .into_iter()
@@ -1080,9 +1402,12 @@ impl<'tcx> SpecParser<'tcx> {
             PRUSTI_SPEC_ATTR,
             &id.to_string(),
         ));
+        if let Some(attr) = self.register_termination_measures(impl_item.span, &measures) {
+            impl_item.attrs.push(attr);
+        }
 
         // Early returns
-        if spec_set.is_empty() {
+        if spec_set.is_empty() && measures.is_empty() {
             trace!("[rewrite_impl_item_method] exit");
             return (SmallVector::one(impl_item), SmallVector::new());
         }
@@ -1092,6 +1417,14 @@ impl<'tcx> SpecParser<'tcx> {
                 return (SmallVector::one(impl_item), SmallVector::new());
             }
         }
+        // The methods of a trait impl have to keep the `impl Trait` arguments of the trait.
+        if !is_trait_impl {
+            if let ast::ImplItemKind::Method(ast::MethodSig { ref mut decl, .. }, _) =
+                impl_item.node
+            {
+                self.desugar_impl_trait_args(decl, &mut impl_item.generics);
+            }
+        }
 
         // Dump modified item
         let new_item_str = syntax::print::pprust::impl_item_to_string(&impl_item);
@@ -1099,17 +1432,25 @@ impl<'tcx> SpecParser<'tcx> {
         self.log_modified_program(new_item_str);
 
         // Create spec item
-        let mut spec_item =
-            self.generate_spec_impl_item(&impl_item, id, &preconditions, &postconditions);
+        let mut spec_item = self.generate_spec_impl_item(
+            &impl_item,
+            id,
+            &[&preconditions[..], &measures[..]].concat(),
+            &postconditions,
+        );
         spec_item
             .attrs
             .extend(impl_item.attrs.iter().cloned().filter(|attr| {
                 !attr.check_name("trusted")
                     && !attr.check_name("pure")
+                    && !attr.check_name("pure_spec")
+                    && !attr.check_name("lock_invariant")
                     && !attr.check_name("invariant")
                     && !attr.check_name("requires")
                     && !attr.check_name("ensures")
+                    && !attr.check_name("decreases")
                     && !attr.check_name(PRUSTI_SPEC_ATTR)
+                    && !attr.check_name("__PRUSTI_TERMINATION_SPEC_ID")
             }));
 
         // Dump spec item
@@ -1122,14 +1463,14 @@ impl<'tcx> SpecParser<'tcx> {
         (SmallVector::one(impl_item), SmallVector::one(spec_item))
     }
 
-    fn rewrite_struct_item(&mut self, item: ptr::P<ast::Item>) -> SmallVector<ptr::P<ast::Item>> {
-        trace!("[rewrite_struct_item] enter");
+    fn rewrite_type_item(&mut self, item: ptr::P<ast::Item>) -> SmallVector<ptr::P<ast::Item>> {
+        trace!("[rewrite_type_item] enter");
         let mut item = item.into_inner();
 
         // Parse specification
         let specs = self.parse_specs(item.attrs.clone());
         if specs.iter().any(|spec| spec.typ != SpecType::Invariant) {
-            self.report_error(item.span, "only invariant allowed for struct");
+            self.report_error(item.span, "only invariant allowed for struct or enum");
             return SmallVector::one(ptr::P(item));
         }
         let invariants: Vec<_> = specs
@@ -1149,7 +1490,7 @@ impl<'tcx> SpecParser<'tcx> {
 
         // Early returns
         if spec_set.is_empty() {
-            trace!("[rewrite_struct_item] exit EARLY");
+            trace!("[rewrite_type_item] exit EARLY");
             return SmallVector::one(ptr::P(item));
         }
 
@@ -1166,6 +1507,7 @@ impl<'tcx> SpecParser<'tcx> {
             .extend(item.attrs.iter().cloned().filter(|attr| {
                 !attr.check_name("trusted")
                     && !attr.check_name("pure")
+                    && !attr.check_name("pure_spec")
                     && !attr.check_name("invariant")
                     && !attr.check_name("requires")
                     && !attr.check_name("ensures")
@@ -1178,7 +1520,7 @@ impl<'tcx> SpecParser<'tcx> {
         self.log_modified_program(spec_item_str);
 
         // Return small vector
-        trace!("[rewrite_struct_item] exit");
+        trace!("[rewrite_type_item] exit");
         let mut result = SmallVector::new();
         result.push(ptr::P(item));
         result.push(ptr::P(spec_item));
@@ -1197,6 +1539,13 @@ impl<'tcx> SpecParser<'tcx> {
             self.report_error(trait_item.span, "invariant not allowed for procedure");
             return SmallVector::one(trait_item);
         }
+        if specs.iter().any(|spec| spec.typ == SpecType::Decreases) {
+            self.report_error(
+                trait_item.span,
+                "termination measure not allowed for trait method",
+            );
+            return SmallVector::one(trait_item);
+        }
         let preconditions: Vec<_> = specs
             .clone()
             .into_iter()
@@ -1254,6 +1603,7 @@ impl<'tcx> SpecParser<'tcx> {
             .extend(trait_item.attrs.iter().cloned().filter(|attr| {
                 !attr.check_name("trusted")
                     && !attr.check_name("pure")
+                    && !attr.check_name("pure_spec")
                     && !attr.check_name("invariant")
                     && !attr.check_name("requires")
                     && !attr.check_name("ensures")
@@ -1387,7 +1737,8 @@ impl<'tcx> SpecParser<'tcx> {
             }
             Err(AssertionParsingError::ParsingRustExpressionFailed)
             | Err(AssertionParsingError::FailedForallMatch)
-            | Err(AssertionParsingError::FailedAfterExpiryMatch) => None,
+            | Err(AssertionParsingError::FailedAfterExpiryMatch)
+            | Err(AssertionParsingError::FailedLetMatch) => None,
         }
     }
 
@@ -1448,6 +1799,22 @@ impl<'tcx> SpecParser<'tcx> {
             spec_string,
             base_span
         );
+        if contains_snap_eq(&spec_string) {
+            let result = self.parse_snap_eq_expression(base_span, &spec_string);
+            trace!("[parse_expression] exit");
+            return result;
+        }
+        let result = self.parse_rust_expression(base_span, spec_string);
+        trace!("[parse_expression] exit");
+        result
+    }
+
+    /// Parse a Rust expression that does not contain the `===` operator.
+    fn parse_rust_expression(
+        &mut self,
+        base_span: Span,
+        spec_string: String,
+    ) -> Result<ptr::P<ast::Expr>, AssertionParsingError> {
         let mut whitespace_count = 0;
         for char in spec_string.chars() {
             if !char.is_whitespace() {
@@ -1462,8 +1829,13 @@ impl<'tcx> SpecParser<'tcx> {
         )
         .parse_expr();
         debug!("Parsed expr: {:?}", expr);
-        let result = match expr {
+        match expr {
             Ok(expr) => {
+                let expr = VariantMacroExpander {
+                    parse_sess: &self.session.parse_sess,
+                    ast_builder: &self.ast_builder,
+                }
+                .fold_expr(expr);
                 let mut rewriter = SpanRewriter::new(whitespace_count, expr.span, base_span);
                 let expr = rewriter.fold_expr(expr);
                 Ok(expr)
@@ -1472,9 +1844,85 @@ impl<'tcx> SpecParser<'tcx> {
                 err.emit();
                 Err(AssertionParsingError::ParsingRustExpressionFailed)
             }
+        }
+    }
+
+    /// Parse a Rust expression that contains the `===` operator. The operator binds tighter
+    /// than `&&` and `||`, thus the expression is first split on these operators. The operator
+    /// can be nested in parentheses, possibly negated, but not in other expressions.
+    fn parse_snap_eq_expression(
+        &mut self,
+        base_span: Span,
+        spec_string: &str,
+    ) -> Result<ptr::P<ast::Expr>, AssertionParsingError> {
+        let (operands, operators) = split_logical_operators(spec_string);
+        if !operators.is_empty() {
+            let mut exprs = vec![];
+            for (start, operand) in operands {
+                let span = shift_span(base_span, start as u32);
+                exprs.push(self.parse_expression(span, operand.to_string())?);
+            }
+            // `&&` binds tighter than `||`.
+            let builder = &self.ast_builder;
+            let mut exprs = exprs.into_iter();
+            let mut disjuncts = vec![];
+            let mut conjunction = exprs.next().unwrap();
+            for (operator, expr) in operators.into_iter().zip(exprs) {
+                if operator == ast::BinOpKind::And {
+                    let span = conjunction.span.to(expr.span);
+                    conjunction = builder.expr_binary(span, operator, conjunction, expr);
+                } else {
+                    disjuncts.push(conjunction);
+                    conjunction = expr;
+                }
+            }
+            return Ok(disjuncts.into_iter().rev().fold(conjunction, |disjunction, disjunct| {
+                let span = disjunct.span.to(disjunction.span);
+                builder.expr_binary(span, ast::BinOpKind::Or, disjunct, disjunction)
+            }));
+        }
+        if let Some(position) = find_snap_eq(spec_string) {
+            let left = self.parse_expression(base_span, spec_string[..position].to_string())?;
+            let right = self.parse_expression(
+                shift_span(base_span, (position + 3) as u32),
+                spec_string[position + 3..].to_string(),
+            )?;
+            let builder = &self.ast_builder;
+            let (left_span, right_span) = (left.span, right.span);
+            return Ok(builder.expr_call_ident(
+                left_span.to(right_span),
+                builder.ident_of("snap_eq"),
+                vec![
+                    builder.expr_addr_of(left_span, left),
+                    builder.expr_addr_of(right_span, right),
+                ],
+            ));
+        }
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"(?s)^(\s*(?P<not>!)?\s*\()(?P<inner>.*)\)\s*$").unwrap();
+        }
+        let (inner_start, inner, is_negated) = match RE.captures(spec_string) {
+            Some(caps) => (
+                caps[1].len(),
+                caps["inner"].to_string(),
+                caps.name("not").is_some(),
+            ),
+            // The Rust parser reports the nested `===`.
+            None => return self.parse_rust_expression(base_span, spec_string.to_string()),
         };
-        trace!("[parse_expression] exit");
-        result
+        if !has_matching_parenthesis(&inner) {
+            return self.parse_rust_expression(base_span, spec_string.to_string());
+        }
+        let inner = self.parse_expression(shift_span(base_span, inner_start as u32), inner)?;
+        let builder = &self.ast_builder;
+        let span = inner.span;
+        let paren = builder.expr(span, ast::ExprKind::Paren(inner));
+        if is_negated {
+            Ok(builder.expr_unary(span, ast::UnOp::Not, paren))
+        } else {
+            Ok(paren)
+        }
     }
 
     /// Parse Rust type. (copied from parse_expression)
@@ -1608,8 +2056,8 @@ impl<'tcx> SpecParser<'tcx> {
         self.parse_expression(span, expr_string)
     }
 
-    // TODO: name; now also parses "assert_on_expiry"
-    fn parse_after_expiry(
+    /// Parse a pledge: `after_expiry(rhs)` or `assert_on_expiry(lhs[, rhs])`.
+    fn parse_pledge(
         &mut self,
         span: Span,
         spec_string: &str,
@@ -1656,30 +2104,43 @@ impl<'tcx> SpecParser<'tcx> {
                 None
             };
             let body_str = caps.name("body").unwrap().as_str();
-            let (lhs_str, rhs_str) = match caps.name("construct").unwrap().as_str() {
+            let whitespace1_len = caps.name("whitespace1").unwrap().as_str().len();
+            let body_span = shift_resize_span(span, whitespace1_len as u32, body_str.len() as u32);
+            let (lhs, rhs) = match caps.name("construct").unwrap().as_str() {
                 "after_expiry" => {
                     // after_expiry(rhs) // lhs == true
-                    ("true", body_str)
+                    (
+                        self.parse_assertion(body_span, "true")?,
+                        self.parse_assertion(body_span, body_str)?,
+                    )
                 }
                 "assert_on_expiry" => {
                     // assert_on_expiry(lhs[, rhs]) // default: rhs == true
-                    let mut iter = body_str.rsplitn(2, ',');
-                    let one = iter.next();
-                    let two = iter.next();
-                    if two.is_none() {
-                        (one.unwrap(), "true")
-                    } else {
-                        (two.unwrap(), one.unwrap())
+                    let args = split_arguments(body_str)?;
+                    let arg_span = |(offset, arg): (usize, &str)| {
+                        shift_resize_span(span, (whitespace1_len + offset) as u32, arg.len() as u32)
+                    };
+                    match args.len() {
+                        1 => (
+                            self.parse_assertion(arg_span(args[0]), args[0].1)?,
+                            self.parse_assertion(body_span, "true")?,
+                        ),
+                        2 => (
+                            self.parse_assertion(arg_span(args[0]), args[0].1)?,
+                            self.parse_assertion(arg_span(args[1]), args[1].1)?,
+                        ),
+                        _ => {
+                            self.report_error(
+                                body_span,
+                                "assert_on_expiry expects an assertion and, optionally, \
+                                 the assertion that holds after the expiry",
+                            );
+                            return Err(AssertionParsingError::FailedAfterExpiryMatch);
+                        }
                     }
                 }
                 _ => unreachable!(),
             };
-            let new_span = {
-                let whitespace1_len = caps.name("whitespace1").unwrap().as_str().len();
-                shift_resize_span(span, whitespace1_len as u32, body_str.len() as u32)
-            };
-            let lhs = self.parse_assertion(new_span, lhs_str)?;
-            let rhs = self.parse_assertion(new_span, rhs_str)?;
             debug!(
                 "after_expiry: reference={:?} lhs={:?} rhs={:?}",
                 reference, lhs, rhs
@@ -1774,6 +2235,164 @@ impl<'tcx> SpecParser<'tcx> {
         }
     }
 
+    /// Parse a quantifier written with the closure syntax, for example
+    /// `forall(|i: usize| i < len ==> v.lookup(i) > 0)` or
+    /// `exists(|i: usize| i < len && v.lookup(i) == 0)`.
+    ///
+    /// The triggers can be given before the body, as in the other syntax of
+    /// `forall`: `forall(|i: usize| {v.lookup(i)} ...)`. If they are missing,
+    /// they are inferred by the verifier.
+    fn parse_closure_quantifier(
+        &mut self,
+        span: Span,
+        caps: regex::Captures,
+    ) -> Result<UntypedAssertion, AssertionParsingError> {
+        let quantifier = caps.name("quantifier").unwrap().as_str();
+        trace!("[enter] parse_closure_quantifier quantifier={}", quantifier);
+        let vars = self.parse_vars(span, caps.name("vars").unwrap())?;
+        let triggers = match caps.name("triggers") {
+            Some(triggers) => self.parse_triggers(span, triggers)?,
+            None => TriggerSet::new(vec![]),
+        };
+        let body_match = caps.name("body").unwrap();
+        let body_string = body_match.as_str();
+        let body_span = shift_resize_span(
+            span,
+            body_match.start() as u32,
+            body_string.len() as u32,
+        );
+        let body = match body_string.find("==>") {
+            Some(position) if quantifier == "forall" => {
+                let filter_string = &body_string[..position];
+                let implied_string = &body_string[position + 3..];
+                if implied_string.contains("==>") {
+                    self.report_error(body_span, "forall can have only one implication");
+                    return Err(AssertionParsingError::FailedForallMatch);
+                }
+                let filter = self.parse_expression(
+                    shift_resize_span(body_span, 0, filter_string.len() as u32),
+                    filter_string.to_string(),
+                )?;
+                let implied = self.parse_expression(
+                    shift_resize_span(
+                        body_span,
+                        (position + 3) as u32,
+                        implied_string.len() as u32,
+                    ),
+                    implied_string.to_string(),
+                )?;
+                UntypedAssertion {
+                    kind: box AssertionKind::Implies(
+                        UntypedAssertion {
+                            kind: box AssertionKind::Expr(Expression {
+                                id: self.get_new_expression_id(),
+                                expr: filter,
+                            }),
+                        },
+                        UntypedAssertion {
+                            kind: box AssertionKind::Expr(Expression {
+                                id: self.get_new_expression_id(),
+                                expr: implied,
+                            }),
+                        },
+                    ),
+                }
+            }
+            Some(_) => {
+                self.report_error(body_span, "exists cannot contain an implication");
+                return Err(AssertionParsingError::FailedForallMatch);
+            }
+            None => {
+                let expr = self.parse_expression(body_span, body_string.to_string())?;
+                UntypedAssertion {
+                    kind: box AssertionKind::Expr(Expression {
+                        id: self.get_new_expression_id(),
+                        expr,
+                    }),
+                }
+            }
+        };
+        debug!(
+            "{}: vars={:?} triggers={:?} body={:?}",
+            quantifier, vars, triggers, body
+        );
+        let vars = ForAllVars {
+            id: self.get_new_expression_id(),
+            vars,
+        };
+        let kind = if quantifier == "forall" {
+            AssertionKind::ForAll(vars, triggers, body)
+        } else {
+            AssertionKind::Exists(vars, triggers, body)
+        };
+        Ok(UntypedAssertion { kind: box kind })
+    }
+
+    /// Parse a let binding: `let var = value in body`. The body is the rest of the
+    /// assertion string.
+    fn parse_let(
+        &mut self,
+        span: Span,
+        spec_string: &str,
+    ) -> Result<UntypedAssertion, AssertionParsingError> {
+        trace!("[enter] parse_let spec_string={}", spec_string);
+        lazy_static! {
+            static ref RE: Regex = Regex::new(
+                r"(?sx)
+                ^\s*let\s+(?P<var>[a-z_][a-z0-9_]*)\s*=\s*
+                (?P<value>.*?)\s+in\s+(?P<body>.*)$
+            ",
+            )
+            .unwrap();
+        }
+        if let Some(caps) = RE.captures(spec_string) {
+            let var_match = caps.name("var").unwrap();
+            let var_span = shift_resize_span(
+                span,
+                var_match.start() as u32,
+                var_match.as_str().len() as u32,
+            );
+            let var = {
+                let builder = &self.ast_builder;
+                builder.arg(
+                    var_span,
+                    builder.ident_of(var_match.as_str()),
+                    builder.ty(var_span, ast::TyKind::Infer),
+                )
+            };
+            let value_match = caps.name("value").unwrap();
+            let value = self.parse_expression(
+                shift_resize_span(
+                    span,
+                    value_match.start() as u32,
+                    value_match.as_str().len() as u32,
+                ),
+                value_match.as_str().to_string(),
+            )?;
+            let body_match = caps.name("body").unwrap();
+            let body = self.parse_assertion(
+                shift_span(span, body_match.start() as u32),
+                body_match.as_str(),
+            )?;
+            Ok(UntypedAssertion {
+                kind: box AssertionKind::Let(
+                    ForAllVars {
+                        id: self.get_new_expression_id(),
+                        vars: vec![var],
+                    },
+                    Expression {
+                        id: self.get_new_expression_id(),
+                        expr: value,
+                    },
+                    body,
+                ),
+            })
+        } else {
+            self.report_error(span, "failed to parse let expression");
+            Err(AssertionParsingError::FailedLetMatch)
+        }
+    }
+
     /// Parse an assertion string into an assertion object.
     /// The assertion string can only contain an implication, forall, or a
     /// Rust expression.
@@ -1798,9 +2417,33 @@ impl<'tcx> SpecParser<'tcx> {
             }
         }
 
+        // Parse let.
+        if is_let_assertion(&spec_string) {
+            return self.parse_let(span, &spec_string);
+        }
+
         // Parse after_expiry or assert_on_expiry.
         if spec_string.contains("after_expiry") || spec_string.contains("assert_on_expiry") {
-            return self.parse_after_expiry(span, &spec_string);
+            return self.parse_pledge(span, &spec_string);
+        }
+
+        // Parse forall and exists with the closure syntax.
+        {
+            lazy_static! {
+                static ref RE: Regex = Regex::new(
+                    r"(?sx)
+                    ^\s*(?P<quantifier>forall|exists)\s*\(\s*
+                    \|(?P<vars>[^|]*)\|\s*(\{(?P<triggers>[^}]*)\})?\s*
+                    (?P<body>.*)\)\s*$
+                ",
+                )
+                .unwrap();
+            }
+            if let Some(caps) = RE.captures(&spec_string) {
+                if has_matching_parenthesis(caps.name("body").unwrap().as_str()) {
+                    return self.parse_closure_quantifier(span, caps);
+                }
+            }
         }
 
         // Parse forall.
@@ -1886,7 +2529,12 @@ impl<'tcx> SpecParser<'tcx> {
         let mut block_start = 0;
         let mut assertions: Vec<UntypedAssertion> = Vec::new();
         let mut parenthesis_depth = 0;
-        while let Some((position, char)) = iter.next() {
+        // The body of a `let` extends to the end of the assertion.
+        while !is_let_assertion(&spec_string[block_start..]) {
+            let (position, char) = match iter.next() {
+                Some(next) => next,
+                None => break,
+            };
             if char == '(' {
                 parenthesis_depth += 1;
                 continue;
@@ -1962,8 +2610,8 @@ impl<'tcx> Folder for SpecParser<'tcx> {
                 // Top-level functions
                 ast::ItemKind::Fn(..) => self.rewrite_fn_item(item),
 
-                // Structs
-                ast::ItemKind::Struct(..) => self.rewrite_struct_item(item),
+                // Structs and enums
+                ast::ItemKind::Struct(..) | ast::ItemKind::Enum(..) => self.rewrite_type_item(item),
 
                 // Impl methods
                 ast::ItemKind::Impl(
@@ -1982,7 +2630,7 @@ impl<'tcx> Folder for SpecParser<'tcx> {
                         match impl_item.node {
                             ast::ImplItemKind::Method(..) => {
                                 let (code_items, spec_items) =
-                                    self.rewrite_impl_item_method(impl_item);
+                                    self.rewrite_impl_item_method(impl_item, ifce.is_some());
                                 new_code_items.extend(code_items);
                                 new_spec_items.extend(spec_items);
                             }
@@ -2063,6 +2711,43 @@ impl<'tcx> Folder for SpecParser<'tcx> {
         }
     }
 
+    fn fold_stmt(&mut self, stmt: ast::Stmt) -> SmallVector<ast::Stmt> {
+        if let ast::StmtKind::Mac(ref mac) = stmt.node {
+            let opt_typ = if mac.0.node.path == "prusti_assume" {
+                Some(SpecType::Assumption)
+            } else if mac.0.node.path == "prusti_assert" {
+                Some(SpecType::Assertion)
+            } else if mac.0.node.path == "prusti_refute" {
+                Some(SpecType::Refutation)
+            } else if mac.0.node.path == "fold" {
+                Some(SpecType::Fold)
+            } else if mac.0.node.path == "unfold" {
+                Some(SpecType::Unfold)
+            } else {
+                None
+            };
+            if let Some(typ) = opt_typ {
+                return self
+                    .rewrite_spec_statement(&mac.0, stmt.span, typ)
+                    .into_iter()
+                    .collect();
+            }
+        }
+        fold::noop_fold_stmt(stmt, self)
+    }
+
+    fn fold_attribute(&mut self, attr: ast::Attribute) -> Option<ast::Attribute> {
+        // Rewrite `#[prusti::skip]`, whose tool name is unknown to the compiler, to an
+        // attribute that can be registered.
+        let is_prusti_skip = attr.path.segments.len() == 2
+            && attr.path.segments[0].ident.name == "prusti"
+            && attr.path.segments[1].ident.name == "skip";
+        if is_prusti_skip {
+            return Some(self.ast_builder.attribute_word(attr.span, "__PRUSTI_SKIP"));
+        }
+        fold::noop_fold_attribute(attr, self)
+    }
+
     fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
         mac
     }
@@ -2079,6 +2764,148 @@ pub enum AssertionParsingError {
     FailedForallMatch,
     /// Reported when matching after_expiry expression fails.
     FailedAfterExpiryMatch,
+    /// Reported when matching let expression fails.
+    FailedLetMatch,
+}
+
+/// Split the arguments of a specification construct at the commas that are not
+/// nested in parentheses, brackets or braces. Return the byte offset and the
+/// text of each argument.
+fn split_arguments(string: &str) -> Result<Vec<(usize, &str)>, AssertionParsingError> {
+    let mut arguments = Vec::new();
+    let mut argument_start = 0;
+    let mut depth = 0;
+    for (position, char) in string.char_indices() {
+        match char {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return Err(AssertionParsingError::NotMatchingParenthesis);
+                }
+            }
+            ',' if depth == 0 => {
+                arguments.push((argument_start, &string[argument_start..position]));
+                argument_start = position + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(AssertionParsingError::NotMatchingParenthesis);
+    }
+    arguments.push((argument_start, &string[argument_start..]));
+    Ok(arguments)
+}
+
+/// The characters of the expression string that are not in string or character literals, with
+/// their position and their nesting depth in parentheses, brackets or braces.
+fn code_chars(string: &str) -> Vec<(usize, char, i32)> {
+    let mut result = vec![];
+    let mut depth = 0;
+    let mut chars = string.char_indices().peekable();
+    while let Some((position, char)) = chars.next() {
+        match char {
+            '"' => {
+                // Skip the string literal.
+                while let Some((_, char)) = chars.next() {
+                    match char {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\'' if is_char_literal(&string[position..]) => {
+                // Skip the character literal.
+                if let Some((_, '\\')) = chars.next() {
+                    chars.next();
+                }
+                while let Some((_, char)) = chars.next() {
+                    if char == '\'' {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                match char {
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => depth -= 1,
+                    _ => {}
+                }
+                result.push((position, char, depth));
+            }
+        }
+    }
+    result
+}
+
+/// Whether the string starts with a character literal, rather than with a lifetime.
+fn is_char_literal(string: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^'(\\.[^']*|[^'\\])'").unwrap();
+    }
+    RE.is_match(string)
+}
+
+/// The position of the first `===` operator of the expression string that is not nested in
+/// parentheses, brackets or braces, nor in a string or character literal.
+fn find_snap_eq(string: &str) -> Option<usize> {
+    code_chars(string)
+        .into_iter()
+        .find(|&(position, char, depth)| {
+            depth == 0 && char == '=' && string[position..].starts_with("===")
+        })
+        .map(|(position, _, _)| position)
+}
+
+/// Whether the expression string contains a `===` operator that is not in a string or
+/// character literal.
+fn contains_snap_eq(string: &str) -> bool {
+    code_chars(string)
+        .into_iter()
+        .any(|(position, char, _)| char == '=' && string[position..].starts_with("==="))
+}
+
+/// Split the expression string on its `&&` and `||` operators that are not nested in
+/// parentheses, brackets or braces, nor in a string or character literal. Returns the operands
+/// with their position, and the operators between them.
+fn split_logical_operators(string: &str) -> (Vec<(usize, &str)>, Vec<ast::BinOpKind>) {
+    let mut operands = vec![];
+    let mut operators = vec![];
+    let mut operand_start = 0;
+    let mut chars = code_chars(string).into_iter().peekable();
+    while let Some((position, char, depth)) = chars.next() {
+        let operator = match char {
+            '&' => ast::BinOpKind::And,
+            '|' => ast::BinOpKind::Or,
+            _ => continue,
+        };
+        let is_doubled = match chars.peek() {
+            Some(&(next_position, next_char, _)) => {
+                next_position == position + 1 && next_char == char
+            }
+            None => false,
+        };
+        if depth == 0 && is_doubled {
+            chars.next();
+            operands.push((operand_start, &string[operand_start..position]));
+            operators.push(operator);
+            operand_start = position + 2;
+        }
+    }
+    operands.push((operand_start, &string[operand_start..]));
+    (operands, operators)
+}
+
+/// Whether the assertion string starts with a `let` binding.
+fn is_let_assertion(string: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^\s*let\s").unwrap();
+    }
+    RE.is_match(string)
 }
 
 fn substring(string: &str, start: usize, end: usize) -> String {
@@ -2114,6 +2941,127 @@ impl Folder for SpanRewriter {
     }
 }
 
+/// Rewrites the `matches!(expr, pattern)` and `is_variant!(expr, Variant)`
+/// macros used in specifications to `match` expressions, because they do
+/// not exist in the standard library.
+struct VariantMacroExpander<'a, 'tcx: 'a> {
+    parse_sess: &'a parse::ParseSess,
+    ast_builder: &'a MinimalAstBuilder<'tcx>,
+}
+
+impl<'a, 'tcx: 'a> VariantMacroExpander<'a, 'tcx> {
+    /// Build `match expr { patterns => true, _ => false }`.
+    fn expand(&self, mac: &ast::Mac, span: Span) -> Option<ptr::P<ast::Expr>> {
+        let is_variant = if mac.node.path == "matches" {
+            false
+        } else if mac.node.path == "is_variant" {
+            true
+        } else {
+            return None;
+        };
+        let mut parser = parse::stream_to_parser(self.parse_sess, mac.node.stream());
+        let parsed: parse::PResult<_> = (|| {
+            let expr = parser.parse_expr()?;
+            parser.expect(&parse::token::Comma)?;
+            let mut patterns = vec![];
+            loop {
+                let pattern = if is_variant {
+                    let path = parser.parse_path(parse::parser::PathStyle::Expr)?;
+                    self.ast_builder
+                        .pat(path.span, ast::PatKind::Struct(path, vec![], true))
+                } else {
+                    parser.parse_pat()?
+                };
+                patterns.push(pattern);
+                if !parser.eat(&parse::token::BinOp(parse::token::Or)) {
+                    break;
+                }
+            }
+            parser.expect(&parse::token::Eof)?;
+            Ok((expr, patterns))
+        })();
+        match parsed {
+            Ok((expr, patterns)) => {
+                let arms = vec![
+                    self.ast_builder
+                        .arm(span, patterns, self.ast_builder.expr_bool(span, true)),
+                    self.ast_builder.arm(
+                        span,
+                        vec![self.ast_builder.pat_wild(span)],
+                        self.ast_builder.expr_bool(span, false),
+                    ),
+                ];
+                Some(self.ast_builder.expr_match(span, expr, arms))
+            }
+            Err(mut err) => {
+                err.emit();
+                None
+            }
+        }
+    }
+}
+
+impl<'a, 'tcx: 'a> Folder for VariantMacroExpander<'a, 'tcx> {
+    fn fold_expr(&mut self, expr: ptr::P<ast::Expr>) -> ptr::P<ast::Expr> {
+        let expanded = match expr.node {
+            ast::ExprKind::Mac(ref mac) => self.expand(mac, expr.span),
+            _ => None,
+        };
+        match expanded {
+            Some(expanded) => self.fold_expr(expanded),
+            None => expr.map(|expr| fold::noop_fold_expr(expr, self)),
+        }
+    }
+
+    fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
+        mac
+    }
+}
+
+/// Replaces the `impl Trait` types with fresh type parameters, collecting their
+/// declarations in `params`.
+struct ImplTraitDesugarer<'a, 'tcx: 'a> {
+    ast_builder: &'a MinimalAstBuilder<'tcx>,
+    params: Vec<ast::GenericParam>,
+}
+
+impl<'a, 'tcx: 'a> Folder for ImplTraitDesugarer<'a, 'tcx> {
+    fn fold_ty(&mut self, ty: ptr::P<ast::Ty>) -> ptr::P<ast::Ty> {
+        let ty = fold::noop_fold_ty(ty, self);
+        ty.map(|ty| match ty.node {
+            ast::TyKind::ImplTrait(bounds) => {
+                let ident = ast::Ident::from_str(&format!(
+                    "__PrustiImplTrait{}",
+                    self.params.len()
+                ));
+                self.params
+                    .push(self.ast_builder.typaram(ty.span, ident, vec![], bounds, None));
+                let path = self.ast_builder.path_ident(ty.span, ident);
+                self.ast_builder.ty_path(path).into_inner()
+            }
+            node => ast::Ty { node, ..ty },
+        })
+    }
+}
+
+/// Check that the parenthesis of `string` are balanced.
+fn has_matching_parenthesis(string: &str) -> bool {
+    let mut depth = 0;
+    for char in string.chars() {
+        match char {
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
 fn shift_span(span: Span, offset: u32) -> Span {
     let offset = syntax::codemap::BytePos(offset);
     Span::new(span.lo() + offset, span.hi() + offset, span.ctxt())
@@ -0,0 +1,122 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! This module defines functions for machine-readable output, meant for IDEs and CI tools.
+//! Each message is printed to *stdout* as a JSON object on its own line.
+//! Note: these functions do nothing unless `config::message_format()` is `json`.
+
+use config;
+use serde_json;
+use std::time::Duration;
+use syntax::codemap::CodeMap;
+use syntax_pos::Span;
+
+/// A source code region, with 1-based lines and columns.
+#[derive(Serialize)]
+struct JsonSpan {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    line_end: usize,
+    column_end: usize,
+}
+
+impl JsonSpan {
+    fn new(codemap: &CodeMap, span: Span) -> Self {
+        let span = span.source_callsite();
+        let start = codemap.lookup_char_pos(span.lo());
+        let end = codemap.lookup_char_pos(span.hi());
+        JsonSpan {
+            file_name: start.file.name.to_string(),
+            line_start: start.line,
+            column_start: start.col.0 + 1,
+            line_end: end.line,
+            column_end: end.col.0 + 1,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonMessage<'a> {
+    Error {
+        /// The Viper error identifier, e.g. `assert.failed:assertion.false`.
+        kind: Option<&'a str>,
+        message: &'a str,
+        help: Option<&'a str>,
+        span: Option<JsonSpan>,
+        /// The identifier of the VIR position of the failing expression.
+        pos_id: Option<&'a str>,
+    },
+    Warning {
+        message: &'a str,
+        span: Option<JsonSpan>,
+    },
+    Timing {
+        stage: &'a str,
+        seconds: f64,
+    },
+    ProfileResult {
+        /// The spec profile, e.g. `debug` or `release`.
+        profile: &'a str,
+        success: bool,
+    },
+}
+
+fn enabled() -> bool {
+    config::message_format() == "json"
+}
+
+fn emit(message: &JsonMessage) {
+    println!("{}", serde_json::to_string(message).unwrap());
+}
+
+/// Print a verification error.
+pub fn error(
+    codemap: &CodeMap,
+    span: Option<Span>,
+    kind: Option<&str>,
+    message: &str,
+    help: Option<&str>,
+    pos_id: Option<&str>,
+) {
+    if enabled() {
+        emit(&JsonMessage::Error {
+            kind,
+            message,
+            help,
+            span: span.map(|span| JsonSpan::new(codemap, span)),
+            pos_id,
+        });
+    }
+}
+
+/// Print a warning.
+pub fn warning(codemap: &CodeMap, span: Option<Span>, message: &str) {
+    if enabled() {
+        emit(&JsonMessage::Warning {
+            message,
+            span: span.map(|span| JsonSpan::new(codemap, span)),
+        });
+    }
+}
+
+/// Print the duration of a stage of the verification.
+pub fn timing(stage: &str, duration: Duration) {
+    if enabled() {
+        emit(&JsonMessage::Timing {
+            stage,
+            seconds: duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9,
+        });
+    }
+}
+
+/// Print the result of the verification under a spec profile.
+pub fn profile_result(profile: &str, success: bool) {
+    if enabled() {
+        emit(&JsonMessage::ProfileResult { profile, success });
+    }
+}
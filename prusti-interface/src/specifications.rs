@@ -25,13 +25,25 @@ pub enum SpecType {
     Postcondition,
     /// Loop invariant or struct invariant
     Invariant,
+    /// Assumption written with `prusti_assume!` in the body of a procedure.
+    Assumption,
+    /// Assertion written with `prusti_assert!` in the body of a procedure.
+    Assertion,
+    /// Refutation written with `prusti_refute!` in the body of a procedure.
+    Refutation,
+    /// Fold of a user predicate written with `fold!` in the body of a procedure.
+    Fold,
+    /// Unfold of a user predicate written with `unfold!` in the body of a procedure.
+    Unfold,
+    /// Termination measure of a recursive pure function.
+    Decreases,
 }
 
 #[derive(Debug)]
 /// A conversion from string into specification type error.
 pub enum TryFromStringError {
     /// Reported when the string being converted is not one of the
-    /// following: `requires`, `ensures`, `invariant`.
+    /// following: `requires`, `ensures`, `invariant`, `decreases`.
     UnknownSpecificationType,
 }
 
@@ -43,6 +55,7 @@ impl<'a> TryFrom<&'a str> for SpecType {
             "requires" => Ok(SpecType::Precondition),
             "ensures" => Ok(SpecType::Postcondition),
             "invariant" => Ok(SpecType::Invariant),
+            "decreases" => Ok(SpecType::Decreases),
             _ => Err(TryFromStringError::UnknownSpecificationType),
         }
     }
@@ -201,6 +214,10 @@ pub enum AssertionKind<ET, AT> {
     TypeCond(ForAllVars<AT>, Assertion<ET, AT>),
     /// Quantifier (forall vars :: {triggers} filter ==> body)
     ForAll(ForAllVars<AT>, TriggerSet<ET>, Assertion<ET, AT>),
+    /// Quantifier (exists vars :: {triggers} body)
+    Exists(ForAllVars<AT>, TriggerSet<ET>, Assertion<ET, AT>),
+    /// Let binding (let var = value in body). The variables contain exactly one element.
+    Let(ForAllVars<AT>, Expression<ET>, Assertion<ET, AT>),
     /// Pledge after_expiry<reference>(rhs)
     ///     or after_expiry_if<reference>(lhs,rhs)
     Pledge(
@@ -225,12 +242,27 @@ pub struct Specification<ET, AT> {
 #[derive(Debug, Clone)]
 /// Specification of a single element such as procedure or loop.
 pub enum SpecificationSet<ET, AT> {
-    /// (Precondition, Postcondition)
+    /// (Precondition, Postcondition), with the clauses in the order in which they are written.
+    /// Later clauses may assume the earlier ones.
     Procedure(Vec<Specification<ET, AT>>, Vec<Specification<ET, AT>>),
     /// Loop invariant.
     Loop(Vec<Specification<ET, AT>>),
     /// Struct invariant.
     Struct(Vec<Specification<ET, AT>>),
+    /// Assumption in the body of a procedure.
+    Assumption(Vec<Specification<ET, AT>>),
+    /// Assertion in the body of a procedure.
+    Assertion(Vec<Specification<ET, AT>>),
+    /// Refutation in the body of a procedure.
+    Refutation(Vec<Specification<ET, AT>>),
+    /// Fold of a user predicate in the body of a procedure. The assertion of the
+    /// specification is the application of the predicate.
+    Fold(Vec<Specification<ET, AT>>),
+    /// Unfold of a user predicate in the body of a procedure.
+    Unfold(Vec<Specification<ET, AT>>),
+    /// Termination measure of a procedure. The assertion of each specification is an
+    /// integer expression.
+    Termination(Vec<Specification<ET, AT>>),
 }
 
 impl<ET, AT> SpecificationSet<ET, AT> {
@@ -239,6 +271,12 @@ impl<ET, AT> SpecificationSet<ET, AT> {
             SpecificationSet::Procedure(ref pres, ref posts) => pres.is_empty() && posts.is_empty(),
             SpecificationSet::Loop(ref invs) => invs.is_empty(),
             SpecificationSet::Struct(ref invs) => invs.is_empty(),
+            SpecificationSet::Assumption(ref assumptions) => assumptions.is_empty(),
+            SpecificationSet::Assertion(ref assertions) => assertions.is_empty(),
+            SpecificationSet::Refutation(ref refutations) => refutations.is_empty(),
+            SpecificationSet::Fold(ref folds) => folds.is_empty(),
+            SpecificationSet::Unfold(ref unfolds) => unfolds.is_empty(),
+            SpecificationSet::Termination(ref measures) => measures.is_empty(),
         }
     }
 }
@@ -293,10 +331,16 @@ impl TypedAssertion {
                 spans.extend(rhs.get_spans());
                 spans
             }
-            AssertionKind::ForAll(ref _vars, ref _trigger_set, ref body) => {
+            AssertionKind::ForAll(ref _vars, ref _trigger_set, ref body) |
+            AssertionKind::Exists(ref _vars, ref _trigger_set, ref body) => {
                 // FIXME: include the variables
                 body.get_spans()
             }
+            AssertionKind::Let(ref _vars, ref value, ref body) => {
+                let mut spans = vec![value.expr.span.clone()];
+                spans.extend(body.get_spans());
+                spans
+            }
             AssertionKind::Pledge(ref _reference, ref lhs, ref rhs) => {
                 // FIXME: include the reference
                 let mut spans = lhs.get_spans();
@@ -0,0 +1,578 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Specification models of the methods of `Vec`, `HashMap`, `BTreeMap`, `String`, `str`,
+//! `Option`, `Result`, `Cow`, `Mutex`, `RwLock`, `slice::Iter` and `slice::IterMut`, and of
+//! `Clone::clone`.
+//!
+//! The models do not depend on the compiler: they describe the contract of each method in terms
+//! of a few abstract functions (the length of a collection, the variant of an enum, the content
+//! of a map), and Prusti encodes them. A `Vec` or a slice is modelled by its length and, if its
+//! elements are integers or characters, by the sequence of its elements, while a map is modelled
+//! by a mathematical map from its keys to its values, and a string by the sequence of its bytes.
+//! An iterator over a slice is modelled like the slice, with a ghost index that separates the
+//! elements that it has yielded from the ones that it has not. The searches of an iterator, e.g.
+//! `Iterator::any`, are modelled in terms of the closure that they take. The handle of a thread,
+//! `JoinHandle`, is abstract: `std::thread::spawn` and `JoinHandle::join` only transfer the
+//! ownership given by their types.
+
+#![warn(missing_docs)]
+
+/// The model of a method of the standard library.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StdModel {
+    /// A pure method that checks whether its receiver, a reference to an enum, is of the variant
+    /// with the given name. E.g. `Option::is_some`.
+    IsVariant(&'static str),
+    /// A pure method that returns the length of its receiver, a reference to a collection.
+    Len,
+    /// A pure method that checks whether the length of its receiver is zero.
+    IsEmpty,
+    /// A procedure that returns an empty collection. E.g. `Vec::new`.
+    New,
+    /// A method that appends its second argument to its receiver, a mutable reference to a
+    /// collection.
+    Push,
+    /// A method that removes the last element of its receiver, a mutable reference to a
+    /// collection, and returns it in an `Option`.
+    Pop,
+    /// A method that removes all the elements of its receiver, a mutable reference to a
+    /// collection.
+    Clear,
+    /// A method that moves out the content of its receiver, an enum that must be of the variant
+    /// with the given name. E.g. `Option::unwrap`, which panics on `None`.
+    Unwrap(&'static str),
+    /// A method that makes its receiver, a mutable reference to a clone-on-write enum, own its
+    /// content (cloning it if borrowed) and returns a mutable reference to the content. E.g.
+    /// `Cow::to_mut`, after which the `Cow` is of the variant with the given name.
+    ToMut(&'static str),
+    /// A pure method that checks whether its receiver, a reference to a map, contains its second
+    /// argument, a reference to a key.
+    ContainsKey,
+    /// A method that maps its second argument, a key, to its third argument, a value, in its
+    /// receiver, a mutable reference to a map. It returns the previous value, in an `Option`.
+    Insert,
+    /// A method that returns a reference to the value of its second argument, a reference to a
+    /// key, in its receiver, a reference to a map that must contain the key. E.g. `map[&key]`.
+    Index,
+    /// A pure method that checks whether its receiver and its second argument, references to
+    /// strings, have the same content. E.g. `<String as PartialEq<&str>>::eq`.
+    StrEq,
+    /// A method that returns a copy of its receiver, a reference to a shared reference or to a
+    /// struct with a derived `Clone`. The copied shared references point to the same referents,
+    /// while the owned content is fresh. E.g. `Clone::clone`.
+    Clone,
+    /// A procedure that returns a lock that protects its argument. E.g. `Mutex::new`.
+    NewLock,
+    /// A method that acquires its receiver, a reference to a lock, and returns a guard in a
+    /// `Result`. E.g. `Mutex::lock`.
+    Lock,
+    /// A method that returns a reference to the content protected by its receiver, a reference
+    /// to a lock guard. E.g. `<MutexGuard as DerefMut>::deref_mut`.
+    GuardDeref,
+    /// A method that returns a reference to the elements of its receiver, a reference to a
+    /// vector, as a slice. E.g. `<Vec as DerefMut>::deref_mut`.
+    AsSlice,
+    /// A method that swaps the elements of its receiver, a mutable reference to a slice, at its
+    /// second and third arguments, two indices.
+    Swap,
+    /// A method that sorts its receiver, a mutable reference to a slice, in ascending order.
+    Sort,
+    /// A method that searches its second argument, a reference to a value, in its receiver, a
+    /// reference to a sorted slice. It returns `Ok` with the index of the value if it is found,
+    /// and `Err` otherwise.
+    BinarySearch,
+    /// A method that returns an iterator over shared references to the elements of its
+    /// receiver, a reference to a slice. E.g. `<[T]>::iter`.
+    Iter,
+    /// A method that returns an iterator over mutable references to the elements of its
+    /// receiver, a mutable reference to a slice. E.g. `<[T]>::iter_mut`.
+    IterMut,
+    /// A method that returns its receiver, an iterator, as it is. E.g. `IntoIterator::into_iter`
+    /// of an iterator, which is called by each `for` loop.
+    IntoIter,
+    /// A method that returns the next element of its receiver, a mutable reference to an
+    /// iterator, in an `Option`, and advances the iterator. E.g. `<IterMut as Iterator>::next`.
+    Next,
+    /// A method that checks whether one of the remaining elements of its receiver, a mutable
+    /// reference to an iterator, satisfies its second argument, a closure. E.g. `Iterator::any`.
+    Any,
+    /// A method that checks whether all the remaining elements of its receiver, a mutable
+    /// reference to an iterator, satisfy its second argument, a closure. E.g. `Iterator::all`.
+    All,
+    /// A method that returns the position, among the remaining elements of its receiver, a
+    /// mutable reference to an iterator, of the first element that satisfies its second
+    /// argument, a closure, in an `Option`. E.g. `Iterator::position`.
+    Position,
+    /// A method that returns the first of the remaining elements of its receiver, a mutable
+    /// reference to an iterator, that satisfies its second argument, a closure, in an `Option`.
+    /// E.g. `Iterator::find`.
+    Find,
+}
+
+impl StdModel {
+    /// Is the method encoded as a pure function?
+    pub fn is_pure(&self) -> bool {
+        match self {
+            StdModel::IsVariant(_)
+            | StdModel::Len
+            | StdModel::IsEmpty
+            | StdModel::ContainsKey
+            | StdModel::StrEq => true,
+            _ => false,
+        }
+    }
+
+    /// A short name of the model, unique among the pure models.
+    pub fn name(&self) -> String {
+        match self {
+            StdModel::IsVariant(variant) => format!("is_variant_{}", variant),
+            StdModel::Len => "len".to_string(),
+            StdModel::IsEmpty => "is_empty".to_string(),
+            StdModel::New => "new".to_string(),
+            StdModel::Push => "push".to_string(),
+            StdModel::Pop => "pop".to_string(),
+            StdModel::Clear => "clear".to_string(),
+            StdModel::Unwrap(variant) => format!("unwrap_{}", variant),
+            StdModel::ToMut(variant) => format!("to_mut_{}", variant),
+            StdModel::ContainsKey => "contains_key".to_string(),
+            StdModel::Insert => "insert".to_string(),
+            StdModel::Index => "index".to_string(),
+            StdModel::StrEq => "str_eq".to_string(),
+            StdModel::Clone => "clone".to_string(),
+            StdModel::NewLock => "new_lock".to_string(),
+            StdModel::Lock => "lock".to_string(),
+            StdModel::GuardDeref => "guard_deref".to_string(),
+            StdModel::AsSlice => "as_slice".to_string(),
+            StdModel::Swap => "swap".to_string(),
+            StdModel::Sort => "sort".to_string(),
+            StdModel::BinarySearch => "binary_search".to_string(),
+            StdModel::Iter => "iter".to_string(),
+            StdModel::IterMut => "iter_mut".to_string(),
+            StdModel::IntoIter => "into_iter".to_string(),
+            StdModel::Next => "next".to_string(),
+            StdModel::Any => "any".to_string(),
+            StdModel::All => "all".to_string(),
+            StdModel::Position => "position".to_string(),
+            StdModel::Find => "find".to_string(),
+        }
+    }
+}
+
+/// The modelled methods, with their paths as printed by `absolute_item_path_str`, without the
+/// generic arguments (see `strip_generic_args`). The types are printed with the path of the crate
+/// through which they are used, so each type has a `core` or `alloc` path, for `no_std` crates,
+/// and a `std` path.
+const MODELS: &[(&str, StdModel)] = &[
+    ("<core::option::Option>::is_some", StdModel::IsVariant("Some")),
+    ("<core::option::Option>::is_none", StdModel::IsVariant("None")),
+    ("<core::option::Option>::unwrap", StdModel::Unwrap("Some")),
+    ("<core::option::Option>::expect", StdModel::Unwrap("Some")),
+    ("<core::result::Result>::is_ok", StdModel::IsVariant("Ok")),
+    ("<core::result::Result>::is_err", StdModel::IsVariant("Err")),
+    ("<core::result::Result>::unwrap", StdModel::Unwrap("Ok")),
+    ("<core::result::Result>::expect", StdModel::Unwrap("Ok")),
+    ("<std::option::Option>::is_some", StdModel::IsVariant("Some")),
+    ("<std::option::Option>::is_none", StdModel::IsVariant("None")),
+    ("<std::option::Option>::unwrap", StdModel::Unwrap("Some")),
+    ("<std::option::Option>::expect", StdModel::Unwrap("Some")),
+    ("<std::result::Result>::is_ok", StdModel::IsVariant("Ok")),
+    ("<std::result::Result>::is_err", StdModel::IsVariant("Err")),
+    ("<std::result::Result>::unwrap", StdModel::Unwrap("Ok")),
+    ("<std::result::Result>::expect", StdModel::Unwrap("Ok")),
+    ("<alloc::borrow::Cow>::to_mut", StdModel::ToMut("Owned")),
+    ("<std::borrow::Cow>::to_mut", StdModel::ToMut("Owned")),
+    ("<alloc::vec::Vec>::new", StdModel::New),
+    ("<alloc::vec::Vec>::with_capacity", StdModel::New),
+    ("<alloc::vec::Vec>::len", StdModel::Len),
+    ("<alloc::vec::Vec>::is_empty", StdModel::IsEmpty),
+    ("<alloc::vec::Vec>::push", StdModel::Push),
+    ("<alloc::vec::Vec>::pop", StdModel::Pop),
+    ("<alloc::vec::Vec>::clear", StdModel::Clear),
+    ("<std::vec::Vec>::new", StdModel::New),
+    ("<std::vec::Vec>::with_capacity", StdModel::New),
+    ("<std::vec::Vec>::len", StdModel::Len),
+    ("<std::vec::Vec>::is_empty", StdModel::IsEmpty),
+    ("<std::vec::Vec>::push", StdModel::Push),
+    ("<std::vec::Vec>::pop", StdModel::Pop),
+    ("<std::vec::Vec>::clear", StdModel::Clear),
+    ("<[T]>::len", StdModel::Len),
+    ("<[T]>::is_empty", StdModel::IsEmpty),
+    ("<[T]>::swap", StdModel::Swap),
+    ("<[T]>::sort", StdModel::Sort),
+    ("<[T]>::sort_unstable", StdModel::Sort),
+    ("<[T]>::binary_search", StdModel::BinarySearch),
+    ("<[T]>::iter", StdModel::Iter),
+    ("<[T]>::iter_mut", StdModel::IterMut),
+    ("<I as core::iter::IntoIterator>::into_iter", StdModel::IntoIter),
+    ("<I as std::iter::IntoIterator>::into_iter", StdModel::IntoIter),
+    ("core::iter::Iterator::any", StdModel::Any),
+    ("core::iter::Iterator::all", StdModel::All),
+    ("core::iter::Iterator::position", StdModel::Position),
+    ("core::iter::Iterator::find", StdModel::Find),
+    ("std::iter::Iterator::any", StdModel::Any),
+    ("std::iter::Iterator::all", StdModel::All),
+    ("std::iter::Iterator::position", StdModel::Position),
+    ("std::iter::Iterator::find", StdModel::Find),
+    ("<str>::len", StdModel::Len),
+    ("<str>::is_empty", StdModel::IsEmpty),
+    ("<str as core::cmp::PartialEq>::eq", StdModel::StrEq),
+    ("<str as std::cmp::PartialEq>::eq", StdModel::StrEq),
+    ("<alloc::string::String>::len", StdModel::Len),
+    ("<alloc::string::String>::is_empty", StdModel::IsEmpty),
+    ("<alloc::string::String as core::cmp::PartialEq>::eq", StdModel::StrEq),
+    ("<std::string::String>::len", StdModel::Len),
+    ("<std::string::String>::is_empty", StdModel::IsEmpty),
+    ("<std::string::String as std::cmp::PartialEq>::eq", StdModel::StrEq),
+    ("core::clone::Clone::clone", StdModel::Clone),
+    ("std::clone::Clone::clone", StdModel::Clone),
+    ("<std::sync::Mutex>::new", StdModel::NewLock),
+    ("<std::sync::Mutex>::lock", StdModel::Lock),
+    ("<std::sync::mutex::Mutex>::new", StdModel::NewLock),
+    ("<std::sync::mutex::Mutex>::lock", StdModel::Lock),
+    ("<std::sync::RwLock>::new", StdModel::NewLock),
+    ("<std::sync::RwLock>::read", StdModel::Lock),
+    ("<std::sync::RwLock>::write", StdModel::Lock),
+    ("<std::sync::rwlock::RwLock>::new", StdModel::NewLock),
+    ("<std::sync::rwlock::RwLock>::read", StdModel::Lock),
+    ("<std::sync::rwlock::RwLock>::write", StdModel::Lock),
+];
+
+/// The modelled methods of the maps, as `(method, model)`. Each method is modelled for each path
+/// of `MAP_TYPES`.
+const MAP_METHODS: &[(&str, StdModel)] = &[
+    ("new", StdModel::New),
+    ("with_capacity", StdModel::New),
+    ("len", StdModel::Len),
+    ("is_empty", StdModel::IsEmpty),
+    ("contains_key", StdModel::ContainsKey),
+    ("insert", StdModel::Insert),
+];
+
+/// The paths of the traits whose methods are modelled for the maps, as `(trait, method, model)`.
+const MAP_TRAIT_METHODS: &[(&str, &str, StdModel)] = &[
+    ("core::ops::Index", "index", StdModel::Index),
+    ("std::ops::Index", "index", StdModel::Index),
+];
+
+/// The modelled maps.
+const MAP_TYPES: &[&str] = &[
+    "std::collections::HashMap",
+    "std::collections::hash_map::HashMap",
+    "std::collections::hash::map::HashMap",
+    "alloc::btree::map::BTreeMap",
+    "alloc::collections::btree::map::BTreeMap",
+    "std::collections::BTreeMap",
+    "std::collections::btree_map::BTreeMap",
+];
+
+/// The paths of the traits whose methods are modelled for the vectors, as `(trait, method, model)`.
+/// Each method is modelled for each path of `MODELLED_TYPES`.
+const VEC_TRAIT_METHODS: &[(&str, &str, StdModel)] = &[
+    ("core::ops::Deref", "deref", StdModel::AsSlice),
+    ("core::ops::DerefMut", "deref_mut", StdModel::AsSlice),
+    ("std::ops::Deref", "deref", StdModel::AsSlice),
+    ("std::ops::DerefMut", "deref_mut", StdModel::AsSlice),
+];
+
+/// The paths of the traits whose methods are modelled for the lock guards, as
+/// `(trait, method, model)`. Each method is modelled for each path of `GUARD_TYPES`.
+const GUARD_TRAIT_METHODS: &[(&str, &str, StdModel)] = &[
+    ("core::ops::Deref", "deref", StdModel::GuardDeref),
+    ("core::ops::DerefMut", "deref_mut", StdModel::GuardDeref),
+    ("std::ops::Deref", "deref", StdModel::GuardDeref),
+    ("std::ops::DerefMut", "deref_mut", StdModel::GuardDeref),
+];
+
+/// The paths of the traits whose methods are modelled for the iterators, as
+/// `(trait, method, model)`. Each method is modelled for each path of `ITERATOR_TYPES`. The
+/// searches are also modelled for the default methods of `Iterator`, in `MODELS`, since an
+/// iterator might not override them.
+const ITERATOR_TRAIT_METHODS: &[(&str, &str, StdModel)] = &[
+    ("core::iter::Iterator", "next", StdModel::Next),
+    ("core::iter::Iterator", "any", StdModel::Any),
+    ("core::iter::Iterator", "all", StdModel::All),
+    ("core::iter::Iterator", "position", StdModel::Position),
+    ("core::iter::Iterator", "find", StdModel::Find),
+    ("std::iter::Iterator", "next", StdModel::Next),
+    ("std::iter::Iterator", "any", StdModel::Any),
+    ("std::iter::Iterator", "all", StdModel::All),
+    ("std::iter::Iterator", "position", StdModel::Position),
+    ("std::iter::Iterator", "find", StdModel::Find),
+];
+
+/// The modelled locks, whose content is only known through the lock invariant.
+const LOCK_TYPES: &[&str] = &[
+    "std::sync::Mutex",
+    "std::sync::mutex::Mutex",
+    "std::sync::RwLock",
+    "std::sync::rwlock::RwLock",
+];
+
+/// The modelled guards of the locks.
+const GUARD_TYPES: &[&str] = &[
+    "std::sync::MutexGuard",
+    "std::sync::mutex::MutexGuard",
+    "std::sync::RwLockReadGuard",
+    "std::sync::rwlock::RwLockReadGuard",
+    "std::sync::RwLockWriteGuard",
+    "std::sync::rwlock::RwLockWriteGuard",
+];
+
+/// The modelled iterators over the elements of a slice.
+const ITERATOR_TYPES: &[&str] = &[
+    "core::slice::Iter",
+    "core::slice::IterMut",
+    "std::slice::Iter",
+    "std::slice::IterMut",
+];
+
+/// The modelled handles of the spawned threads. A handle owns the result of its thread, which is
+/// given back by `JoinHandle::join`.
+const THREAD_TYPES: &[&str] = &["std::thread::JoinHandle"];
+
+/// The modelled collections, whose fields are not encoded.
+const MODELLED_TYPES: &[&str] = &["alloc::vec::Vec", "std::vec::Vec"];
+
+/// The modelled owned strings. Like `str`, they are abstracted as the sequence of their bytes.
+const STRING_TYPES: &[&str] = &["alloc::string::String", "std::string::String"];
+
+/// The variants that only borrow the referents of their fields, with the paths of their types.
+/// The referents are accessed with a read permission, like through a shared reference.
+const BORROWING_VARIANTS: &[(&str, &str)] = &[
+    ("alloc::borrow::Cow", "Borrowed"),
+    ("std::borrow::Cow", "Borrowed"),
+];
+
+/// The model of the method with the given path, if any.
+pub fn find_model(path: &str) -> Option<StdModel> {
+    let path = strip_generic_args(path);
+    MODELS
+        .iter()
+        .find(|(model_path, _)| *model_path == path)
+        .map(|&(_, model)| model)
+        .or_else(|| find_map_model(&path))
+        .or_else(|| find_vec_model(&path))
+        .or_else(|| find_guard_model(&path))
+        .or_else(|| find_iterator_model(&path))
+}
+
+/// The model of a method of a map, with the given path without generic arguments.
+fn find_map_model(path: &str) -> Option<StdModel> {
+    MAP_TYPES
+        .iter()
+        .filter_map(|map_type| {
+            let inherent = MAP_METHODS
+                .iter()
+                .find(|(method, _)| path == format!("<{}>::{}", map_type, method));
+            let from_trait = MAP_TRAIT_METHODS.iter().find(|(trait_path, method, _)| {
+                path == format!("<{} as {}>::{}", map_type, trait_path, method)
+            });
+            inherent
+                .map(|&(_, model)| model)
+                .or_else(|| from_trait.map(|&(_, _, model)| model))
+        })
+        .next()
+}
+
+/// The model of a trait method of a vector, with the given path without generic arguments.
+fn find_vec_model(path: &str) -> Option<StdModel> {
+    find_trait_method_model(MODELLED_TYPES, VEC_TRAIT_METHODS, path)
+}
+
+/// The model of a method of a lock guard, with the given path without generic arguments.
+fn find_guard_model(path: &str) -> Option<StdModel> {
+    find_trait_method_model(GUARD_TYPES, GUARD_TRAIT_METHODS, path)
+}
+
+/// The model of a method of an iterator, with the given path without generic arguments.
+fn find_iterator_model(path: &str) -> Option<StdModel> {
+    find_trait_method_model(ITERATOR_TYPES, ITERATOR_TRAIT_METHODS, path)
+}
+
+/// The model of the method with the given path without generic arguments, if it is one of the
+/// `(trait, method, model)` of `trait_methods` implemented for one of `types`.
+fn find_trait_method_model(
+    types: &[&str],
+    trait_methods: &[(&str, &str, StdModel)],
+    path: &str,
+) -> Option<StdModel> {
+    types
+        .iter()
+        .filter_map(|type_path| {
+            trait_methods
+                .iter()
+                .find(|(trait_path, method, _)| {
+                    path == format!("<{} as {}>::{}", type_path, trait_path, method)
+                })
+                .map(|&(_, _, model)| model)
+        })
+        .next()
+}
+
+/// Remove the generic arguments of the types in a path, which depend on how the `impl` of the
+/// method is written. E.g. `<std::vec::Vec<T>>::len` becomes `<std::vec::Vec>::len`.
+fn strip_generic_args(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut depth = 0;
+    let mut previous = None;
+    for c in path.chars() {
+        let after_name = previous.map_or(false, |p: char| p.is_alphanumeric() || p == '_');
+        match c {
+            '<' if depth > 0 || after_name => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            _ => stripped.push(c),
+        }
+        previous = Some(c);
+    }
+    stripped
+}
+
+/// Is the type with the given path a modelled collection, lock, iterator or thread handle? Its
+/// fields are not encoded, thus they do not need to be supported.
+pub fn is_modelled_type(path: &str) -> bool {
+    MODELLED_TYPES.contains(&path)
+        || is_map_type(path)
+        || is_string_type(path)
+        || LOCK_TYPES.contains(&path)
+        || GUARD_TYPES.contains(&path)
+        || is_iterator_type(path)
+        || THREAD_TYPES.contains(&path)
+}
+
+/// Is the type with the given path a modelled vector? It is abstracted by its length and, if its
+/// elements are integers or characters, by the sequence of its elements.
+pub fn is_vec_type(path: &str) -> bool {
+    MODELLED_TYPES.contains(&path)
+}
+
+/// Is the type with the given path a modelled iterator over the elements of a slice? It is
+/// abstracted like the slice, and by the index of the next element that it yields.
+pub fn is_iterator_type(path: &str) -> bool {
+    ITERATOR_TYPES.contains(&path)
+}
+
+/// Is the type with the given path a modelled map? It is abstracted as a mathematical map.
+pub fn is_map_type(path: &str) -> bool {
+    MAP_TYPES.contains(&path)
+}
+
+/// Is the type with the given path a modelled owned string? It is abstracted as the sequence of
+/// its bytes.
+pub fn is_string_type(path: &str) -> bool {
+    STRING_TYPES.contains(&path)
+}
+
+/// Does the variant with the given name, of the type with the given path, only borrow the
+/// referents of its fields? E.g. `Cow::Borrowed`, while `Cow::Owned` owns its content.
+pub fn is_borrowing_variant(type_path: &str, variant_name: &str) -> bool {
+    BORROWING_VARIANTS.contains(&(type_path, variant_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_generic_args() {
+        assert_eq!(strip_generic_args("<std::vec::Vec<T>>::len"), "<std::vec::Vec>::len");
+        assert_eq!(
+            strip_generic_args("<alloc::borrow::Cow<'a, B>>::to_mut"),
+            "<alloc::borrow::Cow>::to_mut"
+        );
+        assert_eq!(
+            strip_generic_args("<std::collections::HashMap<K, V, S> as core::ops::Index<&'a Q>>::index"),
+            "<std::collections::HashMap as core::ops::Index>::index"
+        );
+    }
+
+    #[test]
+    fn test_find_map_model() {
+        assert_eq!(
+            find_model("<std::collections::hash::map::HashMap<K, V, S>>::insert"),
+            Some(StdModel::Insert)
+        );
+        assert_eq!(
+            find_model("<std::collections::HashMap<K, V, S> as std::ops::Index<&'a Q>>::index"),
+            Some(StdModel::Index)
+        );
+        assert_eq!(find_model("<std::collections::HashMap<K, V, S>>::remove"), None);
+    }
+
+    #[test]
+    fn test_find_string_model() {
+        assert_eq!(find_model("<str>::len"), Some(StdModel::Len));
+        assert_eq!(
+            find_model("<alloc::string::String as core::cmp::PartialEq<&'a str>>::eq"),
+            Some(StdModel::StrEq)
+        );
+        assert_eq!(find_model("<str as core::cmp::PartialEq>::ne"), None);
+    }
+
+    #[test]
+    fn test_find_slice_model() {
+        assert_eq!(find_model("<[T]>::swap"), Some(StdModel::Swap));
+        assert_eq!(find_model("<[T]>::sort_unstable"), Some(StdModel::Sort));
+        assert_eq!(
+            find_model("<alloc::vec::Vec<T> as core::ops::DerefMut>::deref_mut"),
+            Some(StdModel::AsSlice)
+        );
+        assert_eq!(find_model("<[T]>::sort_by"), None);
+        assert!(is_vec_type("std::vec::Vec"));
+    }
+
+    #[test]
+    fn test_find_clone_model() {
+        assert_eq!(find_model("core::clone::Clone::clone"), Some(StdModel::Clone));
+        assert_eq!(find_model("core::clone::Clone::clone_from"), None);
+    }
+
+    #[test]
+    fn test_find_lock_model() {
+        assert_eq!(find_model("<std::sync::Mutex<T>>::lock"), Some(StdModel::Lock));
+        assert_eq!(
+            find_model("<std::sync::MutexGuard<'mutex, T> as core::ops::DerefMut>::deref_mut"),
+            Some(StdModel::GuardDeref)
+        );
+        assert_eq!(find_model("<std::sync::Mutex<T>>::try_lock"), None);
+        assert!(is_modelled_type("std::sync::rwlock::RwLockReadGuard"));
+    }
+
+    #[test]
+    fn test_thread_types() {
+        assert!(is_modelled_type("std::thread::JoinHandle"));
+        assert_eq!(find_model("std::thread::spawn"), None);
+        assert_eq!(find_model("<std::thread::JoinHandle<T>>::join"), None);
+    }
+
+    #[test]
+    fn test_find_iterator_model() {
+        assert_eq!(find_model("<[T]>::iter_mut"), Some(StdModel::IterMut));
+        assert_eq!(
+            find_model("<I as core::iter::IntoIterator>::into_iter"),
+            Some(StdModel::IntoIter)
+        );
+        assert_eq!(
+            find_model("<core::slice::IterMut<'a, T> as core::iter::Iterator>::next"),
+            Some(StdModel::Next)
+        );
+        assert_eq!(find_model("<core::slice::Chunks<'a, T> as core::iter::Iterator>::next"), None);
+        assert!(is_modelled_type("std::slice::IterMut"));
+    }
+
+    #[test]
+    fn test_find_search_model() {
+        assert_eq!(find_model("<[T]>::iter"), Some(StdModel::Iter));
+        assert_eq!(find_model("core::iter::Iterator::any"), Some(StdModel::Any));
+        assert_eq!(
+            find_model("<core::slice::Iter<'a, T> as core::iter::Iterator>::position"),
+            Some(StdModel::Position)
+        );
+        assert_eq!(find_model("core::iter::Iterator::find_map"), None);
+        assert!(is_iterator_type("core::slice::Iter"));
+    }
+}
@@ -0,0 +1,259 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Translation of the names of the encoded items back to Rust syntax.
+//!
+//! The Viper identifiers of the encoded items are derived from their Rust paths and types, e.g.
+//! `m_generics$$Number$_beg_$ref$u32$_end_` is the predicate of `generics::Number<&u32>`. The
+//! names have to stay structured, because the type substitutions of generic items are computed
+//! from them, so they are translated back only when they are shown to users.
+
+use regex::{Captures, Regex};
+
+/// The characters that are escaped in the paths of the encoded items.
+const ESCAPES: &[(&str, &str)] = &[
+    ("$openang$", "<"),
+    ("$closeang$", ">"),
+    ("$openrou$", "("),
+    ("$closerou$", ")"),
+    ("$opensqu$", "["),
+    ("$closesqu$", "]"),
+    ("$opencur$", "{"),
+    ("$closecur$", "}"),
+    ("$comma$", ","),
+    ("$semic$", ";"),
+    ("$space$", " "),
+];
+
+/// Translate the name of an encoded item, or of its type invariant, tag function, domain or
+/// snapshots, to Rust syntax. Names that are not generated from Rust items, like the names of
+/// the local variables and of the fields, are returned unchanged.
+pub fn demangle(name: &str) -> String {
+    if let Some(demangled) = demangle_type(name) {
+        return demangled;
+    }
+    for &(suffix, description) in &[
+        ("$inv", "invariant"),
+        ("$tag", "tag"),
+        ("$domain", "domain"),
+        ("$Snap", "snapshot domain"),
+        ("$snap", "snapshot"),
+    ] {
+        if name.ends_with(suffix) {
+            if let Some(demangled) = demangle_type(&name[..name.len() - suffix.len()]) {
+                return format!("{} of {}", description, demangled);
+            }
+        }
+    }
+    name.to_string()
+}
+
+/// Translate to Rust syntax each name of an encoded item that occurs in `text`, e.g. in a
+/// message reported by Viper.
+pub fn demangle_text(text: &str) -> String {
+    lazy_static! {
+        static ref IDENTIFIER_RE: Regex = Regex::new("[A-Za-z0-9_$]*\\$[A-Za-z0-9_$]*").unwrap();
+    }
+    IDENTIFIER_RE
+        .replace_all(text, |captures: &Captures| demangle(&captures[0]))
+        .into_owned()
+}
+
+fn demangle_type(name: &str) -> Option<String> {
+    if !name.contains('$') {
+        return None;
+    }
+    let mut unescaped = name.to_string();
+    for &(escape, character) in ESCAPES {
+        unescaped = unescaped.replace(escape, character);
+    }
+    let tokens: Vec<&str> = unescaped.split('$').collect();
+    let mut parser = Parser {
+        tokens: &tokens,
+        index: 0,
+    };
+    let demangled = parser.parse_type()?;
+    if parser.index == tokens.len() {
+        Some(demangled)
+    } else {
+        None
+    }
+}
+
+/// A parser of the `$`-separated tokens of a mangled name.
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.index).cloned()
+    }
+
+    fn parse_type(&mut self) -> Option<String> {
+        let token = self.next()?;
+        match token {
+            "ref" => Some(format!("&{}", self.parse_type()?)),
+            "slice" => Some(format!("[{}]", self.parse_type()?)),
+            "array" => {
+                let elem = self.parse_type()?;
+                let size = self.next()?;
+                Some(format!("[{}; {}]", elem, size))
+            }
+            "__TYPARAM__" => {
+                let mut param = self.next()?.to_string();
+                if param == "impl" {
+                    param = format!("impl#{}", self.next()?);
+                }
+                if self.next()? != "__" {
+                    return None;
+                }
+                Some(param)
+            }
+            "__TYDYN__" => match self.peek() {
+                Some(token) if token.starts_with("m_") => {
+                    self.next();
+                    Some(format!("dyn {}", self.parse_item(&token[2..])?))
+                }
+                _ => Some("dyn".to_string()),
+            },
+            "closure" => {
+                let def_id = self.next()?;
+                self.next()?;
+                self.next()?;
+                Some(format!("closure#{}", def_id))
+            }
+            _ if token.starts_with("tuple") && token[5..].parse::<usize>().is_ok() => {
+                let len: usize = token[5..].parse().unwrap();
+                if len == 0 {
+                    // `tuple0$` is followed by an empty token.
+                    return match self.next() {
+                        Some("") | None => Some("()".to_string()),
+                        Some(_) => None,
+                    };
+                }
+                let mut elems = vec![];
+                for _ in 0..len {
+                    elems.push(self.parse_type()?);
+                }
+                if len == 1 {
+                    Some(format!("({},)", elems[0]))
+                } else {
+                    Some(format!("({})", elems.join(", ")))
+                }
+            }
+            _ if token.starts_with("__TYPROJ__m_") => {
+                Some(format!("<{}>", self.parse_item(&token[12..])?))
+            }
+            _ if token.starts_with("__TYANON__m_") => {
+                Some(format!("impl {}", self.parse_item(&token[12..])?))
+            }
+            _ if token.starts_with("m_") => self.parse_item(&token[2..]),
+            _ if token.is_empty() || is_keyword(token) => None,
+            _ => Some(token.to_string()),
+        }
+    }
+
+    /// Parse the rest of the path of an item, whose first segment is `first_segment`, and its
+    /// type arguments, if any. The default disambiguators of the segments, `[0]`, are omitted.
+    fn parse_item(&mut self, first_segment: &str) -> Option<String> {
+        let mut path = first_segment.trim_right_matches("[0]").to_string();
+        // The segments of the path are separated by `$$`, that is by an empty token.
+        while self.peek() == Some("") {
+            match self.tokens.get(self.index + 1) {
+                Some(&segment) if !segment.is_empty() && !is_keyword(segment) => {
+                    path.push_str("::");
+                    path.push_str(segment.trim_right_matches("[0]"));
+                    self.index += 2;
+                }
+                _ => break,
+            }
+        }
+        if self.peek() != Some("_beg_") {
+            return Some(path);
+        }
+        self.next();
+        let mut args = vec![];
+        loop {
+            match self.peek()? {
+                "_end_" => {
+                    self.next();
+                    break;
+                }
+                // The separators of the lifetime arguments, and the empty lists of arguments.
+                "_sep_" | "" => {
+                    self.next();
+                }
+                _ => args.push(self.parse_type()?),
+            }
+        }
+        if args.is_empty() {
+            Some(path)
+        } else {
+            Some(format!("{}<{}>", path, args.join(", ")))
+        }
+    }
+}
+
+/// Is the token one of the delimiters of the type arguments?
+fn is_keyword(token: &str) -> bool {
+    token == "_beg_" || token == "_sep_" || token == "_end_"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle_items() {
+        assert_eq!(demangle("m_foo$$bar$opensqu$0$closesqu$"), "foo::bar");
+        assert_eq!(
+            demangle(
+                "m_foo$$$opencur$$opencur$impl$closecur$$closecur$$opensqu$1$closesqu$$$\
+                 new$opensqu$0$closesqu$"
+            ),
+            "foo::{{impl}}[1]::new"
+        );
+        assert_eq!(demangle("m_foo$$Number$_beg_$_end_"), "foo::Number");
+    }
+
+    #[test]
+    fn test_demangle_types() {
+        assert_eq!(
+            demangle("m_foo$$Pair$_beg_$ref$u32$_sep_$__TYPARAM__$T$__$_end_"),
+            "foo::Pair<&u32, T>"
+        );
+        assert_eq!(demangle("m_foo$$Ref$_beg_$_sep_$i32$_end_"), "foo::Ref<i32>");
+        assert_eq!(demangle("tuple2$i32$tuple0$"), "(i32, ())");
+        assert_eq!(demangle("array$slice$u8$4"), "[[u8]; 4]");
+        assert_eq!(demangle("m_foo$$List$_beg_$_end_$inv"), "invariant of foo::List");
+        assert_eq!(demangle("m_foo$$Set$_beg_$_end_$domain"), "domain of foo::Set");
+        assert_eq!(demangle("m_foo$$Point$_beg_$_end_$snap"), "snapshot of foo::Point");
+    }
+
+    #[test]
+    fn test_demangle_other_names() {
+        assert_eq!(demangle("f$value"), "f$value");
+        assert_eq!(demangle("_1"), "_1");
+        assert_eq!(demangle("m_foo$$inv"), "foo::inv");
+        assert_eq!(demangle("i32"), "i32");
+    }
+
+    #[test]
+    fn test_demangle_text() {
+        assert_eq!(
+            demangle_text("Precondition of m_foo$$bar might not hold, acc(ref$u32(_2))."),
+            "Precondition of foo::bar might not hold, acc(&u32(_2))."
+        );
+    }
+}
@@ -10,6 +10,7 @@ use prusti_interface::specifications::{
     AssertionKind, SpecificationSet, TypedAssertion, TypedExpression, TypedSpecification,
     TypedSpecificationSet,
 };
+use prusti_std_models::StdModel;
 use rustc::hir::{self, Mutability};
 use rustc::mir;
 use rustc::ty::{self, Ty, TyCtxt};
@@ -60,6 +61,30 @@ impl<P: fmt::Debug> fmt::Display for BorrowInfo<P> {
     }
 }
 
+/// The shape of an accessor, i.e. a procedure that returns a mutable reference
+/// to a field of a mutable reference argument, such as `&mut self.f.g`. It is
+/// used to infer the pledge of the accessor.
+#[derive(Clone, Debug)]
+pub struct AccessorInfo<P>
+where
+    P: fmt::Debug,
+{
+    /// The accessed place, e.g. `(*_1).f.g`.
+    pub accessed_place: P,
+    /// The places of primitive type that cannot be modified via the returned
+    /// reference, e.g. the other fields of `*_1` and of `(*_1).f`.
+    pub framed_places: Vec<P>,
+}
+
+impl<P: fmt::Debug> AccessorInfo<P> {
+    fn map<Q: fmt::Debug, F: Fn(&P) -> Q>(&self, f: F) -> AccessorInfo<Q> {
+        AccessorInfo {
+            accessed_place: f(&self.accessed_place),
+            framed_places: self.framed_places.iter().map(f).collect(),
+        }
+    }
+}
+
 /// Contract of a specific procedure. It is a separate struct from a
 /// general procedure info because we want to be able to translate
 /// procedure calls before translating call targets.
@@ -89,6 +114,13 @@ where
     pub borrow_infos: Vec<BorrowInfo<P>>,
     /// The functional specification: precondition and postcondition
     pub specification: TypedSpecificationSet,
+    /// Set if the procedure is an accessor whose pledge should be inferred.
+    pub accessor: Option<AccessorInfo<P>>,
+    /// Set if the procedure is a method of the standard library with a specification model.
+    pub std_model: Option<StdModel>,
+    /// Set if the procedure is the operator of an arithmetic trait (e.g. `Add`) of a newtype
+    /// marked with `#[arithmetic_newtype]`, whose contract relates the inner values.
+    pub arithmetic_newtype: Option<mir::BinOp>,
 }
 
 impl<L: fmt::Debug, P: fmt::Debug> ProcedureContractGeneric<L, P> {
@@ -118,7 +150,9 @@ impl<L: fmt::Debug, P: fmt::Debug> ProcedureContractGeneric<L, P> {
                 AssertionKind::Expr(_)
                 | AssertionKind::Implies(_, _)
                 | AssertionKind::TypeCond(_, _)
-                | AssertionKind::ForAll(_, _, _) => {}
+                | AssertionKind::ForAll(_, _, _)
+                | AssertionKind::Exists(_, _, _)
+                | AssertionKind::Let(_, _, _) => {}
                 AssertionKind::And(ref assertions) => {
                     for assertion in assertions {
                         check_assertion(assertion, pledges);
@@ -199,6 +233,9 @@ impl<'tcx> ProcedureContractMirDef<'tcx> {
             returned_value: self.returned_value.into(),
             borrow_infos,
             specification: self.specification.clone(),
+            accessor: self.accessor.as_ref().map(|info| info.map(|p| p.into())),
+            std_model: self.std_model,
+            arithmetic_newtype: self.arithmetic_newtype,
         }
     }
 
@@ -214,13 +251,15 @@ impl<'tcx> ProcedureContractMirDef<'tcx> {
         for (from, to) in self.args.iter().zip(args) {
             substitutions.insert(*from, *to);
         }
-        let substitute = |(place, mutability): &(_, Mutability)| {
+        let substitute_place = |place: &mir::Place<'tcx>| {
             let root = &get_place_root(place);
-            let substitute_place = places::Place::SubstitutedPlace {
+            places::Place::SubstitutedPlace {
                 substituted_root: *substitutions.get(root).unwrap(),
                 place: place.clone(),
-            };
-            (substitute_place, *mutability)
+            }
+        };
+        let substitute = |(place, mutability): &(_, Mutability)| {
+            (substitute_place(place), *mutability)
         };
         let borrow_infos = self
             .borrow_infos
@@ -238,6 +277,9 @@ impl<'tcx> ProcedureContractMirDef<'tcx> {
             returned_value: target,
             borrow_infos,
             specification: self.specification.clone(),
+            accessor: self.accessor.as_ref().map(|info| info.map(&substitute_place)),
+            std_model: self.std_model,
+            arithmetic_newtype: self.arithmetic_newtype,
         };
         result
     }
@@ -436,8 +478,189 @@ where
         returned_value: mir::RETURN_PLACE,
         borrow_infos,
         specification,
+        accessor: None,
+        std_model: None,
+        arithmetic_newtype: None,
     };
 
     trace!("[compute_borrow_infos] exit result={}", contract);
     contract
 }
+
+/// The definition of a local of an accessor.
+enum Definition<'m, 'tcx: 'm> {
+    Assign(&'m mir::Rvalue<'tcx>),
+    /// The local is the destination of a call of the given function with the given arguments.
+    Call(ProcedureDefId, &'m [mir::Operand<'tcx>]),
+}
+
+/// If `mir` is the body of an accessor, i.e. it returns `&mut (*arg).f.g` (possibly
+/// via reborrows of temporaries), compute the accessed place and the places of
+/// primitive type that are not reachable from the returned reference.
+///
+/// The returned reference can also be obtained by calling another accessor on a
+/// field of the argument, such as `self.f.g_mut()`. The accessor information of
+/// the callees is given by `callee_accessor`.
+pub fn compute_accessor_info<'a, 'tcx>(
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    mir: &mir::Mir<'tcx>,
+    callee_accessor: &mut FnMut(ProcedureDefId) -> Option<AccessorInfo<mir::Place<'tcx>>>,
+) -> Option<AccessorInfo<mir::Place<'tcx>>> {
+    // The definition of each local, if it is assigned exactly once.
+    let mut definitions: HashMap<mir::Local, Option<Definition>> = HashMap::new();
+    {
+        let mut define = |local, definition| {
+            let is_redefined = definitions.contains_key(&local);
+            definitions.insert(local, if is_redefined { None } else { Some(definition) });
+        };
+        for bb_data in mir.basic_blocks() {
+            for stmt in &bb_data.statements {
+                if let mir::StatementKind::Assign(mir::Place::Local(local), ref rvalue) = stmt.kind
+                {
+                    define(local, Definition::Assign(rvalue));
+                }
+            }
+            if let mir::TerminatorKind::Call {
+                func: mir::Operand::Constant(box mir::Constant { ty: func_ty, .. }),
+                ref args,
+                destination: Some((mir::Place::Local(local), _)),
+                ..
+            } = bb_data.terminator().kind
+            {
+                if let ty::TypeVariants::TyFnDef(callee_def_id, _) = func_ty.sty {
+                    define(local, Definition::Call(callee_def_id, args));
+                }
+            }
+        }
+    }
+    let mut place = match definitions.get(&mir::RETURN_PLACE) {
+        Some(Some(Definition::Assign(mir::Rvalue::Ref(_, mir::BorrowKind::Mut { .. }, place)))) => {
+            place.clone()
+        }
+        Some(Some(Definition::Call(callee_def_id, args))) => {
+            let callee_info = callee_accessor(*callee_def_id)?;
+            callee_accessed_place(mir, &definitions, &callee_info, args)?
+        }
+        _ => return None,
+    };
+    // Follow the reborrows `&mut *tmp`, where `tmp = &mut place` or `tmp = accessor(arg)`.
+    loop {
+        let reborrowed_place = match place {
+            mir::Place::Projection(box mir::Projection {
+                base: mir::Place::Local(tmp),
+                elem: mir::ProjectionElem::Deref,
+            }) if mir.local_kind(tmp) == mir::LocalKind::Temp => match definitions.get(&tmp) {
+                Some(Some(Definition::Assign(mir::Rvalue::Ref(
+                    _,
+                    mir::BorrowKind::Mut { .. },
+                    place,
+                )))) => place.clone(),
+                Some(Some(Definition::Call(callee_def_id, args))) => {
+                    let callee_info = callee_accessor(*callee_def_id)?;
+                    callee_accessed_place(mir, &definitions, &callee_info, args)?
+                }
+                _ => return None,
+            },
+            _ => break,
+        };
+        place = reborrowed_place;
+    }
+
+    // The accessed place has to be a sequence of struct fields of a dereferenced argument.
+    let mut framed_places = vec![];
+    let mut current = &place;
+    let mut has_fields = false;
+    loop {
+        match current {
+            mir::Place::Projection(box mir::Projection {
+                ref base,
+                elem: mir::ProjectionElem::Field(ref accessed_field, _),
+            }) => {
+                let base_ty = base.ty(mir, tcx).to_ty(tcx);
+                match base_ty.sty {
+                    ty::TypeVariants::TyAdt(adt_def, substs) if adt_def.is_struct() => {
+                        for (index, field) in adt_def.variants[0].fields.iter().enumerate() {
+                            let field_ty = field.ty(tcx, substs);
+                            if index != accessed_field.index() && is_primitive(field_ty) {
+                                framed_places
+                                    .push(base.clone().field(mir::Field::new(index), field_ty));
+                            }
+                        }
+                    }
+                    _ => return None,
+                }
+                has_fields = true;
+                current = base;
+            }
+            mir::Place::Projection(box mir::Projection {
+                base: mir::Place::Local(ref arg),
+                elem: mir::ProjectionElem::Deref,
+            }) if has_fields && mir.local_kind(*arg) == mir::LocalKind::Arg => break,
+            _ => return None,
+        }
+    }
+
+    Some(AccessorInfo {
+        accessed_place: place,
+        framed_places,
+    })
+}
+
+/// The place accessed by a call of an accessor with the given arguments, in the
+/// caller.
+fn callee_accessed_place<'tcx>(
+    mir: &mir::Mir<'tcx>,
+    definitions: &HashMap<mir::Local, Option<Definition<'_, 'tcx>>>,
+    callee_info: &AccessorInfo<mir::Place<'tcx>>,
+    args: &[mir::Operand<'tcx>],
+) -> Option<mir::Place<'tcx>> {
+    let callee_arg = get_place_root(&callee_info.accessed_place);
+    // The place that the callee receives a mutable reference to.
+    let callee_base = match args[callee_arg.index() - 1] {
+        mir::Operand::Move(mir::Place::Local(arg))
+        | mir::Operand::Copy(mir::Place::Local(arg)) => {
+            match (mir.local_kind(arg), definitions.get(&arg)) {
+                (mir::LocalKind::Arg, _) => mir::Place::Local(arg).deref(),
+                (
+                    mir::LocalKind::Temp,
+                    Some(Some(Definition::Assign(mir::Rvalue::Ref(
+                        _,
+                        mir::BorrowKind::Mut { .. },
+                        place,
+                    )))),
+                ) => place.clone(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(rebase_place(&callee_info.accessed_place, callee_base))
+}
+
+/// Replace the dereferenced argument `*arg` at the root of a place of an accessor by `base`.
+fn rebase_place<'tcx>(place: &mir::Place<'tcx>, base: mir::Place<'tcx>) -> mir::Place<'tcx> {
+    match *place {
+        mir::Place::Projection(box mir::Projection {
+            base: mir::Place::Local(_),
+            elem: mir::ProjectionElem::Deref,
+        }) => base,
+        mir::Place::Projection(box mir::Projection {
+            base: ref place_base,
+            ref elem,
+        }) => mir::Place::Projection(box mir::Projection {
+            base: rebase_place(place_base, base),
+            elem: elem.clone(),
+        }),
+        ref x => unreachable!("{:?} is not the place of an accessor", x),
+    }
+}
+
+fn is_primitive<'tcx>(ty: Ty<'tcx>) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyBool
+        | ty::TypeVariants::TyChar
+        | ty::TypeVariants::TyInt(_)
+        | ty::TypeVariants::TyUint(_) => true,
+        _ => false,
+    }
+}
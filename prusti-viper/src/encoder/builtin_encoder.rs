@@ -5,6 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use encoder::vir;
+use encoder::vir::ExprIterator;
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub enum BuiltinMethodKind {
@@ -13,12 +14,34 @@ pub enum BuiltinMethodKind {
     HavocRef,
 }
 
+/// A bitwise operation on unsigned integers.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum BuiltinFunctionKind {
     /// type
     Unreachable(vir::Type),
     /// type
     Undefined(vir::Type),
+    /// operation, number of bits of the unsigned operands
+    BitOperation(BitOperation, u32),
+}
+
+/// The expression `2^exponent`.
+pub fn power_of_two(exponent: u32) -> vir::Expr {
+    if exponent < 128 {
+        (1u128 << exponent).into()
+    } else {
+        assert_eq!(exponent, 128);
+        vir::Expr::from(vir::Const::BigInt("340282366920938463463374607431768211456".to_string()))
+    }
 }
 
 pub struct BuiltinEncoder {
@@ -41,7 +64,7 @@ impl BuiltinEncoder {
         let return_type = match method {
             BuiltinMethodKind::HavocBool => vir::Type::Bool,
             BuiltinMethodKind::HavocInt => vir::Type::Int,
-            BuiltinMethodKind::HavocRef => vir::Type::TypedRef("".to_string()),
+            BuiltinMethodKind::HavocRef => vir::Type::typed_ref(""),
         };
         vir::BodylessMethod {
             name: self.encode_builtin_method_name(method),
@@ -54,12 +77,30 @@ impl BuiltinEncoder {
         match function {
             BuiltinFunctionKind::Unreachable(vir::Type::Int) => format!("builtin$unreach_int"),
             BuiltinFunctionKind::Unreachable(vir::Type::Bool) => format!("builtin$unreach_bool"),
-            BuiltinFunctionKind::Unreachable(vir::Type::TypedRef(_)) => {
+            BuiltinFunctionKind::Unreachable(vir::Type::TypedRef(..)) => {
                 format!("builtin$unreach_ref")
             }
             BuiltinFunctionKind::Undefined(vir::Type::Int) => format!("builtin$undef_int"),
             BuiltinFunctionKind::Undefined(vir::Type::Bool) => format!("builtin$undef_bool"),
-            BuiltinFunctionKind::Undefined(vir::Type::TypedRef(_)) => format!("builtin$undef_ref"),
+            BuiltinFunctionKind::Undefined(vir::Type::TypedRef(..)) => format!("builtin$undef_ref"),
+            BuiltinFunctionKind::Unreachable(vir::Type::TypedMap(..))
+            | BuiltinFunctionKind::Undefined(vir::Type::TypedMap(..))
+            | BuiltinFunctionKind::Unreachable(vir::Type::TypedSeq(..))
+            | BuiltinFunctionKind::Undefined(vir::Type::TypedSeq(..))
+            | BuiltinFunctionKind::Unreachable(vir::Type::Domain(..))
+            | BuiltinFunctionKind::Undefined(vir::Type::Domain(..)) => unimplemented!(
+                "builtin functions returning maps, sequences or domain values are not supported"
+            ),
+            BuiltinFunctionKind::BitOperation(op, width) => {
+                let op_name = match op {
+                    BitOperation::And => "and",
+                    BitOperation::Or => "or",
+                    BitOperation::Xor => "xor",
+                    BitOperation::Shl => "shl",
+                    BitOperation::Shr => "shr",
+                };
+                format!("builtin$bit_{}_u{}", op_name, width)
+            }
         }
     }
 
@@ -83,6 +124,56 @@ impl BuiltinEncoder {
                 posts: vec![],
                 body: None,
             },
+            BuiltinFunctionKind::BitOperation(op, width) => {
+                let left = vir::LocalVar::new("left", vir::Type::Int);
+                let right = vir::LocalVar::new("right", vir::Type::Int);
+                let result: vir::Expr = vir::LocalVar::new("__result", vir::Type::Int).into();
+                let left: vir::Expr = left.into();
+                let right: vir::Expr = right.into();
+                let in_range = |value: &vir::Expr| {
+                    vir::Expr::and(
+                        vir::Expr::ge_cmp(value.clone(), 0.into()),
+                        vir::Expr::lt_cmp(value.clone(), power_of_two(width)),
+                    )
+                };
+                // The operation is not defined, only the bounds of its result.
+                let bounds = match op {
+                    BitOperation::And => vec![
+                        vir::Expr::ge_cmp(result.clone(), 0.into()),
+                        vir::Expr::le_cmp(result.clone(), left.clone()),
+                        vir::Expr::le_cmp(result.clone(), right.clone()),
+                    ],
+                    BitOperation::Or => vec![
+                        vir::Expr::ge_cmp(result.clone(), left.clone()),
+                        vir::Expr::ge_cmp(result.clone(), right.clone()),
+                        vir::Expr::lt_cmp(result.clone(), power_of_two(width)),
+                    ],
+                    BitOperation::Xor | BitOperation::Shl => vec![in_range(&result)],
+                    BitOperation::Shr => vec![
+                        vir::Expr::ge_cmp(result.clone(), 0.into()),
+                        vir::Expr::le_cmp(result.clone(), left.clone()),
+                    ],
+                };
+                let operands_in_range = if op == BitOperation::Shl || op == BitOperation::Shr {
+                    vir::Expr::and(in_range(&left), vir::Expr::ge_cmp(right.clone(), 0.into()))
+                } else {
+                    vir::Expr::and(in_range(&left), in_range(&right))
+                };
+                vir::Function {
+                    name: fn_name,
+                    formal_args: vec![
+                        vir::LocalVar::new("left", vir::Type::Int),
+                        vir::LocalVar::new("right", vir::Type::Int),
+                    ],
+                    return_type: vir::Type::Int,
+                    pres: vec![],
+                    posts: vec![vir::Expr::implies(
+                        operands_in_range,
+                        bounds.into_iter().conjoin(),
+                    )],
+                    body: None,
+                }
+            }
         }
     }
 }
@@ -0,0 +1,405 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Encoding of the memory regions that are owned through raw pointers, i.e. of the
+//! `deref_spec(ptr, len)` of the specifications.
+//!
+//! A raw pointer is an address that owns no memory: its predicate only holds the permission to
+//! its `val_ref` field. The memory that it points to is owned by a `deref_spec` of a contract or
+//! of a loop invariant. The address `index` values after `target` is `loc(target, index)`, a
+//! function of the `RawPtrRegion` domain whose axioms state that `loc(target, 0)` is `target`,
+//! that the offsets of `loc` add up and that distinct offsets are distinct addresses. Then,
+//! `deref_spec(ptr, len)` is the quantified permission to the value field of `loc(ptr, index)`
+//! for each `index` in `0..len`. `deref_spec_frac(ptr, len, numerator, denominator)` is the same
+//! quantified permission with the constant fraction `numerator / denominator` of the full
+//! permission, which allows reading but not writing the values. A region can also be owned by
+//! the predicate of a `#[predicate]` function whose body is a `deref_spec`; the predicate is
+//! folded and unfolded only by the `fold!` and `unfold!` statements of the users.
+//!
+//! The pointer arithmetic `ptr.add(index)` and `ptr.offset(index)` is encoded as
+//! `loc(ptr, index)`, without checking that the result stays in a region. A dereference of a raw
+//! pointer is instead encoded as an access to `loc(ptr, 0)` that asserts the permission to the
+//! accessed value, which fails if the value is not in an owned region. The values reached through
+//! a raw pointer are not places of the fold-unfold algorithm, thus only raw pointers to booleans,
+//! integers and characters are supported, whose values have no predicate.
+//!
+//! The specifications read the value at `ptr.add(index)` with `deref_value(ptr, index)`, which
+//! is encoded as the application of a lookup function to `loc(ptr, index)`. The lookup
+//! function is a heap-dependent function that requires the permission to the value, thus Viper
+//! frames its applications across the writes to the other addresses of the region. With
+//! `USE_LOOKUP_TRIGGERS`, the quantifiers of the specifications without user-given triggers
+//! are triggered by the lookups in their body, instead of the triggers inferred by Viper, and
+//! each value read through a raw pointer is stated equal to its lookup, which instantiates them.
+
+use encoder::vir;
+use encoder::vir::ExprWalker;
+use encoder::Encoder;
+use prusti_interface::config;
+use rustc::ty;
+
+/// The name of the domain of the addresses of the memory regions.
+pub const REGION_DOMAIN_NAME: &str = "RawPtrRegion";
+
+/// The function `loc(target, index)` that returns the address `index` values after `target`.
+fn loc_function() -> vir::DomainFunc {
+    vir::DomainFunc {
+        name: format!("{}$loc", REGION_DOMAIN_NAME),
+        formal_args: vec![
+            vir::LocalVar::new("target", vir::Type::typed_ref("")),
+            vir::LocalVar::new("index", vir::Type::Int),
+        ],
+        return_type: vir::Type::typed_ref(""),
+        unique: false,
+        domain_name: REGION_DOMAIN_NAME.to_string(),
+    }
+}
+
+fn loc_app(target: vir::Expr, index: vir::Expr) -> vir::Expr {
+    vir::Expr::domain_func_app(loc_function(), vec![target, index])
+}
+
+/// Encode the domain of the addresses of the memory regions, with the axioms of `loc`.
+pub fn encode_region_domain() -> vir::Domain {
+    let mut domain = vir::Domain::new(REGION_DOMAIN_NAME);
+    domain.functions.push(loc_function());
+    let target = vir::LocalVar::new("target", vir::Type::typed_ref(""));
+    let index = vir::LocalVar::new("index", vir::Type::Int);
+    let other_index = vir::LocalVar::new("other_index", vir::Type::Int);
+    let target_expr: vir::Expr = target.clone().into();
+    let index_expr: vir::Expr = index.clone().into();
+    let other_index_expr: vir::Expr = other_index.clone().into();
+
+    let zero_loc = loc_app(target_expr.clone(), 0.into());
+    domain.axioms.push(vir::DomainAxiom {
+        name: format!("{}$zero", REGION_DOMAIN_NAME),
+        expr: vir::Expr::forall(
+            vec![target.clone()],
+            vec![vir::Trigger::new(vec![zero_loc.clone()])],
+            vir::Expr::eq_cmp(zero_loc, target_expr.clone()),
+        ),
+        domain_name: REGION_DOMAIN_NAME.to_string(),
+    });
+
+    let nested_loc = loc_app(
+        loc_app(target_expr.clone(), index_expr.clone()),
+        other_index_expr.clone(),
+    );
+    domain.axioms.push(vir::DomainAxiom {
+        name: format!("{}$offset", REGION_DOMAIN_NAME),
+        expr: vir::Expr::forall(
+            vec![target.clone(), index.clone(), other_index.clone()],
+            vec![vir::Trigger::new(vec![nested_loc.clone()])],
+            vir::Expr::eq_cmp(
+                nested_loc,
+                loc_app(
+                    target_expr.clone(),
+                    vir::Expr::add(index_expr.clone(), other_index_expr.clone()),
+                ),
+            ),
+        ),
+        domain_name: REGION_DOMAIN_NAME.to_string(),
+    });
+
+    let loc = loc_app(target_expr.clone(), index_expr.clone());
+    let other_loc = loc_app(target_expr, other_index_expr.clone());
+    domain.axioms.push(vir::DomainAxiom {
+        name: format!("{}$injective", REGION_DOMAIN_NAME),
+        expr: vir::Expr::forall(
+            vec![target, index, other_index],
+            vec![vir::Trigger::new(vec![loc.clone(), other_loc.clone()])],
+            vir::Expr::implies(
+                vir::Expr::eq_cmp(loc, other_loc),
+                vir::Expr::eq_cmp(index_expr, other_index_expr),
+            ),
+        ),
+        domain_name: REGION_DOMAIN_NAME.to_string(),
+    });
+    domain
+}
+
+/// Encode the address `index` values after `target`.
+pub fn encode_loc<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    target: vir::Expr,
+    index: vir::Expr,
+) -> vir::Expr {
+    encoder.encode_region_domain_use();
+    loc_app(target, index)
+}
+
+/// The name of the function that looks up the value stored in the field `value_field` at an
+/// address of a memory region.
+pub fn lookup_function_name(value_field: &vir::Field) -> String {
+    format!("{}$lookup${}", REGION_DOMAIN_NAME, value_field.name)
+}
+
+/// Encode the function that looks up the value stored in the field `value_field` at an address
+/// of a memory region, i.e. `deref_value`.
+pub fn encode_lookup_function(value_field: vir::Field) -> vir::Function {
+    let address = vir::LocalVar::new("address", vir::Type::typed_ref(""));
+    let location = vir::Expr::from(address.clone()).field(value_field.clone());
+    vir::Function {
+        name: lookup_function_name(&value_field),
+        formal_args: vec![address],
+        return_type: value_field.typ.clone(),
+        // Any positive permission, e.g. the fraction of a `deref_spec_frac`, allows the lookup.
+        pres: vec![vir::Expr::acc_permission(
+            location.clone(),
+            vir::PermAmount::Wildcard,
+        )],
+        posts: vec![],
+        body: Some(location),
+    }
+}
+
+/// Encode `deref_value(target, index)`, the value stored in the field `value_field` at the
+/// address `index` values after `target`.
+pub fn encode_lookup<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    target: vir::Expr,
+    index: vir::Expr,
+    value_field: vir::Field,
+) -> vir::Expr {
+    let address = encode_loc(encoder, target, index);
+    lookup_app(encoder, address, value_field)
+}
+
+fn lookup_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    address: vir::Expr,
+    value_field: vir::Field,
+) -> vir::Expr {
+    let function_name = encoder.encode_region_lookup_function_use(value_field.clone());
+    vir::Expr::func_app(
+        function_name,
+        vec![address],
+        vec![vir::LocalVar::new("address", vir::Type::typed_ref(""))],
+        value_field.typ,
+        vir::Position::default(),
+    )
+}
+
+/// The triggers of a quantifier over `vars` with body `body`: one trigger for each lookup of the
+/// body whose address mentions all the variables, and no arithmetic, which Viper rejects in
+/// triggers. The lookups in `old` expressions are ignored, because they are evaluated in
+/// another state.
+pub fn encode_lookup_triggers(vars: &[vir::LocalVar], body: &vir::Expr) -> Vec<vir::Trigger> {
+    struct LookupCollector<'a> {
+        vars: &'a [vir::LocalVar],
+        lookups: Vec<vir::Expr>,
+    }
+    impl<'a> vir::ExprWalker for LookupCollector<'a> {
+        fn walk_func_app(
+            &mut self,
+            name: &str,
+            args: &Vec<vir::Expr>,
+            formal_args: &Vec<vir::LocalVar>,
+            return_type: &vir::Type,
+            pos: &vir::Position,
+        ) {
+            for arg in args {
+                self.walk(arg);
+            }
+            let is_lookup = name.starts_with(&format!("{}$lookup$", REGION_DOMAIN_NAME));
+            if is_lookup
+                && !has_arithmetic(&args[0])
+                && self
+                    .vars
+                    .iter()
+                    .all(|var| args[0].find(&vir::Expr::local(var.clone())))
+            {
+                let lookup = vir::Expr::FuncApp(
+                    name.to_string(),
+                    args.clone(),
+                    formal_args.clone(),
+                    return_type.clone(),
+                    pos.clone(),
+                );
+                if !self.lookups.contains(&lookup) {
+                    self.lookups.push(lookup);
+                }
+            }
+        }
+        fn walk_labelled_old(&mut self, _label: &str, _body: &vir::Expr, _pos: &vir::Position) {
+            // Stop recursion.
+        }
+    }
+    let mut collector = LookupCollector {
+        vars,
+        lookups: vec![],
+    };
+    collector.walk(body);
+    collector
+        .lookups
+        .into_iter()
+        .map(|lookup| vir::Trigger::new(vec![lookup]))
+        .collect()
+}
+
+fn has_arithmetic(expr: &vir::Expr) -> bool {
+    struct ArithmeticFinder {
+        found: bool,
+    }
+    impl vir::ExprWalker for ArithmeticFinder {
+        fn walk_unary_op(&mut self, _op: vir::UnaryOpKind, _arg: &vir::Expr, _p: &vir::Position) {
+            self.found = true;
+        }
+        fn walk_bin_op(
+            &mut self,
+            _op: vir::BinOpKind,
+            _left: &vir::Expr,
+            _right: &vir::Expr,
+            _pos: &vir::Position,
+        ) {
+            self.found = true;
+        }
+    }
+    let mut finder = ArithmeticFinder { found: false };
+    finder.walk(expr);
+    finder.found
+}
+
+/// Encode `deref_spec(target, len)`, where the values of the region are stored in the field
+/// `value_field`, with the permission `perm_amount` to each value: `Write` for `deref_spec`,
+/// a fraction for `deref_spec_frac`.
+pub fn encode_deref_spec<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    target: vir::Expr,
+    len: vir::Expr,
+    value_field: vir::Field,
+    perm_amount: vir::PermAmount,
+) -> vir::Expr {
+    let index = vir::LocalVar::new("deref_spec$index", vir::Type::Int);
+    let index_expr: vir::Expr = index.clone().into();
+    // The triggers are inferred by Viper, because the optimiser might rewrite the
+    // subexpressions of the body into `let` expressions.
+    vir::Expr::forall(
+        vec![index],
+        vec![],
+        vir::Expr::implies(
+            vir::Expr::and(
+                vir::Expr::le_cmp(0.into(), index_expr.clone()),
+                vir::Expr::lt_cmp(index_expr.clone(), len),
+            ),
+            vir::Expr::acc_permission(
+                encode_loc(encoder, target, index_expr).field(value_field),
+                perm_amount,
+            ),
+        ),
+    )
+}
+
+/// Can the target of a raw pointer of type `ty` be owned by a `deref_spec`? The pointer must
+/// point to a boolean, an integer or a character.
+pub fn is_region_pointer_type<'tcx>(ty: ty::Ty<'tcx>) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ty: target_ty, .. }) => match target_ty.sty {
+            ty::TypeVariants::TyBool
+            | ty::TypeVariants::TyInt(_)
+            | ty::TypeVariants::TyUint(_)
+            | ty::TypeVariants::TyChar => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Rewrite the dereferences of the raw pointers `pointers` (i.e. of their `val_ref` fields) in
+/// `stmts` to accesses to the memory regions, each preceded by an assertion at `pos` of the
+/// permission that it needs.
+pub fn encode_region_accesses<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    stmts: Vec<vir::Stmt>,
+    pointers: &[vir::Expr],
+    pos: vir::Position,
+) -> Vec<vir::Stmt> {
+    let mut result = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+        let mut folder = RegionAccessFolder {
+            encoder,
+            pointers,
+            is_written: false,
+            accesses: vec![],
+        };
+        let stmt = vir::StmtFolder::fold(&mut folder, stmt);
+        for (location, perm_amount) in folder.accesses {
+            result.push(vir::Stmt::Assert(
+                vir::Expr::acc_permission(location.clone(), perm_amount),
+                vir::FoldingBehaviour::Stmt,
+                pos.clone(),
+            ));
+            // The lookup of a read value triggers the quantifiers of the specifications on it.
+            if perm_amount == vir::PermAmount::Wildcard && config::use_lookup_triggers() {
+                if let vir::Expr::Field(box ref address, ref field, _) = location {
+                    let lookup = lookup_app(encoder, address.clone(), field.clone());
+                    result.push(vir::Stmt::Inhale(
+                        vir::Expr::eq_cmp(lookup, location.clone()),
+                        vir::FoldingBehaviour::Stmt,
+                    ));
+                }
+            }
+        }
+        result.push(stmt);
+    }
+    result
+}
+
+struct RegionAccessFolder<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
+    encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
+    pointers: &'p [vir::Expr],
+    /// Is the expression that is being folded the target of an assignment?
+    is_written: bool,
+    /// The accessed locations of the regions, with the permission that they need.
+    accesses: Vec<(vir::Expr, vir::PermAmount)>,
+}
+
+impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::ExprFolder
+    for RegionAccessFolder<'p, 'v, 'r, 'a, 'tcx>
+{
+    fn fold_field(
+        &mut self,
+        receiver: Box<vir::Expr>,
+        field: vir::Field,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        if self.pointers.contains(&*receiver) {
+            let location = encode_loc(self.encoder, *receiver, 0.into()).field(field);
+            // A read needs some permission, e.g. the fraction of a `deref_spec_frac`, which
+            // would not be known to be at least the symbolic `Read` amount.
+            let perm_amount = if self.is_written {
+                vir::PermAmount::Write
+            } else {
+                vir::PermAmount::Wildcard
+            };
+            self.accesses.push((location.clone(), perm_amount));
+            location.set_pos(pos)
+        } else {
+            // Only the outermost field of an assigned place is written.
+            self.is_written = false;
+            vir::Expr::Field(self.fold_boxed(receiver), field, pos)
+        }
+    }
+}
+
+impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::StmtFolder
+    for RegionAccessFolder<'p, 'v, 'r, 'a, 'tcx>
+{
+    fn fold_expr(&mut self, expr: vir::Expr) -> vir::Expr {
+        vir::ExprFolder::fold(self, expr)
+    }
+
+    fn fold_assign(
+        &mut self,
+        target: vir::Expr,
+        expr: vir::Expr,
+        kind: vir::AssignKind,
+    ) -> vir::Stmt {
+        self.is_written = true;
+        let folded_target = self.fold_expr(target);
+        self.is_written = false;
+        vir::Stmt::Assign(folded_target, self.fold_expr(expr), kind)
+    }
+}
@@ -0,0 +1,307 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Encoding of the user-defined domains, which model mathematical structures (e.g. sets, bags
+//! or orders) used by the specifications.
+//!
+//! A struct without fields marked with `#[domain]` declares an uninterpreted sort: its values
+//! are encoded as the values of a Viper domain, like the values of a primitive type. The
+//! `#[pure]` methods of the inherent impls of the struct are the functions of the domain. Their
+//! bodies are not encoded, and the postconditions of each function are an axiom of the domain,
+//! which holds for all the arguments of the function. The functions of a domain cannot have
+//! preconditions, and their arguments and result must be booleans, integers or values of a
+//! domain.
+//!
+//! A bodyless pure function (e.g. a `#[trusted]` one) whose contract does not depend on the heap
+//! is modelled by a function of the `BodylessFunctions` domain. Its contract becomes an axiom of
+//! the model, which holds for all the arguments that satisfy the precondition, and the Viper
+//! function only ensures that its result is the value of the model. The precondition is still
+//! checked at each application of the Viper function.
+
+use encoder::pure_function_encoder::PureFunctionEncoder;
+use encoder::trusted_surface::TrustedItemKind;
+use encoder::vir;
+use encoder::vir::ExprIterator;
+use encoder::Encoder;
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::specifications::SpecificationSet;
+use rustc::ty;
+use std::mem;
+
+/// The attribute that marks a struct whose values are the values of a domain.
+pub const DOMAIN_ATTR: &str = "domain";
+
+/// The name of the domain of the models of the bodyless pure functions.
+pub const BODYLESS_FUNCTIONS_DOMAIN_NAME: &str = "BodylessFunctions";
+
+/// Is the type a struct without fields marked with `#[domain]`?
+pub fn is_domain_type<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => {
+            adt_def.is_struct()
+                && adt_def.variants[0].fields.is_empty()
+                && encoder.env().has_attribute_name(adt_def.did, DOMAIN_ATTR)
+        }
+        _ => false,
+    }
+}
+
+/// The name of the domain of the values of a domain type. It differs from the name of the
+/// predicate of the type, which is encoded too.
+pub fn encode_domain_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> String {
+    format!("{}$domain", encoder.encode_type_predicate_use(ty))
+}
+
+/// The domain type whose inherent impl defines the pure method `def_id`, if any.
+pub fn domain_type_of_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    def_id: ProcedureDefId,
+) -> Option<ty::Ty<'tcx>> {
+    let tcx = encoder.env().tcx();
+    let impl_def_id = tcx.impl_of_method(def_id)?;
+    if tcx.trait_id_of_impl(impl_def_id).is_some()
+        || !encoder.env().has_attribute_name(def_id, "pure")
+    {
+        return None;
+    }
+    let self_ty = tcx.type_of(impl_def_id);
+    if is_domain_type(encoder, self_ty) {
+        Some(self_ty)
+    } else {
+        None
+    }
+}
+
+/// Encode the functions of the domain `domain_name` of the domain type `ty`, with the pure
+/// methods that they encode. Report an error for each pure method of the type that cannot be a
+/// function of a domain.
+pub fn encode_domain_functions<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+    domain_name: &str,
+) -> Vec<(ProcedureDefId, vir::DomainFunc)> {
+    let tcx = encoder.env().tcx();
+    let adt_def_id = match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => adt_def.did,
+        ref x => unreachable!("{:?}", x),
+    };
+    let pure_methods: Vec<ProcedureDefId> = tcx
+        .inherent_impls(adt_def_id)
+        .iter()
+        .flat_map(|&impl_def_id| tcx.associated_item_def_ids(impl_def_id).to_vec())
+        .filter(|&def_id| domain_type_of_function(encoder, def_id).is_some())
+        .collect();
+    let mut functions = vec![];
+    for def_id in pure_methods {
+        if !is_valid_domain_function(encoder, def_id) {
+            continue;
+        }
+        encoder.trusted_surface().register(
+            TrustedItemKind::DomainFunction,
+            encoder.env().get_absolute_item_name(def_id),
+            tcx.hir.span_if_local(def_id),
+            "the body is not encoded and the postconditions are assumed as axioms",
+        );
+        functions.push((def_id, encode_domain_func(encoder, def_id, domain_name)));
+    }
+    functions
+}
+
+/// Encode the axioms of a domain, one for each of the functions returned by
+/// `encode_domain_functions`.
+pub fn encode_domain_axioms<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    functions: &[(ProcedureDefId, vir::DomainFunc)],
+) -> Vec<vir::DomainAxiom> {
+    functions
+        .iter()
+        .map(|(def_id, function)| encode_domain_axiom(encoder, *def_id, function))
+        .collect()
+}
+
+/// Can the pure method be a function of a domain? Report an error otherwise.
+fn is_valid_domain_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    def_id: ProcedureDefId,
+) -> bool {
+    let span = encoder.env().get_item_span(def_id);
+    let function_name = encoder.env().get_item_name(def_id);
+    if let Some(&SpecificationSet::Procedure(ref pres, _)) = encoder.get_spec_by_def_id(def_id) {
+        if !pres.is_empty() {
+            encoder.env().span_err(
+                span,
+                &format!(
+                    "[Prusti] the function `{}` of a domain cannot have preconditions",
+                    function_name
+                ),
+            );
+            return false;
+        }
+    }
+    let fn_sig = encoder.env().tcx().fn_sig(def_id);
+    let fn_sig = fn_sig.skip_binder();
+    let is_value_ty = |ty: ty::Ty<'tcx>| match ty.sty {
+        ty::TypeVariants::TyBool
+        | ty::TypeVariants::TyInt(_)
+        | ty::TypeVariants::TyUint(_)
+        | ty::TypeVariants::TyChar => true,
+        _ => is_domain_type(encoder, ty),
+    };
+    if !fn_sig.inputs().iter().all(|&ty| is_value_ty(ty)) || !is_value_ty(fn_sig.output()) {
+        encoder.env().span_err(
+            span,
+            &format!(
+                "[Prusti] the arguments and the result of the function `{}` of a domain must be \
+                 booleans, integers or values of a domain",
+                function_name
+            ),
+        );
+        return false;
+    }
+    true
+}
+
+fn encode_domain_func<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    def_id: ProcedureDefId,
+    domain_name: &str,
+) -> vir::DomainFunc {
+    let fn_sig = encoder.env().tcx().fn_sig(def_id);
+    let formal_args = fn_sig
+        .inputs()
+        .skip_binder()
+        .iter()
+        .enumerate()
+        .map(|(index, &arg_ty)| {
+            // Same names as the arguments of the pure functions, used by the postconditions.
+            vir::LocalVar::new(format!("_pure_{}", index + 1), encoder.encode_value_type(arg_ty))
+        })
+        .collect();
+    vir::DomainFunc {
+        name: encoder.encode_item_name(def_id),
+        formal_args,
+        return_type: encoder.encode_value_type(fn_sig.output().skip_binder()),
+        unique: false,
+        domain_name: domain_name.to_string(),
+    }
+}
+
+/// Encode the postconditions of the function as the axiom
+/// `forall args :: { f(args) } bounds(args) ==> bounds(f(args)) && post[result := f(args)]`.
+fn encode_domain_axiom<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    def_id: ProcedureDefId,
+    function: &vir::DomainFunc,
+) -> vir::DomainAxiom {
+    let procedure = encoder.env().get_procedure(def_id);
+    let pure_function_encoder =
+        PureFunctionEncoder::new(encoder, def_id, procedure.get_mir(), false);
+    let args: Vec<vir::Expr> = function
+        .formal_args
+        .iter()
+        .cloned()
+        .map(vir::Expr::local)
+        .collect();
+    let app = vir::Expr::domain_func_app(function.clone(), args.clone());
+    let result = vir::LocalVar::new("__result", function.return_type.clone());
+    let post = pure_function_encoder
+        .encode_postcondition()
+        .replace_place(&result.into(), &app);
+
+    let fn_sig = encoder.env().tcx().fn_sig(def_id);
+    let fn_sig = fn_sig.skip_binder();
+    let arg_bounds = args
+        .iter()
+        .zip(fn_sig.inputs())
+        .flat_map(|(arg, &arg_ty)| encoder.encode_type_bounds(arg, arg_ty))
+        .conjoin();
+    let mut conclusion = encoder.encode_type_bounds(&app, fn_sig.output());
+    conclusion.push(post);
+    let body = vir::Expr::implies(arg_bounds, conclusion.into_iter().conjoin());
+    let expr = if function.formal_args.is_empty() {
+        body
+    } else {
+        vir::Expr::forall(
+            function.formal_args.clone(),
+            vec![vir::Trigger::new(vec![app])],
+            body,
+        )
+    };
+    vir::DomainAxiom {
+        name: format!("{}$axiom", function.name),
+        expr,
+        domain_name: function.domain_name.clone(),
+    }
+}
+
+/// Model the bodyless pure function `function` with a function of the `BodylessFunctions`
+/// domain, whose axiom is `forall args :: { model(args) } pre(args) ==> post[result :=
+/// model(args)]`, and return the function with the postcondition `result == model(args)`. The
+/// function is returned unchanged if its arguments, its result or its contract depend on the
+/// heap, because they cannot be used by an axiom.
+pub fn encode_bodyless_function_model<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    mut function: vir::Function,
+) -> vir::Function {
+    let is_value_type = |typ: &vir::Type| match typ {
+        vir::Type::Int | vir::Type::Bool | vir::Type::Domain(_) => true,
+        _ => false,
+    };
+    if !function.formal_args.iter().all(|arg| is_value_type(&arg.typ))
+        || !is_value_type(&function.return_type)
+        || !function
+            .pres
+            .iter()
+            .chain(function.posts.iter())
+            .all(|expr| expr.is_heap_independent())
+    {
+        return function;
+    }
+    let model = vir::DomainFunc {
+        name: format!("{}$model", function.name),
+        formal_args: function.formal_args.clone(),
+        return_type: function.return_type.clone(),
+        unique: false,
+        domain_name: BODYLESS_FUNCTIONS_DOMAIN_NAME.to_string(),
+    };
+    let args: Vec<vir::Expr> = function
+        .formal_args
+        .iter()
+        .cloned()
+        .map(vir::Expr::local)
+        .collect();
+    let app = vir::Expr::domain_func_app(model.clone(), args);
+    let result: vir::Expr = vir::LocalVar::new("__result", function.return_type.clone()).into();
+    let pos = function.posts[0].pos().clone();
+    let posts = mem::replace(&mut function.posts, vec![])
+        .into_iter()
+        .conjoin()
+        .replace_place(&result, &app);
+    let body = vir::Expr::implies(function.pres.iter().cloned().conjoin(), posts);
+    let expr = if function.formal_args.is_empty() {
+        body
+    } else {
+        vir::Expr::forall(
+            function.formal_args.clone(),
+            vec![vir::Trigger::new(vec![app.clone()])],
+            body,
+        )
+    };
+    let axiom = vir::DomainAxiom {
+        name: format!("{}$axiom", model.name),
+        expr,
+        domain_name: BODYLESS_FUNCTIONS_DOMAIN_NAME.to_string(),
+    };
+    encoder.register_bodyless_function_model(model, axiom);
+    function.posts.push(vir::Expr::eq_cmp(result, app).set_pos(pos));
+    function
+}
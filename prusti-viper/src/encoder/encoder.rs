@@ -4,7 +4,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use encoder::borrows::{compute_procedure_contract, ProcedureContract, ProcedureContractMirDef};
+use encoder::borrows::{
+    compute_accessor_info, compute_procedure_contract, AccessorInfo, ProcedureContract,
+    ProcedureContractMirDef,
+};
+use encoder::unsupported_fallback::{report_unsupported_procedure, unsupported_features};
+use encoder::deref_spec::{
+    encode_lookup_function, encode_region_domain, lookup_function_name, REGION_DOMAIN_NAME,
+};
+use encoder::domain_encoder::{
+    domain_type_of_function, encode_domain_axioms, encode_domain_functions, encode_domain_name,
+    is_domain_type, BODYLESS_FUNCTIONS_DOMAIN_NAME,
+};
 use encoder::builtin_encoder::BuiltinEncoder;
 use encoder::builtin_encoder::BuiltinFunctionKind;
 use encoder::builtin_encoder::BuiltinMethodKind;
@@ -12,44 +23,76 @@ use encoder::error_manager::{ErrorCtxt, ErrorManager};
 use encoder::foldunfold;
 use encoder::places;
 use encoder::procedure_encoder::ProcedureEncoder;
-use encoder::pure_function_encoder::PureFunctionEncoder;
+use encoder::pure_function_encoder::{
+    encode_bodyless_trait_method, encode_component_function_name, PureFunctionEncoder,
+};
+use encoder::purity_inference::{infer_purity, is_pure_closure};
+use encoder::snapshot_encoder::{
+    encode_snapshot_domain, encode_snapshot_domain_name, encode_snapshot_eq,
+    encode_snapshot_func_app, encode_snapshot_function, is_snapshot_type,
+};
 use encoder::spec_encoder::SpecEncoder;
+use encoder::std_models_encoder::{
+    encode_closure_function, encode_closure_function_name, encode_iter_index,
+    encode_iter_index_name, encode_map_abstraction,
+    encode_map_abstraction_name, encode_permutation_domain,
+    encode_seq_abstraction, encode_seq_abstraction_name, encode_std_model_function,
+    encode_std_model_function_name, encode_std_model_return_type, encode_str_abstraction,
+    encode_str_abstraction_name, encode_str_literal_function, PERMUTATION_DOMAIN_NAME,
+    STR_LITERAL_FUNCTION_NAME};
+use encoder::symbol_table::{Symbol, SymbolTable};
+use encoder::sync_models_encoder::find_lock_invariant;
+use encoder::timing_report::TimingReport;
+use encoder::trusted_surface::{TrustedItemKind, TrustedSurface};
 use encoder::type_encoder::{
     compute_discriminant_values, compute_discriminant_bounds, TypeEncoder};
 use encoder::vir;
 use encoder::vir::WithIdentifier;
+use encoder::vir::optimisations::simplifier::Simplifier;
 use prusti_interface::config;
 use prusti_interface::constants::PRUSTI_SPEC_ATTR;
 use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::library_models::{LibraryModel, LibraryModels};
 use prusti_interface::environment::Environment;
 use prusti_interface::report::log;
 use prusti_interface::specifications::{
-    SpecID, SpecificationSet, TypedAssertion,
+    SpecID, SpecificationSet, TypedAssertion, TypedSpecification,
     TypedSpecificationMap, TypedSpecificationSet,
 };
+use prusti_interface::utils::is_prefix;
+use prusti_std_models::{find_model, StdModel};
 use rustc::hir;
 use rustc::hir::def_id::DefId;
 use rustc::middle::const_val::ConstVal;
 use rustc::mir;
-use rustc::mir::interpret::GlobalId;
+use rustc::mir::interpret::{AllocType, ConstValue, GlobalId, Scalar};
 use rustc::ty;
-use std::cell::{RefCell, RefMut};
+use rustc::ty::fold::{BottomUpFolder, TypeFoldable};
+use std::cell::{Ref, RefCell, RefMut};
 use std::collections::HashMap;
 use std::io::Write;
 use std::mem;
 use syntax::ast;
-use viper;
+use syntax_pos::Span;
 
 pub struct Encoder<'v, 'r: 'v, 'a: 'r, 'tcx: 'a> {
     env: &'v Environment<'r, 'a, 'tcx>,
     spec: &'v TypedSpecificationMap,
     error_manager: RefCell<ErrorManager<'tcx>>,
+    trusted_surface: RefCell<TrustedSurface>,
+    timing_report: RefCell<TimingReport>,
+    symbol_table: RefCell<SymbolTable>,
     procedure_contracts: RefCell<HashMap<ProcedureDefId, ProcedureContractMirDef<'tcx>>>,
     builtin_methods: RefCell<HashMap<BuiltinMethodKind, vir::BodylessMethod>>,
     builtin_functions: RefCell<HashMap<BuiltinFunctionKind, vir::Function>>,
     procedures: RefCell<HashMap<ProcedureDefId, vir::CfgMethod>>,
     pure_function_bodies: RefCell<HashMap<(ProcedureDefId, String), vir::Expr>>,
-    pure_functions: RefCell<HashMap<(ProcedureDefId, String), vir::Function>>,
+    /// The functions of each pure function, one for each component if it returns a tuple.
+    pure_functions: RefCell<HashMap<(ProcedureDefId, String), Vec<vir::Function>>>,
+    /// The limited versions of the recursive pure functions, by name.
+    limited_pure_functions: RefCell<HashMap<String, vir::Function>>,
+    /// The result of the purity inference of the procedures without `#[pure]` annotation.
+    inferred_purity: RefCell<HashMap<ProcedureDefId, bool>>,
     type_predicate_names: RefCell<HashMap<ty::TypeVariants<'tcx>, String>>,
     type_invariant_names: RefCell<HashMap<ty::TypeVariants<'tcx>, String>>,
     type_tag_names: RefCell<HashMap<ty::TypeVariants<'tcx>, String>>,
@@ -59,7 +102,25 @@ pub struct Encoder<'v, 'r: 'v, 'a: 'r, 'tcx: 'a> {
     type_tags: RefCell<HashMap<String, vir::Function>>,
     type_discriminant_funcs: RefCell<HashMap<String, vir::Function>>,
     memory_eq_funcs: RefCell<HashMap<String, Option<vir::Function>>>,
+    /// The domains of the structs marked with `#[domain]` and the domains of the snapshots of
+    /// the structs, by name.
+    domains: RefCell<HashMap<String, vir::Domain>>,
+    /// The functions that return the snapshots of the values of the structs, by name.
+    snapshot_functions: RefCell<HashMap<String, vir::Function>>,
+    /// The functions of the pure models of the standard library, by name.
+    std_model_functions: RefCell<HashMap<String, vir::Function>>,
+    /// The `#[lock_invariant]` method of each struct or enum protected by a lock, if any.
+    lock_invariants: RefCell<HashMap<DefId, Option<ProcedureDefId>>>,
+    /// The functions that look up the values stored in the memory regions owned through raw
+    /// pointers, by name.
+    region_lookup_functions: RefCell<HashMap<String, vir::Function>>,
+    /// The functions that return the unknown values of the `unsafe_spec` expressions.
+    unsafe_spec_functions: RefCell<Vec<vir::Function>>,
+    /// The predicates declared by the `#[predicate]` functions, by name, or `None` while the
+    /// body of the predicate is being encoded.
+    user_predicates: RefCell<HashMap<String, Option<vir::Predicate>>>,
     fields: RefCell<HashMap<String, vir::Field>>,
+    library_models: LibraryModels,
     /// For each instantiation of each closure: DefId, basic block index, statement index, operands
     closure_instantiations: HashMap<
         DefId,
@@ -101,12 +162,17 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             env,
             spec,
             error_manager: RefCell::new(ErrorManager::new(env.codemap())),
+            trusted_surface: RefCell::new(TrustedSurface::new()),
+            timing_report: RefCell::new(TimingReport::new()),
+            symbol_table: RefCell::new(SymbolTable::new()),
             procedure_contracts: RefCell::new(HashMap::new()),
             builtin_methods: RefCell::new(HashMap::new()),
             builtin_functions: RefCell::new(HashMap::new()),
             procedures: RefCell::new(HashMap::new()),
             pure_function_bodies: RefCell::new(HashMap::new()),
             pure_functions: RefCell::new(HashMap::new()),
+            limited_pure_functions: RefCell::new(HashMap::new()),
+            inferred_purity: RefCell::new(HashMap::new()),
             type_predicate_names: RefCell::new(HashMap::new()),
             type_invariant_names: RefCell::new(HashMap::new()),
             type_tag_names: RefCell::new(HashMap::new()),
@@ -116,7 +182,15 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             type_tags: RefCell::new(HashMap::new()),
             type_discriminant_funcs: RefCell::new(HashMap::new()),
             memory_eq_funcs: RefCell::new(HashMap::new()),
+            domains: RefCell::new(HashMap::new()),
+            snapshot_functions: RefCell::new(HashMap::new()),
+            std_model_functions: RefCell::new(HashMap::new()),
+            region_lookup_functions: RefCell::new(HashMap::new()),
+            lock_invariants: RefCell::new(HashMap::new()),
+            unsafe_spec_functions: RefCell::new(Vec::new()),
+            user_predicates: RefCell::new(HashMap::new()),
             fields: RefCell::new(HashMap::new()),
+            library_models: LibraryModels::new(env.tcx()),
             closure_instantiations: HashMap::new(),
             encoding_queue: RefCell::new(vec![]),
             vir_program_before_foldunfold_writer,
@@ -155,10 +229,175 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         self.collect_closure_instantiations();
     }
 
+    /// Start a new encoding session, forgetting everything encoded so far.
+    /// All the state of the encoder (caches, interned names, registered
+    /// positions) lives in the encoder itself, so encoding the same items
+    /// again after a reset produces the same program.
+    pub fn reset(&mut self) {
+        *self = Encoder::new(self.env, self.spec);
+    }
+
     pub fn env(&self) -> &'v Environment<'r, 'a, 'tcx> {
         self.env
     }
 
+    /// The model of the procedure of the standard library with the given path,
+    /// if the crate links the tier of the standard library that defines it.
+    pub fn library_model(&self, proc_path: &str) -> Option<LibraryModel> {
+        self.library_models.resolve(proc_path)
+    }
+
+    /// The model of a method of the standard library defined in `prusti_std_models`. The
+    /// `clone` method of a derived `Clone` implementation is modelled like `Clone::clone`.
+    pub fn std_model(&self, def_id: ProcedureDefId) -> Option<StdModel> {
+        let tcx = self.env.tcx();
+        if let Some(impl_def_id) = tcx.impl_of_method(def_id) {
+            if self.is_derived_clone_impl(impl_def_id) {
+                return Some(StdModel::Clone);
+            }
+        }
+        if def_id.is_local() {
+            return None;
+        }
+        find_model(&tcx.absolute_item_path_str(def_id))
+    }
+
+    /// The lock invariant of the content of a lock of type `protected_ty`, if any.
+    pub fn lock_invariant(&self, protected_ty: ty::Ty<'tcx>) -> Option<ProcedureDefId> {
+        let adt_def_id = match protected_ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, _) => adt_def.did,
+            _ => return None,
+        };
+        if !self.lock_invariants.borrow().contains_key(&adt_def_id) {
+            let lock_invariant = find_lock_invariant(self, adt_def_id);
+            self.lock_invariants
+                .borrow_mut()
+                .insert(adt_def_id, lock_invariant);
+        }
+        self.lock_invariants.borrow()[&adt_def_id]
+    }
+
+    /// Is the `impl` a derived implementation of `Clone`?
+    fn is_derived_clone_impl(&self, impl_def_id: DefId) -> bool {
+        let tcx = self.env.tcx();
+        let clone_trait = tcx.lang_items().clone_trait();
+        clone_trait.is_some()
+            && tcx.trait_id_of_impl(impl_def_id) == clone_trait
+            && tcx.has_attr(impl_def_id, "automatically_derived")
+    }
+
+    /// Is the `Clone` implementation of the type derived? Then a clone copies the shared
+    /// references of the fields.
+    pub fn has_derived_clone(&self, ty: ty::Ty<'tcx>) -> bool {
+        let tcx = self.env.tcx();
+        let clone_trait = match tcx.lang_items().clone_trait() {
+            Some(clone_trait) => clone_trait,
+            None => return false,
+        };
+        let mut impls = vec![];
+        tcx.for_each_relevant_impl(clone_trait, ty, |impl_def_id| impls.push(impl_def_id));
+        !impls.is_empty()
+            && impls
+                .into_iter()
+                .all(|impl_def_id| self.is_derived_clone_impl(impl_def_id))
+    }
+
+    /// Is the `PartialEq` implementation of the type derived? Then `==` compares the fields.
+    pub fn has_derived_partial_eq(&self, ty: ty::Ty<'tcx>) -> bool {
+        let tcx = self.env.tcx();
+        let eq_trait = match tcx.lang_items().eq_trait() {
+            Some(eq_trait) => eq_trait,
+            None => return false,
+        };
+        let mut impls = vec![];
+        tcx.for_each_relevant_impl(eq_trait, ty, |impl_def_id| impls.push(impl_def_id));
+        !impls.is_empty()
+            && impls
+                .into_iter()
+                .all(|impl_def_id| tcx.has_attr(impl_def_id, "automatically_derived"))
+    }
+
+    /// Is the procedure `PartialEq::eq` or `PartialEq::ne` on two values of a struct that are
+    /// compared by snapshot?
+    pub fn is_snapshot_eq_call(
+        &self,
+        proc_def_id: ProcedureDefId,
+        substs: &ty::subst::Substs<'tcx>,
+    ) -> bool {
+        let tcx = self.env.tcx();
+        let eq_trait = tcx.lang_items().eq_trait();
+        if eq_trait.is_none() || tcx.trait_of_item(proc_def_id) != eq_trait {
+            return false;
+        }
+        let item_name = tcx.item_name(proc_def_id).to_string();
+        (item_name == "eq" || item_name == "ne")
+            && substs.type_at(0) == substs.type_at(1)
+            && self.is_snapshot_type(substs.type_at(0))
+    }
+
+    /// The operator of a procedure that implements an arithmetic trait (e.g. `Add`) for a newtype
+    /// marked with `#[arithmetic_newtype]`, taking and returning the newtype.
+    fn arithmetic_newtype_op(&self, proc_def_id: ProcedureDefId) -> Option<mir::BinOp> {
+        let tcx = self.env.tcx();
+        let impl_def_id = tcx.impl_of_method(proc_def_id)?;
+        let trait_def_id = Some(tcx.trait_id_of_impl(impl_def_id)?);
+        let lang_items = tcx.lang_items();
+        let op = if trait_def_id == lang_items.add_trait() {
+            mir::BinOp::Add
+        } else if trait_def_id == lang_items.sub_trait() {
+            mir::BinOp::Sub
+        } else if trait_def_id == lang_items.mul_trait() {
+            mir::BinOp::Mul
+        } else if trait_def_id == lang_items.div_trait() {
+            mir::BinOp::Div
+        } else if trait_def_id == lang_items.rem_trait() {
+            mir::BinOp::Rem
+        } else {
+            return None;
+        };
+        let fn_sig = tcx.fn_sig(proc_def_id);
+        let newtype_ty = fn_sig.skip_binder().output();
+        let is_newtype_op = self.arithmetic_newtype_inner_ty(newtype_ty).is_some()
+            && fn_sig.skip_binder().inputs().iter().all(|&ty| ty == newtype_ty);
+        if is_newtype_op {
+            Some(op)
+        } else {
+            None
+        }
+    }
+
+    /// The type of the integer wrapped by a newtype marked with `#[arithmetic_newtype]`.
+    fn arithmetic_newtype_inner_ty(&self, ty: ty::Ty<'tcx>) -> Option<ty::Ty<'tcx>> {
+        match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, substs)
+                if adt_def.is_struct()
+                    && adt_def.variants[0].fields.len() == 1
+                    && self.env.has_attribute_name(adt_def.did, "arithmetic_newtype") =>
+            {
+                let field_ty = adt_def.variants[0].fields[0].ty(self.env.tcx(), substs);
+                match field_ty.sty {
+                    ty::TypeVariants::TyInt(_) | ty::TypeVariants::TyUint(_) => Some(field_ty),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode the integer wrapped by a newtype marked with `#[arithmetic_newtype]`, and return
+    /// it with its type.
+    pub fn encode_arithmetic_newtype_value(
+        &self,
+        newtype: vir::Expr,
+        newtype_ty: ty::Ty<'tcx>,
+    ) -> (vir::Expr, ty::Ty<'tcx>) {
+        let inner_ty = self.arithmetic_newtype_inner_ty(newtype_ty).unwrap();
+        let value = newtype
+            .field(self.encode_struct_field("0", inner_ty))
+            .field(self.encode_value_field(inner_ty));
+        (value, inner_ty)
+    }
+
     pub fn spec(&self) -> &'v TypedSpecificationMap {
         self.spec
     }
@@ -167,8 +406,22 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         self.error_manager.borrow_mut()
     }
 
-    pub fn get_used_viper_domains(&self) -> Vec<viper::Domain<'v>> {
-        vec![]
+    pub fn trusted_surface(&self) -> RefMut<TrustedSurface> {
+        self.trusted_surface.borrow_mut()
+    }
+
+    pub fn timing_report(&self) -> RefMut<TimingReport> {
+        self.timing_report.borrow_mut()
+    }
+
+    pub fn symbol_table(&self) -> Ref<SymbolTable> {
+        self.symbol_table.borrow()
+    }
+
+    pub fn get_used_viper_domains(&self) -> Vec<vir::Domain> {
+        let mut domains: Vec<_> = self.domains.borrow().values().cloned().collect();
+        domains.sort_by_key(|domain| domain.get_identifier());
+        domains
     }
 
     pub fn get_used_viper_fields(&self) -> Vec<vir::Field> {
@@ -182,7 +435,10 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         for function in self.builtin_functions.borrow().values() {
             functions.push(function.clone());
         }
-        for function in self.pure_functions.borrow().values() {
+        for pure_functions in self.pure_functions.borrow().values() {
+            functions.extend(pure_functions.iter().cloned());
+        }
+        for function in self.limited_pure_functions.borrow().values() {
             functions.push(function.clone());
         }
         for function in self.type_invariants.borrow().values() {
@@ -197,12 +453,30 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         for function in self.memory_eq_funcs.borrow().values() {
             functions.push(function.as_ref().unwrap().clone());
         }
+        for function in self.snapshot_functions.borrow().values() {
+            functions.push(function.clone());
+        }
+        for function in self.std_model_functions.borrow().values() {
+            functions.push(function.clone());
+        }
+        for function in self.region_lookup_functions.borrow().values() {
+            functions.push(function.clone());
+        }
+        for function in self.unsafe_spec_functions.borrow().iter() {
+            functions.push(function.clone());
+        }
+        if config::simplify_encoding() {
+            functions = functions.into_iter().map(|f| f.simplify()).collect();
+        }
         functions.sort_by_key(|f| f.get_identifier());
         functions
     }
 
     pub fn get_used_viper_predicates(&self) -> Vec<vir::Predicate> {
         let mut predicates: Vec<_> = self.type_predicates.borrow().values().cloned().collect();
+        for predicate in self.user_predicates.borrow().values() {
+            predicates.push(predicate.as_ref().unwrap().clone());
+        }
         predicates.sort_by_key(|f| f.get_identifier());
         predicates
     }
@@ -228,6 +502,21 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             if !(self
                 .env()
                 .has_attribute_name(mir_def_id, "__PRUSTI_LOOP_SPEC_ID")
+                || self
+                    .env()
+                    .has_attribute_name(mir_def_id, "__PRUSTI_ASSUMPTION_SPEC_ID")
+                || self
+                    .env()
+                    .has_attribute_name(mir_def_id, "__PRUSTI_ASSERTION_SPEC_ID")
+                || self
+                    .env()
+                    .has_attribute_name(mir_def_id, "__PRUSTI_REFUTATION_SPEC_ID")
+                || self
+                    .env()
+                    .has_attribute_name(mir_def_id, "__PRUSTI_FOLD_SPEC_ID")
+                || self
+                    .env()
+                    .has_attribute_name(mir_def_id, "__PRUSTI_UNFOLD_SPEC_ID")
                 || self
                     .env()
                     .has_attribute_name(mir_def_id, "__PRUSTI_EXPR_ID")
@@ -306,7 +595,124 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             .and_then(|spec_id| self.spec().get(&spec_id))
     }
 
+    /// The termination measure of a procedure, written with `#[decreases="..."]`.
+    pub fn get_termination_measure(&self, def_id: DefId) -> Option<&TypedSpecification> {
+        if !def_id.is_local() {
+            return None;
+        }
+        let attr = self
+            .env()
+            .get_attr(def_id, "__PRUSTI_TERMINATION_SPEC_ID")?;
+        let spec_id: u64 = attr.parse().unwrap();
+        match self.spec().get(&SpecID::from(spec_id)) {
+            Some(SpecificationSet::Termination(ref measures)) => measures.first(),
+            _ => None,
+        }
+    }
+
+    /// The method of the trait implemented by the given method of an `impl`, if any.
+    pub fn get_implemented_trait_method(
+        &self,
+        proc_def_id: ProcedureDefId,
+    ) -> Option<ProcedureDefId> {
+        let tcx = self.env().tcx();
+        let impl_def_id = tcx.impl_of_method(proc_def_id)?;
+        let trait_def_id = tcx.trait_id_of_impl(impl_def_id)?;
+        let proc_name = tcx.item_name(proc_def_id).to_string();
+        tcx.associated_items(trait_def_id)
+            .find(|assoc_item| assoc_item.name == proc_name)
+            .map(|assoc_item| assoc_item.def_id)
+    }
+
+    /// Resolve a call from `caller_def_id` to a method of a trait to the method of the
+    /// `impl` that will be called, if it is a non-generic method of the verified crate.
+    pub fn resolve_trait_method_call(
+        &self,
+        caller_def_id: ProcedureDefId,
+        called_def_id: ProcedureDefId,
+        substs: &'tcx ty::subst::Substs<'tcx>,
+    ) -> Option<ProcedureDefId> {
+        let tcx = self.env().tcx();
+        tcx.trait_of_item(called_def_id)?;
+        let param_env = tcx.param_env(caller_def_id);
+        let instance = ty::Instance::resolve(tcx, param_env, called_def_id, substs)?;
+        match instance.def {
+            ty::InstanceDef::Item(impl_method_def_id)
+                if impl_method_def_id != called_def_id
+                    && impl_method_def_id.is_local()
+                    && ty::subst::Substs::identity_for_item(tcx, impl_method_def_id).is_empty() =>
+            {
+                debug!("Resolved call of {:?} to {:?}", called_def_id, impl_method_def_id);
+                Some(impl_method_def_id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolve a call from `caller_def_id` to a method of a trait to the method of the `impl`
+    /// of the standard library that will be called, if that method has a model in
+    /// `prusti_std_models`. E.g. a call of `DerefMut::deref_mut` on a `Vec`, which autoderef
+    /// inserts before calling a method of slices, or a call of `Iterator::next`.
+    pub fn resolve_std_model_call(
+        &self,
+        caller_def_id: ProcedureDefId,
+        called_def_id: ProcedureDefId,
+        substs: &'tcx ty::subst::Substs<'tcx>,
+    ) -> Option<ProcedureDefId> {
+        let tcx = self.env().tcx();
+        tcx.trait_of_item(called_def_id)?;
+        let param_env = tcx.param_env(caller_def_id);
+        let instance = ty::Instance::resolve(tcx, param_env, called_def_id, substs)?;
+        match instance.def {
+            ty::InstanceDef::Item(impl_method_def_id)
+                if impl_method_def_id != called_def_id
+                    && self.std_model(impl_method_def_id).is_some() =>
+            {
+                debug!("Resolved call of {:?} to {:?}", called_def_id, impl_method_def_id);
+                Some(impl_method_def_id)
+            }
+            _ => None,
+        }
+    }
+
+    /// The specification of a procedure. The method of an `impl` inherits the precondition
+    /// and the postcondition of the implemented trait method, if it does not have its own.
+    fn get_spec_with_inherited_contract(
+        &self,
+        proc_def_id: ProcedureDefId,
+    ) -> Option<TypedSpecificationSet> {
+        let opt_fun_spec = self.get_spec_by_def_id(proc_def_id).cloned();
+        let opt_trait_spec = self
+            .get_implemented_trait_method(proc_def_id)
+            .and_then(|trait_method_def_id| self.get_spec_by_def_id(trait_method_def_id));
+        match (opt_fun_spec, opt_trait_spec) {
+            (
+                Some(SpecificationSet::Procedure(mut pres, mut posts)),
+                Some(&SpecificationSet::Procedure(ref trait_pres, ref trait_posts)),
+            ) => {
+                if pres.is_empty() {
+                    pres.extend_from_slice(trait_pres);
+                }
+                if posts.is_empty() {
+                    posts.extend_from_slice(trait_posts);
+                }
+                Some(SpecificationSet::Procedure(pres, posts))
+            }
+            (None, Some(trait_spec)) => Some(trait_spec.clone()),
+            (opt_fun_spec, _) => opt_fun_spec,
+        }
+    }
+
     fn get_procedure_contract(&self, proc_def_id: ProcedureDefId) -> ProcedureContractMirDef<'tcx> {
+        self.get_procedure_contract_visiting(proc_def_id, &mut vec![])
+    }
+
+    /// Compute the contract of a procedure, while inferring the accessors among `visiting`.
+    fn get_procedure_contract_visiting(
+        &self,
+        proc_def_id: ProcedureDefId,
+        visiting: &mut Vec<ProcedureDefId>,
+    ) -> ProcedureContractMirDef<'tcx> {
         let opt_fun_spec = self.get_spec_by_def_id(proc_def_id);
         let fun_spec = match opt_fun_spec {
             Some(fun_spec) => fun_spec.clone(),
@@ -315,7 +721,55 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                 SpecificationSet::Procedure(vec![], vec![])
             }
         };
-        compute_procedure_contract(proc_def_id, self.env().tcx(), fun_spec, None)
+        let mut contract = compute_procedure_contract(proc_def_id, self.env().tcx(), fun_spec, None);
+        contract.accessor = self.infer_accessor(proc_def_id, &contract, visiting);
+        contract.arithmetic_newtype = self.arithmetic_newtype_op(proc_def_id);
+        contract
+    }
+
+    /// Infer the shape of an accessor, whose pledge can be inferred, unless the
+    /// procedure has a pledge or its body is not verified. The procedures in
+    /// `visiting` are being inferred, thus they are not accessors for recursive calls.
+    fn infer_accessor(
+        &self,
+        proc_def_id: ProcedureDefId,
+        contract: &ProcedureContractMirDef<'tcx>,
+        visiting: &mut Vec<ProcedureDefId>,
+    ) -> Option<AccessorInfo<mir::Place<'tcx>>> {
+        let tcx = self.env().tcx();
+        if !config::infer_accessor_pledges()
+            || !proc_def_id.is_local()
+            || !tcx.is_mir_available(proc_def_id)
+            || self.is_trusted(proc_def_id)
+            || tcx.generics_of(proc_def_id).count() > 0
+            || contract.borrow_infos.len() != 1
+            || !contract.pledges().is_empty()
+            || visiting.contains(&proc_def_id)
+        {
+            return None;
+        }
+        let mir = tcx.mir_validated(proc_def_id).borrow();
+        visiting.push(proc_def_id);
+        let accessor = compute_accessor_info(tcx, &mir, &mut |callee_def_id| {
+            self.get_procedure_contract_visiting(callee_def_id, visiting)
+                .accessor
+        });
+        visiting.pop();
+        let accessor = accessor?;
+        // The accessed place has to be blocked by the returned reference.
+        let is_blocked = contract.borrow_infos[0]
+            .blocked_paths
+            .iter()
+            .any(|(place, mutability)| {
+                *mutability == hir::Mutability::MutMutable
+                    && is_prefix(&accessor.accessed_place, place)
+            });
+        if is_blocked {
+            debug!("Inferred accessor {:?} for {:?}", accessor, proc_def_id);
+            Some(accessor)
+        } else {
+            None
+        }
     }
 
     pub fn get_procedure_contract_for_def(
@@ -335,18 +789,38 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         args: &Vec<places::Local>,
         target: places::Local,
     ) -> ProcedureContract<'tcx> {
-        let opt_fun_spec = self.get_spec_by_def_id(proc_def_id);
+        let opt_fun_spec = self.get_spec_with_inherited_contract(proc_def_id);
         let fun_spec = match opt_fun_spec {
-            Some(fun_spec) => fun_spec.clone(),
+            Some(fun_spec) => fun_spec,
             None => {
                 debug!("Procedure {:?} has no specification", proc_def_id);
+                if !proc_def_id.is_local() {
+                    self.trusted_surface().register(
+                        TrustedItemKind::CallWithoutSpecification,
+                        self.env.get_absolute_item_name(proc_def_id),
+                        None,
+                        "the callee is assumed to have a trivial contract",
+                    );
+                }
                 SpecificationSet::Procedure(vec![], vec![])
             }
         };
+        if self.is_trusted(proc_def_id) || self.env.has_attribute_name(proc_def_id, "__PRUSTI_SKIP")
+        {
+            self.trusted_surface().register(
+                TrustedItemKind::CallToTrustedProcedure,
+                self.env.get_absolute_item_name(proc_def_id),
+                self.env.tcx().hir.span_if_local(proc_def_id),
+                "the contract of the callee is assumed",
+            );
+        }
         let tymap = self.typaram_repl.borrow_mut();
         assert!(tymap.len() == 1);
-        let contract =
+        let mut contract =
             compute_procedure_contract(proc_def_id, self.env().tcx(), fun_spec, Some(&tymap[0]));
+        contract.accessor = self.infer_accessor(proc_def_id, &contract, &mut vec![]);
+        contract.std_model = self.std_model(proc_def_id);
+        contract.arithmetic_newtype = self.arithmetic_newtype_op(proc_def_id);
         contract.to_call_site_contract(args, target)
     }
 
@@ -361,18 +835,15 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
     }
 
     pub fn encode_raw_ref_field(&self, viper_field_name: String, ty: ty::Ty<'tcx>) -> vir::Field {
-        let type_name = self.encode_type_predicate_use(ty);
+        let typ = self.encode_type(ty);
         self.fields
             .borrow_mut()
             .entry(viper_field_name.clone())
             .or_insert_with(|| {
                 // Do not store the name of the type in self.fields
-                vir::Field::new(
-                    viper_field_name.clone(),
-                    vir::Type::TypedRef("".to_string()),
-                )
+                vir::Field::new(viper_field_name.clone(), vir::Type::typed_ref(""))
             });
-        vir::Field::new(viper_field_name, vir::Type::TypedRef(type_name))
+        vir::Field::new(viper_field_name, typ)
     }
 
     pub fn encode_dereference_field(&self, ty: ty::Ty<'tcx>) -> vir::Field {
@@ -384,12 +855,22 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         self.encode_raw_ref_field(viper_field_name, ty)
     }
 
+    /// Creates the field of the component ``index`` of a tuple, whose type is ``ty``.
+    pub fn encode_tuple_field(&self, index: usize, ty: ty::Ty<'tcx>) -> vir::Field {
+        self.encode_raw_ref_field(vir::Field::tuple_name(index), ty)
+    }
+
+    /// Creates the field of the variable ``index`` captured by a closure, whose type is ``ty``.
+    pub fn encode_closure_field(&self, index: usize, ty: ty::Ty<'tcx>) -> vir::Field {
+        self.encode_raw_ref_field(format!("closure_{}", index), ty)
+    }
+
     /// Creates a field that corresponds to the enum variant ``index``.
     pub fn encode_enum_variant_field(&self, index: &str) {
         let name = format!("enum_{}", index);
         let mut fields = self.fields.borrow_mut();
         if !fields.contains_key(&name) {
-            let field = vir::Field::new(name.clone(), vir::Type::TypedRef("".to_string()));
+            let field = vir::Field::new(name.clone(), vir::Type::typed_ref(""));
             fields.insert(name, field);
         }
     }
@@ -459,8 +940,7 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
     ) -> vir::Expr {
         let mut conjuncts = Vec::new();
         for (field_num, ty) in elems.iter().enumerate() {
-            let field_name = format!("tuple_{}", field_num);
-            let field = self.encode_raw_ref_field(field_name, ty);
+            let field = self.encode_tuple_field(field_num, ty);
             let first_field = first.clone().field(field.clone());
             let second_field = second.clone().field(field);
             let eq = self.encode_memory_eq_func_app(
@@ -485,7 +965,7 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             let variant_def = &adt_def.variants[0];
             for field in &variant_def.fields {
                 let field_name = &field.ident.as_str();
-                let field_ty = field.ty(tcx, subst);
+                let field_ty = self.resolve_field_ty(field, subst);
                 let elem_field = self.encode_struct_field(field_name, field_ty);
                 let first_field = first.clone().field(elem_field.clone());
                 let second_field = second.clone().field(elem_field);
@@ -537,6 +1017,12 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                 let second_field = second.clone().field(field);
                 Some(vir::Expr::eq_cmp(first_field, second_field))
             }
+            ty::TypeVariants::TyAdt(..) if self.is_domain_type(self_ty) => {
+                let field = self.encode_value_field(self_ty);
+                let first_field = first.clone().field(field.clone());
+                let second_field = second.clone().field(field);
+                Some(vir::Expr::eq_cmp(first_field, second_field))
+            }
             ty::TypeVariants::TyAdt(adt_def, subst) if !adt_def.is_box() => {
                 // TODO: If adt_def contains fields of unsupported type,
                 // we should return None.
@@ -545,7 +1031,9 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             ty::TypeVariants::TyTuple(elems) => {
                 Some(self.encode_memory_eq_tuple(first.clone(), second.clone(), elems))
             }
-            ty::TypeVariants::TyParam(_) => {
+            ty::TypeVariants::TyParam(_)
+            | ty::TypeVariants::TyProjection(_)
+            | ty::TypeVariants::TyAnon(..) => {
                 None
             },
 
@@ -563,8 +1051,8 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         // Mark that we started encoding this function to avoid infinite recursion.
         self.memory_eq_funcs.borrow_mut().insert(name.clone(), None);
 
-        let type_name = self.encode_type_predicate_use(self_ty);
-        let typ = vir::Type::TypedRef(type_name.clone());
+        let typ = self.encode_type(self_ty);
+        let type_name = typ.name();
         let first_local_var = vir::LocalVar::new("self", typ.clone());
         let second_local_var = vir::LocalVar::new("other", typ);
         let precondition = vec![
@@ -604,7 +1092,6 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         assert!(!self.memory_eq_funcs.borrow().contains_key(&name));
         // Mark that we started encoding this function to avoid infinite recursion.
         self.memory_eq_funcs.borrow_mut().insert(name.clone(), None);
-        let tcx = self.env().tcx();
         let type_name = typ.name();
         let first_local_var = vir::LocalVar::new("self", typ.clone());
         let second_local_var = vir::LocalVar::new("other", typ);
@@ -624,7 +1111,7 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
             .iter()
             .map(|field| {
                 let field_name = &field.ident.as_str();
-                let field_ty = field.ty(tcx, subst);
+                let field_ty = self.resolve_field_ty(field, subst);
                 let encoded_field = self.encode_struct_field(field_name, field_ty);
                 let first_field = vir::Expr::from(first_local_var.clone())
                     .field(encoded_field.clone());
@@ -765,8 +1252,8 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
     pub fn encode_procedure(&self, proc_def_id: ProcedureDefId) -> vir::CfgMethod {
         debug!("encode_procedure({:?})", proc_def_id);
         assert!(
-            !self.env.has_attribute_name(proc_def_id, "pure"),
-            "procedure is marked as pure: {:?}",
+            !self.is_pure(proc_def_id),
+            "procedure is pure: {:?}",
             proc_def_id
         );
         assert!(
@@ -829,6 +1316,9 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         if !self.type_predicate_names.borrow().contains_key(&ty.sty) {
             let type_encoder = TypeEncoder::new(self, ty);
             let result = type_encoder.encode_predicate_use();
+            self.symbol_table
+                .borrow_mut()
+                .register(&result, Symbol::Type { ty: ty.to_string() });
             self.type_predicate_names
                 .borrow_mut()
                 .insert(ty.sty.clone(), result);
@@ -911,6 +1401,9 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
 
     pub fn encode_const_expr(&self, value: &ty::Const<'tcx>) -> vir::Expr {
         trace!("encode_const_expr {:?}", value);
+        if let Some(content) = self.str_literal_content(value) {
+            return self.encode_str_literal_use(content);
+        }
         let scalar_value = match value.val {
             ConstVal::Value(ref value) => value
                 .to_scalar()
@@ -1033,6 +1526,41 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         expr
     }
 
+    /// The content of an operand, if it is a string literal, e.g. the message passed to the
+    /// panic machinery.
+    pub fn str_literal_operand_content(&self, operand: &mir::Operand<'tcx>) -> Option<String> {
+        match operand {
+            &mir::Operand::Constant(box mir::Constant {
+                literal: mir::Literal::Value { value },
+                ..
+            }) => self.str_literal_content(value),
+            _ => None,
+        }
+    }
+
+    /// The content of a constant, if it is a string literal.
+    fn str_literal_content(&self, value: &ty::Const<'tcx>) -> Option<String> {
+        match (&value.ty.sty, &value.val) {
+            (
+                &ty::TypeVariants::TyRef(_, inner_ty, _),
+                &ConstVal::Value(ConstValue::ScalarPair(
+                    Scalar::Ptr(ptr),
+                    Scalar::Bits { bits: len, .. },
+                )),
+            ) if inner_ty.sty == ty::TypeVariants::TyStr => {
+                match self.env.tcx().alloc_map.lock().get(ptr.alloc_id) {
+                    Some(AllocType::Memory(alloc)) => {
+                        let start = ptr.offset.bytes() as usize;
+                        let bytes = &alloc.bytes[start..start + len as usize];
+                        Some(String::from_utf8_lossy(bytes).into_owned())
+                    }
+                    ref x => unreachable!("the string literal is not in memory: {:?}", x),
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub fn encode_int_cast(&self, value: u128, ty: ty::Ty<'tcx>) -> vir::Expr {
         trace!("encode_int_cast {:?} as {:?}", value, ty);
 
@@ -1080,16 +1608,24 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                 .replace(";", "$semic$")
                 .replace(" ", "$space$"),
         );
+        let tcx = self.env.tcx();
+        let kind = tcx.describe_def(def_id).map_or("item", |def| def.kind_name());
+        self.symbol_table.borrow_mut().register(
+            &final_name,
+            Symbol::Item {
+                kind: kind.to_string(),
+                path: tcx.item_path_str(def_id),
+            },
+        );
         final_name
     }
 
     pub fn encode_invariant_func_app(&self, ty: ty::Ty<'tcx>, encoded_arg: vir::Expr) -> vir::Expr {
-        let type_pred = self.encode_type_predicate_use(ty);
         vir::Expr::FuncApp(
             self.encode_type_invariant_use(ty),
             vec![encoded_arg],
             // TODO ?
-            vec![vir::LocalVar::new("self", vir::Type::TypedRef(type_pred))],
+            vec![vir::LocalVar::new("self", self.encode_type(ty))],
             vir::Type::Bool,
             // TODO
             vir::Position::default(),
@@ -1140,8 +1676,8 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
     ) {
         trace!("[enter] encode_pure_function_def({:?})", proc_def_id);
         assert!(
-            self.env.has_attribute_name(proc_def_id, "pure"),
-            "procedure is not marked as pure: {:?}",
+            self.is_pure(proc_def_id),
+            "procedure is not pure: {:?}",
             proc_def_id
         );
 
@@ -1162,16 +1698,27 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
 
         if !self.pure_functions.borrow().contains_key(&key) {
             trace!("not encoded: {:?}", key);
-            let procedure = self.env.get_procedure(proc_def_id);
-            let pure_function_encoder =
-                PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
-            let function = if self.is_trusted(proc_def_id) {
-                pure_function_encoder.encode_bodyless_function()
+            let functions = if self.is_bodyless_trait_method(proc_def_id) {
+                // There is no MIR, so the signature is taken from the type of the method.
+                if let Some(&SpecificationSet::Procedure(ref pres, ref posts)) =
+                    self.get_spec_by_def_id(proc_def_id)
+                {
+                    if !pres.is_empty() || !posts.is_empty() {
+                        self.env.span_warn(
+                            self.env.get_item_span(proc_def_id),
+                            "[Prusti: unsupported feature] the specification of a pure \
+                             trait method without a default body is ignored",
+                        );
+                    }
+                }
+                vec![encode_bodyless_trait_method(self, proc_def_id)]
             } else {
-                pure_function_encoder.encode_function()
+                self.encode_pure_function_with_mir(proc_def_id)
             };
-            self.log_vir_program_before_viper(function.to_string());
-            self.pure_functions.borrow_mut().insert(key, function);
+            for function in &functions {
+                self.log_vir_program_before_viper(function.to_string());
+            }
+            self.pure_functions.borrow_mut().insert(key, functions);
         }
 
         // FIXME; hideous monstrosity...
@@ -1182,27 +1729,139 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         trace!("[exit] encode_pure_function_def({:?})", proc_def_id);
     }
 
+    /// Register the limited version of a recursive pure function, which is used to encode its
+    /// recursive calls.
+    pub fn register_limited_pure_function(&self, function: vir::Function) {
+        self.log_vir_program_before_viper(function.to_string());
+        self.limited_pure_functions
+            .borrow_mut()
+            .insert(function.name.clone(), function);
+    }
+
+    /// Encode a pure function with MIR. A pure function that returns a tuple is encoded as one
+    /// function for each component of the tuple.
+    fn encode_pure_function_with_mir(&self, proc_def_id: ProcedureDefId) -> Vec<vir::Function> {
+        let procedure = self.env.get_procedure(proc_def_id);
+        let mir = procedure.get_mir();
+        let is_bodyless = if self.is_spec_function(proc_def_id) {
+            self.trusted_surface().register(
+                TrustedItemKind::SpecFunction,
+                self.env.get_absolute_item_name(proc_def_id),
+                self.env.tcx().hir.span_if_local(proc_def_id),
+                "the body is not encoded and the postconditions are assumed",
+            );
+            true
+        } else if self.is_trusted(proc_def_id) {
+            self.trusted_surface().register(
+                TrustedItemKind::TrustedPureFunction,
+                self.env.get_absolute_item_name(proc_def_id),
+                self.env.tcx().hir.span_if_local(proc_def_id),
+                "the body is not verified and the postconditions are assumed",
+            );
+            true
+        } else {
+            false
+        };
+        let pure_function_encoders: Vec<_> = match self.resolve_typaram(mir.return_ty()).sty {
+            ty::TypeVariants::TyTuple(component_tys) => (0..component_tys.len())
+                .map(|component| {
+                    PureFunctionEncoder::new_component(self, proc_def_id, mir, component)
+                })
+                .collect(),
+            _ => vec![PureFunctionEncoder::new(self, proc_def_id, mir, false)],
+        };
+        pure_function_encoders
+            .iter()
+            .map(|pure_function_encoder| {
+                if is_bodyless {
+                    pure_function_encoder.encode_bodyless_function()
+                } else {
+                    pure_function_encoder.encode_function()
+                }
+            })
+            .collect()
+    }
+
     pub fn encode_pure_function_use(&self, proc_def_id: ProcedureDefId) -> String {
         trace!("encode_pure_function_use({:?})", proc_def_id);
         assert!(
-            self.env.has_attribute_name(proc_def_id, "pure"),
-            "procedure is not marked as pure: {:?}",
+            self.is_pure(proc_def_id),
+            "procedure is not pure: {:?}",
             proc_def_id
         );
+        if let Some(domain_func) = self.encode_domain_function_use(proc_def_id) {
+            return domain_func.name;
+        }
+        if let Some(model) = self.std_model(proc_def_id) {
+            let arg_tys = self.std_model_arg_tys(proc_def_id);
+            return self.encode_std_model_function_use(model, &arg_tys);
+        }
         self.queue_pure_function_encoding(proc_def_id);
+        if self.is_bodyless_trait_method(proc_def_id) {
+            return self.encode_item_name(proc_def_id);
+        }
         let procedure = self.env.get_procedure(proc_def_id);
         let pure_function_encoder =
             PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
         pure_function_encoder.encode_function_name()
     }
 
+    /// Encode the predicate declared by a `#[predicate]` function, if needed, and return its
+    /// name. The predicate is registered before its body is encoded, so that the body can apply
+    /// it recursively.
+    pub fn encode_user_predicate_use(&self, proc_def_id: ProcedureDefId) -> String {
+        trace!("encode_user_predicate_use({:?})", proc_def_id);
+        assert!(
+            self.is_predicate(proc_def_id),
+            "procedure is not a predicate: {:?}",
+            proc_def_id
+        );
+        let predicate_name =
+            vir::Predicate::user_predicate_name(&self.encode_item_name(proc_def_id));
+        if !self.user_predicates.borrow().contains_key(&predicate_name) {
+            self.user_predicates
+                .borrow_mut()
+                .insert(predicate_name.clone(), None);
+            let procedure = self.env.get_procedure(proc_def_id);
+            let pure_function_encoder =
+                PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), true);
+            let predicate = pure_function_encoder.encode_predicate();
+            self.log_vir_program_before_viper(predicate.to_string());
+            self.user_predicates
+                .borrow_mut()
+                .insert(predicate_name.clone(), Some(predicate));
+        }
+        predicate_name
+    }
+
+    /// Encode the use of the function of the component `component` of the tuple returned by a
+    /// pure function.
+    pub fn encode_pure_function_component_use(
+        &self,
+        proc_def_id: ProcedureDefId,
+        component: usize,
+    ) -> String {
+        let function_name = self.encode_pure_function_use(proc_def_id);
+        encode_component_function_name(&function_name, component)
+    }
+
     pub fn encode_pure_function_return_type(&self, proc_def_id: ProcedureDefId) -> vir::Type {
         trace!("encode_pure_function_return_type({:?})", proc_def_id);
         assert!(
-            self.env.has_attribute_name(proc_def_id, "pure"),
-            "procedure is not marked as pure: {:?}",
+            self.is_pure(proc_def_id),
+            "procedure is not pure: {:?}",
             proc_def_id
         );
+        if let Some(model) = self.std_model(proc_def_id) {
+            return encode_std_model_return_type(model);
+        }
+        if let Some(domain_func) = self.encode_domain_function_use(proc_def_id) {
+            return domain_func.return_type;
+        }
+        if self.is_bodyless_trait_method(proc_def_id) {
+            let return_ty = self.env.tcx().fn_sig(proc_def_id).output().skip_binder();
+            return self.encode_value_type(self.resolve_typaram(return_ty));
+        }
         let procedure = self.env.get_procedure(proc_def_id);
         let pure_function_encoder =
             PureFunctionEncoder::new(self, proc_def_id, procedure.get_mir(), false);
@@ -1210,6 +1869,314 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         pure_function_encoder.encode_function_return_type()
     }
 
+    /// Is the type a struct marked with `#[domain]`, whose values are the values of a domain?
+    pub fn is_domain_type(&self, ty: ty::Ty<'tcx>) -> bool {
+        is_domain_type(self, ty)
+    }
+
+    /// Encode the domain of the values of a domain type, and return its name.
+    pub fn encode_domain_use(&self, ty: ty::Ty<'tcx>) -> String {
+        let domain_name = encode_domain_name(self, ty);
+        if !self.domains.borrow().contains_key(&domain_name) {
+            // The domain is registered before its functions are encoded, because their types
+            // may use it, and the functions are registered before the axioms, which use them.
+            self.domains
+                .borrow_mut()
+                .insert(domain_name.clone(), vir::Domain::new(domain_name.clone()));
+            let functions = encode_domain_functions(self, ty, &domain_name);
+            self.domains.borrow_mut().get_mut(&domain_name).unwrap().functions =
+                functions.iter().map(|(_, function)| function.clone()).collect();
+            let axioms = encode_domain_axioms(self, &functions);
+            let mut domains = self.domains.borrow_mut();
+            let domain = domains.get_mut(&domain_name).unwrap();
+            domain.axioms = axioms;
+            self.log_vir_program_before_viper(domain.to_string());
+        }
+        domain_name
+    }
+
+    /// Are the values of the type compared by snapshot?
+    pub fn is_snapshot_type(&self, ty: ty::Ty<'tcx>) -> bool {
+        is_snapshot_type(self, ty)
+    }
+
+    /// Encode the domain and the function of the snapshots of the struct `ty`, and return the
+    /// type of the snapshots.
+    pub fn encode_snapshot_type(&self, ty: ty::Ty<'tcx>) -> vir::Type {
+        let domain_name = encode_snapshot_domain_name(self, ty);
+        if !self.domains.borrow().contains_key(&domain_name) {
+            let domain = encode_snapshot_domain(self, ty);
+            self.log_vir_program_before_viper(domain.to_string());
+            self.domains.borrow_mut().insert(domain_name.clone(), domain);
+            let function = encode_snapshot_function(self, ty);
+            self.log_vir_program_before_viper(function.to_string());
+            self.snapshot_functions
+                .borrow_mut()
+                .insert(function.name.clone(), function);
+        }
+        vir::Type::Domain(domain_name)
+    }
+
+    /// Encode the snapshot of the value of the struct `ty` at `place`.
+    pub fn encode_snapshot_use(&self, place: vir::Expr, ty: ty::Ty<'tcx>) -> vir::Expr {
+        self.encode_snapshot_type(ty);
+        encode_snapshot_func_app(self, place, ty)
+    }
+
+    /// Encode the structural equality of the values of type `ty` at `left` and `right`, if the
+    /// values of the type have snapshots.
+    pub fn encode_snapshot_eq(
+        &self,
+        left: vir::Expr,
+        right: vir::Expr,
+        ty: ty::Ty<'tcx>,
+    ) -> Option<vir::Expr> {
+        encode_snapshot_eq(self, left, right, ty)
+    }
+
+    /// Encode the domain of the addresses of the memory regions owned through raw pointers,
+    /// if it is not encoded yet.
+    pub fn encode_region_domain_use(&self) {
+        if !self.domains.borrow().contains_key(REGION_DOMAIN_NAME) {
+            let domain = encode_region_domain();
+            self.log_vir_program_before_viper(domain.to_string());
+            self.domains
+                .borrow_mut()
+                .insert(REGION_DOMAIN_NAME.to_string(), domain);
+        }
+    }
+
+    /// Encode the function that looks up the values stored in the field `value_field` of the
+    /// memory regions, if it is not encoded yet, and return its name.
+    pub fn encode_region_lookup_function_use(&self, value_field: vir::Field) -> String {
+        let function_name = lookup_function_name(&value_field);
+        if !self.region_lookup_functions.borrow().contains_key(&function_name) {
+            let function = encode_lookup_function(value_field);
+            self.log_vir_program_before_viper(function.to_string());
+            self.region_lookup_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Add the model of a bodyless pure function, with its axiom, to the `BodylessFunctions`
+    /// domain.
+    pub fn register_bodyless_function_model(
+        &self,
+        model: vir::DomainFunc,
+        axiom: vir::DomainAxiom,
+    ) {
+        let mut domains = self.domains.borrow_mut();
+        let domain = domains
+            .entry(BODYLESS_FUNCTIONS_DOMAIN_NAME.to_string())
+            .or_insert_with(|| vir::Domain::new(BODYLESS_FUNCTIONS_DOMAIN_NAME));
+        // A function that is encoded for several type substitutions has a single model.
+        if !domain.functions.iter().any(|function| function.name == model.name) {
+            self.log_vir_program_before_viper(format!("{}\n{}", model, axiom));
+            domain.functions.push(model);
+            domain.axioms.push(axiom);
+        }
+    }
+
+    /// The function of a domain that encodes the pure method, if the method belongs to a
+    /// domain type and can be a function of the domain.
+    pub fn encode_domain_function_use(
+        &self,
+        proc_def_id: ProcedureDefId,
+    ) -> Option<vir::DomainFunc> {
+        let domain_ty = domain_type_of_function(self, proc_def_id)?;
+        let domain_name = self.encode_domain_use(domain_ty);
+        let function_name = self.encode_item_name(proc_def_id);
+        self.domains.borrow()[&domain_name]
+            .functions
+            .iter()
+            .find(|function| function.name == function_name)
+            .cloned()
+    }
+
+    /// Encode the function of a pure model of the standard library, for arguments of types
+    /// `arg_tys`, and return its name.
+    pub fn encode_std_model_function_use(
+        &self,
+        model: StdModel,
+        arg_tys: &[ty::Ty<'tcx>],
+    ) -> String {
+        let function_name = encode_std_model_function_name(self, model, arg_tys);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_std_model_function(self, model, arg_tys);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Encode the function that abstracts a map of type `map_ty` to a mathematical map, and
+    /// return its name.
+    pub fn encode_map_abstraction_use(&self, map_ty: ty::Ty<'tcx>) -> String {
+        let function_name = encode_map_abstraction_name(self, map_ty);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_map_abstraction(self, map_ty);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Encode the function that abstracts a string of type `string_ty` to the sequence of its
+    /// bytes, and return its name.
+    pub fn encode_str_abstraction_use(&self, string_ty: ty::Ty<'tcx>) -> String {
+        let function_name = encode_str_abstraction_name(self, string_ty);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_str_abstraction(self, string_ty);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Encode the function that abstracts a vector or a slice of type `seq_ty` to the sequence of
+    /// its elements, and return its name.
+    pub fn encode_seq_abstraction_use(&self, seq_ty: ty::Ty<'tcx>) -> String {
+        let function_name = encode_seq_abstraction_name(self, seq_ty);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_seq_abstraction(self, seq_ty);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Encode the function that abstracts an iterator of type `iter_ty` to its ghost index, and
+    /// return its name.
+    pub fn encode_iter_index_use(&self, iter_ty: ty::Ty<'tcx>) -> String {
+        let function_name = encode_iter_index_name(self, iter_ty);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_iter_index(self, iter_ty);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        function_name
+    }
+
+    /// Encode the function of the value of its argument that encodes the closure
+    /// `closure_def_id`, and return its name. `None` if the closure is not supported, see
+    /// `is_pure_closure`.
+    pub fn encode_closure_function_use(&self, closure_def_id: DefId) -> Option<String> {
+        if !is_pure_closure(self, closure_def_id) {
+            return None;
+        }
+        let function_name = encode_closure_function_name(self, closure_def_id);
+        if !self.std_model_functions.borrow().contains_key(&function_name) {
+            let function = encode_closure_function(self, closure_def_id)?;
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(function_name.clone(), function);
+        }
+        Some(function_name)
+    }
+
+    /// Encode the domain of the permutations of the sequences of elements, if it is not encoded
+    /// yet.
+    pub fn encode_permutation_domain_use(&self) {
+        if !self.domains.borrow().contains_key(PERMUTATION_DOMAIN_NAME) {
+            let domain = encode_permutation_domain();
+            self.log_vir_program_before_viper(domain.to_string());
+            self.domains
+                .borrow_mut()
+                .insert(PERMUTATION_DOMAIN_NAME.to_string(), domain);
+        }
+    }
+
+    /// Encode a string literal with the given content.
+    pub fn encode_str_literal_use(&self, content: String) -> vir::Expr {
+        if !self.std_model_functions.borrow().contains_key(STR_LITERAL_FUNCTION_NAME) {
+            let function = encode_str_literal_function(self);
+            self.log_vir_program_before_viper(function.to_string());
+            self.std_model_functions
+                .borrow_mut()
+                .insert(STR_LITERAL_FUNCTION_NAME.to_string(), function);
+        }
+        let function = &self.std_model_functions.borrow()[STR_LITERAL_FUNCTION_NAME];
+        vir::Expr::func_app(
+            function.name.clone(),
+            vec![vir::Expr::Const(vir::Const::Str(content), vir::Position::default())],
+            function.formal_args.clone(),
+            function.return_type.clone(),
+            vir::Position::default(),
+        )
+    }
+
+    /// Encode the unknown value of type `ty` that replaces the argument of a call of
+    /// `unsafe_spec` in a specification. Each encoding of the call gets a fresh value,
+    /// returned by a function without body.
+    pub fn encode_unsafe_spec_use(&self, ty: ty::Ty<'tcx>, span: Span) -> vir::Expr {
+        let expr_snippet = self
+            .env
+            .codemap()
+            .span_to_snippet(span)
+            .unwrap_or_else(|_| "unsafe_spec(..)".to_string());
+        self.trusted_surface().register(
+            TrustedItemKind::UnsafeSpecExpression,
+            expr_snippet,
+            Some(span),
+            "the subexpression of the specification is replaced by an unknown value",
+        );
+        let mut unsafe_spec_functions = self.unsafe_spec_functions.borrow_mut();
+        let function = vir::Function {
+            name: format!("unsafe_spec${}", unsafe_spec_functions.len()),
+            formal_args: vec![],
+            return_type: self.encode_value_type(ty),
+            pres: vec![],
+            posts: vec![],
+            body: None,
+        };
+        let pos = self.error_manager().register_span(span);
+        let expr = vir::Expr::func_app(
+            function.name.clone(),
+            vec![],
+            vec![],
+            function.return_type.clone(),
+            pos,
+        );
+        unsafe_spec_functions.push(function);
+        expr
+    }
+
+    /// Does the encoding use the mathematical maps of the Viper map domain?
+    pub fn uses_viper_maps(&self) -> bool {
+        self.std_model_functions
+            .borrow()
+            .values()
+            .any(|function| function.return_type.get_id() == vir::TypeId::Map)
+    }
+
+    /// The types of the arguments of a modelled method, behind the references. The first
+    /// argument is the receiver.
+    fn std_model_arg_tys(&self, proc_def_id: ProcedureDefId) -> Vec<ty::Ty<'tcx>> {
+        let fn_sig = self.env.tcx().fn_sig(proc_def_id);
+        fn_sig
+            .skip_binder()
+            .inputs()
+            .iter()
+            .map(|&arg_ty| match self.substitute_and_normalize(arg_ty).sty {
+                ty::TypeVariants::TyRef(_, inner_ty, _) => inner_ty,
+                ref x => unreachable!("the argument is not a reference: {:?}", x),
+            })
+            .collect()
+    }
+
     pub fn queue_procedure_encoding(&self, proc_def_id: ProcedureDefId) {
         self.encoding_queue
             .borrow_mut()
@@ -1232,8 +2199,11 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                 "Encoding: {} from {:?} ({})",
                 proc_name, proc_span, proc_def_path
             );
-            let is_pure_function = self.env.has_attribute_name(proc_def_id, "pure");
-            if is_pure_function {
+            let is_pure_function = self.is_pure(proc_def_id);
+            if let Some(domain_ty) = domain_type_of_function(self, proc_def_id) {
+                // The functions of a domain are encoded with the domain, without body.
+                self.encode_domain_use(domain_ty);
+            } else if is_pure_function {
                 self.encode_pure_function_def(proc_def_id, substs);
             } else {
                 assert!(substs.is_empty());
@@ -1242,6 +2212,18 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                         "Trusted procedure will not be encoded or verified: {:?}",
                         proc_def_id
                     );
+                    self.trusted_surface().register(
+                        TrustedItemKind::TrustedProcedure,
+                        proc_name,
+                        Some(proc_span),
+                        "the body is not verified",
+                    );
+                } else if let Some(reasons) = unsupported_features(self, proc_def_id) {
+                    debug!(
+                        "Procedure with unsupported features will not be encoded: {:?}",
+                        proc_def_id
+                    );
+                    report_unsupported_procedure(self, proc_def_id, &reasons);
                 } else {
                     self.encode_procedure(proc_def_id);
                 }
@@ -1249,6 +2231,70 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         }
     }
 
+    /// Is the procedure marked as `#[pure]` or, if `INFER_PURITY` is enabled, inferred to
+    /// be pure?
+    pub fn is_pure(&self, def_id: ProcedureDefId) -> bool {
+        if self.env().has_attribute_name(def_id, "pure") || self.is_spec_function(def_id) {
+            return true;
+        }
+        if let Some(model) = self.std_model(def_id) {
+            return model.is_pure();
+        }
+        if !config::infer_purity() {
+            return false;
+        }
+        if let Some(&result) = self.inferred_purity.borrow().get(&def_id) {
+            return result;
+        }
+        // Assume that the procedure is impure while analysing it, to reject recursion.
+        self.inferred_purity.borrow_mut().insert(def_id, false);
+        let result = infer_purity(self, def_id);
+        debug!("inferred purity of {:?}: {}", def_id, result);
+        self.inferred_purity.borrow_mut().insert(def_id, result);
+        result
+    }
+
+    /// The procedures without `#[pure]` annotation that have been inferred to be pure.
+    pub fn get_inferred_pure_functions(&self) -> Vec<ProcedureDefId> {
+        self.inferred_purity
+            .borrow()
+            .iter()
+            .filter(|&(_, &is_pure)| is_pure)
+            .map(|(&def_id, _)| def_id)
+            .collect()
+    }
+
+    /// Is the procedure a `#[pure_spec]` function, which can only be used in specifications?
+    pub fn is_spec_function(&self, def_id: ProcedureDefId) -> bool {
+        self.env().has_attribute_name(def_id, "pure_spec")
+    }
+
+    /// Is the procedure a `#[predicate]` function, whose applications in the specifications are
+    /// the permissions to a predicate?
+    pub fn is_predicate(&self, def_id: ProcedureDefId) -> bool {
+        self.env().has_attribute_name(def_id, "predicate")
+    }
+
+    /// Reject a call from executable code, at `span`, if the called procedure is a
+    /// `#[pure_spec]` or a `#[predicate]` function.
+    pub fn check_executable_call(&self, called_def_id: ProcedureDefId, span: Span) {
+        if self.is_spec_function(called_def_id) || self.is_predicate(called_def_id) {
+            self.env.span_err(
+                span,
+                &format!(
+                    "[Prusti] the specification function `{}` can only be used in specifications",
+                    self.env.get_item_name(called_def_id)
+                ),
+            );
+        }
+    }
+
+    /// Is the procedure a method of a trait without a default body (thus without MIR)?
+    fn is_bodyless_trait_method(&self, def_id: ProcedureDefId) -> bool {
+        let tcx = self.env().tcx();
+        tcx.trait_of_item(def_id).is_some() && !tcx.associated_item(def_id).defaultness.has_value()
+    }
+
     pub fn is_trusted(&self, def_id: ProcedureDefId) -> bool {
         trace!("is_trusted {:?}", def_id);
         let result = self.env().has_attribute_name(def_id, "trusted");
@@ -1264,9 +2310,49 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
                 return replaced_ty.clone();
             }
         }
+        if let ty::TypeVariants::TyProjection(_) = ty.sty {
+            return self.substitute_and_normalize(ty);
+        }
         ty
     }
 
+    /// The type of a field of an ADT. The associated types of the field that are concrete
+    /// (e.g. the `<B as ToOwned>::Owned` field of a `Cow<B>`) are normalized, so that the field
+    /// has the same type in the predicate of the ADT and in the places of the MIR.
+    pub fn resolve_field_ty(
+        &self,
+        field: &ty::FieldDef,
+        subst: &ty::subst::Substs<'tcx>,
+    ) -> ty::Ty<'tcx> {
+        let tcx = self.env().tcx();
+        let field_ty = field.ty(tcx, subst);
+        if field_ty.has_projections()
+            && !field_ty.has_param_types()
+            && !field_ty.has_escaping_regions()
+        {
+            tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), field_ty)
+        } else {
+            field_ty
+        }
+    }
+
+    /// Replace the type parameters in a type (e.g. an associated type, including the parameters
+    /// of a generic associated type) and normalize it, if it becomes concrete. Otherwise, an
+    /// associated type is encoded abstractly, like a type parameter.
+    fn substitute_and_normalize(&self, ty: ty::Ty<'tcx>) -> ty::Ty<'tcx> {
+        let tcx = self.env().tcx();
+        let tymap = self.current_tymap();
+        let substituted_ty = ty.fold_with(&mut BottomUpFolder {
+            tcx,
+            fldop: |inner_ty| tymap.get(&inner_ty).cloned().unwrap_or(inner_ty),
+        });
+        if substituted_ty.has_param_types() || substituted_ty.has_escaping_regions() {
+            substituted_ty
+        } else {
+            tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), substituted_ty)
+        }
+    }
+
     /// Merges the stack of type maps into a single map.
     pub fn current_tymap(&self) -> HashMap<ty::Ty<'tcx>, ty::Ty<'tcx>> {
         let mut map = HashMap::new();
@@ -1286,21 +2372,18 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
         map
     }
 
-    /// TODO: This is a hack, it generates strings that can be used to instantiate generic pure
-    /// functions.
-    pub fn type_substitution_strings(&self) -> HashMap<String, String> {
+    /// The instantiations of the type variables of the current type substitution, which are used
+    /// to instantiate generic pure functions. The type variables are identified by the names of
+    /// their predicates.
+    pub fn type_substitution(&self) -> HashMap<String, vir::Type> {
         self.current_tymap()
             .iter()
             .map(|(typ, subst)| {
                 let encoded_typ = match self.encode_type(typ) {
-                    vir::Type::TypedRef(s) => s.clone(),
-                    x => unreachable!("{:?}", x),
-                };
-                let encoded_subst = match self.encode_type(subst) {
-                    vir::Type::TypedRef(s) => s.clone(),
+                    vir::Type::TypedRef(s, _) => s,
                     x => unreachable!("{:?}", x),
                 };
-                (encoded_typ, encoded_subst)
+                (encoded_typ, self.encode_type(subst))
             })
             .collect()
     }
@@ -1309,8 +2392,9 @@ impl<'v, 'r, 'a, 'tcx> Encoder<'v, 'r, 'a, 'tcx> {
     /// type substitution.
     pub fn type_substitution_key(&self) -> String {
         let mut substs: Vec<_> = self
-            .type_substitution_strings()
+            .type_substitution()
             .into_iter()
+            .map(|(typ, subst)| (typ, subst.name()))
             .filter(|(typ, subst)| typ != subst)
             .map(|(typ, subst)| format!("({},{})", typ, subst))
             .collect();
@@ -4,12 +4,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use encoder::vir::Position;
-use std::collections::HashMap;
+use demangle::demangle_text;
+use encoder::vir::{Position, PositionId};
+use prusti_interface::config;
+use prusti_interface::report::json;
+use std::collections::{HashMap, HashSet};
 use syntax::codemap::CodeMap;
-use syntax_pos::MultiSpan;
-use uuid::Uuid;
-use viper::VerificationError;
+use syntax_pos::{MultiSpan, Span};
+use viper::{VerificationBackend, VerificationError};
 
 /// The cause of a panic!()
 #[derive(Clone, Debug)]
@@ -24,6 +26,19 @@ pub enum PanicCause {
     Unreachable,
     /// Caused by an unimplemented!()
     Unimplemented,
+    /// Caused by a conflicting call of `RefCell::borrow` or `RefCell::borrow_mut`
+    DynamicBorrow,
+}
+
+/// The program point whose reachability is checked when `CHECK_UNREACHABILITY` is enabled
+#[derive(Clone, Debug)]
+pub enum ReachabilityCheckKind {
+    /// The beginning of a procedure, after its precondition
+    Precondition,
+    /// The target of a branch
+    Branch,
+    /// The end of a procedure
+    ProcedureEnd,
 }
 
 /// In case of verification error, this enum will contain additional information
@@ -40,6 +55,9 @@ pub enum ErrorCtxt {
     AssertMethodPostconditionTypeInvariants,
     /// A Viper `exhale expr` that encodes the end of a Rust procedure with postcondition `expr`
     ExhaleMethodPostcondition,
+    /// A Viper `assert expr` that asserts the type invariant `expr` of a value with a
+    /// destructor when the value is dropped
+    DropTypeInvariant,
     /// A Viper `exhale expr` that exhales the permissions of a loop invariant `expr`
     ExhaleLoopInvariantOnEntry,
     ExhaleLoopInvariantAfterIteration,
@@ -57,10 +75,12 @@ pub enum ErrorCtxt {
     /// An error that should never happen
     Unexpected,
     /// A pure function definition
-    #[allow(dead_code)]
     PureFunctionDefinition,
     /// A pure function call
     PureFunctionCall,
+    /// A precondition of the limited version of a recursive pure function, which checks that
+    /// the termination measure decreases at a recursive call
+    PureFunctionTerminationMeasure,
     /// An expression that encodes the value range of the result of a pure function
     PureFunctionPostconditionValueRangeOfResult,
     /// A Viper function with `false` precondition that encodes the failure (panic) of an
@@ -79,12 +99,41 @@ pub enum ErrorCtxt {
     DivergingCallInPureFunction,
     /// A Viper pure function call with `false` precondition that encodes a Rust panic in a pure function
     PanicInPureFunction(PanicCause),
+    /// A Viper `assert false` that encodes a call of `RefCell::borrow` or `RefCell::borrow_mut`
+    /// that might violate the dynamic borrow rules
+    DynamicBorrowConflict,
+    /// A Viper `assert expr` that encodes a `prusti_assert!(expr)` statement
+    PrustiAssertion,
+    /// A Viper `assert expr` that encodes a `prusti_refute!(expr)` statement. The
+    /// assertion is expected to fail.
+    PrustiRefutation,
+    /// A Viper `assert false` that checks that a program point is reachable. The
+    /// assertion is expected to fail.
+    ReachabilityCheck(ReachabilityCheckKind),
     /// A Viper `assert e1 ==> e2` that encodes a weakening of the precondition
     /// of a method implementation of a trait
     AssertMethodPreconditionWeakening(MultiSpan),
     /// A Viper `assert e1 ==> e2` that encodes a strengthening of the precondition
     /// of a method implementation of a trait.
     AssertMethodPostconditionStrengthening(MultiSpan),
+    /// A Viper `assert acc(loc.f)` that checks that the access through a raw pointer is in a
+    /// memory region owned by a `deref_spec`
+    DerefSpecAccess,
+    /// A Viper `fold acc(P(x))` that encodes a `fold!(P(x))` statement
+    FoldPredicate,
+    /// A Viper `assert acc(P(x))` that checks that the predicate is held before the
+    /// `unfold acc(P(x))` that encodes an `unfold!(P(x))` statement
+    UnfoldPredicate,
+}
+
+/// A suggestion of how to fix a verification error.
+#[derive(Clone, Debug)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    /// The code that should replace `span`. If `None`, the suggestion is
+    /// reported as a help message, because it cannot be applied automatically.
+    pub code: Option<String>,
 }
 
 /// The Rust error that will be reported from the compiler
@@ -94,6 +143,12 @@ pub struct CompilerError {
     pub span: MultiSpan,
     pub help: Option<String>,
     pub note: Option<(String, MultiSpan)>,
+    /// A structured suggestion: message, span to replace and replacement.
+    pub suggestion: Option<(String, Span, String)>,
+    /// The identifier of the verification error, e.g. `assert.failed:assertion.false`.
+    pub kind: Option<String>,
+    /// The identifier of the VIR position reported by the verifier.
+    pub pos_id: Option<String>,
 }
 
 impl CompilerError {
@@ -103,6 +158,9 @@ impl CompilerError {
             span,
             help: None,
             note: None,
+            suggestion: None,
+            kind: None,
+            pos_id: None,
         }
     }
 
@@ -111,6 +169,25 @@ impl CompilerError {
         self
     }
 
+    pub fn set_suggestion(mut self, suggestion: &Suggestion) -> Self {
+        match suggestion.code {
+            Some(ref code) => {
+                self.suggestion = Some((
+                    suggestion.message.clone(),
+                    suggestion.span,
+                    code.clone(),
+                ));
+            }
+            None => {
+                self.help = Some(match self.help {
+                    Some(help) => format!("{}\n{}", help, suggestion.message),
+                    None => suggestion.message.clone(),
+                });
+            }
+        }
+        self
+    }
+
     /// Set the span of the failing assertion expression.
     ///
     /// Note: this is a noop if `opt_span` is None
@@ -121,6 +198,25 @@ impl CompilerError {
         self
     }
 
+    /// Record the verification error from which this error has been translated.
+    pub fn set_origin(mut self, ver_error: &VerificationError) -> Self {
+        self.kind = Some(ver_error.full_id.clone());
+        self.pos_id = ver_error.pos_id.clone();
+        self
+    }
+
+    /// Emit the error as a JSON message, if enabled by `config::message_format()`.
+    pub fn report_json(&self, codemap: &CodeMap) {
+        json::error(
+            codemap,
+            self.span.primary_span(),
+            self.kind.as_ref().map(|kind| kind.as_str()),
+            &self.message,
+            self.help.as_ref().map(|help| help.as_str()),
+            self.pos_id.as_ref().map(|pos_id| pos_id.as_str()),
+        );
+    }
+
     /// Convert the original error span to a note, and add a new error span.
     ///
     /// Note: this is a noop if `opt_span` is None
@@ -137,16 +233,28 @@ impl CompilerError {
 #[derive(Clone)]
 pub struct ErrorManager<'tcx> {
     codemap: &'tcx CodeMap,
-    source_span: HashMap<String, MultiSpan>,
-    error_contexts: HashMap<String, ErrorCtxt>,
+    /// The span of each registered position, indexed by the position identifier.
+    /// Identifiers are assigned in order, so that encoding the same crate twice
+    /// produces the same program.
+    source_span: Vec<MultiSpan>,
+    error_contexts: HashMap<PositionId, ErrorCtxt>,
+    /// The positions of the parts of a postcondition that are about a single
+    /// component of the returned tuple.
+    result_components: HashMap<PositionId, usize>,
+    suggestions: HashMap<PositionId, Suggestion>,
+    /// The messages given by the user to the panics, indexed by the position of the panic.
+    panic_messages: HashMap<PositionId, String>,
 }
 
 impl<'tcx> ErrorManager<'tcx> {
     pub fn new(codemap: &'tcx CodeMap) -> Self {
         ErrorManager {
             codemap,
-            source_span: HashMap::new(),
+            source_span: Vec::new(),
             error_contexts: HashMap::new(),
+            result_components: HashMap::new(),
+            suggestions: HashMap::new(),
+            panic_messages: HashMap::new(),
         }
     }
 
@@ -158,8 +266,8 @@ impl<'tcx> ErrorManager<'tcx> {
 
     pub fn register_span<T: Into<MultiSpan>>(&mut self, span: T) -> Position {
         let span = span.into();
-        let pos_id = Uuid::new_v4().to_hyphenated().to_string();
-        debug!("Register position {:?} at span {:?}", pos_id, span);
+        let pos_id = PositionId::new(self.source_span.len() as u32);
+        debug!("Register position {} at span {:?}", pos_id, span);
         let pos = if let Some(primary_span) = span.primary_span() {
             let lines_info = self
                 .codemap
@@ -168,30 +276,207 @@ impl<'tcx> ErrorManager<'tcx> {
             let first_line_info = lines_info.lines.get(0).unwrap();
             let line = first_line_info.line_index as i32 + 1;
             let column = first_line_info.start_col.0 as i32 + 1;
-            Position::new(line, column, pos_id.clone())
+            Position::new(line, column, pos_id)
         } else {
-            Position::new(0, 0, pos_id.clone())
+            Position::new(0, 0, pos_id)
         };
-        self.source_span.insert(pos_id, span);
+        self.source_span.push(span);
         pos
     }
 
+    /// Register a copy of the position `pos`, marking that the expression at
+    /// that position is about the `component`-th element of the returned tuple.
+    pub fn register_result_component(&mut self, pos: &Position, component: usize) -> Position {
+        let span = self
+            .get_source_span(pos.id())
+            .cloned()
+            .unwrap_or_else(|| MultiSpan::new());
+        let new_pos = self.register_span(span);
+        self.result_components.insert(new_pos.id(), component);
+        new_pos
+    }
+
     pub fn register_error(&mut self, pos: &Position, error_ctxt: ErrorCtxt) {
-        debug!("Register error at: {:?}", pos.id());
+        debug!("Register error at: {}", pos.id());
         self.error_contexts.insert(pos.id(), error_ctxt);
     }
 
+    /// Register a suggestion, reported if the verification fails at position `pos`.
+    pub fn register_suggestion(&mut self, pos: &Position, suggestion: Suggestion) {
+        debug!("Register suggestion at: {}", pos.id());
+        self.suggestions.insert(pos.id(), suggestion);
+    }
+
+    /// Register the message of the panic at position `pos`, i.e. the string literal that is
+    /// passed to the panic machinery. The message is reported if the verification fails at
+    /// `pos`, unless it is the default message of the macro.
+    pub fn register_panic_message(&mut self, pos: &Position, literal: &str) {
+        if let Some(message) = custom_panic_message(literal) {
+            debug!("Register panic message at: {}", pos.id());
+            self.panic_messages.insert(pos.id(), message);
+        }
+    }
+
+    /// Build an error that is detected while encoding, thus before the verification,
+    /// reported at the span of the registered position `pos`.
+    pub fn encoding_error<S: ToString>(&self, pos: &Position, message: S) -> CompilerError {
+        let span = self
+            .get_source_span(pos.id())
+            .cloned()
+            .unwrap_or_else(|| MultiSpan::new());
+        CompilerError::new(message, span)
+    }
+
+    /// The span of a registered position.
+    fn get_source_span(&self, pos_id: PositionId) -> Option<&MultiSpan> {
+        self.source_span.get(pos_id.index())
+    }
+
+    /// The span of a position reported by the verifier.
+    fn get_reported_source_span(&self, pos_id: &str) -> Option<&MultiSpan> {
+        PositionId::parse(pos_id).and_then(|pos_id| self.get_source_span(pos_id))
+    }
+
+    /// Whether the verification error is the expected failure of an assertion, such as the one
+    /// that encodes a `prusti_refute!` statement or a reachability check.
+    pub fn is_expected_failure(&self, ver_error: &VerificationError) -> bool {
+        if ver_error.full_id != "assert.failed:assertion.false" {
+            return false;
+        }
+        let opt_error_ctxt = ver_error
+            .pos_id
+            .as_ref()
+            .and_then(|pos_id| PositionId::parse(pos_id))
+            .and_then(|pos_id| self.error_contexts.get(&pos_id));
+        match opt_error_ctxt {
+            Some(ErrorCtxt::PrustiRefutation) | Some(ErrorCtxt::ReachabilityCheck(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the failing part of a precondition is the check of a termination measure.
+    fn is_termination_measure_failure(&self, ver_error: &VerificationError) -> bool {
+        let opt_reason_ctxt = ver_error
+            .reason_pos_id
+            .as_ref()
+            .and_then(|reason_pos_id| PositionId::parse(reason_pos_id))
+            .and_then(|reason_pos_id| self.error_contexts.get(&reason_pos_id));
+        match opt_reason_ctxt {
+            Some(ErrorCtxt::PureFunctionTerminationMeasure) => true,
+            _ => false,
+        }
+    }
+
+    /// The registered positions of the assertions that were expected to fail but did not,
+    /// given the verification errors of the failed ones, in the order in which they were
+    /// registered.
+    fn unfailed_positions<'a>(
+        &'a self,
+        expected_failures: &[VerificationError],
+    ) -> Vec<(PositionId, &'a ErrorCtxt)> {
+        let failed_pos_ids: HashSet<PositionId> = expected_failures
+            .iter()
+            .filter_map(|ver_error| ver_error.pos_id.as_ref())
+            .filter_map(|pos_id| PositionId::parse(pos_id))
+            .collect();
+        let mut positions: Vec<_> = self
+            .error_contexts
+            .iter()
+            .map(|(&pos_id, error_ctxt)| (pos_id, error_ctxt))
+            .filter(|(pos_id, _)| !failed_pos_ids.contains(pos_id))
+            .collect();
+        positions.sort_by_key(|&(pos_id, _)| pos_id);
+        positions
+    }
+
+    /// Build an error for each registered `prusti_refute!` statement whose assertion did not
+    /// fail, given the verification errors of the expected failures.
+    pub fn unrefuted_errors(&self, expected_failures: &[VerificationError]) -> Vec<CompilerError> {
+        self.unfailed_positions(expected_failures)
+            .into_iter()
+            .filter_map(|(pos_id, error_ctxt)| match error_ctxt {
+                ErrorCtxt::PrustiRefutation => Some(pos_id),
+                _ => None,
+            })
+            .map(|pos_id| {
+                CompilerError::new(
+                    "the refuted expression holds in all the executions that reach this point",
+                    self.get_source_span(pos_id).cloned().unwrap_or_else(|| MultiSpan::new()),
+                ).set_help(
+                    "a refutation of `false` is reported only if this point is unreachable, \
+                    for example because of contradictory assumptions"
+                )
+            })
+            .collect()
+    }
+
+    /// Build a warning for each registered reachability check whose assertion did not fail,
+    /// given the verification errors of the expected failures.
+    pub fn unreachability_warnings(
+        &self,
+        expected_failures: &[VerificationError],
+    ) -> Vec<CompilerError> {
+        self.unfailed_positions(expected_failures)
+            .into_iter()
+            .filter_map(|(pos_id, error_ctxt)| {
+                let message = match error_ctxt {
+                    ErrorCtxt::ReachabilityCheck(ReachabilityCheckKind::Precondition) => {
+                        "the precondition of the procedure is unsatisfiable, thus the \
+                        procedure is verified vacuously"
+                    }
+                    ErrorCtxt::ReachabilityCheck(ReachabilityCheckKind::Branch) => {
+                        "this branch is unreachable, thus it is verified vacuously"
+                    }
+                    ErrorCtxt::ReachabilityCheck(ReachabilityCheckKind::ProcedureEnd) => {
+                        "the end of the procedure is unreachable, thus its postcondition \
+                        is verified vacuously"
+                    }
+                    _ => return None,
+                };
+                let span = self
+                    .get_source_span(pos_id)
+                    .cloned()
+                    .unwrap_or_else(|| MultiSpan::new());
+                Some(CompilerError::new(message, span))
+            })
+            .collect()
+    }
+
     pub fn translate(&self, ver_error: &VerificationError) -> CompilerError {
+        let mut compiler_error =
+            self.translate_verification_error(ver_error).set_origin(ver_error);
+        let opt_pos_id = ver_error
+            .pos_id
+            .as_ref()
+            .and_then(|pos_id| PositionId::parse(pos_id));
+        if let Some(message) = opt_pos_id.and_then(|pos_id| self.panic_messages.get(&pos_id)) {
+            compiler_error.message = format!("{}: {}", compiler_error.message, message);
+        }
+        let opt_suggestion = opt_pos_id.and_then(|pos_id| self.suggestions.get(&pos_id));
+        match opt_suggestion {
+            Some(suggestion) => compiler_error.set_suggestion(suggestion),
+            None => compiler_error,
+        }
+    }
+
+    fn translate_verification_error(&self, ver_error: &VerificationError) -> CompilerError {
         debug!("Verification error: {:?}", ver_error);
+        if ver_error.full_id == "timeout.occurred" {
+            // Reported by Carbon when Boogie exceeds its time limit
+            return CompilerError::new(
+                format!("the verifier timed out: {}", demangle_text(&ver_error.message)),
+                MultiSpan::new(),
+            ).set_help(unexpected_error_help());
+        }
         let pos_id = &ver_error.pos_id;
         let opt_error_span = pos_id
             .as_ref()
-            .and_then(|pos_id| self.source_span.get(pos_id));
+            .and_then(|pos_id| self.get_reported_source_span(pos_id));
         let opt_cause_span = ver_error
             .reason_pos_id
             .as_ref()
             .and_then(|reason_pos_id| {
-                let res = self.source_span.get(reason_pos_id);
+                let res = self.get_reported_source_span(reason_pos_id);
                 if res.is_none() {
                     debug!("Unregistered reason position: {:?}", reason_pos_id);
                 }
@@ -200,7 +485,8 @@ impl<'tcx> ErrorManager<'tcx> {
 
         let opt_error_ctxt = pos_id
             .as_ref()
-            .and_then(|pos_id| self.error_contexts.get(pos_id));
+            .and_then(|pos_id| PositionId::parse(pos_id))
+            .and_then(|pos_id| self.error_contexts.get(&pos_id));
 
         let (error_span, error_ctxt) = if let Some(error_ctxt) = opt_error_ctxt {
             debug_assert!(opt_error_span.is_some());
@@ -219,27 +505,19 @@ impl<'tcx> ErrorManager<'tcx> {
                     return CompilerError::new(
                         format!(
                             "internal encoding error - unregistered verification error: [{}; {}] {}",
-                            ver_error.full_id, pos_id, ver_error.message
+                            ver_error.full_id, pos_id, demangle_text(&ver_error.message)
                         ),
                         error_span
-                    ).set_help(
-                        "This could be caused by too small assertion timeout. \
-                        Try increasing it by setting the configuration parameter \
-                        ASSERT_TIMEOUT to a larger value."
-                    )
+                    ).set_help(unexpected_error_help())
                 }
                 None => {
                     return CompilerError::new(
                         format!(
                             "internal encoding error - unregistered verification error: [{}] {}",
-                            ver_error.full_id, ver_error.message
+                            ver_error.full_id, demangle_text(&ver_error.message)
                         ),
                         error_span
-                    ).set_help(
-                        "This could be caused by too small assertion timeout. \
-                        Try increasing it by setting the configuration parameter \
-                        ASSERT_TIMEOUT to a larger value."
-                    )
+                    ).set_help(unexpected_error_help())
                 }
             }
         };
@@ -270,6 +548,28 @@ impl<'tcx> ErrorManager<'tcx> {
                     .set_failing_assertion(opt_cause_span)
             }
 
+            ("assert.failed:assertion.false", ErrorCtxt::Panic(PanicCause::DynamicBorrow)) => {
+                CompilerError::new(
+                    "the call might panic, because the RefCell might already be borrowed",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+            }
+
+            ("assert.failed:assertion.false", ErrorCtxt::DynamicBorrowConflict) => {
+                CompilerError::new("the RefCell might already be borrowed", error_span)
+                    .set_failing_assertion(opt_cause_span)
+                    .set_help(
+                        "a RefCell cannot be borrowed mutably while a guard returned by \
+                        `borrow` or `borrow_mut` is alive, nor immutably while a guard \
+                        returned by `borrow_mut` is alive"
+                    )
+            }
+
+            ("assert.failed:assertion.false", ErrorCtxt::PrustiAssertion) => {
+                CompilerError::new("the asserted expression might not hold", error_span)
+                    .set_failing_assertion(opt_cause_span)
+            }
+
             ("assert.failed:assertion.false", ErrorCtxt::AssertTerminator(ref message)) => {
                 CompilerError::new(format!("assertion might fail with \"{}\"", message), error_span)
                     .set_failing_assertion(opt_cause_span)
@@ -294,6 +594,11 @@ impl<'tcx> ErrorManager<'tcx> {
                     .set_failing_assertion(opt_cause_span)
             }
 
+            ("exhale.failed:insufficient.permission", ErrorCtxt::ExhaleMethodPrecondition) => {
+                CompilerError::new(format!("precondition might not hold."), error_span)
+                    .set_failing_assertion(opt_cause_span)
+            }
+
             ("fold.failed:assertion.false", ErrorCtxt::ExhaleMethodPrecondition) => {
                 CompilerError::new(
                     format!(
@@ -308,6 +613,30 @@ impl<'tcx> ErrorManager<'tcx> {
                     .push_primary_span(opt_cause_span)
             }
 
+            ("exhale.failed:insufficient.permission", ErrorCtxt::ExhaleMethodPostcondition) => {
+                CompilerError::new(format!("postcondition might not hold."), error_span)
+                    .push_primary_span(opt_cause_span)
+            }
+
+            ("assert.failed:insufficient.permission", ErrorCtxt::DerefSpecAccess) => {
+                CompilerError::new(
+                    "the access through the raw pointer might not be in a memory region owned \
+                     by a `deref_spec`",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+            }
+
+            ("fold.failed:insufficient.permission", ErrorCtxt::FoldPredicate) |
+            ("fold.failed:assertion.false", ErrorCtxt::FoldPredicate) => {
+                CompilerError::new("the body of the folded predicate might not hold", error_span)
+                    .set_failing_assertion(opt_cause_span)
+            }
+
+            ("assert.failed:insufficient.permission", ErrorCtxt::UnfoldPredicate) => {
+                CompilerError::new("the unfolded predicate might not be held", error_span)
+                    .set_failing_assertion(opt_cause_span)
+            }
+
             ("assert.failed:assertion.false", ErrorCtxt::ExhaleLoopInvariantOnEntry) => {
                 CompilerError::new(format!("loop invariant might not hold on entry."), error_span)
                     .push_primary_span(opt_cause_span)
@@ -339,6 +668,15 @@ impl<'tcx> ErrorManager<'tcx> {
                 ).push_primary_span(opt_cause_span)
             }
 
+            ("application.precondition:assertion.false", ErrorCtxt::PureFunctionCall)
+                if self.is_termination_measure_failure(ver_error) =>
+            {
+                CompilerError::new(
+                    "the termination measure might not decrease at this recursive call",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+            }
+
             ("application.precondition:assertion.false", ErrorCtxt::PureFunctionCall) => {
                 CompilerError::new(
                     format!("precondition of pure function call might not hold."),
@@ -432,8 +770,21 @@ impl<'tcx> ErrorManager<'tcx> {
             }
 
             ("assert.failed:assertion.false", ErrorCtxt::AssertMethodPostcondition) => {
-                CompilerError::new(format!("postcondition might not hold."), error_span)
-                    .push_primary_span(opt_cause_span)
+                let opt_component = ver_error
+                    .reason_pos_id
+                    .as_ref()
+                    .and_then(|reason_pos_id| PositionId::parse(reason_pos_id))
+                    .and_then(|reason_pos_id| self.result_components.get(&reason_pos_id));
+                let error = CompilerError::new(format!("postcondition might not hold."), error_span)
+                    .push_primary_span(opt_cause_span);
+                if let Some(component) = opt_component {
+                    error.set_help(format!(
+                        "The failing part of the postcondition is about `result.{}`.",
+                        component
+                    ))
+                } else {
+                    error
+                }
             }
 
             (
@@ -446,6 +797,13 @@ impl<'tcx> ErrorManager<'tcx> {
                 ).set_failing_assertion(opt_cause_span)
             },
 
+            ("assert.failed:assertion.false", ErrorCtxt::DropTypeInvariant) => {
+                CompilerError::new(
+                    "the type invariant might not hold when the value is dropped",
+                    error_span
+                ).set_failing_assertion(opt_cause_span)
+            }
+
             ("fold.failed:assertion.false", ErrorCtxt::PackageMagicWandForPostcondition) |
             ("fold.failed:assertion.false", ErrorCtxt::AssertMethodPostconditionTypeInvariants) => {
                 CompilerError::new(
@@ -472,16 +830,12 @@ impl<'tcx> ErrorManager<'tcx> {
                 CompilerError::new(
                     format!(
                         "internal encoding error - unexpected verification error: [{}] {}",
-                        full_err_id, ver_error.message
+                        full_err_id, demangle_text(&ver_error.message)
                     ),
                     error_span,
                 ).set_failing_assertion(
                     opt_cause_span
-                ).set_help(
-                    "This could be caused by too small assertion timeout. \
-                    Try increasing it by setting the configuration parameter \
-                    ASSERT_TIMEOUT to a larger value."
-                )
+                ).set_help(unexpected_error_help())
             },
 
             (full_err_id, _) => {
@@ -492,17 +846,93 @@ impl<'tcx> ErrorManager<'tcx> {
                 CompilerError::new(
                     format!(
                         "internal encoding error - unhandled verification error: {:?} [{}] {}",
-                        error_ctxt, full_err_id, ver_error.message,
+                        error_ctxt, full_err_id, demangle_text(&ver_error.message),
                     ),
                     error_span,
                 ).set_failing_assertion(
                     opt_cause_span
-                ).set_help(
-                    "This could be caused by too small assertion timeout. \
-                    Try increasing it by setting the configuration parameter \
-                    ASSERT_TIMEOUT to a larger value."
-                )
+                ).set_help(unexpected_error_help())
             }
         }
     }
 }
+
+/// The help message attached to unexpected verification errors, which depends on the backend.
+fn unexpected_error_help() -> &'static str {
+    match VerificationBackend::from_str(&config::viper_backend()) {
+        VerificationBackend::Silicon => {
+            "This could be caused by too small assertion timeout. \
+            Try increasing it by setting the configuration parameter \
+            ASSERT_TIMEOUT to a larger value."
+        }
+        VerificationBackend::Carbon => {
+            "This could be caused by an incompleteness of Carbon or by a timeout of Boogie. \
+            Try cross-checking the program with Silicon by setting the configuration \
+            parameter VIPER_BACKEND to \"Silicon\"."
+        }
+    }
+}
+
+/// The message given by the user to a panic, from the string literal passed to the panic
+/// machinery. The default messages of the macros are ignored.
+fn custom_panic_message(literal: &str) -> Option<String> {
+    let unreachable_prefix = "internal error: entered unreachable code: ";
+    if literal.starts_with(unreachable_prefix) {
+        return Some(literal[unreachable_prefix.len()..].to_string());
+    }
+    let is_default_message = literal.starts_with("assertion failed: ")
+        || literal == "internal error: entered unreachable code"
+        || literal == "not yet implemented"
+        || literal == "explicit panic"
+        || literal.is_empty();
+    if is_default_message {
+        None
+    } else {
+        Some(literal.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntax::codemap::FilePathMapping;
+
+    fn register_positions(codemap: &CodeMap) -> Vec<PositionId> {
+        let mut error_manager = ErrorManager::new(codemap);
+        (0..3)
+            .map(|_| {
+                let pos = error_manager.register(MultiSpan::new(), ErrorCtxt::GenericStatement);
+                pos.id()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_positions_are_unique() {
+        let codemap = CodeMap::new(FilePathMapping::empty());
+        let positions = register_positions(&codemap);
+        assert_ne!(positions[0], positions[1]);
+        assert_ne!(positions[1], positions[2]);
+    }
+
+    #[test]
+    fn test_positions_are_repeated_across_sessions() {
+        let codemap = CodeMap::new(FilePathMapping::empty());
+        assert_eq!(register_positions(&codemap), register_positions(&codemap));
+    }
+
+    #[test]
+    fn test_custom_panic_messages() {
+        assert_eq!(custom_panic_message("x is zero"), Some("x is zero".to_string()));
+        assert_eq!(
+            custom_panic_message("x is \"zero\"\n"),
+            Some("x is \"zero\"\n".to_string())
+        );
+        assert_eq!(
+            custom_panic_message("internal error: entered unreachable code: bad"),
+            Some("bad".to_string())
+        );
+        assert_eq!(custom_panic_message("assertion failed: x > 0"), None);
+        assert_eq!(custom_panic_message("explicit panic"), None);
+    }
+}
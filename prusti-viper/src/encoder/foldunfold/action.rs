@@ -16,6 +16,9 @@ pub enum Action {
     /// The dropped perm and the missing permission that caused this
     /// perm to be dropped.
     Drop(Perm, Perm),
+    /// An explanation of the following fold or unfold: the requirement that
+    /// triggered it and the folds that needed the requirement.
+    Comment(String),
 }
 
 impl Action {
@@ -34,6 +37,7 @@ impl Action {
                 vir::Stmt::Unfold(pred.clone(), args.clone(), *perm_amount, variant.clone())
             }
             Action::Drop(..) => vir::Stmt::comment(self.to_string()),
+            Action::Comment(ref comment) => vir::Stmt::comment(comment.clone()),
         }
     }
 
@@ -49,7 +53,7 @@ impl Action {
                     pred.clone(), args.clone(), inner_expr, *perm, variant.clone())
             }
 
-            Action::Drop(..) => inner_expr,
+            Action::Drop(..) | Action::Comment(..) => inner_expr,
         }
     }
 }
@@ -57,7 +61,9 @@ impl Action {
 impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Action::Fold(..) | Action::Unfold(..) => write!(f, "{}", self.to_stmt().to_string()),
+            Action::Fold(..) | Action::Unfold(..) | Action::Comment(..) => {
+                write!(f, "{}", self.to_stmt().to_string())
+            }
             Action::Drop(ref perm, ref missing_perm) => {
                 write!(f, "drop {} ({})", perm, missing_perm)
             }
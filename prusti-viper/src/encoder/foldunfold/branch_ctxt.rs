@@ -6,22 +6,39 @@
 
 use encoder::foldunfold::action::*;
 use encoder::foldunfold::perm::*;
+use encoder::foldunfold::place_map::{PlaceMap, PlaceSet};
 use encoder::foldunfold::places_utils::*;
 use encoder::foldunfold::state::*;
 use encoder::vir;
 use encoder::vir::PermAmount;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::iter::FromIterator;
 use utils::to_string::ToString;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct BranchCtxt<'a> {
     state: State,
     /// The definition of the predicates
     predicates: &'a HashMap<String, vir::Predicate>,
+    /// The predicate permissions that could not be obtained by folding, since the state last
+    /// gained some permissions. Folding, unfolding and dropping permissions can not make them
+    /// obtainable. The cache is kept across the statements of a basic block that only consume
+    /// permissions, and cleared by those that add some (e.g. inhales and the targets of
+    /// assignments). Thus the obtain operations between two such statements search each
+    /// fold at most once.
+    failed_folds: HashSet<Perm>,
 }
 
+impl<'a> PartialEq for BranchCtxt<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state && self.predicates == other.predicates
+    }
+}
+
+impl<'a> Eq for BranchCtxt<'a> {}
+
 impl<'a> BranchCtxt<'a> {
     pub fn new(
         local_vars: Vec<vir::LocalVar>,
@@ -38,6 +55,7 @@ impl<'a> BranchCtxt<'a> {
                 HashSet::new(),
             ),
             predicates,
+            failed_folds: HashSet::new(),
         }
     }
 
@@ -46,6 +64,8 @@ impl<'a> BranchCtxt<'a> {
     }
 
     pub fn mut_state(&mut self) -> &mut State {
+        // The caller might add permissions to the state, which cannot be observed afterwards.
+        self.failed_folds.clear();
         &mut self.state
     }
 
@@ -82,7 +102,7 @@ impl<'a> BranchCtxt<'a> {
             .into_iter()
             .map(|perm| {
                 perm.map_place(|p| p.replace_place(&pred_self_place, pred_place))
-                    .update_perm_amount(perm_amount)
+                    .scale_perm_amount(perm_amount)
             })
             .collect();
 
@@ -92,7 +112,11 @@ impl<'a> BranchCtxt<'a> {
         );
 
         // Simulate unfolding of `pred_place`
-        self.state.remove_pred(&pred_place, perm_amount);
+        if perm_amount == PermAmount::Wildcard {
+            self.state.remove_pred_place(&pred_place);
+        } else {
+            self.state.remove_pred(&pred_place, perm_amount);
+        }
         self.state.insert_all_perms(places_in_pred.into_iter());
 
         debug!("We unfolded {}", pred_place);
@@ -124,8 +148,10 @@ impl<'a> BranchCtxt<'a> {
         trace!("Right branch: {}", other.state);
         self.state.check_consistency();
 
-        // If they are already the same, avoid unnecessary operations
-        if self.state != other.state {
+        // If they are already the same, avoid unnecessary operations.
+        // States that still share their permissions are equal by construction,
+        // so the (expensive) structural comparison can be skipped.
+        if !self.state.shares_permissions_with(&other.state) && self.state != other.state {
             // Compute which paths are moved out
             /*
             let moved_paths: HashSet<_> = if anti_join {
@@ -139,18 +165,25 @@ impl<'a> BranchCtxt<'a> {
                 )
             };
             */
+            // Places are compared by their ids in this arena, to avoid rebuilding and comparing
+            // their expressions over and over.
+            let mut arena = vir::ExprArena::new();
+
             // TODO: Remove all paths that are not definitely initialised.
             let moved_paths: HashSet<_> = ancestors(
+                &mut arena,
                 &self
                     .state
                     .moved()
-                    .clone()
-                    .union(other.state.moved())
+                    .iter()
+                    .chain(other.state.moved().iter())
                     .cloned()
                     .collect(),
             );
-            self.state.set_moved(moved_paths.clone());
-            other.state.set_moved(moved_paths.clone());
+            // Both branches share the same moved paths from now on.
+            let moved_paths_set: PlaceSet = moved_paths.iter().cloned().collect();
+            self.state.set_moved(moved_paths_set.clone());
+            other.state.set_moved(moved_paths_set);
             debug!("moved_paths: {}", moved_paths.iter().to_string());
 
             trace!("left acc: {{\n{}\n}}", self.state.display_acc());
@@ -169,8 +202,11 @@ impl<'a> BranchCtxt<'a> {
             );
 
             // Compute which access permissions may be preserved
-            let (unfold_potential_acc, fold_potential_pred) =
-                compute_fold_target(&self.state.acc_places(), &other.state.acc_places());
+            let (unfold_potential_acc, fold_potential_pred) = compute_fold_target(
+                &mut arena,
+                &self.state.acc_places(),
+                &other.state.acc_places(),
+            );
             debug!(
                 "unfold_potential_acc: {}",
                 unfold_potential_acc.iter().to_sorted_multiline_string()
@@ -182,13 +218,13 @@ impl<'a> BranchCtxt<'a> {
 
             // Remove access permissions that can not be obtained due to a moved path
             let unfold_actual_acc =
-                filter_not_proper_extensions_of(&unfold_potential_acc, &moved_paths);
+                filter_not_proper_extensions_of(&mut arena, &unfold_potential_acc, &moved_paths);
             debug!(
                 "unfold_actual_acc: {}",
                 unfold_actual_acc.iter().to_sorted_multiline_string()
             );
             let fold_actual_pred =
-                filter_not_proper_extensions_of(&fold_potential_pred, &moved_paths);
+                filter_not_proper_extensions_of(&mut arena, &fold_potential_pred, &moved_paths);
             debug!(
                 "fold_actual_pred: {}",
                 fold_actual_pred.iter().to_sorted_multiline_string()
@@ -217,7 +253,10 @@ impl<'a> BranchCtxt<'a> {
                             ObtainResult::Success(new_actions) => {
                                 left_actions.extend(new_actions);
                             }
-                            ObtainResult::Failure(missing_perm) => {
+                            ObtainResult::Failure(ObtainFailure {
+                                perm: missing_perm,
+                                ..
+                            }) => {
                                 debug!(
                                     "Failed to obtain: {} because of {}",
                                     pred_perm, missing_perm
@@ -282,7 +321,10 @@ impl<'a> BranchCtxt<'a> {
                                     left_actions.extend(new_actions);
                                     true
                                 }
-                                ObtainResult::Failure(missing_perm) => {
+                                ObtainResult::Failure(ObtainFailure {
+                                    perm: missing_perm,
+                                    ..
+                                }) => {
                                     ctxt_right.state.remove_perm(&perm);
                                     right_actions.push(Action::Drop(perm, missing_perm));
                                     false
@@ -298,8 +340,9 @@ impl<'a> BranchCtxt<'a> {
             }
 
             // Drop predicate permissions that can not be obtained due to a move
-            for pred_place in &filter_proper_extensions_of(&self.state.pred_places(), &moved_paths)
-            {
+            let left_moved_preds =
+                filter_proper_extensions_of(&mut arena, &self.state.pred_places(), &moved_paths);
+            for pred_place in &left_moved_preds {
                 debug!(
                     "Drop pred {} in left branch (it is moved out in the other branch)",
                     pred_place
@@ -309,8 +352,9 @@ impl<'a> BranchCtxt<'a> {
                 let perm = Perm::pred(pred_place.clone(), perm_amount);
                 left_actions.push(Action::Drop(perm.clone(), perm));
             }
-            for pred_place in &filter_proper_extensions_of(&other.state.pred_places(), &moved_paths)
-            {
+            let right_moved_preds =
+                filter_proper_extensions_of(&mut arena, &other.state.pred_places(), &moved_paths);
+            for pred_place in &right_moved_preds {
                 debug!(
                     "Drop pred {} in right branch (it is moved out in the other branch)",
                     pred_place
@@ -322,12 +366,15 @@ impl<'a> BranchCtxt<'a> {
             }
 
             // Compute preserved predicate permissions
-            let preserved_preds: HashSet<_> =
-                intersection(&self.state.pred_places(), &other.state.pred_places());
-            debug!("preserved_preds: {}", preserved_preds.iter().to_string());
+            debug!(
+                "preserved_preds: {}",
+                self.state.pred().common_keys(other.state.pred()).iter().to_string()
+            );
+            let left_only_preds = self.state.pred().missing_keys(other.state.pred());
+            let right_only_preds = other.state.pred().missing_keys(self.state.pred());
 
             // Drop predicate permissions that are not in the other branch
-            for pred_place in self.state.pred_places().difference(&preserved_preds) {
+            for pred_place in &left_only_preds {
                 debug!(
                     "Drop pred {} in left branch (it is not in the other branch)",
                     pred_place
@@ -337,7 +384,7 @@ impl<'a> BranchCtxt<'a> {
                 let perm = Perm::pred(pred_place.clone(), perm_amount);
                 left_actions.push(Action::Drop(perm.clone(), perm));
             }
-            for pred_place in other.state.pred_places().difference(&preserved_preds) {
+            for pred_place in &right_only_preds {
                 debug!(
                     "Drop pred {} in right branch (it is not in the other branch)",
                     pred_place
@@ -349,7 +396,9 @@ impl<'a> BranchCtxt<'a> {
             }
 
             // Drop access permissions that can not be obtained due to a move
-            for acc_place in &filter_proper_extensions_of(&self.state.acc_places(), &moved_paths) {
+            let left_moved_accs =
+                filter_proper_extensions_of(&mut arena, &self.state.acc_places(), &moved_paths);
+            for acc_place in &left_moved_accs {
                 debug!(
                     "Drop acc {} in left branch (it is moved out in the other branch)",
                     acc_place
@@ -359,7 +408,9 @@ impl<'a> BranchCtxt<'a> {
                 let perm = Perm::acc(acc_place.clone(), perm_amount);
                 left_actions.push(Action::Drop(perm.clone(), perm));
             }
-            for acc_place in &filter_proper_extensions_of(&other.state.acc_places(), &moved_paths) {
+            let right_moved_accs =
+                filter_proper_extensions_of(&mut arena, &other.state.acc_places(), &moved_paths);
+            for acc_place in &right_moved_accs {
                 debug!(
                     "Drop acc {} in right branch (it is moved out in the other branch)",
                     acc_place
@@ -371,11 +422,9 @@ impl<'a> BranchCtxt<'a> {
             }
 
             // Drop access permissions not in `actual_acc`
-            for acc_place in self
-                .state
-                .acc_places()
-                .difference(&other.state.acc_places())
-            {
+            let left_only_accs = self.state.acc().missing_keys(other.state.acc());
+            let right_only_accs = other.state.acc().missing_keys(self.state.acc());
+            for acc_place in &left_only_accs {
                 debug!(
                     "Drop acc {} in left branch (not present in the other branch)",
                     acc_place
@@ -387,11 +436,7 @@ impl<'a> BranchCtxt<'a> {
                 let perm = Perm::acc(acc_place.clone(), perm_amount);
                 left_actions.push(Action::Drop(perm.clone(), perm));
             }
-            for acc_place in other
-                .state
-                .acc_places()
-                .difference(&self.state.acc_places())
-            {
+            for acc_place in &right_only_accs {
                 debug!(
                     "Drop acc {} in right branch (not present in the other branch)",
                     acc_place
@@ -406,8 +451,9 @@ impl<'a> BranchCtxt<'a> {
             }
 
             // If we have `Read` and `Write`, make both `Read`.
-            for acc_place in self.state.acc_places() {
-                assert!(other.state.acc().contains_key(&acc_place)
+            let acc_places: Vec<_> = self.state.acc().keys().cloned().collect();
+            for acc_place in acc_places {
+                assert!(other.state.acc().contains_key(&acc_place),
                         "acc_place = {}", acc_place);
                 let left_perm = self.state.acc()[&acc_place];
                 let right_perm = other.state.acc()[&acc_place];
@@ -422,7 +468,8 @@ impl<'a> BranchCtxt<'a> {
                     right_actions.push(Action::Drop(perm.clone(), perm));
                 }
             }
-            for pred_place in self.state.pred_places() {
+            let pred_places: Vec<_> = self.state.pred().keys().cloned().collect();
+            for pred_place in pred_places {
                 assert!(other.state.pred().contains_key(&pred_place));
                 let left_perm = self.state.pred()[&pred_place];
                 let right_perm = other.state.pred()[&pred_place];
@@ -464,17 +511,30 @@ impl<'a> BranchCtxt<'a> {
     }
 
     /// Obtain the required permissions, changing the state inplace and returning the statements.
-    fn obtain_all(&mut self, reqs: Vec<Perm>) -> Vec<Action> {
+    /// Stops at the first permission that can not be obtained.
+    fn obtain_all(&mut self, reqs: Vec<Perm>) -> Result<Vec<Action>, ObtainFailure> {
         debug!("[enter] obtain_all: {{{}}}", reqs.iter().to_string());
-        reqs.iter()
-            .flat_map(|perm| self.obtain(perm, false).unwrap())
-            .collect()
+        let mut actions = vec![];
+        for perm in &reqs {
+            match self.obtain(perm, false) {
+                ObtainResult::Success(new_actions) => actions.extend(new_actions),
+                ObtainResult::Failure(failure) => return Err(failure),
+            }
+        }
+        Ok(actions)
     }
 
     /// Obtain the required permission, changing the state inplace and returning the statements.
     ///
     /// ``in_join`` – are we currently trying to join branches?
     fn obtain(&mut self, req: &Perm, in_join: bool) -> ObtainResult {
+        self.obtain_for(req, in_join, &[])
+    }
+
+    /// Obtain the required permission, which is needed to fold the predicates in
+    /// ``fold_chain`` (the outermost first). Each fold and unfold is preceded by a
+    /// comment that explains it.
+    fn obtain_for(&mut self, req: &Perm, in_join: bool, fold_chain: &[Perm]) -> ObtainResult {
         trace!("[enter] obtain(req={})", req);
 
         let mut actions: Vec<Action> = vec![];
@@ -497,13 +557,16 @@ impl<'a> BranchCtxt<'a> {
         debug!("Try to satisfy requirement {}", req);
 
         // 3. Obtain with an unfold
-        // Find a predicate on a proper prefix of req
-        let existing_prefix_pred_opt: Option<vir::Expr> = self
-            .state
-            .pred_places()
-            .iter()
-            .find(|p| req.has_proper_prefix(p))
-            .cloned();
+        // Find the closest predicate on a proper prefix of req, walking up from req
+        // instead of scanning all the predicates of the state.
+        let mut prefix = req.get_place().get_parent_ref();
+        while let Some(place) = prefix {
+            if self.state.contains_pred(place) {
+                break;
+            }
+            prefix = place.get_parent_ref();
+        }
+        let existing_prefix_pred_opt: Option<vir::Expr> = prefix.cloned();
         if let Some(existing_pred_to_unfold) = existing_prefix_pred_opt {
             let perm_amount = self.state.pred()[&existing_pred_to_unfold];
             debug!(
@@ -512,20 +575,39 @@ impl<'a> BranchCtxt<'a> {
                 perm_amount,
                 req.get_perm_amount()
             );
+            // Unfolding a wildcard predicate gives wildcard permissions, which are
+            // only enough for the requirements of pure function calls.
+            if perm_amount == PermAmount::Wildcard && req.get_perm_amount() != PermAmount::Wildcard
+            {
+                debug!("A wildcard predicate cannot satisfy {}", req);
+                return ObtainResult::Failure(self.explain_failure(req));
+            }
             assert!(perm_amount >= req.get_perm_amount());
             let variant = self.find_variant(&existing_pred_to_unfold, req.get_place());
+            actions.push(Action::Comment(format!(
+                "[foldunfold] unfold {} to obtain {}",
+                existing_pred_to_unfold,
+                describe_requirement(req, fold_chain)
+            )));
             let action = self.unfold(&existing_pred_to_unfold, perm_amount, variant);
             actions.push(action);
             debug!("We unfolded {}", existing_pred_to_unfold);
 
             // Check if we are done
-            let new_actions = self.obtain(req, false).unwrap();
+            let new_actions = match self.obtain_for(req, false, fold_chain) {
+                ObtainResult::Success(new_actions) => new_actions,
+                failure => return failure,
+            };
             actions.extend(new_actions);
             trace!("[exit] obtain");
             return ObtainResult::Success(actions);
         }
 
         // 4. Obtain with a fold
+        if req.is_pred() && self.failed_folds.contains(req) {
+            debug!("We already failed to fold {}", req);
+            return ObtainResult::Failure(self.explain_failure(req));
+        }
         if req.is_pred() {
             // We want to fold `req`
             debug!("We want to fold {}", req);
@@ -534,12 +616,12 @@ impl<'a> BranchCtxt<'a> {
 
             let variant = self.find_fold_variant(req);
 
-            // Find an access permission for which req is a proper suffix
-            let existing_proper_perm_extension_opt: Option<_> = self
+            // Check if there is an access permission for which req is a proper suffix
+            let has_proper_perm_extension = self
                 .state
-                .acc_places()
-                .into_iter()
-                .find(|p| p.has_proper_prefix(req.get_place()));
+                .acc()
+                .keys()
+                .any(|p| p.has_proper_prefix(req.get_place()));
 
             let pred_self_place: vir::Expr = predicate.self_place();
             let places_in_pred: Vec<Perm> = predicate
@@ -551,14 +633,14 @@ impl<'a> BranchCtxt<'a> {
             // Check that there exists something that would make the fold possible.
             // We don't want to end up in an infinite recursion, trying to obtain the
             // predicates in the body.
-            let can_fold = match existing_proper_perm_extension_opt {
-                Some(_) => true,
-                None => places_in_pred.is_empty() && !predicate.is_abstract(),
-            };
+            let can_fold = has_proper_perm_extension
+                || (places_in_pred.is_empty() && !predicate.is_abstract());
 
             if can_fold {
                 let perm_amount = places_in_pred
                     .iter()
+                    // A read permission in the body is available for any amount of the fold.
+                    .filter(|p| p.get_perm_amount() != PermAmount::Read)
                     .map(|p| {
                         self.state
                             .acc()
@@ -581,15 +663,18 @@ impl<'a> BranchCtxt<'a> {
                     req.get_perm_amount()
                 );
 
+                let mut inner_fold_chain = fold_chain.to_vec();
+                inner_fold_chain.push(req.clone());
                 for fold_req_place in &places_in_pred {
                     let pos = req.get_place().pos().clone();
                     let new_req_place = fold_req_place.clone().set_default_pos(pos);
-                    let obtain_result = self.obtain(&new_req_place, false);
+                    let obtain_result = self.obtain_for(&new_req_place, false, &inner_fold_chain);
                     match obtain_result {
                         ObtainResult::Success(new_actions) => {
                             actions.extend(new_actions);
                         }
                         ObtainResult::Failure(_) => {
+                            self.failed_folds.insert(req.clone());
                             return obtain_result;
                         }
                     }
@@ -597,9 +682,14 @@ impl<'a> BranchCtxt<'a> {
 
                 let scaled_places_in_pred: Vec<_> = places_in_pred
                     .into_iter()
-                    .map(|perm| perm.update_perm_amount(perm_amount))
+                    .map(|perm| perm.scale_perm_amount(perm_amount))
                     .collect();
 
+                actions.push(Action::Comment(format!(
+                    "[foldunfold] fold {} to obtain {}",
+                    req.get_place(),
+                    describe_requirement(req, fold_chain)
+                )));
                 let pos = req.get_place().pos().clone();
                 let fold_action = Action::Fold(
                     predicate_name.clone(),
@@ -619,7 +709,13 @@ impl<'a> BranchCtxt<'a> {
                     self.state
                 );
                 assert!(!self.state.contains_pred(req.get_place()));
-                self.state.remove_all_perms(scaled_places_in_pred.iter());
+                if perm_amount == PermAmount::Wildcard {
+                    for perm in &scaled_places_in_pred {
+                        self.state.remove_perm_place(perm);
+                    }
+                } else {
+                    self.state.remove_all_perms(scaled_places_in_pred.iter());
+                }
                 self.state.insert_pred(req.get_place().clone(), perm_amount);
 
                 // Done. Continue checking the remaining requirements
@@ -642,12 +738,16 @@ Predicates: {{
                     self.state.display_acc(),
                     self.state.display_pred()
                 );
-                return ObtainResult::Failure(req.clone());
+                self.failed_folds.insert(req.clone());
+                return ObtainResult::Failure(self.explain_failure(req));
             }
-        } else if in_join && req.get_perm_amount() == vir::PermAmount::Read {
+        } else if in_join
+            && (req.get_perm_amount() == vir::PermAmount::Read
+                || req.get_perm_amount() == vir::PermAmount::Wildcard)
+        {
             // Permissions held by shared references can be dropped
             // without being explicitly moved becauce &T implements Copy.
-            return ObtainResult::Failure(req.clone());
+            return ObtainResult::Failure(self.explain_failure(req));
         } else {
             // We have no predicate to obtain the access permission `req`
             debug!(
@@ -665,10 +765,37 @@ Predicates: {{
                 self.state.display_acc(),
                 self.state.display_pred()
             );
-            return ObtainResult::Failure(req.clone());
+            return ObtainResult::Failure(self.explain_failure(req));
         };
     }
 
+    /// Explain why the permission `req` can not be obtained in the current state.
+    fn explain_failure(&self, req: &Perm) -> ObtainFailure {
+        let place = req.get_place();
+        let moved_place = self
+            .state
+            .moved()
+            .iter()
+            .find(|moved_place| place.has_prefix(moved_place) || moved_place.has_prefix(place))
+            .cloned();
+        let held_amount = if req.is_acc() {
+            self.state.acc().get(place)
+        } else {
+            self.state.pred().get(place)
+        };
+        let reason = if let Some(moved_place) = moved_place {
+            ObtainFailureReason::MovedOut(moved_place)
+        } else if let Some(&perm_amount) = held_amount {
+            ObtainFailureReason::InsufficientPermission(perm_amount)
+        } else {
+            ObtainFailureReason::Missing
+        };
+        ObtainFailure {
+            perm: req.clone(),
+            reason,
+        }
+    }
+
     /// Returns some of the dropped permissions
     pub fn apply_stmt(&mut self, stmt: &vir::Stmt) {
         debug!("apply_stmt: {}", stmt);
@@ -678,7 +805,16 @@ Predicates: {{
 
         self.state.check_consistency();
 
+        // The maps share their entries with the state until it is modified, so the copies are
+        // cheap and unmodified maps are compared in constant time.
+        let old_acc = self.state.acc().clone();
+        let old_pred = self.state.pred().clone();
         stmt.apply_on_state(&mut self.state, self.predicates);
+        if gains_permissions(&old_acc, self.state.acc())
+            || gains_permissions(&old_pred, self.state.pred())
+        {
+            self.failed_folds.clear();
+        }
 
         trace!("Acc state after: {{\n{}\n}}", self.state.display_acc());
         trace!("Pred state after: {{\n{}\n}}", self.state.display_pred());
@@ -686,7 +822,10 @@ Predicates: {{
         self.state.check_consistency();
     }
 
-    pub fn obtain_permissions(&mut self, permissions: Vec<Perm>) -> Vec<Action> {
+    pub fn obtain_permissions(
+        &mut self,
+        permissions: Vec<Perm>,
+    ) -> Result<Vec<Action>, ObtainFailure> {
         trace!(
             "[enter] obtain_permissions: {}",
             permissions.iter().to_string()
@@ -704,7 +843,9 @@ Predicates: {{
 
         self.state.check_consistency();
 
-        trace!("[exit] obtain_permissions: {}", actions.iter().to_string());
+        if let Ok(ref actions) = actions {
+            trace!("[exit] obtain_permissions: {}", actions.iter().to_string());
+        }
         actions
     }
 
@@ -736,15 +877,17 @@ Predicates: {{
     /// Find the variant of enum that should be folded.
     fn find_fold_variant(&self, req: &Perm) -> vir::MaybeEnumVariantIndex {
         let req_place = req.get_place();
-        // Find an access permission for which req is a proper suffix and extract variant from it.
+        // Find the closest access permission for which req is a proper suffix and extract
+        // the variant from it.
         self.state
-            .acc_places()
-            .into_iter()
-            .find(|place| {
+            .acc()
+            .keys()
+            .filter(|place| {
                 place.has_proper_prefix(req_place) && place.is_variant()
             })
+            .min_by_key(|place| place.place_depth())
             .and_then(|prefixed_place| {
-                self.find_variant(req_place, &prefixed_place)
+                self.find_variant(req_place, prefixed_place)
             })
     }
 }
@@ -773,54 +916,65 @@ Predicates: {{
 /// The elements from the first set that have any element in the second
 /// set as a prefix are dropped.
 pub fn compute_fold_target(
+    arena: &mut vir::ExprArena,
     left: &HashSet<vir::Expr>,
     right: &HashSet<vir::Expr>,
 ) -> (HashSet<vir::Expr>, HashSet<vir::Expr>) {
+    let left: Vec<_> = left.iter().map(|place| (place, arena.intern(place))).collect();
+    let right: Vec<_> = right.iter().map(|place| (place, arena.intern(place))).collect();
+
     let mut conflicting_base = HashSet::new();
     // If we have an enum unfolded only in one, then we add that enum to
     // conflicting places.
-    let mut conflicting_base_check = |item: &vir::Expr, second_set: &HashSet<vir::Expr>| {
-        if let vir::Expr::Variant(box ref base, _, _) = item {
-            if !second_set.iter().any(|p| p.has_prefix(item)) {
-                // The enum corresponding to base is completely folded in second_set or unfolded
-                // with a different variant.
-                conflicting_base.insert(base.clone());
+    let mut conflicting_base_check =
+        |item: &vir::Expr, item_id: vir::ExprId, second_set: &[(&vir::Expr, vir::ExprId)]| {
+            if let vir::Expr::Variant(..) = item {
+                if !second_set.iter().any(|&(_, p)| arena.has_prefix(p, item_id)) {
+                    // The enum corresponding to base is completely folded in second_set or
+                    // unfolded with a different variant.
+                    conflicting_base.insert(arena.parent(item_id).unwrap());
+                }
             }
-        }
-    };
-    for left_item in left.iter() {
-        conflicting_base_check(left_item, right);
+        };
+    for &(left_item, left_id) in left.iter() {
+        conflicting_base_check(left_item, left_id, &right);
     }
-    for right_item in right.iter() {
-        conflicting_base_check(right_item, left);
+    for &(right_item, right_id) in right.iter() {
+        conflicting_base_check(right_item, right_id, &left);
     }
 
     let mut places = HashSet::new();
-    let mut place_check = |item: &vir::Expr, item_set: &HashSet<vir::Expr>,
-                                            other_set: &HashSet<vir::Expr>| {
-        let is_leaf = !item_set.iter().any(|p| p.has_proper_prefix(item));
-        let below_all_others = !other_set.iter().any(|p| p.has_prefix(item));
-        let no_conflict_base = !conflicting_base.iter().any(|base| item.has_prefix(base));
+    let mut place_check = |item: &vir::Expr,
+                           item_id: vir::ExprId,
+                           item_set: &[(&vir::Expr, vir::ExprId)],
+                           other_set: &[(&vir::Expr, vir::ExprId)]| {
+        let is_leaf = !item_set
+            .iter()
+            .any(|&(_, p)| arena.has_proper_prefix(p, item_id));
+        let below_all_others = !other_set.iter().any(|&(_, p)| arena.has_prefix(p, item_id));
+        let no_conflict_base = !conflicting_base
+            .iter()
+            .any(|&base| arena.has_prefix(item_id, base));
         if is_leaf && below_all_others && no_conflict_base {
             places.insert(item.clone());
         }
     };
-    for left_item in left.iter() {
-        place_check(left_item, left, right);
+    for &(left_item, left_id) in left.iter() {
+        place_check(left_item, left_id, &left, &right);
     }
-    for right_item in right.iter() {
-        place_check(right_item, right, left);
+    for &(right_item, right_id) in right.iter() {
+        place_check(right_item, right_id, &right, &left);
     }
 
     let acc_places = places;
     let pred_places: HashSet<_> = conflicting_base
         .iter()
-        .filter(|place| {
+        .filter(|&&place| {
             !conflicting_base
                 .iter()
-                .any(|base| place.has_proper_prefix(base))
+                .any(|&base| arena.has_proper_prefix(place, base))
         })
-        .cloned()
+        .map(|&place| arena.expr(place))
         .collect();
     (acc_places, pred_places)
 }
@@ -829,14 +983,134 @@ pub fn compute_fold_target(
 /// permission that was missing.
 enum ObtainResult {
     Success(Vec<Action>),
-    Failure(Perm),
+    Failure(ObtainFailure),
+}
+
+/// Why the fold/unfold algorithm could not obtain a permission.
+#[derive(Debug, Clone)]
+pub enum ObtainFailureReason {
+    /// The place, or a place that it contains, has been moved out on some path
+    /// that reaches the current program point.
+    MovedOut(vir::Expr),
+    /// The place is accessible, but only with the given (too small) permission amount,
+    /// e.g. because it has been reborrowed through a shared reference.
+    InsufficientPermission(PermAmount),
+    /// No permission of the place nor of one of its prefixes is available.
+    Missing,
+}
+
+/// A permission that the fold/unfold algorithm could not obtain, together with the reason.
+#[derive(Debug, Clone)]
+pub struct ObtainFailure {
+    pub perm: Perm,
+    pub reason: ObtainFailureReason,
+}
+
+impl ObtainFailure {
+    /// A message that explains which Rust place lacks the capability, and why.
+    pub fn message(&self) -> String {
+        let place = describe_place(self.perm.get_place());
+        match self.reason {
+            ObtainFailureReason::MovedOut(ref moved_place)
+                if moved_place == self.perm.get_place() =>
+            {
+                format!("the place `{}` might have been moved out on some path", place)
+            }
+            ObtainFailureReason::MovedOut(ref moved_place) => format!(
+                "the place `{}` is not accessible, because `{}` might have been moved out on \
+                 some path",
+                place,
+                describe_place(moved_place)
+            ),
+            ObtainFailureReason::InsufficientPermission(perm_amount) => format!(
+                "the place `{}` is accessible only with {} permission, but {} permission is needed",
+                place,
+                perm_amount,
+                self.perm.get_perm_amount()
+            ),
+            ObtainFailureReason::Missing => format!("the place `{}` is not accessible", place),
+        }
+    }
+
+    /// A suggestion of how to fix the failure.
+    pub fn help(&self) -> String {
+        match self.reason {
+            ObtainFailureReason::MovedOut(_) => "consider restructuring the code so that the \
+                 place is not used after it has been moved out on some path, e.g. by moving it \
+                 only in the last branch that uses it"
+                .to_string(),
+            ObtainFailureReason::InsufficientPermission(_) => "the place might be reachable \
+                 only through a shared reborrow; consider borrowing it mutably, or restructuring \
+                 the code so that the shared reborrow expires before the place is used"
+                .to_string(),
+            ObtainFailureReason::Missing => "consider adding a `#[requires(...)]` clause about \
+                 the borrow through which the place is reached, or restructuring the code"
+                .to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ObtainFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reason {
+            ObtainFailureReason::MovedOut(ref moved_place) => write!(
+                f,
+                "cannot obtain {} because {} might have been moved out",
+                self.perm, moved_place
+            ),
+            ObtainFailureReason::InsufficientPermission(perm_amount) => write!(
+                f,
+                "cannot obtain {} because only {} permission is available",
+                self.perm, perm_amount
+            ),
+            ObtainFailureReason::Missing => write!(f, "cannot obtain {}", self.perm),
+        }
+    }
 }
 
-impl ObtainResult {
-    pub fn unwrap(self) -> Vec<Action> {
-        match self {
-            ObtainResult::Success(actions) => actions,
-            ObtainResult::Failure(_) => unreachable!(),
+/// Render a place as the Rust place that it encodes, e.g. `_1.val_ref.f$x.val_int` as
+/// `(*_1).x`. Enum variants and the fields of primitive values are omitted.
+fn describe_place(place: &vir::Expr) -> String {
+    match *place {
+        vir::Expr::Local(ref var, _) => var.name.clone(),
+        vir::Expr::Variant(box ref base, _, _) => describe_place(base),
+        vir::Expr::Field(box ref base, ref field, _) => {
+            if field.name == "val_ref" {
+                format!("(*{})", describe_place(base))
+            } else if field.name.starts_with("f$") {
+                format!("{}.{}", describe_place(base), &field.name["f$".len()..])
+            } else if let Some(index) = field.tuple_index() {
+                format!("{}.{}", describe_place(base), index)
+            } else if field.name.starts_with("val_") {
+                describe_place(base)
+            } else {
+                format!("{}.{}", describe_place(base), field.name)
+            }
         }
+        _ => place.to_string(),
     }
 }
+
+/// Describe a requirement of the fold/unfold algorithm and the chain of folds
+/// that needed it (the outermost first), e.g. `acc(x.f) (needed to fold P(x))`.
+fn describe_requirement(req: &Perm, fold_chain: &[Perm]) -> String {
+    let mut description = req.to_string();
+    if !fold_chain.is_empty() {
+        let folds: Vec<_> = fold_chain
+            .iter()
+            .rev()
+            .map(|perm| format!("needed to fold {}", perm))
+            .collect();
+        description.push_str(&format!(" ({})", folds.join(", ")));
+    }
+    description
+}
+
+/// Does `new` hold a permission that `old` does not hold, or holds with a smaller amount?
+fn gains_permissions(old: &PlaceMap<PermAmount>, new: &PlaceMap<PermAmount>) -> bool {
+    !new.ptr_eq(old)
+        && new.iter().any(|(place, perm_amount)| match old.get(place) {
+            Some(old_perm_amount) => old_perm_amount < perm_amount,
+            None => true,
+        })
+}
@@ -26,6 +26,7 @@ mod branch_ctxt;
 mod log;
 mod perm;
 mod permissions;
+mod place_map;
 mod places_utils;
 mod semantics;
 mod state;
@@ -33,7 +34,12 @@ mod state;
 pub fn add_folding_unfolding_to_expr(expr: vir::Expr, bctxt: &BranchCtxt) -> vir::Expr {
     let bctxt_at_label = HashMap::new();
     let expr = ExprReplacer::new(bctxt.clone(), &bctxt_at_label, true).fold(expr);
-    ExprReplacer::new(bctxt.clone(), &bctxt_at_label, false).fold(expr)
+    let mut replacer = ExprReplacer::new(bctxt.clone(), &bctxt_at_label, false);
+    let expr = replacer.fold(expr);
+    if let Some((failure, _)) = replacer.failure {
+        panic!("Fold/unfold failure in {}: {}", expr, failure);
+    }
+    expr
 }
 
 pub fn add_folding_unfolding_to_function(
@@ -75,7 +81,51 @@ pub fn add_fold_unfold<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a>(
     let cfg_vars = cfg.get_all_vars();
     let predicates = encoder.get_used_viper_predicates_map();
     let initial_bctxt = BranchCtxt::new(cfg_vars, &predicates);
-    FoldUnfold::new(encoder, initial_bctxt, &cfg, borrow_positions, method_pos).replace_cfg(&cfg)
+    let method = FoldUnfold::new(encoder, initial_bctxt, &cfg, borrow_positions, method_pos)
+        .replace_cfg(&cfg);
+    if config::dump_fold_unfold_actions() {
+        report::log::report(
+            "fold_unfold_actions",
+            format!("{}.actions", method.name()),
+            fold_unfold_actions_summary(&method),
+        );
+    }
+    method
+}
+
+/// A summary of the fold and unfold statements of each basic block, without positions, so
+/// that it can be compared with the summary produced by another version of the algorithm.
+fn fold_unfold_actions_summary(method: &vir::CfgMethod) -> String {
+    fn summarize_stmts(stmts: &[vir::Stmt], indent: usize, lines: &mut Vec<String>) {
+        for stmt in stmts {
+            match *stmt {
+                vir::Stmt::Fold(..) | vir::Stmt::Unfold(..) => {
+                    lines.push(format!("{:indent$}{}", "", stmt, indent = indent));
+                }
+                vir::Stmt::If(ref guard, ref then_stmts) => {
+                    let mut then_lines = vec![];
+                    summarize_stmts(then_stmts, indent + 2, &mut then_lines);
+                    if !then_lines.is_empty() {
+                        lines.push(format!("{:indent$}if {}", "", guard, indent = indent));
+                        lines.extend(then_lines);
+                    }
+                }
+                vir::Stmt::PackageMagicWand(ref wand, ref package_stmts, ..) => {
+                    lines.push(format!("{:indent$}package {}", "", wand, indent = indent));
+                    summarize_stmts(package_stmts, indent + 2, lines);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut lines = vec![format!("method {}", method.name())];
+    for (index, block) in method.basic_blocks.iter().enumerate() {
+        lines.push(format!("block {}", index));
+        summarize_stmts(&block.stmts, 2, &mut lines);
+    }
+    lines.push(String::new());
+    lines.join("\n")
 }
 
 #[derive(Clone)]
@@ -113,11 +163,67 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> FoldUnfold<'p, 'v, 'r, 'a, 'tcx> {
     }
 
     fn replace_expr(&self, expr: &vir::Expr, curr_bctxt: &BranchCtxt<'p>) -> vir::Expr {
-        ExprReplacer::new(curr_bctxt.clone(), &self.bctxt_at_label, false).fold(expr.clone())
+        let mut replacer = ExprReplacer::new(curr_bctxt.clone(), &self.bctxt_at_label, false);
+        let new_expr = replacer.fold(expr.clone());
+        if let Some((failure, pos)) = replacer.failure {
+            self.report_obtain_failure(&failure, &pos);
+        }
+        new_expr
     }
 
     fn replace_old_expr(&self, expr: &vir::Expr, curr_bctxt: &BranchCtxt<'p>) -> vir::Expr {
-        ExprReplacer::new(curr_bctxt.clone(), &self.bctxt_at_label, true).fold(expr.clone())
+        let mut replacer = ExprReplacer::new(curr_bctxt.clone(), &self.bctxt_at_label, true);
+        let new_expr = replacer.fold(expr.clone());
+        if let Some((failure, pos)) = replacer.failure {
+            self.report_obtain_failure(&failure, &pos);
+        }
+        new_expr
+    }
+
+    /// Obtain the permissions `perms`, which are required at the position `pos`.
+    fn obtain_permissions(
+        &self,
+        bctxt: &mut BranchCtxt,
+        perms: Vec<Perm>,
+        pos: &vir::Position,
+    ) -> Vec<Action> {
+        match bctxt.obtain_permissions(perms) {
+            Ok(actions) => actions,
+            Err(failure) => self.report_obtain_failure(&failure, pos),
+        }
+    }
+
+    /// Report that a permission required at the position `pos` can not be obtained, and
+    /// abort, because the rest of the encoding of the method would be meaningless.
+    ///
+    /// The error is reported at the position of the place that lacks the permission, if it
+    /// has one, otherwise at `pos` or at the position of the method.
+    fn report_obtain_failure(&self, failure: &ObtainFailure, pos: &vir::Position) -> ! {
+        debug!("Fold/unfold failure: {}", failure);
+        let place_pos = failure.perm.get_place().pos();
+        let error_pos = if !place_pos.is_default() {
+            place_pos
+        } else if !pos.is_default() {
+            pos
+        } else {
+            &self.method_pos
+        };
+        let error = self
+            .encoder
+            .error_manager()
+            .encoding_error(error_pos, format!("[Prusti] {}", failure.message()))
+            .set_help(failure.help());
+        let env = self.encoder.env();
+        error.report_json(env.codemap());
+        env.span_err_with_help_and_note(
+            error.span,
+            &error.message,
+            &error.help,
+            &error.note,
+            &error.suggestion,
+        );
+        env.abort_if_errors();
+        unreachable!("the fold/unfold failure has been reported as an error")
     }
 
     /// Insert "unfolding in" in old expressions
@@ -488,8 +594,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> FoldUnfold<'p, 'v, 'r, 'a, 'tcx> {
                 x => unreachable!("{:?}", x),
             };
             stmts.extend(
-                bctxt
-                    .obtain_permissions(vec![perm])
+                self.obtain_permissions(bctxt, vec![perm], access.pos())
                     .iter()
                     .map(|a| a.to_stmt()),
             );
@@ -689,7 +794,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::CfgReplacer<BranchCtxt<'p>, Vec<
         debug!("[step.2] replace_stmt: {}", stmt);
         match &stmt {
             vir::Stmt::Inhale(_, vir::FoldingBehaviour::Expr) |
-            vir::Stmt::Assert(_, vir::FoldingBehaviour::Expr, _) => {
+            vir::Stmt::Assert(_, vir::FoldingBehaviour::Expr, _) |
+            vir::Stmt::Assume(_, _) |
+            vir::Stmt::Refute(_, _) => {
                 // Unfolding expressions will be added in step 4.
             }
             _ => {
@@ -729,7 +836,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::CfgReplacer<BranchCtxt<'p>, Vec<
                 );
 
                 if !perms.is_empty() {
-                    stmts.extend(bctxt.obtain_permissions(perms).iter().map(|a| a.to_stmt()));
+                    let pos = stmt.pos().cloned().unwrap_or_else(|| self.method_pos.clone());
+                    stmts.extend(
+                        self.obtain_permissions(bctxt, perms, &pos)
+                            .iter()
+                            .map(|a| a.to_stmt()),
+                    );
 
                     if self.check_foldunfold_state && !is_last_before_return {
                         stmts.push(vir::Stmt::comment("Assert content of fold/unfold state"));
@@ -737,7 +849,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::CfgReplacer<BranchCtxt<'p>, Vec<
                             vir::Stmt::Assert(
                                 bctxt.state().as_vir_expr(),
                                 vir::FoldingBehaviour::Expr,
-                                vir::Position::new(0, 0, "check fold/unfold state".to_string()),
+                                vir::Position::new(0, 0, vir::PositionId::CHECK_FOLD_UNFOLD_STATE),
                             )
                         );
                     }
@@ -1019,9 +1131,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::CfgReplacer<BranchCtxt<'p>, Vec<
                 let mut opt_old_bctxt =
                     label.map(|label_name| self.bctxt_at_label.get(&label_name).unwrap().clone());
                 let label_bctxt = opt_old_bctxt.as_mut().unwrap_or(bctxt);
+                let pos = exprs
+                    .first()
+                    .map(|expr| expr.pos().clone())
+                    .unwrap_or_else(|| self.method_pos.clone());
                 stmts.extend(
-                    label_bctxt
-                        .obtain_permissions(perms)
+                    self.obtain_permissions(label_bctxt, perms, &pos)
                         .iter()
                         .map(|a| a.to_stmt())
                         .collect::<Vec<_>>(),
@@ -1035,7 +1150,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> vir::CfgReplacer<BranchCtxt<'p>, Vec<
                 vir::Stmt::Assert(
                     bctxt.state().as_vir_expr(),
                     vir::FoldingBehaviour::Expr,
-                    vir::Position::new(0, 0, "check fold/unfold state".to_string()),
+                    vir::Position::new(0, 0, vir::PositionId::CHECK_FOLD_UNFOLD_STATE),
                 )
             );
         }
@@ -1125,6 +1240,9 @@ struct ExprReplacer<'b, 'a: 'b> {
     bctxt_at_label: &'b HashMap<String, BranchCtxt<'a>>,
     lhs_bctxt: Option<BranchCtxt<'a>>,
     wait_old_expr: bool,
+    /// The first permission that could not be obtained, with the position of the
+    /// expression that required it.
+    failure: Option<(ObtainFailure, vir::Position)>,
 }
 
 impl<'b, 'a: 'b> ExprReplacer<'b, 'a> {
@@ -1138,6 +1256,20 @@ impl<'b, 'a: 'b> ExprReplacer<'b, 'a> {
             bctxt_at_label,
             lhs_bctxt: None,
             wait_old_expr,
+            failure: None,
+        }
+    }
+
+    /// Obtain the permissions `perms` required by `expr`, recording the first failure.
+    fn obtain_permissions(&mut self, perms: Vec<Perm>, expr: &vir::Expr) -> Vec<Action> {
+        match self.curr_bctxt.clone().obtain_permissions(perms) {
+            Ok(actions) => actions,
+            Err(failure) => {
+                if self.failure.is_none() {
+                    self.failure = Some((failure, expr.pos().clone()));
+                }
+                vec![]
+            }
         }
     }
 }
@@ -1364,9 +1496,7 @@ impl<'b, 'a: 'b> ExprFolder for ExprReplacer<'b, 'a> {
             // Add appropriate unfolding around this old expression
             // Note: unfoldings must have no effect on siblings
             let result = self
-                .curr_bctxt
-                .clone()
-                .obtain_permissions(perms)
+                .obtain_permissions(perms, &inner_expr)
                 .into_iter()
                 .rev()
                 .fold(inner_expr, |expr, action| action.to_expr(expr));
@@ -1406,9 +1536,7 @@ impl<'b, 'a: 'b> ExprFolder for ExprReplacer<'b, 'a> {
                 .collect();
 
             let result = self
-                .curr_bctxt
-                .clone()
-                .obtain_permissions(perms)
+                .obtain_permissions(perms, &func_app)
                 .into_iter()
                 .rev()
                 .fold(func_app, |expr, action| action.to_expr(expr));
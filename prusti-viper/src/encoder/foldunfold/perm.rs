@@ -103,6 +103,19 @@ impl Perm {
         }
     }
 
+    /// Scale the permission amount of a permission in the body of a predicate, when the
+    /// predicate is folded or unfolded with `perm_amount`. Most bodies contain only `Write`
+    /// permissions, which become `perm_amount`.
+    pub fn scale_perm_amount(self, perm_amount: PermAmount) -> Self {
+        let scaled_perm_amount = match (self.get_perm_amount(), perm_amount) {
+            (PermAmount::Write, _) => perm_amount,
+            (PermAmount::Read, PermAmount::Wildcard) => PermAmount::Wildcard,
+            (PermAmount::Read, _) => PermAmount::Read,
+            (body_perm_amount, _) => body_perm_amount,
+        };
+        self.update_perm_amount(scaled_perm_amount)
+    }
+
     pub fn set_default_pos(self, pos: vir::Position) -> Self {
         match self {
             Perm::Acc(expr, perm) => Perm::Acc(expr.set_default_pos(pos), perm),
@@ -216,7 +229,10 @@ fn place_perm_difference(
             Some(left_perm_amount) => match (*left_perm_amount, right_perm_amount) {
                 (PermAmount::Read, PermAmount::Read)
                 | (PermAmount::Read, PermAmount::Write)
-                | (PermAmount::Write, PermAmount::Write) => {
+                | (PermAmount::Write, PermAmount::Write)
+                | (PermAmount::Wildcard, _)
+                | (PermAmount::Read, PermAmount::Wildcard)
+                | (PermAmount::Write, PermAmount::Wildcard) => {
                     left.remove(&place);
                 }
                 _ => unreachable!("left={} right={}", left_perm_amount, right_perm_amount),
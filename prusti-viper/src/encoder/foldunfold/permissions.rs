@@ -9,9 +9,11 @@ use encoder::foldunfold::perm::Perm::*;
 use encoder::foldunfold::perm::*;
 use encoder::vir;
 use encoder::vir::PermAmount;
+use prusti_interface::config;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::iter::FromIterator;
+use viper::VerificationBackend;
 
 pub trait RequiredPermissionsGetter {
     /// Returns the permissions required for the expression to be well-defined
@@ -68,6 +70,8 @@ impl RequiredPermissionsGetter for vir::Stmt {
 
             &vir::Stmt::Exhale(ref expr, ref pos)
             | &vir::Stmt::Assert(ref expr, _, ref pos)
+            | &vir::Stmt::Assume(ref expr, ref pos)
+            | &vir::Stmt::Refute(ref expr, ref pos)
             | &vir::Stmt::Obtain(ref expr, ref pos) => {
                 let perms = expr.get_required_permissions(predicates);
                 perms
@@ -88,10 +92,23 @@ impl RequiredPermissionsGetter for vir::Stmt {
 
             &vir::Stmt::Assign(ref lhs, ref rhs, _kind) => {
                 let mut res = rhs.get_required_permissions(predicates);
-                res.insert(Acc(lhs.clone(), PermAmount::Write));
+                if lhs.is_domain_location() {
+                    res.extend(lhs.get_required_permissions(predicates));
+                } else {
+                    res.insert(Acc(lhs.clone(), PermAmount::Write));
+                }
                 res
             }
 
+            &vir::Stmt::Fold(ref pred_name, ref args, ..)
+            | &vir::Stmt::Unfold(ref pred_name, ref args, ..)
+                if vir::Predicate::is_user_predicate_name(pred_name) =>
+            {
+                // The permissions in the body of a user predicate are not tracked
+                assert_eq!(args.len(), 1);
+                args[0].get_required_permissions(predicates)
+            }
+
             &vir::Stmt::Fold(_, ref args, perm_amount, ref variant, _) => {
                 assert_eq!(args.len(), 1);
                 let place = &args[0];
@@ -107,7 +124,7 @@ impl RequiredPermissionsGetter for vir::Stmt {
                     .into_iter()
                     .map(|perm| {
                         perm.map_place(|p| p.replace_place(&pred_self_place, &place))
-                            .init_perm_amount(perm_amount)
+                            .scale_perm_amount(perm_amount)
                     })
                     .collect();
 
@@ -163,6 +180,17 @@ impl RequiredPermissionsGetter for vir::Stmt {
     }
 }
 
+/// The amount of the predicate instances obtained for the reference arguments of a pure function
+/// call. The preconditions of pure functions only ask for a wildcard amount, but Carbon is less
+/// complete than Silicon when wildcard amounts are folded and unfolded in method bodies. With
+/// Carbon we obtain `read` instances instead, which also satisfy the wildcard preconditions.
+fn pure_call_perm_amount() -> PermAmount {
+    match VerificationBackend::from_str(&config::viper_backend()) {
+        VerificationBackend::Silicon => PermAmount::Wildcard,
+        VerificationBackend::Carbon => PermAmount::Read,
+    }
+}
+
 impl RequiredPermissionsGetter for vir::Expr {
     /// Returns the permissions required for the expression to be well-defined
     fn get_required_permissions(
@@ -188,7 +216,7 @@ impl RequiredPermissionsGetter for vir::Expr {
                     .into_iter()
                     .map(|aop| {
                         aop.map_place(|p| p.replace_place(&pred_self_place, place))
-                            .update_perm_amount(*perm_amount)
+                            .scale_perm_amount(*perm_amount)
                     })
                     .collect();
 
@@ -201,6 +229,13 @@ impl RequiredPermissionsGetter for vir::Expr {
 
             vir::Expr::LabelledOld(_label, _expr, _) => HashSet::new(),
 
+            vir::Expr::PredicateAccessPredicate(name, box arg, _perm_amount, _)
+                if vir::Predicate::is_user_predicate_name(name) =>
+            {
+                // The permissions to the user predicates are not tracked
+                arg.get_required_permissions(predicates)
+            }
+
             vir::Expr::PredicateAccessPredicate(_, box place, _perm_amount, _) => {
                 debug_assert!(place.is_place());
                 let epsilon = PermAmount::Read;
@@ -247,11 +282,34 @@ impl RequiredPermissionsGetter for vir::Expr {
                 vec![guard, left, right].get_required_permissions(predicates)
             }
 
-            vir::Expr::LetExpr(_variable, _expr, _body, _) => {
-                unreachable!("Let expressions should be introduced after fold/unfold.");
+            vir::Expr::MapLookup(box map, box key, _) |
+            vir::Expr::MapContains(box map, box key, _) |
+            vir::Expr::SeqIndex(box map, box key, _) => {
+                vec![map, key].get_required_permissions(predicates)
             }
 
-            vir::Expr::ForAll(vars, _triggers, box body, _) => {
+            vir::Expr::MapUpdate(box map, box key, box value, _) => {
+                vec![map, key, value].get_required_permissions(predicates)
+            }
+
+            vir::Expr::MapLen(box map, _) => map.get_required_permissions(predicates),
+
+            vir::Expr::SeqLen(box seq, _) => seq.get_required_permissions(predicates),
+
+            vir::Expr::DomainFuncApp(_, args, _) => args.get_required_permissions(predicates),
+
+            vir::Expr::LetExpr(variable, box expr, box body, _) => {
+                assert!(!variable.typ.is_ref());
+                let var_place = Acc(vir::Expr::local(variable.clone()), PermAmount::Write);
+                let body_places = perm_difference(
+                    body.get_required_permissions(predicates),
+                    Some(var_place).into_iter().collect(),
+                );
+                union(&expr.get_required_permissions(predicates), &body_places)
+            }
+
+            vir::Expr::ForAll(vars, _triggers, box body, _) |
+            vir::Expr::Exists(vars, _triggers, box body, _) => {
                 assert!(vars.iter().all(|var| !var.typ.is_ref()));
 
                 let vars_places: HashSet<_> = vars
@@ -269,6 +327,12 @@ impl RequiredPermissionsGetter for vir::Expr {
                 .into_iter()
                 .collect(),
 
+            // The permission to a domain location is not tracked, only the permissions needed
+            // to evaluate its address.
+            vir::Expr::Field(box vir::Expr::DomainFuncApp(_, args, _), _, _) => {
+                args.get_required_permissions(predicates)
+            }
+
             vir::Expr::Field(..) => Some(Acc(self.clone(), PermAmount::Read))
                 .into_iter()
                 .collect(),
@@ -279,6 +343,7 @@ impl RequiredPermissionsGetter for vir::Expr {
             }
 
             vir::Expr::FuncApp(ref _name, ref args, ..) => {
+                let perm_amount = pure_call_perm_amount();
                 args.iter()
                     .map(|arg| {
                         if arg.is_place() && arg.get_type().is_ref() {
@@ -291,15 +356,17 @@ impl RequiredPermissionsGetter for vir::Expr {
                                         field_place.clone(),
                                         vir::PermAmount::Read,
                                     ),
-                                    vir::Expr::pred_permission(field_place, vir::PermAmount::Read)
-                                        .unwrap(),
+                                    vir::Expr::pred_permission(
+                                        field_place,
+                                        perm_amount,
+                                    ).unwrap(),
                                 )
                             } else {
                                 let predicate_name = arg.get_type().name();
                                 vir::Expr::predicate_access_predicate(
                                     predicate_name.clone(),
                                     arg.clone().into(),
-                                    PermAmount::Read,
+                                    perm_amount,
                                 )
                             }
                         } else {
@@ -332,7 +399,14 @@ impl vir::Expr {
             | vir::Expr::AddrOf(_, _, _)
             | vir::Expr::LabelledOld(_, _, _)
             | vir::Expr::Const(_, _)
-            | vir::Expr::FuncApp(..) => HashSet::new(),
+            | vir::Expr::FuncApp(..)
+            | vir::Expr::DomainFuncApp(..)
+            | vir::Expr::MapLookup(..)
+            | vir::Expr::MapContains(..)
+            | vir::Expr::MapUpdate(..)
+            | vir::Expr::MapLen(..)
+            | vir::Expr::SeqLen(..)
+            | vir::Expr::SeqIndex(..) => HashSet::new(),
 
             vir::Expr::Unfolding(_, args, expr, perm_amount, variant, _) => {
                 assert_eq!(args.len(), 1);
@@ -349,7 +423,7 @@ impl vir::Expr {
                     .into_iter()
                     .map(|aop| {
                         aop.map_place(|p| p.replace_place(&pred_self_place, place))
-                            .init_perm_amount(*perm_amount)
+                            .scale_perm_amount(*perm_amount)
                     })
                     .collect();
 
@@ -373,7 +447,8 @@ impl vir::Expr {
                 &right.get_permissions(predicates),
             ),
 
-            vir::Expr::ForAll(vars, _triggers, box body, _) => {
+            vir::Expr::ForAll(vars, _triggers, box body, _) |
+            vir::Expr::Exists(vars, _triggers, box body, _) => {
                 assert!(vars.iter().all(|var| !var.typ.is_ref()));
                 let vars_places: HashSet<Perm> = vars
                     .iter()
@@ -382,6 +457,13 @@ impl vir::Expr {
                 perm_difference(body.get_permissions(predicates), vars_places)
             }
 
+            vir::Expr::PredicateAccessPredicate(ref name, _, _, _)
+                if vir::Predicate::is_user_predicate_name(name) =>
+            {
+                // The permissions to the user predicates are not tracked
+                HashSet::new()
+            }
+
             vir::Expr::PredicateAccessPredicate(_, box ref arg, perm_amount, _) => {
                 let opt_perm = if arg.is_place() {
                     Some(match arg.get_label() {
@@ -395,6 +477,13 @@ impl vir::Expr {
                 opt_perm.into_iter().collect()
             }
 
+            vir::Expr::FieldAccessPredicate(box ref location, _, _)
+                if location.is_domain_location() =>
+            {
+                // The permissions to the domain locations are not tracked
+                HashSet::new()
+            }
+
             vir::Expr::FieldAccessPredicate(box ref place, perm_amount, _) => {
                 // In Prusti we assume to have only places here
                 debug_assert!(place.is_place());
@@ -410,8 +499,14 @@ impl vir::Expr {
                 HashSet::new()
             }
 
-            vir::Expr::LetExpr(ref _variable, ref _expr, ref _body, _) => {
-                unreachable!("Let expressions should be introduced after fold/unfold.");
+            vir::Expr::LetExpr(ref variable, box ref expr, box ref body, _) => {
+                assert!(!variable.typ.is_ref());
+                let var_place = Acc(vir::Expr::local(variable.clone()), PermAmount::Write);
+                let body_places = perm_difference(
+                    body.get_permissions(predicates),
+                    Some(var_place).into_iter().collect(),
+                );
+                union(&expr.get_permissions(predicates), &body_places)
             }
         }
     }
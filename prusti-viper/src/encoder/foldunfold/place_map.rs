@@ -0,0 +1,265 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use encoder::vir;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::rc::Rc;
+
+fn place_hash(place: &vir::Expr) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    place.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A persistent map from places, used by the fold/unfold state.
+///
+/// The entries are kept in a vector sorted by the hash of their place. The
+/// vector is shared between clones and copied only when one of the clones
+/// modifies it. Because two maps store their places in the same order, the
+/// places that are (or are not) in another map are computed by merging the two
+/// vectors, without building any intermediate set.
+#[derive(Clone)]
+pub struct PlaceMap<V> {
+    entries: Rc<Vec<(u64, vir::Expr, V)>>,
+}
+
+impl<V: Clone> PlaceMap<V> {
+    pub fn new() -> Self {
+        PlaceMap {
+            entries: Rc::new(vec![]),
+        }
+    }
+
+    /// Returns `Ok` with the index of `place`, or `Err` with the index at
+    /// which it should be inserted.
+    fn find(&self, hash: u64, place: &vir::Expr) -> Result<usize, usize> {
+        let mut low = 0;
+        let mut high = self.entries.len();
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.entries[mid].0 < hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        // Places with colliding hashes are next to each other.
+        let mut index = low;
+        while index < self.entries.len() && self.entries[index].0 == hash {
+            if self.entries[index].1 == *place {
+                return Ok(index);
+            }
+            index += 1;
+        }
+        Err(index)
+    }
+
+    pub fn get(&self, place: &vir::Expr) -> Option<&V> {
+        self.find(place_hash(place), place)
+            .ok()
+            .map(|index| &self.entries[index].2)
+    }
+
+    pub fn contains_key(&self, place: &vir::Expr) -> bool {
+        self.find(place_hash(place), place).is_ok()
+    }
+
+    pub fn insert(&mut self, place: vir::Expr, value: V) -> Option<V> {
+        let hash = place_hash(&place);
+        match self.find(hash, &place) {
+            Ok(index) => {
+                let entry = &mut Rc::make_mut(&mut self.entries)[index];
+                Some(::std::mem::replace(&mut entry.2, value))
+            }
+            Err(index) => {
+                Rc::make_mut(&mut self.entries).insert(index, (hash, place, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, place: &vir::Expr) -> Option<V> {
+        match self.find(place_hash(place), place) {
+            Ok(index) => Some(Rc::make_mut(&mut self.entries).remove(index).2),
+            Err(_) => None,
+        }
+    }
+
+    /// Keep only the entries that satisfy `keep`. The entries are copied only
+    /// if some entry has to be removed.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&vir::Expr, &V) -> bool,
+    {
+        if self.entries.iter().all(|(_, place, value)| keep(place, value)) {
+            return;
+        }
+        Rc::make_mut(&mut self.entries).retain(|(_, place, value)| keep(place, value));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&vir::Expr, &V)> {
+        self.entries.iter().map(|(_, place, value)| (place, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &vir::Expr> {
+        self.entries.iter().map(|(_, place, _)| place)
+    }
+
+    /// Returns `true` if the two maps share their entries, which implies that
+    /// they are equal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.entries, &other.entries)
+    }
+
+    /// Returns the places of `self` that are (if `common`) or are not (if
+    /// `!common`) in `other`.
+    fn merge_keys<W>(&self, other: &PlaceMap<W>, common: bool) -> Vec<vir::Expr> {
+        let mut result = vec![];
+        let mut start = 0;
+        for (hash, place, _) in self.entries.iter() {
+            while start < other.entries.len() && other.entries[start].0 < *hash {
+                start += 1;
+            }
+            let found = other.entries[start..]
+                .iter()
+                .take_while(|(other_hash, _, _)| other_hash == hash)
+                .any(|(_, other_place, _)| other_place == place);
+            if found == common {
+                result.push(place.clone());
+            }
+        }
+        result
+    }
+
+    /// Returns the places of `self` that are in `other`.
+    pub fn common_keys<W>(&self, other: &PlaceMap<W>) -> Vec<vir::Expr> {
+        self.merge_keys(other, true)
+    }
+
+    /// Returns the places of `self` that are not in `other`.
+    pub fn missing_keys<W>(&self, other: &PlaceMap<W>) -> Vec<vir::Expr> {
+        self.merge_keys(other, false)
+    }
+}
+
+impl<V: Clone> FromIterator<(vir::Expr, V)> for PlaceMap<V> {
+    fn from_iter<I: IntoIterator<Item = (vir::Expr, V)>>(iter: I) -> Self {
+        let mut map = PlaceMap::new();
+        for (place, value) in iter {
+            map.insert(place, value);
+        }
+        map
+    }
+}
+
+impl<'a, V: Clone> Index<&'a vir::Expr> for PlaceMap<V> {
+    type Output = V;
+
+    fn index(&self, place: &vir::Expr) -> &V {
+        self.get(place)
+            .unwrap_or_else(|| panic!("Place {} is not in the map", place))
+    }
+}
+
+impl<V: Clone + PartialEq> PartialEq for PlaceMap<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+            || (self.entries.len() == other.entries.len()
+                && self.iter().all(|(place, value)| other.get(place) == Some(value)))
+    }
+}
+
+impl<V: Clone + Eq> Eq for PlaceMap<V> {}
+
+impl<V: fmt::Debug> fmt::Debug for PlaceMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(_, place, value)| (place, value)))
+            .finish()
+    }
+}
+
+/// A persistent set of places, see `PlaceMap`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PlaceSet {
+    map: PlaceMap<()>,
+}
+
+impl PlaceSet {
+    pub fn insert(&mut self, place: vir::Expr) -> bool {
+        self.map.insert(place, ()).is_none()
+    }
+
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: Fn(&vir::Expr) -> bool,
+    {
+        self.map.retain(|place, _| keep(place))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &vir::Expr> {
+        self.map.keys()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.map.ptr_eq(&other.map)
+    }
+}
+
+impl FromIterator<vir::Expr> for PlaceSet {
+    fn from_iter<I: IntoIterator<Item = vir::Expr>>(iter: I) -> Self {
+        PlaceSet {
+            map: iter.into_iter().map(|place| (place, ())).collect(),
+        }
+    }
+}
+
+impl fmt::Debug for PlaceSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(name: &str) -> vir::Expr {
+        vir::Expr::local(vir::LocalVar::new(name, vir::Type::Int))
+    }
+
+    #[test]
+    fn clones_share_entries_until_modified() {
+        let map: PlaceMap<u32> = vec![(local("a"), 1), (local("b"), 2)].into_iter().collect();
+        let mut other = map.clone();
+        assert!(map.ptr_eq(&other));
+        other.retain(|_, _| true);
+        assert!(map.ptr_eq(&other));
+        other.insert(local("c"), 3);
+        assert!(!map.ptr_eq(&other));
+        assert_eq!(map.get(&local("c")), None);
+        assert_eq!(other[&local("c")], 3);
+        other.remove(&local("c"));
+        assert_eq!(map, other);
+    }
+
+    #[test]
+    fn common_and_missing_keys() {
+        let left: PlaceMap<u32> = vec![(local("a"), 1), (local("b"), 2), (local("c"), 3)]
+            .into_iter()
+            .collect();
+        let right: PlaceMap<u32> = vec![(local("b"), 4), (local("d"), 5)].into_iter().collect();
+        assert_eq!(left.common_keys(&right), vec![local("b")]);
+        let mut missing = left.missing_keys(&right);
+        missing.sort_by_key(|place| place.to_string());
+        assert_eq!(missing, vec![local("a"), local("c")]);
+        assert!(right.missing_keys(&right).is_empty());
+    }
+}
@@ -8,6 +8,11 @@ use encoder::vir;
 use std::collections::HashSet;
 use std::hash::Hash;
 
+/// Intern all the places of a set in `arena`.
+fn intern_all(arena: &mut vir::ExprArena, places: &HashSet<vir::Expr>) -> Vec<vir::ExprId> {
+    places.iter().map(|place| arena.intern(place)).collect()
+}
+
 /// Returns the elements of A1 that are a proper extension of at least one element in A2.
 ///
 /// e.g.
@@ -16,16 +21,19 @@ use std::hash::Hash;
 ///   { a, b.c, d.e.f, d.g }
 /// ) = { b.c.d, b.c.e }
 pub fn filter_proper_extensions_of(
+    arena: &mut vir::ExprArena,
     left: &HashSet<vir::Expr>,
     right: &HashSet<vir::Expr>,
 ) -> HashSet<vir::Expr> {
+    let right_ids = intern_all(arena, right);
     let mut res = HashSet::new();
     for left_item in left.iter() {
-        for right_item in right.iter() {
-            if left_item.has_proper_prefix(right_item) {
-                res.insert(left_item.clone());
-                break;
-            }
+        let left_id = arena.intern(left_item);
+        if right_ids
+            .iter()
+            .any(|&right_id| arena.has_proper_prefix(left_id, right_id))
+        {
+            res.insert(left_item.clone());
         }
     }
     res
@@ -39,19 +47,18 @@ pub fn filter_proper_extensions_of(
 ///   { a, b.c, d.e.f, d.g }
 /// ) = { a, d.e }
 pub fn filter_not_proper_extensions_of(
+    arena: &mut vir::ExprArena,
     left: &HashSet<vir::Expr>,
     right: &HashSet<vir::Expr>,
 ) -> HashSet<vir::Expr> {
+    let right_ids = intern_all(arena, right);
     let mut res = HashSet::new();
     for left_item in left.iter() {
-        let mut keep: bool = true;
-        for right_item in right.iter() {
-            if left_item.has_proper_prefix(right_item) {
-                keep = false;
-                break;
-            }
-        }
-        if keep {
+        let left_id = arena.intern(left_item);
+        if !right_ids
+            .iter()
+            .any(|&right_id| arena.has_proper_prefix(left_id, right_id))
+        {
             res.insert(left_item.clone());
         }
     }
@@ -64,23 +71,16 @@ pub fn filter_not_proper_extensions_of(
 /// ancestors(
 ///   { a, b.c, b.c.e, d.e.f },
 /// ) = { a, b.c, d.e.f }
-pub fn ancestors(initial: &HashSet<vir::Expr>) -> HashSet<vir::Expr> {
-    let mut res = HashSet::new();
+pub fn ancestors(arena: &mut vir::ExprArena, initial: &HashSet<vir::Expr>) -> HashSet<vir::Expr> {
+    let ids = intern_all(arena, initial);
     // Filter paths that are an extension of some other path
     // This way, we avoid having both `d` and `d.g`
-    for a in initial.iter() {
-        let mut keep_a = true;
-        for b in initial.iter() {
-            if a.has_proper_prefix(b) {
-                keep_a = false;
-                break;
-            }
-        }
-        if keep_a {
-            res.insert(a.clone());
-        }
-    }
-    res
+    initial
+        .iter()
+        .zip(ids.iter())
+        .filter(|&(_, &a)| !ids.iter().any(|&b| arena.has_proper_prefix(a, b)))
+        .map(|(place, _)| place.clone())
+        .collect()
 }
 
 /// Returns the union of two sets
@@ -102,8 +102,3 @@ pub fn union3<T: Eq + Hash + Clone>(
         .cloned()
         .collect()
 }
-
-/// Returns the intersection of two sets
-pub fn intersection<T: Eq + Hash + Clone>(left: &HashSet<T>, right: &HashSet<T>) -> HashSet<T> {
-    left.clone().intersection(right).cloned().collect()
-}
@@ -38,6 +38,8 @@ impl vir::Stmt {
             &vir::Stmt::Comment(_)
             | &vir::Stmt::Label(_)
             | &vir::Stmt::Assert(_, _, _)
+            | &vir::Stmt::Assume(_, _)
+            | &vir::Stmt::Refute(_, _)
             | &vir::Stmt::Obtain(_, _) => {}
 
             &vir::Stmt::Inhale(ref expr, _) => {
@@ -58,7 +60,7 @@ impl vir::Stmt {
             }
 
             &vir::Stmt::Assign(ref lhs_place, ref rhs, kind) if kind != vir::AssignKind::Ghost => {
-                debug_assert!(lhs_place.is_place());
+                debug_assert!(lhs_place.is_place() || lhs_place.is_domain_location());
                 let original_state = state.clone();
 
                 // Check the state of rhs.
@@ -152,6 +154,12 @@ impl vir::Stmt {
                 // Do nothing.
             }
 
+            &vir::Stmt::Fold(ref pred_name, ..) | &vir::Stmt::Unfold(ref pred_name, ..)
+                if vir::Predicate::is_user_predicate_name(pred_name) =>
+            {
+                // The permissions of the user predicates are not tracked
+            }
+
             &vir::Stmt::Fold(ref _pred_name, ref args, perm_amount, ref variant, _) => {
                 assert_eq!(args.len(), 1);
                 let place = &args[0];
@@ -169,7 +177,7 @@ impl vir::Stmt {
                     .into_iter()
                     .map(|perm| {
                         perm.map_place(|p| p.replace_place(&pred_self_place, place))
-                            .init_perm_amount(perm_amount)
+                            .scale_perm_amount(perm_amount)
                     })
                     .collect();
 
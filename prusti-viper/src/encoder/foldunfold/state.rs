@@ -5,21 +5,29 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use encoder::foldunfold::perm::*;
+use encoder::foldunfold::place_map::{PlaceMap, PlaceSet};
 use encoder::vir;
 use encoder::vir::ExprIterator;
 use encoder::vir::PermAmount;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
-
+use std::mem;
+
+/// The fold/unfold state of a branch.
+///
+/// The `acc`, `pred` and `moved` collections are persistent: they are shared
+/// between clones and copied only when one of the clones modifies them, so
+/// that cloning a `BranchCtxt` for every branch of a conditional is cheap and
+/// joining two branches does not need to build sets of their places.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct State {
     /// paths on which we (may) have a full access permission
-    acc: HashMap<vir::Expr, PermAmount>,
+    acc: PlaceMap<PermAmount>,
     /// paths on which we (may) have a full predicate permission
-    pred: HashMap<vir::Expr, PermAmount>,
+    pred: PlaceMap<PermAmount>,
     /// paths that have been "moved out" (for sure)
-    moved: HashSet<vir::Expr>,
+    moved: PlaceSet,
     /// Permissions currently framed
     framing_stack: Vec<PermSet>,
     /// Permissions that should be removed from the state
@@ -34,9 +42,9 @@ impl State {
         moved: HashSet<vir::Expr>,
     ) -> Self {
         State {
-            acc,
-            pred,
-            moved,
+            acc: acc.into_iter().collect(),
+            pred: pred.into_iter().collect(),
+            moved: moved.into_iter().collect(),
             framing_stack: vec![],
             dropped: HashSet::new(),
         }
@@ -61,6 +69,7 @@ impl State {
                 if !contains_parent_pred
                     && self.pred[place] != PermAmount::Remaining
                     && self.pred[place] != PermAmount::Read
+                    && self.pred[place] != PermAmount::Wildcard
                     && !place.is_mir_reference()
                 {
                     trace!("place: {:?}", place);
@@ -77,7 +86,9 @@ impl State {
             if place.is_simple_place() && !place.is_local() {
                 let parent = place.clone().get_parent().unwrap();
                 if !self.contains_acc(&parent) {
-                    if self.acc[place] == PermAmount::Read {
+                    if self.acc[place] == PermAmount::Read
+                        || self.acc[place] == PermAmount::Wildcard
+                    {
                         let grand_parent = parent.clone().get_parent().unwrap();
                         if grand_parent.is_local() {
                             continue;
@@ -99,8 +110,10 @@ impl State {
                     && place.has_proper_prefix(&other_place)
                 {
                     if !((self.pred[place] == PermAmount::Read ||
-                          self.pred[place] == PermAmount::Remaining)
-                        && self.pred[other_place] == PermAmount::Read)
+                          self.pred[place] == PermAmount::Remaining ||
+                          self.pred[place] == PermAmount::Wildcard)
+                        && (self.pred[other_place] == PermAmount::Read ||
+                            self.pred[other_place] == PermAmount::Wildcard))
                     {
                         panic!(
                             "Consistency error: state has pred {} ({}), but also pred {} ({})",
@@ -124,7 +137,7 @@ impl State {
             }
         }
         for acc_place in self.acc.keys() {
-            for moved_place in &self.moved {
+            for moved_place in self.moved.iter() {
                 if moved_place.is_simple_place()
                     && acc_place.is_simple_place()
                     && acc_place.has_proper_prefix(moved_place)
@@ -137,7 +150,7 @@ impl State {
             }
         }
         for pred_place in self.pred.keys() {
-            for moved_place in &self.moved {
+            for moved_place in self.moved.iter() {
                 if moved_place.is_simple_place()
                     && pred_place.is_simple_place()
                     && pred_place.has_prefix(moved_place)
@@ -180,18 +193,25 @@ impl State {
         F: Fn(vir::Expr) -> vir::Expr,
     {
         for coll in vec![&mut self.acc, &mut self.pred] {
-            let new_values = coll
-                .clone()
-                .into_iter()
-                .map(|(place, perm)| (replace(place), perm));
-            coll.clear();
-            for (key, value) in new_values {
-                coll.insert(key, value);
-            }
+            *coll = coll
+                .iter()
+                .map(|(place, perm)| (replace(place.clone()), *perm))
+                .collect();
         }
     }
 
-    pub fn acc(&self) -> &HashMap<vir::Expr, PermAmount> {
+    /// Returns `true` if the two states still share all their permissions,
+    /// i.e. neither of them has been modified since one was cloned from the
+    /// other. In that case the states are guaranteed to be equal.
+    pub fn shares_permissions_with(&self, other: &State) -> bool {
+        self.acc.ptr_eq(&other.acc)
+            && self.pred.ptr_eq(&other.pred)
+            && self.moved.ptr_eq(&other.moved)
+            && self.framing_stack == other.framing_stack
+            && self.dropped == other.dropped
+    }
+
+    pub fn acc(&self) -> &PlaceMap<PermAmount> {
         &self.acc
     }
 
@@ -209,7 +229,7 @@ impl State {
         acc_leaves
     }
 
-    pub fn pred(&self) -> &HashMap<vir::Expr, PermAmount> {
+    pub fn pred(&self) -> &PlaceMap<PermAmount> {
         &self.pred
     }
 
@@ -217,11 +237,11 @@ impl State {
         self.pred.keys().cloned().collect()
     }
 
-    pub fn moved(&self) -> &HashSet<vir::Expr> {
+    pub fn moved(&self) -> &PlaceSet {
         &self.moved
     }
 
-    pub fn set_moved(&mut self, moved: HashSet<vir::Expr>) {
+    pub fn set_moved(&mut self, moved: PlaceSet) {
         self.moved = moved
     }
 
@@ -276,7 +296,7 @@ impl State {
     }
 
     pub fn is_prefix_of_some_moved(&self, prefix: &vir::Expr) -> bool {
-        for place in &self.moved {
+        for place in self.moved.iter() {
             if place.has_prefix(prefix) {
                 return true;
             }
@@ -353,7 +373,9 @@ impl State {
         if self.acc.contains_key(&place) {
             let new_perm = self.acc[&place] + perm;
             assert!(
-                new_perm == PermAmount::Write || new_perm == PermAmount::Read,
+                new_perm == PermAmount::Write
+                    || new_perm == PermAmount::Read
+                    || new_perm == PermAmount::Wildcard,
                 "Trying to inhale {} access permission, while there is already {}",
                 perm,
                 self.acc[&place]
@@ -378,7 +400,9 @@ impl State {
         if self.pred.contains_key(&place) {
             let new_perm = self.pred[&place] + perm;
             assert!(
-                new_perm == PermAmount::Write || new_perm == PermAmount::Read,
+                new_perm == PermAmount::Write
+                    || new_perm == PermAmount::Read
+                    || new_perm == PermAmount::Wildcard,
                 "Trying to inhale {} predicate permission, while there is already {}",
                 perm,
                 self.pred[&place]
@@ -441,19 +465,22 @@ impl State {
         self.pred.remove(place).unwrap()
     }
 
+    /// Note: removing a wildcard amount never removes `place` from the state.
     pub fn remove_acc(&mut self, place: &vir::Expr, perm: PermAmount) {
         assert!(
             self.acc.contains_key(place),
             "Place {} is not in state (acc), so it can not be removed.",
             place
         );
-        if self.acc[place] == perm {
+        if self.acc[place] == perm && perm != PermAmount::Wildcard {
             self.acc.remove(place);
         } else {
-            self.acc.insert(place.clone(), self.acc[place] - perm);
+            let new_perm = self.acc[place] - perm;
+            self.acc.insert(place.clone(), new_perm);
         }
     }
 
+    /// Note: removing a wildcard amount never removes `place` from the state.
     pub fn remove_pred(&mut self, place: &vir::Expr, perm: PermAmount) {
         trace!("remove_pred {}, {}", place, perm);
         assert!(
@@ -461,10 +488,11 @@ impl State {
             "Place {} is not in state (pred), so it can not be removed.",
             place
         );
-        if self.pred[place] == perm {
+        if self.pred[place] == perm && perm != PermAmount::Wildcard {
             self.pred.remove(place);
         } else {
-            self.pred.insert(place.clone(), self.pred[place] - perm);
+            let new_perm = self.pred[place] - perm;
+            self.pred.insert(place.clone(), new_perm);
         }
     }
 
@@ -475,6 +503,18 @@ impl State {
         };
     }
 
+    /// Remove the place of `item` from the state, whatever the permission amount is.
+    ///
+    /// This is needed when a wildcard permission is converted by a fold or an
+    /// unfold, because the state can not keep the wildcard permission of a
+    /// predicate together with the permissions of its body.
+    pub fn remove_perm_place(&mut self, item: &Perm) -> PermAmount {
+        match item {
+            &Perm::Acc(_, _) => self.remove_acc_place(item.get_place()),
+            &Perm::Pred(_, _) => self.remove_pred_place(item.get_place()),
+        }
+    }
+
     pub fn remove_all_perms<'a, I>(&mut self, items: I)
     where
         I: Iterator<Item = &'a Perm>,
@@ -504,7 +544,7 @@ impl State {
     /// permissions.
     pub fn restore_dropped_perm(&mut self, item: Perm) {
         trace!("[enter] restore_dropped_perm item={}", item);
-        for moved_place in &self.moved {
+        for moved_place in self.moved.iter() {
             trace!("  moved_place={}", moved_place);
         }
         match item {
@@ -602,14 +642,14 @@ impl State {
             self.framing_stack.len()
         );
         let mut framed_perms = PermSet::empty();
-        for (place, perm) in self.acc.clone().into_iter() {
+        for (place, perm) in self.acc.iter() {
             if !place.is_local() {
-                self.acc.remove(&place);
-                framed_perms.add(Perm::Acc(place.clone(), perm));
+                framed_perms.add(Perm::Acc(place.clone(), *perm));
             }
         }
-        for (place, perm) in self.pred.drain() {
-            framed_perms.add(Perm::Pred(place.clone(), perm));
+        self.acc.retain(|place, _| place.is_local());
+        for (place, perm) in mem::replace(&mut self.pred, PlaceMap::new()).iter() {
+            framed_perms.add(Perm::Pred(place.clone(), *perm));
         }
         debug!("Framed permissions: {}", framed_perms);
         self.framing_stack.push(framed_perms);
@@ -116,4 +116,23 @@ impl<'a, 'tcx: 'a> LoopEncoder<'a, 'tcx> {
             .iter()
             .any(|def_init_place| utils::is_prefix(place, def_init_place))
     }
+
+    /// Is the ``place`` definitely initialised before the statement at ``location``?
+    pub fn is_definitely_initialised_at(
+        &self,
+        place: &mir::Place,
+        location: mir::Location,
+    ) -> bool {
+        if location.statement_index == 0 {
+            return self.is_definitely_initialised(place, location.block);
+        }
+        let previous_location = mir::Location {
+            statement_index: location.statement_index - 1,
+            ..location
+        };
+        self.initialization
+            .get_after_statement(previous_location)
+            .iter()
+            .any(|def_init_place| utils::is_prefix(place, def_init_place))
+    }
 }
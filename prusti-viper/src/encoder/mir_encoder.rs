@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use encoder::builtin_encoder::BuiltinFunctionKind;
+use encoder::builtin_encoder::{power_of_two, BitOperation, BuiltinFunctionKind};
 use encoder::error_manager::ErrorCtxt;
 use encoder::vir;
 use encoder::Encoder;
@@ -70,10 +70,8 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
 
     pub fn encode_local(&self, local: mir::Local) -> vir::LocalVar {
         let var_name = self.encode_local_var_name(local);
-        let type_name = self
-            .encoder
-            .encode_type_predicate_use(self.get_local_ty(local));
-        vir::LocalVar::new(var_name, vir::Type::TypedRef(type_name))
+        let typ = self.encoder.encode_type(self.get_local_ty(local));
+        vir::LocalVar::new(var_name, typ)
     }
 
     /// Returns
@@ -126,9 +124,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
                     }
 
                     ty::TypeVariants::TyTuple(elems) => {
-                        let field_name = format!("tuple_{}", field.index());
                         let field_ty = elems[field.index()];
-                        let encoded_field = self.encoder.encode_raw_ref_field(field_name, field_ty);
+                        let encoded_field =
+                            self.encoder.encode_tuple_field(field.index(), field_ty);
                         let encoded_projection = encoded_base.field(encoded_field);
                         (encoded_projection, field_ty, None)
                     }
@@ -141,7 +139,6 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
                             assert_eq!(num_variants, 1);
                             0
                         });
-                        let tcx = self.encoder.env().tcx();
                         let variant_def = &adt_def.variants[variant_index];
                         let encoded_variant = if num_variants != 1 {
                             encoded_base.variant(&variant_def.name.as_str())
@@ -149,7 +146,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
                             encoded_base
                         };
                         let field = &variant_def.fields[field.index()];
-                        let field_ty = field.ty(tcx, subst);
+                        let field_ty = self.encoder.resolve_field_ty(field, subst);
                         let encoded_field = self
                             .encoder
                             .encode_struct_field(&field.ident.as_str(), field_ty);
@@ -168,8 +165,8 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
 
                         let encoded_projection: vir::Expr = tcx.with_freevars(node_id, |freevars| {
                             let freevar = &freevars[field.index()];
-                            let field_name = format!("closure_{}", field.index());
-                            let encoded_field = self.encoder.encode_raw_ref_field(field_name, field_ty);
+                            let encoded_field =
+                                self.encoder.encode_closure_field(field.index(), field_ty);
                             let res = encoded_base.field(encoded_field);
                             let var_name = tcx.hir.name(freevar.var_id()).to_string();
                             trace!("Field {:?} of closure corresponds to variable '{}', encoded as {}", field, var_name, res);
@@ -355,10 +352,109 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
             mir::BinOp::BitAnd if is_bool => vir::Expr::and(left, right),
             mir::BinOp::BitOr if is_bool => vir::Expr::or(left, right),
             mir::BinOp::BitXor if is_bool => vir::Expr::xor(left, right),
+            mir::BinOp::BitAnd
+            | mir::BinOp::BitOr
+            | mir::BinOp::BitXor
+            | mir::BinOp::Shl
+            | mir::BinOp::Shr
+                if self.is_bit_precise() =>
+            {
+                self.encode_bit_op_expr(op, left, right, ty)
+            }
             x => unimplemented!("{:?}", x),
         }
     }
 
+    /// Is the encoded procedure marked with `#[bit_precise]`?
+    fn is_bit_precise(&self) -> bool {
+        self.encoder.env().has_attribute_name(self.def_id, "bit_precise")
+    }
+
+    /// The number of bits of the unsigned operands of a bitwise operation.
+    fn get_bit_width(&self, op: mir::BinOp, ty: ty::Ty<'tcx>) -> u32 {
+        match ty.sty {
+            ty::TypeVariants::TyUint(ast::UintTy::U8) => 8,
+            ty::TypeVariants::TyUint(ast::UintTy::U16) => 16,
+            ty::TypeVariants::TyUint(ast::UintTy::U32) => 32,
+            ty::TypeVariants::TyUint(ast::UintTy::U64)
+            | ty::TypeVariants::TyUint(ast::UintTy::Usize) => 64,
+            ty::TypeVariants::TyUint(ast::UintTy::U128) => 128,
+            _ => unimplemented!("Bitwise operation {:?} on type {:?}", op, ty),
+        }
+    }
+
+    /// Encode a bitwise operation on unsigned integers in a `#[bit_precise]` procedure.
+    /// Shifts by a constant amount and masks `x & (2^k - 1)` are encoded exactly with
+    /// integer arithmetic. The other operations are encoded with builtin functions that
+    /// only bound their result.
+    fn encode_bit_op_expr(
+        &self,
+        op: mir::BinOp,
+        left: vir::Expr,
+        right: vir::Expr,
+        ty: ty::Ty<'tcx>,
+    ) -> vir::Expr {
+        let width = self.get_bit_width(op, ty);
+        let constant = |expr: &vir::Expr| match *expr {
+            vir::Expr::Const(vir::Const::Int(value), _) if value >= 0 => Some(value as u128),
+            vir::Expr::Const(vir::Const::BigInt(ref value), _) => value.parse::<u128>().ok(),
+            _ => None,
+        };
+        // The exponent `k` such that `value == 2^k - 1`.
+        let mask_width = |value: u128| {
+            if value.wrapping_add(1) & value == 0 {
+                Some(128 - value.leading_zeros())
+            } else {
+                None
+            }
+        };
+        let bit_operation = match op {
+            mir::BinOp::Shl => match constant(&right) {
+                Some(shift) if shift < width as u128 => {
+                    return vir::Expr::rem(
+                        vir::Expr::mul(left, power_of_two(shift as u32)),
+                        power_of_two(width),
+                    );
+                }
+                _ => BitOperation::Shl,
+            },
+            mir::BinOp::Shr => match constant(&right) {
+                Some(shift) if shift < width as u128 => {
+                    return vir::Expr::div(left, power_of_two(shift as u32));
+                }
+                _ => BitOperation::Shr,
+            },
+            mir::BinOp::BitAnd => {
+                match (constant(&left).and_then(mask_width), constant(&right).and_then(mask_width)) {
+                    (_, Some(bits)) if bits < width => {
+                        return vir::Expr::rem(left, power_of_two(bits));
+                    }
+                    (Some(bits), _) if bits < width => {
+                        return vir::Expr::rem(right, power_of_two(bits));
+                    }
+                    _ => BitOperation::And,
+                }
+            }
+            mir::BinOp::BitOr => BitOperation::Or,
+            mir::BinOp::BitXor => BitOperation::Xor,
+            _ => unreachable!("{:?}", op),
+        };
+        let function_name = self
+            .encoder
+            .encode_builtin_function_use(BuiltinFunctionKind::BitOperation(bit_operation, width));
+        let formal_args = vec![
+            vir::LocalVar::new("left", vir::Type::Int),
+            vir::LocalVar::new("right", vir::Type::Int),
+        ];
+        vir::Expr::func_app(
+            function_name,
+            vec![left, right],
+            formal_args,
+            vir::Type::Int,
+            vir::Position::default(),
+        )
+    }
+
     pub fn encode_unary_op_expr(&self, op: mir::UnOp, expr: vir::Expr) -> vir::Expr {
         match op {
             mir::UnOp::Not => vir::Expr::not(expr),
@@ -441,6 +537,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> MirEncoder<'p, 'v, 'r, 'a, 'tcx> {
                     }
                 },
 
+                mir::BinOp::Shl | mir::BinOp::Shr if self.is_bit_precise() => {
+                    // The shift amount overflows if it is not smaller than the number of bits.
+                    let width = self.get_bit_width(op, ty);
+                    vir::Expr::ge_cmp(right, width.into())
+                }
+
                 mir::BinOp::Shl | mir::BinOp::Shr => {
                     debug!("Encoding of bin op check '{:?}' is incomplete", op);
                     false.into()
@@ -5,9 +5,13 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 pub use self::encoder::Encoder;
+pub use self::timing_report::VerificationPhase;
 
 mod borrows;
+mod unsupported_fallback;
 mod builtin_encoder;
+mod deref_spec;
+mod domain_encoder;
 mod encoder;
 mod error_manager;
 mod foldunfold;
@@ -15,11 +19,20 @@ mod initialisation;
 mod loop_encoder;
 mod mir_encoder;
 mod mir_interpreter;
+mod model_fields;
 mod optimiser;
 mod places;
 mod procedure_encoder;
 mod pure_function_encoder;
+mod purity_inference;
+mod snapshot_encoder;
 mod spec_encoder;
+mod std_models_encoder;
+mod suggestions;
+mod symbol_table;
+mod sync_models_encoder;
+mod timing_report;
+mod trusted_surface;
 mod type_encoder;
 mod utils;
 
@@ -0,0 +1,138 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Checks of the fields of structs and enums that are marked with an attribute.
+//!
+//! A field marked with `#[model]` is a ghost field that abstracts the private state of its
+//! type, for example a sequence that models the content of a ring buffer. It is encoded like
+//! the other fields, so it is part of the predicate of the type and specifications can mention
+//! it, but its value is only constrained by the type invariants that link it to the concrete
+//! fields. Only the module that defines the type maintains the model: outside of it, executable
+//! code can neither read nor update a model field, which can only be used in specifications.
+
+use encoder::Encoder;
+use prusti_interface::data::ProcedureDefId;
+use rustc::mir;
+use rustc::mir::visit::{PlaceContext, Visitor};
+use rustc::ty;
+
+/// The attribute that marks a field as a model field.
+pub const MODEL_FIELD_ATTR: &str = "model";
+
+/// The field marked with `attr_name`, with the definition of its struct or enum, that contains
+/// `place`. A field that is only dereferenced is not accessed by an access to `place`.
+pub fn find_annotated_field<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    mir: &mir::Mir<'tcx>,
+    place: &mir::Place<'tcx>,
+    attr_name: &str,
+) -> Option<(ProcedureDefId, &'tcx ty::FieldDef)> {
+    let tcx = encoder.env().tcx();
+    let mut current_place = place;
+    while let mir::Place::Projection(box mir::Projection { ref base, ref elem }) = *current_place {
+        match *elem {
+            mir::ProjectionElem::Deref => return None,
+            mir::ProjectionElem::Field(field, _) => {
+                let opt_variant = match base.ty(mir, tcx) {
+                    mir::tcx::PlaceTy::Ty { ty } => match ty.sty {
+                        ty::TypeVariants::TyAdt(adt_def, _) if adt_def.variants.len() == 1 => {
+                            Some((adt_def, &adt_def.variants[0]))
+                        }
+                        _ => None,
+                    },
+                    mir::tcx::PlaceTy::Downcast {
+                        adt_def,
+                        variant_index,
+                        ..
+                    } => Some((adt_def, &adt_def.variants[variant_index])),
+                };
+                if let Some((adt_def, variant_def)) = opt_variant {
+                    let field_def = &variant_def.fields[field.index()];
+                    if encoder.env().has_attribute_name(field_def.did, attr_name) {
+                        return Some((adt_def.did, field_def));
+                    }
+                }
+            }
+            _ => {}
+        }
+        current_place = base;
+    }
+    None
+}
+
+/// Is the procedure `proc_def_id` in the module that defines the struct or enum `adt_def_id`?
+pub fn is_in_defining_module<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    adt_def_id: ProcedureDefId,
+) -> bool {
+    let tcx = encoder.env().tcx();
+    tcx.parent(adt_def_id)
+        .map_or(false, |module| tcx.is_descendant_of(proc_def_id, module))
+}
+
+/// Report an error for each read of a model field in `mir`, the body of the procedure
+/// `proc_def_id`, outside of the module that defines the type of the field.
+pub fn check_model_field_reads<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    mir: &mir::Mir<'tcx>,
+) {
+    let mut checker = ModelFieldReadChecker {
+        encoder,
+        proc_def_id,
+        mir,
+    };
+    checker.visit_mir(mir);
+}
+
+struct ModelFieldReadChecker<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
+    encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    mir: &'p mir::Mir<'tcx>,
+}
+
+impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> Visitor<'tcx>
+    for ModelFieldReadChecker<'p, 'v, 'r, 'a, 'tcx>
+{
+    fn visit_place(
+        &mut self,
+        place: &mir::Place<'tcx>,
+        context: PlaceContext<'tcx>,
+        location: mir::Location,
+    ) {
+        // A mutable borrow of a model field is an update of the model, which is checked
+        // together with the other writes.
+        let is_read = match context {
+            PlaceContext::Copy | PlaceContext::Move | PlaceContext::Inspect => true,
+            PlaceContext::Borrow {
+                kind: mir::BorrowKind::Shared,
+                ..
+            } => true,
+            _ => false,
+        };
+        if !is_read {
+            return;
+        }
+        if let Some((adt_def_id, field_def)) =
+            find_annotated_field(self.encoder, self.mir, place, MODEL_FIELD_ATTR)
+        {
+            if is_in_defining_module(self.encoder, self.proc_def_id, adt_def_id) {
+                return;
+            }
+            let tcx = self.encoder.env().tcx();
+            self.encoder.env().span_err(
+                self.mir.source_info(location).span,
+                &format!(
+                    "[Prusti] the model field `{}` of `{}` can only be read in specifications \
+                     outside of its module",
+                    field_def.ident,
+                    tcx.item_path_str(adt_def_id)
+                ),
+            );
+        }
+    }
+}
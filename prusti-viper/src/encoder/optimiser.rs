@@ -11,21 +11,35 @@ use std::mem;
 /// Optimisations currently done:
 ///
 /// 1.  Replace all `old(...)` inside `forall ..` with `let tmp == (old(..)) in forall ..`.
-/// 2.  Pull out all `unfolding ... in` that are inside `forall` to outside of `forall`.
+/// 2.  Pull out all `unfolding ... in` that are inside `forall` to outside of `forall`, if the
+///     unfolded predicate instance does not depend on the bound variables. The other
+///     unfoldings are left in place and reported as unsupported.
 /// 3.  Replace all arithmetic expressions inside `forall` that do not depend on bound variables
 ///     with `let tmp == (...) in forall ..`.
 ///
+/// The same is done for `exists ..`. The variables of the `let` expressions inside the body of
+/// a quantifier are treated like the quantified variables, because the expressions that use
+/// them cannot be moved out of the `let`.
+///
 /// Note: this seems to be required to workaround some Silicon incompleteness.
-pub fn rewrite(cfg: vir::CfgMethod) -> vir::CfgMethod {
+///
+/// Returns the optimised method and the description of the unfoldings that could not be
+/// pulled out of a quantifier.
+pub fn rewrite(cfg: vir::CfgMethod) -> (vir::CfgMethod, Vec<String>) {
     let mut optimiser = Optimiser::new();
-    optimiser.replace_cfg(cfg)
+    let cfg = optimiser.replace_cfg(cfg);
+    (cfg, optimiser.unsupported_unfoldings)
 }
 
-struct Optimiser {}
+struct Optimiser {
+    unsupported_unfoldings: Vec<String>,
+}
 
 impl Optimiser {
     fn new() -> Self {
-        Self {}
+        Self {
+            unsupported_unfoldings: vec![],
+        }
     }
 
     fn replace_cfg(&mut self, mut cfg: vir::CfgMethod) -> vir::CfgMethod {
@@ -52,11 +66,13 @@ impl Optimiser {
 
     fn replace_expr_unfolding(&mut self, expr: vir::Expr) -> vir::Expr {
         let mut unfolding_extractor = UnfoldingExtractor {
-            unfoldings: HashMap::new(),
-            in_quantifier: false,
+            scopes: vec![],
+            unsupported: vec![],
         };
         use self::vir::ExprFolder;
-        unfolding_extractor.fold(expr)
+        let pulled_unfolding = unfolding_extractor.fold(expr);
+        self.unsupported_unfoldings.extend(unfolding_extractor.unsupported);
+        pulled_unfolding
     }
 }
 
@@ -71,15 +87,25 @@ impl vir::StmtFolder for Optimiser {
         let replaced_old = self.replace_expr_old(pulled_unfodling);
         vir::Stmt::Assert(replaced_old, folding, pos)
     }
+    fn fold_refute(&mut self, expr: vir::Expr, pos: vir::Position) -> vir::Stmt {
+        let pulled_unfodling = self.replace_expr_unfolding(expr);
+        let replaced_old = self.replace_expr_old(pulled_unfodling);
+        vir::Stmt::Refute(replaced_old, pos)
+    }
     fn fold_inhale(&mut self, expr: vir::Expr, folding: vir::FoldingBehaviour) -> vir::Stmt {
         let pulled_unfodling = self.replace_expr_unfolding(expr);
         vir::Stmt::Inhale(pulled_unfodling, folding)
     }
 }
 
-impl vir::ExprFolder for Optimiser {
-    fn fold_forall(
+/// The constructor of a quantifier: `vir::Expr::ForAll` or `vir::Expr::Exists`.
+type QuantifierConstructor =
+    fn(Vec<vir::LocalVar>, Vec<vir::Trigger>, Box<vir::Expr>, vir::Position) -> vir::Expr;
+
+impl Optimiser {
+    fn replace_quantifier(
         &mut self,
+        quantifier: QuantifierConstructor,
         variables: Vec<vir::LocalVar>,
         triggers: Vec<vir::Trigger>,
         body: Box<vir::Expr>,
@@ -89,16 +115,41 @@ impl vir::ExprFolder for Optimiser {
         let mut replacer = Replacer::new(&variables);
         let replaced_body = replacer.fold_boxed(body);
         debug!("replaced body: {}", replaced_body);
-        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, pos.clone());
+        let mut quantified = quantifier(variables, triggers, replaced_body, pos.clone());
 
         if replacer.counter > 0 {
-            for (expr, variable) in replacer.map {
-                forall = vir::Expr::LetExpr(variable, box expr, box forall, pos.clone());
+            // Sort the bindings, so that the encoding does not depend on the iteration order
+            // of the map.
+            let mut bindings: Vec<_> = replacer.map.into_iter().collect();
+            bindings.sort_by_cached_key(|(expr, _)| expr.canonical_string());
+            for (expr, variable) in bindings {
+                quantified = vir::Expr::LetExpr(variable, box expr, box quantified, pos.clone());
             }
-            debug!("replaced quantifier: {}", forall);
+            debug!("replaced quantifier: {}", quantified.canonical_string());
         }
 
-        forall
+        quantified
+    }
+}
+
+impl vir::ExprFolder for Optimiser {
+    fn fold_forall(
+        &mut self,
+        variables: Vec<vir::LocalVar>,
+        triggers: Vec<vir::Trigger>,
+        body: Box<vir::Expr>,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        self.replace_quantifier(vir::Expr::ForAll, variables, triggers, body, pos)
+    }
+    fn fold_exists(
+        &mut self,
+        variables: Vec<vir::LocalVar>,
+        triggers: Vec<vir::Trigger>,
+        body: Box<vir::Expr>,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        self.replace_quantifier(vir::Expr::Exists, variables, triggers, body, pos)
     }
 }
 
@@ -176,39 +227,96 @@ impl vir::ExprFolder for Replacer {
             original_expr
         }
     }
+    fn fold_let_expr(
+        &mut self,
+        variable: vir::LocalVar,
+        expr: Box<vir::Expr>,
+        body: Box<vir::Expr>,
+        pos: vir::Position
+    ) -> vir::Expr {
+        let folded_expr = self.fold_boxed(expr);
+        self.bound_vars.push(variable.clone().into());
+        let folded_body = self.fold_boxed(body);
+        self.bound_vars.pop();
+        vir::Expr::LetExpr(variable, folded_expr, folded_body, pos)
+    }
+}
+
+/// An unfolding pulled out of a quantifier: the name of the predicate, its arguments,
+/// its permission amount, its variant and its position.
+type PulledUnfolding =
+    (String, Vec<vir::Expr>, vir::PermAmount, vir::MaybeEnumVariantIndex, vir::Position);
+
+/// A quantifier whose body is being visited.
+struct QuantifierScope {
+    bound_vars: Vec<vir::Expr>,
+    /// The unfoldings that will be wrapped around the quantifier, outermost first.
+    unfoldings: Vec<PulledUnfolding>,
 }
 
 struct UnfoldingExtractor {
-    unfoldings: HashMap<(String, Vec<vir::Expr>),
-                        (vir::PermAmount, vir::MaybeEnumVariantIndex, vir::Position)>,
-    in_quantifier: bool,
+    /// The quantifiers enclosing the visited expression, outermost first.
+    scopes: Vec<QuantifierScope>,
+    /// The description of the unfoldings that could not be pulled out of a quantifier.
+    unsupported: Vec<String>,
 }
 
-impl vir::ExprFolder for UnfoldingExtractor {
-    fn fold_forall(
+impl UnfoldingExtractor {
+    fn extract_from_quantifier(
         &mut self,
+        quantifier: QuantifierConstructor,
         variables: Vec<vir::LocalVar>,
         triggers: Vec<vir::Trigger>,
         body: Box<vir::Expr>,
         pos: vir::Position,
     ) -> vir::Expr {
-        assert!(self.unfoldings.is_empty(), "Nested quantifiers are not supported.");
         debug!("original body: {}", body);
 
-        self.in_quantifier = true;
+        self.scopes.push(QuantifierScope {
+            bound_vars: variables.iter().cloned().map(|v| v.into()).collect(),
+            unfoldings: vec![],
+        });
         let replaced_body = self.fold_boxed(body);
-        self.in_quantifier = false;
-
-        let mut forall = vir::Expr::ForAll(variables, triggers, replaced_body, pos.clone());
+        let scope = self.scopes.pop().unwrap();
 
-        let unfoldings = mem::replace(&mut self.unfoldings, HashMap::new());
+        let mut quantified = quantifier(variables, triggers, replaced_body, pos.clone());
 
-        for ((name, args), (perm_amount, variant, _)) in unfoldings {
-            forall = vir::Expr::Unfolding(name, args, box forall, perm_amount, variant, pos.clone());
+        // The first pulled unfolding might be needed to evaluate the arguments of the
+        // next ones, so it has to be the outermost.
+        for (name, args, perm_amount, variant, _) in scope.unfoldings.into_iter().rev() {
+            quantified = vir::Expr::Unfolding(
+                name,
+                args,
+                box quantified,
+                perm_amount,
+                variant,
+                pos.clone(),
+            );
         }
-        debug!("replaced quantifier: {}", forall);
+        debug!("replaced quantifier: {}", quantified.canonical_string());
 
-        forall
+        quantified
+    }
+}
+
+impl vir::ExprFolder for UnfoldingExtractor {
+    fn fold_forall(
+        &mut self,
+        variables: Vec<vir::LocalVar>,
+        triggers: Vec<vir::Trigger>,
+        body: Box<vir::Expr>,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        self.extract_from_quantifier(vir::Expr::ForAll, variables, triggers, body, pos)
+    }
+    fn fold_exists(
+        &mut self,
+        variables: Vec<vir::LocalVar>,
+        triggers: Vec<vir::Trigger>,
+        body: Box<vir::Expr>,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        self.extract_from_quantifier(vir::Expr::Exists, variables, triggers, body, pos)
     }
     fn fold_unfolding(
         &mut self,
@@ -219,12 +327,56 @@ impl vir::ExprFolder for UnfoldingExtractor {
         variant: vir::MaybeEnumVariantIndex,
         pos: vir::Position,
     ) -> vir::Expr {
-        if self.in_quantifier {
-            self.unfoldings.insert((name, args), (perm, variant, pos));
-            self.fold(*expr)
-        } else {
-            vir::Expr::Unfolding(name, args, expr, perm, variant, pos)
+        if self.scopes.is_empty() {
+            let folded_expr = self.fold_boxed(expr);
+            return vir::Expr::Unfolding(name, args, folded_expr, perm, variant, pos);
+        }
+
+        // The innermost quantifier whose variables are used by the arguments.
+        let depending_scope = self.scopes.iter().rposition(|scope| {
+            scope.bound_vars.iter().any(|var| args.iter().any(|arg| arg.find(var)))
+        });
+        let target_scope = depending_scope.map_or(0, |index| index + 1);
+
+        if target_scope == self.scopes.len() {
+            // The predicate instance depends on the innermost quantifier.
+            let bound_vars = &self.scopes[target_scope - 1].bound_vars;
+            let description = format!(
+                "unfolding of {}({}), which depends on the quantified variables {}",
+                name,
+                args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", "),
+                bound_vars.iter().map(|var| var.to_string()).collect::<Vec<_>>().join(", "),
+            );
+            debug!("Cannot pull out of a quantifier the {}", description);
+            self.unsupported.push(description);
+            let folded_expr = self.fold_boxed(expr);
+            return vir::Expr::Unfolding(name, args, folded_expr, perm, variant, pos);
+        }
+
+        let unfoldings = &mut self.scopes[target_scope].unfoldings;
+        if !unfoldings.iter().any(|u| u.0 == name && u.1 == args) {
+            unfoldings.push((name, args, perm, variant, pos));
+        }
+        self.fold(*expr)
+    }
+    fn fold_let_expr(
+        &mut self,
+        variable: vir::LocalVar,
+        expr: Box<vir::Expr>,
+        body: Box<vir::Expr>,
+        pos: vir::Position
+    ) -> vir::Expr {
+        let folded_expr = self.fold_boxed(expr);
+        // The unfoldings that depend on the variable cannot be pulled out of the `let`.
+        let is_in_quantifier = !self.scopes.is_empty();
+        if is_in_quantifier {
+            self.scopes.last_mut().unwrap().bound_vars.push(variable.clone().into());
+        }
+        let folded_body = self.fold_boxed(body);
+        if is_in_quantifier {
+            self.scopes.last_mut().unwrap().bound_vars.pop();
         }
+        vir::Expr::LetExpr(variable, folded_expr, folded_body, pos)
     }
     fn fold_labelled_old(
         &mut self,
@@ -4,27 +4,41 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use encoder::borrows::ProcedureContract;
+use encoder::borrows::{AccessorInfo, ProcedureContract};
 use encoder::builtin_encoder::BuiltinMethodKind;
+use encoder::deref_spec::{encode_loc, encode_region_accesses, is_region_pointer_type};
 use encoder::error_manager::ErrorCtxt;
 use encoder::error_manager::PanicCause;
+use encoder::error_manager::ReachabilityCheckKind;
 use encoder::foldunfold;
 use encoder::initialisation::InitInfo;
 use encoder::loop_encoder::LoopEncoder;
 use encoder::mir_encoder::MirEncoder;
 use encoder::mir_encoder::{POSTCONDITION_LABEL, PRECONDITION_LABEL};
+use encoder::model_fields::{
+    check_model_field_reads, find_annotated_field, is_in_defining_module, MODEL_FIELD_ATTR,
+};
 use encoder::optimiser;
 use encoder::places::{Local, LocalVariableManager, Place};
-use encoder::vir::fixes::{fix_ghost_vars, havoc_assigned_locals};
+use encoder::std_models_encoder::{encode_std_model_contract, encode_std_model_pledge};
+use encoder::suggestions;
+use encoder::sync_models_encoder::encode_sync_model_obligation;
+use encoder::trusted_surface::TrustedItemKind;
+use encoder::vir::fixes::{
+    fix_default_positions, fix_ghost_vars, havoc_assigned_locals, lower_refutations,
+};
 use encoder::vir::optimisations::methods::{
-    remove_trivial_assertions, remove_unused_vars, remove_empty_if
+    remove_trivial_assertions, remove_unused_vars, remove_empty_if, remove_fold_unfold_markers
 };
+use encoder::vir::optimisations::simplifier::Simplifier;
+use encoder::vir::passes::MethodPipeline;
 use encoder::vir::{ExprIterator, FoldingBehaviour};
 use encoder::vir::{self, CfgBlockIndex, Successor};
-use encoder::Encoder;
+use encoder::{Encoder, VerificationPhase};
 use prusti_interface::config;
 use prusti_interface::data::ProcedureDefId;
 use prusti_interface::environment::borrowck::facts;
+use prusti_interface::environment::library_models::LibraryModel;
 use prusti_interface::environment::polonius_info::{LoanPlaces, PoloniusInfo};
 use prusti_interface::environment::polonius_info::{
     ReborrowingDAG, ReborrowingDAGNode,
@@ -35,17 +49,23 @@ use prusti_interface::environment::PermissionKind;
 use prusti_interface::environment::Procedure;
 use prusti_interface::report::log;
 use prusti_interface::specifications::*;
+use prusti_interface::utils::is_prefix;
 use rustc::hir::Mutability;
 use rustc::mir;
 use rustc::mir::TerminatorKind;
 use rustc::ty;
 use rustc::ty::layout;
+use rustc::ty::DefIdTree;
 use rustc::ty::layout::IntegerExt;
 use rustc_data_structures::indexed_vec::Idx;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fs::{create_dir_all, File};
+use std::path::PathBuf;
+use std::time::Instant;
 use syntax::attr::SignedInt;
-use syntax::codemap::MultiSpan;
+use syntax::codemap::{MultiSpan, Span};
 use utils::to_string::ToString;
 
 pub struct ProcedureEncoder<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
@@ -59,6 +79,7 @@ pub struct ProcedureEncoder<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
     auxiliar_local_vars: HashMap<String, vir::Type>,
     mir_encoder: MirEncoder<'p, 'v, 'r, 'a, 'tcx>,
     check_panics: bool,
+    check_debug_assertions: bool,
     check_fold_unfold_state: bool,
     polonius_info: PoloniusInfo<'p, 'tcx>,
     label_after_location: HashMap<mir::Location, String>,
@@ -118,6 +139,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             auxiliar_local_vars: HashMap::new(),
             mir_encoder: mir_encoder,
             check_panics: config::check_panics(),
+            check_debug_assertions: config::check_debug_assertions(),
             check_fold_unfold_state: config::check_foldunfold_state(),
             polonius_info: PoloniusInfo::new(procedure),
             label_after_location: HashMap::new(),
@@ -135,6 +157,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
 
     pub fn encode(mut self) -> vir::CfgMethod {
         trace!("Encode procedure {}", self.cfg_method.name());
+        let start = Instant::now();
+
+        check_model_field_reads(self.encoder, self.proc_def_id, self.mir);
 
         let mut procedure_contract = self
             .encoder
@@ -144,103 +169,82 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         let mut postcondition_strengthening: Option<TypedAssertion> = None;
         debug!("procedure_contract: {:?}", &procedure_contract);
         //trace!("def_id of proc: {:?}", &self.proc_def_id);
-        let impl_def_id = self.encoder.env().tcx().impl_of_method(self.proc_def_id);
-        //trace!("def_id of impl: {:?}", &impl_def_id);
-        if let Some(id) = impl_def_id {
-            let def_id_trait = self.encoder.env().tcx().trait_id_of_impl(id);
-            //trace!("def_id of trait: {:?}", &def_id_trait);
-            // Trait implementation method refinement
-            // Choosing alternative C as discussed in
-            // https://ethz.ch/content/dam/ethz/special-interest/infk/chair-program-method/pm/documents/Education/Theses/Matthias_Erdin_MA_report.pdf
-            // pp 19-23
-            if let Some(id) = def_id_trait {
-                let proc_name = self
-                    .encoder
-                    .env()
-                    .tcx()
-                    .item_name(self.proc_def_id)
-                    .to_string();
-                //trace!("proc_name: {:?}", proc_name);
-                let assoc_items: Vec<_> = self.encoder.env().tcx().associated_items(id).collect();
-                //trace!("assoc items: {:?}", &assoc_items);
-                for assoc_item in assoc_items {
-                    if assoc_item.name == proc_name {
-                        // TODO use the impl's specs if there are any (separately replace pre/post!)
-                        let procedure_trait_contract = self
-                            .encoder
-                            .get_procedure_contract_for_def(assoc_item.def_id);
-                        let (proc_pre_specs, proc_post_specs) = {
-                            if let SpecificationSet::Procedure(ref mut pre, ref mut post) = procedure_contract.specification {
-                                (pre, post)
-                            } else {
-                                unreachable!("Unexpected: {:?}", procedure_trait_contract.specification)
-                            }
-                        };
+        // Trait implementation method refinement
+        // Choosing alternative C as discussed in
+        // https://ethz.ch/content/dam/ethz/special-interest/infk/chair-program-method/pm/documents/Education/Theses/Matthias_Erdin_MA_report.pdf
+        // pp 19-23
+        if let Some(trait_method_def_id) =
+            self.encoder.get_implemented_trait_method(self.proc_def_id)
+        {
+            let procedure_trait_contract = self
+                .encoder
+                .get_procedure_contract_for_def(trait_method_def_id);
+            let (proc_pre_specs, proc_post_specs) = {
+                if let SpecificationSet::Procedure(ref mut pre, ref mut post) = procedure_contract.specification {
+                    (pre, post)
+                } else {
+                    unreachable!("Unexpected: {:?}", procedure_trait_contract.specification)
+                }
+            };
 
-                        if proc_pre_specs.is_empty() {
-                            proc_pre_specs.extend_from_slice(procedure_trait_contract.functional_precondition())
-                        } else {
-                            let proc_pre = Assertion {
-                                kind: Box::new(AssertionKind::And(
-                                    proc_pre_specs.iter()
-                                        .map(|spe| spe.assertion.clone())
-                                        .collect()
-                                ))
-                            };
-                            let proc_trait_pre = Assertion {
-                                kind: Box::new(AssertionKind::And(
-                                    procedure_trait_contract.functional_precondition()
-                                        .iter()
-                                        .map(|spe| spe.assertion.clone())
-                                        .collect()
-                                ))
-                            };
-                            precondition_weakening = Some(Assertion {
-                                kind: Box::new(AssertionKind::Implies(
-                                    proc_trait_pre,
-                                    proc_pre,
-                                ))
-                            });
-                        }
+            if proc_pre_specs.is_empty() {
+                proc_pre_specs.extend_from_slice(procedure_trait_contract.functional_precondition())
+            } else {
+                let proc_pre = Assertion {
+                    kind: Box::new(AssertionKind::And(
+                        proc_pre_specs.iter()
+                            .map(|spe| spe.assertion.clone())
+                            .collect()
+                    ))
+                };
+                let proc_trait_pre = Assertion {
+                    kind: Box::new(AssertionKind::And(
+                        procedure_trait_contract.functional_precondition()
+                            .iter()
+                            .map(|spe| spe.assertion.clone())
+                            .collect()
+                    ))
+                };
+                precondition_weakening = Some(Assertion {
+                    kind: Box::new(AssertionKind::Implies(
+                        proc_trait_pre,
+                        proc_pre,
+                    ))
+                });
+            }
 
-                        if proc_post_specs.is_empty() {
-                            proc_post_specs.extend_from_slice(procedure_trait_contract.functional_postcondition())
-                        } else {
-                            let proc_post = Assertion {
-                                kind: Box::new(AssertionKind::And(
-                                    proc_post_specs.iter()
-                                        .map(|spe| spe.assertion.clone())
-                                        .collect()
-                                ))
-                            };
-                            let proc_trait_post = Assertion {
-                                kind: Box::new(AssertionKind::And(
-                                    procedure_trait_contract.functional_postcondition()
-                                        .iter()
-                                        .map(|spe| spe.assertion.clone())
-                                        .collect()
-                                ))
-                            };
-                            postcondition_strengthening = Some(Assertion {
-                                kind: Box::new(AssertionKind::Implies(
-                                    proc_post,
-                                    proc_trait_post,
-                                ))
-                            });
-                        }
-                    }
-                }
+            if proc_post_specs.is_empty() {
+                proc_post_specs.extend_from_slice(procedure_trait_contract.functional_postcondition())
+            } else {
+                let proc_post = Assertion {
+                    kind: Box::new(AssertionKind::And(
+                        proc_post_specs.iter()
+                            .map(|spe| spe.assertion.clone())
+                            .collect()
+                    ))
+                };
+                let proc_trait_post = Assertion {
+                    kind: Box::new(AssertionKind::And(
+                        procedure_trait_contract.functional_postcondition()
+                            .iter()
+                            .map(|spe| spe.assertion.clone())
+                            .collect()
+                    ))
+                };
+                postcondition_strengthening = Some(Assertion {
+                    kind: Box::new(AssertionKind::Implies(
+                        proc_post,
+                        proc_trait_post,
+                    ))
+                });
             }
         }
 
         // Formal return
         for local in self.mir.local_decls.indices().take(1) {
             let name = self.mir_encoder.encode_local_var_name(local);
-            let type_name = self
-                .encoder
-                .encode_type_predicate_use(self.mir_encoder.get_local_ty(local));
-            self.cfg_method
-                .add_formal_return(&name, vir::Type::TypedRef(type_name))
+            let typ = self.encoder.encode_type(self.mir_encoder.get_local_ty(local));
+            self.cfg_method.add_formal_return(&name, typ)
         }
 
         let mut cfg_edges: HashMap<BasicBlockIndex, HashMap<BasicBlockIndex, CfgBlockIndex>> =
@@ -340,13 +344,23 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         self.encode_preconditions(start_cfg_block, &mut procedure_contract, precondition_weakening);
 
         // Encode postcondition
-        self.encode_postconditions(return_cfg_block, &mut procedure_contract, postcondition_strengthening);
+        self.encode_postconditions(
+            start_cfg_block,
+            return_cfg_block,
+            &mut procedure_contract,
+            postcondition_strengthening,
+        );
 
         // Encode statements
         for bbi in self.procedure.get_reachable_cfg_blocks() {
             self.encode_block(bbi, &cfg_edges, &mut procedure_contract, return_cfg_block);
         }
 
+        // Check that the code of the procedure is reachable
+        if config::check_unreachability() {
+            self.encode_reachability_checks(start_cfg_block, return_cfg_block, &cfg_edges);
+        }
+
         let local_vars: Vec<_> = self
             .locals
             .iter()
@@ -354,14 +368,13 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             .collect();
         for local in local_vars.iter() {
             let local_ty = self.locals.get_type(*local);
-            if let ty::TypeVariants::TyClosure(..) = local_ty.sty {
+            if self.is_borrowing_closure(local_ty) {
                 // Do not encode closures
                 continue;
             }
-            let type_name = self.encoder.encode_type_predicate_use(local_ty);
+            let typ = self.encoder.encode_type(local_ty);
             let var_name = self.locals.get_name(*local);
-            self.cfg_method
-                .add_local_var(&var_name, vir::Type::TypedRef(type_name));
+            self.cfg_method.add_local_var(&var_name, typ);
         }
 
         /*
@@ -450,31 +463,65 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             self.mir.span,
             ErrorCtxt::Unexpected,
         );
+        let fold_unfold_start = Instant::now();
         let method_with_fold_unfold = foldunfold::add_fold_unfold(
-            self.encoder, self.cfg_method, loan_positions, method_pos);
-
-        // Fix variable declarations.
-        let mut fixed_method = fix_ghost_vars(method_with_fold_unfold);
+            self.encoder, self.cfg_method, loan_positions, method_pos.clone());
+        let fold_unfold_duration = fold_unfold_start.elapsed();
 
         let havoc_methods = self.encoder.encode_havoc_methods();
-        if config::use_assume_false_back_edges() {
-            havoc_assigned_locals(&mut fixed_method, &havoc_methods);
-        }
-
-        // Do some optimizations
-        let final_method = if config::simplify_encoding() {
-            optimiser::rewrite(
-                remove_trivial_assertions(
-                    remove_unused_vars(
-                        remove_empty_if(
-                            fixed_method
-                        )
-                    )
-                )
+        let unsupported_unfoldings = RefCell::new(vec![]);
+        let (final_method, pass_statistics) = {
+            let mut pipeline = MethodPipeline::new();
+            // Fix variable declarations.
+            pipeline.add_pass("fix_ghost_vars", fix_ghost_vars);
+            if config::use_assume_false_back_edges() {
+                pipeline.add_pass("havoc_assigned_locals", |mut method| {
+                    havoc_assigned_locals(&mut method, &havoc_methods);
+                    method
+                });
+            }
+            // Do some optimizations
+            if config::simplify_encoding() {
+                pipeline.add_pass("remove_fold_unfold_markers", remove_fold_unfold_markers);
+                pipeline.add_pass("remove_empty_if", remove_empty_if);
+                pipeline.add_pass("remove_unused_vars", remove_unused_vars);
+                pipeline.add_pass("simplify", |method| method.simplify());
+                pipeline.add_pass("remove_trivial_assertions", remove_trivial_assertions);
+                pipeline.add_pass("optimiser", |method| {
+                    let (optimised_method, unsupported) = optimiser::rewrite(method);
+                    unsupported_unfoldings.borrow_mut().extend(unsupported);
+                    optimised_method
+                });
+            }
+            // Give a position to the statements that can fail.
+            pipeline.add_pass("fix_default_positions", |method| {
+                fix_default_positions(method, method_pos.clone())
+            });
+            // Viper does not support refute statements.
+            pipeline.add_pass("lower_refutations", lower_refutations);
+            pipeline.run(
+                method_with_fold_unfold,
+                &format!("{}.{}", source_filename, method_name),
             )
-        } else {
-            fixed_method
         };
+        for description in unsupported_unfoldings.into_inner() {
+            self.encoder.env().span_warn(
+                self.mir.span,
+                &format!(
+                    "[Prusti: unsupported feature] the {} cannot be moved out of \
+                     the quantifier; verification might fail or be slow",
+                    description
+                ),
+            );
+        }
+        if config::dump_debug_info() {
+            let report: Vec<String> = pass_statistics.iter().map(|s| s.to_string()).collect();
+            log::report(
+                "vir_passes",
+                format!("{}.{}.txt", source_filename, method_name),
+                report.join("\n"),
+            );
+        }
 
         // Dump final CFG
         if config::dump_debug_info() {
@@ -484,6 +531,30 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 |writer| final_method.to_graphviz(writer),
             );
         }
+        if let Some(dump_dir) = config::dump_cfg_dir() {
+            let dump_path = PathBuf::from(dump_dir).join(source_filename);
+            create_dir_all(&dump_path).unwrap();
+            let file_path = dump_path.join(format!("{}.dot", method_name));
+            let mut file = File::create(&file_path).unwrap_or_else(|e| {
+                panic!("Failed to write the CFG to {:?}: {}", file_path, e)
+            });
+            final_method.to_graphviz(&mut file);
+        }
+
+        if config::report_verification_times() {
+            let mut timing_report = self.encoder.timing_report();
+            timing_report.register_procedure(
+                &method_name,
+                self.encoder.env().get_absolute_item_name(self.proc_def_id),
+                self.mir.span,
+            );
+            timing_report.record(&method_name, VerificationPhase::FoldUnfold, fold_unfold_duration);
+            timing_report.record(
+                &method_name,
+                VerificationPhase::Encoding,
+                start.elapsed() - fold_unfold_duration,
+            );
+        }
 
         final_method
     }
@@ -506,6 +577,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
         self.encode_statements(bbi, cfg_block, cfg_edges);
         self.encode_terminators(bbi, cfg_edges, procedure_contract, return_cfg_block);
+        self.encode_loop_exit_frames(bbi, cfg_edges);
         self.encode_loop_invariant_exhale(bbi, cfg_edges);
     }
 
@@ -541,6 +613,48 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Add an `EndFrame` statement for each loop that an outgoing edge leaves from the loop body,
+    /// e.g. because of a `break`, a `return` or the failed pattern match of a `while let`.
+    ///
+    /// The edges that leave a loop from its head already end the frame of that loop when they
+    /// inhale the loop invariant. The value of a `break` is assigned by MIR to the local of the
+    /// loop expression in a block after the edge, so it needs no special encoding.
+    fn encode_loop_exit_frames(
+        &mut self,
+        bbi: BasicBlockIndex,
+        cfg_edges: &HashMap<BasicBlockIndex, HashMap<BasicBlockIndex, CfgBlockIndex>>,
+    ) {
+        let enclosing_loop_heads = self.loop_encoder.get_enclosing_loop_heads(bbi);
+        if enclosing_loop_heads.is_empty() {
+            return;
+        }
+        for (successor, cfg_edge_block) in &cfg_edges[&bbi] {
+            let successor_loop_heads = self.loop_encoder.get_enclosing_loop_heads(*successor);
+            let exited_loops = enclosing_loop_heads
+                .iter()
+                .filter(|loop_head| !successor_loop_heads.contains(loop_head))
+                .count();
+            let unframed_loops = if self.loop_encoder.is_loop_head(bbi) {
+                exited_loops.saturating_sub(1)
+            } else {
+                exited_loops
+            };
+            if unframed_loops > 0 {
+                self.cfg_method.add_stmt(
+                    *cfg_edge_block,
+                    vir::Stmt::comment(format!(
+                        "Exit {} loop(s) from the body of the loop of block {:?}",
+                        unframed_loops,
+                        enclosing_loop_heads.last().unwrap()
+                    )),
+                );
+                for _ in 0..unframed_loops {
+                    self.cfg_method.add_stmt(*cfg_edge_block, vir::Stmt::EndFrame);
+                }
+            }
+        }
+    }
+
     /// Exhale the loop invariant if the successor is a loop head
     /// Add an `BeginFrame` statement if the outgoing edge is an *in* edge
     fn encode_loop_invariant_exhale(
@@ -557,8 +671,11 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 for stmt in stmts.into_iter() {
                     self.cfg_method.add_stmt(cfg_edge_block, stmt);
                 }
-                let is_edge_in_loop = self.loop_encoder.get_loop_depth(successor)
-                    > self.loop_encoder.get_loop_depth(bbi);
+                // The edge may come from the body of another loop, e.g. because of a `break`.
+                let is_edge_in_loop = !self
+                    .loop_encoder
+                    .get_enclosing_loop_heads(bbi)
+                    .contains(&successor);
                 if is_edge_in_loop {
                     self.cfg_method
                         .add_stmt(cfg_edge_block, vir::Stmt::BeginFrame);
@@ -575,12 +692,19 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         cfg_edges: &HashMap<BasicBlockIndex, HashMap<BasicBlockIndex, CfgBlockIndex>>,
     ) {
         if !self.procedure.is_spec_block(bbi) {
+            let spec_stmts = self.encode_spec_statements(bbi);
             if self.loop_encoder.is_loop_head(bbi) {
                 for cfg_successor in cfg_edges[&bbi].values() {
                     self.encode_block_statements(bbi, *cfg_successor);
+                    for stmt in spec_stmts.iter() {
+                        self.cfg_method.add_stmt(*cfg_successor, stmt.clone());
+                    }
                 }
             } else {
                 self.encode_block_statements(bbi, cfg_block);
+                for stmt in spec_stmts.into_iter() {
+                    self.cfg_method.add_stmt(cfg_block, stmt);
+                }
             }
         } else {
             // Any spec block must be unreachable
@@ -599,6 +723,352 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Collect the specification statements (e.g. `prusti_assume!`) that follow the statements
+    /// of the block, whose marker closure has the attribute `attr_name`. Each of them is
+    /// rewritten to a spec block that follows the block.
+    fn get_spec_statements(
+        &self,
+        bbi: BasicBlockIndex,
+        attr_name: &str,
+    ) -> Vec<(SpecID, BasicBlockIndex)> {
+        let mut spec_statements = vec![];
+        for spec_bbi in self.procedure.successors(bbi) {
+            if !self.procedure.is_spec_block(spec_bbi) {
+                continue;
+            }
+            for stmt in &self.mir.basic_blocks()[spec_bbi].statements {
+                if let mir::StatementKind::Assign(
+                    _,
+                    mir::Rvalue::Aggregate(box mir::AggregateKind::Closure(cl_def_id, _), _),
+                ) = stmt.kind
+                {
+                    if let Some(attr) = self.encoder.env().get_attr(cl_def_id, attr_name) {
+                        let spec_id: u64 = attr.parse().unwrap();
+                        spec_statements.push((SpecID::from(spec_id), spec_bbi));
+                    }
+                }
+            }
+        }
+        spec_statements
+    }
+
+    /// Encode the assumptions written with `prusti_assume!`, the assertions written with
+    /// `prusti_assert!`, the refutations written with `prusti_refute!` and the `fold!` and
+    /// `unfold!` of the user predicates just after the statements of the block.
+    fn encode_spec_statements(&self, bbi: BasicBlockIndex) -> Vec<vir::Stmt> {
+        let mut stmts = vec![];
+        for (spec_id, spec_bbi) in self.get_spec_statements(bbi, "__PRUSTI_ASSUMPTION_SPEC_ID") {
+            stmts.extend(self.encode_assumption(spec_id, spec_bbi));
+        }
+        for (spec_id, spec_bbi) in self.get_spec_statements(bbi, "__PRUSTI_ASSERTION_SPEC_ID") {
+            stmts.extend(self.encode_prusti_assertion(spec_id, spec_bbi));
+        }
+        for (spec_id, spec_bbi) in self.get_spec_statements(bbi, "__PRUSTI_REFUTATION_SPEC_ID") {
+            stmts.extend(self.encode_refutation(spec_id, spec_bbi));
+        }
+        for (spec_id, spec_bbi) in self.get_spec_statements(bbi, "__PRUSTI_FOLD_SPEC_ID") {
+            stmts.extend(self.encode_predicate_fold(spec_id, spec_bbi, true));
+        }
+        for (spec_id, spec_bbi) in self.get_spec_statements(bbi, "__PRUSTI_UNFOLD_SPEC_ID") {
+            stmts.extend(self.encode_predicate_fold(spec_id, spec_bbi, false));
+        }
+        stmts
+    }
+
+    /// Encode the assertion of a specification statement, returning also its spans.
+    fn encode_spec_statement_assertion(
+        &self,
+        spec: &TypedSpecification,
+        spec_bbi: BasicBlockIndex,
+    ) -> (vir::Expr, Vec<Span>) {
+        let encoded_args: Vec<vir::Expr> = self
+            .mir
+            .args_iter()
+            .map(|local| self.mir_encoder.encode_local(local).into())
+            .collect();
+        let encoded_spec = self.encoder.encode_assertion(
+            &spec.assertion,
+            self.mir,
+            PRECONDITION_LABEL,
+            &encoded_args,
+            None,
+            false,
+            Some(spec_bbi),
+            ErrorCtxt::GenericExpression,
+        );
+        let spec_spans = spec.assertion.get_spans();
+        let expr_pos = self.encoder.error_manager().register_span(spec_spans.clone());
+        (encoded_spec.set_default_pos(expr_pos), spec_spans)
+    }
+
+    fn encode_assumption(&self, spec_id: SpecID, spec_bbi: BasicBlockIndex) -> Vec<vir::Stmt> {
+        let specs = match self.encoder.spec().get(&spec_id) {
+            Some(SpecificationSet::Assumption(ref specs)) => specs,
+            _ => unreachable!("spec {:?} is not an assumption", spec_id),
+        };
+        let mut stmts = vec![];
+        for spec in specs.iter() {
+            let (encoded_spec, spec_spans) = self.encode_spec_statement_assertion(spec, spec_bbi);
+            let spec_span = spec_spans.iter().cloned().fold(None, |span: Option<Span>, other| {
+                Some(span.map_or(other, |span| span.to(other)))
+            });
+            let snippet = spec_span
+                .and_then(|span| self.encoder.env().codemap().span_to_snippet(span).ok())
+                .unwrap_or_else(|| "prusti_assume!(..)".to_string());
+            self.encoder.trusted_surface().register(
+                TrustedItemKind::Assumption,
+                snippet,
+                spec_span,
+                "the assertion is assumed without being checked",
+            );
+            let stmt_pos = self
+                .encoder
+                .error_manager()
+                .register(spec_spans, ErrorCtxt::GenericStatement);
+            stmts.push(vir::Stmt::comment(format!("Assumption {:?}", spec_id)));
+            stmts.push(vir::Stmt::Assume(encoded_spec, stmt_pos));
+        }
+        stmts
+    }
+
+    fn encode_prusti_assertion(
+        &self,
+        spec_id: SpecID,
+        spec_bbi: BasicBlockIndex,
+    ) -> Vec<vir::Stmt> {
+        let specs = match self.encoder.spec().get(&spec_id) {
+            Some(SpecificationSet::Assertion(ref specs)) => specs,
+            _ => unreachable!("spec {:?} is not an assertion", spec_id),
+        };
+        let mut stmts = vec![];
+        for spec in specs.iter() {
+            let (encoded_spec, spec_spans) = self.encode_spec_statement_assertion(spec, spec_bbi);
+            let stmt_pos = self
+                .encoder
+                .error_manager()
+                .register(spec_spans, ErrorCtxt::PrustiAssertion);
+            stmts.push(vir::Stmt::comment(format!("Assertion {:?}", spec_id)));
+            stmts.push(vir::Stmt::Assert(encoded_spec, vir::FoldingBehaviour::Expr, stmt_pos));
+        }
+        stmts
+    }
+
+    fn encode_refutation(&self, spec_id: SpecID, spec_bbi: BasicBlockIndex) -> Vec<vir::Stmt> {
+        let specs = match self.encoder.spec().get(&spec_id) {
+            Some(SpecificationSet::Refutation(ref specs)) => specs,
+            _ => unreachable!("spec {:?} is not a refutation", spec_id),
+        };
+        let mut stmts = vec![];
+        for spec in specs.iter() {
+            let (encoded_spec, spec_spans) = self.encode_spec_statement_assertion(spec, spec_bbi);
+            let stmt_pos = self
+                .encoder
+                .error_manager()
+                .register(spec_spans, ErrorCtxt::PrustiRefutation);
+            stmts.push(vir::Stmt::comment(format!("Refutation {:?}", spec_id)));
+            stmts.push(vir::Stmt::Refute(encoded_spec, stmt_pos));
+        }
+        stmts
+    }
+
+    /// Encode a `fold!` (if `is_fold`) or an `unfold!` of a user predicate. The fold-unfold
+    /// algorithm does not track the permissions of the user predicates, thus these statements
+    /// are the only folds and unfolds of them.
+    fn encode_predicate_fold(
+        &self,
+        spec_id: SpecID,
+        spec_bbi: BasicBlockIndex,
+        is_fold: bool,
+    ) -> Vec<vir::Stmt> {
+        let specs = match self.encoder.spec().get(&spec_id) {
+            Some(SpecificationSet::Fold(ref specs)) if is_fold => specs,
+            Some(SpecificationSet::Unfold(ref specs)) if !is_fold => specs,
+            _ => unreachable!("spec {:?} is not a fold or an unfold", spec_id),
+        };
+        let (macro_name, error_ctxt) = if is_fold {
+            ("fold", ErrorCtxt::FoldPredicate)
+        } else {
+            ("unfold", ErrorCtxt::UnfoldPredicate)
+        };
+        let mut stmts = vec![];
+        for spec in specs.iter() {
+            let (encoded_spec, spec_spans) = self.encode_spec_statement_assertion(spec, spec_bbi);
+            let (predicate_name, arg) = match encoded_spec {
+                vir::Expr::PredicateAccessPredicate(name, box arg, _, _)
+                    if vir::Predicate::is_user_predicate_name(&name) =>
+                {
+                    (name, arg)
+                }
+                _ => {
+                    self.encoder.env().span_err(
+                        spec_spans,
+                        &format!(
+                            "[Prusti] the argument of `{}!` must be an application of a \
+                             `#[predicate]` function",
+                            macro_name
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let stmt_pos = self.encoder.error_manager().register(spec_spans, error_ctxt.clone());
+            stmts.push(vir::Stmt::comment(format!("{} {:?}", macro_name, spec_id)));
+            if is_fold {
+                stmts.push(vir::Stmt::Fold(
+                    predicate_name,
+                    vec![arg],
+                    vir::PermAmount::Write,
+                    None,
+                    stmt_pos,
+                ));
+            } else {
+                // An unfold has no position, thus the predicate is asserted first
+                let access = vir::Expr::PredicateAccessPredicate(
+                    predicate_name.clone(),
+                    box arg.clone(),
+                    vir::PermAmount::Write,
+                    stmt_pos.clone(),
+                );
+                stmts.push(vir::Stmt::Assert(access, vir::FoldingBehaviour::Expr, stmt_pos));
+                stmts.push(vir::Stmt::Unfold(
+                    predicate_name,
+                    vec![arg],
+                    vir::PermAmount::Write,
+                    None,
+                ));
+            }
+        }
+        stmts
+    }
+
+    /// Make `cfg_block` jump non-deterministically either to `cfg_target` or to a new block
+    /// for each of the `checks`. The blocks of the checks end the execution, so that the
+    /// (possibly expected) failure of a check does not stop the verification of the rest of
+    /// the procedure.
+    fn encode_check_branches(
+        &mut self,
+        cfg_block: vir::CfgBlockIndex,
+        checks: Vec<Vec<vir::Stmt>>,
+        cfg_target: vir::CfgBlockIndex,
+    ) {
+        let mut cfg_targets = vec![];
+        for check_stmts in checks {
+            let check_label = self.cfg_method.get_fresh_label_name();
+            let mut stmts = vec![vir::Stmt::comment(format!(
+                "========== {} ==========",
+                check_label
+            ))];
+            stmts.extend(check_stmts);
+            let check_block = self.cfg_method.add_block(&check_label, vec![], stmts);
+            self.cfg_method.set_successor(check_block, Successor::Return);
+            // Make the jump non-deterministic.
+            let guard = self.cfg_method.add_fresh_local_var(vir::Type::Bool);
+            cfg_targets.push((guard.into(), check_block));
+        }
+        self.cfg_method
+            .set_successor(cfg_block, Successor::GotoSwitch(cfg_targets, cfg_target));
+    }
+
+    /// Check that the beginning of the procedure, the targets of its branches and its end are
+    /// reachable, by asserting `false` in separate branches (see `encode_check_branches`).
+    /// The assertions are expected to fail; the ones that do not fail are reported as
+    /// warnings, because the obligations that follow them hold vacuously.
+    fn encode_reachability_checks(
+        &mut self,
+        start_cfg_block: vir::CfgBlockIndex,
+        return_cfg_block: vir::CfgBlockIndex,
+        cfg_edges: &HashMap<BasicBlockIndex, HashMap<BasicBlockIndex, CfgBlockIndex>>,
+    ) {
+        // The precondition
+        if let Some(first_cfg_block) = self
+            .mir_to_vir_blocks
+            .get(&self.procedure.get_first_cfg_block())
+            .cloned()
+        {
+            let check = self.encode_reachability_check(
+                self.mir.span,
+                ReachabilityCheckKind::Precondition,
+            );
+            self.encode_check_branches(start_cfg_block, vec![check], first_cfg_block);
+        }
+
+        // The targets of the branches written in the procedure. The branches that jump to spec
+        // blocks and the compiler-generated targets that end with `unreachable` are ignored.
+        for bbi in self.procedure.get_reachable_cfg_blocks() {
+            let is_branch = match self.mir.basic_blocks()[bbi].terminator().kind {
+                TerminatorKind::SwitchInt { .. } => true,
+                _ => false,
+            };
+            let has_spec_successor = self
+                .procedure
+                .successors(bbi)
+                .into_iter()
+                .any(|successor| self.procedure.is_spec_block(successor));
+            if !is_branch || has_spec_successor {
+                continue;
+            }
+            let mut targets = self.procedure.successors(bbi);
+            targets.sort();
+            targets.dedup();
+            for target in targets {
+                if !self.procedure.is_reachable_block(target) {
+                    continue;
+                }
+                let target_data = &self.mir.basic_blocks()[target];
+                if let TerminatorKind::Unreachable = target_data.terminator().kind {
+                    continue;
+                }
+                let is_back_edge = self.loop_encoder.is_loop_head(target)
+                    && self.loop_encoder.get_loop_head(bbi) == Some(target);
+                if is_back_edge {
+                    continue;
+                }
+                let target_span = target_data
+                    .statements
+                    .first()
+                    .map(|stmt| stmt.source_info.span)
+                    .unwrap_or_else(|| target_data.terminator().source_info.span);
+                let check = self.encode_reachability_check(
+                    target_span,
+                    ReachabilityCheckKind::Branch,
+                );
+                let target_cfg_block = self.mir_to_vir_blocks[&target];
+                self.encode_check_branches(cfg_edges[&bbi][&target], vec![check], target_cfg_block);
+            }
+        }
+
+        // The end of the procedure, unless it is known to diverge
+        if !self.mir.return_ty().is_never() {
+            let end_label = self.cfg_method.get_fresh_label_name();
+            let end_cfg_block = self.cfg_method.add_block(
+                &end_label,
+                vec![],
+                vec![vir::Stmt::comment(format!("========== {} ==========", end_label))],
+            );
+            self.cfg_method.set_successor(end_cfg_block, Successor::Return);
+            let check = self.encode_reachability_check(
+                self.mir.span,
+                ReachabilityCheckKind::ProcedureEnd,
+            );
+            self.encode_check_branches(return_cfg_block, vec![check], end_cfg_block);
+        }
+    }
+
+    fn encode_reachability_check(
+        &self,
+        span: Span,
+        kind: ReachabilityCheckKind,
+    ) -> Vec<vir::Stmt> {
+        let pos = self
+            .encoder
+            .error_manager()
+            .register(span, ErrorCtxt::ReachabilityCheck(kind.clone()));
+        vec![
+            vir::Stmt::comment(format!("Reachability check ({:?})", kind)),
+            vir::Stmt::Assert(false.into(), vir::FoldingBehaviour::Stmt, pos),
+        ]
+    }
+
     /// Encode terminators and set CFG edges
     fn encode_terminators(
         &mut self,
@@ -740,6 +1210,14 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             | mir::StatementKind::Nop => vec![],
 
             mir::StatementKind::Assign(ref lhs, ref rhs) => {
+                self.check_field_write(lhs, stmt.source_info.span);
+                match rhs {
+                    &mir::Rvalue::Ref(_, mir::BorrowKind::Shared, _) => {}
+                    &mir::Rvalue::Ref(_, _, ref place) => {
+                        self.check_field_write(place, stmt.source_info.span);
+                    }
+                    _ => {}
+                }
                 let (encoded_lhs, ty, _) = self.mir_encoder.encode_place(lhs);
                 match rhs {
                     &mir::Rvalue::Use(ref operand) => {
@@ -783,6 +1261,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
 
             ref x => unimplemented!("{:?}", x),
         };
+        let stmts = self.encode_raw_pointer_accesses(stmts, stmt.source_info.span);
         stmts
             .into_iter()
             .map(|s| {
@@ -799,6 +1278,28 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             .collect()
     }
 
+    /// Encode the dereferences of the raw pointers in `stmts` as accesses to the memory regions
+    /// owned by `deref_spec`, checking that each access is in an owned region.
+    fn encode_raw_pointer_accesses(&self, stmts: Vec<vir::Stmt>, span: Span) -> Vec<vir::Stmt> {
+        let pointers: Vec<vir::Expr> = self
+            .locals
+            .iter()
+            .filter(|&local| is_region_pointer_type(self.locals.get_type(local)))
+            .map(|local| {
+                let value_field = self.encoder.encode_value_field(self.locals.get_type(local));
+                vir::Expr::from(self.encode_prusti_local(local)).field(value_field)
+            })
+            .collect();
+        if pointers.is_empty() {
+            return stmts;
+        }
+        let pos = self
+            .encoder
+            .error_manager()
+            .register(span, ErrorCtxt::DerefSpecAccess);
+        encode_region_accesses(self.encoder, stmts, &pointers, pos)
+    }
+
     /// Translate a borrowed place to a place that is currently usable
     fn translate_maybe_borrowed_place(
         &self,
@@ -1325,6 +1826,16 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                     vir::AssignKind::Copy,
                 ));
 
+                // The discriminant of the enumeration matched by the switch, if any.
+                let encoded_enum_discr = self
+                    .find_switched_enum(location, discr)
+                    .map(|(encoded_enum, adt_def)| {
+                        self.encoder.encode_discriminant_func_app(
+                            self.translate_maybe_borrowed_place(location, encoded_enum),
+                            adt_def,
+                        )
+                    });
+
                 for (i, &value) in values.iter().enumerate() {
                     let target = targets[i as usize];
                     // Convert int to bool, if required
@@ -1349,6 +1860,24 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                         ref x => unreachable!("{:?}", x),
                     };
                     let target_cfg_block = cfg_blocks.get(&target).unwrap();
+                    // Refine the variant of the matched enumeration on the edge to the target,
+                    // so that the success path of `if let` and `let ... else` keeps it even if
+                    // the link with the local discriminant is lost (e.g. by a havoc at a loop).
+                    let is_unique_target = targets.iter().filter(|&&t| t == target).count() == 1;
+                    if let Some(ref enum_discr) = encoded_enum_discr {
+                        if is_unique_target {
+                            self.cfg_method.add_stmt(
+                                *target_cfg_block,
+                                vir::Stmt::Inhale(
+                                    vir::Expr::eq_cmp(
+                                        enum_discr.clone(),
+                                        self.encoder.encode_int_cast(value, switch_ty),
+                                    ),
+                                    vir::FoldingBehaviour::Expr,
+                                ),
+                            );
+                        }
+                    }
                     cfg_targets.push((viper_guard, *target_cfg_block))
                 }
                 let default_target = targets[values.len()];
@@ -1420,7 +1949,16 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 (stmts, Successor::Return)
             }
 
-            TerminatorKind::Drop { ref target, .. } => {
+            TerminatorKind::Drop {
+                ref target,
+                location: ref dropped_place,
+                ..
+            } => {
+                stmts.extend(self.encode_drop_type_invariant(
+                    dropped_place,
+                    location,
+                    term.source_info.span,
+                ));
                 let target_cfg_block = cfg_blocks.get(&target).unwrap();
                 (stmts, Successor::Goto(*target_cfg_block))
             }
@@ -1445,6 +1983,8 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 ref value,
                 ..
             } => {
+                // The old value of `lhs` is dropped
+                stmts.extend(self.encode_drop_type_invariant(lhs, location, term.source_info.span));
                 let (encoded_lhs, _, _) = self.mir_encoder.encode_place(lhs);
                 stmts.extend(self.encode_assign_operand(&encoded_lhs, value, location));
                 let target_cfg_block = cfg_blocks.get(&target).unwrap();
@@ -1473,6 +2013,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 ..
             } => {
                 let func_proc_name: &str = &self.encoder.env().tcx().absolute_item_path_str(def_id);
+                self.encoder.check_executable_call(def_id, term.source_info.span);
 
                 let own_substs =
                     ty::subst::Substs::identity_for_item(self.encoder.env().tcx(), def_id);
@@ -1494,14 +2035,21 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                     tymap_stack.push(tymap);
                 }
 
-                match func_proc_name {
-                    "std::rt::begin_panic" | "std::panicking::begin_panic" => {
+                let library_model = self.encoder.library_model(func_proc_name);
+                stmts.extend(self.encode_dynamic_borrow_check(
+                    library_model,
+                    location,
+                    term.source_info.span,
+                ));
+
+                match library_model {
+                    Some(LibraryModel::BeginPanic) => {
                         // This is called when a Rust assertion fails
                         // args[0]: message
                         // args[1]: position of failing assertions
 
                         // Example of args[0]: 'const "internal error: entered unreachable code"'
-                        let panic_message = format!("{:?}", args[0]);
+                        let panic_message = self.encoder.str_literal_operand_content(&args[0]);
 
                         // Pattern match on the macro that generated the panic
                         // TODO: use a better approach to match macros
@@ -1570,11 +2118,21 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             .encoder
                             .error_manager()
                             .register(term.source_info.span, ErrorCtxt::Panic(panic_cause));
+                        if let Some(ref message) = panic_message {
+                            self.encoder
+                                .error_manager()
+                                .register_panic_message(&pos, message);
+                        }
 
-                        if self.check_panics {
+                        let is_debug_assertion = macro_backtrace.iter().any(|macro_info| {
+                            macro_info.macro_decl_name.starts_with("debug_assert")
+                        });
+                        if is_debug_assertion && !self.check_debug_assertions {
+                            debug!("The debug assertion will not be checked")
+                        } else if self.check_panics {
                             stmts.push(vir::Stmt::comment(format!(
                                 "Rust panic - {}",
-                                panic_message
+                                panic_message.unwrap_or_else(|| format!("{:?}", args[0]))
                             )));
                             stmts.push(
                                 vir::Stmt::Assert(
@@ -1588,7 +2146,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                         }
                     }
 
-                    "<std::boxed::Box<T>>::new" => {
+                    Some(LibraryModel::BoxNew) => {
                         // This is the initialization of a box
                         // args[0]: value to put in the box
                         assert_eq!(args.len(), 1);
@@ -1614,9 +2172,70 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                         stmts.extend(self.encode_assign_operand(&box_content, &args[0], location));
                     }
 
+                    Some(LibraryModel::PtrOffset) => {
+                        // The address args[1] values after the address args[0], which is not
+                        // checked to be in a memory region
+                        // args[0]: raw pointer
+                        // args[1]: offset
+                        assert_eq!(args.len(), 2);
+                        let &(ref target_place, _) = destination.as_ref().unwrap();
+                        let (dst, dest_ty, _) = self.mir_encoder.encode_place(target_place);
+                        let ref_field = self.encoder.encode_value_field(dest_ty);
+                        stmts.extend(self.prepare_assign_target(
+                            dst.clone(),
+                            ref_field.clone(),
+                            location,
+                            vir::AssignKind::Move,
+                        ));
+                        let address = encode_loc(
+                            self.encoder,
+                            self.mir_encoder.encode_operand_expr(&args[0]),
+                            self.mir_encoder.encode_operand_expr(&args[1]),
+                        );
+                        stmts.push(vir::Stmt::Assign(
+                            dst.field(ref_field),
+                            address,
+                            vir::AssignKind::Copy,
+                        ));
+                    }
+
+                    // The `?` operator on a `Result` calls `Try::into_result`, then on the error
+                    // path `From::from` and `Try::from_error`. They are encoded as moves and as
+                    // the construction of an `Err`, so that the postconditions can distinguish
+                    // the `Ok` and `Err` results of a function that uses `?`.
+                    Some(model) if self.is_identity_library_call(model, substs) => {
+                        // args[0]: value that is returned unchanged
+                        assert_eq!(args.len(), 1);
+                        let &(ref target_place, _) = destination.as_ref().unwrap();
+                        let (dst, _, _) = self.mir_encoder.encode_place(target_place);
+                        stmts.extend(self.encode_assign_operand(&dst, &args[0], location));
+                    }
+
+                    Some(LibraryModel::TryFromError) if self.is_result_type(substs.type_at(0)) => {
+                        // args[0]: error to put in the `Err` variant
+                        assert_eq!(args.len(), 1);
+                        let &(ref target_place, _) = destination.as_ref().unwrap();
+                        let (dst, dest_ty, _) = self.mir_encoder.encode_place(target_place);
+                        let (adt_def, result_substs) = match dest_ty.sty {
+                            ty::TypeVariants::TyAdt(adt_def, result_substs) => {
+                                (adt_def, result_substs)
+                            }
+                            ref x => unreachable!("{:?} is not a Result", x),
+                        };
+                        let err_index = adt_def
+                            .variants
+                            .iter()
+                            .position(|variant| variant.name.as_str() == "Err")
+                            .unwrap();
+                        let aggregate =
+                            mir::AggregateKind::Adt(adt_def, err_index, result_substs, None);
+                        stmts.extend(
+                            self.encode_assign_aggregate(&dst, dest_ty, &aggregate, args, location)
+                        );
+                    }
+
                     _ => {
-                        let is_pure_function =
-                            self.encoder.env().has_attribute_name(def_id, "pure");
+                        let is_pure_function = self.encoder.is_pure(def_id);
                         if is_pure_function {
                             let function_name = self.encoder.encode_pure_function_use(def_id);
                             debug!("Encoding pure function call '{}'", function_name);
@@ -1628,7 +2247,6 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                                 arg_exprs.push(arg_expr);
                             }
 
-                            let return_type = self.encoder.encode_pure_function_return_type(def_id);
                             let formal_args: Vec<vir::LocalVar> = args
                                 .iter()
                                 .enumerate()
@@ -1644,19 +2262,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                                 .encoder
                                 .error_manager()
                                 .register(term.source_info.span, ErrorCtxt::PureFunctionCall);
-                            let func_call = vir::Expr::func_app(
-                                function_name,
-                                arg_exprs,
-                                formal_args,
-                                return_type,
-                                pos,
-                            );
 
                             let label = self.cfg_method.get_fresh_label_name();
                             stmts.push(vir::Stmt::Label(label.clone()));
 
                             // Havoc the content of the lhs
-                            let (target_place, _target_ty, _) = match destination.as_ref() {
+                            let (target_place, target_ty, _) = match destination.as_ref() {
                                 Some((ref dst, _)) => self.mir_encoder.encode_place(dst),
                                 None => unreachable!(),
                             };
@@ -1676,19 +2287,57 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             );
 
                             // Initialize the lhs
-                            let target_value = match destination.as_ref() {
-                                Some((ref dst, _)) => self.mir_encoder.eval_place(dst),
-                                None => unreachable!(),
-                            };
-                            stmts.push(
-                                vir::Stmt::Inhale(
-                                    vir::Expr::eq_cmp(
-                                        target_value.into(),
-                                        func_call,
-                                    ),
-                                    vir::FoldingBehaviour::Stmt,
-                                )
-                            );
+                            if let ty::TypeVariants::TyTuple(component_tys) = target_ty.sty {
+                                // Each component of a returned tuple has its own function.
+                                for (component, &component_ty) in component_tys.iter().enumerate()
+                                {
+                                    let component_field =
+                                        self.encoder.encode_tuple_field(component, component_ty);
+                                    let component_value = target_place
+                                        .clone()
+                                        .field(component_field)
+                                        .field(self.encoder.encode_value_field(component_ty));
+                                    let func_call = vir::Expr::func_app(
+                                        self.encoder
+                                            .encode_pure_function_component_use(def_id, component),
+                                        arg_exprs.clone(),
+                                        formal_args.clone(),
+                                        self.encoder.encode_value_type(component_ty),
+                                        pos.clone(),
+                                    );
+                                    stmts.push(vir::Stmt::Inhale(
+                                        vir::Expr::eq_cmp(component_value, func_call),
+                                        vir::FoldingBehaviour::Stmt,
+                                    ));
+                                }
+                            } else {
+                                let func_call =
+                                    match self.encoder.encode_domain_function_use(def_id) {
+                                        Some(domain_func) => {
+                                            vir::Expr::DomainFuncApp(domain_func, arg_exprs, pos)
+                                        }
+                                        None => vir::Expr::func_app(
+                                            function_name,
+                                            arg_exprs,
+                                            formal_args,
+                                            self.encoder.encode_pure_function_return_type(def_id),
+                                            pos,
+                                        ),
+                                    };
+                                let target_value = match destination.as_ref() {
+                                    Some((ref dst, _)) => self.mir_encoder.eval_place(dst),
+                                    None => unreachable!(),
+                                };
+                                stmts.push(
+                                    vir::Stmt::Inhale(
+                                        vir::Expr::eq_cmp(
+                                            target_value.into(),
+                                            func_call,
+                                        ),
+                                        vir::FoldingBehaviour::Stmt,
+                                    )
+                                );
+                            }
 
                             // Store a label for permissions got back from the call
                             debug!(
@@ -1701,15 +2350,10 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             for operand in args.iter() {
                                 let operand_ty = self.mir_encoder.get_operand_ty(operand);
                                 let operand_place = self.mir_encoder.encode_operand_place(operand);
+                                // A raw pointer does not own its target, which has nothing
+                                // to transfer.
                                 match (operand_place, &operand_ty.sty) {
                                     (
-                                        Some(ref place),
-                                        ty::TypeVariants::TyRawPtr(ty::TypeAndMut {
-                                            ty: ref inner_ty,
-                                            ..
-                                        }),
-                                    )
-                                    | (
                                         Some(ref place),
                                         ty::TypeVariants::TyRef(_, ref inner_ty, _),
                                     ) => {
@@ -1872,9 +2516,19 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                                 expr
                             };
 
+                            // If the called trait method is statically resolved to the method
+                            // of an `impl`, use its (possibly refined) contract or its model.
+                            let contract_def_id = self
+                                .encoder
+                                .resolve_trait_method_call(self.proc_def_id, def_id, substs)
+                                .or_else(|| {
+                                    self.encoder
+                                        .resolve_std_model_call(self.proc_def_id, def_id, substs)
+                                })
+                                .unwrap_or(def_id);
                             let procedure_contract = {
                                 self.encoder.get_procedure_contract_for_call(
-                                    def_id,
+                                    contract_def_id,
                                     &fake_vars,
                                     fake_target_local,
                                 )
@@ -1884,6 +2538,34 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             let pre_label = self.cfg_method.get_fresh_label_name();
                             stmts.push(vir::Stmt::Label(pre_label.clone()));
 
+                            // The contract of a modelled method of the standard library
+                            let (std_model_pre, std_model_post) =
+                                match procedure_contract.std_model {
+                                    Some(model) => {
+                                        let model_args: Vec<_> = fake_vars
+                                            .iter()
+                                            .map(|&var| {
+                                                let place = self.encode_prusti_local(var);
+                                                (vir::Expr::local(place), self.locals.get_type(var))
+                                            })
+                                            .collect();
+                                        let model_target = (
+                                            vir::Expr::local(
+                                                self.encode_prusti_local(fake_target_local),
+                                            ),
+                                            self.locals.get_type(fake_target_local),
+                                        );
+                                        encode_std_model_contract(
+                                            self.encoder,
+                                            model,
+                                            &model_args,
+                                            &model_target,
+                                            &pre_label,
+                                        )
+                                    }
+                                    None => (true.into(), true.into()),
+                                };
+
                             // Havoc and inhale variables that store constants
                             for constant_arg in &constant_args {
                                 stmts.extend(self.encode_havoc_and_allocation(constant_arg));
@@ -1902,17 +2584,22 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                                 term.source_info.span,
                                 ErrorCtxt::ExhaleMethodPrecondition,
                             );
+                            // Same order as in the inhale of the precondition in the callee, so
+                            // that the clauses may assume the type invariants.
                             stmts.push(
                                 vir::Stmt::Assert(
-                                    replace_fake_exprs(pre_func_spec),
-                                    vir::FoldingBehaviour::Stmt, // TODO: Should be Expr.
+                                    replace_fake_exprs(pre_invs_spec),
+                                    vir::FoldingBehaviour::Stmt,
                                     pos.clone(),
                                 )
                             );
                             stmts.push(
                                 vir::Stmt::Assert(
-                                    replace_fake_exprs(pre_invs_spec),
-                                    vir::FoldingBehaviour::Stmt,
+                                    replace_fake_exprs(vir::Expr::and(
+                                        pre_func_spec,
+                                        std_model_pre,
+                                    )),
+                                    vir::FoldingBehaviour::Stmt, // TODO: Should be Expr.
                                     pos.clone(),
                                 )
                             );
@@ -2020,7 +2707,10 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             );
                             stmts.push(
                                 vir::Stmt::Inhale(
-                                    replace_fake_exprs(post_func_spec),
+                                    replace_fake_exprs(vir::Expr::and(
+                                        post_func_spec,
+                                        std_model_post,
+                                    )),
                                     vir::FoldingBehaviour::Expr,
                                 )
                             );
@@ -2099,14 +2789,23 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             msg.description()
                         )),
                         if self.check_panics {
-                            vir::Stmt::Assert(
-                                false.into(),
-                                vir::FoldingBehaviour::Stmt,
-                                self.encoder.error_manager().register(
-                                    term.source_info.span,
-                                    ErrorCtxt::AssertTerminator(msg.description().to_string()),
-                                ),
-                            )
+                            let pos = self.encoder.error_manager().register(
+                                term.source_info.span,
+                                ErrorCtxt::AssertTerminator(msg.description().to_string()),
+                            );
+                            let opt_suggestion = suggestions::suggest_loop_counter_bound(
+                                self.encoder.env().codemap(),
+                                self.mir,
+                                &self.loop_encoder,
+                                location,
+                                cond,
+                            );
+                            if let Some(suggestion) = opt_suggestion {
+                                self.encoder
+                                    .error_manager()
+                                    .register_suggestion(&pos, suggestion);
+                            }
+                            vir::Stmt::Assert(false.into(), vir::FoldingBehaviour::Stmt, pos)
                         } else {
                             vir::Stmt::comment("This assertion will not be checked")
                         },
@@ -2127,14 +2826,209 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Encode the check of the type invariant of the value dropped at `location`, if its type
+    /// implements `Drop`. The destructor takes the value as `&mut self`, thus the invariant is
+    /// part of its precondition. Values that might have been moved out are not dropped.
+    fn encode_drop_type_invariant(
+        &mut self,
+        dropped_place: &mir::Place<'tcx>,
+        location: mir::Location,
+        span: Span,
+    ) -> Vec<vir::Stmt> {
+        let (encoded_place, ty, _) = self.mir_encoder.encode_place(dropped_place);
+        let has_destructor = match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, _) if !adt_def.is_box() => {
+                self.encoder.env().tcx().adt_destructor(adt_def.did).is_some()
+            }
+            _ => false,
+        };
+        if !has_destructor
+            || !self
+                .loop_encoder
+                .is_definitely_initialised_at(dropped_place, location)
+        {
+            return vec![];
+        }
+        let pos = self
+            .encoder
+            .error_manager()
+            .register(span, ErrorCtxt::DropTypeInvariant);
+        vec![
+            vir::Stmt::comment(format!("Drop {}", encoded_place)),
+            vir::Stmt::Assert(
+                self.encoder.encode_invariant_func_app(ty, encoded_place),
+                vir::FoldingBehaviour::Stmt,
+                pos,
+            ),
+        ]
+    }
+
+    /// Is the type `Result`?
+    fn is_result_type(&self, ty: ty::Ty<'tcx>) -> bool {
+        match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, _) => {
+                let path = self.encoder.env().tcx().absolute_item_path_str(adt_def.did);
+                path == "core::result::Result" || path == "std::result::Result"
+            }
+            _ => false,
+        }
+    }
+
+    /// Does the call of the library model, with the given type arguments, return its argument
+    /// unchanged? E.g. `Try::into_result` on a `Result`, or `From::from` on an error that
+    /// already has the returned type.
+    fn is_identity_library_call(
+        &self,
+        model: LibraryModel,
+        substs: &ty::subst::Substs<'tcx>,
+    ) -> bool {
+        match model {
+            LibraryModel::TryIntoResult => self.is_result_type(substs.type_at(0)),
+            LibraryModel::ConvertFrom => substs.type_at(0) == substs.type_at(1),
+            _ => false,
+        }
+    }
+
     /// Encode permissions that are implicitly carried by the given local variable.
+    /// Collect the calls of `RefCell::borrow` and `RefCell::borrow_mut` whose
+    /// argument is a reference `&cell` created in the same basic block. For each
+    /// call, return the location of the call, the location of the loan of `cell`,
+    /// `cell` and whether the call is `borrow_mut`.
+    fn collect_dynamic_borrows(&self) -> Vec<(mir::Location, mir::Location, mir::Place<'tcx>, bool)> {
+        let mut dynamic_borrows = vec![];
+        for (bbi, bb_data) in self.mir.basic_blocks().iter_enumerated() {
+            let term = bb_data.terminator();
+            let (def_id, arg) = match term.kind {
+                TerminatorKind::Call {
+                    ref args,
+                    func:
+                        mir::Operand::Constant(box mir::Constant {
+                            literal:
+                                mir::Literal::Value {
+                                    value:
+                                        ty::Const {
+                                            ty:
+                                                &ty::TyS {
+                                                    sty: ty::TyFnDef(def_id, _),
+                                                    ..
+                                                },
+                                            ..
+                                        },
+                                },
+                            ..
+                        }),
+                    ..
+                } if args.len() == 1 => (def_id, &args[0]),
+                _ => continue,
+            };
+            let func_proc_name = self.encoder.env().tcx().absolute_item_path_str(def_id);
+            let is_mut = match self.encoder.library_model(&func_proc_name) {
+                Some(LibraryModel::RefCellBorrow) => false,
+                Some(LibraryModel::RefCellBorrowMut) => true,
+                _ => continue,
+            };
+            let arg_local = match arg {
+                mir::Operand::Move(mir::Place::Local(local)) => *local,
+                _ => continue,
+            };
+            let cell_loan = bb_data.statements.iter().enumerate().rev().filter_map(|(index, stmt)| {
+                match stmt.kind {
+                    mir::StatementKind::Assign(
+                        mir::Place::Local(local),
+                        mir::Rvalue::Ref(_, _, ref cell),
+                    ) if local == arg_local => Some((index, cell.clone())),
+                    _ => None,
+                }
+            }).next();
+            if let Some((statement_index, cell)) = cell_loan {
+                let call_location = mir::Location {
+                    block: bbi,
+                    statement_index: bb_data.statements.len(),
+                };
+                let loan_location = mir::Location {
+                    block: bbi,
+                    statement_index,
+                };
+                dynamic_borrows.push((call_location, loan_location, cell, is_mut));
+            }
+        }
+        dynamic_borrows
+    }
+
+    /// Encode the obligation that the call of `RefCell::borrow` or `RefCell::borrow_mut`
+    /// at `location` respects the dynamic borrow rules. The state of the dynamic borrows
+    /// of a `RefCell` is tracked by the loans of the `RefCell` taken by the calls, which
+    /// are alive as long as the returned guards. The obligation fails if a conflicting
+    /// guard of the same `RefCell` might still be alive.
+    fn encode_dynamic_borrow_check(
+        &self,
+        library_model: Option<LibraryModel>,
+        location: mir::Location,
+        span: Span,
+    ) -> Vec<vir::Stmt> {
+        match library_model {
+            Some(LibraryModel::RefCellBorrow) | Some(LibraryModel::RefCellBorrowMut) => {}
+            _ => return vec![],
+        }
+        let dynamic_borrows = self.collect_dynamic_borrows();
+        let (_, _, cell, is_mut) = match dynamic_borrows
+            .iter()
+            .find(|(call_location, ..)| *call_location == location)
+        {
+            Some(dynamic_borrow) => dynamic_borrow,
+            None => {
+                debug!("The dynamic borrow at {:?} will not be checked", location);
+                return vec![];
+            }
+        };
+        let (active_loans, _) = self.polonius_info.get_all_active_loans(location);
+        let active_loan_locations: HashSet<_> = active_loans
+            .iter()
+            .map(|loan| self.polonius_info.get_loan_location(loan))
+            .collect();
+        let conflicting_borrow = dynamic_borrows.iter().find(
+            |(other_call_location, other_loan_location, other_cell, other_is_mut)| {
+                *other_call_location != location
+                    && (*is_mut || *other_is_mut)
+                    && active_loan_locations.contains(other_loan_location)
+                    && (is_prefix(cell, other_cell) || is_prefix(other_cell, cell))
+            },
+        );
+        if conflicting_borrow.is_none() {
+            return vec![];
+        }
+        debug!(
+            "The dynamic borrow at {:?} might conflict with {:?}",
+            location, conflicting_borrow
+        );
+        let error_ctxt = if config::relaxed_dynamic_borrows() {
+            if !self.check_panics {
+                return vec![];
+            }
+            ErrorCtxt::Panic(PanicCause::DynamicBorrow)
+        } else {
+            ErrorCtxt::DynamicBorrowConflict
+        };
+        let pos = self.encoder.error_manager().register(span, error_ctxt);
+        vec![
+            vir::Stmt::comment(format!("Conflicting dynamic borrow of {:?}", cell)),
+            vir::Stmt::Assert(false.into(), vir::FoldingBehaviour::Stmt, pos),
+        ]
+    }
+
     fn encode_local_variable_permission(&self, local: Local) -> vir::Expr {
         match self.locals.get_type(local).sty {
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut {
-                ref ty,
-                mutbl: mutability,
-            })
-            | ty::TypeVariants::TyRef(_, ref ty, mutability) => {
+            // A raw pointer is an address, which owns no memory. The memory that it points to
+            // is owned by the `deref_spec` of the contracts.
+            ty::TypeVariants::TyRawPtr(..) => {
+                let encoded_local = self.encode_prusti_local(local);
+                let field = self.encoder.encode_value_field(self.locals.get_type(local));
+                vir::Expr::acc_permission(
+                    vir::Expr::from(encoded_local).field(field),
+                    vir::PermAmount::Write,
+                )
+            }
+            ty::TypeVariants::TyRef(_, ref ty, mutability) => {
                 // Use unfolded references.
                 let encoded_local = self.encode_prusti_local(local);
                 let field = self.encoder.encode_dereference_field(ty);
@@ -2231,6 +3125,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             .iter()
             .map(|local| self.encode_prusti_local(*local).into())
             .collect();
+        // The clauses are conjoined in order, thus each may assume the previous ones.
         let func_precondition = contract.functional_precondition();
         for item in func_precondition {
             // FIXME
@@ -2248,6 +3143,18 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             //warn!("after:  {:?}", &value);
             func_spec.push(value);
         }
+        if let Some(op) = contract.arithmetic_newtype {
+            let (left, right, inner_ty) =
+                self.encode_arithmetic_newtype_operands(contract, &encoded_args);
+            if op == mir::BinOp::Div || op == mir::BinOp::Rem {
+                func_spec.push(vir::Expr::ne_cmp(right.clone(), 0.into()));
+            }
+            if op.is_checkable() && config::check_binary_operations() {
+                func_spec.push(vir::Expr::not(
+                    self.mir_encoder.encode_bin_op_check(op, left, right, inner_ty),
+                ));
+            }
+        }
         let precondition_weakening = precondition_weakening
             .map(|pw| self.encoder.encode_assertion(
                 &pw,
@@ -2421,6 +3328,45 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 assertion_rhs = assertion_rhs.remove_redundant_old();
                 lhs.push(assertion_lhs);
                 rhs.push(assertion_rhs);
+            } else if let Some(ref accessor) = contract.accessor {
+                rhs.extend(self.encode_accessor_pledge(
+                    accessor,
+                    contract,
+                    &encoded_args,
+                    &encoded_return,
+                    pre_label,
+                    post_label,
+                ));
+            } else if let Some(model) = contract.std_model {
+                let model_args: Vec<_> = encoded_args
+                    .iter()
+                    .cloned()
+                    .zip(contract.args.iter().map(|&arg| self.locals.get_type(arg)))
+                    .collect();
+                let model_return = (
+                    encoded_return.clone(),
+                    self.locals.get_type(contract.returned_value),
+                );
+                if let Some(pledge) = encode_std_model_pledge(
+                    self.encoder,
+                    model,
+                    &model_args,
+                    &model_return,
+                    post_label,
+                ) {
+                    rhs.push(self.wrap_arguments_into_old(
+                        pledge,
+                        pre_label,
+                        contract,
+                        &encoded_args,
+                    ));
+                }
+                lhs.extend(encode_sync_model_obligation(
+                    self.encoder,
+                    model,
+                    &model_return,
+                    post_label,
+                ));
             }
             let lhs = lhs.into_iter().conjoin();
             let rhs = rhs.into_iter().conjoin();
@@ -2430,10 +3376,71 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Encode the inferred pledge of an accessor that returns `&mut (*arg).f`:
+    /// when the returned reference expires, `(*arg).f` has the final value of
+    /// the reference and the other primitive fields are unchanged.
+    fn encode_accessor_pledge(
+        &self,
+        accessor: &AccessorInfo<Place<'tcx>>,
+        contract: &ProcedureContract<'tcx>,
+        encoded_args: &[vir::Expr],
+        encoded_return: &vir::Expr,
+        pre_label: &str,
+        post_label: &str,
+    ) -> Vec<vir::Expr> {
+        let mut conjuncts = vec![];
+        let (accessed_expr, accessed_ty, _) = self.encode_generic_place(&accessor.accessed_place);
+        match accessed_ty.sty {
+            ty::TypeVariants::TyBool
+            | ty::TypeVariants::TyChar
+            | ty::TypeVariants::TyInt(_)
+            | ty::TypeVariants::TyUint(_) => {
+                let value_field = self.encoder.encode_value_field(accessed_ty);
+                let return_ty = self.locals.get_type(contract.returned_value);
+                let (encoded_deref, ..) =
+                    self.mir_encoder.encode_deref(encoded_return.clone(), return_ty);
+                conjuncts.push(vir::Expr::eq_cmp(
+                    accessed_expr.field(value_field.clone()),
+                    vir::Expr::labelled_old(post_label, encoded_deref).field(value_field),
+                ));
+            }
+            _ => {}
+        }
+        for framed_place in &accessor.framed_places {
+            let (framed_expr, framed_ty, _) = self.encode_generic_place(framed_place);
+            let value = framed_expr.field(self.encoder.encode_value_field(framed_ty));
+            conjuncts.push(vir::Expr::eq_cmp(value.clone(), value.old(pre_label)));
+        }
+        conjuncts
+            .into_iter()
+            .map(|conjunct| {
+                self.wrap_arguments_into_old(conjunct, pre_label, contract, encoded_args)
+                    .remove_redundant_old()
+            })
+            .collect()
+    }
+
     /// Wrap function arguments used in the postcondition into ``old``:
     ///
     /// +   For references wrap the base ``_1.var_ref``.
     /// +   For non-references wrap the entire place into old.
+    /// Encode the integers wrapped by the two operands of the operator of a newtype marked with
+    /// `#[arithmetic_newtype]`, and return them with their type.
+    fn encode_arithmetic_newtype_operands(
+        &self,
+        contract: &ProcedureContract<'tcx>,
+        encoded_args: &[vir::Expr],
+    ) -> (vir::Expr, vir::Expr, ty::Ty<'tcx>) {
+        let newtype_ty = self.locals.get_type(contract.args[0]);
+        let (left, inner_ty) = self
+            .encoder
+            .encode_arithmetic_newtype_value(encoded_args[0].clone(), newtype_ty);
+        let (right, _) = self
+            .encoder
+            .encode_arithmetic_newtype_value(encoded_args[1].clone(), newtype_ty);
+        (left, right, inner_ty)
+    }
+
     fn wrap_arguments_into_old(
         &self,
         mut assertion: vir::Expr,
@@ -2464,6 +3471,56 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         assertion.remove_redundant_old()
     }
 
+    /// Give a new position to each conjunct of `assertion` that is about exactly one
+    /// component of the returned tuple, so that the failure of a postcondition
+    /// reports the component at fault.
+    fn set_result_component_positions(
+        &self,
+        assertion: vir::Expr,
+        encoded_return: &vir::Expr,
+    ) -> vir::Expr {
+        struct ComponentFinder<'a> {
+            encoded_return: &'a vir::Expr,
+            components: HashSet<usize>,
+        }
+        impl<'a> vir::ExprWalker for ComponentFinder<'a> {
+            fn walk_field(&mut self, receiver: &vir::Expr, field: &vir::Field, _pos: &vir::Position) {
+                match field.tuple_index() {
+                    Some(component) if receiver == self.encoded_return => {
+                        self.components.insert(component);
+                    }
+                    _ => self.walk(receiver),
+                }
+            }
+        }
+
+        match assertion {
+            vir::Expr::BinOp(vir::BinOpKind::And, box lhs, box rhs, pos) => vir::Expr::BinOp(
+                vir::BinOpKind::And,
+                box self.set_result_component_positions(lhs, encoded_return),
+                box self.set_result_component_positions(rhs, encoded_return),
+                pos,
+            ),
+            conjunct => {
+                let mut finder = ComponentFinder {
+                    encoded_return,
+                    components: HashSet::new(),
+                };
+                vir::ExprWalker::walk(&mut finder, &conjunct);
+                if finder.components.len() == 1 && !conjunct.pos().is_default() {
+                    let component = finder.components.into_iter().next().unwrap();
+                    let pos = self
+                        .encoder
+                        .error_manager()
+                        .register_result_component(conjunct.pos(), component);
+                    conjunct.set_pos(pos)
+                } else {
+                    conjunct
+                }
+            }
+        }
+    }
+
     /// Encode the postcondition with three expressions:
     /// - one for the type encoding
     /// - one for the type invariants
@@ -2584,8 +3641,28 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             );
             func_spec_spans.extend(item.assertion.get_spans());
             assertion = self.wrap_arguments_into_old(assertion, pre_label, contract, &encoded_args);
+            if let ty::TypeVariants::TyTuple(..) = self.locals.get_type(contract.returned_value).sty {
+                assertion = self.set_result_component_positions(assertion, &encoded_return);
+            }
             func_spec.push(assertion);
         }
+        if let Some(op) = contract.arithmetic_newtype {
+            let (left, right, inner_ty) =
+                self.encode_arithmetic_newtype_operands(contract, &encoded_args);
+            let (result, _) = self.encoder.encode_arithmetic_newtype_value(
+                encoded_return.clone(),
+                self.locals.get_type(contract.returned_value),
+            );
+            let assertion = vir::Expr::eq_cmp(
+                result,
+                self.mir_encoder.encode_bin_op_expr(op, left, right, inner_ty),
+            );
+            // The generated postcondition has no span, so its errors are reported on the operator.
+            let pos = self.encoder.error_manager().register_span(self.mir.span);
+            let assertion =
+                self.wrap_arguments_into_old(assertion, pre_label, contract, &encoded_args);
+            func_spec.push(assertion.set_default_pos(pos));
+        }
         let func_spec_pos = self.encoder.error_manager().register_span(func_spec_spans);
 
         // Encode possible strengthening, in case of trait method implementation
@@ -2684,6 +3761,90 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         vir::ExprFolder::fold(&mut replacer, expr)
     }
 
+    /// The permissions of an argument passed by value are lost as soon as the argument is
+    /// moved, so old expressions that read its fields cannot be evaluated at the end of the
+    /// method. This method stores in ghost variables, at the beginning of the method, the
+    /// values of the moved arguments that are read in the old expressions of `exprs`, and
+    /// registers them in `old_to_ghost_var` so that `replace_old_places_with_ghost_vars` uses
+    /// the snapshots instead of the old expressions.
+    fn encode_moved_argument_snapshots<'b, I>(
+        &mut self,
+        start_cfg_block: CfgBlockIndex,
+        contract: &ProcedureContract<'tcx>,
+        exprs: I,
+    ) where
+        I: Iterator<Item = &'b vir::Expr>,
+    {
+        struct OldValueCollector {
+            moved_args: Vec<vir::LocalVar>,
+            old_values: Vec<vir::Expr>,
+        }
+        impl vir::ExprWalker for OldValueCollector {
+            fn walk_labelled_old(&mut self, label: &str, body: &vir::Expr, pos: &vir::Position) {
+                if !body.is_place() {
+                    self.walk(body);
+                    return;
+                }
+                let is_snapshotable = label == PRECONDITION_LABEL
+                    && !body.get_type().is_ref()
+                    && self.moved_args.contains(&body.get_base())
+                    // Enumerations might be in a different variant, so their fields cannot be
+                    // read unconditionally.
+                    && !body.all_prefixes().iter().any(|prefix| prefix.is_variant());
+                let old_value = vir::Expr::LabelledOld(
+                    label.to_string(),
+                    box body.clone(),
+                    pos.clone(),
+                );
+                if is_snapshotable && !self.old_values.contains(&old_value) {
+                    self.old_values.push(old_value);
+                }
+            }
+        }
+
+        let moved_args = contract
+            .args
+            .iter()
+            .filter(|&&arg| match self.locals.get_type(arg).sty {
+                ty::TypeVariants::TyAdt(..) => true,
+                _ => false,
+            })
+            .map(|&arg| self.encode_prusti_local(arg))
+            .collect();
+        let mut collector = OldValueCollector {
+            moved_args,
+            old_values: vec![],
+        };
+        for expr in exprs {
+            vir::ExprWalker::walk(&mut collector, expr);
+        }
+        if collector.old_values.is_empty() {
+            return;
+        }
+
+        self.cfg_method.add_stmt(
+            start_cfg_block,
+            vir::Stmt::comment("Snapshot the moved arguments used in the postcondition"),
+        );
+        for old_value in collector.old_values {
+            if self.old_to_ghost_var.contains_key(&old_value) {
+                continue;
+            }
+            let value = match old_value {
+                vir::Expr::LabelledOld(_, box ref value, _) => value.clone(),
+                _ => unreachable!(),
+            };
+            let vir_type = value.get_type().clone();
+            let var = self.cfg_method.add_fresh_local_var(vir_type);
+            let var_expr: vir::Expr = var.into();
+            self.cfg_method.add_stmt(
+                start_cfg_block,
+                vir::Stmt::Assign(var_expr.clone(), value, vir::AssignKind::Copy),
+            );
+            self.old_to_ghost_var.insert(old_value, var_expr);
+        }
+    }
+
     /// Encode the package statement of magic wands at the end of the method
     fn encode_package_end_of_method(
         &mut self,
@@ -2794,6 +3955,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
     /// Encode postcondition exhale on the definition side.
     fn encode_postconditions(
         &mut self,
+        start_cfg_block: CfgBlockIndex,
         return_cfg_block: CfgBlockIndex,
         contract: &ProcedureContract<'tcx>,
         postcondition_strengthening: Option<TypedAssertion>,
@@ -2818,6 +3980,14 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 true,
             );
 
+        // Take a snapshot at the beginning of the method of the values of moved arguments that
+        // are used in old expressions of the postcondition.
+        self.encode_moved_argument_snapshots(
+            start_cfg_block,
+            contract,
+            Some(&func_spec).into_iter().chain(strengthening_spec.as_ref()),
+        );
+
         // Find which arguments are blocked by the returned reference.
         let blocked_args: Vec<usize> = {
             let borrow_infos = &contract.borrow_infos;
@@ -2940,6 +4110,14 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                     vir::Stmt::Assert(patched_strengthening_spec, FoldingBehaviour::Expr, pos)
                 );
         }
+        // Assert type invariants, before the functional specification like in the inhale of the
+        // postcondition at call sites.
+        let patched_invs_spec = self.replace_old_places_with_ghost_vars(None, invs_spec);
+        self.cfg_method.add_stmt(
+            return_cfg_block,
+            vir::Stmt::Assert(patched_invs_spec, vir::FoldingBehaviour::Stmt, type_inv_pos),
+        );
+
         // Assert functional specification of postcondition
         let func_pos = self.encoder.error_manager().register(
             self.mir.span,
@@ -2955,13 +4133,6 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             ),
         );
 
-        // Assert type invariants
-        let patched_invs_spec = self.replace_old_places_with_ghost_vars(None, invs_spec);
-        self.cfg_method.add_stmt(
-            return_cfg_block,
-            vir::Stmt::Assert(patched_invs_spec, vir::FoldingBehaviour::Stmt, type_inv_pos),
-        );
-
         // Exhale permissions of postcondition
         let perm_pos = self
             .encoder
@@ -3005,7 +4176,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 counter += 1;
                 name = format!("_preserve${}", counter);
             }
-            let vir_type = vir::Type::TypedRef(String::from("AuxRef"));
+            let vir_type = vir::Type::typed_ref("AuxRef");
             self.cfg_method.add_local_var(&name, vir_type.clone());
             self.auxiliar_local_vars
                 .insert(name.clone(), vir_type.clone());
@@ -3070,7 +4241,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 }
                 let (encoded_place, ty, _) = self.mir_encoder.encode_place(&mir_place);
                 debug!("kind={:?} mir_place={:?} ty={:?}", kind, mir_place, ty);
-                if let ty::TypeVariants::TyClosure(..) = ty.sty {
+                if self.is_borrowing_closure(ty) {
                     // Do not encode closures
                     continue;
                 }
@@ -3107,8 +4278,10 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                         {
                             let (_, ref_ty, _) = self.mir_encoder.encode_place(base);
                             match ref_ty.sty {
-                                ty::TypeVariants::TyRawPtr(ty::TypeAndMut { mutbl, .. })
-                                | ty::TypeVariants::TyRef(_, _, mutbl) => {
+                                // The target of a raw pointer is owned by the `deref_spec` of
+                                // the loop invariant.
+                                ty::TypeVariants::TyRawPtr(..) => continue,
+                                ty::TypeVariants::TyRef(_, _, mutbl) => {
                                     if def_init {
                                         equalities.push(
                                             self.construct_value_preserving_equality(
@@ -3127,8 +4300,15 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             }
                         }
                         match ty.sty {
-                            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, mutbl })
-                            | ty::TypeVariants::TyRef(_, ref ty, mutbl) => {
+                            ty::TypeVariants::TyRawPtr(..) => {
+                                // A raw pointer owns only its address.
+                                let field = self.encoder.encode_value_field(ty);
+                                permissions.push(vir::Expr::acc_permission(
+                                    vir::Expr::from(encoded_place).field(field),
+                                    perm_amount,
+                                ));
+                            }
+                            ty::TypeVariants::TyRef(_, ref ty, mutbl) => {
                                 debug!(
                                     "encode_loop_invariant_permissions \
                                      mir_place={:?} mutability={:?} \
@@ -3413,10 +4593,8 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
     // TODO: What is this?
     fn encode_prusti_local(&self, local: Local) -> vir::LocalVar {
         let var_name = self.locals.get_name(local);
-        let type_name = self
-            .encoder
-            .encode_type_predicate_use(self.locals.get_type(local));
-        vir::LocalVar::new(var_name, vir::Type::TypedRef(type_name))
+        let typ = self.encoder.encode_type(self.locals.get_type(local));
+        vir::LocalVar::new(var_name, typ)
     }
 
     /// Returns
@@ -3444,9 +4622,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                     }
 
                     ty::TypeVariants::TyTuple(elems) => {
-                        let field_name = format!("tuple_{}", field.index());
                         let field_ty = elems[field.index()];
-                        let encoded_field = self.encoder.encode_raw_ref_field(field_name, field_ty);
+                        let encoded_field =
+                            self.encoder.encode_tuple_field(field.index(), field_ty);
                         let encoded_projection = encoded_base.field(encoded_field);
                         (encoded_projection, field_ty, None)
                     }
@@ -3459,7 +4637,6 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             assert_eq!(num_variants, 1);
                             0
                         });
-                        let tcx = self.encoder.env().tcx();
                         let variant_def = &adt_def.variants[variant_index];
                         let encoded_variant = if num_variants != 1 {
                             encoded_base.variant(&variant_def.name.as_str())
@@ -3467,7 +4644,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                             encoded_base
                         };
                         let field = &variant_def.fields[field.index()];
-                        let field_ty = field.ty(tcx, subst);
+                        let field_ty = self.encoder.resolve_field_ty(field, subst);
                         let encoded_field = self
                             .encoder
                             .encode_struct_field(&field.ident.as_str(), field_ty);
@@ -3584,7 +4761,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             &mir::Operand::Copy(ref place) => {
                 let (src, ty, _) = self.mir_encoder.encode_place(place);
 
-                let mut stmts = if self.mir_encoder.is_reference(ty) {
+                let mut stmts = if let ty::TypeVariants::TyRawPtr(..) = ty.sty {
+                    self.encode_copy_raw_pointer(src, lhs.clone(), ty, location)
+                } else if self.mir_encoder.is_reference(ty) {
                     let loan = self.polonius_info.get_loan_at_location(location);
                     let ref_field = self.encoder.encode_value_field(ty);
                     let mut stmts = self.prepare_assign_target(
@@ -3626,12 +4805,28 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 match literal {
                     mir::Literal::Value { value } => {
                         let const_val = self.encoder.encode_const_expr(value);
+                        let is_str_literal = match ty.sty {
+                            ty::TypeVariants::TyRef(_, inner_ty, _) => {
+                                inner_ty.sty == ty::TypeVariants::TyStr
+                            }
+                            _ => false,
+                        };
                         // Initialize value of lhs
                         stmts.push(vir::Stmt::Assign(
-                            lhs.clone().field(field),
+                            lhs.clone().field(field.clone()),
                             const_val,
                             vir::AssignKind::Copy,
                         ));
+                        if is_str_literal {
+                            // A string literal is immutable, so a read permission is enough.
+                            stmts.push(vir::Stmt::Inhale(
+                                vir::Expr::pred_permission(
+                                    lhs.clone().field(field),
+                                    vir::PermAmount::Read,
+                                ).unwrap(),
+                                vir::FoldingBehaviour::Stmt,
+                            ));
+                        }
                     }
                     mir::Literal::Promoted { index } => {
                         trace!("promoted constant literal {:?}: {:?}", index, ty);
@@ -3736,13 +4931,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         } else {
             unreachable!()
         };
-        let value_field = self
-            .encoder
-            .encode_raw_ref_field("tuple_0".to_string(), field_types[0]);
+        let value_field = self.encoder.encode_tuple_field(0, field_types[0]);
         let value_field_value = self.encoder.encode_value_field(field_types[0]);
-        let check_field = self
-            .encoder
-            .encode_raw_ref_field("tuple_1".to_string(), field_types[1]);
+        let check_field = self.encoder.encode_tuple_field(1, field_types[1]);
         let check_field_value = self.encoder.encode_value_field(field_types[1]);
         let mut stmts = if !self
             .init_info
@@ -3850,6 +5041,92 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Is `ty` the type of a closure that captures references, e.g. of a specification? Such
+    /// closures are not encoded, while a closure that captures by value is encoded like a struct
+    /// with a field for each captured variable, which can be passed to a modelled search like
+    /// `Iterator::any` or moved to a thread by `std::thread::spawn`.
+    fn is_borrowing_closure(&self, ty: ty::Ty<'tcx>) -> bool {
+        match ty.sty {
+            ty::TypeVariants::TyClosure(def_id, closure_substs) => closure_substs
+                .upvar_tys(def_id, self.encoder.env().tcx())
+                .any(|upvar_ty| {
+                    upvar_ty.walk().any(|inner_ty| match inner_ty.sty {
+                        ty::TypeVariants::TyRef(..) => true,
+                        _ => false,
+                    })
+                }),
+            _ => false,
+        }
+    }
+
+    /// Report an error if `place` is in a `#[readonly]` or `#[model]` field and the procedure
+    /// is outside of the module that defines the type of the field.
+    fn check_field_write(&self, place: &mir::Place<'tcx>, span: Span) {
+        let restricted_fields = [
+            ("readonly", "is read-only outside of its module"),
+            (MODEL_FIELD_ATTR, "can only be updated in its module"),
+        ];
+        for &(attr_name, reason) in restricted_fields.iter() {
+            if let Some((adt_def_id, field_def)) =
+                find_annotated_field(self.encoder, self.mir, place, attr_name)
+            {
+                if !is_in_defining_module(self.encoder, self.proc_def_id, adt_def_id) {
+                    let tcx = self.encoder.env().tcx();
+                    self.encoder.env().span_err(
+                        span,
+                        &format!(
+                            "[Prusti] the field `{}` of `{}` {}",
+                            field_def.ident,
+                            tcx.item_path_str(adt_def_id),
+                            reason
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// If `discr` is the discriminant of an enumeration with more than one variant, read
+    /// in the block of `location` (e.g. for a `match`, `if let` or `let ... else`), return the
+    /// encoded place of the enumeration and its definition.
+    fn find_switched_enum(
+        &self,
+        location: mir::Location,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<(vir::Expr, &'tcx ty::AdtDef)> {
+        let discr_place = match discr {
+            &mir::Operand::Copy(ref place) | &mir::Operand::Move(ref place) => place,
+            &mir::Operand::Constant(_) => return None,
+        };
+        let statements = &self.mir[location.block].statements[..location.statement_index];
+        for stmt in statements.iter().rev() {
+            match stmt.kind {
+                mir::StatementKind::Assign(ref lhs, mir::Rvalue::Discriminant(ref src))
+                    if lhs == discr_place =>
+                {
+                    let (encoded_src, src_ty, _) = self.mir_encoder.encode_place(src);
+                    return match src_ty.sty {
+                        ty::TypeVariants::TyAdt(adt_def, _)
+                            if !adt_def.is_box() && adt_def.variants.len() > 1 =>
+                        {
+                            Some((encoded_src, adt_def))
+                        }
+                        _ => None,
+                    };
+                }
+                // These statements do not change the matched enumeration.
+                mir::StatementKind::StorageLive(_)
+                | mir::StatementKind::StorageDead(_)
+                | mir::StatementKind::ReadForMatch(_)
+                | mir::StatementKind::EndRegion(_)
+                | mir::StatementKind::Validate(..)
+                | mir::StatementKind::Nop => {}
+                _ => return None,
+            }
+        }
+        None
+    }
+
     fn encode_assign_discriminant(
         &mut self,
         src: &mir::Place<'tcx>,
@@ -4044,6 +5321,27 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
         }
     }
 
+    /// Copy the address of a raw pointer, which owns nothing. The address is copied as the
+    /// location at offset zero of its memory region, because the fold-unfold algorithm would
+    /// move or borrow the target of a copied place of reference type.
+    fn encode_copy_raw_pointer(
+        &mut self,
+        src: vir::Expr,
+        dst: vir::Expr,
+        ty: ty::Ty<'tcx>,
+        location: mir::Location,
+    ) -> Vec<vir::Stmt> {
+        let field = self.encoder.encode_value_field(ty);
+        let mut stmts =
+            self.prepare_assign_target(dst.clone(), field.clone(), location, vir::AssignKind::Move);
+        stmts.push(vir::Stmt::Assign(
+            dst.field(field.clone()),
+            encode_loc(self.encoder, src.field(field), 0.into()),
+            vir::AssignKind::Copy,
+        ));
+        stmts
+    }
+
     /// Encode value copy assignment. Havoc and allocate the target if necessary.
     fn encode_copy_value_assign2(
         &mut self,
@@ -4098,8 +5396,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
     ) -> Vec<vir::Stmt> {
         let mut stmts = self.encode_havoc(&dst);
         for (field_num, ty) in elems.iter().enumerate() {
-            let field_name = format!("tuple_{}", field_num);
-            let field = self.encoder.encode_raw_ref_field(field_name, ty);
+            let field = self.encoder.encode_tuple_field(field_num, ty);
             let dst_field = dst.clone().field(field.clone());
             let acc = vir::Expr::acc_permission(dst_field.clone(), vir::PermAmount::Write);
             let pred =
@@ -4128,13 +5425,18 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
             | ty::TypeVariants::TyChar => {
                 self.encode_copy_primitive_value(src, dst, self_ty, location)
             }
+            ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(self_ty) => {
+                self.encode_copy_primitive_value(src, dst, self_ty, location)
+            }
             ty::TypeVariants::TyAdt(adt_def, _subst) if !adt_def.is_box() => {
                 self.encode_deep_copy_adt(src, dst, self_ty)
             }
             ty::TypeVariants::TyTuple(elems) => {
                 self.encode_deep_copy_tuple(src, dst, elems)
             }
-            ty::TypeVariants::TyParam(_) => {
+            ty::TypeVariants::TyParam(_)
+            | ty::TypeVariants::TyProjection(_)
+            | ty::TypeVariants::TyAnon(..) => {
                 let mut stmts = self.encode_havoc_and_allocation(&dst.clone());
                 let eq = self.encoder.encode_memory_eq_func_app(
                     src, dst, self_ty, vir::Position::default());
@@ -4169,10 +5471,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                     unreachable!()
                 };
                 for (field_num, operand) in operands.iter().enumerate() {
-                    let field_name = format!("tuple_{}", field_num);
                     let encoded_field = self
                         .encoder
-                        .encode_raw_ref_field(field_name, field_types[field_num]);
+                        .encode_tuple_field(field_num, field_types[field_num]);
                     stmts.extend(self.encode_assign_operand(
                         &dst.clone().field(encoded_field),
                         operand,
@@ -4227,8 +5528,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 for (field_index, field) in variant_def.fields.iter().enumerate() {
                     let operand = &operands[field_index];
                     let field_name = &field.ident.as_str();
-                    let tcx = self.encoder.env().tcx();
-                    let field_ty = field.ty(tcx, subst);
+                    let field_ty = self.encoder.resolve_field_ty(field, subst);
                     let encoded_field = self.encoder.encode_struct_field(field_name, field_ty);
                     stmts.extend(self.encode_assign_operand(
                         &dst_base.clone().field(encoded_field),
@@ -4239,6 +5539,20 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> ProcedureEncoder<'p, 'v, 'r, 'a, 'tcx
                 stmts
             }
 
+            &mir::AggregateKind::Closure(def_id, closure_subst) => {
+                let tcx = self.encoder.env().tcx();
+                let upvar_tys = closure_subst.upvar_tys(def_id, tcx);
+                for (index, (operand, upvar_ty)) in operands.iter().zip(upvar_tys).enumerate() {
+                    let encoded_field = self.encoder.encode_closure_field(index, upvar_ty);
+                    stmts.extend(self.encode_assign_operand(
+                        &dst.clone().field(encoded_field),
+                        operand,
+                        location,
+                    ));
+                }
+                stmts
+            }
+
             ref x => unimplemented!("{:?}", x),
         }
     }
@@ -6,6 +6,8 @@
 
 use encoder::borrows::{compute_procedure_contract, ProcedureContract};
 use encoder::builtin_encoder::BuiltinFunctionKind;
+use encoder::deref_spec::{encode_deref_spec, encode_lookup, is_region_pointer_type};
+use encoder::domain_encoder::encode_bodyless_function_model;
 use encoder::error_manager::ErrorCtxt;
 use encoder::error_manager::PanicCause;
 use encoder::foldunfold;
@@ -14,17 +16,33 @@ use encoder::mir_encoder::{PRECONDITION_LABEL, WAND_LHS_LABEL};
 use encoder::mir_interpreter::{
     run_backward_interpretation, BackwardMirInterpreter, MultiExprBackwardInterpreterState,
 };
+use encoder::model_fields::check_model_field_reads;
+use encoder::purity_inference::check_no_writes_through_references;
 use encoder::vir;
 use encoder::vir::ExprIterator;
 use encoder::Encoder;
 use prusti_interface::config;
-use prusti_interface::specifications::SpecificationSet;
+use prusti_interface::environment::library_models::LibraryModel;
+use prusti_interface::specifications::{SpecificationSet, TypedSpecification};
 use rustc::hir;
 use rustc::hir::def_id::DefId;
 use rustc::mir;
 use rustc::ty;
+use rustc_data_structures::indexed_vec::Idx;
 use std::collections::HashMap;
 
+/// The function that marks the subexpressions of a specification that are not encoded.
+const UNSAFE_SPEC_NAME: &str = "prusti_contracts::internal::unsafe_spec";
+/// The function that compares two values of a specification by snapshot, i.e. `===`.
+const SNAP_EQ_NAME: &str = "prusti_contracts::internal::snap_eq";
+/// The function that owns the memory region of a raw pointer, i.e. `deref_spec`.
+const DEREF_SPEC_NAME: &str = "prusti_contracts::internal::deref_spec";
+/// The function that owns a fraction of the memory region of a raw pointer, i.e.
+/// `deref_spec_frac`.
+const DEREF_SPEC_FRAC_NAME: &str = "prusti_contracts::internal::deref_spec_frac";
+/// The function that reads a value of the memory region of a raw pointer, i.e. `deref_value`.
+const DEREF_VALUE_NAME: &str = "prusti_contracts::internal::deref_value";
+
 pub struct PureFunctionEncoder<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
     encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
     proc_def_id: DefId,
@@ -55,6 +73,19 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
         }
     }
 
+    /// Create the encoder of the function of the component `component` of the tuple returned
+    /// by a pure function. Such a function is encoded as one Viper function per component.
+    pub fn new_component(
+        encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
+        proc_def_id: DefId,
+        mir: &'p mir::Mir<'tcx>,
+        component: usize,
+    ) -> Self {
+        let mut pure_function_encoder = Self::new(encoder, proc_def_id, mir, false);
+        pure_function_encoder.interpreter.returned_component = Some(component);
+        pure_function_encoder
+    }
+
     /// Used to encode expressions in assertions
     pub fn encode_body(&self) -> vir::Expr {
         let function_name = self.encoder.env().get_absolute_item_name(self.proc_def_id);
@@ -67,15 +98,32 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             "Pure function {} has been encoded with expr: {}",
             function_name, body_expr
         );
-        let subst_strings = self.encoder.type_substitution_strings();
-        let patched_body_expr = body_expr.patch_types(&subst_strings);
-        patched_body_expr
+        let type_substs = self.encoder.type_substitution();
+        body_expr.substitute_types(&type_substs)
     }
 
     pub fn encode_function(&self) -> vir::Function {
         let function_name = self.encode_function_name();
         debug!("Encode pure function {}", function_name);
 
+        // The body is checked once, not once for each component of a returned tuple.
+        if self.interpreter.returned_component.unwrap_or(0) == 0 {
+            check_model_field_reads(self.encoder, self.proc_def_id, self.mir);
+            check_no_writes_through_references(self.encoder, self.proc_def_id, self.mir);
+        }
+
+        let body_expr = self.encode_body_of_formal_args();
+        debug!(
+            "Pure function {} has been encoded with expr: {}",
+            function_name, body_expr
+        );
+        let body_expr = self.encode_recursive_calls(body_expr);
+
+        self.encode_function_given_body(Some(body_expr))
+    }
+
+    /// Encode the body in terms of the formal arguments of the function, which are values.
+    fn encode_body_of_formal_args(&self) -> vir::Expr {
         let mut state = run_backward_interpretation(self.mir, &self.interpreter)
             .expect(&format!("Procedure {:?} contains a loop", self.proc_def_id));
 
@@ -90,25 +138,180 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             state.substitute_place(&target_place, new_place);
         }
 
-        let body_expr = state.into_expressions().remove(0);
+        state.into_expressions().remove(0)
+    }
+
+    /// Encode the predicate declared by a `#[predicate]` function, whose argument is the value
+    /// of the argument of the function and whose body is the returned assertion.
+    pub fn encode_predicate(&self) -> vir::Predicate {
+        let item_name = self.encoder.encode_item_name(self.proc_def_id);
+        debug!("Encode predicate {}", item_name);
+
+        let args: Vec<_> = self.mir.args_iter().collect();
+        let is_supported_arg = |arg: mir::Local| {
+            match self.interpreter.mir_encoder().get_local_ty(arg).sty {
+                ty::TypeVariants::TyBool
+                | ty::TypeVariants::TyInt(_)
+                | ty::TypeVariants::TyUint(_)
+                | ty::TypeVariants::TyChar
+                | ty::TypeVariants::TyRawPtr(_) => true,
+                _ => false,
+            }
+        };
+        let returns_bool = match self.mir.return_ty().sty {
+            ty::TypeVariants::TyBool => true,
+            _ => false,
+        };
+        if args.len() != 1 || !is_supported_arg(args[0]) || !returns_bool {
+            self.encoder.env().span_err(
+                self.mir.span,
+                &format!(
+                    "[Prusti] the predicate `{}` must take a single argument of a boolean, \
+                     integer, character or raw pointer type, and return a `bool`",
+                    self.encoder.env().get_item_name(self.proc_def_id)
+                ),
+            );
+            let this = vir::LocalVar::new("self", vir::Type::Int);
+            return vir::Predicate::new_user(&item_name, this, false.into());
+        }
+
+        let body_expr = vir::ExprFolder::fold(
+            &mut PermissionConditionFolder,
+            self.encode_body_of_formal_args(),
+        );
         debug!(
-            "Pure function {} has been encoded with expr: {}",
-            function_name, body_expr
+            "Predicate {} has been encoded with body: {}",
+            item_name, body_expr
         );
-
-        self.encode_function_given_body(Some(body_expr))
+        vir::Predicate::new_user(&item_name, self.encode_local(args[0]), body_expr)
     }
 
+    /// Encode the function without body, e.g. for a `#[trusted]` pure function. If possible, its
+    /// contract becomes the axiom of a model of the function in a domain, otherwise Viper
+    /// assumes the postconditions at each application whose precondition holds.
     pub fn encode_bodyless_function(&self) -> vir::Function {
         let function_name = self.encode_function_name();
         debug!("Encode trusted (bodyless) pure function {}", function_name);
 
-        self.encode_function_given_body(None)
+        let function = self.build_function(None);
+        let function = if self.interpreter.returned_component.is_none() {
+            encode_bodyless_function_model(self.encoder, function)
+        } else {
+            // The postconditions of a component refer to the functions of the other components.
+            function
+        };
+        self.finalize_function(function)
+    }
+
+    /// Encode the functional postcondition, in terms of the arguments and of `__result`. The
+    /// postcondition of a function of a domain is encoded as an axiom of the domain.
+    pub fn encode_postcondition(&self) -> vir::Expr {
+        let contract = self.encode_contract();
+        self.encode_postcondition_expr(&contract)
     }
 
     // Private
 
+    /// Replace the recursive calls in `body` with calls of the limited version of the function,
+    /// which is encoded as a function without body whose postcondition states that it is equal
+    /// to the function. This way, the verifier unrolls the definition of the function only once
+    /// for each application that appears in the program.
+    ///
+    /// If the function has a termination measure, the limited version takes the value of the
+    /// measure at the caller as an additional argument and requires the measure of its own
+    /// arguments to be smaller and non-negative, which ensures that the recursion terminates.
+    fn encode_recursive_calls(&self, body: vir::Expr) -> vir::Expr {
+        let opt_measure = self.encoder.get_termination_measure(self.proc_def_id);
+        if opt_measure.is_none() && !config::use_limited_recursive_functions() {
+            return body;
+        }
+        let function_name = self.encode_function_name();
+        let mut replacer = RecursiveCallReplacer {
+            function_name: function_name.clone(),
+            limited_function_name: format!("{}__limited", function_name),
+            measure: opt_measure.map(|measure| self.encode_termination_measure(measure)),
+            found: false,
+        };
+        let new_body = vir::ExprFolder::fold(&mut replacer, body);
+        if !replacer.found {
+            return new_body;
+        }
+        if opt_measure.is_none() {
+            self.encoder.env().span_warn(
+                self.mir.span,
+                "[Prusti] the termination of this recursive pure function is assumed, \
+                 because it has no termination measure",
+            );
+        }
+
+        let mut limited_function = self.build_function(None);
+        let unlimited_call = vir::Expr::func_app(
+            function_name,
+            limited_function
+                .formal_args
+                .iter()
+                .cloned()
+                .map(vir::Expr::local)
+                .collect(),
+            limited_function.formal_args.clone(),
+            limited_function.return_type.clone(),
+            vir::Position::default(),
+        );
+        let pos = self
+            .encoder
+            .error_manager()
+            .register(self.mir.span, ErrorCtxt::PureFunctionDefinition);
+        let result = vir::LocalVar::new("__result", limited_function.return_type.clone());
+        limited_function
+            .posts
+            .push(vir::Expr::eq_cmp(result.into(), unlimited_call).set_pos(pos));
+        if let (Some(measure), Some(measure_spec)) = (replacer.measure, opt_measure) {
+            let bound = vir::LocalVar::new("__measure_bound", vir::Type::Int);
+            let pos = self.encoder.error_manager().register(
+                measure_spec.assertion.get_spans(),
+                ErrorCtxt::PureFunctionTerminationMeasure,
+            );
+            limited_function
+                .pres
+                .push(vir::Expr::le_cmp(0.into(), measure.clone()).set_pos(pos.clone()));
+            limited_function
+                .pres
+                .push(vir::Expr::lt_cmp(measure, bound.clone().into()).set_pos(pos));
+            limited_function.formal_args.push(bound);
+        }
+        limited_function.name = replacer.limited_function_name;
+        self.encoder
+            .register_limited_pure_function(self.finalize_function(limited_function));
+        new_body
+    }
+
+    /// Encode the termination measure of the function in terms of its formal arguments.
+    fn encode_termination_measure(&self, measure: &TypedSpecification) -> vir::Expr {
+        let encoded_args: Vec<vir::Expr> = self
+            .mir
+            .args_iter()
+            .map(|local| self.encode_local(local).into())
+            .collect();
+        self.encoder.encode_assertion(
+            &measure.assertion,
+            &self.mir,
+            &"",
+            &encoded_args,
+            None,
+            true,
+            None,
+            ErrorCtxt::GenericExpression,
+        )
+    }
+
     fn encode_function_given_body(&self, body: Option<vir::Expr>) -> vir::Function {
+        let function = self.build_function(body);
+        self.finalize_function(function)
+    }
+
+    /// Build the function, without simplifying it and without adding the fold/unfold
+    /// operations.
+    fn build_function(&self, body: Option<vir::Expr>) -> vir::Function {
         let function_name = self.encode_function_name();
         let is_bodyless = body.is_none();
         if is_bodyless {
@@ -121,32 +324,14 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             );
         }
 
-        // TODO: Clean up code duplication:
-        //let contract = self.encoder.get_procedure_contract_for_def(self.proc_def_id);
-        let contract = {
-            let opt_fun_spec = self.encoder.get_spec_by_def_id(self.proc_def_id);
-            let fun_spec = match opt_fun_spec {
-                Some(fun_spec) => fun_spec.clone(),
-                None => {
-                    debug!("Procedure {:?} has no specification", self.proc_def_id);
-                    SpecificationSet::Procedure(vec![], vec![])
-                }
-            };
-            let tymap = self.encoder.current_tymap();
-            let contract = compute_procedure_contract(
-                self.proc_def_id,
-                self.encoder.env().tcx(),
-                fun_spec,
-                Some(&tymap),
-            );
-            contract.to_def_site_contract()
-        };
-        let subst_strings = self.encoder.type_substitution_strings();
+        let contract = self.encode_contract();
+        let type_substs = self.encoder.type_substitution();
 
         let (type_precondition, func_precondition) = self.encode_precondition_expr(&contract);
-        let patched_type_precondition = type_precondition.patch_types(&subst_strings);
-        let mut precondition = vec![patched_type_precondition, func_precondition];
-        let mut postcondition = vec![self.encode_postcondition_expr(&contract)];
+        let patched_type_precondition = type_precondition.substitute_types(&type_substs);
+        // The value ranges are added before the functional specification, which may assume them.
+        let mut precondition = vec![patched_type_precondition];
+        let mut postcondition = vec![];
 
         let formal_args: Vec<_> = self
             .mir
@@ -157,7 +342,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
                 let var_type = self
                     .encoder
                     .encode_value_type(self.encoder.resolve_typaram(mir_type));
-                let var_type = var_type.patch(&subst_strings);
+                let var_type = var_type.substitute(&type_substs);
                 vir::LocalVar::new(var_name, var_type)
             })
             .collect();
@@ -175,7 +360,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
                 .encoder
                 .encode_type_bounds(
                     &vir::Expr::local(pure_fn_return_variable),
-                    self.mir.return_ty(),
+                    self.interpreter.returned_value_ty(),
                 )
                 .into_iter()
                 .map(|p| p.set_default_pos(res_value_range_pos.clone()))
@@ -190,7 +375,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
                 precondition.extend(bounds);
             }
         } else if config::encode_unsigned_num_constraint() {
-            if let ty::TypeVariants::TyUint(_) = self.mir.return_ty().sty {
+            if let ty::TypeVariants::TyUint(_) = self.interpreter.returned_value_ty().sty {
                 let expr = vir::Expr::le_cmp(0.into(), pure_fn_return_variable.into());
                 postcondition.push(expr.set_default_pos(res_value_range_pos));
             }
@@ -202,26 +387,52 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             }
         }
 
+        precondition.push(func_precondition);
+        postcondition.push(self.encode_postcondition_expr(&contract));
+
         debug_assert!(
             !postcondition.iter().any(|p| p.pos().is_default()),
             "Some postcondition has no position: {:?}",
             postcondition
         );
 
-        let mut function = vir::Function {
-            name: function_name.clone(),
+        vir::Function {
+            name: function_name,
             formal_args,
             return_type,
             pres: precondition,
             posts: postcondition,
             body,
+        }
+    }
+
+    fn encode_contract(&self) -> ProcedureContract<'tcx> {
+        // TODO: Clean up code duplication:
+        //let contract = self.encoder.get_procedure_contract_for_def(self.proc_def_id);
+        let opt_fun_spec = self.encoder.get_spec_by_def_id(self.proc_def_id);
+        let fun_spec = match opt_fun_spec {
+            Some(fun_spec) => fun_spec.clone(),
+            None => {
+                debug!("Procedure {:?} has no specification", self.proc_def_id);
+                SpecificationSet::Procedure(vec![], vec![])
+            }
         };
+        let tymap = self.encoder.current_tymap();
+        let contract = compute_procedure_contract(
+            self.proc_def_id,
+            self.encoder.env().tcx(),
+            fun_spec,
+            Some(&tymap),
+        );
+        contract.to_def_site_contract()
+    }
 
+    fn finalize_function(&self, mut function: vir::Function) -> vir::Function {
         self.encoder
             .log_vir_program_before_foldunfold(function.to_string());
 
         if config::simplify_encoding() {
-            function = vir::optimisations::functions::Simplifier::simplify(function);
+            function = vir::optimisations::simplifier::Simplifier::simplify(function);
         }
 
         // Add folding/unfolding
@@ -240,10 +451,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
     ) -> (vir::Expr, vir::Expr) {
         let type_spec = contract.args.iter().flat_map(|&local| {
             let local_ty = self.interpreter.mir_encoder().get_local_ty(local.into());
+            // Shared references only need a wildcard amount, so that the same
+            // reference can be passed several times to a pure function.
             let fraction = if let ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) =
                 local_ty.sty
             {
-                vir::PermAmount::Read
+                vir::PermAmount::Wildcard
             } else {
                 vir::PermAmount::Write
             };
@@ -290,7 +503,13 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             .iter()
             .map(|local| self.encode_local(local.clone().into()).into())
             .collect();
-        let encoded_return = self.encode_local(contract.returned_value.clone().into());
+        let encoded_return: vir::Expr = if self.interpreter.returned_component.is_some() {
+            // The components of the returned tuple are fields of the returned reference.
+            let returned_local = contract.returned_value.clone().into();
+            self.interpreter.mir_encoder().encode_local(returned_local).into()
+        } else {
+            self.encode_local(contract.returned_value.clone().into()).into()
+        };
         debug!("encoded_return: {:?}", encoded_return);
         for item in contract.functional_postcondition() {
             let encoded_postcond = self.encoder.encode_assertion(
@@ -298,7 +517,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
                 &self.mir,
                 &"",
                 &encoded_args,
-                Some(&encoded_return.clone().into()),
+                Some(&encoded_return),
                 true,
                 None,
                 ErrorCtxt::GenericExpression,
@@ -307,8 +526,6 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
             func_spec.push(encoded_postcond);
         }
 
-        let post = func_spec.into_iter().conjoin();
-
         // TODO: use a better span
         let postcondition_pos = self
             .encoder
@@ -318,8 +535,74 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
         // Fix return variable
         let pure_fn_return_variable =
             vir::LocalVar::new("__result", self.encode_function_return_type());
-        post.replace_place(&encoded_return.into(), &pure_fn_return_variable.into())
-            .set_default_pos(postcondition_pos)
+        let post = match self.interpreter.returned_component {
+            Some(component) => self.encode_component_postcondition(
+                func_spec,
+                &encoded_return,
+                component,
+                &pure_fn_return_variable.into(),
+            ),
+            None => func_spec
+                .into_iter()
+                .conjoin()
+                .replace_place(&encoded_return, &pure_fn_return_variable.into()),
+        };
+        post.set_default_pos(postcondition_pos)
+    }
+
+    /// Keep the postconditions that are about the component `component` of the returned tuple,
+    /// or about none of its components if `component` is the first one, and express them in
+    /// terms of the result of the function and of the functions of the other components.
+    fn encode_component_postcondition(
+        &self,
+        posts: Vec<vir::Expr>,
+        encoded_return: &vir::Expr,
+        component: usize,
+        result: &vir::Expr,
+    ) -> vir::Expr {
+        let component_tys = match self.encoder.resolve_typaram(self.mir.return_ty()).sty {
+            ty::TypeVariants::TyTuple(component_tys) => component_tys,
+            ref x => unreachable!("{:?}", x),
+        };
+        let component_values: Vec<vir::Expr> = component_tys
+            .iter()
+            .enumerate()
+            .map(|(index, &component_ty)| {
+                encoded_return
+                    .clone()
+                    .field(self.encoder.encode_tuple_field(index, component_ty))
+                    .field(self.encoder.encode_value_field(component_ty))
+            })
+            .collect();
+        let mut post = posts
+            .into_iter()
+            .filter(|post| {
+                post.find(&component_values[component])
+                    || (component == 0 && !component_values.iter().any(|value| post.find(value)))
+            })
+            .conjoin();
+
+        let function_name = self.encoder.encode_item_name(self.proc_def_id);
+        let formal_args: Vec<vir::LocalVar> = self
+            .mir
+            .args_iter()
+            .map(|local| self.encode_local(local))
+            .collect();
+        for (index, &component_ty) in component_tys.iter().enumerate() {
+            let replacement = if index == component {
+                result.clone()
+            } else {
+                vir::Expr::func_app(
+                    encode_component_function_name(&function_name, index),
+                    formal_args.iter().cloned().map(vir::Expr::local).collect(),
+                    formal_args.clone(),
+                    self.encoder.encode_value_type(component_ty),
+                    vir::Position::default(),
+                )
+            };
+            post = post.replace_place(&component_values[index], &replacement);
+        }
+        post
     }
 
     fn encode_local(&self, local: mir::Local) -> vir::LocalVar {
@@ -331,18 +614,152 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionEncoder<'p, 'v, 'r, 'a, '
     }
 
     pub fn encode_function_name(&self) -> String {
-        self.encoder.encode_item_name(self.proc_def_id)
+        let function_name = self.encoder.encode_item_name(self.proc_def_id);
+        match self.interpreter.returned_component {
+            Some(component) => encode_component_function_name(&function_name, component),
+            None => function_name,
+        }
     }
 
     pub fn encode_function_return_type(&self) -> vir::Type {
-        let ty = self.encoder.resolve_typaram(self.mir.return_ty());
+        let ty = self.encoder.resolve_typaram(self.interpreter.returned_value_ty());
         self.encoder.encode_value_type(ty)
     }
 }
 
+/// The name of the function that encodes the component `component` of the tuple returned by
+/// the pure function `function_name`.
+pub fn encode_component_function_name(function_name: &str, component: usize) -> String {
+    format!("{}__tuple_{}", function_name, component)
+}
+
+/// Replaces the recursive calls of a pure function with calls of its limited version.
+struct RecursiveCallReplacer {
+    function_name: String,
+    limited_function_name: String,
+    /// The termination measure of the function, if any, which is passed as an additional
+    /// argument of the limited version.
+    measure: Option<vir::Expr>,
+    /// Whether a recursive call has been found.
+    found: bool,
+}
+
+impl vir::ExprFolder for RecursiveCallReplacer {
+    fn fold_func_app(
+        &mut self,
+        name: String,
+        args: Vec<vir::Expr>,
+        formal_args: Vec<vir::LocalVar>,
+        return_type: vir::Type,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        let mut args: Vec<_> = args.into_iter().map(|e| self.fold(e)).collect();
+        if name != self.function_name {
+            return vir::Expr::FuncApp(name, args, formal_args, return_type, pos);
+        }
+        self.found = true;
+        let mut formal_args = formal_args;
+        if let Some(ref measure) = self.measure {
+            args.push(measure.clone());
+            formal_args.push(vir::LocalVar::new("__measure_bound", vir::Type::Int));
+        }
+        vir::Expr::FuncApp(
+            self.limited_function_name.clone(),
+            args,
+            formal_args,
+            return_type,
+            pos,
+        )
+    }
+}
+
+/// Replaces the conditional expressions of the body of a predicate whose guard is a permission,
+/// which Viper rejects, with conjunctions. For example, `deref_spec(p, 1) && x` is encoded as
+/// `!acc(..) ? false : x` by the backward interpretation.
+struct PermissionConditionFolder;
+
+impl vir::ExprFolder for PermissionConditionFolder {
+    fn fold_cond(
+        &mut self,
+        guard: Box<vir::Expr>,
+        then_expr: Box<vir::Expr>,
+        else_expr: Box<vir::Expr>,
+        pos: vir::Position,
+    ) -> vir::Expr {
+        let guard = self.fold_boxed(guard);
+        let then_expr = self.fold_boxed(then_expr);
+        let else_expr = self.fold_boxed(else_expr);
+        if guard.is_pure() {
+            return vir::Expr::Cond(guard, then_expr, else_expr, pos);
+        }
+        match (*guard, *then_expr, *else_expr) {
+            (
+                vir::Expr::UnaryOp(vir::UnaryOpKind::Not, box guard, _),
+                vir::Expr::Const(vir::Const::Bool(false), _),
+                then_expr,
+            )
+            | (guard, then_expr, vir::Expr::Const(vir::Const::Bool(false), _)) => {
+                vir::Expr::BinOp(vir::BinOpKind::And, box guard, box then_expr, pos)
+            }
+            (guard, then_expr, else_expr) => {
+                vir::Expr::Cond(box guard, box then_expr, box else_expr, pos)
+            }
+        }
+    }
+}
+
+/// Encode a pure method of a trait without a default body as a function without body. There is
+/// no MIR, so the signature is taken from the type of the method; the `Self` type is a type
+/// parameter, thus it is encoded with an abstract predicate.
+pub fn encode_bodyless_trait_method<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: DefId,
+) -> vir::Function {
+    let function_name = encoder.encode_item_name(proc_def_id);
+    debug!("Encode body-less trait method {}", function_name);
+
+    let fn_sig = encoder.env().tcx().fn_sig(proc_def_id);
+    let type_substs = encoder.type_substitution();
+    let mut formal_args = vec![];
+    let mut type_precondition: Vec<vir::Expr> = vec![];
+    for (index, &arg_ty) in fn_sig.inputs().skip_binder().iter().enumerate() {
+        let arg_ty = encoder.resolve_typaram(arg_ty);
+        let var_type = encoder.encode_value_type(arg_ty).substitute(&type_substs);
+        // Same names as the arguments of the other pure functions.
+        let formal_arg = vir::LocalVar::new(format!("_pure_{}", index + 1), var_type);
+        let fraction = if let ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) =
+            arg_ty.sty
+        {
+            vir::PermAmount::Wildcard
+        } else {
+            vir::PermAmount::Write
+        };
+        type_precondition.extend(vir::Expr::pred_permission(formal_arg.clone().into(), fraction));
+        formal_args.push(formal_arg);
+    }
+    let return_ty = encoder.resolve_typaram(fn_sig.output().skip_binder());
+
+    let function = vir::Function {
+        name: function_name,
+        formal_args,
+        return_type: encoder.encode_value_type(return_ty),
+        pres: vec![type_precondition.into_iter().conjoin()],
+        posts: vec![],
+        body: None,
+    };
+    encoder.log_vir_program_before_foldunfold(function.to_string());
+
+    foldunfold::add_folding_unfolding_to_function(
+        function,
+        encoder.get_used_viper_predicates_map(),
+    )
+}
+
 pub(super) struct PureFunctionBackwardInterpreter<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
     encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
     mir: &'p mir::Mir<'tcx>,
+    /// The procedure (or the specification closure) whose MIR is encoded.
+    def_id: DefId,
     mir_encoder: MirEncoder<'p, 'v, 'r, 'a, 'tcx>,
     namespace: String,
     /// True if the encoder is currently encoding an assertion and not a pure function body. This
@@ -350,6 +767,8 @@ pub(super) struct PureFunctionBackwardInterpreter<'p, 'v: 'p, 'r: 'v, 'a: 'r, 't
     /// when to a undefined function calls. This distinction allows overflow checks to be checked
     /// on the caller side and assumed on the definition side.
     is_encoding_assertion: bool,
+    /// The component of the returned tuple that is encoded, if the function returns a tuple.
+    returned_component: Option<usize>,
 }
 
 /// XXX: This encoding works backward, but there is the risk of generating expressions whose length
@@ -366,9 +785,11 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionBackwardInterpreter<'p, '
         PureFunctionBackwardInterpreter {
             encoder,
             mir,
+            def_id,
             mir_encoder: MirEncoder::new_with_namespace(encoder, mir, def_id, namespace.clone()),
             namespace,
             is_encoding_assertion,
+            returned_component: None,
         }
     }
 
@@ -376,6 +797,104 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> PureFunctionBackwardInterpreter<'p, '
         &self.mir_encoder
     }
 
+    /// The type of the encoded value: the returned value, or its encoded component.
+    pub(super) fn returned_value_ty(&self) -> ty::Ty<'tcx> {
+        match (self.returned_component, &self.mir.return_ty().sty) {
+            (Some(component), ty::TypeVariants::TyTuple(component_tys)) => {
+                component_tys[component]
+            }
+            _ => self.mir.return_ty(),
+        }
+    }
+
+    /// Encode the place returned by the `deref` (or `deref_mut`) method of an `impl`, if the body
+    /// of the method only borrows a place of `*self` (e.g. `&self.field`). `self_target` is the
+    /// encoded place of `*self` at the call site.
+    fn encode_transparent_deref(
+        &self,
+        impl_method_def_id: DefId,
+        self_target: vir::Expr,
+    ) -> Option<vir::Expr> {
+        let procedure = self.encoder.env().get_procedure(impl_method_def_id);
+        let mir = procedure.get_mir();
+        let mir_encoder = MirEncoder::new(self.encoder, mir, impl_method_def_id);
+        if mir.basic_blocks().len() != 1 {
+            return None;
+        }
+        let basic_block = &mir.basic_blocks()[mir::START_BLOCK];
+        match basic_block.terminator().kind {
+            mir::TerminatorKind::Return => {}
+            _ => return None,
+        }
+        let encode_deref_local = |local: mir::Local| {
+            let encoded_local: vir::Expr = mir_encoder.encode_local(local).into();
+            mir_encoder
+                .encode_deref(encoded_local, mir_encoder.get_local_ty(local))
+                .0
+        };
+        // The targets of the references stored in the local variables, starting from `self`.
+        let self_local = mir::Local::new(1);
+        let mut targets = vec![(self_local, encode_deref_local(self_local), self_target)];
+        for stmt in &basic_block.statements {
+            let (local, rvalue) = match stmt.kind {
+                mir::StatementKind::Assign(mir::Place::Local(local), ref rvalue) => {
+                    (local, rvalue)
+                }
+                mir::StatementKind::StorageLive(_) | mir::StatementKind::StorageDead(_) => {
+                    continue
+                }
+                _ => return None,
+            };
+            let target = match rvalue {
+                mir::Rvalue::Ref(_, _, ref place) => {
+                    let base_local = place_base_local(place)?;
+                    if !targets.iter().any(|(target_local, _, _)| *target_local == base_local) {
+                        return None;
+                    }
+                    targets.iter().fold(
+                        mir_encoder.encode_place(place).0,
+                        |target, (_, deref_place, deref_target)| {
+                            target.replace_place(deref_place, deref_target)
+                        },
+                    )
+                }
+                mir::Rvalue::Use(mir::Operand::Copy(mir::Place::Local(source)))
+                | mir::Rvalue::Use(mir::Operand::Move(mir::Place::Local(source))) => targets
+                    .iter()
+                    .find(|(target_local, _, _)| target_local == source)?
+                    .2
+                    .clone(),
+                _ => return None,
+            };
+            targets.push((local, encode_deref_local(local), target));
+        }
+        targets
+            .into_iter()
+            .rev()
+            .find(|(local, _, _)| *local == mir::RETURN_PLACE)
+            .map(|(_, _, target)| target)
+    }
+}
+
+/// The local variable at the root of the place.
+fn place_base_local(place: &mir::Place) -> Option<mir::Local> {
+    match place {
+        mir::Place::Local(local) => Some(*local),
+        mir::Place::Projection(box mir::Projection { ref base, .. }) => place_base_local(base),
+        _ => None,
+    }
+}
+
+/// Encode a reference to the place, simplifying the address of a dereference.
+fn encode_address_of(place: vir::Expr) -> vir::Expr {
+    match place {
+        vir::Expr::Field(box ref base, vir::Field { ref name, .. }, ref _pos)
+            if name == "val_ref" =>
+        {
+            base.clone()
+        }
+        other_place => other_place.addr_of(),
+    }
 }
 
 impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
@@ -394,7 +913,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
 
         // Generate a function call that leaves the expression undefined.
         let unreachable_expr = |pos| {
-            let encoded_type = self.encoder.encode_value_type(self.mir.return_ty());
+            let encoded_type = self.encoder.encode_value_type(self.returned_value_ty());
             let function_name =
                 self.encoder
                     .encode_builtin_function_use(BuiltinFunctionKind::Unreachable(
@@ -405,7 +924,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
 
         // Generate a function call that leaves the expression undefined.
         let undef_expr = |pos| {
-            let encoded_type = self.encoder.encode_value_type(self.mir.return_ty());
+            let encoded_type = self.encoder.encode_value_type(self.returned_value_ty());
             let function_name = self
                 .encoder
                 .encode_builtin_function_use(BuiltinFunctionKind::Undefined(encoded_type.clone()));
@@ -460,10 +979,15 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                 trace!("Return type: {:?}", self.mir.return_ty());
                 let return_type = self.encoder.encode_type(self.mir.return_ty());
                 let return_var = vir::LocalVar::new(format!("{}_0", self.namespace), return_type);
-                let field = self.encoder.encode_value_field(self.mir.return_ty());
-                MultiExprBackwardInterpreterState::new_single(
-                    vir::Expr::local(return_var.into()).field(field).into(),
-                )
+                let mut returned_place = vir::Expr::local(return_var.into());
+                if let Some(component) = self.returned_component {
+                    let component_field = self
+                        .encoder
+                        .encode_tuple_field(component, self.returned_value_ty());
+                    returned_place = returned_place.field(component_field);
+                }
+                let field = self.encoder.encode_value_field(self.returned_value_ty());
+                MultiExprBackwardInterpreterState::new_single(returned_place.field(field).into())
             }
 
             TerminatorKind::SwitchInt {
@@ -576,6 +1100,10 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                 ..
             } => {
                 let func_proc_name: &str = &self.encoder.env().tcx().absolute_item_path_str(def_id);
+                let library_model = self.encoder.library_model(func_proc_name);
+                if !self.is_encoding_assertion {
+                    self.encoder.check_executable_call(def_id, term.source_info.span);
+                }
 
                 let own_substs =
                     ty::subst::Substs::identity_for_item(self.encoder.env().tcx(), def_id);
@@ -600,13 +1128,38 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                 let state = if destination.is_some() {
                     let (ref lhs_place, target_block) = destination.as_ref().unwrap();
                     let (encoded_lhs, ty, _) = self.mir_encoder.encode_place(lhs_place);
-                    let lhs_value = encoded_lhs
-                        .clone()
-                        .field(self.encoder.encode_value_field(ty));
-                    let encoded_args: Vec<vir::Expr> = args
-                        .iter()
-                        .map(|arg| self.mir_encoder.encode_operand_expr(arg))
-                        .collect();
+                    // A returned tuple has no value field, only its components have one.
+                    let lhs_value = || {
+                        encoded_lhs
+                            .clone()
+                            .field(self.encoder.encode_value_field(ty))
+                    };
+                    let encoded_args: Vec<vir::Expr> = if func_proc_name == UNSAFE_SPEC_NAME {
+                        // The argument of `unsafe_spec` is not meant to be encoded
+                        vec![]
+                    } else {
+                        args.iter()
+                            .map(|arg| self.mir_encoder.encode_operand_expr(arg))
+                            .collect()
+                    };
+
+                    // The place returned by an overloaded dereference of the argument.
+                    let overloaded_deref_target = if library_model == Some(LibraryModel::Deref)
+                        && !self.mir_encoder.can_be_dereferenced(substs.type_at(0))
+                    {
+                        self.encoder
+                            .resolve_trait_method_call(self.def_id, def_id, substs)
+                            .and_then(|impl_method_def_id| {
+                                let arg_place =
+                                    self.mir_encoder.encode_operand_place(&args[0])?;
+                                let arg_ty = self.mir_encoder.get_operand_ty(&args[0]);
+                                let (self_target, _, _) =
+                                    self.mir_encoder.encode_deref(arg_place, arg_ty);
+                                self.encode_transparent_deref(impl_method_def_id, self_target)
+                            })
+                    } else {
+                        None
+                    };
 
                     match func_proc_name {
                         "prusti_contracts::internal::old" => {
@@ -616,7 +1169,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                                 .mir_encoder
                                 .encode_old_expr(encoded_args[0].clone(), PRECONDITION_LABEL);
                             let mut state = states[&target_block].clone();
-                            state.substitute_value(&lhs_value, encoded_rhs);
+                            state.substitute_value(&lhs_value(), encoded_rhs);
                             state
                         }
 
@@ -627,16 +1180,230 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                                 .mir_encoder
                                 .encode_old_expr(encoded_args[0].clone(), WAND_LHS_LABEL);
                             let mut state = states[&target_block].clone();
-                            state.substitute_value(&lhs_value, encoded_rhs);
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        UNSAFE_SPEC_NAME => {
+                            trace!("Encoding unsafe_spec expression {:?}", args[0]);
+                            assert_eq!(args.len(), 1);
+                            let encoded_rhs = self
+                                .encoder
+                                .encode_unsafe_spec_use(ty, term.source_info.span);
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        // Calls to `deref` of a type with a builtin dereference (e.g. a `Box`)
+                        // are inserted by autoderef. Encode them as the builtin dereference.
+                        _ if library_model == Some(LibraryModel::Deref)
+                            && self.mir_encoder.can_be_dereferenced(substs.type_at(0)) =>
+                        {
+                            trace!("Encoding builtin dereference {:?}", args[0]);
+                            assert_eq!(args.len(), 1);
+                            let arg_place = self.mir_encoder.encode_operand_place(&args[0]).unwrap();
+                            let arg_ty = self.mir_encoder.get_operand_ty(&args[0]);
+                            let (deref_arg_place, deref_arg_ty, _) =
+                                self.mir_encoder.encode_deref(arg_place, arg_ty);
+                            let (target_place, _, _) =
+                                self.mir_encoder.encode_deref(deref_arg_place, deref_arg_ty);
+                            let mut state = states[&target_block].clone();
+                            state.substitute_place(&encoded_lhs, encode_address_of(target_place));
+                            state
+                        }
+
+                        // Calls to `deref` of a type with an overloaded dereference are inserted
+                        // by autoderef too. Encode the place returned by the `impl` that rustc
+                        // resolves the call to.
+                        _ if overloaded_deref_target.is_some() => {
+                            trace!("Encoding overloaded dereference {:?}", args[0]);
+                            let target_place = overloaded_deref_target.clone().unwrap();
+                            let mut state = states[&target_block].clone();
+                            state.substitute_place(&encoded_lhs, encode_address_of(target_place));
+                            state
+                        }
+
+                        SNAP_EQ_NAME => {
+                            trace!("Encoding snap_eq expression {:?}", args);
+                            assert_eq!(args.len(), 2);
+                            let arg_ty = substs.type_at(0);
+                            let encoded_rhs = match self.encoder.encode_snapshot_eq(
+                                encoded_args[0].clone(),
+                                encoded_args[1].clone(),
+                                arg_ty,
+                            ) {
+                                Some(snapshots_eq) => snapshots_eq,
+                                None => {
+                                    self.encoder.env().span_err(
+                                        term.source_info.span,
+                                        &format!(
+                                            "[Prusti] the values of type `{}` cannot be compared \
+                                             with `===`, which supports booleans, integers, \
+                                             characters, values of a domain, structs of such \
+                                             values and references to them",
+                                            arg_ty
+                                        ),
+                                    );
+                                    false.into()
+                                }
+                            };
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        DEREF_SPEC_NAME | DEREF_SPEC_FRAC_NAME => {
+                            trace!("Encoding deref_spec expression {:?}", args);
+                            let perm_amount = if func_proc_name == DEREF_SPEC_NAME {
+                                assert_eq!(args.len(), 2);
+                                Some(vir::PermAmount::Write)
+                            } else {
+                                assert_eq!(args.len(), 4);
+                                match (&encoded_args[2], &encoded_args[3]) {
+                                    (
+                                        &vir::Expr::Const(vir::Const::Int(numerator), _),
+                                        &vir::Expr::Const(vir::Const::Int(denominator), _),
+                                    ) if 0 < numerator
+                                        && numerator <= denominator
+                                        && denominator <= i64::from(::std::u32::MAX) =>
+                                    {
+                                        Some(vir::PermAmount::frac(
+                                            numerator as u32,
+                                            denominator as u32,
+                                        ))
+                                    }
+                                    _ => {
+                                        self.encoder.env().span_err(
+                                            term.source_info.span,
+                                            "[Prusti] the fraction of a `deref_spec_frac` must \
+                                             be given by integer literals, and be positive and \
+                                             at most one",
+                                        );
+                                        None
+                                    }
+                                }
+                            };
+                            let ptr_ty = substs.type_at(0);
+                            let encoded_rhs = match (&ptr_ty.sty, perm_amount) {
+                                (
+                                    &ty::TypeVariants::TyRawPtr(ty::TypeAndMut {
+                                        ty: pointee_ty,
+                                        ..
+                                    }),
+                                    Some(perm_amount),
+                                ) if is_region_pointer_type(ptr_ty) => encode_deref_spec(
+                                    self.encoder,
+                                    encoded_args[0].clone(),
+                                    encoded_args[1].clone(),
+                                    self.encoder.encode_value_field(pointee_ty),
+                                    perm_amount,
+                                ),
+                                (_, None) => false.into(),
+                                _ => {
+                                    self.encoder.env().span_err(
+                                        term.source_info.span,
+                                        &format!(
+                                            "[Prusti] `deref_spec` supports only raw pointers \
+                                             to booleans, integers and characters, not `{}`",
+                                            ptr_ty
+                                        ),
+                                    );
+                                    false.into()
+                                }
+                            };
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        DEREF_VALUE_NAME => {
+                            trace!("Encoding deref_value expression {:?}", args);
+                            assert_eq!(args.len(), 2);
+                            let ptr_ty = substs.type_at(0);
+                            let encoded_rhs = match ptr_ty.sty {
+                                ty::TypeVariants::TyRawPtr(ty::TypeAndMut {
+                                    ty: pointee_ty,
+                                    ..
+                                }) if is_region_pointer_type(ptr_ty) => encode_lookup(
+                                    self.encoder,
+                                    encoded_args[0].clone(),
+                                    encoded_args[1].clone(),
+                                    self.encoder.encode_value_field(pointee_ty),
+                                ),
+                                _ => {
+                                    self.encoder.env().span_err(
+                                        term.source_info.span,
+                                        &format!(
+                                            "[Prusti] `deref_value` supports only raw pointers \
+                                             to booleans, integers and characters, not `{}`",
+                                            ptr_ty
+                                        ),
+                                    );
+                                    false.into()
+                                }
+                            };
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        // `==` and `!=` on the values of a struct with a derived `PartialEq`
+                        // compare their snapshots.
+                        _ if self.encoder.is_snapshot_eq_call(def_id, substs) => {
+                            trace!("Encoding snapshot equality {:?}", args);
+                            assert_eq!(args.len(), 2);
+                            let self_ty = substs.type_at(0);
+                            let lhs_snapshot = self
+                                .encoder
+                                .encode_snapshot_use(encoded_args[0].clone(), self_ty);
+                            let rhs_snapshot = self
+                                .encoder
+                                .encode_snapshot_use(encoded_args[1].clone(), self_ty);
+                            let snapshots_eq = vir::Expr::eq_cmp(lhs_snapshot, rhs_snapshot);
+                            let item_name = self.encoder.env().tcx().item_name(def_id).to_string();
+                            let encoded_rhs = if item_name == "ne" {
+                                vir::Expr::not(snapshots_eq)
+                            } else {
+                                snapshots_eq
+                            };
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
+                            state
+                        }
+
+                        // The application of a `#[predicate]` function is the permission to the
+                        // predicate that it declares.
+                        _ if self.encoder.is_predicate(def_id) => {
+                            trace!("Encoding predicate application {:?}", args);
+                            let predicate_name = self.encoder.encode_user_predicate_use(def_id);
+                            let encoded_rhs = if encoded_args.len() == 1 {
+                                vir::Expr::predicate_access_predicate(
+                                    predicate_name,
+                                    encoded_args[0].clone(),
+                                    vir::PermAmount::Write,
+                                )
+                            } else {
+                                // The signature of the predicate has been reported
+                                false.into()
+                            };
+                            let mut state = states[&target_block].clone();
+                            state.substitute_value(&lhs_value(), encoded_rhs);
                             state
                         }
 
                         // generic function call
                         _ => {
+                            // If the called trait method is statically resolved to a pure method
+                            // of an `impl`, call its function.
+                            let def_id = self
+                                .encoder
+                                .resolve_trait_method_call(self.def_id, def_id, substs)
+                                .filter(|&impl_def_id| self.encoder.is_pure(impl_def_id))
+                                .unwrap_or(def_id);
                             let function_name = self.encoder.encode_pure_function_use(def_id);
                             trace!("Encoding pure function call '{}'", function_name);
 
-                            let return_type = self.encoder.encode_pure_function_return_type(def_id);
                             let formal_args: Vec<vir::LocalVar> = args
                                 .iter()
                                 .enumerate()
@@ -652,23 +1419,51 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                                 .encoder
                                 .error_manager()
                                 .register(term.source_info.span, ErrorCtxt::PureFunctionCall);
-                            let encoded_rhs = vir::Expr::func_app(
-                                function_name,
-                                encoded_args,
-                                formal_args,
-                                return_type,
-                                pos,
-                            );
 
                             let mut state = states[&target_block].clone();
-                            state.substitute_value(&lhs_value, encoded_rhs);
+                            if let ty::TypeVariants::TyTuple(component_tys) = ty.sty {
+                                // Each component of a returned tuple has its own function.
+                                for (component, &component_ty) in component_tys.iter().enumerate()
+                                {
+                                    let component_field =
+                                        self.encoder.encode_tuple_field(component, component_ty);
+                                    let component_value = encoded_lhs
+                                        .clone()
+                                        .field(component_field)
+                                        .field(self.encoder.encode_value_field(component_ty));
+                                    let encoded_rhs = vir::Expr::func_app(
+                                        self.encoder
+                                            .encode_pure_function_component_use(def_id, component),
+                                        encoded_args.clone(),
+                                        formal_args.clone(),
+                                        self.encoder.encode_value_type(component_ty),
+                                        pos.clone(),
+                                    );
+                                    state.substitute_value(&component_value, encoded_rhs);
+                                }
+                            } else {
+                                let encoded_rhs =
+                                    match self.encoder.encode_domain_function_use(def_id) {
+                                        Some(domain_func) => {
+                                            vir::Expr::DomainFuncApp(domain_func, encoded_args, pos)
+                                        }
+                                        None => vir::Expr::func_app(
+                                            function_name,
+                                            encoded_args,
+                                            formal_args,
+                                            self.encoder.encode_pure_function_return_type(def_id),
+                                            pos,
+                                        ),
+                                    };
+                                state.substitute_value(&lhs_value(), encoded_rhs);
+                            }
                             state
                         }
                     }
                 } else {
                     // Encoding of a non-terminating function call
-                    let error_ctxt = match func_proc_name {
-                        "std::rt::begin_panic" | "std::panicking::begin_panic" => {
+                    let error_ctxt = match library_model {
+                        Some(LibraryModel::BeginPanic) => {
                             // This is called when a Rust assertion fails
                             // args[0]: message
                             // args[1]: position of failing assertions
@@ -745,6 +1540,14 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                         .encoder
                         .error_manager()
                         .register(term.source_info.span, error_ctxt);
+                    if library_model == Some(LibraryModel::BeginPanic) {
+                        // args[0]: message
+                        if let Some(message) = self.encoder.str_literal_operand_content(&args[0]) {
+                            self.encoder
+                                .error_manager()
+                                .register_panic_message(&pos, &message);
+                        }
+                    }
                     MultiExprBackwardInterpreterState::new_single(unreachable_expr(pos))
                 };
 
@@ -842,6 +1645,11 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                             .clone()
                             .field(self.encoder.encode_value_field(ty)),
                     ),
+                    ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(ty) => Some(
+                        encoded_lhs
+                            .clone()
+                            .field(self.encoder.encode_value_field(ty)),
+                    ),
                     _ => None,
                 };
 
@@ -872,10 +1680,39 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                                     unreachable!()
                                 };
                                 for (field_num, operand) in operands.iter().enumerate() {
-                                    let field_name = format!("tuple_{}", field_num);
                                     let field_ty = field_types[field_num];
                                     let encoded_field =
-                                        self.encoder.encode_raw_ref_field(field_name, field_ty);
+                                        self.encoder.encode_tuple_field(field_num, field_ty);
+                                    let field_place = encoded_lhs.clone().field(encoded_field);
+
+                                    match self.mir_encoder.encode_operand_place(operand) {
+                                        Some(encoded_rhs) => {
+                                            // Substitute a place
+                                            state.substitute_place(&field_place, encoded_rhs);
+                                        }
+                                        None => {
+                                            // Substitute a place of a value with an expression
+                                            let rhs_expr =
+                                                self.mir_encoder.encode_operand_expr(operand);
+                                            let value_field =
+                                                self.encoder.encode_value_field(field_ty);
+                                            state.substitute_value(
+                                                &field_place.field(value_field),
+                                                rhs_expr,
+                                            );
+                                        }
+                                    }
+                                }
+                            }
+
+                            &mir::AggregateKind::Closure(def_id, closure_subst) => {
+                                let tcx = self.encoder.env().tcx();
+                                let upvar_tys = closure_subst.upvar_tys(def_id, tcx);
+                                for (index, (operand, field_ty)) in
+                                    operands.iter().zip(upvar_tys).enumerate()
+                                {
+                                    let encoded_field =
+                                        self.encoder.encode_closure_field(index, field_ty);
                                     let field_place = encoded_lhs.clone().field(encoded_field);
 
                                     match self.mir_encoder.encode_operand_place(operand) {
@@ -914,8 +1751,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                                 for (field_index, field) in variant_def.fields.iter().enumerate() {
                                     let operand = &operands[field_index];
                                     let field_name = &field.ident.as_str();
-                                    let tcx = self.encoder.env().tcx();
-                                    let field_ty = field.ty(tcx, subst);
+                                    let field_ty = self.encoder.resolve_field_ty(field, subst);
                                     let encoded_field =
                                         self.encoder.encode_struct_field(field_name, field_ty);
 
@@ -987,13 +1823,9 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                         } else {
                             unreachable!()
                         };
-                        let value_field = self
-                            .encoder
-                            .encode_raw_ref_field("tuple_0".to_string(), field_types[0]);
+                        let value_field = self.encoder.encode_tuple_field(0, field_types[0]);
                         let value_field_value = self.encoder.encode_value_field(field_types[0]);
-                        let check_field = self
-                            .encoder
-                            .encode_raw_ref_field("tuple_1".to_string(), field_types[1]);
+                        let check_field = self.encoder.encode_tuple_field(1, field_types[1]);
                         let check_field_value = self.encoder.encode_value_field(field_types[1]);
 
                         let lhs_value = encoded_lhs
@@ -1006,7 +1838,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                             .field(check_field_value);
 
                         // Substitute a place of a value with an expression
-                        state.substitute_value(&lhs_value, encoded_value);
+                        state.substitute_value(&lhs_value(), encoded_value);
                         state.substitute_value(&lhs_check, encoded_check);
                     }
 
@@ -1063,20 +1895,8 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> BackwardMirInterpreter<'tcx>
                     | &mir::Rvalue::Ref(_, mir::BorrowKind::Mut { .. }, ref place)
                     | &mir::Rvalue::Ref(_, mir::BorrowKind::Shared, ref place) => {
                         let encoded_place = self.mir_encoder.encode_place(place).0;
-                        let encoded_ref = match encoded_place {
-                            vir::Expr::Field(
-                                box ref base,
-                                vir::Field { ref name, .. },
-                                ref _pos,
-                            ) if name == "val_ref" => {
-                                // Simplify "address of reference"
-                                base.clone()
-                            }
-                            other_place => other_place.addr_of(),
-                        };
-
                         // Substitute the place
-                        state.substitute_place(&encoded_lhs, encoded_ref);
+                        state.substitute_place(&encoded_lhs, encode_address_of(encoded_place));
                     }
 
                     &mir::Rvalue::Cast(mir::CastKind::Misc, ref operand, dst_ty) => {
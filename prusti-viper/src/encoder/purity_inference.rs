@@ -0,0 +1,259 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Inference of the procedures that can be encoded as pure functions without a
+//! `#[pure]` annotation, and of the closures that can be encoded as functions of their
+//! argument, and check that the pure functions do not write to the heap.
+
+use encoder::Encoder;
+use prusti_filter::validators::Validator;
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::{Procedure, ProcedureLoops};
+use rustc::hir;
+use rustc::mir;
+use rustc::mir::visit::{PlaceContext, Visitor};
+use rustc::ty;
+
+/// The attribute that prevents a procedure from being inferred as pure.
+pub const IMPURE_ATTR: &str = "impure";
+
+/// Check whether a procedure without a `#[pure]` annotation can be encoded as a pure
+/// function. The procedure must:
+/// * be a private procedure of the verified crate, not trusted, not a `#[predicate]` and not
+///   marked `#[impure]`;
+/// * not be a method of a trait, whose body might be overridden or missing;
+/// * be supported by the encoding of pure functions (e.g. no loops, primitive result);
+/// * not take mutable references and not write through references;
+/// * call only pure procedures, and never itself (directly or indirectly), so that it
+///   trivially terminates.
+///
+/// `Encoder::is_pure` marks the procedure as impure while this check runs, so that
+/// recursive calls are rejected.
+pub fn infer_purity(encoder: &Encoder, proc_def_id: ProcedureDefId) -> bool {
+    let tcx = encoder.env().tcx();
+    if !proc_def_id.is_local()
+        || tcx.is_closure(proc_def_id)
+        || tcx.trait_of_item(proc_def_id).is_some()
+        || tcx.visibility(proc_def_id) == ty::Visibility::Public
+        || encoder.is_trusted(proc_def_id)
+        || encoder.is_predicate(proc_def_id)
+        || encoder.env().has_attribute_name(proc_def_id, IMPURE_ATTR)
+    {
+        return false;
+    }
+
+    let validator = Validator::new(tcx);
+    if !validator.pure_function_support_status(proc_def_id).is_supported() {
+        trace!("{:?} is not supported as a pure function", proc_def_id);
+        return false;
+    }
+
+    let procedure = Procedure::new(tcx, proc_def_id);
+    let mir = procedure.get_mir();
+
+    for arg_index in mir.args_iter() {
+        if mutably_references(mir.local_decls[arg_index].ty) {
+            trace!("{:?} takes a mutable reference", proc_def_id);
+            return false;
+        }
+    }
+
+    has_pure_body(encoder, &procedure)
+}
+
+/// Check whether a closure can be encoded as a function of the value of its argument, e.g. the
+/// predicate passed to `Iterator::any`. The closure must:
+/// * capture no variables, so that it has no state;
+/// * take a single argument, an integer or a character behind shared references, and return a
+///   boolean;
+/// * have no loops, not write to the heap and call only pure procedures, like an inferred pure
+///   function.
+pub fn is_pure_closure(encoder: &Encoder, closure_def_id: ProcedureDefId) -> bool {
+    let tcx = encoder.env().tcx();
+    if !closure_def_id.is_local() || !tcx.is_closure(closure_def_id) {
+        return false;
+    }
+    let procedure = Procedure::new(tcx, closure_def_id);
+    let mir = procedure.get_mir();
+    // The first argument is the environment of the closure.
+    if !mir.upvar_decls.is_empty() || mir.arg_count != 2 {
+        return false;
+    }
+    match mir.return_ty().sty {
+        ty::TypeVariants::TyBool => {}
+        _ => return false,
+    }
+    let mut arg_ty = mir.local_decls[mir.args_iter().nth(1).unwrap()].ty;
+    while let ty::TypeVariants::TyRef(_, inner_ty, hir::Mutability::MutImmutable) = arg_ty.sty {
+        arg_ty = inner_ty;
+    }
+    match arg_ty.sty {
+        ty::TypeVariants::TyInt(_) | ty::TypeVariants::TyUint(_) | ty::TypeVariants::TyChar => {}
+        _ => return false,
+    }
+    if ProcedureLoops::new(mir).count_loop_heads() > 0 {
+        trace!("{:?} has a loop", closure_def_id);
+        return false;
+    }
+    has_pure_body(encoder, &procedure)
+}
+
+/// Check that the reachable blocks of the body of a procedure do not write to the heap and call
+/// only pure procedures.
+fn has_pure_body(encoder: &Encoder, procedure: &Procedure) -> bool {
+    let proc_def_id = procedure.get_id();
+    let mir = procedure.get_mir();
+    for (bbi, basic_block_data) in mir.basic_blocks().iter_enumerated() {
+        if !procedure.is_reachable_block(bbi) || procedure.is_spec_block(bbi) {
+            continue;
+        }
+        for stmt in &basic_block_data.statements {
+            if let mir::StatementKind::Assign(ref place, ref rvalue) = stmt.kind {
+                if writes_through_reference(place) || borrows_mutably(rvalue) {
+                    trace!("{:?} writes to the heap: {:?}", proc_def_id, stmt);
+                    return false;
+                }
+            }
+        }
+        if let mir::TerminatorKind::Call {
+            ref func,
+            destination: Some(_),
+            ..
+        } = basic_block_data.terminator().kind
+        {
+            // Calls without destination diverge, and are encoded as failures.
+            if !calls_pure_procedure(encoder, func) {
+                trace!("{:?} calls an impure procedure: {:?}", proc_def_id, func);
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn mutably_references(ty: ty::Ty) -> bool {
+    ty.walk().any(|inner_ty| match inner_ty.sty {
+        ty::TypeVariants::TyRef(_, _, hir::Mutability::MutMutable) => true,
+        ty::TypeVariants::TyRawPtr(_) => true,
+        _ => false,
+    })
+}
+
+fn writes_through_reference(place: &mir::Place) -> bool {
+    match *place {
+        mir::Place::Projection(box mir::Projection {
+            elem: mir::ProjectionElem::Deref,
+            ..
+        }) => true,
+        mir::Place::Projection(box mir::Projection { ref base, .. }) => {
+            writes_through_reference(base)
+        }
+        _ => false,
+    }
+}
+
+fn borrows_mutably(rvalue: &mir::Rvalue) -> bool {
+    match *rvalue {
+        mir::Rvalue::Ref(_, mir::BorrowKind::Shared, _) => false,
+        mir::Rvalue::Ref(..) => true,
+        _ => false,
+    }
+}
+
+fn calls_pure_procedure(encoder: &Encoder, func: &mir::Operand) -> bool {
+    match *func {
+        mir::Operand::Constant(box mir::Constant {
+            literal: mir::Literal::Value {
+                value: ty::Const {
+                    ty: &ty::TyS {
+                        sty: ty::TyFnDef(called_def_id, _),
+                        ..
+                    },
+                    ..
+                },
+            },
+            ..
+        }) => encoder.is_pure(called_def_id),
+        _ => false,
+    }
+}
+
+/// Report an error for each write through a reference in `mir`, the body of the pure function
+/// `proc_def_id`. A pure function can take mutable references, e.g. a getter whose receiver is
+/// `&mut self`, but its callers assume that the memory behind them is not modified.
+pub fn check_no_writes_through_references<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    mir: &mir::Mir<'tcx>,
+) {
+    let mut checker = HeapWriteChecker {
+        encoder,
+        proc_def_id,
+        mir,
+    };
+    checker.visit_mir(mir);
+}
+
+struct HeapWriteChecker<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> {
+    encoder: &'p Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    mir: &'p mir::Mir<'tcx>,
+}
+
+impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> HeapWriteChecker<'p, 'v, 'r, 'a, 'tcx> {
+    /// Does `place` dereference a reference? The dereferences of the boxes owned by the
+    /// function do not access memory of the callers.
+    fn is_behind_reference(&self, place: &mir::Place<'tcx>) -> bool {
+        match *place {
+            mir::Place::Projection(box mir::Projection {
+                ref base,
+                elem: mir::ProjectionElem::Deref,
+            }) => {
+                let tcx = self.encoder.env().tcx();
+                match base.ty(self.mir, tcx).to_ty(tcx).sty {
+                    ty::TypeVariants::TyRef(..) | ty::TypeVariants::TyRawPtr(..) => true,
+                    _ => self.is_behind_reference(base),
+                }
+            }
+            mir::Place::Projection(box mir::Projection { ref base, .. }) => {
+                self.is_behind_reference(base)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> Visitor<'tcx>
+    for HeapWriteChecker<'p, 'v, 'r, 'a, 'tcx>
+{
+    fn visit_place(
+        &mut self,
+        place: &mir::Place<'tcx>,
+        context: PlaceContext<'tcx>,
+        location: mir::Location,
+    ) {
+        // A `DropAndReplace` terminator drops the place before writing to it.
+        let is_write = match context {
+            PlaceContext::Store
+            | PlaceContext::AsmOutput
+            | PlaceContext::Call
+            | PlaceContext::Drop => true,
+            _ => false,
+        };
+        if is_write && self.is_behind_reference(place) {
+            let tcx = self.encoder.env().tcx();
+            self.encoder.env().span_err(
+                self.mir.source_info(location).span,
+                &format!(
+                    "[Prusti] the pure function `{}` must not modify the memory behind a \
+                     reference",
+                    tcx.item_path_str(self.proc_def_id)
+                ),
+            );
+        }
+    }
+}
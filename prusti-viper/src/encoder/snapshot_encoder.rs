@@ -0,0 +1,301 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Encoding of the snapshots of the values of the structs, which are used to encode `==` and
+//! `!=` on the structs with a derived `PartialEq` as an extensional equality.
+//!
+//! The snapshot of a value is a heap-independent value that contains the values of its fields.
+//! The snapshots of the values of a struct are the values of a domain, built by a constructor
+//! function that takes the snapshots of the fields. An axiom states that the projections of a
+//! snapshot return the arguments of its constructor, thus two snapshots are equal if and only if
+//! the snapshots of their fields are equal. The snapshot of a value in the heap is returned by a
+//! function that requires the predicate of the value.
+//!
+//! A struct has snapshots if the values of its fields are booleans, integers, characters, values
+//! of a `#[domain]` struct or values of a struct with snapshots. It is compared by snapshot if,
+//! in addition, its `PartialEq` is derived and its fields are compared by snapshot, where a field
+//! of a `#[domain]` struct is compared by snapshot only if the `PartialEq` of the domain struct
+//! is derived too. Then, the derived `PartialEq` compares the fields in the same way, whereas a
+//! custom `PartialEq` of a domain struct might not be the equality of its values. The `===` operator of the
+//! specifications (i.e. `snap_eq`) compares by snapshot the values of any struct with snapshots,
+//! regardless of its `PartialEq`.
+
+use encoder::vir;
+use encoder::vir::ExprIterator;
+use encoder::Encoder;
+use rustc::ty;
+
+/// Are the values of the type compared by snapshot?
+pub fn is_snapshot_type<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    is_struct_with_fields(encoder, ty, &|field_ty| {
+        is_primitive_type(field_ty)
+            || (encoder.is_domain_type(field_ty) && encoder.has_derived_partial_eq(field_ty))
+            || is_snapshot_type(encoder, field_ty)
+    }) && encoder.has_derived_partial_eq(ty)
+}
+
+/// Has the type snapshots, whatever its `PartialEq`?
+fn has_snapshots<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    is_struct_with_fields(encoder, ty, &|field_ty| {
+        is_snapshot_value_type(encoder, field_ty) || has_snapshots(encoder, field_ty)
+    })
+}
+
+/// Is the type a struct, other than a box or a domain type, whose fields satisfy `is_valid_field`?
+fn is_struct_with_fields<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+    is_valid_field: &Fn(ty::Ty<'tcx>) -> bool,
+) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, subst) if adt_def.is_struct() && !adt_def.is_box() => {
+            !encoder.is_domain_type(ty)
+                && adt_def.variants[0]
+                    .fields
+                    .iter()
+                    .all(|field| is_valid_field(encoder.resolve_field_ty(field, subst)))
+        }
+        _ => false,
+    }
+}
+
+/// Encode the structural equality of the values of type `ty` at the places `left` and `right`,
+/// if the type is a primitive type, a domain type, a struct with snapshots or a reference to
+/// one of them.
+pub fn encode_snapshot_eq<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    left: vir::Expr,
+    right: vir::Expr,
+    ty: ty::Ty<'tcx>,
+) -> Option<vir::Expr> {
+    match ty.sty {
+        ty::TypeVariants::TyRef(_, target_ty, _) => {
+            let value_field = encoder.encode_value_field(ty);
+            encode_snapshot_eq(
+                encoder,
+                left.field(value_field.clone()),
+                right.field(value_field),
+                target_ty,
+            )
+        }
+        _ if is_snapshot_value_type(encoder, ty) => {
+            let value_field = encoder.encode_value_field(ty);
+            Some(vir::Expr::eq_cmp(
+                left.field(value_field.clone()),
+                right.field(value_field),
+            ))
+        }
+        _ if has_snapshots(encoder, ty) => Some(vir::Expr::eq_cmp(
+            encoder.encode_snapshot_use(left, ty),
+            encoder.encode_snapshot_use(right, ty),
+        )),
+        _ => None,
+    }
+}
+
+/// Is the snapshot of a value of the type the value itself?
+fn is_snapshot_value_type<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    is_primitive_type(ty) || encoder.is_domain_type(ty)
+}
+
+/// Is the type a boolean, an integer or a character type?
+fn is_primitive_type(ty: ty::Ty) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyBool
+        | ty::TypeVariants::TyInt(_)
+        | ty::TypeVariants::TyUint(_)
+        | ty::TypeVariants::TyChar => true,
+        _ => false,
+    }
+}
+
+/// The name of the domain of the snapshots of the struct `ty`.
+pub fn encode_snapshot_domain_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> String {
+    format!("{}$Snap", encoder.encode_type_predicate_use(ty))
+}
+
+/// The name of the function that returns the snapshot of a value of the struct `ty`.
+pub fn encode_snapshot_function_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> String {
+    format!("{}$snap", encoder.encode_type_predicate_use(ty))
+}
+
+/// Encode the application of the snapshot function of the struct `ty` to `place`.
+pub fn encode_snapshot_func_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    place: vir::Expr,
+    ty: ty::Ty<'tcx>,
+) -> vir::Expr {
+    let self_var = vir::LocalVar::new("self", encoder.encode_type(ty));
+    vir::Expr::func_app(
+        encode_snapshot_function_name(encoder, ty),
+        vec![place],
+        vec![self_var],
+        vir::Type::Domain(encode_snapshot_domain_name(encoder, ty)),
+        vir::Position::default(),
+    )
+}
+
+/// Encode the domain of the snapshots of the struct `ty`, with the constructor, the
+/// projections on the fields and the axiom that relates them.
+pub fn encode_snapshot_domain<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> vir::Domain {
+    let domain_name = encode_snapshot_domain_name(encoder, ty);
+    let mut domain = vir::Domain::new(domain_name.clone());
+    let snapshot_type = domain.get_type();
+    let constructor = encode_constructor(encoder, ty);
+    let field_args = constructor.formal_args.clone();
+    let constructor_app = vir::Expr::domain_func_app(
+        constructor.clone(),
+        field_args.iter().cloned().map(vir::Expr::local).collect(),
+    );
+    domain.functions.push(constructor.clone());
+    let mut projection_eqs = vec![];
+    for field_arg in &field_args {
+        let projection = vir::DomainFunc {
+            name: format!("{}$proj${}", encoder.encode_type_predicate_use(ty), field_arg.name),
+            formal_args: vec![vir::LocalVar::new("snapshot", snapshot_type.clone())],
+            return_type: field_arg.typ.clone(),
+            unique: false,
+            domain_name: domain_name.clone(),
+        };
+        let projection_app = vir::Expr::domain_func_app(
+            projection.clone(),
+            vec![constructor_app.clone()],
+        );
+        projection_eqs.push(vir::Expr::eq_cmp(projection_app, field_arg.clone().into()));
+        domain.functions.push(projection);
+    }
+    if !field_args.is_empty() {
+        domain.axioms.push(vir::DomainAxiom {
+            name: format!("{}$injective", constructor.name),
+            expr: vir::Expr::forall(
+                field_args,
+                vec![vir::Trigger::new(vec![constructor_app])],
+                projection_eqs.into_iter().conjoin(),
+            ),
+            domain_name,
+        });
+    }
+    domain
+}
+
+/// Encode the function that returns the snapshot of a value of the struct `ty`, i.e. the
+/// constructor applied to the snapshots of the fields of the value.
+pub fn encode_snapshot_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> vir::Function {
+    let self_var = vir::LocalVar::new("self", encoder.encode_type(ty));
+    let self_place: vir::Expr = self_var.clone().into();
+    let field_snapshots: Vec<vir::Expr> = snapshot_fields(encoder, ty)
+        .into_iter()
+        .map(|(field_name, field_ty)| {
+            let field_place = self_place
+                .clone()
+                .field(encoder.encode_struct_field(&field_name, field_ty));
+            encode_field_snapshot(encoder, field_place, field_ty)
+        })
+        .collect();
+    let constructor = encode_constructor(encoder, ty);
+    let snapshot_type = constructor.return_type.clone();
+    // Like the memory equality functions, the function is generated with its unfoldings.
+    let body = vir::Expr::wrap_in_unfolding(
+        self_place.clone(),
+        vir::Expr::domain_func_app(constructor, field_snapshots),
+    );
+    vir::Function {
+        name: encode_snapshot_function_name(encoder, ty),
+        formal_args: vec![self_var],
+        return_type: snapshot_type,
+        pres: vec![vir::Expr::pred_permission(self_place, vir::PermAmount::Read).unwrap()],
+        posts: vec![],
+        body: Some(body),
+    }
+}
+
+/// The constructor of the snapshots of the struct `ty`, which takes the snapshots of the fields.
+fn encode_constructor<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> vir::DomainFunc {
+    let domain_name = encode_snapshot_domain_name(encoder, ty);
+    let formal_args = snapshot_fields(encoder, ty)
+        .into_iter()
+        .map(|(field_name, field_ty)| {
+            vir::LocalVar::new(
+                format!("f${}", field_name),
+                encode_field_snapshot_type(encoder, field_ty),
+            )
+        })
+        .collect();
+    vir::DomainFunc {
+        name: format!("{}$cons", encoder.encode_type_predicate_use(ty)),
+        formal_args,
+        return_type: vir::Type::Domain(domain_name.clone()),
+        unique: false,
+        domain_name,
+    }
+}
+
+/// The names and the types of the fields of the struct `ty`.
+fn snapshot_fields<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> Vec<(String, ty::Ty<'tcx>)> {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, subst) => adt_def.variants[0]
+            .fields
+            .iter()
+            .map(|field| (field.ident.to_string(), encoder.resolve_field_ty(field, subst)))
+            .collect(),
+        ref x => unreachable!("{:?}", x),
+    }
+}
+
+fn encode_field_snapshot_type<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    field_ty: ty::Ty<'tcx>,
+) -> vir::Type {
+    if is_snapshot_value_type(encoder, field_ty) {
+        encoder.encode_value_type(field_ty)
+    } else {
+        encoder.encode_snapshot_type(field_ty)
+    }
+}
+
+/// Encode the snapshot of the value of type `field_ty` at `field_place`, with the unfolding of
+/// its predicate if the value is primitive.
+fn encode_field_snapshot<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    field_place: vir::Expr,
+    field_ty: ty::Ty<'tcx>,
+) -> vir::Expr {
+    if is_snapshot_value_type(encoder, field_ty) {
+        let value = field_place
+            .clone()
+            .field(encoder.encode_value_field(field_ty));
+        vir::Expr::wrap_in_unfolding(field_place, value)
+    } else {
+        encoder.encode_snapshot_use(field_place, field_ty)
+    }
+}
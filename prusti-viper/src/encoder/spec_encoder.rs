@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use encoder::deref_spec::encode_lookup_triggers;
 use encoder::error_manager::ErrorCtxt;
 use encoder::mir_encoder::MirEncoder;
 use encoder::mir_encoder::PRECONDITION_LABEL;
@@ -15,6 +16,7 @@ use encoder::pure_function_encoder::PureFunctionBackwardInterpreter;
 use encoder::vir::ExprIterator;
 use encoder::vir;
 use encoder::Encoder;
+use prusti_interface::config;
 use prusti_interface::specifications::*;
 use rustc::hir;
 use rustc::hir::def_id::DefId;
@@ -119,8 +121,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 }
             },
             None => {
-                let field_name = format!("tuple_{}", field_index);
-                let field = vir::Field::new(field_name, encoded_type);
+                let field = self.encoder.encode_tuple_field(field_index, field_ty);
                 base_place.field(field)
             }
         }
@@ -141,12 +142,34 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
         assert!(
             match arg_ty.sty {
                 ty::TypeVariants::TyInt(..) | ty::TypeVariants::TyUint(..) => true,
-                _ => false,
+                _ => self.encoder.is_domain_type(arg_ty),
+            },
+            "Quantification is only supported over integer and domain values"
+        );
+
+        vir::LocalVar::new(var_name, self.encoder.encode_value_type(arg_ty))
+    }
+
+    /// Encode the variable bound by a `let` of a specification.
+    fn encode_let_var(&self, arg: &hir::Arg) -> vir::LocalVar {
+        trace!("encode_let_var: {:?}", arg);
+        let var_name = match arg.pat.node {
+            hir::PatKind::Binding(_, _, ident, ..) => ident.node.to_string(),
+            ref x => unimplemented!("{:?}", x),
+        };
+        let arg_ty = self.encoder.env().hir_id_to_type(arg.hir_id);
+
+        assert!(
+            match arg_ty.sty {
+                ty::TypeVariants::TyBool
+                | ty::TypeVariants::TyInt(..)
+                | ty::TypeVariants::TyUint(..) => true,
+                _ => self.encoder.is_domain_type(arg_ty),
             },
-            "Quantification is only supported over integer values"
+            "Let bindings are only supported for integer, boolean and domain values"
         );
 
-        vir::LocalVar::new(var_name, vir::Type::Int)
+        vir::LocalVar::new(var_name, self.encoder.encode_value_type(arg_ty))
     }
 
     fn path_to_string(&self, var_path: &hir::Path) -> String {
@@ -203,14 +226,13 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
             assert!(
                 match var_ty.sty {
                     ty::TypeVariants::TyInt(..) | ty::TypeVariants::TyUint(..) => true,
-                    _ => false,
+                    _ => self.encoder.is_domain_type(var_ty),
                 },
-                "Quantification is only supported over integer values"
+                "Quantification is only supported over integer and domain values"
             );
-            vir::Type::Int
+            self.encoder.encode_value_type(var_ty)
         } else {
-            let type_name = self.encoder.encode_type_predicate_use(&var_ty);
-            vir::Type::TypedRef(type_name)
+            self.encoder.encode_type(&var_ty)
         };
 
         vir::LocalVar::new(var_name, encoded_type)
@@ -231,10 +253,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 assert!(place.get_type().is_ref());
                 match place {
                     vir::Expr::AddrOf(box base, _typ, _) => base,
-                    _ => {
-                        let type_name: String = self.encoder.encode_type_predicate_use(base_ty);
-                        place.field(vir::Field::new("val_ref", vir::Type::TypedRef(type_name)))
-                    }
+                    _ => place.field(vir::Field::new("val_ref", self.encoder.encode_type(base_ty))),
                 }
             }
 
@@ -269,6 +288,10 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                     .field(vir::Field::new("val_int", vir::Type::Int))
                     .into(),
 
+                ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(base_ty) => place
+                    .field(self.encoder.encode_value_field(base_ty))
+                    .into(),
+
                 ty::TypeVariants::TyTuple(..) | ty::TypeVariants::TyAdt(..) => place.into(),
 
                 ref x => unimplemented!("{:?}", x),
@@ -340,6 +363,26 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
         )
     }
 
+    /// Encode the triggers of a quantifier over `vars` with body `body`. Without user-given
+    /// triggers, the quantifier is triggered by the lookups of the memory regions in its body if
+    /// `USE_LOOKUP_TRIGGERS` is enabled, otherwise its triggers are inferred by Viper.
+    fn encode_triggers(
+        &self,
+        trigger_set: &TypedTriggerSet,
+        vars: &[vir::LocalVar],
+        body: &vir::Expr,
+    ) -> Vec<vir::Trigger> {
+        if trigger_set.triggers().is_empty() && config::use_lookup_triggers() {
+            encode_lookup_triggers(vars, body)
+        } else {
+            trigger_set
+                .triggers()
+                .iter()
+                .map(|x| self.encode_trigger(x))
+                .collect()
+        }
+    }
+
     /// Encode a specification item as a single expression.
     pub fn encode_assertion(&self, assertion: &TypedAssertion) -> vir::Expr {
         trace!("encode_assertion {:?}", assertion);
@@ -347,7 +390,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
             box AssertionKind::Expr(ref assertion_expr) => self.encode_expression(assertion_expr),
             box AssertionKind::And(ref assertions) => assertions
                 .iter()
-                .map(|x| self.encode_assertion(x))
+                .map(|x| self.encode_conjunct(x))
                 .collect::<Vec<vir::Expr>>()
                 .into_iter()
                 .conjoin(),
@@ -367,15 +410,32 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                     vir::Expr::eq_cmp(enc(vars.vars[0].hir_id), enc(vars.vars[1].hir_id));
                 vir::Expr::implies(typecond, self.encode_assertion(assertion))
             }
-            box AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => vir::Expr::forall(
-                vars.vars.iter().map(|x| self.encode_hir_arg(x)).collect(),
-                trigger_set
-                    .triggers()
-                    .iter()
-                    .map(|x| self.encode_trigger(x))
-                    .collect(),
-                self.encode_assertion(body),
-            ),
+            box AssertionKind::ForAll(ref vars, ref trigger_set, ref body) => {
+                let encoded_vars: Vec<_> =
+                    vars.vars.iter().map(|x| self.encode_hir_arg(x)).collect();
+                let encoded_body = self.encode_assertion(body);
+                let triggers = self.encode_triggers(trigger_set, &encoded_vars, &encoded_body);
+                vir::Expr::forall(encoded_vars, triggers, encoded_body)
+            }
+            box AssertionKind::Exists(ref vars, ref trigger_set, ref body) => {
+                let encoded_vars: Vec<_> =
+                    vars.vars.iter().map(|x| self.encode_hir_arg(x)).collect();
+                let encoded_body = self.encode_assertion(body);
+                let triggers = self.encode_triggers(trigger_set, &encoded_vars, &encoded_body);
+                vir::Expr::exists(encoded_vars, triggers, encoded_body)
+            }
+            box AssertionKind::Let(ref vars, ref value, ref body) => {
+                let pos = self
+                    .encoder
+                    .error_manager()
+                    .register_span(assertion.get_spans());
+                vir::Expr::let_expr(
+                    self.encode_let_var(&vars.vars[0]),
+                    self.encode_expression(value),
+                    self.encode_assertion(body),
+                )
+                .set_pos(pos)
+            }
             box AssertionKind::Pledge(ref _reference, ref _lhs, ref _rhs) => {
                 // Pledges are moved inside magic wands, so here we have only true.
                 true.into()
@@ -383,6 +443,16 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
         }
     }
 
+    /// Encode a conjunct of a specification item, recording its span so that
+    /// a failure is reported at the failing conjunct instead of at the whole item.
+    fn encode_conjunct(&self, assertion: &TypedAssertion) -> vir::Expr {
+        let pos = self
+            .encoder
+            .error_manager()
+            .register_span(assertion.get_spans());
+        self.encode_assertion(assertion).set_default_pos(pos)
+    }
+
     fn encode_expression(&self, assertion_expr: &TypedExpression) -> vir::Expr {
         debug!("encode_expression {:?}", assertion_expr);
         let tcx = self.encoder.env().tcx();
@@ -489,8 +559,7 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 .iter()
                 .enumerate()
                 .map(|(index, &captured_ty)| {
-                    let field_name = format!("closure_{}", index);
-                    let encoded_field = self.encoder.encode_raw_ref_field(field_name, captured_ty);
+                    let encoded_field = self.encoder.encode_closure_field(index, captured_ty);
                     deref_closure_var.clone().field(encoded_field)
                 })
                 .collect();
@@ -627,7 +696,12 @@ impl<'p, 'v: 'p, 'r: 'v, 'a: 'r, 'tcx: 'a> SpecEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 None => {}
             }*/
 
-            let spec_fake_return_place: vir::Expr = if self.targets_are_values {
+            // A returned tuple has no value, its components are projected from the target.
+            let is_tuple = match fake_return_ty.sty {
+                ty::TypeVariants::TyTuple(..) => true,
+                _ => false,
+            };
+            let spec_fake_return_place: vir::Expr = if self.targets_are_values && !is_tuple {
                 let value_field = self.encoder.encode_value_field(fake_return_ty);
                 vir::Expr::local(spec_fake_return).field(value_field)
             } else {
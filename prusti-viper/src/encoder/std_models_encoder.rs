@@ -0,0 +1,1403 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Encoding of the models of the standard library defined in `prusti_std_models`.
+//!
+//! A pure model is encoded as a function for each type of receiver, since the precondition of
+//! the function mentions the predicate of the receiver. An impure model is encoded as an
+//! additional precondition and postcondition of the calls, expressed with the pure models.
+//!
+//! The maps are abstracted by a function to a mathematical map of the Viper map domain, which
+//! requires the keys and the values to be of primitive types. The strings are abstracted by a
+//! function to the sequence of their bytes, and the vectors and slices of integers or characters
+//! by a function to the sequence of their elements. The permutations of such sequences are
+//! described by the `SeqPermutation` domain. The models of the locks are encoded in
+//! `sync_models_encoder`.
+//!
+//! An iterator over the elements of a slice is abstracted like the slice, by the elements that
+//! the slice had when the iterator was created, and by a ghost index that splits them into the
+//! visited elements, before the index, and the unvisited ones. The iterator does not block the
+//! slice: each reference that it yields has the permission to a fresh element, whose value is
+//! the element at the index. Since the values written through these references are not given
+//! back to the slice, the slice has unknown elements, with the same length, after the call that
+//! creates the iterator.
+//!
+//! The searches of such an iterator (`any`, `all`, `position` and `find`) are described by
+//! quantifiers over its unvisited elements, if the closure that they take is accepted by
+//! `is_pure_closure`. The closure is then encoded as a Viper function of the value of its
+//! argument, whose body is the encoded body of the closure, in which the value replaces the
+//! dereferences of the argument.
+
+use encoder::foldunfold;
+use encoder::mir_encoder::MirEncoder;
+use encoder::sync_models_encoder::encode_sync_model_contract;
+use encoder::type_encoder::compute_discriminant_values;
+use encoder::vir;
+use encoder::vir::ExprIterator;
+use encoder::Encoder;
+use prusti_std_models::{is_iterator_type, is_map_type, is_string_type, is_vec_type, StdModel};
+use rustc::hir;
+use rustc::hir::def_id::DefId;
+use rustc::ty;
+
+/// The name of the function that encodes a pure model for arguments of types `arg_tys`, behind
+/// the references. The first argument is the receiver.
+pub fn encode_std_model_function_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    arg_tys: &[ty::Ty<'tcx>],
+) -> String {
+    let mut name = format!("std_model${}", model.name());
+    for &arg_ty in arg_tys {
+        name.push_str(&format!("${}", encoder.encode_type_predicate_use(arg_ty)));
+    }
+    name
+}
+
+pub fn encode_std_model_return_type(model: StdModel) -> vir::Type {
+    match model {
+        StdModel::Len => vir::Type::Int,
+        StdModel::IsVariant(_)
+        | StdModel::IsEmpty
+        | StdModel::ContainsKey
+        | StdModel::StrEq => vir::Type::Bool,
+        _ => unreachable!("{:?} is not a pure model", model),
+    }
+}
+
+/// Encode the function of a pure model, for arguments of types `arg_tys`, behind the
+/// references. The first argument is the receiver.
+pub fn encode_std_model_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    arg_tys: &[ty::Ty<'tcx>],
+) -> vir::Function {
+    let mut formal_args = vec![];
+    let mut pres = vec![];
+    for (index, &arg_ty) in arg_tys.iter().enumerate() {
+        let typ = encoder.encode_type(arg_ty);
+        let name = if index == 0 {
+            "self".to_string()
+        } else {
+            format!("arg{}", index)
+        };
+        let arg = vir::LocalVar::new(name, typ);
+        pres.push(vir::Expr::predicate_access_predicate(
+            arg.typ.name(),
+            arg.clone().into(),
+            vir::PermAmount::Read,
+        ));
+        formal_args.push(arg);
+    }
+    let receiver_ty = arg_tys[0];
+    let receiver_expr: vir::Expr = formal_args[0].clone().into();
+    let return_type = encode_std_model_return_type(model);
+
+    let (posts, body) = match model {
+        StdModel::Len if is_map(encoder, receiver_ty) => {
+            let map = encode_map_abstraction_app(encoder, receiver_ty, receiver_expr);
+            (vec![], Some(vir::Expr::map_len(map)))
+        }
+
+        StdModel::Len if is_string(encoder, receiver_ty) => {
+            let bytes = encode_str_abstraction_app(encoder, receiver_ty, receiver_expr);
+            (vec![], Some(vir::Expr::seq_len(bytes)))
+        }
+
+        StdModel::Len if is_sequence(encoder, receiver_ty) => {
+            let elements = encode_seq_abstraction_app(encoder, receiver_ty, receiver_expr);
+            (vec![], Some(vir::Expr::seq_len(elements)))
+        }
+
+        // The length is not defined in terms of the content of the collection.
+        StdModel::Len => {
+            let result = vir::LocalVar::new("__result", vir::Type::Int);
+            (vec![vir::Expr::ge_cmp(result.into(), 0.into())], None)
+        }
+
+        StdModel::IsEmpty => {
+            let len = encode_std_model_app(encoder, StdModel::Len, receiver_ty, receiver_expr);
+            (vec![], Some(vir::Expr::eq_cmp(len, 0.into())))
+        }
+
+        StdModel::IsVariant(variant_name) => {
+            let adt_def = match receiver_ty.sty {
+                ty::TypeVariants::TyAdt(adt_def, _) => adt_def,
+                ref x => unreachable!("{:?} is not an enum", x),
+            };
+            let variant_index = adt_def
+                .variants
+                .iter()
+                .position(|variant_def| &*variant_def.name.as_str() == variant_name)
+                .unwrap_or_else(|| panic!("{:?} has no variant {}", adt_def, variant_name));
+            let body = if adt_def.variants.len() == 1 {
+                true.into()
+            } else {
+                let discr_values = compute_discriminant_values(adt_def, encoder.env().tcx());
+                vir::Expr::eq_cmp(
+                    encoder.encode_discriminant_func_app(receiver_expr, adt_def),
+                    discr_values[variant_index].into(),
+                )
+            };
+            (vec![], Some(body))
+        }
+
+        StdModel::ContainsKey => {
+            let key_ty = arg_tys[1];
+            let key_expr: vir::Expr = formal_args[1].clone().into();
+            let map = encode_map_abstraction_app(encoder, receiver_ty, receiver_expr);
+            let key_value = key_expr.field(encoder.encode_value_field(key_ty));
+            (vec![], Some(vir::Expr::map_contains(map, key_value)))
+        }
+
+        // E.g. `String == &str`: the second argument is behind one more reference.
+        StdModel::StrEq => {
+            let (other, other_ty) =
+                dereference_all(encoder, formal_args[1].clone().into(), arg_tys[1]);
+            let bytes = encode_str_abstraction_app(encoder, receiver_ty, receiver_expr);
+            let other_bytes = encode_str_abstraction_app(encoder, other_ty, other);
+            (vec![], Some(vir::Expr::eq_cmp(bytes, other_bytes)))
+        }
+
+        _ => unreachable!("{:?} is not a pure model", model),
+    };
+
+    let function = vir::Function {
+        name: encode_std_model_function_name(encoder, model, arg_tys),
+        formal_args,
+        return_type,
+        pres,
+        posts,
+        body,
+    };
+    encoder.log_vir_program_before_foldunfold(function.to_string());
+
+    foldunfold::add_folding_unfolding_to_function(
+        function,
+        encoder.get_used_viper_predicates_map(),
+    )
+}
+
+/// Encode an application of the function of a pure model to `receiver`, of type `receiver_ty`.
+pub fn encode_std_model_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    receiver_ty: ty::Ty<'tcx>,
+    receiver: vir::Expr,
+) -> vir::Expr {
+    let formal_arg = vir::LocalVar::new("self", receiver.get_type().clone());
+    vir::Expr::func_app(
+        encoder.encode_std_model_function_use(model, &[receiver_ty]),
+        vec![receiver],
+        vec![formal_arg],
+        encode_std_model_return_type(model),
+        vir::Position::default(),
+    )
+}
+
+/// Is `ty` a map modelled by a mathematical map?
+fn is_map<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => {
+            is_map_type(&encoder.env().tcx().absolute_item_path_str(adt_def.did))
+        }
+        _ => false,
+    }
+}
+
+/// The types of the keys and of the values of a map of type `map_ty`.
+fn map_key_value_tys<'tcx>(map_ty: ty::Ty<'tcx>) -> (ty::Ty<'tcx>, ty::Ty<'tcx>) {
+    match map_ty.sty {
+        ty::TypeVariants::TyAdt(_, substs) => (substs.type_at(0), substs.type_at(1)),
+        ref x => unreachable!("{:?} is not a map", x),
+    }
+}
+
+/// The type of the mathematical map that abstracts a map of type `map_ty`.
+fn encode_map_type<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    map_ty: ty::Ty<'tcx>,
+) -> vir::Type {
+    let (key_ty, value_ty) = map_key_value_tys(map_ty);
+    let encode_primitive_type = |ty: ty::Ty<'tcx>| match encoder.encode_value_type(ty) {
+        vir::Type::TypedRef(..) | vir::Type::TypedMap(..) => {
+            unimplemented!("maps with keys or values of type {:?} are not supported", ty)
+        }
+        typ => typ,
+    };
+    vir::Type::TypedMap(
+        box encode_primitive_type(key_ty),
+        box encode_primitive_type(value_ty),
+    )
+}
+
+/// The name of the function that abstracts a map of type `map_ty` to a mathematical map.
+pub fn encode_map_abstraction_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    map_ty: ty::Ty<'tcx>,
+) -> String {
+    format!("std_model$map${}", encoder.encode_type_predicate_use(map_ty))
+}
+
+/// Encode the function that abstracts a map of type `map_ty` to a mathematical map. The
+/// function has no body: the contracts of the modelled methods relate the abstractions of the
+/// map before and after a call.
+pub fn encode_map_abstraction<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    map_ty: ty::Ty<'tcx>,
+) -> vir::Function {
+    let receiver = vir::LocalVar::new("self", encoder.encode_type(map_ty));
+    let precondition = vir::Expr::predicate_access_predicate(
+        receiver.typ.name(),
+        receiver.clone().into(),
+        vir::PermAmount::Read,
+    );
+    vir::Function {
+        name: encode_map_abstraction_name(encoder, map_ty),
+        formal_args: vec![receiver],
+        return_type: encode_map_type(encoder, map_ty),
+        pres: vec![precondition],
+        posts: vec![],
+        body: None,
+    }
+}
+
+/// Encode the mathematical map that abstracts `map`, of type `map_ty`.
+fn encode_map_abstraction_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    map_ty: ty::Ty<'tcx>,
+    map: vir::Expr,
+) -> vir::Expr {
+    let formal_arg = vir::LocalVar::new("self", map.get_type().clone());
+    vir::Expr::func_app(
+        encoder.encode_map_abstraction_use(map_ty),
+        vec![map],
+        vec![formal_arg],
+        encode_map_type(encoder, map_ty),
+        vir::Position::default(),
+    )
+}
+
+/// Is `ty` a string (a `str` or a modelled owned string) abstracted by the sequence of its
+/// bytes?
+fn is_string<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyStr => true,
+        ty::TypeVariants::TyAdt(adt_def, _) => {
+            is_string_type(&encoder.env().tcx().absolute_item_path_str(adt_def.did))
+        }
+        _ => false,
+    }
+}
+
+/// The type of the sequences of bytes that abstract the strings.
+fn encode_str_abstraction_type() -> vir::Type {
+    vir::Type::TypedSeq(box vir::Type::Int)
+}
+
+/// The name of the function that abstracts a string of type `string_ty` to the sequence of its
+/// bytes.
+pub fn encode_str_abstraction_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    string_ty: ty::Ty<'tcx>,
+) -> String {
+    format!("std_model$str${}", encoder.encode_type_predicate_use(string_ty))
+}
+
+/// Encode the function that abstracts a string of type `string_ty` to the sequence of its bytes.
+/// A `str` cannot be modified, thus the function does not require a permission on it, which
+/// allows the string literals to be abstracted too.
+pub fn encode_str_abstraction<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    string_ty: ty::Ty<'tcx>,
+) -> vir::Function {
+    let receiver = vir::LocalVar::new("self", encoder.encode_type(string_ty));
+    let pres = match string_ty.sty {
+        ty::TypeVariants::TyStr => vec![],
+        _ => vec![vir::Expr::predicate_access_predicate(
+            receiver.typ.name(),
+            receiver.clone().into(),
+            vir::PermAmount::Read,
+        )],
+    };
+    vir::Function {
+        name: encode_str_abstraction_name(encoder, string_ty),
+        formal_args: vec![receiver],
+        return_type: encode_str_abstraction_type(),
+        pres,
+        posts: vec![],
+        body: None,
+    }
+}
+
+/// Encode the sequence of bytes that abstracts `string`, of type `string_ty`.
+fn encode_str_abstraction_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    string_ty: ty::Ty<'tcx>,
+    string: vir::Expr,
+) -> vir::Expr {
+    let formal_arg = vir::LocalVar::new("self", string.get_type().clone());
+    vir::Expr::func_app(
+        encoder.encode_str_abstraction_use(string_ty),
+        vec![string],
+        vec![formal_arg],
+        encode_str_abstraction_type(),
+        vir::Position::default(),
+    )
+}
+
+/// The name of the function that encodes the string literals.
+pub const STR_LITERAL_FUNCTION_NAME: &str = "std_model$str_literal";
+
+/// Encode the function that returns a `str` with the given bytes, which encodes the string
+/// literals. It has no body, but its postcondition defines the abstraction of its result.
+pub fn encode_str_literal_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+) -> vir::Function {
+    let str_ty = encoder.env().tcx().mk_str();
+    let bytes = vir::LocalVar::new("bytes", encode_str_abstraction_type());
+    let return_type = encoder.encode_type(str_ty);
+    let result = vir::LocalVar::new("__result", return_type.clone());
+    let postcondition = vir::Expr::eq_cmp(
+        encode_str_abstraction_app(encoder, str_ty, result.into()),
+        bytes.clone().into(),
+    );
+    vir::Function {
+        name: STR_LITERAL_FUNCTION_NAME.to_string(),
+        formal_args: vec![bytes],
+        return_type,
+        pres: vec![],
+        posts: vec![postcondition],
+        body: None,
+    }
+}
+
+/// Is `ty` a vector, a slice or an iterator over a slice of integers or characters, abstracted
+/// by the sequence of its elements?
+fn is_sequence<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    let elem_ty = match ty.sty {
+        ty::TypeVariants::TySlice(elem_ty) => elem_ty,
+        ty::TypeVariants::TyAdt(adt_def, substs)
+            if is_vec_type(&encoder.env().tcx().absolute_item_path_str(adt_def.did))
+                || is_iterator(encoder, ty) =>
+        {
+            // The lifetime of an iterator comes before the type of its elements.
+            substs.types().next().unwrap()
+        }
+        _ => return false,
+    };
+    match elem_ty.sty {
+        ty::TypeVariants::TyInt(_) | ty::TypeVariants::TyUint(_) | ty::TypeVariants::TyChar => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Is `ty` an iterator over the elements of a slice, abstracted like the slice and by a ghost
+/// index?
+fn is_iterator<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => {
+            is_iterator_type(&encoder.env().tcx().absolute_item_path_str(adt_def.did))
+        }
+        _ => false,
+    }
+}
+
+/// The type of the sequences of elements that abstract the vectors and the slices.
+fn encode_seq_abstraction_type() -> vir::Type {
+    vir::Type::TypedSeq(box vir::Type::Int)
+}
+
+/// The name of the function that abstracts a vector or a slice of type `seq_ty` to the sequence
+/// of its elements.
+pub fn encode_seq_abstraction_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    seq_ty: ty::Ty<'tcx>,
+) -> String {
+    format!("std_model$seq${}", encoder.encode_type_predicate_use(seq_ty))
+}
+
+/// Encode the function that abstracts a vector or a slice of type `seq_ty` to the sequence of
+/// its elements. Like the abstraction of a map, it has no body.
+pub fn encode_seq_abstraction<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    seq_ty: ty::Ty<'tcx>,
+) -> vir::Function {
+    let receiver = vir::LocalVar::new("self", encoder.encode_type(seq_ty));
+    let precondition = vir::Expr::predicate_access_predicate(
+        receiver.typ.name(),
+        receiver.clone().into(),
+        vir::PermAmount::Read,
+    );
+    vir::Function {
+        name: encode_seq_abstraction_name(encoder, seq_ty),
+        formal_args: vec![receiver],
+        return_type: encode_seq_abstraction_type(),
+        pres: vec![precondition],
+        posts: vec![],
+        body: None,
+    }
+}
+
+/// Encode the sequence of elements that abstracts `seq`, of type `seq_ty`.
+fn encode_seq_abstraction_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    seq_ty: ty::Ty<'tcx>,
+    seq: vir::Expr,
+) -> vir::Expr {
+    let formal_arg = vir::LocalVar::new("self", seq.get_type().clone());
+    vir::Expr::func_app(
+        encoder.encode_seq_abstraction_use(seq_ty),
+        vec![seq],
+        vec![formal_arg],
+        encode_seq_abstraction_type(),
+        vir::Position::default(),
+    )
+}
+
+/// The content of a vector or a slice `collection`, of type `ty`, that is known: the sequence of
+/// its elements, if it is abstracted by one, and its length otherwise.
+fn encode_content<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+    collection: vir::Expr,
+) -> vir::Expr {
+    if is_sequence(encoder, ty) {
+        encode_seq_abstraction_app(encoder, ty, collection)
+    } else {
+        encode_std_model_app(encoder, StdModel::Len, ty, collection)
+    }
+}
+
+/// The name of the function that abstracts an iterator of type `iter_ty` to its ghost index,
+/// the index of the next element that it yields.
+pub fn encode_iter_index_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    iter_ty: ty::Ty<'tcx>,
+) -> String {
+    format!("std_model$index${}", encoder.encode_type_predicate_use(iter_ty))
+}
+
+/// Encode the function that abstracts an iterator of type `iter_ty` to its ghost index. Like the
+/// abstraction of a vector, it has no body.
+pub fn encode_iter_index<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    iter_ty: ty::Ty<'tcx>,
+) -> vir::Function {
+    let receiver = vir::LocalVar::new("self", encoder.encode_type(iter_ty));
+    let precondition = vir::Expr::predicate_access_predicate(
+        receiver.typ.name(),
+        receiver.clone().into(),
+        vir::PermAmount::Read,
+    );
+    let result = vir::LocalVar::new("__result", vir::Type::Int);
+    vir::Function {
+        name: encode_iter_index_name(encoder, iter_ty),
+        formal_args: vec![receiver],
+        return_type: vir::Type::Int,
+        pres: vec![precondition],
+        posts: vec![vir::Expr::ge_cmp(result.into(), 0.into())],
+        body: None,
+    }
+}
+
+/// Encode the ghost index of `iter`, of type `iter_ty`.
+fn encode_iter_index_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    iter_ty: ty::Ty<'tcx>,
+    iter: vir::Expr,
+) -> vir::Expr {
+    let formal_arg = vir::LocalVar::new("self", iter.get_type().clone());
+    vir::Expr::func_app(
+        encoder.encode_iter_index_use(iter_ty),
+        vec![iter],
+        vec![formal_arg],
+        vir::Type::Int,
+        vir::Position::default(),
+    )
+}
+
+/// The content of the `Some` variant of `option`, of type `option_ty`, with its type. For a
+/// reference, this is the referent.
+fn encode_some_content<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    option: &vir::Expr,
+    option_ty: ty::Ty<'tcx>,
+) -> (vir::Expr, ty::Ty<'tcx>) {
+    let content_ty = match option_ty.sty {
+        ty::TypeVariants::TyAdt(_, substs) => substs.type_at(0),
+        ref x => unreachable!("{:?} is not an `Option`", x),
+    };
+    let content = option
+        .clone()
+        .variant("Some")
+        .field(encoder.encode_struct_field("0", content_ty));
+    dereference(encoder, &content, content_ty)
+}
+
+/// The name of the function that encodes the closure `closure_def_id` as a function of the
+/// value of its argument.
+pub fn encode_closure_function_name<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    closure_def_id: DefId,
+) -> String {
+    format!("std_model$closure${}", encoder.encode_item_name(closure_def_id))
+}
+
+/// Encode the closure `closure_def_id`, accepted by `is_pure_closure`, as a function of the value
+/// of its argument. `None` if the encoded body uses the argument otherwise than by reading the
+/// value, e.g. by passing the reference to a pure function.
+pub fn encode_closure_function<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    closure_def_id: DefId,
+) -> Option<vir::Function> {
+    let procedure = encoder.env().get_procedure(closure_def_id);
+    let mir = procedure.get_mir();
+    // The namespace of the locals of the encoded body.
+    let mir_encoder =
+        MirEncoder::new_with_namespace(encoder, mir, closure_def_id, "_pure".to_string());
+    // The first argument is the environment of the closure, which captures no variables.
+    let arg_local = mir.args_iter().nth(1).unwrap();
+    let arg: vir::Expr = mir_encoder.encode_local(arg_local).into();
+    let (arg_value, value_ty) =
+        dereference_all(encoder, arg.clone(), mir_encoder.get_local_ty(arg_local));
+    let value = vir::LocalVar::new("value", vir::Type::Int);
+    let body = encoder
+        .encode_pure_function_body(closure_def_id, false)
+        .replace_place(
+            &arg_value.field(encoder.encode_value_field(value_ty)),
+            &value.clone().into(),
+        );
+    if body.find(&arg) {
+        debug!("The closure {:?} uses its argument by reference", closure_def_id);
+        return None;
+    }
+    Some(vir::Function {
+        name: encode_closure_function_name(encoder, closure_def_id),
+        formal_args: vec![value],
+        return_type: vir::Type::Bool,
+        pres: vec![],
+        posts: vec![],
+        body: Some(body),
+    })
+}
+
+/// Apply the function `function_name` of a closure to `value`.
+fn encode_closure_function_app(function_name: &str, value: vir::Expr) -> vir::Expr {
+    vir::Expr::func_app(
+        function_name.to_string(),
+        vec![value],
+        vec![vir::LocalVar::new("value", vir::Type::Int)],
+        vir::Type::Bool,
+        vir::Position::default(),
+    )
+}
+
+/// Encode the postcondition of the search `model` (e.g. `StdModel::Any`) among the unvisited
+/// elements of `iter`, an iterator of type `iter_ty` over a sequence, of an element that
+/// satisfies the closure encoded by `closure_function`. The result of the search is `target`, of
+/// type `target_ty`. The search visits the elements up to the first one that decides the result.
+fn encode_search_postcondition<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    closure_function: &str,
+    iter: &(vir::Expr, ty::Ty<'tcx>),
+    target: &(vir::Expr, ty::Ty<'tcx>),
+    pre_label: &str,
+) -> vir::Expr {
+    let (ref iter, iter_ty) = *iter;
+    let (ref target, target_ty) = *target;
+    // The content is not modified, thus the quantifiers do not need to refer to the old state.
+    let content = encode_content(encoder, iter_ty, iter.clone());
+    let index = encode_iter_index_app(encoder, iter_ty, iter.clone());
+    let old_index = vir::Expr::labelled_old(pre_label, index.clone());
+    let len = vir::Expr::seq_len(content.clone());
+    let position = vir::LocalVar::new("search$position", vir::Type::Int);
+    let position_expr: vir::Expr = position.clone().into();
+    let is_unvisited = |position: vir::Expr, end: vir::Expr| {
+        vir::Expr::and(
+            vir::Expr::le_cmp(old_index.clone(), position.clone()),
+            vir::Expr::lt_cmp(position, end),
+        )
+    };
+    let satisfies = |position: vir::Expr| {
+        encode_closure_function_app(
+            closure_function,
+            vir::Expr::seq_index(content.clone(), position),
+        )
+    };
+    // The triggers are inferred by Viper.
+    let found = vir::Expr::exists(
+        vec![position.clone()],
+        vec![],
+        vir::Expr::and(
+            is_unvisited(position_expr.clone(), len.clone()),
+            satisfies(position_expr.clone()),
+        ),
+    );
+    let none_before = |end: vir::Expr| {
+        vir::Expr::forall(
+            vec![position.clone()],
+            vec![],
+            vir::Expr::implies(
+                is_unvisited(position_expr.clone(), end),
+                vir::Expr::not(satisfies(position_expr.clone())),
+            ),
+        )
+    };
+    let mut postcondition = vec![
+        vir::Expr::eq_cmp(
+            content.clone(),
+            vir::Expr::labelled_old(pre_label, content.clone()),
+        ),
+        vir::Expr::le_cmp(old_index.clone(), index.clone()),
+        vir::Expr::le_cmp(index.clone(), len.clone()),
+    ];
+    let is_some = || {
+        encode_std_model_app(
+            encoder,
+            StdModel::IsVariant("Some"),
+            target_ty,
+            target.clone(),
+        )
+    };
+    match model {
+        StdModel::Any | StdModel::All => {
+            let result = target.clone().field(encoder.encode_value_field(target_ty));
+            let expected = if model == StdModel::Any {
+                found
+            } else {
+                vir::Expr::forall(
+                    vec![position.clone()],
+                    vec![],
+                    vir::Expr::implies(
+                        is_unvisited(position_expr.clone(), len.clone()),
+                        satisfies(position_expr.clone()),
+                    ),
+                )
+            };
+            postcondition.push(vir::Expr::eq_cmp(result, expected));
+        }
+
+        StdModel::Position => {
+            let (offset, offset_ty) = encode_some_content(encoder, target, target_ty);
+            let first = vir::Expr::add(
+                old_index.clone(),
+                offset.field(encoder.encode_value_field(offset_ty)),
+            );
+            postcondition.push(vir::Expr::eq_cmp(is_some(), found));
+            postcondition.push(vir::Expr::implies(
+                is_some(),
+                vec![
+                    satisfies(first.clone()),
+                    none_before(first.clone()),
+                    vir::Expr::eq_cmp(index.clone(), vir::Expr::add(first, 1.into())),
+                ]
+                .into_iter()
+                .conjoin(),
+            ));
+            postcondition.push(vir::Expr::implies(
+                vir::Expr::not(is_some()),
+                vir::Expr::eq_cmp(index, len),
+            ));
+        }
+
+        StdModel::Find => {
+            let (element, element_ty) = encode_some_content(encoder, target, target_ty);
+            let (element, element_ty) = dereference_all(encoder, element, element_ty);
+            let element_value = element.field(encoder.encode_value_field(element_ty));
+            postcondition.push(vir::Expr::eq_cmp(is_some(), found));
+            postcondition.push(vir::Expr::implies(
+                is_some(),
+                vir::Expr::exists(
+                    vec![position.clone()],
+                    vec![],
+                    vec![
+                        is_unvisited(position_expr.clone(), len.clone()),
+                        vir::Expr::eq_cmp(
+                            element_value,
+                            vir::Expr::seq_index(content.clone(), position_expr.clone()),
+                        ),
+                        satisfies(position_expr.clone()),
+                        none_before(position_expr.clone()),
+                        vir::Expr::eq_cmp(
+                            index.clone(),
+                            vir::Expr::add(position_expr.clone(), 1.into()),
+                        ),
+                    ]
+                    .into_iter()
+                    .conjoin(),
+                ),
+            ));
+            postcondition.push(vir::Expr::implies(
+                vir::Expr::not(is_some()),
+                vir::Expr::eq_cmp(index, len),
+            ));
+        }
+
+        _ => unreachable!("{:?} is not a search", model),
+    }
+    postcondition.into_iter().conjoin()
+}
+
+/// The name of the domain that describes the permutations of the sequences of elements.
+pub const PERMUTATION_DOMAIN_NAME: &str = "SeqPermutation";
+
+/// The function `permutation(left, right)` that holds if `left` is a permutation of `right`.
+fn permutation_function() -> vir::DomainFunc {
+    vir::DomainFunc {
+        name: format!("{}$permutation", PERMUTATION_DOMAIN_NAME),
+        formal_args: vec![
+            vir::LocalVar::new("left", encode_seq_abstraction_type()),
+            vir::LocalVar::new("right", encode_seq_abstraction_type()),
+        ],
+        return_type: vir::Type::Bool,
+        unique: false,
+        domain_name: PERMUTATION_DOMAIN_NAME.to_string(),
+    }
+}
+
+fn permutation_app(left: vir::Expr, right: vir::Expr) -> vir::Expr {
+    vir::Expr::domain_func_app(permutation_function(), vec![left, right])
+}
+
+/// Encode the domain of the permutations. A permutation is an equivalence relation that
+/// preserves the length and the elements of the sequences, i.e. the multiset of the elements.
+pub fn encode_permutation_domain() -> vir::Domain {
+    let mut domain = vir::Domain::new(PERMUTATION_DOMAIN_NAME);
+    domain.functions.push(permutation_function());
+    let seq_var = |name: &str| vir::LocalVar::new(name, encode_seq_abstraction_type());
+    let (left, middle, right) = (seq_var("left"), seq_var("middle"), seq_var("right"));
+    let index = vir::LocalVar::new("index", vir::Type::Int);
+    let other_index = vir::LocalVar::new("other_index", vir::Type::Int);
+    let left_expr: vir::Expr = left.clone().into();
+    let middle_expr: vir::Expr = middle.clone().into();
+    let right_expr: vir::Expr = right.clone().into();
+    let index_expr: vir::Expr = index.clone().into();
+    let other_index_expr: vir::Expr = other_index.clone().into();
+
+    let reflexive = permutation_app(left_expr.clone(), left_expr.clone());
+    domain.axioms.push(permutation_axiom(
+        "reflexive",
+        vir::Expr::forall(
+            vec![left.clone()],
+            vec![vir::Trigger::new(vec![reflexive.clone()])],
+            reflexive,
+        ),
+    ));
+
+    let left_right = permutation_app(left_expr.clone(), right_expr.clone());
+    domain.axioms.push(permutation_axiom(
+        "symmetric",
+        vir::Expr::forall(
+            vec![left.clone(), right.clone()],
+            vec![vir::Trigger::new(vec![left_right.clone()])],
+            vir::Expr::implies(
+                left_right.clone(),
+                permutation_app(right_expr.clone(), left_expr.clone()),
+            ),
+        ),
+    ));
+
+    let left_middle = permutation_app(left_expr.clone(), middle_expr.clone());
+    let middle_right = permutation_app(middle_expr, right_expr.clone());
+    domain.axioms.push(permutation_axiom(
+        "transitive",
+        vir::Expr::forall(
+            vec![left.clone(), middle, right.clone()],
+            vec![vir::Trigger::new(vec![left_middle.clone(), middle_right.clone()])],
+            vir::Expr::implies(
+                vir::Expr::and(left_middle, middle_right),
+                left_right.clone(),
+            ),
+        ),
+    ));
+
+    domain.axioms.push(permutation_axiom(
+        "length",
+        vir::Expr::forall(
+            vec![left.clone(), right.clone()],
+            vec![vir::Trigger::new(vec![left_right.clone()])],
+            vir::Expr::implies(
+                left_right.clone(),
+                vir::Expr::eq_cmp(
+                    vir::Expr::seq_len(left_expr.clone()),
+                    vir::Expr::seq_len(right_expr.clone()),
+                ),
+            ),
+        ),
+    ));
+
+    let left_element = vir::Expr::seq_index(left_expr.clone(), index_expr.clone());
+    let right_element = vir::Expr::seq_index(right_expr.clone(), other_index_expr.clone());
+    domain.axioms.push(permutation_axiom(
+        "elements",
+        vir::Expr::forall(
+            vec![left, right, index],
+            vec![vir::Trigger::new(vec![left_right.clone(), left_element.clone()])],
+            vir::Expr::implies(
+                vir::Expr::and(left_right, encode_in_bounds(index_expr, left_expr)),
+                vir::Expr::exists(
+                    vec![other_index],
+                    vec![vir::Trigger::new(vec![right_element.clone()])],
+                    vir::Expr::and(
+                        encode_in_bounds(other_index_expr, right_expr),
+                        vir::Expr::eq_cmp(right_element, left_element),
+                    ),
+                ),
+            ),
+        ),
+    ));
+    domain
+}
+
+fn permutation_axiom(name: &str, expr: vir::Expr) -> vir::DomainAxiom {
+    vir::DomainAxiom {
+        name: format!("{}${}", PERMUTATION_DOMAIN_NAME, name),
+        expr,
+        domain_name: PERMUTATION_DOMAIN_NAME.to_string(),
+    }
+}
+
+/// Encode that `left` is a permutation of `right`.
+fn encode_permutation<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    left: vir::Expr,
+    right: vir::Expr,
+) -> vir::Expr {
+    encoder.encode_permutation_domain_use();
+    permutation_app(left, right)
+}
+
+/// Encode that `index` is an index of the sequence `seq`.
+fn encode_in_bounds(index: vir::Expr, seq: vir::Expr) -> vir::Expr {
+    vir::Expr::and(
+        vir::Expr::le_cmp(0.into(), index.clone()),
+        vir::Expr::lt_cmp(index, vir::Expr::seq_len(seq)),
+    )
+}
+
+/// Encode that the elements of the sequence `seq` are in ascending order. The triggers of the
+/// quantifiers of the contracts are inferred by Viper, since the optimiser may bind the old
+/// subexpressions of their bodies to `let` variables.
+fn encode_sorted(seq: vir::Expr) -> vir::Expr {
+    let index = vir::LocalVar::new("std_model$index", vir::Type::Int);
+    let other_index = vir::LocalVar::new("std_model$other_index", vir::Type::Int);
+    let index_expr: vir::Expr = index.clone().into();
+    let other_index_expr: vir::Expr = other_index.clone().into();
+    vir::Expr::forall(
+        vec![index, other_index],
+        vec![],
+        vir::Expr::implies(
+            vir::Expr::and(
+                vir::Expr::le_cmp(0.into(), index_expr.clone()),
+                vir::Expr::and(
+                    vir::Expr::lt_cmp(index_expr.clone(), other_index_expr.clone()),
+                    vir::Expr::lt_cmp(other_index_expr.clone(), vir::Expr::seq_len(seq.clone())),
+                ),
+            ),
+            vir::Expr::le_cmp(
+                vir::Expr::seq_index(seq.clone(), index_expr),
+                vir::Expr::seq_index(seq, other_index_expr),
+            ),
+        ),
+    )
+}
+
+/// The place behind an argument of type `ty`, if the argument is a reference, or the argument
+/// itself, with the type of the place.
+fn dereference<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    arg: &vir::Expr,
+    ty: ty::Ty<'tcx>,
+) -> (vir::Expr, ty::Ty<'tcx>) {
+    match ty.sty {
+        ty::TypeVariants::TyRef(_, inner_ty, _) => {
+            (arg.clone().field(encoder.encode_value_field(ty)), inner_ty)
+        }
+        _ => (arg.clone(), ty),
+    }
+}
+
+/// The value behind all the references of an argument of type `ty`, with its type.
+fn dereference_all<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    mut arg: vir::Expr,
+    mut ty: ty::Ty<'tcx>,
+) -> (vir::Expr, ty::Ty<'tcx>) {
+    while let ty::TypeVariants::TyRef(_, inner_ty, _) = ty.sty {
+        arg = arg.field(encoder.encode_value_field(ty));
+        ty = inner_ty;
+    }
+    (arg, ty)
+}
+
+/// The shared references that are copied by a clone of a value of type `ty`: the value itself,
+/// if it is a shared reference, or the shared reference fields of a struct with a derived
+/// `Clone`. Each reference is given by the fields that lead from the value to the address of
+/// its referent.
+fn copied_shared_references<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> Vec<Vec<vir::Field>> {
+    match ty.sty {
+        ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) => {
+            vec![vec![encoder.encode_value_field(ty)]]
+        }
+        ty::TypeVariants::TyAdt(adt_def, subst)
+            if adt_def.is_struct()
+                && !encoder.encode_type_predicate_def(ty).is_abstract()
+                && encoder.has_derived_clone(ty) =>
+        {
+            adt_def.variants[0]
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    let field_ty = encoder.resolve_field_ty(field, subst);
+                    match field_ty.sty {
+                        ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) => Some(vec![
+                            encoder.encode_struct_field(&field.ident.as_str(), field_ty),
+                            encoder.encode_value_field(field_ty),
+                        ]),
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Encode the precondition and the postcondition of a call of an impure model. The arguments
+/// and the target of the call are places, given with their types; `pre_label` labels the state
+/// before the call.
+pub fn encode_std_model_contract<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    args: &[(vir::Expr, ty::Ty<'tcx>)],
+    target: &(vir::Expr, ty::Ty<'tcx>),
+    pre_label: &str,
+) -> (vir::Expr, vir::Expr) {
+    let (ref target, target_ty) = *target;
+    let receiver = args
+        .first()
+        .map(|&(ref arg, ty)| dereference(encoder, arg, ty));
+    let len = |when_old: bool| {
+        let (receiver, receiver_ty) = receiver.clone().unwrap();
+        let len = encode_std_model_app(encoder, StdModel::Len, receiver_ty, receiver);
+        if when_old {
+            vir::Expr::labelled_old(pre_label, len)
+        } else {
+            len
+        }
+    };
+
+    match model {
+        StdModel::New => {
+            let len = encode_std_model_app(encoder, StdModel::Len, target_ty, target.clone());
+            (true.into(), vir::Expr::eq_cmp(len, 0.into()))
+        }
+
+        StdModel::Push => (
+            true.into(),
+            vir::Expr::eq_cmp(len(false), vir::Expr::add(len(true), 1.into())),
+        ),
+
+        StdModel::Pop => {
+            let is_some = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant("Some"),
+                target_ty,
+                target.clone(),
+            );
+            let was_empty = vir::Expr::eq_cmp(len(true), 0.into());
+            let postcondition = vir::Expr::and(
+                vir::Expr::implies(
+                    was_empty.clone(),
+                    vir::Expr::and(
+                        vir::Expr::not(is_some.clone()),
+                        vir::Expr::eq_cmp(len(false), 0.into()),
+                    ),
+                ),
+                vir::Expr::implies(
+                    vir::Expr::not(was_empty),
+                    vir::Expr::and(
+                        is_some,
+                        vir::Expr::eq_cmp(len(false), vir::Expr::sub(len(true), 1.into())),
+                    ),
+                ),
+            );
+            (true.into(), postcondition)
+        }
+
+        StdModel::Clear => (true.into(), vir::Expr::eq_cmp(len(false), 0.into())),
+
+        StdModel::Unwrap(variant_name) => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let precondition = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant(variant_name),
+                receiver_ty,
+                receiver,
+            );
+            (precondition, true.into())
+        }
+
+        // The content is cloned only if it is borrowed, which is not known at the call.
+        StdModel::ToMut(_) => (true.into(), true.into()),
+
+        StdModel::Insert => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let (key, value) = (&args[1], &args[2]);
+            let key_value = key.0.clone().field(encoder.encode_value_field(key.1));
+            let value_value = value.0.clone().field(encoder.encode_value_field(value.1));
+            let map = encode_map_abstraction_app(encoder, receiver_ty, receiver);
+            let old_map = vir::Expr::labelled_old(pre_label, map.clone());
+            let updated_map = vir::Expr::map_update(
+                old_map.clone(),
+                vir::Expr::labelled_old(pre_label, key_value.clone()),
+                vir::Expr::labelled_old(pre_label, value_value),
+            );
+            let is_some = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant("Some"),
+                target_ty,
+                target.clone(),
+            );
+            let contained_key = vir::Expr::map_contains(
+                old_map,
+                vir::Expr::labelled_old(pre_label, key_value),
+            );
+            let postcondition = vir::Expr::and(
+                vir::Expr::eq_cmp(map, updated_map),
+                vir::Expr::eq_cmp(is_some, contained_key),
+            );
+            (true.into(), postcondition)
+        }
+
+        StdModel::Index => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let (key, key_ty) = dereference(encoder, &args[1].0, args[1].1);
+            let key_value = key.field(encoder.encode_value_field(key_ty));
+            let (result, result_ty) = dereference(encoder, target, target_ty);
+            let result_value = result.field(encoder.encode_value_field(result_ty));
+            let map = encode_map_abstraction_app(encoder, receiver_ty, receiver);
+            let precondition = vir::Expr::map_contains(map.clone(), key_value.clone());
+            let postcondition = vir::Expr::eq_cmp(
+                result_value,
+                vir::Expr::labelled_old(pre_label, vir::Expr::map_lookup(map, key_value)),
+            );
+            (precondition, postcondition)
+        }
+
+        // The clone reads the same referents as the receiver through its shared references.
+        StdModel::Clone => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let postcondition = copied_shared_references(encoder, receiver_ty)
+                .into_iter()
+                .map(|fields| {
+                    let address = |base: &vir::Expr| {
+                        fields
+                            .iter()
+                            .fold(base.clone(), |place, field| place.field(field.clone()))
+                    };
+                    vir::Expr::eq_cmp(
+                        address(target),
+                        vir::Expr::labelled_old(pre_label, address(&receiver)),
+                    )
+                })
+                .conjoin();
+            (true.into(), postcondition)
+        }
+
+        StdModel::NewLock | StdModel::Lock | StdModel::GuardDeref => {
+            encode_sync_model_contract(encoder, model, args, &(target.clone(), target_ty))
+        }
+
+        // The vector is blocked or read by the slice after the call.
+        StdModel::AsSlice => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let (slice, slice_ty) = dereference(encoder, target, target_ty);
+            let postcondition = vir::Expr::eq_cmp(
+                encode_content(encoder, slice_ty, slice),
+                vir::Expr::labelled_old(pre_label, encode_content(encoder, receiver_ty, receiver)),
+            );
+            (true.into(), postcondition)
+        }
+
+        StdModel::Swap => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let index = |arg: &(vir::Expr, ty::Ty<'tcx>)| {
+                arg.0.clone().field(encoder.encode_value_field(arg.1))
+            };
+            let (first, second) = (index(&args[1]), index(&args[2]));
+            let precondition = vir::Expr::and(
+                vir::Expr::lt_cmp(first.clone(), len(false)),
+                vir::Expr::lt_cmp(second.clone(), len(false)),
+            );
+            let mut postcondition = vir::Expr::eq_cmp(len(false), len(true));
+            if is_sequence(encoder, receiver_ty) {
+                let elements = encode_seq_abstraction_app(encoder, receiver_ty, receiver);
+                let old_elements = vir::Expr::labelled_old(pre_label, elements.clone());
+                let first = vir::Expr::labelled_old(pre_label, first);
+                let second = vir::Expr::labelled_old(pre_label, second);
+                let other = vir::LocalVar::new("std_model$index", vir::Type::Int);
+                let other_expr: vir::Expr = other.clone().into();
+                let unchanged = vir::Expr::forall(
+                    vec![other],
+                    vec![],
+                    vir::Expr::implies(
+                        vir::Expr::and(
+                            encode_in_bounds(other_expr.clone(), elements.clone()),
+                            vir::Expr::and(
+                                vir::Expr::ne_cmp(other_expr.clone(), first.clone()),
+                                vir::Expr::ne_cmp(other_expr.clone(), second.clone()),
+                            ),
+                        ),
+                        vir::Expr::eq_cmp(
+                            vir::Expr::seq_index(elements.clone(), other_expr.clone()),
+                            vir::Expr::seq_index(old_elements.clone(), other_expr),
+                        ),
+                    ),
+                );
+                postcondition = vec![
+                    postcondition,
+                    vir::Expr::eq_cmp(
+                        vir::Expr::seq_index(elements.clone(), first.clone()),
+                        vir::Expr::seq_index(old_elements.clone(), second.clone()),
+                    ),
+                    vir::Expr::eq_cmp(
+                        vir::Expr::seq_index(elements.clone(), second),
+                        vir::Expr::seq_index(old_elements.clone(), first),
+                    ),
+                    unchanged,
+                    encode_permutation(encoder, elements, old_elements),
+                ]
+                .into_iter()
+                .conjoin();
+            }
+            (precondition, postcondition)
+        }
+
+        StdModel::Sort => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let mut postcondition = vir::Expr::eq_cmp(len(false), len(true));
+            if is_sequence(encoder, receiver_ty) {
+                let elements = encode_seq_abstraction_app(encoder, receiver_ty, receiver);
+                let old_elements = vir::Expr::labelled_old(pre_label, elements.clone());
+                postcondition = vec![
+                    postcondition,
+                    encode_sorted(elements.clone()),
+                    encode_permutation(encoder, elements, old_elements),
+                ]
+                .into_iter()
+                .conjoin();
+            }
+            (true.into(), postcondition)
+        }
+
+        // Only whether the value is found is known, not the returned index.
+        StdModel::BinarySearch => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            if !is_sequence(encoder, receiver_ty) {
+                return (true.into(), true.into());
+            }
+            let (value, value_ty) = dereference(encoder, &args[1].0, args[1].1);
+            let value = value.field(encoder.encode_value_field(value_ty));
+            let elements = encode_seq_abstraction_app(encoder, receiver_ty, receiver);
+            let precondition = encode_sorted(elements.clone());
+            let old_elements = vir::Expr::labelled_old(pre_label, elements);
+            let index = vir::LocalVar::new("std_model$index", vir::Type::Int);
+            let index_expr: vir::Expr = index.clone().into();
+            let is_found = vir::Expr::exists(
+                vec![index],
+                vec![],
+                vir::Expr::and(
+                    encode_in_bounds(index_expr.clone(), old_elements.clone()),
+                    vir::Expr::eq_cmp(
+                        vir::Expr::seq_index(old_elements, index_expr),
+                        vir::Expr::labelled_old(pre_label, value),
+                    ),
+                ),
+            );
+            let is_ok = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant("Ok"),
+                target_ty,
+                target.clone(),
+            );
+            (precondition, vir::Expr::eq_cmp(is_ok, is_found))
+        }
+
+        // A mutably borrowed slice is given back to the caller with unknown elements, see the
+        // module documentation.
+        StdModel::Iter | StdModel::IterMut => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let postcondition = vec![
+                vir::Expr::eq_cmp(
+                    encode_content(encoder, target_ty, target.clone()),
+                    vir::Expr::labelled_old(
+                        pre_label,
+                        encode_content(encoder, receiver_ty, receiver),
+                    ),
+                ),
+                vir::Expr::eq_cmp(
+                    encode_iter_index_app(encoder, target_ty, target.clone()),
+                    0.into(),
+                ),
+                vir::Expr::eq_cmp(len(false), len(true)),
+            ]
+            .into_iter()
+            .conjoin();
+            (true.into(), postcondition)
+        }
+
+        // Only the iterators over slices are known to be returned as they are.
+        StdModel::IntoIter => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            if !is_iterator(encoder, receiver_ty) {
+                return (true.into(), true.into());
+            }
+            let old = |expr| vir::Expr::labelled_old(pre_label, expr);
+            let postcondition = vir::Expr::and(
+                vir::Expr::eq_cmp(
+                    encode_content(encoder, target_ty, target.clone()),
+                    old(encode_content(encoder, receiver_ty, receiver.clone())),
+                ),
+                vir::Expr::eq_cmp(
+                    encode_iter_index_app(encoder, target_ty, target.clone()),
+                    old(encode_iter_index_app(encoder, receiver_ty, receiver)),
+                ),
+            );
+            (true.into(), postcondition)
+        }
+
+        // The element at the ghost index becomes the current element, and the index moves to
+        // the first unvisited element.
+        StdModel::Next => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let index = encode_iter_index_app(encoder, receiver_ty, receiver.clone());
+            let old_index = vir::Expr::labelled_old(pre_label, index.clone());
+            let content = encode_content(encoder, receiver_ty, receiver);
+            let old_content = vir::Expr::labelled_old(pre_label, content.clone());
+            let is_some = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant("Some"),
+                target_ty,
+                target.clone(),
+            );
+            let mut yielded = vec![
+                is_some.clone(),
+                vir::Expr::eq_cmp(index.clone(), vir::Expr::add(old_index.clone(), 1.into())),
+            ];
+            if is_sequence(encoder, receiver_ty) {
+                let (element, element_ty) = encode_some_content(encoder, target, target_ty);
+                yielded.push(vir::Expr::eq_cmp(
+                    element.field(encoder.encode_value_field(element_ty)),
+                    vir::Expr::seq_index(old_content.clone(), old_index.clone()),
+                ));
+            }
+            let has_next = vir::Expr::lt_cmp(old_index.clone(), len(true));
+            let postcondition = vec![
+                vir::Expr::eq_cmp(content, old_content),
+                vir::Expr::implies(has_next.clone(), yielded.into_iter().conjoin()),
+                vir::Expr::implies(
+                    vir::Expr::not(has_next),
+                    vir::Expr::and(
+                        vir::Expr::not(is_some),
+                        vir::Expr::eq_cmp(index, old_index),
+                    ),
+                ),
+            ]
+            .into_iter()
+            .conjoin();
+            (true.into(), postcondition)
+        }
+
+        // Without a supported closure, the result of the search is unknown.
+        StdModel::Any | StdModel::All | StdModel::Position | StdModel::Find => {
+            let (receiver, receiver_ty) = receiver.clone().unwrap();
+            let closure_function = match args[1].1.sty {
+                ty::TypeVariants::TyClosure(closure_def_id, _)
+                    if is_sequence(encoder, receiver_ty) =>
+                {
+                    encoder.encode_closure_function_use(closure_def_id)
+                }
+                _ => None,
+            };
+            match closure_function {
+                Some(closure_function) => (
+                    true.into(),
+                    encode_search_postcondition(
+                        encoder,
+                        model,
+                        &closure_function,
+                        &(receiver, receiver_ty),
+                        &(target.clone(), target_ty),
+                        pre_label,
+                    ),
+                ),
+                None => (true.into(), true.into()),
+            }
+        }
+
+        _ => unreachable!("{:?} is not an impure model", model),
+    }
+}
+
+/// Encode the pledge of a call of an impure model that returns a mutable reference, i.e. what
+/// holds for the receiver when the returned reference expires. The arguments and the result of
+/// the call are places, given with their types; `post_label` labels the state after the call.
+pub fn encode_std_model_pledge<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    args: &[(vir::Expr, ty::Ty<'tcx>)],
+    result: &(vir::Expr, ty::Ty<'tcx>),
+    post_label: &str,
+) -> Option<vir::Expr> {
+    match model {
+        // Both cases of `Cow::to_mut` result in an owned content: the borrowed content is
+        // cloned, while the owned content is returned as it is.
+        StdModel::ToMut(variant_name) => {
+            let (ref arg, ty) = args[0];
+            let (receiver, receiver_ty) = dereference(encoder, arg, ty);
+            Some(encode_std_model_app(
+                encoder,
+                StdModel::IsVariant(variant_name),
+                receiver_ty,
+                receiver,
+            ))
+        }
+
+        // The vector gets the final content of the slice.
+        StdModel::AsSlice => {
+            let (ref result, result_ty) = *result;
+            let (ref arg, ty) = args[0];
+            let (receiver, receiver_ty) = dereference(encoder, arg, ty);
+            let (slice, slice_ty) = dereference(encoder, result, result_ty);
+            Some(vir::Expr::eq_cmp(
+                encode_content(encoder, receiver_ty, receiver),
+                encode_content(encoder, slice_ty, vir::Expr::labelled_old(post_label, slice)),
+            ))
+        }
+
+        _ => None,
+    }
+}
@@ -0,0 +1,156 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Heuristics that suggest how to fix common verification failures.
+//!
+//! The suggestions are computed while encoding, because they need the MIR,
+//! and are attached to the position of the assertion that might fail. They
+//! are reported only if the verifier reports a failure at that position.
+
+use encoder::error_manager::Suggestion;
+use encoder::loop_encoder::LoopEncoder;
+use rustc::mir;
+use syntax::codemap::CodeMap;
+use syntax_pos::{BytePos, Span};
+
+/// Follow the chain of copies and moves of `local` in the basic block `bbi`,
+/// returning the local from which the value comes.
+fn resolve_copies(mir: &mir::Mir, bbi: mir::BasicBlock, local: mir::Local) -> mir::Local {
+    let mut local = local;
+    for stmt in mir[bbi].statements.iter().rev() {
+        if let mir::StatementKind::Assign(mir::Place::Local(lhs), ref rhs) = stmt.kind {
+            if lhs == local {
+                match rhs {
+                    mir::Rvalue::Use(mir::Operand::Copy(mir::Place::Local(source))) |
+                    mir::Rvalue::Use(mir::Operand::Move(mir::Place::Local(source))) => {
+                        local = *source;
+                    }
+                    _ => break,
+                }
+            }
+        }
+    }
+    local
+}
+
+fn operand_local(mir: &mir::Mir, bbi: mir::BasicBlock, operand: &mir::Operand) -> Option<mir::Local> {
+    match operand {
+        mir::Operand::Copy(mir::Place::Local(local)) |
+        mir::Operand::Move(mir::Place::Local(local)) => Some(resolve_copies(mir, bbi, *local)),
+        _ => None,
+    }
+}
+
+/// The span of the source expression of `operand`.
+fn operand_span(mir: &mir::Mir, operand: &mir::Operand) -> Option<Span> {
+    match operand {
+        mir::Operand::Copy(mir::Place::Local(local)) |
+        mir::Operand::Move(mir::Place::Local(local)) => {
+            Some(mir.local_decls[*local].source_info.span)
+        }
+        mir::Operand::Constant(box mir::Constant { span, .. }) => Some(*span),
+        _ => None,
+    }
+}
+
+/// Find the definition `local = rvalue` in the basic block `bbi`.
+fn find_definition<'a, 'tcx>(
+    mir: &'a mir::Mir<'tcx>,
+    bbi: mir::BasicBlock,
+    local: mir::Local,
+) -> Option<&'a mir::Rvalue<'tcx>> {
+    mir[bbi].statements.iter().rev().filter_map(|stmt| match stmt.kind {
+        mir::StatementKind::Assign(mir::Place::Local(lhs), ref rhs) if lhs == local => Some(rhs),
+        _ => None,
+    }).next()
+}
+
+/// The position where an attribute of the `while` loop whose guard is at
+/// `guard_span` can be inserted, with the indentation of the loop.
+fn loop_attribute_insertion_point(codemap: &CodeMap, guard_span: Span) -> Option<(Span, String)> {
+    let prev_source = codemap.span_to_prev_source(guard_span).ok()?;
+    let before_guard = prev_source.trim_right();
+    if !before_guard.ends_with("while") {
+        return None;
+    }
+    let while_start = before_guard.len() - "while".len();
+    let line_start = prev_source[..while_start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let indent = &prev_source[line_start..while_start];
+    if !indent.chars().all(|c| c == ' ' || c == '\t') {
+        // The loop does not start on its own line
+        return None;
+    }
+    let offset = (prev_source.len() - while_start) as u32;
+    let lo = BytePos(guard_span.lo().0 - offset);
+    Some((guard_span.with_lo(lo).with_hi(lo), indent.to_string()))
+}
+
+/// If the overflow check `cond` at `location` fails on a counter incremented
+/// in a `while counter < bound` loop, suggest the invariant `counter <= bound`.
+/// If the loop is guarded by another variable, `while other < bound`, suggest
+/// the invariant `counter <= other` instead.
+pub fn suggest_loop_counter_bound<'a, 'tcx>(
+    codemap: &CodeMap,
+    mir: &mir::Mir<'tcx>,
+    loop_encoder: &LoopEncoder<'a, 'tcx>,
+    location: mir::Location,
+    cond: &mir::Operand<'tcx>,
+) -> Option<Suggestion> {
+    let loop_head = loop_encoder.get_loop_head(location.block)?;
+
+    // The checked operation is `tmp = CheckedAdd(counter, _)` and the condition is `tmp.1`
+    let checked_local = match cond {
+        mir::Operand::Move(mir::Place::Projection(box mir::Projection {
+            base: mir::Place::Local(local),
+            elem: mir::ProjectionElem::Field(..),
+        })) => *local,
+        _ => return None,
+    };
+    let counter = match find_definition(mir, location.block, checked_local)? {
+        mir::Rvalue::CheckedBinaryOp(mir::BinOp::Add, ref left, _) => {
+            operand_local(mir, location.block, left)?
+        }
+        _ => return None,
+    };
+    let counter_name = mir.local_decls[counter].name?;
+
+    // The loop guard is `guard = Lt(guard_var, bound)`
+    let guard_term = mir[loop_head].terminator();
+    let guard_local = match guard_term.kind {
+        mir::TerminatorKind::SwitchInt { ref discr, .. } => operand_local(mir, loop_head, discr)?,
+        _ => return None,
+    };
+    let (guard_var, bound) = match find_definition(mir, loop_head, guard_local)? {
+        mir::Rvalue::BinaryOp(mir::BinOp::Lt, ref left, ref right) => {
+            (operand_local(mir, loop_head, left)?, right)
+        }
+        _ => return None,
+    };
+    let bound_snippet = if guard_var == counter {
+        // `while counter < bound`
+        codemap.span_to_snippet(operand_span(mir, bound)?).ok()?
+    } else {
+        // `while guard_var < bound`, with `counter` incremented alongside `guard_var`
+        mir.local_decls[guard_var].name?.to_string()
+    };
+    let invariant = format!("#[invariant=\"{} <= {}\"]", counter_name, bound_snippet);
+
+    Some(match loop_attribute_insertion_point(codemap, guard_term.source_info.span) {
+        Some((span, indent)) => Suggestion {
+            message: format!("consider bounding `{}` with a loop invariant", counter_name),
+            span,
+            code: Some(format!("{}\n{}", invariant, indent)),
+        },
+        None => Suggestion {
+            message: format!(
+                "consider bounding `{}` with a loop invariant, e.g. `{}`",
+                counter_name, invariant
+            ),
+            span: guard_term.source_info.span,
+            code: None,
+        },
+    })
+}
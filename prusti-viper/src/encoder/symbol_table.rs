@@ -0,0 +1,60 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The Rust items and types from which the Viper identifiers are generated, so that the dumped
+//! Viper programs can be read without decoding the identifiers by hand.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The Rust entity that a Viper identifier encodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Symbol {
+    /// An item, e.g. a function or a struct, with the kind of item and its path.
+    Item { kind: String, path: String },
+    /// A type, which is encoded as a predicate.
+    Type { ty: String },
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Symbol::Item { kind, path } => write!(f, "{} `{}`", kind, path),
+            Symbol::Type { ty } => write!(f, "type `{}`", ty),
+        }
+    }
+}
+
+/// The Rust entities encoded by the Viper identifiers, indexed by identifier.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: BTreeMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            symbols: BTreeMap::new(),
+        }
+    }
+
+    pub fn register<S: ToString>(&mut self, identifier: S, symbol: Symbol) {
+        self.symbols.insert(identifier.to_string(), symbol);
+    }
+
+    /// Returns a Viper comment that describes each registered identifier used in
+    /// `program_text`, to be put at the beginning of a dumped program.
+    pub fn to_legend(&self, program_text: &str) -> String {
+        let mut lines = vec!["// Rust items and types encoded by the identifiers:".to_string()];
+        for (identifier, symbol) in &self.symbols {
+            if program_text.contains(identifier.as_str()) {
+                lines.push(format!("//   {}: {}", identifier, symbol));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
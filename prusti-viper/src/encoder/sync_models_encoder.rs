@@ -0,0 +1,176 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Encoding of the models of the locks defined in `prusti_std_models`, `Mutex` and `RwLock`.
+//!
+//! The lock invariant of a struct or enum is declared by marking one of its pure methods
+//! `fn(&self) -> bool` with `#[lock_invariant]`. The locks and their guards are encoded as
+//! abstract predicates, thus the content of a lock is known only through its invariant: the
+//! invariant is required when the lock is created, and each dereference of a guard returns a
+//! content that satisfies it. The content can only be modified through the mutable references
+//! returned by the guards, thus the invariant is required again when each of them expires, and
+//! it holds when the guard is dropped and the lock is released. The locks whose content has no
+//! lock invariant are encoded in the same way, without the invariant.
+
+use encoder::std_models_encoder::encode_std_model_app;
+use encoder::vir;
+use encoder::Encoder;
+use prusti_interface::data::ProcedureDefId;
+use prusti_std_models::StdModel;
+use rustc::hir;
+use rustc::hir::def_id::DefId;
+use rustc::ty;
+
+/// The attribute that marks the lock invariant of a struct or enum.
+pub const LOCK_INVARIANT_ATTR: &str = "lock_invariant";
+
+/// The method of the struct or enum `adt_def_id` that is marked with `#[lock_invariant]`, if
+/// any. Report an error for each marked method that cannot be a lock invariant.
+pub fn find_lock_invariant<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    adt_def_id: DefId,
+) -> Option<ProcedureDefId> {
+    let tcx = encoder.env().tcx();
+    let marked_methods: Vec<ProcedureDefId> = tcx
+        .inherent_impls(adt_def_id)
+        .iter()
+        .flat_map(|&impl_def_id| tcx.associated_item_def_ids(impl_def_id).to_vec())
+        .filter(|&def_id| encoder.env().has_attribute_name(def_id, LOCK_INVARIANT_ATTR))
+        .collect();
+    let mut lock_invariant = None;
+    for def_id in marked_methods {
+        let span = encoder.env().get_item_span(def_id);
+        if !is_valid_lock_invariant(encoder, def_id) {
+            encoder.env().span_err(
+                span,
+                &format!(
+                    "[Prusti] the lock invariant `{}` must be a pure method that takes `&self` \
+                     and returns `bool`",
+                    encoder.env().get_item_name(def_id)
+                ),
+            );
+        } else if lock_invariant.is_some() {
+            encoder.env().span_err(
+                span,
+                &format!(
+                    "[Prusti] `{}` has more than one lock invariant",
+                    tcx.item_path_str(adt_def_id)
+                ),
+            );
+        } else {
+            lock_invariant = Some(def_id);
+        }
+    }
+    lock_invariant
+}
+
+/// Is the procedure a pure method that takes `&self` and returns `bool`?
+fn is_valid_lock_invariant<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    def_id: ProcedureDefId,
+) -> bool {
+    let tcx = encoder.env().tcx();
+    let item = tcx.associated_item(def_id);
+    if item.kind != ty::AssociatedKind::Method || !item.method_has_self_argument {
+        return false;
+    }
+    let fn_sig = tcx.fn_sig(def_id);
+    let inputs = fn_sig.skip_binder().inputs();
+    let takes_shared_self = inputs.len() == 1 && match inputs[0].sty {
+        ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) => true,
+        _ => false,
+    };
+    takes_shared_self && fn_sig.skip_binder().output().is_bool() && encoder.is_pure(def_id)
+}
+
+/// Encode the lock invariant of `content`, a place of type `protected_ty`. The invariant of a
+/// content without lock invariant is `true`.
+fn encode_lock_invariant_app<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    protected_ty: ty::Ty<'tcx>,
+    content: vir::Expr,
+) -> vir::Expr {
+    match encoder.lock_invariant(protected_ty) {
+        Some(def_id) => {
+            let formal_arg = vir::LocalVar::new("self", content.get_type().clone());
+            vir::Expr::func_app(
+                encoder.encode_pure_function_use(def_id),
+                vec![content],
+                vec![formal_arg],
+                encoder.encode_pure_function_return_type(def_id),
+                vir::Position::default(),
+            )
+        }
+        None => true.into(),
+    }
+}
+
+/// Encode the precondition and the postcondition of a call of a model of a lock. The arguments
+/// and the target of the call are places, given with their types.
+pub fn encode_sync_model_contract<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    args: &[(vir::Expr, ty::Ty<'tcx>)],
+    target: &(vir::Expr, ty::Ty<'tcx>),
+) -> (vir::Expr, vir::Expr) {
+    let (ref target, target_ty) = *target;
+    match model {
+        StdModel::NewLock => {
+            let (ref content, content_ty) = args[0];
+            let precondition = encode_lock_invariant_app(encoder, content_ty, content.clone());
+            (precondition, true.into())
+        }
+
+        // A lock is poisoned only if a thread panics while holding it, which Prusti rules out.
+        StdModel::Lock => {
+            let is_ok = encode_std_model_app(
+                encoder,
+                StdModel::IsVariant("Ok"),
+                target_ty,
+                target.clone(),
+            );
+            (true.into(), is_ok)
+        }
+
+        StdModel::GuardDeref => {
+            let content_ty = match target_ty.sty {
+                ty::TypeVariants::TyRef(_, content_ty, _) => content_ty,
+                ref x => unreachable!("{:?} is not a reference to the content of a lock", x),
+            };
+            let content = target.clone().field(encoder.encode_value_field(target_ty));
+            (true.into(), encode_lock_invariant_app(encoder, content_ty, content))
+        }
+
+        _ => unreachable!("{:?} is not a model of a lock", model),
+    }
+}
+
+/// Encode what must hold when the reference returned by a call of a model of a lock expires:
+/// the lock invariant of the content behind a mutable reference returned by a guard. The
+/// returned reference is given with its type, and `post_label` labels the state after the call.
+pub fn encode_sync_model_obligation<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    model: StdModel,
+    result: &(vir::Expr, ty::Ty<'tcx>),
+    post_label: &str,
+) -> Option<vir::Expr> {
+    let (ref result, result_ty) = *result;
+    match (model, &result_ty.sty) {
+        (
+            StdModel::GuardDeref,
+            &ty::TypeVariants::TyRef(_, content_ty, hir::Mutability::MutMutable),
+        ) => {
+            // The content is at the address returned by the call.
+            let content = result
+                .clone()
+                .field(encoder.encode_value_field(result_ty))
+                .old(post_label);
+            Some(encode_lock_invariant_app(encoder, content_ty, content))
+        }
+
+        _ => None,
+    }
+}
@@ -0,0 +1,148 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Collects the time spent in each phase of the verification of each procedure, so that
+//! users can find the procedures that make the verification slow.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use syntax::codemap::CodeMap;
+use syntax_pos::Span;
+
+/// A phase of the verification of a procedure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerificationPhase {
+    /// The encoding of the MIR of the procedure to VIR, without the fold-unfold algorithm.
+    Encoding,
+    /// The fold-unfold algorithm.
+    FoldUnfold,
+    /// The verification of the encoded method with Viper.
+    Viper,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProcedureTimes {
+    /// The Rust name of the procedure, if known.
+    item_name: Option<String>,
+    span: Option<Span>,
+    encoding: Duration,
+    fold_unfold: Duration,
+    viper: Duration,
+}
+
+impl ProcedureTimes {
+    fn total(&self) -> Duration {
+        self.encoding + self.fold_unfold + self.viper
+    }
+}
+
+/// The time spent to verify each procedure, indexed by the name of its Viper method.
+#[derive(Debug, Clone, Default)]
+pub struct TimingReport {
+    procedures: HashMap<String, ProcedureTimes>,
+}
+
+impl TimingReport {
+    pub fn new() -> Self {
+        TimingReport {
+            procedures: HashMap::new(),
+        }
+    }
+
+    /// Associate the Viper method `method_name` with the Rust procedure that it encodes.
+    pub fn register_procedure<S1: ToString, S2: ToString>(
+        &mut self,
+        method_name: S1,
+        item_name: S2,
+        span: Span,
+    ) {
+        let times = self
+            .procedures
+            .entry(method_name.to_string())
+            .or_insert_with(ProcedureTimes::default);
+        times.item_name = Some(item_name.to_string());
+        times.span = Some(span);
+    }
+
+    /// Associate the Viper method `obligation_method_name`, which checks the assertion at
+    /// `line` of the Viper method `method_name`, with the Rust procedure of the latter.
+    pub fn register_obligation<S1: ToString, S2: ToString>(
+        &mut self,
+        method_name: S1,
+        obligation_method_name: S2,
+        line: i32,
+    ) {
+        let (item_name, span) = match self.procedures.get(&method_name.to_string()) {
+            Some(times) => (times.item_name.clone(), times.span),
+            None => (None, None),
+        };
+        let times = self
+            .procedures
+            .entry(obligation_method_name.to_string())
+            .or_insert_with(ProcedureTimes::default);
+        times.item_name =
+            item_name.map(|item_name| format!("{} (assertion at line {})", item_name, line));
+        times.span = span;
+    }
+
+    /// Add `duration` to the time spent in `phase` for the Viper method `method_name`.
+    pub fn record<S: ToString>(
+        &mut self,
+        method_name: S,
+        phase: VerificationPhase,
+        duration: Duration,
+    ) {
+        let times = self
+            .procedures
+            .entry(method_name.to_string())
+            .or_insert_with(ProcedureTimes::default);
+        match phase {
+            VerificationPhase::Encoding => times.encoding += duration,
+            VerificationPhase::FoldUnfold => times.fold_unfold += duration,
+            VerificationPhase::Viper => times.viper += duration,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.procedures.len()
+    }
+
+    /// Returns a human-readable table, with the slowest procedures first.
+    pub fn to_report(&self, codemap: &CodeMap) -> String {
+        let mut rows: Vec<_> = self.procedures.iter().collect();
+        rows.sort_by(|(name_a, times_a), (name_b, times_b)| {
+            times_b
+                .total()
+                .cmp(&times_a.total())
+                .then_with(|| name_a.cmp(name_b))
+        });
+        let mut lines = vec![format!(
+            "{:>10} {:>10} {:>12} {:>10}  {}",
+            "total (s)", "encoding", "fold-unfold", "viper", "procedure"
+        )];
+        for (method_name, times) in rows {
+            let procedure = match (&times.item_name, times.span) {
+                (Some(item_name), Some(span)) => {
+                    format!("{} at {}", item_name, codemap.span_to_string(span))
+                }
+                _ => method_name.clone(),
+            };
+            lines.push(format!(
+                "{:>10.3} {:>10.3} {:>12.3} {:>10.3}  {}",
+                seconds(times.total()),
+                seconds(times.encoding),
+                seconds(times.fold_unfold),
+                seconds(times.viper),
+                procedure
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
@@ -0,0 +1,197 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Collects everything that the verification of a crate relies on without
+//! checking it, so that users can audit their trust boundary.
+
+use prusti_interface::config;
+use std::fmt;
+use syntax::codemap::CodeMap;
+use syntax_pos::Span;
+
+/// Why an item is part of the trusted surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrustedItemKind {
+    /// A `#[trusted]` procedure, whose body is not verified.
+    TrustedProcedure,
+    /// A `#[trusted_if_unsupported]` procedure that uses unsupported features, whose body is
+    /// not verified.
+    UnsupportedProcedure,
+    /// A `#[trusted]` pure function, whose postconditions are assumed.
+    TrustedPureFunction,
+    /// A `#[pure_spec]` function, whose postconditions are assumed as axioms.
+    SpecFunction,
+    /// A `#[pure]` function of a `#[domain]` struct, whose postconditions are axioms of the
+    /// domain.
+    DomainFunction,
+    /// A call to a procedure that is not verified, whose contract is assumed.
+    CallToTrustedProcedure,
+    /// A call to an external procedure without specification.
+    CallWithoutSpecification,
+    /// A subexpression of a specification marked with `unsafe_spec`, replaced by an
+    /// unknown value.
+    UnsafeSpecExpression,
+    /// An assertion assumed with `prusti_assume!` in the body of a procedure.
+    Assumption,
+    /// A configuration flag that disables some checks.
+    UncheckedConfiguration,
+}
+
+impl fmt::Display for TrustedItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrustedItemKind::TrustedProcedure => write!(f, "trusted procedure"),
+            TrustedItemKind::UnsupportedProcedure => write!(f, "unsupported procedure"),
+            TrustedItemKind::TrustedPureFunction => write!(f, "trusted pure function"),
+            TrustedItemKind::SpecFunction => write!(f, "specification function"),
+            TrustedItemKind::DomainFunction => write!(f, "domain function"),
+            TrustedItemKind::CallToTrustedProcedure => write!(f, "call to trusted procedure"),
+            TrustedItemKind::CallWithoutSpecification => {
+                write!(f, "call without specification")
+            }
+            TrustedItemKind::UnsafeSpecExpression => write!(f, "unsafe specification expression"),
+            TrustedItemKind::Assumption => write!(f, "assumption"),
+            TrustedItemKind::UncheckedConfiguration => write!(f, "configuration"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrustedItem {
+    pub kind: TrustedItemKind,
+    /// The name of the item, or of the configuration flag.
+    pub name: String,
+    pub span: Option<Span>,
+    pub reason: String,
+}
+
+/// The trusted surface of a crate.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedSurface {
+    items: Vec<TrustedItem>,
+}
+
+impl TrustedSurface {
+    pub fn new() -> Self {
+        TrustedSurface { items: vec![] }
+    }
+
+    pub fn register<S1: ToString, S2: ToString>(
+        &mut self,
+        kind: TrustedItemKind,
+        name: S1,
+        span: Option<Span>,
+        reason: S2,
+    ) {
+        let name = name.to_string();
+        let already_registered = self
+            .items
+            .iter()
+            .any(|item| item.kind == kind && item.name == name && item.span == span);
+        if !already_registered {
+            self.items.push(TrustedItem {
+                kind,
+                name,
+                span,
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    /// Register the configuration flags that disable some checks.
+    pub fn register_configuration(&mut self) {
+        let kind = TrustedItemKind::UncheckedConfiguration;
+        if !config::check_panics() {
+            self.register(kind, "CHECK_PANICS=false", None, "absence of panics is assumed");
+        }
+        if !config::check_binary_operations() {
+            self.register(
+                kind,
+                "CHECK_BINARY_OPERATIONS=false",
+                None,
+                "absence of overflows in binary operations is assumed",
+            );
+        }
+        if !config::check_debug_assertions() {
+            self.register(
+                kind,
+                "CHECK_DEBUG_ASSERTIONS=false",
+                None,
+                "the assertions of `debug_assert!` are assumed",
+            );
+        }
+        if config::use_assume_false_back_edges() {
+            self.register(
+                kind,
+                "USE_ASSUME_FALSE_BACK_EDGES=true",
+                None,
+                "loop back edges are assumed to be unreachable",
+            );
+        }
+        let verify_only_items = config::verify_only_items();
+        if !verify_only_items.is_empty() {
+            self.register(
+                kind,
+                format!("VERIFY_ONLY={}", verify_only_items.join(",")),
+                None,
+                "only the procedures that match the filters are verified",
+            );
+        }
+        if config::verify_only_preamble() {
+            self.register(
+                kind,
+                "VERIFY_ONLY_PREAMBLE=true",
+                None,
+                "no procedure is verified",
+            );
+        }
+        if config::enable_verify_only_basic_block_path() {
+            self.register(
+                kind,
+                "ENABLE_VERIFY_ONLY_BASIC_BLOCK_PATH=true",
+                None,
+                "only one execution path is verified",
+            );
+        }
+        if !config::delete_basic_blocks().is_empty() {
+            self.register(
+                kind,
+                "DELETE_BASIC_BLOCKS",
+                None,
+                "some basic blocks are assumed to be unreachable",
+            );
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns a human-readable report, ordered by kind and by position.
+    pub fn to_report(&self, codemap: &CodeMap) -> String {
+        let mut lines: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| {
+                let location = match item.span {
+                    Some(span) => codemap.span_to_string(span),
+                    None => "<configuration>".to_string(),
+                };
+                (
+                    item.kind,
+                    location.clone(),
+                    format!("[{}] {} at {}: {}", item.kind, item.name, location, item.reason),
+                )
+            })
+            .collect();
+        lines.sort();
+        lines
+            .into_iter()
+            .map(|(_, _, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
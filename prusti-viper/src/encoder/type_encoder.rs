@@ -14,6 +14,8 @@ use encoder::vir::ExprIterator;
 use encoder::Encoder;
 use prusti_interface::config;
 use prusti_interface::specifications::*;
+use prusti_std_models::{is_borrowing_variant, is_modelled_type};
+use rustc::hir;
 use rustc::middle::const_val::ConstVal;
 use rustc::ty;
 use rustc::ty::layout;
@@ -47,7 +49,8 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
             ty::TypeVariants::TyAdt(_, _) |
             ty::TypeVariants::TyTuple(_) |
             ty::TypeVariants::TyNever |
-            ty::TypeVariants::TyParam(_) => {
+            ty::TypeVariants::TyParam(_) |
+            ty::TypeVariants::TyProjection(_) => {
                 true
             }
             _ => {
@@ -79,12 +82,16 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
         }
     }
 
-    /// Are all fields in the struct of a supported type?
+    /// Are all fields in the struct of a supported type? The collections modelled by
+    /// `prusti_std_models` are encoded as abstract predicates, like unsupported types.
     fn is_supported_struct_type(&self, adt_def: &ty::AdtDef, subst: &ty::subst::Substs<'tcx>) -> bool {
         let tcx = self.encoder.env().tcx();
+        if is_modelled_type(&tcx.absolute_item_path_str(adt_def.did)) {
+            return false;
+        }
         let supported_fields = adt_def.variants.iter().all(|variant| {
             variant.fields.iter().all(|field| {
-                let field_ty = field.ty(tcx, subst);
+                let field_ty = self.encoder.resolve_field_ty(field, subst);
                 trace!("is_supported_type({:?}) = {}", field_ty, self.is_supported_type(field_ty));
                 self.is_supported_field_type(field_ty)
             })
@@ -94,7 +101,26 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
 
     pub fn encode_type(self) -> vir::Type {
         debug!("Encode type '{:?}'", self.ty);
-        vir::Type::TypedRef(self.encode_predicate_use())
+        let kind = match self.ty.sty {
+            ty::TypeVariants::TyRef(_, ref ty, _) => {
+                vir::TypedRefKind::Reference(box self.encoder.encode_type(ty))
+            }
+            ty::TypeVariants::TyAdt(adt_def, _) if adt_def.is_box() => {
+                vir::TypedRefKind::Box(box self.encoder.encode_type(self.ty.boxed_ty()))
+            }
+            ty::TypeVariants::TyAdt(_, subst) => vir::TypedRefKind::Adt(
+                subst
+                    .types()
+                    .map(|ty| self.encoder.encode_type(ty))
+                    .collect(),
+            ),
+            ty::TypeVariants::TyTuple(elems) => vir::TypedRefKind::Tuple(
+                elems.iter().map(|&ty| self.encoder.encode_type(ty)).collect(),
+            ),
+            ty::TypeVariants::TyParam(_) => vir::TypedRefKind::TypeVar,
+            _ => vir::TypedRefKind::Other,
+        };
+        vir::Type::TypedRef(self.encoder.encode_type_predicate_use(self.ty), kind)
     }
 
     pub fn encode_value_type(self) -> vir::Type {
@@ -106,17 +132,15 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 vir::Type::Int
             }
 
-            ty::TypeVariants::TyRef(_, ref ty, _) => {
-                let type_name = self.encoder.encode_type_predicate_use(ty);
-                vir::Type::TypedRef(type_name)
+            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. })
+            | ty::TypeVariants::TyRef(_, ref ty, _) => self.encoder.encode_type(ty),
+
+            ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(self.ty) => {
+                vir::Type::Domain(self.encoder.encode_domain_use(self.ty))
             }
 
             ty::TypeVariants::TyAdt(_, _) | ty::TypeVariants::TyTuple(_) => unimplemented!(),
 
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. }) => {
-                unimplemented!("Raw pointers are unsupported. (ty={:?})", ty);
-            }
-
             ref x => unimplemented!("{:?}", x),
         }
     }
@@ -130,17 +154,20 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 vir::Field::new("val_int", vir::Type::Int)
             }
 
-            ty::TypeVariants::TyRef(_, ref ty, _) => {
-                let type_name = self.encoder.encode_type_predicate_use(ty);
-                vir::Field::new("val_ref", vir::Type::TypedRef(type_name))
+            // The value of a raw pointer is the address of its target, like for a reference.
+            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. })
+            | ty::TypeVariants::TyRef(_, ref ty, _) => {
+                vir::Field::new("val_ref", self.encoder.encode_type(ty))
             }
 
-            ty::TypeVariants::TyAdt(_, _) | ty::TypeVariants::TyTuple(_) => unreachable!(),
-
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. }) => {
-                unimplemented!("Raw pointers are unsupported. (ty={:?})", ty);
+            // Each domain has its own field, because a field has a single type.
+            ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(self.ty) => {
+                let domain_name = self.encoder.encode_domain_use(self.ty);
+                vir::Field::new(format!("val_{}", domain_name), vir::Type::Domain(domain_name))
             }
 
+            ty::TypeVariants::TyAdt(_, _) | ty::TypeVariants::TyTuple(_) => unreachable!(),
+
             ref x => unimplemented!("{:?}", x),
         }
     }
@@ -173,7 +200,10 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 // char is always four bytes in size
                 Some((0.into(), 0xFFFFFFFFu32.into()))
             }
-            ty::TypeVariants::TyBool | ty::TypeVariants::TyRef(_, _, _) => None,
+            ty::TypeVariants::TyBool
+            | ty::TypeVariants::TyRef(_, _, _)
+            | ty::TypeVariants::TyRawPtr(_) => None,
+            ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(self.ty) => None,
             ref x => unreachable!("{:?}", x),
         }
     }
@@ -191,8 +221,7 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
 
     pub fn encode_predicate_def(self) -> Vec<vir::Predicate> {
         debug!("Encode type predicate '{:?}'", self.ty);
-        let predicate_name = self.encoder.encode_type_predicate_use(self.ty);
-        let typ = vir::Type::TypedRef(predicate_name.clone());
+        let typ = self.encoder.encode_type(self.ty);
 
         match self.ty.sty {
             ty::TypeVariants::TyBool => vec![vir::Predicate::new_primitive_value(
@@ -221,9 +250,13 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 )]
             }
 
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. }) => {
-                unimplemented!("Raw pointers are unsupported. (ty={:?})", ty);
-            }
+            // A raw pointer owns only its address, not its target.
+            ty::TypeVariants::TyRawPtr(..) => vec![vir::Predicate::new_primitive_value(
+                typ,
+                self.encoder.encode_value_field(self.ty),
+                None,
+                false,
+            )],
 
             ty::TypeVariants::TyRef(_, ref ty, _) => vec![vir::Predicate::new_struct(
                 typ,
@@ -234,14 +267,20 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 let fields = elems
                     .iter()
                     .enumerate()
-                    .map(|(field_num, ty)| {
-                        let field_name = format!("tuple_{}", field_num);
-                        self.encoder.encode_raw_ref_field(field_name, ty)
-                    })
+                    .map(|(field_num, ty)| self.encoder.encode_tuple_field(field_num, ty))
                     .collect();
                 vec![vir::Predicate::new_struct(typ, fields)]
             }
 
+            ty::TypeVariants::TyAdt(..) if self.encoder.is_domain_type(self.ty) => {
+                vec![vir::Predicate::new_primitive_value(
+                    typ,
+                    self.encoder.encode_value_field(self.ty),
+                    None,
+                    false,
+                )]
+            }
+
             ty::TypeVariants::TyAdt(adt_def, subst) if !adt_def.is_box() => {
                 if !self.is_supported_struct_type(adt_def, subst) {
                     vec![vir::Predicate::new_abstract(typ)]
@@ -255,11 +294,16 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                             .iter()
                             .map(|field| {
                                 let field_name = field.ident.to_string();
-                                let field_ty = field.ty(tcx, subst);
-                                self.encoder.encode_struct_field(&field_name, field_ty)
+                                let field_ty = self.encoder.resolve_field_ty(field, subst);
+                                (
+                                    self.encoder.encode_struct_field(&field_name, field_ty),
+                                    field_predicate_perm(field_ty),
+                                )
                             })
                             .collect();
-                        vec![vir::Predicate::new_struct(typ, fields)]
+                        vec![vir::Predicate::Struct(
+                            vir::StructPredicate::new_with_field_predicate_perms(typ, fields),
+                        )]
                     } else {
                         debug!("ADT {:?} has {} variants", adt_def, num_variants);
                         let discriminant_field = self.encoder.encode_discriminant_field();
@@ -269,22 +313,32 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                             adt_def, tcx, &discriminant_loc);
 
                         let discriminant_values = compute_discriminant_values(adt_def, tcx);
+                        let adt_path = tcx.absolute_item_path_str(adt_def.did);
                         let variants: Vec<_> = adt_def
                             .variants
                             .iter()
                             .zip(discriminant_values)
                             .map(|(variant_def, variant_index)| {
+                                let variant_name = &variant_def.name.as_str();
+                                let is_borrowing = is_borrowing_variant(&adt_path, variant_name);
                                 let fields = variant_def
                                     .fields
                                     .iter()
                                     .map(|field| {
                                         debug!("Encoding field {:?}", field);
                                         let field_name = &field.ident.as_str();
-                                        let field_ty = field.ty(tcx, subst);
-                                        self.encoder.encode_struct_field(field_name, field_ty)
+                                        let field_ty = self.encoder.resolve_field_ty(field, subst);
+                                        let perm_amount = if is_borrowing {
+                                            vir::PermAmount::Read
+                                        } else {
+                                            field_predicate_perm(field_ty)
+                                        };
+                                        (
+                                            self.encoder.encode_struct_field(field_name, field_ty),
+                                            perm_amount,
+                                        )
                                     })
                                     .collect();
-                                let variant_name = &variant_def.name.as_str();
                                 let guard = vir::Expr::eq_cmp(
                                     discriminant_loc.clone().into(),
                                     variant_index.into(),
@@ -293,7 +347,10 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                                 (
                                     guard,
                                     variant_name.to_string(),
-                                    vir::StructPredicate::new(variant_typ, fields),
+                                    vir::StructPredicate::new_with_field_predicate_perms(
+                                        variant_typ,
+                                        fields,
+                                    ),
                                 )
                             })
                             .collect();
@@ -327,16 +384,46 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                 )]
             }
 
+            ty::TypeVariants::TyStr => {
+                // The strings are abstracted by the sequence of their bytes.
+                vec![vir::Predicate::new_abstract(typ)]
+            }
+
+            ty::TypeVariants::TySlice(..) => {
+                // The slices are abstracted by their length, and by the sequence of their
+                // elements if they are integers or characters.
+                vec![vir::Predicate::new_abstract(typ)]
+            }
+
             ty::TypeVariants::TyNever => {
                 // FIXME: This should be a predicate with the body `false`. See issue #38.
                 vec![vir::Predicate::new_abstract(typ)]
             }
 
-            ty::TypeVariants::TyParam(_) => {
-                // special case: type parameters shall be encoded as *abstract* predicates
+            ty::TypeVariants::TyParam(_) | ty::TypeVariants::TyProjection(_) => {
+                // special case: type parameters (and associated types that could not be
+                // normalized) shall be encoded as *abstract* predicates
+                vec![vir::Predicate::new_abstract(typ)]
+            }
+
+            ty::TypeVariants::TyAnon(..) | ty::TypeVariants::TyDynamic(..) => {
+                // The concrete type of `impl Trait` and of trait objects is opaque: only the
+                // contracts of the methods of the trait are known.
                 vec![vir::Predicate::new_abstract(typ)]
             }
 
+            // A closure owns the variables that it captures by value, e.g. a closure passed to
+            // `std::thread::spawn`. The closures that capture by reference are not encoded.
+            ty::TypeVariants::TyClosure(def_id, closure_subst) => {
+                let tcx = self.encoder.env().tcx();
+                let fields = closure_subst
+                    .upvar_tys(def_id, tcx)
+                    .enumerate()
+                    .map(|(index, upvar_ty)| self.encoder.encode_closure_field(index, upvar_ty))
+                    .collect();
+                vec![vir::Predicate::new_struct(typ, fields)]
+            }
+
             ref ty_variant => {
                 debug!("Encoding of type '{}' is incomplete", ty_variant);
                 vec![vir::Predicate::new_abstract(typ)]
@@ -366,8 +453,11 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
 
             ty::TypeVariants::TyChar => "char".to_string(),
 
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. })
-            | ty::TypeVariants::TyRef(_, ref ty, _) => {
+            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. }) => {
+                format!("raw_ref${}", self.encoder.encode_type_predicate_use(ty))
+            }
+
+            ty::TypeVariants::TyRef(_, ref ty, _) => {
                 format!("ref${}", self.encoder.encode_type_predicate_use(ty))
             }
 
@@ -439,7 +529,59 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
             }
 
             ty::TypeVariants::TyParam(param_ty) => {
-                format!("__TYPARAM__${}$__", param_ty.name.as_str())
+                format!("__TYPARAM__${}$__", encode_type_param_name(param_ty))
+            }
+
+            ty::TypeVariants::TyProjection(ty::ProjectionTy {
+                substs,
+                item_def_id,
+            }) => {
+                // The substitutions are the ones of the trait followed, for a generic
+                // associated type, by its own parameters. Lifetimes are ignored.
+                let mut composed_name = vec![format!(
+                    "__TYPROJ__{}",
+                    self.encoder.encode_item_name(item_def_id)
+                )];
+                composed_name.push("_beg_".to_string());
+                let type_names: Vec<String> = substs
+                    .types()
+                    .map(|ty| self.encoder.encode_type_predicate_use(ty))
+                    .collect();
+                composed_name.push(type_names.join("$_sep_$"));
+                composed_name.push("_end_".to_string());
+                composed_name.join("$")
+            }
+
+            ty::TypeVariants::TyAnon(def_id, substs) => {
+                // An `impl Trait` type is identified by the item that defines it.
+                let mut composed_name =
+                    vec![format!("__TYANON__{}", self.encoder.encode_item_name(def_id))];
+                composed_name.push("_beg_".to_string());
+                let type_names: Vec<String> = substs
+                    .types()
+                    .map(|ty| self.encoder.encode_type_predicate_use(ty))
+                    .collect();
+                composed_name.push(type_names.join("$_sep_$"));
+                composed_name.push("_end_".to_string());
+                composed_name.join("$")
+            }
+
+            ty::TypeVariants::TyDynamic(predicates, _) => {
+                // A trait object is identified by its principal trait. Auto traits are ignored.
+                let mut composed_name = vec!["__TYDYN__".to_string()];
+                if let Some(principal) = predicates.principal() {
+                    let trait_ref = principal.skip_binder();
+                    composed_name.push(self.encoder.encode_item_name(trait_ref.def_id));
+                    composed_name.push("_beg_".to_string());
+                    let type_names: Vec<String> = trait_ref
+                        .substs
+                        .types()
+                        .map(|ty| self.encoder.encode_type_predicate_use(ty))
+                        .collect();
+                    composed_name.push(type_names.join("$_sep_$"));
+                    composed_name.push("_end_".to_string());
+                }
+                composed_name.join("$")
             }
 
             ref x => unimplemented!("{:?}", x),
@@ -450,14 +592,12 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
         debug!("[enter] encode_invariant_def({:?})", self.ty);
 
         let predicate_name = self.encoder.encode_type_predicate_use(self.ty);
-        let self_local_var =
-            vir::LocalVar::new("self", vir::Type::TypedRef(predicate_name.clone()));
+        let self_local_var = vir::LocalVar::new("self", self.encoder.encode_type(self.ty));
 
         let invariant_name = self.encoder.encode_type_invariant_use(self.ty);
 
         let field_invariants = match self.ty.sty {
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. })
-            | ty::TypeVariants::TyRef(_, ref ty, _) => {
+            ty::TypeVariants::TyRef(_, ref ty, _) => {
                 let elem_field = self.encoder.encode_dereference_field(ty);
                 let elem_loc = vir::Expr::from(self_local_var.clone()).field(elem_field);
                 Some(vec![self.encoder.encode_invariant_func_app(ty, elem_loc)])
@@ -528,14 +668,47 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
                         for field in &adt_def.variants[0].fields {
                             debug!("Encoding field {:?}", field);
                             let field_name = &field.ident.as_str();
-                            let field_ty = field.ty(tcx, subst);
+                            let field_ty = self.encoder.resolve_field_ty(field, subst);
                             let elem_field = self.encoder.encode_struct_field(field_name, field_ty);
                             let elem_loc = vir::Expr::from(self_local_var.clone()).field(elem_field);
                             exprs.push(self.encoder.encode_invariant_func_app(field_ty, elem_loc));
                         }
                     } else {
                         debug!("ADT {:?} has {} variants", adt_def, num_variants);
-                        // TODO: https://gitlab.inf.ethz.ch/OU-PMUELLER/prusti-dev/issues/201
+                        let discriminant_field = self.encoder.encode_discriminant_field();
+                        let discriminant_loc =
+                            vir::Expr::from(self_local_var.clone()).field(discriminant_field);
+                        let discriminant_values = compute_discriminant_values(adt_def, tcx);
+                        for (variant_def, variant_index) in
+                            adt_def.variants.iter().zip(discriminant_values)
+                        {
+                            let variant_loc = vir::Expr::from(self_local_var.clone())
+                                .variant(&variant_def.name.as_str());
+                            let variant_invariants: Vec<_> = variant_def
+                                .fields
+                                .iter()
+                                .map(|field| {
+                                    debug!("Encoding field {:?}", field);
+                                    let field_name = &field.ident.as_str();
+                                    let field_ty = self.encoder.resolve_field_ty(field, subst);
+                                    let elem_field =
+                                        self.encoder.encode_struct_field(field_name, field_ty);
+                                    let elem_loc = variant_loc.clone().field(elem_field);
+                                    self.encoder.encode_invariant_func_app(field_ty, elem_loc)
+                                })
+                                .collect();
+                            if !variant_invariants.is_empty() {
+                                // The fields of a variant are accessible only if it is active.
+                                let guard = vir::Expr::eq_cmp(
+                                    discriminant_loc.clone(),
+                                    variant_index.into(),
+                                );
+                                exprs.push(vir::Expr::implies(
+                                    guard,
+                                    variant_invariants.into_iter().conjoin(),
+                                ));
+                            }
+                        }
                     }
 
                     Some(exprs)
@@ -550,8 +723,13 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
         };
 
         let precondition = match self.ty.sty {
-            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. })
-            | ty::TypeVariants::TyRef(_, ref ty, _) => {
+            // This is a raw pointer, which is unfolded and owns only its address.
+            ty::TypeVariants::TyRawPtr(..) => {
+                let value_field = self.encoder.encode_value_field(self.ty);
+                let value_loc = vir::Expr::from(self_local_var.clone()).field(value_field);
+                vir::Expr::acc_permission(value_loc, vir::PermAmount::Read)
+            }
+            ty::TypeVariants::TyRef(_, ref ty, _) => {
                 // This is a reference, so we need to have it already unfolded.
                 let elem_field = self.encoder.encode_dereference_field(ty);
                 let elem_loc = vir::Expr::from(self_local_var.clone()).field(elem_field);
@@ -602,7 +780,10 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
         let tag_name = self.encoder.encode_type_tag_use(self.ty);
 
         let body = match self.ty.sty {
-            ty::TypeVariants::TyParam(_param_ty) => None,
+            ty::TypeVariants::TyParam(_)
+            | ty::TypeVariants::TyProjection(_)
+            | ty::TypeVariants::TyAnon(..)
+            | ty::TypeVariants::TyDynamic(..) => None,
             _ => Some((vir::Const::Int((self.ty as *const ty::TyS<'tcx>) as i64)).into()),
         };
 
@@ -635,6 +816,16 @@ impl<'p, 'v, 'r: 'v, 'a: 'r, 'tcx: 'a> TypeEncoder<'p, 'v, 'r, 'a, 'tcx> {
     }
 }
 
+/// The amount of the permission of a struct field to the predicate of its type. A shared
+/// reference only reads its referent, so that copies of the reference (e.g. made by a derived
+/// `Clone`) can point to the same referent.
+fn field_predicate_perm(field_ty: ty::Ty) -> vir::PermAmount {
+    match field_ty.sty {
+        ty::TypeVariants::TyRef(_, _, hir::Mutability::MutImmutable) => vir::PermAmount::Read,
+        _ => vir::PermAmount::Write,
+    }
+}
+
 /// Compute the values that a discriminant can take.
 pub fn compute_discriminant_values(
     adt_def: &ty::AdtDef,
@@ -707,3 +898,19 @@ impl ExprFolder for HackyExprFolder {
         vir::Expr::Local(self.saelf.clone(), pos)
     }
 }
+
+/// Encode the name of a type parameter.
+///
+/// The parser replaces the `impl Trait` arguments of the functions with specifications
+/// by named type parameters, but the methods of traits and of trait impls keep them.
+/// Their type parameters are named after their source text (e.g.
+/// `impl Iterator<Item = u32>`), which is not a valid Viper identifier, so they are
+/// identified by their index.
+fn encode_type_param_name(param_ty: ty::ParamTy) -> String {
+    let name = param_ty.name.as_str();
+    if name.starts_with("impl ") {
+        format!("impl${}", param_ty.idx)
+    } else {
+        name.to_string()
+    }
+}
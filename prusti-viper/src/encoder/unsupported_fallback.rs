@@ -0,0 +1,74 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The fallback for the procedures that use features that the encoder does not support.
+//!
+//! The encoding of such a procedure usually aborts the verification of the whole crate. A
+//! procedure marked with `#[trusted_if_unsupported]` whose body uses unsupported features, e.g.
+//! raw pointers, is instead treated like a `#[trusted]` procedure: its whole body is trusted
+//! and not encoded, not only the unsupported statements, and its callers assume its contract.
+//! Each unsupported feature is reported as a warning, and the procedure is part of the trusted
+//! surface. A procedure marked with the attribute whose body is supported is verified as usual.
+
+use encoder::trusted_surface::TrustedItemKind;
+use encoder::Encoder;
+use prusti_filter::validators::{Reason, Validator};
+use prusti_interface::data::ProcedureDefId;
+
+/// The attribute that enables the fallback for a procedure.
+pub const TRUSTED_IF_UNSUPPORTED_ATTR: &str = "trusted_if_unsupported";
+
+/// The reasons why the body of the procedure cannot be encoded, if the procedure is marked
+/// with `#[trusted_if_unsupported]` and uses unsupported features.
+pub fn unsupported_features<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+) -> Option<Vec<Reason>> {
+    if !encoder
+        .env()
+        .has_attribute_name(proc_def_id, TRUSTED_IF_UNSUPPORTED_ATTR)
+    {
+        return None;
+    }
+    let validator = Validator::new(encoder.env().tcx());
+    let support_status = validator.procedure_support_status(proc_def_id);
+    let mut reasons = support_status.get_unsupported_reasons();
+    reasons.extend(support_status.get_partially_supported_reasons());
+    if reasons.is_empty() {
+        None
+    } else {
+        Some(reasons)
+    }
+}
+
+/// Report the unsupported features of a procedure whose body is not encoded, and register the
+/// procedure in the trusted surface.
+pub fn report_unsupported_procedure<'v, 'r: 'v, 'a: 'r, 'tcx: 'a>(
+    encoder: &Encoder<'v, 'r, 'a, 'tcx>,
+    proc_def_id: ProcedureDefId,
+    reasons: &[Reason],
+) {
+    let proc_name = encoder.env().get_absolute_item_name(proc_def_id);
+    for reason in reasons {
+        encoder.env().span_warn(
+            reason.position,
+            &format!(
+                "[Prusti] the body of `{}` is not verified and its contract is assumed, \
+                 because it {}",
+                proc_name, reason.reason
+            ),
+        );
+    }
+    let mut features: Vec<&str> = reasons.iter().map(|reason| reason.reason.as_str()).collect();
+    features.sort();
+    features.dedup();
+    encoder.trusted_surface().register(
+        TrustedItemKind::UnsupportedProcedure,
+        proc_name,
+        Some(encoder.env().get_item_span(proc_def_id)),
+        format!("the body is not verified, because it {}", features.join(", ")),
+    );
+}
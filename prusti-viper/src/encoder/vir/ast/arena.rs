@@ -0,0 +1,481 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A hash-consed arena of expressions.
+//!
+//! Each distinct subexpression is stored once and identified by an `ExprId`. Like the
+//! `PartialEq` of `Expr`, interning ignores positions: the position of an id is the one of the
+//! first expression interned with that id. Comparing, hashing and copying ids is constant-time,
+//! which makes them cheap keys for the set computations of the fold/unfold algorithm.
+
+use super::super::borrows::Borrow;
+use encoder::vir::ast::*;
+use std::collections::HashMap;
+
+/// The handle of an expression interned in an `ExprArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A shallow `Expr`, whose subexpressions are ids of the same arena.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExprNode {
+    Local(LocalVar),
+    Variant(ExprId, Field),
+    Field(ExprId, Field),
+    AddrOf(ExprId, Type),
+    LabelledOld(String, ExprId),
+    Const(Const),
+    MagicWand(ExprId, ExprId, Option<Borrow>),
+    PredicateAccessPredicate(String, ExprId, PermAmount),
+    FieldAccessPredicate(ExprId, PermAmount),
+    UnaryOp(UnaryOpKind, ExprId),
+    BinOp(BinOpKind, ExprId, ExprId),
+    Unfolding(String, Vec<ExprId>, ExprId, PermAmount, MaybeEnumVariantIndex),
+    Cond(ExprId, ExprId, ExprId),
+    ForAll(Vec<LocalVar>, Vec<Trigger>, ExprId),
+    Exists(Vec<LocalVar>, Vec<Trigger>, ExprId),
+    LetExpr(LocalVar, ExprId, ExprId),
+    FuncApp(String, Vec<ExprId>, Vec<LocalVar>, Type),
+    DomainFuncApp(DomainFunc, Vec<ExprId>),
+    MapLookup(ExprId, ExprId),
+    MapContains(ExprId, ExprId),
+    MapUpdate(ExprId, ExprId, ExprId),
+    MapLen(ExprId),
+    SeqLen(ExprId),
+    SeqIndex(ExprId, ExprId),
+}
+
+impl ExprNode {
+    /// The direct subexpressions, from left to right.
+    pub fn children(&self) -> Vec<ExprId> {
+        match self {
+            ExprNode::Local(..) | ExprNode::Const(..) => vec![],
+            ExprNode::Variant(base, ..)
+            | ExprNode::Field(base, ..)
+            | ExprNode::AddrOf(base, ..)
+            | ExprNode::LabelledOld(_, base)
+            | ExprNode::PredicateAccessPredicate(_, base, _)
+            | ExprNode::FieldAccessPredicate(base, _)
+            | ExprNode::UnaryOp(_, base)
+            | ExprNode::ForAll(_, _, base)
+            | ExprNode::Exists(_, _, base)
+            | ExprNode::MapLen(base)
+            | ExprNode::SeqLen(base) => vec![*base],
+            ExprNode::MagicWand(left, right, _)
+            | ExprNode::BinOp(_, left, right)
+            | ExprNode::LetExpr(_, left, right)
+            | ExprNode::MapLookup(left, right)
+            | ExprNode::MapContains(left, right)
+            | ExprNode::SeqIndex(left, right) => vec![*left, *right],
+            ExprNode::Cond(first, second, third) | ExprNode::MapUpdate(first, second, third) => {
+                vec![*first, *second, *third]
+            }
+            ExprNode::Unfolding(_, args, base, _, _) => {
+                let mut children = args.clone();
+                children.push(*base);
+                children
+            }
+            ExprNode::FuncApp(_, args, _, _) | ExprNode::DomainFuncApp(_, args) => args.clone(),
+        }
+    }
+
+    /// Replace the direct subexpressions with `f`, from left to right.
+    pub fn map_children<F: FnMut(ExprId) -> ExprId>(self, mut f: F) -> Self {
+        match self {
+            node @ ExprNode::Local(..) | node @ ExprNode::Const(..) => node,
+            ExprNode::Variant(base, variant) => ExprNode::Variant(f(base), variant),
+            ExprNode::Field(base, field) => ExprNode::Field(f(base), field),
+            ExprNode::AddrOf(base, typ) => ExprNode::AddrOf(f(base), typ),
+            ExprNode::LabelledOld(label, base) => ExprNode::LabelledOld(label, f(base)),
+            ExprNode::MagicWand(lhs, rhs, borrow) => {
+                let lhs = f(lhs);
+                ExprNode::MagicWand(lhs, f(rhs), borrow)
+            }
+            ExprNode::PredicateAccessPredicate(name, arg, perm) => {
+                ExprNode::PredicateAccessPredicate(name, f(arg), perm)
+            }
+            ExprNode::FieldAccessPredicate(base, perm) => {
+                ExprNode::FieldAccessPredicate(f(base), perm)
+            }
+            ExprNode::UnaryOp(op, arg) => ExprNode::UnaryOp(op, f(arg)),
+            ExprNode::BinOp(op, left, right) => {
+                let left = f(left);
+                ExprNode::BinOp(op, left, f(right))
+            }
+            ExprNode::Unfolding(name, args, base, perm, variant) => {
+                let args = args.into_iter().map(&mut f).collect();
+                ExprNode::Unfolding(name, args, f(base), perm, variant)
+            }
+            ExprNode::Cond(guard, then_expr, else_expr) => {
+                let guard = f(guard);
+                let then_expr = f(then_expr);
+                ExprNode::Cond(guard, then_expr, f(else_expr))
+            }
+            ExprNode::ForAll(vars, triggers, body) => ExprNode::ForAll(vars, triggers, f(body)),
+            ExprNode::Exists(vars, triggers, body) => ExprNode::Exists(vars, triggers, f(body)),
+            ExprNode::LetExpr(var, def, body) => {
+                let def = f(def);
+                ExprNode::LetExpr(var, def, f(body))
+            }
+            ExprNode::FuncApp(name, args, formal_args, return_type) => {
+                let args = args.into_iter().map(&mut f).collect();
+                ExprNode::FuncApp(name, args, formal_args, return_type)
+            }
+            ExprNode::DomainFuncApp(func, args) => {
+                ExprNode::DomainFuncApp(func, args.into_iter().map(&mut f).collect())
+            }
+            ExprNode::MapLookup(map, key) => {
+                let map = f(map);
+                ExprNode::MapLookup(map, f(key))
+            }
+            ExprNode::MapContains(map, key) => {
+                let map = f(map);
+                ExprNode::MapContains(map, f(key))
+            }
+            ExprNode::MapUpdate(map, key, value) => {
+                let map = f(map);
+                let key = f(key);
+                ExprNode::MapUpdate(map, key, f(value))
+            }
+            ExprNode::MapLen(map) => ExprNode::MapLen(f(map)),
+            ExprNode::SeqLen(seq) => ExprNode::SeqLen(f(seq)),
+            ExprNode::SeqIndex(seq, index) => {
+                let seq = f(seq);
+                ExprNode::SeqIndex(seq, f(index))
+            }
+        }
+    }
+}
+
+/// Stores each distinct (modulo positions) expression once.
+#[derive(Debug, Clone, Default)]
+pub struct ExprArena {
+    nodes: Vec<ExprNode>,
+    positions: Vec<Position>,
+    ids: HashMap<ExprNode, ExprId>,
+}
+
+impl ExprArena {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn node(&self, id: ExprId) -> &ExprNode {
+        &self.nodes[id.index()]
+    }
+
+    pub fn position(&self, id: ExprId) -> Position {
+        self.positions[id.index()]
+    }
+
+    /// Intern a shallow expression, reusing the id of an equal one if there is any.
+    pub fn intern_node(&mut self, node: ExprNode, pos: Position) -> ExprId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let id = ExprId(self.nodes.len() as u32);
+        self.nodes.push(node.clone());
+        self.positions.push(pos);
+        self.ids.insert(node, id);
+        id
+    }
+
+    pub fn intern(&mut self, expr: &Expr) -> ExprId {
+        let node = match expr {
+            Expr::Local(ref var, _) => ExprNode::Local(var.clone()),
+            Expr::Variant(box ref base, ref variant, _) => {
+                ExprNode::Variant(self.intern(base), variant.clone())
+            }
+            Expr::Field(box ref base, ref field, _) => {
+                ExprNode::Field(self.intern(base), field.clone())
+            }
+            Expr::AddrOf(box ref base, ref typ, _) => {
+                ExprNode::AddrOf(self.intern(base), typ.clone())
+            }
+            Expr::LabelledOld(ref label, box ref base, _) => {
+                ExprNode::LabelledOld(label.clone(), self.intern(base))
+            }
+            Expr::Const(ref value, _) => ExprNode::Const(value.clone()),
+            Expr::MagicWand(box ref lhs, box ref rhs, borrow, _) => {
+                ExprNode::MagicWand(self.intern(lhs), self.intern(rhs), *borrow)
+            }
+            Expr::PredicateAccessPredicate(ref name, box ref arg, perm, _) => {
+                ExprNode::PredicateAccessPredicate(name.clone(), self.intern(arg), *perm)
+            }
+            Expr::FieldAccessPredicate(box ref base, perm, _) => {
+                ExprNode::FieldAccessPredicate(self.intern(base), *perm)
+            }
+            Expr::UnaryOp(op, box ref arg, _) => ExprNode::UnaryOp(*op, self.intern(arg)),
+            Expr::BinOp(op, box ref left, box ref right, _) => {
+                ExprNode::BinOp(*op, self.intern(left), self.intern(right))
+            }
+            Expr::Unfolding(ref name, ref args, box ref base, perm, ref variant, _) => {
+                ExprNode::Unfolding(
+                    name.clone(),
+                    args.iter().map(|arg| self.intern(arg)).collect(),
+                    self.intern(base),
+                    *perm,
+                    variant.clone(),
+                )
+            }
+            Expr::Cond(box ref guard, box ref then_expr, box ref else_expr, _) => ExprNode::Cond(
+                self.intern(guard),
+                self.intern(then_expr),
+                self.intern(else_expr),
+            ),
+            Expr::ForAll(ref vars, ref triggers, box ref body, _) => {
+                ExprNode::ForAll(vars.clone(), triggers.clone(), self.intern(body))
+            }
+            Expr::Exists(ref vars, ref triggers, box ref body, _) => {
+                ExprNode::Exists(vars.clone(), triggers.clone(), self.intern(body))
+            }
+            Expr::LetExpr(ref var, box ref def, box ref body, _) => {
+                ExprNode::LetExpr(var.clone(), self.intern(def), self.intern(body))
+            }
+            Expr::FuncApp(ref name, ref args, ref formal_args, ref return_type, _) => {
+                ExprNode::FuncApp(
+                    name.clone(),
+                    args.iter().map(|arg| self.intern(arg)).collect(),
+                    formal_args.clone(),
+                    return_type.clone(),
+                )
+            }
+            Expr::DomainFuncApp(ref func, ref args, _) => ExprNode::DomainFuncApp(
+                func.clone(),
+                args.iter().map(|arg| self.intern(arg)).collect(),
+            ),
+            Expr::MapLookup(box ref map, box ref key, _) => {
+                ExprNode::MapLookup(self.intern(map), self.intern(key))
+            }
+            Expr::MapContains(box ref map, box ref key, _) => {
+                ExprNode::MapContains(self.intern(map), self.intern(key))
+            }
+            Expr::MapUpdate(box ref map, box ref key, box ref value, _) => ExprNode::MapUpdate(
+                self.intern(map),
+                self.intern(key),
+                self.intern(value),
+            ),
+            Expr::MapLen(box ref map, _) => ExprNode::MapLen(self.intern(map)),
+            Expr::SeqLen(box ref seq, _) => ExprNode::SeqLen(self.intern(seq)),
+            Expr::SeqIndex(box ref seq, box ref index, _) => {
+                ExprNode::SeqIndex(self.intern(seq), self.intern(index))
+            }
+        };
+        self.intern_node(node, *expr.pos())
+    }
+
+    /// Rebuild the expression of an id.
+    pub fn expr(&self, id: ExprId) -> Expr {
+        let pos = self.position(id);
+        let boxed = |id: ExprId| box self.expr(id);
+        let exprs = |ids: &[ExprId]| -> Vec<Expr> {
+            ids.iter().map(|&id| self.expr(id)).collect()
+        };
+        match self.node(id) {
+            ExprNode::Local(ref var) => Expr::Local(var.clone(), pos),
+            ExprNode::Variant(base, ref variant) => {
+                Expr::Variant(boxed(*base), variant.clone(), pos)
+            }
+            ExprNode::Field(base, ref field) => Expr::Field(boxed(*base), field.clone(), pos),
+            ExprNode::AddrOf(base, ref typ) => Expr::AddrOf(boxed(*base), typ.clone(), pos),
+            ExprNode::LabelledOld(ref label, base) => {
+                Expr::LabelledOld(label.clone(), boxed(*base), pos)
+            }
+            ExprNode::Const(ref value) => Expr::Const(value.clone(), pos),
+            ExprNode::MagicWand(lhs, rhs, borrow) => {
+                Expr::MagicWand(boxed(*lhs), boxed(*rhs), *borrow, pos)
+            }
+            ExprNode::PredicateAccessPredicate(ref name, arg, perm) => {
+                Expr::PredicateAccessPredicate(name.clone(), boxed(*arg), *perm, pos)
+            }
+            ExprNode::FieldAccessPredicate(base, perm) => {
+                Expr::FieldAccessPredicate(boxed(*base), *perm, pos)
+            }
+            ExprNode::UnaryOp(op, arg) => Expr::UnaryOp(*op, boxed(*arg), pos),
+            ExprNode::BinOp(op, left, right) => Expr::BinOp(*op, boxed(*left), boxed(*right), pos),
+            ExprNode::Unfolding(ref name, ref args, base, perm, ref variant) => Expr::Unfolding(
+                name.clone(),
+                exprs(args),
+                boxed(*base),
+                *perm,
+                variant.clone(),
+                pos,
+            ),
+            ExprNode::Cond(guard, then_expr, else_expr) => {
+                Expr::Cond(boxed(*guard), boxed(*then_expr), boxed(*else_expr), pos)
+            }
+            ExprNode::ForAll(ref vars, ref triggers, body) => {
+                Expr::ForAll(vars.clone(), triggers.clone(), boxed(*body), pos)
+            }
+            ExprNode::Exists(ref vars, ref triggers, body) => {
+                Expr::Exists(vars.clone(), triggers.clone(), boxed(*body), pos)
+            }
+            ExprNode::LetExpr(ref var, def, body) => {
+                Expr::LetExpr(var.clone(), boxed(*def), boxed(*body), pos)
+            }
+            ExprNode::FuncApp(ref name, ref args, ref formal_args, ref return_type) => {
+                Expr::FuncApp(
+                    name.clone(),
+                    exprs(args),
+                    formal_args.clone(),
+                    return_type.clone(),
+                    pos,
+                )
+            }
+            ExprNode::DomainFuncApp(ref func, ref args) => {
+                Expr::DomainFuncApp(func.clone(), exprs(args), pos)
+            }
+            ExprNode::MapLookup(map, key) => Expr::MapLookup(boxed(*map), boxed(*key), pos),
+            ExprNode::MapContains(map, key) => Expr::MapContains(boxed(*map), boxed(*key), pos),
+            ExprNode::MapUpdate(map, key, value) => {
+                Expr::MapUpdate(boxed(*map), boxed(*key), boxed(*value), pos)
+            }
+            ExprNode::MapLen(map) => Expr::MapLen(boxed(*map), pos),
+            ExprNode::SeqLen(seq) => Expr::SeqLen(boxed(*seq), pos),
+            ExprNode::SeqIndex(seq, index) => Expr::SeqIndex(boxed(*seq), boxed(*index), pos),
+        }
+    }
+
+    /// The parent of a place, like `Expr::get_parent`.
+    pub fn parent(&self, id: ExprId) -> Option<ExprId> {
+        match self.node(id) {
+            ExprNode::Variant(base, _) | ExprNode::Field(base, _) | ExprNode::AddrOf(base, _) => {
+                Some(*base)
+            }
+            _ => None,
+        }
+    }
+
+    /// Like `Expr::has_prefix`, without rebuilding the parents of the place.
+    pub fn has_prefix(&self, id: ExprId, prefix: ExprId) -> bool {
+        let mut current = Some(id);
+        while let Some(place) = current {
+            if place == prefix {
+                return true;
+            }
+            current = self.parent(place);
+        }
+        false
+    }
+
+    pub fn has_proper_prefix(&self, id: ExprId, prefix: ExprId) -> bool {
+        id != prefix && self.has_prefix(id, prefix)
+    }
+}
+
+/// Like `ExprFolder`, on expressions interned in an arena.
+pub trait ExprIdFolder: Sized {
+    fn fold(&mut self, arena: &mut ExprArena, id: ExprId) -> ExprId {
+        default_fold_expr_id(self, arena, id)
+    }
+}
+
+pub fn default_fold_expr_id<T: ExprIdFolder>(
+    this: &mut T,
+    arena: &mut ExprArena,
+    id: ExprId,
+) -> ExprId {
+    let pos = arena.position(id);
+    let node = arena.node(id).clone().map_children(|child| this.fold(arena, child));
+    arena.intern_node(node, pos)
+}
+
+/// Like `ExprWalker`, on expressions interned in an arena.
+pub trait ExprIdWalker: Sized {
+    fn walk(&mut self, arena: &ExprArena, id: ExprId) {
+        default_walk_expr_id(self, arena, id)
+    }
+}
+
+pub fn default_walk_expr_id<T: ExprIdWalker>(this: &mut T, arena: &ExprArena, id: ExprId) {
+    for child in arena.node(id).children() {
+        this.walk(arena, child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(path: &[&str]) -> Expr {
+        let mut expr = Expr::local(LocalVar::new(path[0], Type::typed_ref("T")));
+        for name in &path[1..] {
+            expr = expr.field(Field::new(*name, Type::typed_ref("T")));
+        }
+        expr
+    }
+
+    #[test]
+    fn test_intern_shares_equal_subexpressions() {
+        let mut arena = ExprArena::new();
+        let left = arena.intern(&place(&["x", "f", "g"]));
+        let right = arena.intern(&place(&["x", "f", "g"]));
+        let other = arena.intern(&place(&["x", "f", "h"]));
+        assert_eq!(left, right);
+        assert_ne!(left, other);
+        assert_eq!(arena.parent(left), arena.parent(other));
+        assert_eq!(arena.expr(left), place(&["x", "f", "g"]));
+    }
+
+    #[test]
+    fn test_has_prefix() {
+        let mut arena = ExprArena::new();
+        let base = arena.intern(&place(&["x", "f"]));
+        let leaf = arena.intern(&place(&["x", "f", "g"]));
+        assert!(arena.has_prefix(leaf, base));
+        assert!(arena.has_proper_prefix(leaf, base));
+        assert!(arena.has_prefix(base, base));
+        assert!(!arena.has_proper_prefix(base, base));
+        assert!(!arena.has_prefix(base, leaf));
+    }
+
+    #[test]
+    fn test_fold_and_walk() {
+        struct Replacer {
+            target: ExprId,
+            replacement: ExprId,
+        }
+        impl ExprIdFolder for Replacer {
+            fn fold(&mut self, arena: &mut ExprArena, id: ExprId) -> ExprId {
+                if id == self.target {
+                    self.replacement
+                } else {
+                    default_fold_expr_id(self, arena, id)
+                }
+            }
+        }
+        struct LocalCounter(usize);
+        impl ExprIdWalker for LocalCounter {
+            fn walk(&mut self, arena: &ExprArena, id: ExprId) {
+                if let ExprNode::Local(_) = arena.node(id) {
+                    self.0 += 1;
+                }
+                default_walk_expr_id(self, arena, id)
+            }
+        }
+
+        let mut arena = ExprArena::new();
+        let sum = Expr::add(place(&["x", "f"]), place(&["x", "f", "g"]));
+        let id = arena.intern(&sum);
+        let target = arena.intern(&place(&["x", "f"]));
+        let replacement = arena.intern(&place(&["y"]));
+        let mut replacer = Replacer { target, replacement };
+        let replaced = replacer.fold(&mut arena, id);
+        assert_eq!(
+            arena.expr(replaced),
+            Expr::add(place(&["y"]), place(&["y", "g"]))
+        );
+        let mut counter = LocalCounter(0);
+        counter.walk(&arena, replaced);
+        assert_eq!(counter.0, 2);
+    }
+}
@@ -10,21 +10,83 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::mem::discriminant;
 use std::ops;
+use std::u32;
 
 pub trait WithIdentifier {
     fn get_identifier(&self) -> String;
 }
 
+/// The identifier of a position registered in the error manager, which maps it to a span
+/// of the source code. Positions are cloned pervasively, so they do not store strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PositionId(u32);
+
+impl PositionId {
+    /// The identifier of the default position.
+    pub const NONE: PositionId = PositionId(u32::MAX);
+    /// Fake identifiers of the positions that are not registered in the error manager.
+    pub const INHALE: PositionId = PositionId(u32::MAX - 1);
+    pub const METHOD_CALL: PositionId = PositionId(u32::MAX - 2);
+    pub const FOLD_ASSERT: PositionId = PositionId(u32::MAX - 3);
+    pub const DELETED: PositionId = PositionId(u32::MAX - 4);
+    pub const BACK_EDGE: PositionId = PositionId(u32::MAX - 5);
+    pub const CHECK_FOLD_UNFOLD_STATE: PositionId = PositionId(u32::MAX - 6);
+
+    pub fn new(index: u32) -> Self {
+        assert!(index < PositionId::CHECK_FOLD_UNFOLD_STATE.0, "too many positions");
+        PositionId(index)
+    }
+
+    /// The index of a registered position.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Parse the identifier of a position reported by the verifier.
+    pub fn parse(pos_id: &str) -> Option<Self> {
+        if pos_id.starts_with("pos") {
+            pos_id[3..].parse().ok().map(PositionId)
+        } else {
+            None
+        }
+    }
+
+    fn fake_name(self) -> Option<&'static str> {
+        let fake_positions = [
+            (PositionId::NONE, "no-position"),
+            (PositionId::INHALE, "inhale"),
+            (PositionId::METHOD_CALL, "method_call"),
+            (PositionId::FOLD_ASSERT, "fold_assert"),
+            (PositionId::DELETED, "deleted"),
+            (PositionId::BACK_EDGE, "back edge"),
+            (PositionId::CHECK_FOLD_UNFOLD_STATE, "check fold/unfold state"),
+        ];
+        fake_positions
+            .iter()
+            .find(|(id, _)| *id == self)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for PositionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fake_name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "pos{}", self.0),
+        }
+    }
+}
+
 /// The identifier of a statement. Used in error reporting.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
     line: i32,
     column: i32,
-    id: String,
+    id: PositionId,
 }
 
 impl Position {
-    pub fn new(line: i32, column: i32, id: String) -> Self {
+    pub fn new(line: i32, column: i32, id: PositionId) -> Self {
         Position { line, column, id }
     }
 
@@ -36,18 +98,18 @@ impl Position {
         self.column
     }
 
-    pub fn id(&self) -> String {
-        self.id.to_string()
+    pub fn id(&self) -> PositionId {
+        self.id
     }
 
     pub fn is_default(&self) -> bool {
-        self.line == 0 && self.column == 0 && self.id == "no-position"
+        self.line == 0 && self.column == 0 && self.id == PositionId::NONE
     }
 }
 
 impl Default for Position {
     fn default() -> Self {
-        Position::new(0, 0, "no-position".to_string())
+        Position::new(0, 0, PositionId::NONE)
     }
 }
 
@@ -57,9 +119,51 @@ mod tests {
 
     #[test]
     fn test_default_position() {
-        assert!(!Position::new(123, 234, "123123123".to_string()).is_default());
+        assert!(!Position::new(123, 234, PositionId::new(123123123)).is_default());
         assert!(Position::default().is_default());
     }
+
+    #[test]
+    fn test_position_id_roundtrip() {
+        let id = PositionId::new(42);
+        assert_eq!(id.to_string(), "pos42");
+        assert_eq!(PositionId::parse(&id.to_string()), Some(id));
+        assert_eq!(PositionId::parse(&PositionId::INHALE.to_string()), None);
+    }
+
+    #[test]
+    fn test_wildcard_is_never_exhausted() {
+        assert_eq!(PermAmount::Wildcard - PermAmount::Wildcard, PermAmount::Wildcard);
+        assert_eq!(PermAmount::Write - PermAmount::Wildcard, PermAmount::Write);
+        assert_eq!(PermAmount::Read - PermAmount::Wildcard, PermAmount::Read);
+        assert_eq!(PermAmount::Remaining - PermAmount::Wildcard, PermAmount::Remaining);
+        assert_eq!(PermAmount::Read + PermAmount::Wildcard, PermAmount::Read);
+        assert!(PermAmount::Wildcard < PermAmount::Read);
+        assert!(PermAmount::Wildcard < PermAmount::Remaining);
+        assert!(PermAmount::Remaining > PermAmount::Wildcard);
+    }
+
+    #[test]
+    fn test_fractions() {
+        let half = PermAmount::frac(1, 2);
+        assert_eq!(PermAmount::frac(2, 4), half);
+        assert_eq!(PermAmount::frac(3, 3), PermAmount::Write);
+        assert_eq!(half + half, PermAmount::Write);
+        assert_eq!(PermAmount::frac(1, 3) + PermAmount::frac(1, 6), half);
+        assert_eq!(PermAmount::Write - PermAmount::frac(1, 4), PermAmount::frac(3, 4));
+        assert_eq!(half - PermAmount::Wildcard, half);
+        assert!(PermAmount::frac(1, 3) < half);
+        assert!(half < PermAmount::Write);
+        assert!(PermAmount::Wildcard < half);
+    }
+
+    #[test]
+    fn test_tuple_field_roundtrip() {
+        let field = Field::new(Field::tuple_name(3), Type::Int);
+        assert_eq!(field.tuple_index(), Some(3));
+        assert_eq!(Field::new("f$tuple_3", Type::Int).tuple_index(), None);
+        assert_eq!(Field::new("tuple_x", Type::Int).tuple_index(), None);
+    }
 }
 
 /// The permission amount.
@@ -69,16 +173,75 @@ pub enum PermAmount {
     Write,
     /// The permission remaining after ``Read`` was subtracted from ``Write``.
     Remaining,
+    /// An unspecified positive permission amount (Viper's ``wildcard``).
+    ///
+    /// Unlike ``Read``, a wildcard amount can be taken arbitrarily many times
+    /// from a permission without ever exhausting it, so it does not need to
+    /// be counted.
+    Wildcard,
+    /// The constant fraction ``numerator / denominator`` of a full permission, strictly
+    /// between none and ``Write``, e.g. the ``1/2`` of a ``deref_spec_frac`` in a specification.
+    /// Built by ``PermAmount::frac``, which reduces the fraction.
+    Frac(u32, u32),
 }
 
 impl PermAmount {
+    /// The permission amount ``numerator / denominator``, which must be positive and at most
+    /// ``Write``.
+    pub fn frac(numerator: u32, denominator: u32) -> Self {
+        assert!(0 < numerator && numerator <= denominator);
+        let divisor = gcd(u64::from(numerator), u64::from(denominator)) as u32;
+        if numerator == denominator {
+            PermAmount::Write
+        } else {
+            PermAmount::Frac(numerator / divisor, denominator / divisor)
+        }
+    }
+
     /// Can this permission amount be used in specifications?
     pub fn is_valid_for_specs(&self) -> bool {
         match self {
-            PermAmount::Read | PermAmount::Write => true,
+            PermAmount::Read | PermAmount::Write | PermAmount::Wildcard | PermAmount::Frac(..) => {
+                true
+            }
             PermAmount::Remaining => false,
         }
     }
+
+    /// The fraction of a full permission of a constant permission amount.
+    fn as_fraction(&self) -> Option<(u64, u64)> {
+        match self {
+            PermAmount::Write => Some((1, 1)),
+            PermAmount::Frac(numerator, denominator) => {
+                Some((u64::from(*numerator), u64::from(*denominator)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The permission amount of the fraction ``numerator / denominator``, if it is positive and
+    /// at most ``Write``.
+    fn from_fraction(numerator: u64, denominator: u64) -> Option<Self> {
+        if numerator == 0 || numerator > denominator {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        let numerator = numerator / divisor;
+        let denominator = denominator / divisor;
+        if numerator > u64::from(u32::MAX) || denominator > u64::from(u32::MAX) {
+            return None;
+        }
+        Some(PermAmount::frac(numerator as u32, denominator as u32))
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
 }
 
 impl fmt::Display for PermAmount {
@@ -87,6 +250,8 @@ impl fmt::Display for PermAmount {
             PermAmount::Read => write!(f, "read"),
             PermAmount::Write => write!(f, "write"),
             PermAmount::Remaining => write!(f, "write-read"),
+            PermAmount::Wildcard => write!(f, "wildcard"),
+            PermAmount::Frac(numerator, denominator) => write!(f, "{}/{}", numerator, denominator),
         }
     }
 }
@@ -98,7 +263,25 @@ impl ops::Add for PermAmount {
         match (self, other) {
             (PermAmount::Read, PermAmount::Remaining)
             | (PermAmount::Remaining, PermAmount::Read) => PermAmount::Write,
-            _ => unreachable!("Invalid addition: {} + {}", self, other),
+            // Adding a wildcard amount gives an unknown amount that is at least
+            // the other one, which we conservatively approximate by the latter.
+            (PermAmount::Wildcard, PermAmount::Wildcard) => PermAmount::Wildcard,
+            (PermAmount::Wildcard, PermAmount::Read)
+            | (PermAmount::Read, PermAmount::Wildcard) => PermAmount::Read,
+            (PermAmount::Wildcard, PermAmount::Remaining)
+            | (PermAmount::Remaining, PermAmount::Wildcard) => PermAmount::Remaining,
+            (PermAmount::Wildcard, PermAmount::Frac(..)) => other,
+            (PermAmount::Frac(..), PermAmount::Wildcard) => self,
+            _ => match (self.as_fraction(), other.as_fraction()) {
+                (Some((left_num, left_den)), Some((right_num, right_den))) => {
+                    PermAmount::from_fraction(
+                        left_num * right_den + right_num * left_den,
+                        left_den * right_den,
+                    )
+                    .unwrap_or_else(|| unreachable!("Invalid addition: {} + {}", self, other))
+                }
+                _ => unreachable!("Invalid addition: {} + {}", self, other),
+            },
         }
     }
 }
@@ -110,7 +293,21 @@ impl ops::Sub for PermAmount {
         match (self, other) {
             (PermAmount::Write, PermAmount::Read) => PermAmount::Remaining,
             (PermAmount::Write, PermAmount::Remaining) => PermAmount::Read,
-            _ => unreachable!("Invalid subtraction: {} - {}", self, other),
+            // Subtracting a wildcard amount never exhausts a permission, so the
+            // minuend is kept.
+            (_, PermAmount::Wildcard) => self,
+            _ => match (self.as_fraction(), other.as_fraction()) {
+                (Some((left_num, left_den)), Some((right_num, right_den)))
+                    if left_num * right_den > right_num * left_den =>
+                {
+                    PermAmount::from_fraction(
+                        left_num * right_den - right_num * left_den,
+                        left_den * right_den,
+                    )
+                    .unwrap()
+                }
+                _ => unreachable!("Invalid subtraction: {} - {}", self, other),
+            },
         }
     }
 }
@@ -119,11 +316,28 @@ impl Ord for PermAmount {
     fn cmp(&self, other: &PermAmount) -> Ordering {
         match (self, other) {
             (PermAmount::Read, PermAmount::Write) => Ordering::Less,
-            (PermAmount::Read, PermAmount::Read) | (PermAmount::Write, PermAmount::Write) => {
-                Ordering::Equal
-            }
+            (PermAmount::Read, PermAmount::Read)
+            | (PermAmount::Write, PermAmount::Write)
+            | (PermAmount::Remaining, PermAmount::Remaining)
+            | (PermAmount::Wildcard, PermAmount::Wildcard) => Ordering::Equal,
             (PermAmount::Write, PermAmount::Read) => Ordering::Greater,
-            _ => unreachable!("self={} other={}", self, other),
+            // A wildcard amount is smaller than any other positive amount.
+            (PermAmount::Wildcard, PermAmount::Read)
+            | (PermAmount::Wildcard, PermAmount::Write)
+            | (PermAmount::Wildcard, PermAmount::Remaining) => Ordering::Less,
+            (PermAmount::Read, PermAmount::Wildcard)
+            | (PermAmount::Write, PermAmount::Wildcard)
+            | (PermAmount::Remaining, PermAmount::Wildcard) => Ordering::Greater,
+            (PermAmount::Wildcard, PermAmount::Frac(..)) => Ordering::Less,
+            (PermAmount::Frac(..), PermAmount::Wildcard) => Ordering::Greater,
+            // The constant amounts are compared as fractions. `Read` is an unknown fraction,
+            // which cannot be compared with them.
+            _ => match (self.as_fraction(), other.as_fraction()) {
+                (Some((left_num, left_den)), Some((right_num, right_den))) => {
+                    (left_num * right_den).cmp(&(right_num * left_den))
+                }
+                _ => unreachable!("self={} other={}", self, other),
+            },
         }
     }
 }
@@ -139,8 +353,52 @@ pub enum Type {
     Int,
     Bool,
     //Ref, // At the moment we don't need this
-    /// TypedRef: the first parameter is the name of the predicate that encodes the type
-    TypedRef(String),
+    /// TypedRef: the first parameter is the name of the predicate that encodes the type,
+    /// the second one is the kind of the encoded Rust type
+    TypedRef(String, TypedRefKind),
+    /// TypedMap: a mathematical map, with the types of its keys and of its values
+    TypedMap(Box<Type>, Box<Type>),
+    /// TypedSeq: a mathematical sequence, with the type of its elements
+    TypedSeq(Box<Type>),
+    /// Domain: a value of the Viper domain with the given name
+    Domain(String),
+}
+
+/// The kind of a Rust type that is encoded as a `Type::TypedRef`. It allows to inspect
+/// a VIR type without parsing the name of its predicate.
+#[derive(Debug, Clone)]
+pub enum TypedRefKind {
+    /// A reference, whose `val_ref` field has the given type
+    Reference(Box<Type>),
+    /// A box, whose `val_ref` field has the given type
+    Box(Box<Type>),
+    /// A struct or an enum, with the types of its type arguments
+    Adt(Vec<Type>),
+    /// A tuple, with the types of its elements
+    Tuple(Vec<Type>),
+    /// A type parameter, whose predicate is abstract. The type parameter is identified by the
+    /// name of its predicate, which is a part of the names of the types that contain it.
+    TypeVar,
+    /// Any other type, or a type that is not known where the VIR type is constructed
+    /// (e.g. the type of the fields declared in the Viper program)
+    Other,
+}
+
+impl TypedRefKind {
+    fn substitute(self, substs: &HashMap<String, Type>) -> Self {
+        let substitute_all = |types: Vec<Type>| -> Vec<Type> {
+            types.into_iter().map(|typ| typ.substitute(substs)).collect()
+        };
+        match self {
+            TypedRefKind::Reference(target) => {
+                TypedRefKind::Reference(box target.substitute(substs))
+            }
+            TypedRefKind::Box(target) => TypedRefKind::Box(box target.substitute(substs)),
+            TypedRefKind::Adt(args) => TypedRefKind::Adt(substitute_all(args)),
+            TypedRefKind::Tuple(elems) => TypedRefKind::Tuple(substitute_all(elems)),
+            kind => kind,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -148,6 +406,9 @@ pub enum TypeId {
     Int,
     Bool,
     Ref,
+    Map,
+    Seq,
+    Domain,
 }
 
 impl fmt::Display for Type {
@@ -156,51 +417,140 @@ impl fmt::Display for Type {
             &Type::Int => write!(f, "Int"),
             &Type::Bool => write!(f, "Bool"),
             //&Type::Ref => write!(f, "Ref"),
-            &Type::TypedRef(ref name) => write!(f, "Ref({})", name),
+            &Type::TypedRef(ref name, _) => write!(f, "Ref({})", name),
+            &Type::TypedMap(ref key, ref value) => write!(f, "Map[{}, {}]", key, value),
+            &Type::TypedSeq(ref elem) => write!(f, "Seq[{}]", elem),
+            &Type::Domain(ref name) => write!(f, "{}", name),
         }
     }
 }
 
 impl Type {
+    /// A `TypedRef` type whose Rust type is not known, e.g. because it is only used
+    /// to declare a local variable or a field of the Viper program.
+    pub fn typed_ref<S: Into<String>>(predicate_name: S) -> Self {
+        Type::TypedRef(predicate_name.into(), TypedRefKind::Other)
+    }
+
     pub fn is_ref(&self) -> bool {
         match self {
             //&Type::Ref |
-            &Type::TypedRef(_) => true,
+            &Type::TypedRef(..) => true,
             _ => false,
         }
     }
 
+    /// Does this type encode a Rust reference?
+    pub fn is_reference(&self) -> bool {
+        self.get_reference_target().is_some()
+    }
+
+    /// The type of the `val_ref` field of a Rust reference.
+    pub fn get_reference_target(&self) -> Option<&Type> {
+        match self {
+            &Type::TypedRef(_, TypedRefKind::Reference(box ref target)) => Some(target),
+            _ => None,
+        }
+    }
+
     pub fn name(&self) -> String {
         match self {
             &Type::Bool => "bool".to_string(),
             &Type::Int => "int".to_string(),
-            &Type::TypedRef(ref pred_name) => format!("{}", pred_name),
+            &Type::TypedRef(ref pred_name, _) => format!("{}", pred_name),
+            &Type::TypedMap(ref key, ref value) => {
+                format!("map${}${}", key.name(), value.name())
+            }
+            &Type::TypedSeq(ref elem) => format!("seq${}", elem.name()),
+            &Type::Domain(ref name) => name.clone(),
         }
     }
 
     /// Construct a new VIR type that corresponds to an enum variant.
     pub fn variant(self, variant: &str) -> Self {
         match self {
-            Type::TypedRef(mut name) => {
+            Type::TypedRef(mut name, kind) => {
                 name.push_str(variant);
-                Type::TypedRef(name)
+                Type::TypedRef(name, kind)
             }
             _ => unreachable!(),
         }
     }
 
-    /// FIXME: A hack. Replaces all generic types with their instantiations by using string
-    /// substitution.
-    pub fn patch(self, substs: &HashMap<String, String>) -> Self {
+    /// Replace the type variables of the type with their instantiations in `substs`, which
+    /// maps the names of the predicates of the type variables to types. The names of the
+    /// predicates of the types that contain a type variable are built from the name of its
+    /// predicate, thus they are instantiated by replacing it.
+    pub fn substitute(self, substs: &HashMap<String, Type>) -> Self {
         match self {
-            Type::Bool => Type::Bool,
-            Type::Int => Type::Int,
-            Type::TypedRef(mut predicate_name) => {
-                for (typ, subst) in substs {
-                    predicate_name = predicate_name.replace(typ, subst);
+            Type::TypedRef(name, TypedRefKind::TypeVar) => match substs.get(&name) {
+                Some(subst) => subst.clone(),
+                None => Type::TypedRef(name, TypedRefKind::TypeVar),
+            },
+            Type::TypedRef(mut name, kind) => {
+                for (type_var, subst) in substs {
+                    name = name.replace(type_var, &subst.name());
+                }
+                Type::TypedRef(name, kind.substitute(substs))
+            }
+            Type::TypedMap(key, value) => {
+                Type::TypedMap(box key.substitute(substs), box value.substitute(substs))
+            }
+            Type::TypedSeq(elem) => Type::TypedSeq(box elem.substitute(substs)),
+            typ => typ,
+        }
+    }
+
+    /// Learn the instantiations of the type variables of this type that yield the `concrete`
+    /// type, by matching the type arguments that are recorded in the kinds of the two types.
+    /// Returns `false` if the types do not match, or if the structure of a type that contains
+    /// type variables is not known.
+    pub fn learn_substs(&self, concrete: &Type, substs: &mut HashMap<String, Type>) -> bool {
+        fn learn_all(
+            types: &[Type],
+            concrete_types: &[Type],
+            substs: &mut HashMap<String, Type>,
+        ) -> bool {
+            types.len() == concrete_types.len()
+                && types
+                    .iter()
+                    .zip(concrete_types)
+                    .all(|(typ, concrete_typ)| typ.learn_substs(concrete_typ, substs))
+        }
+        match (self, concrete) {
+            (&Type::TypedRef(ref name, TypedRefKind::TypeVar), _) => {
+                if let Some(subst) = substs.get(name) {
+                    return subst.name() == concrete.name();
                 }
-                Type::TypedRef(predicate_name)
+                substs.insert(name.clone(), concrete.clone());
+                true
+            }
+            (
+                &Type::TypedRef(_, TypedRefKind::Reference(box ref target)),
+                &Type::TypedRef(_, TypedRefKind::Reference(box ref concrete_target)),
+            )
+            | (
+                &Type::TypedRef(_, TypedRefKind::Box(box ref target)),
+                &Type::TypedRef(_, TypedRefKind::Box(box ref concrete_target)),
+            )
+            | (&Type::TypedSeq(box ref target), &Type::TypedSeq(box ref concrete_target)) => {
+                target.learn_substs(concrete_target, substs)
             }
+            (
+                &Type::TypedRef(_, TypedRefKind::Adt(ref args)),
+                &Type::TypedRef(_, TypedRefKind::Adt(ref concrete_args)),
+            )
+            | (
+                &Type::TypedRef(_, TypedRefKind::Tuple(ref args)),
+                &Type::TypedRef(_, TypedRefKind::Tuple(ref concrete_args)),
+            ) => learn_all(args, concrete_args, substs),
+            (
+                &Type::TypedMap(box ref key, box ref value),
+                &Type::TypedMap(box ref concrete_key, box ref concrete_value),
+            ) => {
+                key.learn_substs(concrete_key, substs) && value.learn_substs(concrete_value, substs)
+            }
+            _ => self.name() == concrete.name(),
         }
     }
 
@@ -208,7 +558,10 @@ impl Type {
         match self {
             Type::Bool => TypeId::Bool,
             Type::Int => TypeId::Int,
-            Type::TypedRef(_) => TypeId::Ref,
+            Type::TypedRef(..) => TypeId::Ref,
+            Type::TypedMap(..) => TypeId::Map,
+            Type::TypedSeq(..) => TypeId::Seq,
+            Type::Domain(..) => TypeId::Domain,
         }
     }
 }
@@ -254,6 +607,9 @@ impl LocalVar {
     }
 }
 
+/// The prefix of the names of the fields that encode the components of a tuple.
+const TUPLE_FIELD_PREFIX: &str = "tuple_";
+
 #[derive(Clone)]
 pub struct Field {
     pub name: String,
@@ -280,9 +636,23 @@ impl Field {
         }
     }
 
+    /// The name of the field that encodes the component `index` of a tuple.
+    pub fn tuple_name(index: usize) -> String {
+        format!("{}{}", TUPLE_FIELD_PREFIX, index)
+    }
+
+    /// The index of the tuple component that the field encodes, if any.
+    pub fn tuple_index(&self) -> Option<usize> {
+        if self.name.starts_with(TUPLE_FIELD_PREFIX) {
+            self.name[TUPLE_FIELD_PREFIX.len()..].parse().ok()
+        } else {
+            None
+        }
+    }
+
     pub fn typed_ref_name(&self) -> Option<String> {
         match self.typ {
-            Type::TypedRef(ref name) => Some(name.clone()),
+            Type::TypedRef(ref name, _) => Some(name.clone()),
             _ => None,
         }
     }
@@ -0,0 +1,95 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Viper domains: uninterpreted sorts, with functions on them and axioms that constrain the
+//! functions. The values of a domain are of type `Type::Domain`.
+
+use encoder::vir::ast::*;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct Domain {
+    pub name: String,
+    pub functions: Vec<DomainFunc>,
+    pub axioms: Vec<DomainAxiom>,
+}
+
+/// A function of a domain. Unlike a `Function`, it does not depend on the heap and it has
+/// neither a body nor a contract: it is constrained only by the axioms of its domain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DomainFunc {
+    pub name: String,
+    pub formal_args: Vec<LocalVar>,
+    pub return_type: Type,
+    /// Do the nullary applications of distinct unique functions denote distinct values?
+    pub unique: bool,
+    pub domain_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DomainAxiom {
+    pub name: String,
+    pub expr: Expr,
+    pub domain_name: String,
+}
+
+impl Domain {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Domain {
+            name: name.into(),
+            functions: vec![],
+            axioms: vec![],
+        }
+    }
+
+    /// The type of the values of the domain.
+    pub fn get_type(&self) -> Type {
+        Type::Domain(self.name.clone())
+    }
+}
+
+impl WithIdentifier for Domain {
+    fn get_identifier(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "domain {} {{", self.name)?;
+        for function in &self.functions {
+            writeln!(f, "  {}", function)?;
+        }
+        for axiom in &self.axioms {
+            writeln!(f, "  {}", axiom)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for DomainFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unique {
+            write!(f, "unique ")?;
+        }
+        write!(f, "function {}(", self.name)?;
+        let mut first = true;
+        for arg in &self.formal_args {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}", arg)?;
+            first = false
+        }
+        write!(f, "): {}", self.return_type)
+    }
+}
+
+impl fmt::Display for DomainAxiom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "axiom {} {{ {} }}", self.name, self.expr)
+    }
+}
@@ -0,0 +1,526 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Queries on VIR expressions: the structure of places, their types, and the permissions
+//! mentioned in an expression.
+
+use encoder::vir::ast::*;
+
+impl Expr {
+    pub fn is_place(&self) -> bool {
+        match self {
+            &Expr::Local(_, _) => true,
+            &Expr::Variant(ref base, _, _)
+            | &Expr::Field(ref base, _, _)
+            | &Expr::AddrOf(ref base, _, _)
+            | &Expr::LabelledOld(_, ref base, _)
+            | &Expr::Unfolding(_, _, ref base, _, _, _) => base.is_place(),
+            _ => false,
+        }
+    }
+
+    /// Is this a field of a location returned by a domain function, e.g. the value at an
+    /// address of a memory region owned through a raw pointer? Such a location is not a
+    /// place, thus it is ignored by the fold-unfold algorithm.
+    pub fn is_domain_location(&self) -> bool {
+        match self {
+            Expr::Field(box Expr::DomainFuncApp(..), _, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_simple_place(&self) -> bool {
+        match self {
+            &Expr::Local(_, _) => true,
+            &Expr::Variant(ref base, _, _) | &Expr::Field(ref base, _, _) => base.is_simple_place(),
+            _ => false,
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        match self {
+            &Expr::Local(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_variant(&self) -> bool {
+        match self {
+            Expr::Variant(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_addr_of(&self) -> bool {
+        match self {
+            &Expr::AddrOf(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Is this place a MIR reference?
+    pub fn is_mir_reference(&self) -> bool {
+        debug_assert!(self.is_place());
+        if let Expr::Field(box Expr::Local(LocalVar { typ, .. }, _), _, _) = self {
+            return typ.is_reference();
+        }
+        false
+    }
+
+    /// How many parts this place has? Used for ordering places.
+    pub fn place_depth(&self) -> u32 {
+        match self {
+            &Expr::Local(_, _) => 1,
+            &Expr::Variant(ref base, _, _)
+            | &Expr::Field(ref base, _, _)
+            | &Expr::AddrOf(ref base, _, _)
+            | &Expr::LabelledOld(_, ref base, _)
+            | &Expr::Unfolding(_, _, ref base, _, _, _) => base.place_depth() + 1,
+            x => unreachable!("{:?}", x),
+        }
+    }
+
+    /// Only defined for places
+    pub fn get_parent_ref(&self) -> Option<&Expr> {
+        debug_assert!(self.is_place());
+        match self {
+            &Expr::Local(_, _) => None,
+            &Expr::Variant(box ref base, _, _)
+            | &Expr::Field(box ref base, _, _)
+            | &Expr::AddrOf(box ref base, _, _) => Some(base),
+            &Expr::LabelledOld(_, _, _) => None,
+            &Expr::Unfolding(_, _, _, _, _, _) => None,
+            ref x => unreachable!("{}", x),
+        }
+    }
+
+    /// Only defined for places
+    pub fn get_parent(&self) -> Option<Expr> {
+        self.get_parent_ref().cloned()
+    }
+
+    /// Only defined for places
+    pub fn get_base(&self) -> LocalVar {
+        debug_assert!(self.is_place());
+        match self {
+            &Expr::Local(ref var, _) => var.clone(),
+            &Expr::LabelledOld(_, ref base, _) |
+            &Expr::Unfolding(_, _, ref base, _, _, _) => {
+                base.get_base()
+            }
+            _ => self.get_parent().unwrap().get_base(),
+        }
+    }
+
+    pub fn get_label(&self) -> Option<&String> {
+        match self {
+            &Expr::LabelledOld(ref label, _, _) => Some(label),
+            _ => None,
+        }
+    }
+
+    pub fn is_old(&self) -> bool {
+        self.get_label().is_some()
+    }
+
+    pub fn is_curr(&self) -> bool {
+        !self.is_old()
+    }
+
+    /// If self is a MIR reference, dereference it.
+    pub fn try_deref(&self) -> Option<Self> {
+        self.get_type().get_reference_target().map(|target_type| {
+            let field = Field::new("val_ref", target_type.clone());
+            self.clone().field(field)
+        })
+    }
+
+    /// Split place into place components.
+    pub fn explode_place(&self) -> (Expr, Vec<PlaceComponent>) {
+        match self {
+            Expr::Variant(ref base, ref variant, ref pos) => {
+                let (base_base, mut components) = base.explode_place();
+                components.push(PlaceComponent::Variant(variant.clone(), pos.clone()));
+                (base_base, components)
+            }
+            Expr::Field(ref base, ref field, ref pos) => {
+                let (base_base, mut components) = base.explode_place();
+                components.push(PlaceComponent::Field(field.clone(), pos.clone()));
+                (base_base, components)
+            }
+            _ => (self.clone(), vec![]),
+        }
+    }
+
+    /// Reconstruct place from the place components.
+    pub fn reconstruct_place(self, components: Vec<PlaceComponent>) -> Expr {
+        components
+            .into_iter()
+            .fold(self, |acc, component| match component {
+                PlaceComponent::Variant(variant, pos) => Expr::Variant(box acc, variant, pos),
+                PlaceComponent::Field(field, pos) => Expr::Field(box acc, field, pos),
+            })
+    }
+
+    pub fn has_proper_prefix(&self, other: &Expr) -> bool {
+        debug_assert!(self.is_place(), "self={} other={}", self, other);
+        debug_assert!(other.is_place(), "self={} other={}", self, other);
+        self != other && self.has_prefix(other)
+    }
+
+    pub fn has_prefix(&self, other: &Expr) -> bool {
+        debug_assert!(self.is_place());
+        debug_assert!(other.is_place());
+        if self == other {
+            true
+        } else {
+            match self.get_parent() {
+                Some(parent) => parent.has_prefix(other),
+                None => false,
+            }
+        }
+    }
+
+    pub fn all_proper_prefixes(&self) -> Vec<Expr> {
+        debug_assert!(self.is_place());
+        match self.get_parent() {
+            Some(parent) => parent.all_prefixes(),
+            None => vec![],
+        }
+    }
+
+    // Returns all prefixes, from the shortest to the longest
+    pub fn all_prefixes(&self) -> Vec<Expr> {
+        debug_assert!(self.is_place());
+        let mut res = self.all_proper_prefixes();
+        res.push(self.clone());
+        res
+    }
+
+    pub fn get_type(&self) -> &Type {
+        debug_assert!(self.is_place());
+        match self {
+            &Expr::Local(LocalVar { ref typ, .. }, _)
+            | &Expr::Variant(_, Field { ref typ, .. }, _)
+            | &Expr::Field(_, Field { ref typ, .. }, _)
+            | &Expr::AddrOf(_, ref typ, _) => {
+                &typ
+            },
+            &Expr::LabelledOld(_, box ref base, _)
+            | &Expr::Unfolding(_, _, box ref base, _, _, _) => {
+                base.get_type()
+            }
+            _ => panic!(),
+        }
+    }
+
+    pub fn typed_ref_name(&self) -> Option<String> {
+        match self.get_type() {
+            &Type::TypedRef(ref name, _) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn local_type(&self) -> String {
+        match &self {
+            Expr::Local(localvar, _) => match &localvar.typ {
+                Type::TypedRef(str, _) => str.clone(),
+                _ => panic!("expected Type::TypedRef"),
+            },
+            _ => panic!("expected Expr::Local"),
+        }
+    }
+
+    /// If returns true, then the expression is guaranteed to be boolean. However, it may return
+    /// false even for boolean expressions.
+    pub fn is_bool(&self) -> bool {
+        if self.is_place() {
+            self.get_type() == &Type::Bool
+        } else {
+            match self {
+                Expr::Const(Const::Bool(_), _) |
+                Expr::UnaryOp(UnaryOpKind::Not, _, _) |
+                Expr::FuncApp(_, _, _, Type::Bool, _) |
+                Expr::DomainFuncApp(DomainFunc { return_type: Type::Bool, .. }, _, _) |
+                Expr::MapContains(..) |
+                Expr::ForAll(..) |
+                Expr::Exists(..) => {
+                    true
+                },
+                Expr::BinOp(kind, _, _, _) => {
+                    use encoder::vir::ast::BinOpKind::*;
+                    *kind == EqCmp || *kind == NeCmp ||
+                    *kind == GtCmp || *kind == GeCmp || *kind == LtCmp || *kind == LeCmp ||
+                    *kind == And || *kind == Or || *kind == Implies
+                },
+                _ => {
+                    false
+                }
+            }
+        }
+    }
+
+    /// The types of the keys and of the values of an expression of type `Type::TypedMap`.
+    pub fn get_map_types(&self) -> (&Type, &Type) {
+        let typ = match self {
+            &Expr::FuncApp(_, _, _, ref typ, _) => typ,
+            &Expr::MapUpdate(box ref map, _, _, _)
+            | &Expr::LabelledOld(_, box ref map, _)
+            | &Expr::Unfolding(_, _, box ref map, _, _, _)
+            | &Expr::Cond(_, box ref map, _, _)
+            | &Expr::LetExpr(_, _, box ref map, _) => return map.get_map_types(),
+            _ => self.get_type(),
+        };
+        match typ {
+            &Type::TypedMap(box ref key, box ref value) => (key, value),
+            ref x => panic!("{} is not of a map type", x),
+        }
+    }
+
+    pub fn is_only_permissions(&self) -> bool {
+        match self {
+            Expr::PredicateAccessPredicate(..) |
+            Expr::FieldAccessPredicate(..) => true,
+            Expr::BinOp(BinOpKind::And, box lhs, box rhs, _) => {
+                lhs.is_only_permissions() && rhs.is_only_permissions()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_pure(&self) -> bool {
+        struct PurityFinder {
+            non_pure: bool,
+        }
+        impl ExprWalker for PurityFinder {
+            fn walk_predicate_access_predicate(
+                &mut self,
+                _name: &str,
+                _arg: &Expr,
+                _perm_amount: PermAmount,
+                _pos: &Position
+            ) {
+                self.non_pure = true;
+            }
+            fn walk_field_access_predicate(
+                &mut self,
+                _receiver: &Expr,
+                _perm_amount: PermAmount,
+                _pos: &Position
+            ) {
+                self.non_pure = true;
+            }
+        }
+        let mut walker = PurityFinder { non_pure: false };
+        walker.walk(self);
+        !walker.non_pure
+    }
+
+    /// Does the value of the expression not depend on the heap? It then neither accesses
+    /// fields, nor applies functions, nor mentions permissions.
+    pub fn is_heap_independent(&self) -> bool {
+        struct HeapAccessFinder {
+            found: bool,
+        }
+        impl ExprWalker for HeapAccessFinder {
+            fn walk_field(&mut self, _receiver: &Expr, _field: &Field, _pos: &Position) {
+                self.found = true;
+            }
+            fn walk_labelled_old(&mut self, _label: &str, _body: &Expr, _pos: &Position) {
+                self.found = true;
+            }
+            fn walk_predicate_access_predicate(
+                &mut self,
+                _name: &str,
+                _arg: &Expr,
+                _perm_amount: PermAmount,
+                _pos: &Position
+            ) {
+                self.found = true;
+            }
+            fn walk_field_access_predicate(
+                &mut self,
+                _receiver: &Expr,
+                _perm_amount: PermAmount,
+                _pos: &Position
+            ) {
+                self.found = true;
+            }
+            fn walk_unfolding(
+                &mut self,
+                _name: &str,
+                _args: &Vec<Expr>,
+                _body: &Expr,
+                _perm: PermAmount,
+                _variant: &MaybeEnumVariantIndex,
+                _pos: &Position
+            ) {
+                self.found = true;
+            }
+            fn walk_func_app(
+                &mut self,
+                _name: &str,
+                _args: &Vec<Expr>,
+                _formal_args: &Vec<LocalVar>,
+                _return_type: &Type,
+                _pos: &Position
+            ) {
+                self.found = true;
+            }
+        }
+        let mut walker = HeapAccessFinder { found: false };
+        walker.walk(self);
+        !walker.found
+    }
+
+    pub fn get_place(&self) -> Option<&Expr> {
+        match self {
+            Expr::PredicateAccessPredicate(_, ref arg, _, _) => Some(arg),
+            Expr::FieldAccessPredicate(box ref arg, _, _) => Some(arg),
+            _ => None,
+        }
+    }
+
+    pub fn get_perm_amount(&self) -> PermAmount {
+        match self {
+            Expr::PredicateAccessPredicate(_, _, perm_amount, _) => *perm_amount,
+            Expr::FieldAccessPredicate(_, perm_amount, _) => *perm_amount,
+            x => unreachable!("{}", x),
+        }
+    }
+
+    pub fn find(&self, sub_target: &Expr) -> bool {
+        pub struct ExprFinder<'a> {
+            sub_target: &'a Expr,
+            found: bool,
+        }
+        impl<'a> ExprWalker for ExprFinder<'a> {
+            fn walk(&mut self, expr: &Expr) {
+                if expr == self.sub_target || (expr.is_place() && expr == self.sub_target) {
+                    self.found = true;
+                } else {
+                    default_walk_expr(self, expr)
+                }
+            }
+        }
+
+        let mut finder = ExprFinder {
+            sub_target,
+            found: false,
+        };
+        finder.walk(self);
+        finder.found
+    }
+
+    /// Extract all predicates places mentioned in the expression whose predicates have the given
+    /// permission amount.
+    pub fn extract_predicate_places(&self, perm_amount: PermAmount) -> Vec<Expr> {
+        pub struct PredicateFinder {
+            predicates: Vec<Expr>,
+            perm_amount: PermAmount,
+        }
+        impl ExprWalker for PredicateFinder {
+            fn walk_predicate_access_predicate(
+                &mut self,
+                _name: &str,
+                arg: &Expr,
+                perm_amount: PermAmount,
+                _pos: &Position
+            ) {
+                if perm_amount == self.perm_amount {
+                    self.predicates.push(arg.clone());
+                }
+            }
+        }
+
+        let mut finder = PredicateFinder {
+            predicates: Vec::new(),
+            perm_amount: perm_amount,
+        };
+        finder.walk(self);
+        finder.predicates
+    }
+
+    /// Compute the permissions that are needed for this expression to
+    /// be successfully evaluated. This is method is used for `fold` and
+    /// `exhale` statements inside `package` statements because Silicon
+    /// fails to compute which permissions it should take into the magic
+    /// wand.
+    pub fn compute_footprint(&self, perm_amount: PermAmount) -> Vec<Expr> {
+        struct Collector {
+            perm_amount: PermAmount,
+            perms: Vec<Expr>,
+        }
+        impl ExprWalker for Collector {
+            fn walk_variant(&mut self, e: &Expr, v: &Field, p: &Position) {
+                self.walk(e);
+                let expr = Expr::Variant(box e.clone(), v.clone(), p.clone());
+                let perm = Expr::acc_permission(expr, self.perm_amount);
+                self.perms.push(perm);
+            }
+            fn walk_field(&mut self, e: &Expr, f: &Field, p: &Position) {
+                self.walk(e);
+                let expr = Expr::Field(box e.clone(), f.clone(), p.clone());
+                let perm = Expr::acc_permission(expr, self.perm_amount);
+                self.perms.push(perm);
+            }
+            fn walk_labelled_old(&mut self, _label: &str, _expr: &Expr, _pos: &Position) {
+                // Stop recursion.
+            }
+        }
+        let mut collector = Collector {
+            perm_amount: perm_amount,
+            perms: Vec::new(),
+        };
+        collector.walk(self);
+        collector.perms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The places `x`, `x.f` and `x.f.g`.
+    fn places() -> (Expr, Expr, Expr) {
+        let x = Expr::local(LocalVar::new("x", Type::typed_ref("T")));
+        let x_f = x.clone().field(Field::new("f", Type::typed_ref("U")));
+        let x_f_g = x_f.clone().field(Field::new("g", Type::Int));
+        (x, x_f, x_f_g)
+    }
+
+    #[test]
+    fn prefixes_of_a_place() {
+        let (x, x_f, x_f_g) = places();
+        assert_eq!(x_f_g.place_depth(), 3);
+        assert!(x_f_g.has_proper_prefix(&x));
+        assert!(!x.has_proper_prefix(&x));
+        assert_eq!(x_f_g.all_prefixes(), vec![x.clone(), x_f, x_f_g.clone()]);
+        assert_eq!(x_f_g.get_base(), LocalVar::new("x", Type::typed_ref("T")));
+    }
+
+    #[test]
+    fn explode_and_reconstruct_a_place() {
+        let (x, _, x_f_g) = places();
+        let (base, components) = x_f_g.explode_place();
+        assert_eq!(base, x);
+        assert_eq!(components.len(), 2);
+        assert_eq!(base.reconstruct_place(components), x_f_g);
+    }
+
+    #[test]
+    fn permissions_of_an_expression() {
+        let (x, x_f, x_f_g) = places();
+        let expr = Expr::and(
+            Expr::acc_permission(x_f, PermAmount::Read),
+            Expr::predicate_access_predicate("T", x.clone(), PermAmount::Read),
+        );
+        assert!(expr.is_only_permissions());
+        assert!(!expr.is_pure());
+        assert_eq!(expr.extract_predicate_places(PermAmount::Read), vec![x]);
+        assert!(Expr::gt_cmp(x_f_g, 0.into()).is_pure());
+    }
+}
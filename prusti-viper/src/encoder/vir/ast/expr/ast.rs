@@ -0,0 +1,213 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The definition of VIR expressions, and their positions.
+
+use encoder::vir::ast::*;
+use encoder::vir::borrows::Borrow;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// A local var
+    Local(LocalVar, Position),
+    /// An enum variant: base, variant index.
+    Variant(Box<Expr>, Field, Position),
+    /// A field access
+    Field(Box<Expr>, Field, Position),
+    /// The inverse of a `val_ref` field access
+    AddrOf(Box<Expr>, Type, Position),
+    LabelledOld(String, Box<Expr>, Position),
+    Const(Const, Position),
+    /// lhs, rhs, borrow, position
+    MagicWand(Box<Expr>, Box<Expr>, Option<Borrow>, Position),
+    /// PredicateAccessPredicate: predicate_name, arg, permission amount
+    PredicateAccessPredicate(String, Box<Expr>, PermAmount, Position),
+    FieldAccessPredicate(Box<Expr>, PermAmount, Position),
+    UnaryOp(UnaryOpKind, Box<Expr>, Position),
+    BinOp(BinOpKind, Box<Expr>, Box<Expr>, Position),
+    /// Unfolding: predicate name, predicate_args, in_expr, permission amount, enum variant
+    Unfolding(String, Vec<Expr>, Box<Expr>, PermAmount, MaybeEnumVariantIndex, Position),
+    /// Cond: guard, then_expr, else_expr
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>, Position),
+    /// ForAll: variables, triggers, body
+    ForAll(Vec<LocalVar>, Vec<Trigger>, Box<Expr>, Position),
+    /// Exists: variables, triggers, body
+    Exists(Vec<LocalVar>, Vec<Trigger>, Box<Expr>, Position),
+    /// let variable == (expr) in body
+    LetExpr(LocalVar, Box<Expr>, Box<Expr>, Position),
+    /// FuncApp: function_name, args, formal_args, return_type, Viper position
+    FuncApp(String, Vec<Expr>, Vec<LocalVar>, Type, Position),
+    /// DomainFuncApp: function of a domain, args
+    DomainFuncApp(DomainFunc, Vec<Expr>, Position),
+    /// MapLookup: map, key
+    MapLookup(Box<Expr>, Box<Expr>, Position),
+    /// MapContains: map, key
+    MapContains(Box<Expr>, Box<Expr>, Position),
+    /// MapUpdate: map, key, value
+    MapUpdate(Box<Expr>, Box<Expr>, Box<Expr>, Position),
+    /// MapLen: map
+    MapLen(Box<Expr>, Position),
+    /// SeqLen: sequence
+    SeqLen(Box<Expr>, Position),
+    /// SeqIndex: sequence, index
+    SeqIndex(Box<Expr>, Box<Expr>, Position),
+}
+
+/// A component that can be used to represent a place as a vector.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlaceComponent {
+    Field(Field, Position),
+    Variant(Field, Position),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOpKind {
+    Not,
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOpKind {
+    EqCmp,
+    NeCmp,
+    GtCmp,
+    GeCmp,
+    LtCmp,
+    LeCmp,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Implies,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Const {
+    Bool(bool),
+    Int(i64),
+    BigInt(String),
+    /// Str: the content of a string literal, a sequence of bytes
+    Str(String),
+}
+
+impl Expr {
+    pub fn pos(&self) -> &Position {
+        match self {
+            Expr::Local(_, ref p) => p,
+            Expr::Variant(_, _, ref p) => p,
+            Expr::Field(_, _, ref p) => p,
+            Expr::AddrOf(_, _, ref p) => p,
+            Expr::Const(_, ref p) => p,
+            Expr::LabelledOld(_, _, ref p) => p,
+            Expr::MagicWand(_, _, _, ref p) => p,
+            Expr::PredicateAccessPredicate(_, _, _, ref p) => p,
+            Expr::FieldAccessPredicate(_, _, ref p) => p,
+            Expr::UnaryOp(_, _, ref p) => p,
+            Expr::BinOp(_, _, _, ref p) => p,
+            Expr::Unfolding(_, _, _, _, _, ref p) => p,
+            Expr::Cond(_, _, _, ref p) => p,
+            Expr::ForAll(_, _, _, ref p) => p,
+            Expr::Exists(_, _, _, ref p) => p,
+            Expr::LetExpr(_, _, _, ref p) => p,
+            Expr::FuncApp(_, _, _, _, ref p) => p,
+            Expr::DomainFuncApp(_, _, ref p) => p,
+            Expr::MapLookup(_, _, ref p) => p,
+            Expr::MapContains(_, _, ref p) => p,
+            Expr::MapUpdate(_, _, _, ref p) => p,
+            Expr::MapLen(_, ref p) => p,
+            Expr::SeqLen(_, ref p) => p,
+            Expr::SeqIndex(_, _, ref p) => p,
+        }
+    }
+
+    pub fn set_pos(self, pos: Position) -> Self {
+        match self {
+            Expr::Local(v, _) => Expr::Local(v, pos),
+            Expr::Variant(base, variant_index, _) => Expr::Variant(base, variant_index, pos),
+            Expr::Field(e, f, _) => Expr::Field(e, f, pos),
+            Expr::AddrOf(e, t, _) => Expr::AddrOf(e, t, pos),
+            Expr::Const(x, _) => Expr::Const(x, pos),
+            Expr::LabelledOld(x, y, _) => Expr::LabelledOld(x, y, pos),
+            Expr::MagicWand(x, y, b, _) => Expr::MagicWand(x, y, b, pos),
+            Expr::PredicateAccessPredicate(x, y, z, _) => {
+                Expr::PredicateAccessPredicate(x, y, z, pos)
+            }
+            Expr::FieldAccessPredicate(x, y, _) => Expr::FieldAccessPredicate(x, y, pos),
+            Expr::UnaryOp(x, y, _) => Expr::UnaryOp(x, y, pos),
+            Expr::BinOp(x, y, z, _) => Expr::BinOp(x, y, z, pos),
+            Expr::Unfolding(x, y, z, perm, variant, _) => {
+                Expr::Unfolding(x, y, z, perm, variant, pos)
+            },
+            Expr::Cond(x, y, z, _) => Expr::Cond(x, y, z, pos),
+            Expr::ForAll(x, y, z, _) => Expr::ForAll(x, y, z, pos),
+            Expr::Exists(x, y, z, _) => Expr::Exists(x, y, z, pos),
+            Expr::LetExpr(x, y, z, _) => Expr::LetExpr(x, y, z, pos),
+            Expr::FuncApp(x, y, z, k, _) => Expr::FuncApp(x, y, z, k, pos),
+            Expr::DomainFuncApp(x, y, _) => Expr::DomainFuncApp(x, y, pos),
+            Expr::MapLookup(x, y, _) => Expr::MapLookup(x, y, pos),
+            Expr::MapContains(x, y, _) => Expr::MapContains(x, y, pos),
+            Expr::MapUpdate(x, y, z, _) => Expr::MapUpdate(x, y, z, pos),
+            Expr::MapLen(x, _) => Expr::MapLen(x, pos),
+            Expr::SeqLen(x, _) => Expr::SeqLen(x, pos),
+            Expr::SeqIndex(x, y, _) => Expr::SeqIndex(x, y, pos),
+        }
+    }
+
+    // Replace all Position::default() positions with `pos`
+    pub fn set_default_pos(self, pos: Position) -> Self {
+        struct DefaultPosReplacer {
+            new_pos: Position,
+        };
+        impl ExprFolder for DefaultPosReplacer {
+            fn fold(&mut self, e: Expr) -> Expr {
+                let expr = default_fold_expr(self, e);
+                if expr.pos().is_default() {
+                    expr.set_pos(self.new_pos.clone())
+                } else {
+                    expr
+                }
+            }
+        }
+        DefaultPosReplacer { new_pos: pos }.fold(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: i32) -> Position {
+        Position::new(line, 1, PositionId::new(line as u32))
+    }
+
+    #[test]
+    fn set_pos_replaces_only_the_outer_position() {
+        let x = Expr::local(LocalVar::new("x", Type::Int));
+        let expr = Expr::add(x, 1.into()).set_pos(position(1));
+        assert_eq!(expr.pos(), &position(1));
+        match expr {
+            Expr::BinOp(_, box left, _, _) => assert!(left.pos().is_default()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn set_default_pos_keeps_the_other_positions() {
+        let x = Expr::local(LocalVar::new("x", Type::Int)).set_pos(position(1));
+        let expr = Expr::add(x, 1.into()).set_default_pos(position(2));
+        assert_eq!(expr.pos(), &position(2));
+        match expr {
+            Expr::BinOp(_, box left, box right, _) => {
+                assert_eq!(left.pos(), &position(1));
+                assert_eq!(right.pos(), &position(2));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Constructors of VIR expressions.
+//!
+//! Places are built from a local variable with `Expr::local`, followed by `field`, `variant`,
+//! `addr_of` and `old`. Operators and quantifiers are built with the associated functions named
+//! after them (`Expr::and`, `Expr::gt_cmp`, `Expr::forall`, ...), and a sequence of expressions
+//! is combined with `ExprIterator::conjoin` or `ExprIterator::disjoin`. For example,
+//! `x.f > 0 && old[pre](x.f) == 1` is built as follows:
+//!
+//! ```rust,ignore
+//! let x_f = Expr::local(LocalVar::new("x", Type::typed_ref("T"))).field(f);
+//! vec![
+//!     Expr::gt_cmp(x_f.clone(), 0.into()),
+//!     Expr::eq_cmp(x_f.old("pre"), 1.into()),
+//! ].into_iter().conjoin()
+//! ```
+//!
+//! The built expressions have the default position. It is later replaced by `set_default_pos`
+//! or by the position fixer, so that the verification errors can be reported on the Rust code.
+
+use encoder::vir::ast::*;
+use encoder::vir::borrows::Borrow;
+use std::fmt;
+
+impl Expr {
+    pub fn local(local: LocalVar) -> Self {
+        Expr::Local(local, Position::default())
+    }
+
+    pub fn variant(self, index: &str) -> Self {
+        assert!(self.is_place());
+        let field_name = format!("enum_{}", index);
+        let typ = self.get_type();
+        let variant = Field::new(field_name, typ.clone().variant(index));
+        Expr::Variant(box self, variant, Position::default())
+    }
+
+    pub fn field(self, field: Field) -> Self {
+        Expr::Field(box self, field, Position::default())
+    }
+
+    pub fn addr_of(self) -> Self {
+        let typ = match self.get_type() {
+            typ @ &Type::TypedRef(..) => typ.clone(),
+            typ => Type::typed_ref(typ.name()),
+        };
+        Expr::AddrOf(box self, typ, Position::default())
+    }
+
+    /// Puts an `old[label](..)` around the expression
+    pub fn old<S: fmt::Display + ToString>(self, label: S) -> Self {
+        match self {
+            Expr::Local(..) => {
+                /*
+                debug!(
+                    "Trying to put an old expression 'old[{}](..)' around {}, which is a local variable",
+                    label,
+                    self
+                );
+                */
+                self
+            }
+            Expr::LabelledOld(..) => {
+                /*
+                debug!(
+                    "Trying to put an old expression 'old[{}](..)' around {}, which already has a label",
+                    label,
+                    self
+                );
+                */
+                self
+            }
+            _ => Expr::LabelledOld(label.to_string(), box self, Position::default()),
+        }
+    }
+
+    pub fn predicate_access_predicate<S: ToString>(name: S, place: Expr, perm: PermAmount) -> Self {
+        let pos = place.pos().clone();
+        Expr::PredicateAccessPredicate(name.to_string(), box place, perm, pos)
+    }
+
+    pub fn pred_permission(place: Expr, perm: PermAmount) -> Option<Self> {
+        place
+            .typed_ref_name()
+            .map(|pred_name| Expr::predicate_access_predicate(pred_name, place, perm))
+    }
+
+    pub fn acc_permission(place: Expr, perm: PermAmount) -> Self {
+        Expr::FieldAccessPredicate(box place, perm, Position::default())
+    }
+
+    pub fn labelled_old(label: &str, expr: Expr) -> Self {
+        Expr::LabelledOld(label.to_string(), box expr, Position::default())
+    }
+
+    pub fn not(expr: Expr) -> Self {
+        Expr::UnaryOp(UnaryOpKind::Not, box expr, Position::default())
+    }
+
+    pub fn minus(expr: Expr) -> Self {
+        Expr::UnaryOp(UnaryOpKind::Minus, box expr, Position::default())
+    }
+
+    pub fn gt_cmp(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::GtCmp, box left, box right, Position::default())
+    }
+
+    pub fn ge_cmp(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::GeCmp, box left, box right, Position::default())
+    }
+
+    pub fn lt_cmp(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::LtCmp, box left, box right, Position::default())
+    }
+
+    pub fn le_cmp(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::LeCmp, box left, box right, Position::default())
+    }
+
+    pub fn eq_cmp(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::EqCmp, box left, box right, Position::default())
+    }
+
+    pub fn ne_cmp(left: Expr, right: Expr) -> Self {
+        Expr::not(Expr::eq_cmp(left, right))
+    }
+
+    pub fn add(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Add, box left, box right, Position::default())
+    }
+
+    pub fn sub(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Sub, box left, box right, Position::default())
+    }
+
+    pub fn mul(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Mul, box left, box right, Position::default())
+    }
+
+    pub fn div(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Div, box left, box right, Position::default())
+    }
+
+    pub fn modulo(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Mod, box left, box right, Position::default())
+    }
+
+    /// Encode Rust reminder. This is *not* Viper modulo.
+    pub fn rem(left: Expr, right: Expr) -> Self {
+        let abs_right = Expr::ite(
+            Expr::ge_cmp(right.clone(), 0.into()),
+            right.clone(),
+            Expr::minus(right.clone()),
+        );
+        Expr::ite(
+            Expr::or(
+                Expr::ge_cmp(left.clone(), 0.into()),
+                Expr::eq_cmp(Expr::modulo(left.clone(), right.clone()), 0.into()),
+            ),
+            // positive value or left % right == 0
+            Expr::modulo(left.clone(), right.clone()),
+            // negative value
+            Expr::sub(Expr::modulo(left, right), abs_right),
+        )
+    }
+
+    pub fn and(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::And, box left, box right, Position::default())
+    }
+
+    pub fn or(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Or, box left, box right, Position::default())
+    }
+
+    pub fn xor(left: Expr, right: Expr) -> Self {
+        Expr::not(Expr::eq_cmp(left, right))
+    }
+
+    pub fn implies(left: Expr, right: Expr) -> Self {
+        Expr::BinOp(BinOpKind::Implies, box left, box right, Position::default())
+    }
+
+    pub fn forall(vars: Vec<LocalVar>, triggers: Vec<Trigger>, body: Expr) -> Self {
+        Expr::ForAll(vars, triggers, box body, Position::default())
+    }
+
+    pub fn exists(vars: Vec<LocalVar>, triggers: Vec<Trigger>, body: Expr) -> Self {
+        Expr::Exists(vars, triggers, box body, Position::default())
+    }
+
+    pub fn let_expr(variable: LocalVar, expr: Expr, body: Expr) -> Self {
+        Expr::LetExpr(variable, box expr, box body, Position::default())
+    }
+
+    pub fn ite(guard: Expr, left: Expr, right: Expr) -> Self {
+        Expr::Cond(box guard, box left, box right, Position::default())
+    }
+
+    pub fn unfolding(
+        pred_name: String,
+        args: Vec<Expr>,
+        expr: Expr,
+        perm: PermAmount,
+        variant: MaybeEnumVariantIndex,
+    ) -> Self {
+        Expr::Unfolding(pred_name, args, box expr, perm, variant, Position::default())
+    }
+
+    /// Create `unfolding T(arg) in body` where `T` is the type of `arg`.
+    pub fn wrap_in_unfolding(arg: Expr, body: Expr) -> Expr {
+        let type_name = arg.get_type().name();
+        let pos = body.pos().clone();
+        Expr::Unfolding(type_name, vec![arg], box body, PermAmount::Read, None, pos)
+    }
+
+    pub fn func_app(
+        name: String,
+        args: Vec<Expr>,
+        internal_args: Vec<LocalVar>,
+        return_type: Type,
+        pos: Position,
+    ) -> Self {
+        Expr::FuncApp(name, args, internal_args, return_type, pos)
+    }
+
+    pub fn domain_func_app(func: DomainFunc, args: Vec<Expr>) -> Self {
+        Expr::DomainFuncApp(func, args, Position::default())
+    }
+
+    pub fn map_lookup(map: Expr, key: Expr) -> Self {
+        Expr::MapLookup(box map, box key, Position::default())
+    }
+
+    pub fn map_contains(map: Expr, key: Expr) -> Self {
+        Expr::MapContains(box map, box key, Position::default())
+    }
+
+    pub fn map_update(map: Expr, key: Expr, value: Expr) -> Self {
+        Expr::MapUpdate(box map, box key, box value, Position::default())
+    }
+
+    pub fn map_len(map: Expr) -> Self {
+        Expr::MapLen(box map, Position::default())
+    }
+
+    pub fn seq_len(seq: Expr) -> Self {
+        Expr::SeqLen(box seq, Position::default())
+    }
+
+    pub fn seq_index(seq: Expr, index: Expr) -> Self {
+        Expr::SeqIndex(box seq, box index, Position::default())
+    }
+
+    pub fn magic_wand(lhs: Expr, rhs: Expr, borrow: Option<Borrow>) -> Self {
+        Expr::MagicWand(box lhs, box rhs, borrow, Position::default())
+    }
+}
+
+pub trait ExprIterator {
+    /// Conjoin a sequence of expressions into a single expression.
+    /// Returns true if the sequence has no elements.
+    fn conjoin(&mut self) -> Expr;
+
+    /// Disjoin a sequence of expressions into a single expression.
+    /// Returns true if the sequence has no elements.
+    fn disjoin(&mut self) -> Expr;
+}
+
+impl<T> ExprIterator for T
+where
+    T: Iterator<Item = Expr>,
+{
+    fn conjoin(&mut self) -> Expr {
+        fn rfold<T>(s: &mut T) -> Expr
+        where
+            T: Iterator<Item = Expr>,
+        {
+            if let Some(conjunct) = s.next() {
+                Expr::and(conjunct, rfold(s))
+            } else {
+                true.into()
+            }
+        }
+        rfold(self)
+    }
+
+    fn disjoin(&mut self) -> Expr {
+        fn rfold<T>(s: &mut T) -> Expr
+        where
+            T: Iterator<Item = Expr>,
+        {
+            if let Some(conjunct) = s.next() {
+                Expr::or(conjunct, rfold(s))
+            } else {
+                false.into()
+            }
+        }
+        rfold(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conjoin_and_disjoin_empty_sequences() {
+        assert_eq!(Vec::<Expr>::new().into_iter().conjoin(), Expr::from(true));
+        assert_eq!(Vec::<Expr>::new().into_iter().disjoin(), Expr::from(false));
+    }
+
+    #[test]
+    fn conjoin_is_nested_to_the_right() {
+        let a = Expr::local(LocalVar::new("a", Type::Bool));
+        let b = Expr::local(LocalVar::new("b", Type::Bool));
+        assert_eq!(
+            vec![a.clone(), b.clone()].into_iter().conjoin(),
+            Expr::and(a, Expr::and(b, true.into()))
+        );
+    }
+
+    #[test]
+    fn old_is_not_put_around_locals_and_old_places() {
+        let x = Expr::local(LocalVar::new("x", Type::typed_ref("T")));
+        assert_eq!(x.clone().old("pre"), x);
+        let old_field = x.field(Field::new("f", Type::Int)).old("pre");
+        assert_eq!(old_field.clone().old("post"), old_field);
+    }
+}
@@ -0,0 +1,187 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The textual form of VIR expressions, used in logs and in error messages.
+
+use encoder::vir::ast::*;
+use std::fmt;
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Local(ref v, ref _pos) => write!(f, "{}", v),
+            Expr::Variant(ref base, ref variant_index, ref _pos) => {
+                write!(f, "{}[{}]", base, variant_index)
+            }
+            Expr::Field(ref base, ref field, ref _pos) => write!(f, "{}.{}", base, field),
+            Expr::AddrOf(ref base, _, ref _pos) => write!(f, "&({})", base),
+            Expr::Const(ref value, ref _pos) => write!(f, "{}", value),
+            Expr::BinOp(op, ref left, ref right, ref _pos) => {
+                write!(f, "({}) {} ({})", left, op, right)
+            }
+            Expr::UnaryOp(op, ref expr, ref _pos) => write!(f, "{}({})", op, expr),
+            Expr::PredicateAccessPredicate(ref pred_name, ref arg, perm, ref _pos) => {
+                write!(f, "acc({}({}), {})", pred_name, arg, perm)
+            }
+            Expr::FieldAccessPredicate(ref expr, perm, ref _pos) => {
+                write!(f, "acc({}, {})", expr, perm)
+            }
+            Expr::LabelledOld(ref label, ref expr, ref _pos) => {
+                write!(f, "old[{}]({})", label, expr)
+            }
+            Expr::MagicWand(ref left, ref right, ref borrow, ref _pos) => {
+                write!(f, "({}) {:?} --* ({})", left, borrow, right)
+            }
+            Expr::Unfolding(ref pred_name, ref args, ref expr, perm, ref variant, ref _pos) => {
+                write!(
+                    f,
+                    "(unfolding acc({}:{:?}({}), {}) in {})",
+                    pred_name,
+                    variant,
+                    args.iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    perm,
+                    expr
+                )
+            },
+            Expr::Cond(ref guard, ref left, ref right, ref _pos) => {
+                write!(f, "({})?({}):({})", guard, left, right)
+            }
+            Expr::ForAll(ref vars, ref triggers, ref body, ref _pos) => write!(
+                f,
+                "forall {} {} :: {}",
+                vars.iter()
+                    .map(|x| format!("{:?}", x))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                triggers
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                body.to_string()
+            ),
+            Expr::Exists(ref vars, ref triggers, ref body, ref _pos) => write!(
+                f,
+                "exists {} {} :: {}",
+                vars.iter()
+                    .map(|x| format!("{:?}", x))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                triggers
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                body.to_string()
+            ),
+            Expr::LetExpr(ref var, ref expr, ref body, ref _pos) => write!(
+                f,
+                "(let {:?} == ({}) in {})",
+                var,
+                expr.to_string(),
+                body.to_string()
+            ),
+            Expr::FuncApp(ref name, ref args, ref params, ref typ, ref _pos) => write!(
+                f,
+                "{}<{},{}>({})",
+                name,
+                params
+                    .iter()
+                    .map(|p| p.typ.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                typ.to_string(),
+                args.iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            Expr::DomainFuncApp(ref func, ref args, ref _pos) => write!(
+                f,
+                "{}({})",
+                func.name,
+                args.iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            Expr::MapLookup(ref map, ref key, ref _pos) => write!(f, "{}[{}]", map, key),
+            Expr::MapContains(ref map, ref key, ref _pos) => write!(f, "{} in {}", key, map),
+            Expr::MapUpdate(ref map, ref key, ref value, ref _pos) => {
+                write!(f, "{}[{} := {}]", map, key, value)
+            }
+            Expr::MapLen(ref map, ref _pos) => write!(f, "|{}|", map),
+            Expr::SeqLen(ref seq, ref _pos) => write!(f, "|{}|", seq),
+            Expr::SeqIndex(ref seq, ref index, ref _pos) => write!(f, "{}[{}]", seq, index),
+        }
+    }
+}
+
+impl fmt::Display for UnaryOpKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &UnaryOpKind::Not => write!(f, "!"),
+            &UnaryOpKind::Minus => write!(f, "-"),
+        }
+    }
+}
+
+impl fmt::Display for BinOpKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &BinOpKind::EqCmp => write!(f, "=="),
+            &BinOpKind::NeCmp => write!(f, "!="),
+            &BinOpKind::GtCmp => write!(f, ">"),
+            &BinOpKind::GeCmp => write!(f, ">="),
+            &BinOpKind::LtCmp => write!(f, "<"),
+            &BinOpKind::LeCmp => write!(f, "<="),
+            &BinOpKind::Add => write!(f, "+"),
+            &BinOpKind::Sub => write!(f, "-"),
+            &BinOpKind::Mul => write!(f, "*"),
+            &BinOpKind::Div => write!(f, "\\"),
+            &BinOpKind::Mod => write!(f, "%"),
+            &BinOpKind::And => write!(f, "&&"),
+            &BinOpKind::Or => write!(f, "||"),
+            &BinOpKind::Implies => write!(f, "==>"),
+        }
+    }
+}
+
+impl fmt::Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Const::Bool(val) => write!(f, "{}", val),
+            &Const::Int(val) => write!(f, "{}", val),
+            &Const::BigInt(ref val) => write!(f, "{}", val),
+            &Const::Str(ref val) => write!(f, "{:?}", val),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_operations() {
+        let x = Expr::local(LocalVar::new("x", Type::Int));
+        let expr = Expr::and(
+            Expr::not(Expr::gt_cmp(x.clone(), 0.into())),
+            Expr::eq_cmp(x, 1.into()),
+        );
+        assert_eq!(expr.to_string(), "(!((x) > (0))) && ((x) == (1))");
+    }
+
+    #[test]
+    fn display_old_places() {
+        let x = Expr::local(LocalVar::new("x", Type::typed_ref("T")));
+        let expr = x.field(Field::new("f", Type::Int)).old("pre");
+        assert_eq!(expr.to_string(), "old[pre](x.f)");
+    }
+}
@@ -0,0 +1,370 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Substitutions in VIR expressions: of places, of old labels and of generic types, and the
+//! rewritings that simplify the result.
+
+use encoder::vir::ast::*;
+use std::collections::HashMap;
+use std::mem;
+
+impl Expr {
+    pub fn replace_place(self, target: &Expr, replacement: &Expr) -> Self {
+        debug_assert!(target.is_place());
+        //assert_eq!(target.get_type(), replacement.get_type());
+        if replacement.is_place() {
+            assert!(
+                target.get_type() == replacement.get_type(),
+                "Cannot substitute '{}' with '{}', because they have incompatible types '{}' and '{}'",
+                target,
+                replacement,
+                target.get_type(),
+                replacement.get_type()
+            );
+        }
+        struct PlaceReplacer<'a> {
+            target: &'a Expr,
+            replacement: &'a Expr,
+            // FIXME: the following fields serve a grotesque hack.
+            //  Purpose:  Generics. When a less-generic function-under-test desugars specs from
+            //            a more-generic function, the vir::Expr contains Local's with __TYPARAM__s,
+            //            but Field's with the function-under-test's concrete types. The purpose is
+            //            the to "fix" the (Viper) predicates of the fields, i.e. replace those
+            //            typarams with local (more) concrete types. The substitution is
+            //            learnt from the type arguments of the types of the two locals, or from
+            //            their names if their structure is not known.
+            typaram_substs: Option<typaram::Substs>,
+            subst: bool,
+        };
+        impl<'a> ExprFolder for PlaceReplacer<'a> {
+            fn fold(&mut self, e: Expr) -> Expr {
+                if e.is_place() && &e == self.target {
+                    self.subst = true;
+                    self.replacement.clone()
+                } else {
+                    match default_fold_expr(self, e) {
+                        Expr::Field(expr, mut field, pos) => {
+                            if let Some(ts) = &self.typaram_substs {
+                                if self.subst && field.typ.is_ref() {
+                                    let inner1 = field.typ.name();
+                                    let inner2 = ts.apply(&inner1);
+                                    debug!("replacing:\n{}\n{}\n========", &inner1, &inner2);
+                                    if let Type::TypedRef(ref mut name, _) = field.typ {
+                                        *name = inner2;
+                                    }
+                                }
+                            }
+                            Expr::Field(expr, field, pos)
+                        }
+                        x => {
+                            self.subst = false;
+                            x
+                        }
+                    }
+                }
+            }
+
+            fn fold_forall(
+                &mut self,
+                vars: Vec<LocalVar>,
+                triggers: Vec<Trigger>,
+                body: Box<Expr>,
+                pos: Position,
+            ) -> Expr {
+                if vars.contains(&self.target.get_base()) {
+                    // Do nothing
+                    Expr::ForAll(vars, triggers, body, pos)
+                } else {
+                    Expr::ForAll(
+                        vars,
+                        triggers
+                            .into_iter()
+                            .map(|x| x.replace_place(self.target, self.replacement))
+                            .collect(),
+                        self.fold_boxed(body),
+                        pos,
+                    )
+                }
+            }
+
+            fn fold_exists(
+                &mut self,
+                vars: Vec<LocalVar>,
+                triggers: Vec<Trigger>,
+                body: Box<Expr>,
+                pos: Position,
+            ) -> Expr {
+                if vars.contains(&self.target.get_base()) {
+                    // Do nothing
+                    Expr::Exists(vars, triggers, body, pos)
+                } else {
+                    Expr::Exists(
+                        vars,
+                        triggers
+                            .into_iter()
+                            .map(|x| x.replace_place(self.target, self.replacement))
+                            .collect(),
+                        self.fold_boxed(body),
+                        pos,
+                    )
+                }
+            }
+
+            fn fold_let_expr(
+                &mut self,
+                var: LocalVar,
+                expr: Box<Expr>,
+                body: Box<Expr>,
+                pos: Position,
+            ) -> Expr {
+                let folded_expr = self.fold_boxed(expr);
+                if self.target.get_base() == var {
+                    // The variable shadows the target in the body
+                    Expr::LetExpr(var, folded_expr, body, pos)
+                } else {
+                    Expr::LetExpr(var, folded_expr, self.fold_boxed(body), pos)
+                }
+            }
+        }
+        let typaram_substs = match (&target, &replacement) {
+            (Expr::Local(tv, _), Expr::Local(rv, _)) => {
+                if tv.typ.is_ref() && rv.typ.is_ref() {
+                    debug!(
+                        "learning:\n{}\n{}\n=======",
+                        &target.local_type(),
+                        replacement.local_type()
+                    );
+                    Some(typaram::Substs::learn_types(&tv.typ, &rv.typ))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        PlaceReplacer {
+            target,
+            replacement,
+            typaram_substs,
+            subst: false,
+        }
+        .fold(self)
+    }
+
+    pub fn map_labels<F>(self, f: F) -> Self
+    where
+        F: Fn(String) -> Option<String>,
+    {
+        struct OldLabelReplacer<T: Fn(String) -> Option<String>> {
+            f: T,
+        };
+        impl<T: Fn(String) -> Option<String>> ExprFolder for OldLabelReplacer<T> {
+            fn fold_labelled_old(&mut self, label: String, base: Box<Expr>, pos: Position) -> Expr {
+                match (self.f)(label) {
+                    Some(new_label) => base.old(new_label).set_pos(pos),
+                    None => *base,
+                }
+            }
+        }
+        OldLabelReplacer { f }.fold(self)
+    }
+
+    /// Replaces the type variables of the types of the expression with their instantiations
+    /// in `substs` (see `Type::substitute`).
+    pub fn substitute_types(self, substs: &HashMap<String, Type>) -> Self {
+        struct TypePatcher<'a> {
+            substs: &'a HashMap<String, Type>,
+        }
+        impl<'a> ExprFolder for TypePatcher<'a> {
+            fn fold_predicate_access_predicate(
+                &mut self,
+                mut predicate_name: String,
+                arg: Box<Expr>,
+                perm_amount: PermAmount,
+                pos: Position,
+            ) -> Expr {
+                for (type_var, subst) in self.substs {
+                    predicate_name = predicate_name.replace(type_var, &subst.name());
+                }
+                Expr::PredicateAccessPredicate(
+                    predicate_name,
+                    self.fold_boxed(arg),
+                    perm_amount,
+                    pos,
+                )
+            }
+            fn fold_local(&mut self, mut var: LocalVar, pos: Position) -> Expr {
+                var.typ = var.typ.substitute(self.substs);
+                Expr::Local(var, pos)
+            }
+            fn fold_func_app(
+                &mut self,
+                name: String,
+                args: Vec<Expr>,
+                formal_args: Vec<LocalVar>,
+                return_type: Type,
+                pos: Position,
+            ) -> Expr {
+                let formal_args = formal_args
+                    .into_iter()
+                    .map(|mut var| {
+                        var.typ = var.typ.substitute(self.substs);
+                        var
+                    })
+                    .collect();
+                // FIXME: We do not patch the return_type because pure functions cannot return
+                // generic values.
+                Expr::FuncApp(
+                    name,
+                    args.into_iter().map(|e| self.fold(e)).collect(),
+                    formal_args,
+                    return_type,
+                    pos,
+                )
+            }
+        }
+        let mut patcher = TypePatcher { substs: substs };
+        patcher.fold(self)
+    }
+
+    /// Replaces expressions like `old[l5](old[l5](_9.val_ref).foo.bar)`
+    /// into `old[l5](_9.val_ref.foo.bar)`
+    pub fn remove_redundant_old(self) -> Self {
+        struct RedundantOldRemover {
+            current_label: Option<String>,
+        };
+        impl ExprFolder for RedundantOldRemover {
+            fn fold_labelled_old(&mut self, label: String, base: Box<Expr>, pos: Position) -> Expr {
+                let old_current_label = mem::replace(&mut self.current_label, Some(label.clone()));
+                let new_base = default_fold_expr(self, *base);
+                let new_expr = if Some(label.clone()) == old_current_label {
+                    new_base
+                } else {
+                    new_base.old(label).set_pos(pos)
+                };
+                self.current_label = old_current_label;
+                new_expr
+            }
+        }
+        RedundantOldRemover {
+            current_label: None,
+        }
+        .fold(self)
+    }
+
+    /// Leaves a conjunction of `acc(..)` expressions
+    pub fn filter_perm_conjunction(self) -> Self {
+        struct PermConjunctionFilter();
+        impl ExprFolder for PermConjunctionFilter {
+            fn fold(&mut self, e: Expr) -> Expr {
+                match e {
+                    f @ Expr::PredicateAccessPredicate(..) => f,
+                    f @ Expr::FieldAccessPredicate(..) => f,
+                    Expr::BinOp(BinOpKind::And, y, z, p) => {
+                        self.fold_bin_op(BinOpKind::And, y, z, p)
+                    }
+
+                    Expr::BinOp(..)
+                    | Expr::MagicWand(..)
+                    | Expr::Unfolding(..)
+                    | Expr::Cond(..)
+                    | Expr::UnaryOp(..)
+                    | Expr::Const(..)
+                    | Expr::Local(..)
+                    | Expr::Variant(..)
+                    | Expr::Field(..)
+                    | Expr::AddrOf(..)
+                    | Expr::LabelledOld(..)
+                    | Expr::ForAll(..)
+                    | Expr::Exists(..)
+                    | Expr::LetExpr(..)
+                    | Expr::FuncApp(..)
+                    | Expr::DomainFuncApp(..)
+                    | Expr::MapLookup(..)
+                    | Expr::MapContains(..)
+                    | Expr::MapUpdate(..)
+                    | Expr::MapLen(..)
+                    | Expr::SeqLen(..)
+                    | Expr::SeqIndex(..) => true.into(),
+                }
+            }
+        }
+        PermConjunctionFilter().fold(self)
+    }
+
+    /// Remove read permissions. For example, if the expression is
+    /// `acc(x.f, read) && acc(P(x.f), write)`, then after the
+    /// transformation it will be: `acc(P(x.f), write)`.
+    pub fn remove_read_permissions(self) -> Self {
+        struct ReadPermRemover {};
+        impl ExprFolder for ReadPermRemover {
+            fn fold_predicate_access_predicate(
+                &mut self,
+                name: String,
+                arg: Box<Expr>,
+                perm_amount: PermAmount,
+                p: Position,
+            ) -> Expr {
+                assert!(perm_amount.is_valid_for_specs());
+                match perm_amount {
+                    PermAmount::Write => Expr::PredicateAccessPredicate(name, arg, perm_amount, p),
+                    PermAmount::Read | PermAmount::Wildcard | PermAmount::Frac(..) => {
+                        true.into()
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            fn fold_field_access_predicate(
+                &mut self,
+                reference: Box<Expr>,
+                perm_amount: PermAmount,
+                p: Position,
+            ) -> Expr {
+                assert!(perm_amount.is_valid_for_specs());
+                match perm_amount {
+                    PermAmount::Write => Expr::FieldAccessPredicate(reference, perm_amount, p),
+                    PermAmount::Read | PermAmount::Wildcard | PermAmount::Frac(..) => {
+                        true.into()
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+        let mut remover = ReadPermRemover {};
+        remover.fold(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_place_substitutes_prefixes() {
+        let x = Expr::local(LocalVar::new("x", Type::typed_ref("T")));
+        let y = Expr::local(LocalVar::new("y", Type::typed_ref("T")));
+        let f = Field::new("f", Type::Int);
+        let expr = Expr::gt_cmp(x.clone().field(f.clone()), 0.into());
+        assert_eq!(
+            expr.replace_place(&x, &y),
+            Expr::gt_cmp(y.field(f), 0.into())
+        );
+    }
+
+    #[test]
+    fn map_labels_renames_and_removes_labels() {
+        let x = Expr::local(LocalVar::new("x", Type::typed_ref("T")));
+        let x_f = x.field(Field::new("f", Type::Int));
+        let expr = Expr::add(x_f.clone().old("pre"), x_f.clone().old("tmp"));
+        let renamed = expr.map_labels(|label| {
+            if label == "pre" {
+                Some("entry".to_string())
+            } else {
+                None
+            }
+        });
+        assert_eq!(renamed, Expr::add(x_f.clone().old("entry"), x_f));
+    }
+}
@@ -0,0 +1,30 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! VIR expressions.
+//!
+//! * `ast`: the definition of `Expr` and of its operators and constants;
+//! * `builders`: the constructors of expressions, see the documentation of the module;
+//! * `display`: the textual form of expressions;
+//! * `analysis`: queries on places, types and permissions;
+//! * `unify`: equality, hashing and alpha-equivalence of expressions;
+//! * `instantiation`: substitutions of places, old labels and generic types;
+//! * `visitors`: the `ExprFolder` and `ExprWalker` traits.
+//!
+//! All the public items are re-exported here, so users should import them from
+//! `encoder::vir` rather than from the submodules.
+
+pub use self::ast::*;
+pub use self::builders::*;
+pub use self::visitors::*;
+
+mod analysis;
+mod ast;
+mod builders;
+mod display;
+mod instantiation;
+mod unify;
+mod visitors;
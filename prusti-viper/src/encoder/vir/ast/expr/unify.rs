@@ -0,0 +1,343 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! When two VIR expressions are considered the same: equality and hashing ignore the
+//! positions, and `canonical_string` also ignores the names of the bound variables and the
+//! order of the triggers.
+
+use encoder::vir::ast::*;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+
+impl PartialEq for Expr {
+    /// Compare ignoring the `position` field
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Local(ref self_var, _), Expr::Local(ref other_var, _)) => self_var == other_var,
+            (
+                Expr::Variant(box ref self_base, ref self_variant, _),
+                Expr::Variant(box ref other_base, ref other_variant, _),
+            ) => (self_base, self_variant) == (other_base, other_variant),
+            (
+                Expr::Field(box ref self_base, ref self_field, _),
+                Expr::Field(box ref other_base, ref other_field, _),
+            ) => (self_base, self_field) == (other_base, other_field),
+            (
+                Expr::AddrOf(box ref self_base, ref self_typ, _),
+                Expr::AddrOf(box ref other_base, ref other_typ, _),
+            ) => (self_base, self_typ) == (other_base, other_typ),
+            (
+                Expr::LabelledOld(ref self_label, box ref self_base, _),
+                Expr::LabelledOld(ref other_label, box ref other_base, _),
+            ) => (self_label, self_base) == (other_label, other_base),
+            (Expr::Const(ref self_const, _), Expr::Const(ref other_const, _)) => {
+                self_const == other_const
+            }
+            (
+                Expr::MagicWand(box ref self_lhs, box ref self_rhs, self_borrow, _),
+                Expr::MagicWand(box ref other_lhs, box ref other_rhs, other_borrow, _),
+            ) => (self_lhs, self_rhs, self_borrow) == (other_lhs, other_rhs, other_borrow),
+            (
+                Expr::PredicateAccessPredicate(ref self_name, ref self_arg, self_perm, _),
+                Expr::PredicateAccessPredicate(ref other_name, ref other_arg, other_perm, _),
+            ) => (self_name, self_arg, self_perm) == (other_name, other_arg, other_perm),
+            (
+                Expr::FieldAccessPredicate(box ref self_base, self_perm, _),
+                Expr::FieldAccessPredicate(box ref other_base, other_perm, _),
+            ) => (self_base, self_perm) == (other_base, other_perm),
+            (
+                Expr::UnaryOp(self_op, box ref self_arg, _),
+                Expr::UnaryOp(other_op, box ref other_arg, _),
+            ) => (self_op, self_arg) == (other_op, other_arg),
+            (
+                Expr::BinOp(self_op, box ref self_left, box ref self_right, _),
+                Expr::BinOp(other_op, box ref other_left, box ref other_right, _),
+            ) => (self_op, self_left, self_right) == (other_op, other_left, other_right),
+            (
+                Expr::Cond(box ref self_cond, box ref self_then, box ref self_else, _),
+                Expr::Cond(box ref other_cond, box ref other_then, box ref other_else, _),
+            ) => (self_cond, self_then, self_else) == (other_cond, other_then, other_else),
+            (
+                Expr::ForAll(ref self_vars, ref self_triggers, box ref self_expr, _),
+                Expr::ForAll(ref other_vars, ref other_triggers, box ref other_expr, _),
+            ) => (self_vars, self_triggers, self_expr) == (other_vars, other_triggers, other_expr),
+            (
+                Expr::Exists(ref self_vars, ref self_triggers, box ref self_expr, _),
+                Expr::Exists(ref other_vars, ref other_triggers, box ref other_expr, _),
+            ) => (self_vars, self_triggers, self_expr) == (other_vars, other_triggers, other_expr),
+            (
+                Expr::LetExpr(ref self_var, box ref self_def, box ref self_expr, _),
+                Expr::LetExpr(ref other_var, box ref other_def, box ref other_expr, _),
+            ) => (self_var, self_def, self_expr) == (other_var, other_def, other_expr),
+            (
+                Expr::FuncApp(ref self_name, ref self_args, _, _, _),
+                Expr::FuncApp(ref other_name, ref other_args, _, _, _),
+            ) => (self_name, self_args) == (other_name, other_args),
+            (
+                Expr::DomainFuncApp(ref self_func, ref self_args, _),
+                Expr::DomainFuncApp(ref other_func, ref other_args, _),
+            ) => (self_func, self_args) == (other_func, other_args),
+            (
+                Expr::MapLookup(box ref self_map, box ref self_key, _),
+                Expr::MapLookup(box ref other_map, box ref other_key, _),
+            ) => (self_map, self_key) == (other_map, other_key),
+            (
+                Expr::MapContains(box ref self_map, box ref self_key, _),
+                Expr::MapContains(box ref other_map, box ref other_key, _),
+            ) => (self_map, self_key) == (other_map, other_key),
+            (
+                Expr::MapUpdate(box ref self_map, box ref self_key, box ref self_value, _),
+                Expr::MapUpdate(box ref other_map, box ref other_key, box ref other_value, _),
+            ) => (self_map, self_key, self_value) == (other_map, other_key, other_value),
+            (Expr::MapLen(box ref self_map, _), Expr::MapLen(box ref other_map, _)) => {
+                self_map == other_map
+            }
+            (Expr::SeqLen(box ref self_seq, _), Expr::SeqLen(box ref other_seq, _)) => {
+                self_seq == other_seq
+            }
+            (
+                Expr::SeqIndex(box ref self_seq, box ref self_index, _),
+                Expr::SeqIndex(box ref other_seq, box ref other_index, _),
+            ) => (self_seq, self_index) == (other_seq, other_index),
+            (
+                Expr::Unfolding(ref self_name, ref self_args, box ref self_base, self_perm, ref self_variant, _),
+                Expr::Unfolding(ref other_name, ref other_args, box ref other_base, other_perm, ref other_variant, _),
+            ) => {
+                (self_name, self_args, self_base, self_perm, self_variant)
+                    == (other_name, other_args, other_base, other_perm, other_variant)
+            }
+            (a, b) => {
+                debug_assert_ne!(discriminant(a), discriminant(b));
+                false
+            }
+        }
+    }
+}
+
+impl Eq for Expr {}
+
+impl Hash for Expr {
+    /// Hash ignoring the `position` field
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Expr::Local(ref var, _) => var.hash(state),
+            Expr::Variant(box ref base, variant_index, _) => (base, variant_index).hash(state),
+            Expr::Field(box ref base, ref field, _) => (base, field).hash(state),
+            Expr::AddrOf(box ref base, ref typ, _) => (base, typ).hash(state),
+            Expr::LabelledOld(ref label, box ref base, _) => (label, base).hash(state),
+            Expr::Const(ref const_expr, _) => const_expr.hash(state),
+            Expr::MagicWand(box ref lhs, box ref rhs, b, _) => (lhs, rhs, b).hash(state),
+            Expr::PredicateAccessPredicate(ref name, ref arg, perm, _) => {
+                (name, arg, perm).hash(state)
+            }
+            Expr::FieldAccessPredicate(box ref base, perm, _) => (base, perm).hash(state),
+            Expr::UnaryOp(op, box ref arg, _) => (op, arg).hash(state),
+            Expr::BinOp(op, box ref left, box ref right, _) => (op, left, right).hash(state),
+            Expr::Cond(box ref cond, box ref then_expr, box ref else_expr, _) => {
+                (cond, then_expr, else_expr).hash(state)
+            }
+            Expr::ForAll(ref vars, ref triggers, box ref expr, _) => {
+                (vars, triggers, expr).hash(state)
+            }
+            Expr::Exists(ref vars, ref triggers, box ref expr, _) => {
+                (vars, triggers, expr).hash(state)
+            }
+            Expr::LetExpr(ref var, box ref def, box ref expr, _) => (var, def, expr).hash(state),
+            Expr::FuncApp(ref name, ref args, _, _, _) => (name, args).hash(state),
+            Expr::DomainFuncApp(ref func, ref args, _) => (func, args).hash(state),
+            Expr::MapLookup(box ref map, box ref key, _) => (map, key).hash(state),
+            Expr::MapContains(box ref map, box ref key, _) => (map, key).hash(state),
+            Expr::MapUpdate(box ref map, box ref key, box ref value, _) => {
+                (map, key, value).hash(state)
+            }
+            Expr::MapLen(box ref map, _) => map.hash(state),
+            Expr::SeqLen(box ref seq, _) => seq.hash(state),
+            Expr::SeqIndex(box ref seq, box ref index, _) => (seq, index).hash(state),
+            Expr::Unfolding(ref name, ref args, box ref base, perm, ref variant, _) => {
+                (name, args, base, perm, variant).hash(state)
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// A textual form of the expression that does not depend on the names of the variables
+    /// bound by quantifiers, nor on the order of the triggers, to be used in logs and as a key.
+    /// The bound variables are renamed to `_q0`, `_q1`, ... in the order of their quantifiers.
+    pub fn canonical_string(&self) -> String {
+        struct Canonicalizer {
+            counter: usize,
+            renamings: HashMap<String, String>,
+        }
+        impl Canonicalizer {
+            fn fold_quantifier(
+                &mut self,
+                quantifier: fn(Vec<LocalVar>, Vec<Trigger>, Box<Expr>, Position) -> Expr,
+                vars: Vec<LocalVar>,
+                triggers: Vec<Trigger>,
+                body: Box<Expr>,
+            ) -> Expr {
+                let saved_renamings = self.renamings.clone();
+                let vars: Vec<_> = vars
+                    .into_iter()
+                    .map(|var| {
+                        let name = format!("_q{}", self.counter);
+                        self.counter += 1;
+                        self.renamings.insert(var.name, name.clone());
+                        LocalVar::new(name, var.typ)
+                    })
+                    .collect();
+                let mut triggers: Vec<_> = triggers
+                    .into_iter()
+                    .map(|trigger| {
+                        let mut elements: Vec<_> = trigger
+                            .elements()
+                            .iter()
+                            .map(|element| self.fold(element.clone()))
+                            .collect();
+                        elements.sort_by_cached_key(|element| element.to_string());
+                        Trigger::new(elements)
+                    })
+                    .collect();
+                triggers.sort_by_cached_key(|trigger| trigger.to_string());
+                let body = self.fold_boxed(body);
+                self.renamings = saved_renamings;
+                quantifier(vars, triggers, body, Position::default())
+            }
+        }
+        impl ExprFolder for Canonicalizer {
+            fn fold_local(&mut self, mut var: LocalVar, pos: Position) -> Expr {
+                if let Some(name) = self.renamings.get(&var.name) {
+                    var.name = name.clone();
+                }
+                Expr::Local(var, pos)
+            }
+            fn fold_forall(
+                &mut self,
+                vars: Vec<LocalVar>,
+                triggers: Vec<Trigger>,
+                body: Box<Expr>,
+                _pos: Position,
+            ) -> Expr {
+                self.fold_quantifier(Expr::ForAll, vars, triggers, body)
+            }
+            fn fold_exists(
+                &mut self,
+                vars: Vec<LocalVar>,
+                triggers: Vec<Trigger>,
+                body: Box<Expr>,
+                _pos: Position,
+            ) -> Expr {
+                self.fold_quantifier(Expr::Exists, vars, triggers, body)
+            }
+        }
+        let mut canonicalizer = Canonicalizer {
+            counter: 0,
+            renamings: HashMap::new(),
+        };
+        canonicalizer.fold(self.clone()).to_string()
+    }
+
+    /* Moved to the Eq impl
+    /// Place equality after type elision
+    pub fn weak_eq(&self, other: &Expr) -> bool {
+        debug_assert!(self.is_place());
+        debug_assert!(other.is_place());
+        match (self, other) {
+            (
+                Expr::Local(ref self_var),
+                Expr::Local(ref other_var)
+            ) => self_var.weak_eq(other_var),
+            (
+                Expr::Field(box ref self_base, ref self_field),
+                Expr::Field(box ref other_base, ref other_field)
+            ) => self_field.weak_eq(other_field) && self_base.weak_eq(other_base),
+            (
+                Expr::AddrOf(box ref self_base, ref self_typ),
+                Expr::AddrOf(box ref other_base, ref other_typ)
+            ) => self_typ.weak_eq(other_typ) && self_base.weak_eq(other_base),
+            (
+                Expr::LabelledOld(ref self_label, box ref self_base),
+                Expr::LabelledOld(ref other_label, box ref other_base)
+            ) => self_label == other_label && self_base.weak_eq(other_base),
+            (
+                Expr::Unfolding(ref self_name, ref self_args, box ref self_base, self_frac),
+                Expr::Unfolding(ref other_name, ref other_args, box ref other_base, other_frac)
+            ) => self_name == other_name && self_frac == other_frac &&
+                self_args[0].weak_eq(&other_args[0]) && self_base.weak_eq(other_base),
+            _ => false
+        }
+    }
+    */
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(expr: &Expr) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_equality_and_hash_ignore_positions() {
+        let x = Expr::local(LocalVar::new("x", Type::Int));
+        let x_with_pos = x.clone().set_pos(Position::new(1, 2, PositionId::new(3)));
+        assert_eq!(x, x_with_pos);
+        assert_eq!(hash(&x), hash(&x_with_pos));
+        assert_ne!(x, Expr::local(LocalVar::new("y", Type::Int)));
+    }
+
+    fn quantified_gt(var_name: &str, other_name: &str, swap_triggers: bool) -> Expr {
+        let var = LocalVar::new(var_name, Type::Int);
+        let other = LocalVar::new(other_name, Type::Int);
+        let mut triggers = vec![
+            Trigger::new(vec![Expr::local(var.clone())]),
+            Trigger::new(vec![Expr::local(other.clone())]),
+        ];
+        if swap_triggers {
+            triggers.reverse();
+        }
+        Expr::forall(
+            vec![var.clone(), other.clone()],
+            triggers,
+            Expr::gt_cmp(Expr::local(var), Expr::local(other)),
+        )
+    }
+
+    #[test]
+    fn test_canonical_string_renames_bound_vars() {
+        assert_eq!(
+            quantified_gt("i", "j", false).canonical_string(),
+            quantified_gt("x", "y", false).canonical_string()
+        );
+    }
+
+    #[test]
+    fn test_canonical_string_sorts_triggers() {
+        assert_eq!(
+            quantified_gt("i", "j", false).canonical_string(),
+            quantified_gt("i", "j", true).canonical_string()
+        );
+    }
+
+    #[test]
+    fn test_canonical_string_keeps_free_vars() {
+        let bound = LocalVar::new("i", Type::Int);
+        let free = LocalVar::new("n", Type::Int);
+        let expr = Expr::forall(
+            vec![bound.clone()],
+            vec![],
+            Expr::gt_cmp(Expr::local(bound), Expr::local(free)),
+        );
+        assert_eq!(expr.canonical_string(), "forall _q0: Int  :: (_q0) > (n)");
+    }
+}
@@ -0,0 +1,492 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Folders and walkers over VIR expressions.
+
+use encoder::vir::ast::*;
+
+pub trait ExprFolder: Sized {
+    fn fold(&mut self, e: Expr) -> Expr {
+        default_fold_expr(self, e)
+    }
+
+    fn fold_boxed(&mut self, e: Box<Expr>) -> Box<Expr> {
+        box self.fold(*e)
+    }
+
+    fn fold_local(&mut self, v: LocalVar, p: Position) -> Expr {
+        Expr::Local(v, p)
+    }
+    fn fold_variant(&mut self, base: Box<Expr>, variant: Field, p: Position) -> Expr {
+        Expr::Variant(self.fold_boxed(base), variant, p)
+    }
+    fn fold_field(&mut self, receiver: Box<Expr>, field: Field, pos: Position) -> Expr {
+        Expr::Field(self.fold_boxed(receiver), field, pos)
+    }
+    fn fold_addr_of(&mut self, e: Box<Expr>, t: Type, p: Position) -> Expr {
+        Expr::AddrOf(self.fold_boxed(e), t, p)
+    }
+    fn fold_const(&mut self, x: Const, p: Position) -> Expr {
+        Expr::Const(x, p)
+    }
+    fn fold_labelled_old(
+        &mut self,
+        label: String,
+        body: Box<Expr>,
+        pos: Position
+    ) -> Expr {
+        Expr::LabelledOld(label, self.fold_boxed(body), pos)
+    }
+    fn fold_magic_wand(
+        &mut self,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        borrow: Option<Borrow>,
+        pos: Position,
+    ) -> Expr {
+        Expr::MagicWand(self.fold_boxed(lhs), self.fold_boxed(rhs), borrow, pos)
+    }
+    fn fold_predicate_access_predicate(
+        &mut self,
+        name: String,
+        arg: Box<Expr>,
+        perm_amount: PermAmount,
+        pos: Position,
+    ) -> Expr {
+        Expr::PredicateAccessPredicate(name, self.fold_boxed(arg), perm_amount, pos)
+    }
+    fn fold_field_access_predicate(
+        &mut self,
+        receiver: Box<Expr>,
+        perm_amount: PermAmount,
+        pos: Position
+    ) -> Expr {
+        Expr::FieldAccessPredicate(self.fold_boxed(receiver), perm_amount, pos)
+    }
+    fn fold_unary_op(&mut self, x: UnaryOpKind, y: Box<Expr>, p: Position) -> Expr {
+        Expr::UnaryOp(x, self.fold_boxed(y), p)
+    }
+    fn fold_bin_op(
+        &mut self,
+        kind: BinOpKind,
+        first: Box<Expr>,
+        second: Box<Expr>,
+        pos: Position
+    ) -> Expr {
+        Expr::BinOp(kind, self.fold_boxed(first), self.fold_boxed(second), pos)
+    }
+    fn fold_unfolding(
+        &mut self,
+        name: String,
+        args: Vec<Expr>,
+        expr: Box<Expr>,
+        perm: PermAmount,
+        variant: MaybeEnumVariantIndex,
+        pos: Position,
+    ) -> Expr {
+        Expr::Unfolding(
+            name,
+            args.into_iter().map(|e| self.fold(e)).collect(),
+            self.fold_boxed(expr),
+            perm,
+            variant,
+            pos,
+        )
+    }
+    fn fold_cond(
+        &mut self,
+        guard: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+        pos: Position
+    ) -> Expr {
+        Expr::Cond(
+            self.fold_boxed(guard),
+            self.fold_boxed(then_expr),
+            self.fold_boxed(else_expr),
+            pos,
+        )
+    }
+    fn fold_forall(
+        &mut self,
+        x: Vec<LocalVar>,
+        y: Vec<Trigger>,
+        z: Box<Expr>,
+        p: Position,
+    ) -> Expr {
+        Expr::ForAll(x, y, self.fold_boxed(z), p)
+    }
+    fn fold_exists(
+        &mut self,
+        x: Vec<LocalVar>,
+        y: Vec<Trigger>,
+        z: Box<Expr>,
+        p: Position,
+    ) -> Expr {
+        Expr::Exists(x, y, self.fold_boxed(z), p)
+    }
+    fn fold_let_expr(
+        &mut self,
+        var: LocalVar,
+        expr: Box<Expr>,
+        body: Box<Expr>,
+        pos: Position
+    ) -> Expr {
+        Expr::LetExpr(var, self.fold_boxed(expr), self.fold_boxed(body), pos)
+    }
+    fn fold_func_app(
+        &mut self,
+        name: String,
+        args: Vec<Expr>,
+        formal_args: Vec<LocalVar>,
+        return_type: Type,
+        pos: Position,
+    ) -> Expr {
+        Expr::FuncApp(
+            name,
+            args.into_iter().map(|e| self.fold(e)).collect(),
+            formal_args,
+            return_type,
+            pos
+        )
+    }
+    fn fold_domain_func_app(
+        &mut self,
+        func: DomainFunc,
+        args: Vec<Expr>,
+        pos: Position,
+    ) -> Expr {
+        Expr::DomainFuncApp(func, args.into_iter().map(|e| self.fold(e)).collect(), pos)
+    }
+    fn fold_map_lookup(&mut self, map: Box<Expr>, key: Box<Expr>, pos: Position) -> Expr {
+        Expr::MapLookup(self.fold_boxed(map), self.fold_boxed(key), pos)
+    }
+    fn fold_map_contains(&mut self, map: Box<Expr>, key: Box<Expr>, pos: Position) -> Expr {
+        Expr::MapContains(self.fold_boxed(map), self.fold_boxed(key), pos)
+    }
+    fn fold_map_update(
+        &mut self,
+        map: Box<Expr>,
+        key: Box<Expr>,
+        value: Box<Expr>,
+        pos: Position,
+    ) -> Expr {
+        Expr::MapUpdate(
+            self.fold_boxed(map),
+            self.fold_boxed(key),
+            self.fold_boxed(value),
+            pos,
+        )
+    }
+    fn fold_map_len(&mut self, map: Box<Expr>, pos: Position) -> Expr {
+        Expr::MapLen(self.fold_boxed(map), pos)
+    }
+    fn fold_seq_len(&mut self, seq: Box<Expr>, pos: Position) -> Expr {
+        Expr::SeqLen(self.fold_boxed(seq), pos)
+    }
+    fn fold_seq_index(&mut self, seq: Box<Expr>, index: Box<Expr>, pos: Position) -> Expr {
+        Expr::SeqIndex(self.fold_boxed(seq), self.fold_boxed(index), pos)
+    }
+}
+
+pub fn default_fold_expr<T: ExprFolder>(this: &mut T, e: Expr) -> Expr {
+    match e {
+        Expr::Local(v, p) => this.fold_local(v, p),
+        Expr::Variant(base, variant, p) => this.fold_variant(base, variant, p),
+        Expr::Field(e, f, p) => this.fold_field(e, f, p),
+        Expr::AddrOf(e, t, p) => this.fold_addr_of(e, t, p),
+        Expr::Const(x, p) => this.fold_const(x, p),
+        Expr::LabelledOld(x, y, p) => this.fold_labelled_old(x, y, p),
+        Expr::MagicWand(x, y, b, p) => this.fold_magic_wand(x, y, b, p),
+        Expr::PredicateAccessPredicate(x, y, z, p) => {
+            this.fold_predicate_access_predicate(x, y, z, p)
+        }
+        Expr::FieldAccessPredicate(x, y, p) => this.fold_field_access_predicate(x, y, p),
+        Expr::UnaryOp(x, y, p) => this.fold_unary_op(x, y, p),
+        Expr::BinOp(x, y, z, p) => this.fold_bin_op(x, y, z, p),
+        Expr::Unfolding(x, y, z, perm, variant, p) => {
+            this.fold_unfolding(x, y, z, perm, variant, p)
+        },
+        Expr::Cond(x, y, z, p) => this.fold_cond(x, y, z, p),
+        Expr::ForAll(x, y, z, p) => this.fold_forall(x, y, z, p),
+        Expr::Exists(x, y, z, p) => this.fold_exists(x, y, z, p),
+        Expr::LetExpr(x, y, z, p) => this.fold_let_expr(x, y, z, p),
+        Expr::FuncApp(x, y, z, k, p) => this.fold_func_app(x, y, z, k, p),
+        Expr::DomainFuncApp(x, y, p) => this.fold_domain_func_app(x, y, p),
+        Expr::MapLookup(x, y, p) => this.fold_map_lookup(x, y, p),
+        Expr::MapContains(x, y, p) => this.fold_map_contains(x, y, p),
+        Expr::MapUpdate(x, y, z, p) => this.fold_map_update(x, y, z, p),
+        Expr::MapLen(x, p) => this.fold_map_len(x, p),
+        Expr::SeqLen(x, p) => this.fold_seq_len(x, p),
+        Expr::SeqIndex(x, y, p) => this.fold_seq_index(x, y, p),
+    }
+}
+
+pub trait ExprWalker: Sized {
+    fn walk(&mut self, expr: &Expr) {
+        default_walk_expr(self, expr);
+    }
+
+    fn walk_local_var(&mut self, _var: &LocalVar) {}
+
+    fn walk_local(&mut self, var: &LocalVar, _pos: &Position) {
+        self.walk_local_var(var);
+    }
+    fn walk_variant(&mut self, base: &Expr, _variant: &Field, _pos: &Position) {
+        self.walk(base);
+    }
+    fn walk_field(&mut self, receiver: &Expr, _field: &Field, _pos: &Position) {
+        self.walk(receiver);
+    }
+    fn walk_addr_of(&mut self, receiver: &Expr, _typ: &Type, _pos: &Position) {
+        self.walk(receiver);
+    }
+    fn walk_const(&mut self, _const: &Const, _pos: &Position) {}
+    fn walk_labelled_old(&mut self, _label: &str, body: &Expr, _pos: &Position) {
+        self.walk(body);
+    }
+    fn walk_magic_wand(
+        &mut self,
+        lhs: &Expr,
+        rhs: &Expr,
+        _borrow: &Option<Borrow>,
+        _pos: &Position
+    ) {
+        self.walk(lhs);
+        self.walk(rhs);
+    }
+    fn walk_predicate_access_predicate(
+        &mut self,
+        _name: &str,
+        arg: &Expr,
+        _perm_amount: PermAmount,
+        _pos: &Position
+    ) {
+        self.walk(arg)
+    }
+    fn walk_field_access_predicate(
+        &mut self,
+        receiver: &Expr,
+        _perm_amount: PermAmount,
+        _pos: &Position
+    ) {
+        self.walk(receiver)
+    }
+    fn walk_unary_op(&mut self, _op: UnaryOpKind, arg: &Expr, _pos: &Position) {
+        self.walk(arg)
+    }
+    fn walk_bin_op(&mut self, _op: BinOpKind, arg1: &Expr, arg2: &Expr, _pos: &Position) {
+        self.walk(arg1);
+        self.walk(arg2);
+    }
+    fn walk_unfolding(
+        &mut self,
+        _name: &str,
+        args: &Vec<Expr>,
+        body: &Expr,
+        _perm: PermAmount,
+        _variant: &MaybeEnumVariantIndex,
+        _pos: &Position
+    ) {
+        for arg in args {
+            self.walk(arg);
+        }
+        self.walk(body);
+    }
+    fn walk_cond(&mut self, guard: &Expr, then_expr: &Expr, else_expr: &Expr, _pos: &Position) {
+        self.walk(guard);
+        self.walk(then_expr);
+        self.walk(else_expr);
+    }
+    fn walk_forall(
+        &mut self,
+        vars: &Vec<LocalVar>,
+        _triggers: &Vec<Trigger>,
+        body: &Expr,
+        _pos: &Position
+    ) {
+        for var in vars {
+            self.walk_local_var(var);
+        }
+        self.walk(body);
+    }
+    fn walk_exists(
+        &mut self,
+        vars: &Vec<LocalVar>,
+        _triggers: &Vec<Trigger>,
+        body: &Expr,
+        _pos: &Position
+    ) {
+        for var in vars {
+            self.walk_local_var(var);
+        }
+        self.walk(body);
+    }
+    fn walk_let_expr(&mut self, bound_var: &LocalVar, expr: &Expr, body: &Expr, _pos: &Position) {
+        self.walk_local_var(bound_var);
+        self.walk(expr);
+        self.walk(body);
+    }
+    fn walk_func_app(
+        &mut self,
+        _name: &str,
+        args: &Vec<Expr>,
+        formal_args: &Vec<LocalVar>,
+        _return_type: &Type,
+        _pos: &Position
+    ) {
+        for arg in args {
+            self.walk(arg)
+        }
+        for arg in formal_args {
+            self.walk_local_var(arg);
+        }
+    }
+    fn walk_domain_func_app(&mut self, func: &DomainFunc, args: &Vec<Expr>, _pos: &Position) {
+        for arg in args {
+            self.walk(arg)
+        }
+        for arg in &func.formal_args {
+            self.walk_local_var(arg);
+        }
+    }
+    fn walk_map_lookup(&mut self, map: &Expr, key: &Expr, _pos: &Position) {
+        self.walk(map);
+        self.walk(key);
+    }
+    fn walk_map_contains(&mut self, map: &Expr, key: &Expr, _pos: &Position) {
+        self.walk(map);
+        self.walk(key);
+    }
+    fn walk_map_update(&mut self, map: &Expr, key: &Expr, value: &Expr, _pos: &Position) {
+        self.walk(map);
+        self.walk(key);
+        self.walk(value);
+    }
+    fn walk_map_len(&mut self, map: &Expr, _pos: &Position) {
+        self.walk(map);
+    }
+    fn walk_seq_len(&mut self, seq: &Expr, _pos: &Position) {
+        self.walk(seq);
+    }
+    fn walk_seq_index(&mut self, seq: &Expr, index: &Expr, _pos: &Position) {
+        self.walk(seq);
+        self.walk(index);
+    }
+}
+
+pub fn default_walk_expr<T: ExprWalker>(this: &mut T, e: &Expr) {
+    match *e {
+        Expr::Local(ref v, ref p) => this.walk_local(v, p),
+        Expr::Variant(ref base, ref variant, ref p) => this.walk_variant(base, variant, p),
+        Expr::Field(ref e, ref f, ref p) => this.walk_field(e, f, p),
+        Expr::AddrOf(ref e, ref t, ref p) => this.walk_addr_of(e, t, p),
+        Expr::Const(ref x, ref p) => this.walk_const(x, p),
+        Expr::LabelledOld(ref x, ref y, ref p) => this.walk_labelled_old(x, y, p),
+        Expr::MagicWand(ref x, ref y, ref b, ref p) => this.walk_magic_wand(x, y, b, p),
+        Expr::PredicateAccessPredicate(ref x, ref y, z, ref p) => {
+            this.walk_predicate_access_predicate(x, y, z, p)
+        }
+        Expr::FieldAccessPredicate(ref x, y, ref p) => this.walk_field_access_predicate(x, y, p),
+        Expr::UnaryOp(x, ref y, ref p) => this.walk_unary_op(x, y, p),
+        Expr::BinOp(x, ref y, ref z, ref p) => this.walk_bin_op(x, y, z, p),
+        Expr::Unfolding(ref x, ref y, ref z, perm, ref variant, ref p) => {
+            this.walk_unfolding(x, y, z, perm, variant, p)
+        },
+        Expr::Cond(ref x, ref y, ref z, ref p) => this.walk_cond(x, y, z, p),
+        Expr::ForAll(ref x, ref y, ref z, ref p) => this.walk_forall(x, y, z, p),
+        Expr::Exists(ref x, ref y, ref z, ref p) => this.walk_exists(x, y, z, p),
+        Expr::LetExpr(ref x, ref y, ref z, ref p) => this.walk_let_expr(x, y, z, p),
+        Expr::FuncApp(ref x, ref y, ref z, ref k, ref p) => this.walk_func_app(x, y, z, k, p),
+        Expr::DomainFuncApp(ref x, ref y, ref p) => this.walk_domain_func_app(x, y, p),
+        Expr::MapLookup(ref x, ref y, ref p) => this.walk_map_lookup(x, y, p),
+        Expr::MapContains(ref x, ref y, ref p) => this.walk_map_contains(x, y, p),
+        Expr::MapUpdate(ref x, ref y, ref z, ref p) => this.walk_map_update(x, y, z, p),
+        Expr::MapLen(ref x, ref p) => this.walk_map_len(x, p),
+        Expr::SeqLen(ref x, ref p) => this.walk_seq_len(x, p),
+        Expr::SeqIndex(ref x, ref y, ref p) => this.walk_seq_index(x, y, p),
+    }
+}
+
+impl Expr {
+    /// Apply the closure to all places in the expression.
+    pub fn fold_places<F>(self, f: F) -> Expr
+    where
+        F: Fn(Expr) -> Expr,
+    {
+        struct PlaceFolder<F>
+        where
+            F: Fn(Expr) -> Expr,
+        {
+            f: F,
+        };
+        impl<F> ExprFolder for PlaceFolder<F>
+        where
+            F: Fn(Expr) -> Expr,
+        {
+            fn fold(&mut self, e: Expr) -> Expr {
+                if e.is_place() {
+                    (self.f)(e)
+                } else {
+                    default_fold_expr(self, e)
+                }
+            }
+            // TODO: Handle triggers?
+        }
+        PlaceFolder { f }.fold(self)
+    }
+
+    /// Apply the closure to all expressions.
+    pub fn fold_expr<F>(self, f: F) -> Expr
+    where
+        F: Fn(Expr) -> Expr,
+    {
+        struct ExprFolderImpl<F>
+        where
+            F: Fn(Expr) -> Expr,
+        {
+            f: F,
+        };
+        impl<F> ExprFolder for ExprFolderImpl<F>
+        where
+            F: Fn(Expr) -> Expr,
+        {
+            fn fold(&mut self, e: Expr) -> Expr {
+                let new_expr = default_fold_expr(self, e);
+                (self.f)(new_expr)
+            }
+        }
+        ExprFolderImpl { f }.fold(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_expr_rewrites_all_subexpressions() {
+        let x = Expr::local(LocalVar::new("x", Type::Int));
+        let expr = Expr::add(x.clone(), Expr::add(x.clone(), 1.into()));
+        let folded = expr.fold_expr(|e| if e == x { 0.into() } else { e });
+        assert_eq!(folded, Expr::add(0.into(), Expr::add(0.into(), 1.into())));
+    }
+
+    #[test]
+    fn walker_visits_all_locals() {
+        struct LocalCounter(usize);
+        impl ExprWalker for LocalCounter {
+            fn walk_local(&mut self, _var: &LocalVar, _pos: &Position) {
+                self.0 += 1;
+            }
+        }
+        let x = Expr::local(LocalVar::new("x", Type::Int));
+        let expr = Expr::add(x.clone(), Expr::add(x, 1.into()));
+        let mut counter = LocalCounter(0);
+        counter.walk(&expr);
+        assert_eq!(counter.0, 2);
+    }
+}
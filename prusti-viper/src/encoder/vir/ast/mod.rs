@@ -4,16 +4,20 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub use self::arena::*;
 pub use self::bodyless_method::*;
 pub use self::common::*;
+pub use self::domain::*;
 pub use self::expr::*;
 pub use self::function::*;
 pub use self::predicate::*;
 pub use self::stmt::*;
 pub use self::trigger::*;
 
+mod arena;
 mod bodyless_method;
 mod common;
+mod domain;
 mod expr;
 mod function;
 mod predicate;
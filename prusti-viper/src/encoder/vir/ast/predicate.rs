@@ -22,7 +22,29 @@ impl fmt::Display for Predicate {
     }
 }
 
+/// The prefix of the names of the predicates declared with `#[predicate]` functions.
+const USER_PREDICATE_PREFIX: &str = "user_predicate_";
+
 impl Predicate {
+    /// Construct the predicate declared by a `#[predicate]` function, whose argument is a value
+    /// and whose body is the assertion returned by the function.
+    pub fn new_user(item_name: &str, this: LocalVar, body: Expr) -> Predicate {
+        Predicate::Struct(StructPredicate {
+            name: Self::user_predicate_name(item_name),
+            this,
+            body: Some(body),
+        })
+    }
+    /// The name of the predicate declared by the `#[predicate]` function `item_name`.
+    pub fn user_predicate_name(item_name: &str) -> String {
+        format!("{}{}", USER_PREDICATE_PREFIX, item_name)
+    }
+    /// Is `name` the name of a predicate declared by a `#[predicate]` function? Unlike the
+    /// predicate of a type, it is not named after the type of its argument, and it is folded and
+    /// unfolded only by the `fold!` and `unfold!` statements of the users.
+    pub fn is_user_predicate_name(name: &str) -> bool {
+        name.starts_with(USER_PREDICATE_PREFIX)
+    }
     /// A helper for constructing the predicate's `self` argument.
     pub fn construct_this(typ: Type) -> LocalVar {
         LocalVar::new("self", typ)
@@ -143,16 +165,26 @@ impl fmt::Display for StructPredicate {
 
 impl StructPredicate {
     pub fn new(typ: Type, fields: Vec<Field>) -> Self {
+        let fields = fields
+            .into_iter()
+            .map(|field| (field, PermAmount::Write))
+            .collect();
+        Self::new_with_field_predicate_perms(typ, fields)
+    }
+    /// Like `new`, but the predicate of each field is accessed with the given amount. E.g. the
+    /// predicate of a shared reference field, or of a variant that only borrows the referents of
+    /// its fields.
+    pub fn new_with_field_predicate_perms(typ: Type, fields: Vec<(Field, PermAmount)>) -> Self {
         let predicate_name = typ.name();
         let this = Predicate::construct_this(typ);
         let body = fields
             .into_iter()
-            .flat_map(|field| {
+            .flat_map(|(field, perm_amount)| {
                 let predicate_name = field.typed_ref_name().unwrap();
                 let location: Expr = Expr::from(this.clone()).field(field).into();
                 let field_perm = Expr::acc_permission(location.clone(), PermAmount::Write);
                 let pred_perm =
-                    Expr::predicate_access_predicate(predicate_name, location, PermAmount::Write);
+                    Expr::predicate_access_predicate(predicate_name, location, perm_amount);
                 vec![field_perm, pred_perm]
             })
             .conjoin();
@@ -16,6 +16,15 @@ pub enum Stmt {
     Inhale(Expr, FoldingBehaviour),
     Exhale(Expr, Position),
     Assert(Expr, FoldingBehaviour, Position),
+    /// Assume a pure expression without checking it. Unlike `Inhale`, it never adds permissions.
+    /// Used for the assumptions written with `prusti_assume!`.
+    Assume(Expr, Position),
+    /// Check that a pure expression does *not* hold in all the executions that reach this
+    /// point, without affecting the rest of the execution. Since Viper has no such statement,
+    /// it is lowered to an assertion in a branch that is taken non-deterministically (see
+    /// `fixes::lower_refutations`), whose failure is the expected outcome. Used for the
+    /// refutations written with `prusti_refute!`.
+    Refute(Expr, Position),
     /// MethodCall: method_name, args, targets
     MethodCall(String, Vec<Expr>, Vec<LocalVar>),
     Assign(Expr, Expr, AssignKind),
@@ -95,6 +104,8 @@ impl fmt::Display for Stmt {
             Stmt::Assert(ref expr, ref folding, _) => {
                 write!(f, "assert({:?}) {}", folding, expr)
             },
+            Stmt::Assume(ref expr, _) => write!(f, "assume {}", expr),
+            Stmt::Refute(ref expr, _) => write!(f, "refute {}", expr),
             Stmt::MethodCall(ref name, ref args, ref vars) => write!(
                 f,
                 "{} := {}({})",
@@ -157,13 +168,16 @@ impl fmt::Display for Stmt {
             ),
 
             Stmt::PackageMagicWand(
-                Expr::MagicWand(ref lhs, ref rhs, None, _),
+                Expr::MagicWand(ref lhs, ref rhs, ref borrow, _),
                 ref package_stmts,
                 ref label,
                 _vars,
                 _position,
             ) => {
-                writeln!(f, "package[{}] {}", label, lhs)?;
+                match borrow {
+                    Some(borrow) => writeln!(f, "package[{}, {:?}] {}", label, borrow, lhs)?,
+                    None => writeln!(f, "package[{}] {}", label, lhs)?,
+                }
                 writeln!(f, "    --* {}", rhs)?;
                 write!(f, "{{")?;
                 if !package_stmts.is_empty() {
@@ -175,10 +189,23 @@ impl fmt::Display for Stmt {
                 write!(f, "}}")
             }
 
+            Stmt::PackageMagicWand(ref wand, ref package_stmts, ref label, _vars, _position) => {
+                write!(f, "package[{}] {} {{", label, wand)?;
+                if !package_stmts.is_empty() {
+                    write!(f, "\n")?;
+                }
+                for stmt in package_stmts.iter() {
+                    writeln!(f, "    {}", stmt.to_string().replace("\n", "\n    "))?;
+                }
+                write!(f, "}}")
+            }
+
             Stmt::ApplyMagicWand(Expr::MagicWand(ref lhs, ref rhs, Some(borrow), _), _) => {
                 writeln!(f, "apply[{:?}] {} --* {}", borrow, lhs, rhs)
             }
 
+            Stmt::ApplyMagicWand(ref wand, _) => writeln!(f, "apply {}", wand),
+
             Stmt::ExpireBorrows(dag) => writeln!(f, "expire_borrows {:?}", dag),
 
             Stmt::If(ref guard, ref then_stmts) => {
@@ -191,8 +218,6 @@ impl fmt::Display for Stmt {
                 }
                 write!(f, "}}")
             }
-
-            ref x => unimplemented!("{:?}", x),
         }
     }
 }
@@ -269,6 +294,8 @@ pub trait StmtFolder {
             Stmt::Inhale(expr, folding) => self.fold_inhale(expr, folding),
             Stmt::Exhale(e, p) => self.fold_exhale(e, p),
             Stmt::Assert(expr, folding, pos) => self.fold_assert(expr, folding, pos),
+            Stmt::Assume(e, p) => self.fold_assume(e, p),
+            Stmt::Refute(e, p) => self.fold_refute(e, p),
             Stmt::MethodCall(s, ve, vv) => self.fold_method_call(s, ve, vv),
             Stmt::Assign(p, e, k) => self.fold_assign(p, e, k),
             Stmt::Fold(s, ve, perm, variant, p) => self.fold_fold(s, ve, perm, variant, p),
@@ -308,6 +335,14 @@ pub trait StmtFolder {
         Stmt::Assert(self.fold_expr(expr), folding, pos)
     }
 
+    fn fold_assume(&mut self, e: Expr, p: Position) -> Stmt {
+        Stmt::Assume(self.fold_expr(e), p)
+    }
+
+    fn fold_refute(&mut self, e: Expr, p: Position) -> Stmt {
+        Stmt::Refute(self.fold_expr(e), p)
+    }
+
     fn fold_method_call(
         &mut self,
         name: String,
@@ -410,6 +445,8 @@ pub trait StmtWalker {
             Stmt::Inhale(expr, folding) => self.walk_inhale(expr, folding),
             Stmt::Exhale(e, p) => self.walk_exhale(e, p),
             Stmt::Assert(expr, folding, pos) => self.walk_assert(expr, folding, pos),
+            Stmt::Assume(e, p) => self.walk_assume(e, p),
+            Stmt::Refute(e, p) => self.walk_refute(e, p),
             Stmt::MethodCall(s, ve, vv) => self.walk_method_call(s, ve, vv),
             Stmt::Assign(p, e, k) => self.walk_assign(p, e, k),
             Stmt::Fold(s, ve, perm, variant, pos) => self.walk_fold(s, ve, perm, variant, pos),
@@ -445,6 +482,14 @@ pub trait StmtWalker {
         self.walk_expr(expr);
     }
 
+    fn walk_assume(&mut self, expr: &Expr, _pos: &Position) {
+        self.walk_expr(expr);
+    }
+
+    fn walk_refute(&mut self, expr: &Expr, _pos: &Position) {
+        self.walk_expr(expr);
+    }
+
     fn walk_method_call(&mut self, _method_name: &str, args: &Vec<Expr>, targets: &Vec<LocalVar>) {
         for arg in args {
             self.walk_expr(arg);
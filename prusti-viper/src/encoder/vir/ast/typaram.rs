@@ -4,9 +4,16 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+//! The substitutions of the type variables of the names of the predicates.
+
+use encoder::vir::ast::Type;
 use regex::Regex;
 use std::collections::HashMap;
 
+lazy_static! {
+    static ref TYPARAM_RE: Regex = Regex::new("(__TYPARAM__\\$(.*?)\\$__)").unwrap();
+}
+
 pub struct Substs {
     regex: Regex,
     repls: HashMap<String, String>,
@@ -17,14 +24,31 @@ fn escape_dollars(s: &str) -> String {
 }
 
 impl Substs {
+    /// Computes the type substitution needed to go from the generic type `from` to the more
+    /// concrete type `to`, by matching the type arguments that are recorded in their kinds
+    /// (see `Type::learn_substs`). If the structure of the types is not known, the substitution
+    /// is learnt from their names.
+    pub fn learn_types(from: &Type, to: &Type) -> Self {
+        let mut substs = HashMap::new();
+        let is_learnt = from.learn_substs(to, &mut substs)
+            && from.clone().substitute(&substs).name() == to.name();
+        if is_learnt {
+            Substs {
+                regex: TYPARAM_RE.clone(),
+                repls: substs
+                    .into_iter()
+                    .map(|(type_var, subst)| (type_var, subst.name()))
+                    .collect(),
+            }
+        } else {
+            Substs::learn(&from.name(), &to.name())
+        }
+    }
+
     /// Takes the string representation of two types: `from` is the generic one; `to` is the more
     /// concrete one.
     /// This function will compute what is the type substitution needed to go from `from` to `to`.
     pub fn learn(from: &str, to: &str) -> Self {
-        lazy_static! {
-            static ref TYPARAM_RE: Regex = Regex::new("(__TYPARAM__\\$(.*?)\\$__)").unwrap();
-        }
-
         // Start with an empty `repls_regex`
         let mut repls_regex_str = String::new();
         repls_regex_str.push('^');
@@ -83,7 +107,11 @@ impl Substs {
         let mut last = 0;
         for matsh in self.regex.find_iter(inner1) {
             newstr.push_str(&inner1[last..matsh.start()]);
-            newstr.push_str(&self.repls[matsh.as_str()]);
+            // The type variables that are not substituted are left unchanged.
+            match self.repls.get(matsh.as_str()) {
+                Some(repl) => newstr.push_str(repl),
+                None => newstr.push_str(matsh.as_str()),
+            }
             last = matsh.end();
         }
         newstr.push_str(&inner1[last..]);
@@ -94,6 +122,7 @@ impl Substs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use encoder::vir::ast::TypedRefKind;
 
     fn test(outer1: &str, outer2: &str, inner1: &str, inner2: &str) {
         let substs = Substs::learn(outer1, outer2);
@@ -167,4 +196,58 @@ mod tests {
         let inner2 = "tuple2$__TYPARAM__$T$__$__TYPARAM__$T$__";
         test(outer1, outer2, inner1, inner2);
     }
+
+    fn type_var(name: &str) -> Type {
+        Type::TypedRef(format!("__TYPARAM__${}$__", name), TypedRefKind::TypeVar)
+    }
+
+    fn adt(name: &str, args: Vec<Type>) -> Type {
+        let arg_names: Vec<String> = args.iter().map(|arg| arg.name()).collect();
+        Type::TypedRef(
+            format!("m_{}$_beg_${}$_end_", name, arg_names.join("$_sep_$")),
+            TypedRefKind::Adt(args),
+        )
+    }
+
+    fn tuple(elems: Vec<Type>) -> Type {
+        let elem_names: Vec<String> = elems.iter().map(|elem| elem.name()).collect();
+        Type::TypedRef(
+            format!("tuple{}${}", elems.len(), elem_names.join("$")),
+            TypedRefKind::Tuple(elems),
+        )
+    }
+
+    #[test]
+    fn test_learn_types_of_nested_arguments() {
+        // The names alone are ambiguous: the first type variable could be `m_Pair`.
+        let from = tuple(vec![type_var("A"), type_var("B")]);
+        let pair = adt("Pair", vec![Type::typed_ref("u8"), Type::typed_ref("u16")]);
+        let to = tuple(vec![pair.clone(), Type::typed_ref("u32")]);
+        let substs = Substs::learn_types(&from, &to);
+        assert_eq!(
+            substs.apply("m_Box$_beg_$__TYPARAM__$A$__$_end_"),
+            adt("Box", vec![pair]).name()
+        );
+        assert_eq!(substs.apply("ref$__TYPARAM__$B$__"), "ref$u32");
+    }
+
+    #[test]
+    fn test_learn_types_keeps_unknown_type_variables() {
+        let from = adt("Number", vec![type_var("A")]);
+        let to = adt("Number", vec![Type::typed_ref("i16")]);
+        let substs = Substs::learn_types(&from, &to);
+        assert_eq!(
+            substs.apply("tuple2$__TYPARAM__$A$__$__TYPARAM__$C$__"),
+            "tuple2$i16$__TYPARAM__$C$__"
+        );
+    }
+
+    #[test]
+    fn test_learn_types_from_names() {
+        // The structure of the types is not known, thus the names are matched.
+        let from = Type::typed_ref("slice$__TYPARAM__$T$__");
+        let to = Type::typed_ref("slice$i32");
+        let substs = Substs::learn_types(&from, &to);
+        assert_eq!(substs.apply("ref$__TYPARAM__$T$__"), "ref$i32");
+    }
 }
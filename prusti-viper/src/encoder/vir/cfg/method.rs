@@ -154,6 +154,11 @@ impl CfgMethod {
         self.method_name.clone()
     }
 
+    /// Rename the method, e.g. to verify a copy of it as another Viper method.
+    pub fn set_name<S: ToString>(&mut self, method_name: S) {
+        self.method_name = method_name.to_string();
+    }
+
     pub(super) fn block_index(&self, index: usize) -> CfgBlockIndex {
         CfgBlockIndex {
             method_uuid: self.uuid,
@@ -269,6 +274,27 @@ impl CfgMethod {
         self.basic_blocks[index.block_index].successor = successor;
     }
 
+    /// The block where the execution of the method starts, if the method has a body.
+    pub fn start_block(&self) -> Option<CfgBlockIndex> {
+        if self.basic_blocks.is_empty() {
+            None
+        } else {
+            Some(self.block_index(0))
+        }
+    }
+
+    pub fn get_block_stmts(&self, index: CfgBlockIndex) -> &[Stmt] {
+        &self.basic_blocks[index.block_index].stmts
+    }
+
+    pub fn get_block_invariants(&self, index: CfgBlockIndex) -> &[Expr] {
+        &self.basic_blocks[index.block_index].invs
+    }
+
+    pub fn get_block_successor(&self, index: CfgBlockIndex) -> &Successor {
+        &self.basic_blocks[index.block_index].successor
+    }
+
     pub fn get_preceding(&self, target_index: CfgBlockIndex) -> Vec<CfgBlockIndex> {
         assert_eq!(
             self.uuid, target_index.method_uuid,
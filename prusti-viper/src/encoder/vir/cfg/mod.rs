@@ -13,5 +13,6 @@ pub use self::visitor::*;
 mod display;
 mod method;
 mod to_graphviz;
+mod to_text;
 mod to_viper;
 mod visitor;
@@ -0,0 +1,97 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use encoder::vir::ast::{Position, PositionId};
+use encoder::vir::cfg::method::*;
+use encoder::vir::to_text::{write_if, ToViperText, ViperText};
+use prusti_interface::config;
+
+impl ToViperText for CfgMethod {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        assert!(
+            !config::enable_verify_only_basic_block_path(),
+            "The source text of a method cannot be restricted to a path of basic blocks."
+        );
+        text.write(&format!("method {}() returns (", self.method_name));
+        for (index, local_var) in self.formal_returns.iter().enumerate() {
+            if index > 0 {
+                text.write(", ");
+            }
+            text.write(&format!("{}: ", local_var.name));
+            local_var.typ.to_viper_text(text);
+        }
+        text.write(")");
+        text.newline();
+        text.write("{");
+        text.indent();
+
+        for local_var in self.local_vars.iter() {
+            text.newline();
+            text.write(&format!("var {}: ", local_var.name));
+            local_var.typ.to_viper_text(text);
+        }
+
+        // Sort blocks by label, except for the first block
+        let mut blocks: Vec<_> = self.basic_blocks.iter().enumerate().skip(1).collect();
+        blocks.sort_by_key(|(index, _)| &self.basic_blocks_labels[*index]);
+        blocks.insert(0, (0, &self.basic_blocks[0]));
+
+        for (index, block) in blocks.into_iter() {
+            text.newline();
+            text.write(&format!("label {}", self.basic_blocks_labels[index]));
+            for inv in &block.invs {
+                text.write(" invariant ");
+                inv.to_viper_text(text);
+            }
+            for stmt in &block.stmts {
+                text.newline();
+                stmt.to_viper_text(text);
+            }
+            text.newline();
+            self.successor_to_viper_text(index, &block.successor, text);
+        }
+        text.newline();
+        text.write(&format!("label {}", RETURN_LABEL));
+
+        text.dedent();
+        text.newline();
+        text.write("}");
+    }
+}
+
+impl CfgMethod {
+    fn successor_to_viper_text(&self, index: usize, successor: &Successor, text: &mut ViperText) {
+        let goto = |text: &mut ViperText, label: &str| text.write(&format!("goto {}", label));
+        match *successor {
+            Successor::Undefined => panic!(
+                "CFG block '{}' has no successor.",
+                self.basic_blocks_labels[index]
+            ),
+            Successor::Return => goto(text, RETURN_LABEL),
+            Successor::BackEdge(target) => {
+                if config::use_assume_false_back_edges() {
+                    text.mark(&Position::new(0, 0, PositionId::BACK_EDGE));
+                    text.write("inhale false");
+                    text.newline();
+                    goto(text, RETURN_LABEL);
+                } else {
+                    goto(text, &self.basic_blocks_labels[target.block_index]);
+                }
+            }
+            Successor::Goto(target) => goto(text, &self.basic_blocks_labels[target.block_index]),
+            Successor::GotoSwitch(ref successors, ref default_target) => {
+                for (test, target) in successors {
+                    write_if(text, test, |text| {
+                        text.newline();
+                        goto(text, &self.basic_blocks_labels[target.block_index]);
+                    });
+                    text.newline();
+                }
+                goto(text, &self.basic_blocks_labels[default_target.block_index]);
+            }
+        }
+    }
+}
@@ -5,7 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use encoder::vir::cfg::method::*;
-use encoder::vir::ast::Position;
+use encoder::vir::ast::{Position, PositionId};
 use encoder::vir::to_viper::{ToViper, ToViperDecl};
 use prusti_interface::config;
 use viper;
@@ -114,7 +114,7 @@ impl CfgMethod {
 
         for label in config::delete_basic_blocks() {
             let (index, block) = remaining_blocks.remove(&label).unwrap();
-            let fake_position = Position::new(0, 0, "deleted".to_string());
+            let fake_position = Position::new(0, 0, PositionId::DELETED);
             let stmts: Vec<viper::Stmt> = vec![
                 ast.label(&label, &block.invs.to_viper(ast)),
                 ast.inhale(
@@ -164,7 +164,7 @@ fn successor_to_viper<'a>(
         Successor::Return => ast.goto(RETURN_LABEL),
         Successor::BackEdge(target) => {
             if config::use_assume_false_back_edges() {
-                let fake_position = Position::new(0, 0, "back edge".to_string());
+                let fake_position = Position::new(0, 0, PositionId::BACK_EDGE);
                 ast.seqn(
                     &vec![
                         ast.inhale(
@@ -177,10 +177,15 @@ pub trait CfgReplacer<BranchCtxt: Debug + Clone, Action: CheckNoOpAction + Debug
                         let new_block_index = new_cfg.add_block(
                             &new_label,
                             vec![],
-                            vec![Stmt::comment(format!(
-                                "========== {} ==========",
-                                new_label
-                            ))],
+                            vec![
+                                Stmt::comment(format!("========== {} ==========", new_label)),
+                                Stmt::comment(format!(
+                                    "[join] from {} to {}, merging {} branches",
+                                    cfg.basic_blocks_labels[src_index],
+                                    cfg.basic_blocks_labels[curr_index],
+                                    incoming_edges.len()
+                                )),
+                            ],
                         );
                         let stmts_to_add = self.perform_prejoin_action(new_block_index, action);
                         new_cfg.add_stmts(new_block_index, stmts_to_add);
@@ -343,6 +348,17 @@ impl CfgMethod {
         }
     }
 
+    pub fn walk_invariants<F>(&self, mut walker: F)
+    where
+        F: FnMut(&Expr),
+    {
+        for block in self.basic_blocks.iter() {
+            for inv in block.invs.iter() {
+                walker(inv);
+            }
+        }
+    }
+
     pub fn walk_successors<F>(&self, mut walker: F)
     where
         F: FnMut(&Successor),
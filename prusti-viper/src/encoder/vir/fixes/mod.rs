@@ -8,6 +8,10 @@
 
 mod ghost_vars;
 mod loops;
+mod positions;
+mod refutations;
 
 pub use self::ghost_vars::fix_ghost_vars;
 pub use self::loops::havoc_assigned_locals;
+pub use self::positions::fix_default_positions;
+pub use self::refutations::lower_refutations;
@@ -0,0 +1,112 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Fix default positions.
+
+use super::super::ast;
+use super::super::cfg;
+use std::fmt;
+use std::mem;
+
+/// The verification errors of a statement or an expression with a
+/// `Position::default()` position cannot be reported with a span. Since
+/// many positions are defaulted by the constructors of the encoding, this
+/// pass replaces the default positions of the statements that can fail
+/// (and of their expressions) with the position of the closest preceding
+/// statement of the same block, or with `method_pos` at the beginning of
+/// a block. Each replacement is logged, so that the encoder can be fixed.
+pub fn fix_default_positions(
+    mut method: cfg::CfgMethod,
+    method_pos: ast::Position,
+) -> cfg::CfgMethod {
+    let method_name = method.name();
+    for (block_index, block) in method.basic_blocks.iter_mut().enumerate() {
+        let mut fixer = PositionFixer {
+            method_name: &method_name,
+            block_index,
+            enclosing_pos: method_pos.clone(),
+        };
+        let stmts = mem::replace(&mut block.stmts, vec![]);
+        block.stmts = stmts.into_iter().map(|stmt| fixer.fix_stmt(stmt)).collect();
+    }
+    method
+}
+
+struct PositionFixer<'a> {
+    method_name: &'a str,
+    block_index: usize,
+    /// The position of the closest preceding statement with a position.
+    enclosing_pos: ast::Position,
+}
+
+impl<'a> PositionFixer<'a> {
+    /// Replace `pos`, if it is a default position, with the enclosing one.
+    fn fix_pos(
+        &mut self,
+        pos: ast::Position,
+        stmt_kind: &str,
+        subject: &fmt::Display,
+    ) -> ast::Position {
+        if pos.is_default() {
+            debug!(
+                "{} without position in block {} of method {}: {}",
+                stmt_kind, self.block_index, self.method_name, subject
+            );
+            self.enclosing_pos.clone()
+        } else {
+            self.enclosing_pos = pos.clone();
+            pos
+        }
+    }
+
+    fn fix_stmt(&mut self, stmt: ast::Stmt) -> ast::Stmt {
+        match stmt {
+            ast::Stmt::Exhale(expr, pos) => {
+                let pos = self.fix_pos(pos, "exhale", &expr);
+                ast::Stmt::Exhale(expr.set_default_pos(pos.clone()), pos)
+            }
+            ast::Stmt::Assert(expr, folding, pos) => {
+                let pos = self.fix_pos(pos, "assert", &expr);
+                ast::Stmt::Assert(expr.set_default_pos(pos.clone()), folding, pos)
+            }
+            ast::Stmt::Assume(expr, pos) => {
+                let pos = self.fix_pos(pos, "assume", &expr);
+                ast::Stmt::Assume(expr.set_default_pos(pos.clone()), pos)
+            }
+            ast::Stmt::Refute(expr, pos) => {
+                let pos = self.fix_pos(pos, "refute", &expr);
+                ast::Stmt::Refute(expr.set_default_pos(pos.clone()), pos)
+            }
+            ast::Stmt::Obtain(expr, pos) => {
+                let pos = self.fix_pos(pos, "obtain", &expr);
+                ast::Stmt::Obtain(expr.set_default_pos(pos.clone()), pos)
+            }
+            ast::Stmt::ApplyMagicWand(wand, pos) => {
+                let pos = self.fix_pos(pos, "apply", &wand);
+                ast::Stmt::ApplyMagicWand(wand.set_default_pos(pos.clone()), pos)
+            }
+            ast::Stmt::Fold(name, args, perm, variant, pos) => {
+                let pos = self.fix_pos(pos, "fold", &name);
+                ast::Stmt::Fold(name, args, perm, variant, pos)
+            }
+            ast::Stmt::PackageMagicWand(wand, body, label, vars, pos) => {
+                let pos = self.fix_pos(pos, "package", &wand);
+                let wand = wand.set_default_pos(pos.clone());
+                let body = body.into_iter().map(|stmt| self.fix_stmt(stmt)).collect();
+                self.enclosing_pos = pos.clone();
+                ast::Stmt::PackageMagicWand(wand, body, label, vars, pos)
+            }
+            ast::Stmt::If(guard, then_stmts) => {
+                let then_stmts = then_stmts
+                    .into_iter()
+                    .map(|stmt| self.fix_stmt(stmt))
+                    .collect();
+                ast::Stmt::If(guard, then_stmts)
+            }
+            stmt => stmt,
+        }
+    }
+}
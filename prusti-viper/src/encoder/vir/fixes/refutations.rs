@@ -0,0 +1,54 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Lower the refute statements, which Viper does not support.
+
+use super::super::{ast, cfg};
+use std::mem;
+
+/// Replace each `refute expr` statement with
+///
+/// ```viper
+/// if (guard) {
+///     assert expr
+///     inhale false
+/// }
+/// ```
+///
+/// where `guard` is a fresh local variable with an unknown value. The assertion is thus
+/// checked in all the executions that reach the statement, and the failure of the assertion
+/// does not stop the verification of the rest of the method.
+pub fn lower_refutations(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
+    for block_index in 0..method.basic_blocks.len() {
+        let stmts = mem::replace(&mut method.basic_blocks[block_index].stmts, vec![]);
+        let lowered_stmts = lower_stmts(&mut method, stmts);
+        method.basic_blocks[block_index].stmts = lowered_stmts;
+    }
+    method
+}
+
+fn lower_stmts(method: &mut cfg::CfgMethod, stmts: Vec<ast::Stmt>) -> Vec<ast::Stmt> {
+    stmts
+        .into_iter()
+        .map(|stmt| match stmt {
+            ast::Stmt::Refute(expr, pos) => {
+                let guard = method.add_fresh_local_var(ast::Type::Bool);
+                ast::Stmt::If(
+                    guard.into(),
+                    vec![
+                        ast::Stmt::comment(format!("refute {}", expr)),
+                        ast::Stmt::Assert(expr, ast::FoldingBehaviour::None, pos.clone()),
+                        ast::Stmt::Assume(false.into(), pos),
+                    ],
+                )
+            }
+            ast::Stmt::If(guard, then_stmts) => {
+                ast::Stmt::If(guard, lower_stmts(method, then_stmts))
+            }
+            stmt => stmt,
+        })
+        .collect()
+}
@@ -0,0 +1,276 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The Viper domain of the mathematical maps of `Type::TypedMap`.
+
+use viper;
+use viper::AstFactory;
+
+/// The name of the Viper domain of the mathematical maps.
+const MAP_DOMAIN_NAME: &str = "Map$";
+
+const MAP_FUNCS: [MapFunc; 4] = [
+    MapFunc::Lookup,
+    MapFunc::Contains,
+    MapFunc::Update,
+    MapFunc::Len,
+];
+
+/// The functions of the Viper domain of the mathematical maps.
+#[derive(Clone, Copy, Debug)]
+pub enum MapFunc {
+    /// `map$lookup(map, key)`: the value of `key` in `map`.
+    Lookup,
+    /// `map$contains(map, key)`: whether `map` has a value for `key`.
+    Contains,
+    /// `map$update(map, key, value)`: `map`, where `key` has the value `value`.
+    Update,
+    /// `map$len(map)`: the number of keys of `map`.
+    Len,
+}
+
+impl MapFunc {
+    fn name(self) -> &'static str {
+        match self {
+            MapFunc::Lookup => "map$lookup",
+            MapFunc::Contains => "map$contains",
+            MapFunc::Update => "map$update",
+            MapFunc::Len => "map$len",
+        }
+    }
+
+    fn to_viper<'v>(self, ast: &AstFactory<'v>) -> viper::DomainFunc<'v> {
+        let (key_type, value_type) = type_vars(ast);
+        let map = ast.local_var_decl("m", map_type(ast, key_type, value_type));
+        let key = ast.local_var_decl("k", key_type);
+        let value = ast.local_var_decl("v", value_type);
+        let (formal_args, typ) = match self {
+            MapFunc::Lookup => (vec![map, key], value_type),
+            MapFunc::Contains => (vec![map, key], ast.bool_type()),
+            MapFunc::Update => (vec![map, key, value], map_type(ast, key_type, value_type)),
+            MapFunc::Len => (vec![map], ast.int_type()),
+        };
+        ast.domain_func(self.name(), &formal_args, typ, false, MAP_DOMAIN_NAME)
+    }
+
+    /// The declaration of the function in the source text of the domain.
+    fn to_text(self) -> String {
+        let (formal_args, typ) = match self {
+            MapFunc::Lookup => ("m: Map$[K, V], k: K", "V"),
+            MapFunc::Contains => ("m: Map$[K, V], k: K", "Bool"),
+            MapFunc::Update => ("m: Map$[K, V], k: K, v: V", "Map$[K, V]"),
+            MapFunc::Len => ("m: Map$[K, V]", "Int"),
+        };
+        format!("function {}({}): {}", self.name(), formal_args, typ)
+    }
+}
+
+fn type_vars<'v>(ast: &AstFactory<'v>) -> (viper::Type<'v>, viper::Type<'v>) {
+    (ast.type_var("K"), ast.type_var("V"))
+}
+
+/// The type of the maps from `key_type` to `value_type`.
+pub fn map_type<'v>(
+    ast: &AstFactory<'v>,
+    key_type: viper::Type<'v>,
+    value_type: viper::Type<'v>,
+) -> viper::Type<'v> {
+    let (key_var, value_var) = type_vars(ast);
+    ast.domain_type(
+        MAP_DOMAIN_NAME,
+        &[(key_var, key_type), (value_var, value_type)],
+        &[key_var, value_var],
+    )
+}
+
+/// Apply a function of the domain to arguments of maps from `key_type` to `value_type`.
+pub fn map_func_app<'v>(
+    ast: &AstFactory<'v>,
+    func: MapFunc,
+    args: &[viper::Expr<'v>],
+    key_type: viper::Type<'v>,
+    value_type: viper::Type<'v>,
+) -> viper::Expr<'v> {
+    let (key_var, value_var) = type_vars(ast);
+    ast.domain_func_app(
+        func.to_viper(ast),
+        args,
+        &[(key_var, key_type), (value_var, value_type)],
+    )
+}
+
+/// The Viper domain of the mathematical maps, with the axioms that relate lookups,
+/// updates and lengths of maps.
+pub fn map_domain<'v>(ast: &AstFactory<'v>) -> viper::Domain<'v> {
+    let (key_type, value_type) = type_vars(ast);
+    let map_decl = ast.local_var_decl("m", map_type(ast, key_type, value_type));
+    let key_decl = ast.local_var_decl("k", key_type);
+    let other_key_decl = ast.local_var_decl("k2", key_type);
+    let value_decl = ast.local_var_decl("v", value_type);
+    let map = ast.local_var("m", map_type(ast, key_type, value_type));
+    let key = ast.local_var("k", key_type);
+    let other_key = ast.local_var("k2", key_type);
+    let value = ast.local_var("v", value_type);
+    let app = |func: MapFunc, args: &[viper::Expr<'v>]| {
+        map_func_app(ast, func, args, key_type, value_type)
+    };
+
+    let len = app(MapFunc::Len, &[map]);
+    let contains = app(MapFunc::Contains, &[map, key]);
+    let updated = app(MapFunc::Update, &[map, key, value]);
+    let updated_len = app(MapFunc::Len, &[updated]);
+    let lookup_updated = app(MapFunc::Lookup, &[updated, other_key]);
+    let contains_updated = app(MapFunc::Contains, &[updated, other_key]);
+    let keys_differ = ast.ne_cmp(key, other_key);
+
+    let axioms = vec![
+        (
+            "map$len_non_negative",
+            ast.forall(
+                &[map_decl],
+                &[ast.trigger(&[len])],
+                ast.ge_cmp(len, ast.int_lit(0)),
+            ),
+        ),
+        (
+            "map$lookup_updated",
+            ast.forall(
+                &[map_decl, key_decl, value_decl],
+                &[ast.trigger(&[updated])],
+                ast.eq_cmp(app(MapFunc::Lookup, &[updated, key]), value),
+            ),
+        ),
+        (
+            "map$contains_updated",
+            ast.forall(
+                &[map_decl, key_decl, value_decl],
+                &[ast.trigger(&[updated])],
+                app(MapFunc::Contains, &[updated, key]),
+            ),
+        ),
+        (
+            "map$lookup_other_key",
+            ast.forall(
+                &[map_decl, key_decl, other_key_decl, value_decl],
+                &[ast.trigger(&[lookup_updated])],
+                ast.implies(
+                    keys_differ,
+                    ast.eq_cmp(lookup_updated, app(MapFunc::Lookup, &[map, other_key])),
+                ),
+            ),
+        ),
+        (
+            "map$contains_other_key",
+            ast.forall(
+                &[map_decl, key_decl, other_key_decl, value_decl],
+                &[ast.trigger(&[contains_updated])],
+                ast.implies(
+                    keys_differ,
+                    ast.eq_cmp(contains_updated, app(MapFunc::Contains, &[map, other_key])),
+                ),
+            ),
+        ),
+        (
+            "map$len_updated",
+            ast.forall(
+                &[map_decl, key_decl, value_decl],
+                &[ast.trigger(&[updated_len])],
+                ast.eq_cmp(
+                    updated_len,
+                    ast.cond_exp(contains, len, ast.add(len, ast.int_lit(1))),
+                ),
+            ),
+        ),
+        (
+            "map$len_empty",
+            ast.forall(
+                &[map_decl, key_decl],
+                &[ast.trigger(&[len, contains])],
+                ast.implies(ast.eq_cmp(len, ast.int_lit(0)), ast.not(contains)),
+            ),
+        ),
+    ];
+
+    let functions: Vec<_> = MAP_FUNCS
+        .iter()
+        .map(|func| func.to_viper(ast))
+        .collect();
+    let axioms: Vec<_> = axioms
+        .into_iter()
+        .map(|(name, expr)| ast.named_domain_axiom(name, expr, MAP_DOMAIN_NAME))
+        .collect();
+    ast.domain(MAP_DOMAIN_NAME, &functions, &axioms, &[key_type, value_type])
+}
+
+/// The source text of `map_domain`.
+pub fn map_domain_text() -> String {
+    let map = "m: Map$[K, V]";
+    let axioms = [
+        (
+            "map$len_non_negative",
+            format!("forall {} :: {{ map$len(m) }} map$len(m) >= 0", map),
+        ),
+        (
+            "map$lookup_updated",
+            format!(
+                "forall {}, k: K, v: V :: {{ map$update(m, k, v) }} \
+                 map$lookup(map$update(m, k, v), k) == v",
+                map
+            ),
+        ),
+        (
+            "map$contains_updated",
+            format!(
+                "forall {}, k: K, v: V :: {{ map$update(m, k, v) }} \
+                 map$contains(map$update(m, k, v), k)",
+                map
+            ),
+        ),
+        (
+            "map$lookup_other_key",
+            format!(
+                "forall {}, k: K, k2: K, v: V :: {{ map$lookup(map$update(m, k, v), k2) }} \
+                 k != k2 ==> map$lookup(map$update(m, k, v), k2) == map$lookup(m, k2)",
+                map
+            ),
+        ),
+        (
+            "map$contains_other_key",
+            format!(
+                "forall {}, k: K, k2: K, v: V :: {{ map$contains(map$update(m, k, v), k2) }} \
+                 k != k2 ==> map$contains(map$update(m, k, v), k2) == map$contains(m, k2)",
+                map
+            ),
+        ),
+        (
+            "map$len_updated",
+            format!(
+                "forall {}, k: K, v: V :: {{ map$len(map$update(m, k, v)) }} \
+                 map$len(map$update(m, k, v)) == \
+                 (map$contains(m, k) ? map$len(m) : map$len(m) + 1)",
+                map
+            ),
+        ),
+        (
+            "map$len_empty",
+            format!(
+                "forall {}, k: K :: {{ map$len(m), map$contains(m, k) }} \
+                 map$len(m) == 0 ==> !map$contains(m, k)",
+                map
+            ),
+        ),
+    ];
+
+    let mut lines = vec![format!("domain {}[K, V] {{", MAP_DOMAIN_NAME)];
+    lines.extend(MAP_FUNCS.iter().map(|func| format!("  {}", func.to_text())));
+    lines.extend(
+        axioms
+            .iter()
+            .map(|(name, axiom)| format!("  axiom {} {{ {} }}", name, axiom)),
+    );
+    lines.push("}".to_string());
+    lines.join("\n")
+}
@@ -7,6 +7,8 @@
 pub use self::ast::*;
 pub use self::cfg::*;
 pub use self::conversions::*;
+pub use self::map_domain::{map_domain, map_domain_text};
+pub use self::to_text::*;
 pub use self::to_viper::*;
 
 mod ast;
@@ -14,6 +16,10 @@ pub mod borrows;
 mod cfg;
 mod conversions;
 pub mod fixes;
+mod map_domain;
 pub mod optimisations;
+pub mod passes;
+mod to_text;
 mod to_viper;
+pub mod typecheck;
 pub mod utils;
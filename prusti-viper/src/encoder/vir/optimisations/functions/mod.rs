@@ -7,7 +7,5 @@
 //! A module that contains optimisations for functions.
 
 mod inliner;
-mod simplifier;
 
 pub use self::inliner::inline_constant_functions;
-pub use self::simplifier::Simplifier;
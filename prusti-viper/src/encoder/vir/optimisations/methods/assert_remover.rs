@@ -9,15 +9,19 @@
 use encoder::vir::cfg;
 use encoder::vir::{Const, Expr, Stmt};
 
-/// Remove trivial assertions:
+/// Remove trivial assertions and assumptions:
 /// * `assert true`
 /// * `exhale true`
+/// * `inhale true`
+/// * `assume true`
 pub fn remove_trivial_assertions(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
     method.retain_stmts(|stmt| {
         // Remove those statements marked with `false`
         match stmt {
             Stmt::Assert(Expr::Const(Const::Bool(true), _), _, _) => false,
             Stmt::Exhale(Expr::Const(Const::Bool(true), _), _) => false,
+            Stmt::Inhale(Expr::Const(Const::Bool(true), _), _) => false,
+            Stmt::Assume(Expr::Const(Const::Bool(true), _), _) => false,
             _ => true, // Keep the rest
         }
     });
@@ -0,0 +1,40 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Optimisation that removes the statements used only by the fold/unfold algorithm.
+
+use encoder::vir::cfg;
+use encoder::vir::Stmt;
+use std::mem;
+
+/// Remove the statements that guide the fold/unfold algorithm and that are
+/// emitted as comments, once the algorithm has run:
+/// * `begin frame` and `end frame`
+/// * `obtain ...`
+pub fn remove_fold_unfold_markers(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
+    for block in &mut method.basic_blocks {
+        let stmts = mem::replace(&mut block.stmts, vec![]);
+        block.stmts = remove_markers(stmts);
+    }
+    method
+}
+
+fn remove_markers(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    stmts
+        .into_iter()
+        .filter(|stmt| match stmt {
+            Stmt::BeginFrame | Stmt::EndFrame | Stmt::Obtain(..) => false,
+            _ => true, // Keep the rest
+        })
+        .map(|stmt| match stmt {
+            Stmt::If(guard, then_stmts) => Stmt::If(guard, remove_markers(then_stmts)),
+            Stmt::PackageMagicWand(wand, body, label, vars, pos) => {
+                Stmt::PackageMagicWand(wand, remove_markers(body), label, vars, pos)
+            }
+            stmt => stmt,
+        })
+        .collect()
+}
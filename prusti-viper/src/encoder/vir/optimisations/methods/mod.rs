@@ -8,10 +8,14 @@
 
 mod empty_if_remover;
 mod assert_remover;
+mod marker_remover;
 mod var_remover;
 mod purifier;
+mod obligation_splitter;
 
 pub use self::empty_if_remover::remove_empty_if;
 pub use self::assert_remover::remove_trivial_assertions;
+pub use self::marker_remover::remove_fold_unfold_markers;
 pub use self::var_remover::remove_unused_vars;
 pub use self::purifier::purify_vars;
+pub use self::obligation_splitter::{obligation_positions, split_obligations};
@@ -0,0 +1,147 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Transformation that splits the assertions of a method into separate methods, so that the
+//! verification of a method that times out because of a single assertion still reports the
+//! result of the other assertions.
+//!
+//! The obligations are the conjuncts of the assertions, e.g. each conjunct of the functional
+//! postcondition. An assertion that mentions permissions is a single obligation, because its
+//! conjuncts cannot be assumed separately.
+
+use encoder::vir::cfg;
+use encoder::vir::{BinOpKind, Const, Expr, Position, Stmt};
+use std::mem;
+
+/// The positions of the obligations of the method, in the order in which `split_obligations`
+/// numbers them. The position of a conjunct is its own one, if any, and otherwise the one of
+/// its assertion. The assertions in the bodies of magic wands are not counted, because they
+/// cannot be turned into assumptions.
+pub fn obligation_positions(method: &cfg::CfgMethod) -> Vec<Position> {
+    let mut positions = vec![];
+    for block in &method.basic_blocks {
+        collect_obligation_positions(&block.stmts, &mut positions);
+    }
+    positions
+}
+
+fn collect_obligation_positions(stmts: &[Stmt], positions: &mut Vec<Position>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Assert(expr, _, pos) => {
+                for conjunct in obligations_of(expr) {
+                    if conjunct.pos().is_default() {
+                        positions.push(pos.clone());
+                    } else {
+                        positions.push(conjunct.pos().clone());
+                    }
+                }
+            }
+            Stmt::If(_, then_stmts) => collect_obligation_positions(then_stmts, positions),
+            _ => {}
+        }
+    }
+}
+
+/// The obligations of an assertion: its conjuncts if it is pure, otherwise the assertion
+/// itself.
+fn obligations_of(expr: &Expr) -> Vec<Expr> {
+    if !expr.is_pure() {
+        return vec![expr.clone()];
+    }
+    let mut conjuncts = vec![];
+    collect_conjuncts(expr, &mut conjuncts);
+    if conjuncts.is_empty() {
+        // The assertion is trivially true, but it is still an obligation.
+        conjuncts.push(expr.clone());
+    }
+    conjuncts
+}
+
+fn collect_conjuncts(expr: &Expr, conjuncts: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinOp(BinOpKind::And, box left, box right, _) => {
+            collect_conjuncts(left, conjuncts);
+            collect_conjuncts(right, conjuncts);
+        }
+        Expr::Const(Const::Bool(true), _) => {}
+        _ => conjuncts.push(expr.clone()),
+    }
+}
+
+/// Replace each assertion by one assertion for each of its obligations, in order. Each new
+/// assertion keeps the position of the original one, which determines the reported error.
+fn split_conjunctions(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut result = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+        match stmt {
+            Stmt::Assert(expr, folding, pos) => {
+                for conjunct in obligations_of(&expr) {
+                    result.push(Stmt::Assert(conjunct, folding, pos.clone()));
+                }
+            }
+            Stmt::If(guard, then_stmts) => {
+                result.push(Stmt::If(guard, split_conjunctions(then_stmts)));
+            }
+            stmt => result.push(stmt),
+        }
+    }
+    result
+}
+
+/// Split a method with more than one obligation into one method per obligation. The `i`-th
+/// method, named `<method>$obligation$<i>`, checks the `i`-th obligation and assumes the other
+/// ones. The exhales are checked by all the methods.
+pub fn split_obligations(mut method: cfg::CfgMethod) -> Vec<cfg::CfgMethod> {
+    let num_obligations = obligation_positions(&method).len();
+    if num_obligations <= 1 {
+        return vec![method];
+    }
+    for block in &mut method.basic_blocks {
+        let stmts = mem::replace(&mut block.stmts, vec![]);
+        block.stmts = split_conjunctions(stmts);
+    }
+    let method_name = method.name();
+    (0..num_obligations)
+        .map(|obligation_index| {
+            let mut obligation_method = method.clone();
+            obligation_method.set_name(format!("{}$obligation${}", method_name, obligation_index));
+            let mut assertion_index = 0;
+            for block in &mut obligation_method.basic_blocks {
+                let stmts = mem::replace(&mut block.stmts, vec![]);
+                block.stmts =
+                    assume_other_assertions(stmts, obligation_index, &mut assertion_index);
+            }
+            obligation_method
+        })
+        .collect()
+}
+
+fn assume_other_assertions(
+    stmts: Vec<Stmt>,
+    obligation_index: usize,
+    assertion_index: &mut usize,
+) -> Vec<Stmt> {
+    stmts
+        .into_iter()
+        .map(|stmt| match stmt {
+            Stmt::Assert(expr, folding, pos) => {
+                let is_obligation = *assertion_index == obligation_index;
+                *assertion_index += 1;
+                if is_obligation {
+                    Stmt::Assert(expr, folding, pos)
+                } else {
+                    Stmt::Assume(expr, pos)
+                }
+            }
+            Stmt::If(guard, then_stmts) => Stmt::If(
+                guard,
+                assume_other_assertions(then_stmts, obligation_index, assertion_index),
+            ),
+            stmt => stmt,
+        })
+        .collect()
+}
@@ -24,7 +24,7 @@ pub fn purify_vars(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
         replacements: HashMap::new(),
     };
     // Since we cannot purify the return value, mark it as impure.
-    let return_var = ast::LocalVar::new("_0".to_string(), ast::Type::TypedRef("".to_string()));
+    let return_var = ast::LocalVar::new("_0".to_string(), ast::Type::typed_ref(""));
     collector.impure_vars.insert(return_var);
     method.walk_statements(|stmt| {
         ast::StmtWalker::walk(&mut collector, stmt);
@@ -182,6 +182,15 @@ impl ast::ExprWalker for VarCollector {
             self.all_vars.remove(var);
         }
     }
+    fn walk_exists(
+        &mut self,
+        vars: &Vec<ast::LocalVar>,
+        triggers: &Vec<ast::Trigger>,
+        body: &ast::Expr,
+        pos: &ast::Position
+    ) {
+        self.walk_forall(vars, triggers, body, pos);
+    }
 }
 
 impl ast::StmtWalker for VarCollector {
@@ -418,7 +427,10 @@ impl ast::StmtFolder for VarPurifier {
             name = match replacement.typ {
                 ast::Type::Int => "builtin$havoc_int",
                 ast::Type::Bool => "builtin$havoc_bool",
-                ast::Type::TypedRef(_) => "builtin$havoc_ref",
+                ast::Type::TypedRef(..) => "builtin$havoc_ref",
+                ast::Type::TypedMap(..) | ast::Type::TypedSeq(..) | ast::Type::Domain(..) => {
+                    unreachable!("maps, sequences and domain values are not stored in variables")
+                }
             }.to_string();
             targets = vec![replacement];
         }
@@ -12,7 +12,21 @@ use std::collections::HashSet;
 use std::mem;
 
 /// Remove unused temporary variables and related inhale statements.
+///
+/// A variable that is only assigned is unused, and its assignments are
+/// removed as well, unless evaluating the assigned expression can fail. This
+/// can make other variables unused, so this is repeated until a fixpoint.
 pub fn remove_unused_vars(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
+    loop {
+        let num_local_vars = method.local_vars.len();
+        method = remove_unused_vars_once(method);
+        if method.local_vars.len() == num_local_vars {
+            return method;
+        }
+    }
+}
+
+fn remove_unused_vars_once(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
     let mut collector = UsedVarCollector {
         used_vars: HashSet::new(),
     };
@@ -41,13 +55,9 @@ pub fn remove_unused_vars(mut method: cfg::CfgMethod) -> cfg::CfgMethod {
     let mut remover = UnusedVarRemover {
         unused_vars: unused_vars,
     };
-    let mut sentinel_stmt = ast::Stmt::Comment(String::from("moved out stmt"));
     for block in &mut method.basic_blocks {
-        for stmt in &mut block.stmts {
-            mem::swap(&mut sentinel_stmt, stmt);
-            sentinel_stmt = ast::StmtFolder::fold(&mut remover, sentinel_stmt);
-            mem::swap(&mut sentinel_stmt, stmt);
-        }
+        let stmts = mem::replace(&mut block.stmts, vec![]);
+        block.stmts = remover.fold_stmts(stmts);
     }
     method
 }
@@ -83,6 +93,14 @@ impl ast::StmtWalker for UsedVarCollector {
     fn walk_expr(&mut self, expr: &ast::Expr) {
         ast::ExprWalker::walk(self, expr);
     }
+    fn walk_assign(&mut self, target: &ast::Expr, expr: &ast::Expr, _kind: &ast::AssignKind) {
+        // Assigning a variable does not use it, unless the assignment cannot be removed.
+        match target {
+            ast::Expr::Local(..) if !may_fail(expr) => {}
+            _ => self.walk_expr(target),
+        }
+        self.walk_expr(expr);
+    }
     fn walk_local_var(&mut self, local_var: &ast::LocalVar) {
         self.used_vars.insert(local_var.name.clone());
     }
@@ -104,10 +122,69 @@ impl ast::StmtWalker for UsedVarCollector {
     }
 }
 
+/// Could the evaluation of `expr` fail? Function applications have preconditions,
+/// divisions and modulos fail on zero, field accesses need a permission and sequence
+/// indices must be in bounds.
+fn may_fail(expr: &ast::Expr) -> bool {
+    struct PartialOpFinder {
+        found: bool,
+    }
+    impl ast::ExprWalker for PartialOpFinder {
+        fn walk_bin_op(
+            &mut self,
+            op: ast::BinOpKind,
+            left: &ast::Expr,
+            right: &ast::Expr,
+            _pos: &ast::Position,
+        ) {
+            if op == ast::BinOpKind::Div || op == ast::BinOpKind::Mod {
+                self.found = true;
+            }
+            self.walk(left);
+            self.walk(right);
+        }
+        fn walk_func_app(
+            &mut self,
+            _name: &str,
+            _args: &Vec<ast::Expr>,
+            _formal_args: &Vec<ast::LocalVar>,
+            _return_type: &ast::Type,
+            _pos: &ast::Position,
+        ) {
+            self.found = true;
+        }
+        fn walk_field(&mut self, _receiver: &ast::Expr, _field: &ast::Field, _pos: &ast::Position) {
+            self.found = true;
+        }
+        fn walk_seq_index(&mut self, _seq: &ast::Expr, _index: &ast::Expr, _pos: &ast::Position) {
+            self.found = true;
+        }
+    }
+    let mut finder = PartialOpFinder { found: false };
+    ast::ExprWalker::walk(&mut finder, expr);
+    finder.found
+}
+
 struct UnusedVarRemover {
     unused_vars: HashSet<ast::LocalVar>,
 }
 
+impl UnusedVarRemover {
+    /// Remove the assignments to unused variables and fold the other statements.
+    fn fold_stmts(&mut self, stmts: Vec<ast::Stmt>) -> Vec<ast::Stmt> {
+        stmts
+            .into_iter()
+            .filter(|stmt| match stmt {
+                ast::Stmt::Assign(ast::Expr::Local(ref var, _), _, _) => {
+                    !self.unused_vars.contains(var)
+                }
+                _ => true,
+            })
+            .map(|stmt| ast::StmtFolder::fold(self, stmt))
+            .collect()
+    }
+}
+
 impl ast::ExprFolder for UnusedVarRemover {
     fn fold_predicate_access_predicate(
         &mut self,
@@ -132,8 +209,7 @@ impl ast::ExprFolder for UnusedVarRemover {
         perm_amount: ast::PermAmount,
         pos: ast::Position,
     ) -> ast::Expr {
-        let var = expr.get_base();
-        if self.unused_vars.contains(&var) {
+        if expr.is_place() && self.unused_vars.contains(&expr.get_base()) {
             return true.into();
         }
         ast::Expr::FieldAccessPredicate(expr, perm_amount, pos)
@@ -144,4 +220,50 @@ impl ast::StmtFolder for UnusedVarRemover {
     fn fold_expr(&mut self, e: ast::Expr) -> ast::Expr {
         ast::ExprFolder::fold(self, e)
     }
+    fn fold_package_magic_wand(
+        &mut self,
+        wand: ast::Expr,
+        body: Vec<ast::Stmt>,
+        label: String,
+        vars: Vec<ast::LocalVar>,
+        pos: ast::Position,
+    ) -> ast::Stmt {
+        ast::Stmt::PackageMagicWand(self.fold_expr(wand), self.fold_stmts(body), label, vars, pos)
+    }
+    fn fold_if(&mut self, guard: ast::Expr, then_stmts: Vec<ast::Stmt>) -> ast::Stmt {
+        ast::Stmt::If(self.fold_expr(guard), self.fold_stmts(then_stmts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_access_is_kept() {
+        let x = ast::LocalVar::new("x", ast::Type::Int);
+        let y = ast::LocalVar::new("y", ast::Type::typed_ref("T"));
+        let z = ast::LocalVar::new("z", ast::Type::Int);
+        let mut method = cfg::CfgMethod::new(
+            "test".to_string(),
+            0,
+            vec![],
+            vec![x.clone(), y.clone(), z.clone()],
+            vec![],
+        );
+        let field = ast::Field::new("f", ast::Type::Int);
+        let stmts = vec![
+            // Removing this assignment would drop the permission check of `y.f`.
+            ast::Stmt::Assign(
+                x.clone().into(),
+                ast::Expr::from(y.clone()).field(field),
+                ast::AssignKind::Copy,
+            ),
+            ast::Stmt::Assign(z.into(), 1.into(), ast::AssignKind::Copy),
+        ];
+        method.add_block("start", vec![], stmts);
+        let method = remove_unused_vars(method);
+        assert_eq!(method.local_vars, vec![x, y]);
+        assert_eq!(method.basic_blocks[0].stmts.len(), 1);
+    }
 }
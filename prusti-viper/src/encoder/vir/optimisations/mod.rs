@@ -9,3 +9,5 @@
 pub mod folding;
 pub mod functions;
 pub mod methods;
+pub mod predicates;
+pub mod simplifier;
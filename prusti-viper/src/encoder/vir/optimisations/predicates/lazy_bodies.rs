@@ -0,0 +1,171 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Optimisation that drops the bodies of predicates that are only used opaquely.
+
+use super::super::super::ast;
+use super::super::super::cfg;
+use std::collections::HashSet;
+
+/// Replace with abstract predicates all the predicates whose body is never
+/// needed, i.e. that are never folded, unfolded or used in an `unfolding`
+/// expression by `methods` or `functions`.
+///
+/// This is sound because an abstract predicate provides less information to
+/// the verifier than the original one. Note that the fold-unfold algorithm
+/// must have already run, because it relies on the bodies of the predicates.
+///
+/// Returns the new predicates, together with the names of the predicates
+/// that have been made abstract.
+pub fn abstract_unused_bodies(
+    predicates: Vec<ast::Predicate>,
+    methods: &[cfg::CfgMethod],
+    functions: &[ast::Function],
+) -> (Vec<ast::Predicate>, Vec<String>) {
+    let used_bodies = collect_predicates_with_used_bodies(methods, functions);
+    let mut abstracted = vec![];
+    let new_predicates = predicates
+        .into_iter()
+        .map(|predicate| {
+            // An abstract predicate is named after its type, which a user predicate is not.
+            if predicate.is_abstract()
+                || used_bodies.contains(predicate.name())
+                || ast::Predicate::is_user_predicate_name(predicate.name())
+            {
+                predicate
+            } else {
+                abstracted.push(predicate.name().to_string());
+                ast::Predicate::new_abstract(predicate.self_place().get_type().clone())
+            }
+        })
+        .collect();
+    (new_predicates, abstracted)
+}
+
+/// Collect the names of the predicates whose body is needed by `methods` or
+/// `functions`.
+pub fn collect_predicates_with_used_bodies(
+    methods: &[cfg::CfgMethod],
+    functions: &[ast::Function],
+) -> HashSet<String> {
+    let mut collector = UsedBodyCollector {
+        predicates: HashSet::new(),
+    };
+    for method in methods {
+        method.walk_statements(|stmt| {
+            ast::StmtWalker::walk(&mut collector, stmt);
+        });
+        method.walk_successors(|successor| match successor {
+            cfg::Successor::Undefined | cfg::Successor::Return |
+            cfg::Successor::Goto(_) | cfg::Successor::BackEdge(_) => {}
+            cfg::Successor::GotoSwitch(conditional_targets, _) => {
+                for (expr, _) in conditional_targets {
+                    ast::ExprWalker::walk(&mut collector, expr);
+                }
+            }
+        });
+    }
+    for function in functions {
+        for expr in function.pres.iter().chain(function.posts.iter()) {
+            ast::ExprWalker::walk(&mut collector, expr);
+        }
+        if let Some(ref body) = function.body {
+            ast::ExprWalker::walk(&mut collector, body);
+        }
+    }
+    collector.predicates
+}
+
+/// Collects the names of the predicates that are folded or unfolded.
+struct UsedBodyCollector {
+    predicates: HashSet<String>,
+}
+
+impl ast::ExprWalker for UsedBodyCollector {
+    fn walk_unfolding(
+        &mut self,
+        predicate_name: &str,
+        args: &Vec<ast::Expr>,
+        body: &ast::Expr,
+        _perm: ast::PermAmount,
+        _variant: &ast::MaybeEnumVariantIndex,
+        _pos: &ast::Position,
+    ) {
+        self.predicates.insert(predicate_name.to_string());
+        for arg in args {
+            ast::ExprWalker::walk(self, arg);
+        }
+        ast::ExprWalker::walk(self, body);
+    }
+}
+
+impl ast::StmtWalker for UsedBodyCollector {
+    fn walk_expr(&mut self, expr: &ast::Expr) {
+        ast::ExprWalker::walk(self, expr);
+    }
+
+    fn walk_fold(
+        &mut self,
+        predicate_name: &str,
+        args: &Vec<ast::Expr>,
+        _perm: &ast::PermAmount,
+        _variant: &ast::MaybeEnumVariantIndex,
+        _pos: &ast::Position,
+    ) {
+        self.predicates.insert(predicate_name.to_string());
+        for arg in args {
+            ast::StmtWalker::walk_expr(self, arg);
+        }
+    }
+
+    fn walk_unfold(
+        &mut self,
+        predicate_name: &str,
+        args: &Vec<ast::Expr>,
+        _perm: &ast::PermAmount,
+        _variant: &ast::MaybeEnumVariantIndex,
+    ) {
+        self.predicates.insert(predicate_name.to_string());
+        for arg in args {
+            ast::StmtWalker::walk_expr(self, arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn struct_type(name: &str) -> ast::Type {
+        ast::Type::TypedRef(name.to_string(), ast::TypedRefKind::Adt(vec![]))
+    }
+
+    fn struct_predicate(name: &str) -> ast::Predicate {
+        ast::Predicate::new_struct(
+            struct_type(name),
+            vec![ast::Field::new("f$value", ast::Type::Int)],
+        )
+    }
+
+    #[test]
+    fn abstracts_the_predicates_that_are_never_unfolded() {
+        let mut method = cfg::CfgMethod::new("m".to_string(), 0, vec![], vec![], vec![]);
+        let unfold = ast::Stmt::Unfold(
+            "Transparent".to_string(),
+            vec![ast::Expr::local(ast::LocalVar::new("t", struct_type("Transparent")))],
+            ast::PermAmount::Write,
+            None,
+        );
+        let block = method.add_block("start", vec![], vec![unfold]);
+        method.set_successor(block, cfg::Successor::Return);
+        let predicates = vec![struct_predicate("Opaque"), struct_predicate("Transparent")];
+        let (new_predicates, abstracted) = abstract_unused_bodies(predicates, &[method], &[]);
+        assert_eq!(abstracted, vec!["Opaque".to_string()]);
+        assert!(new_predicates[0].is_abstract());
+        assert_eq!(new_predicates[0].name(), "Opaque");
+        assert!(!new_predicates[1].is_abstract());
+    }
+}
@@ -0,0 +1,11 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A module that contains optimisations for predicates.
+
+mod lazy_bodies;
+
+pub use self::lazy_bodies::{abstract_unused_bodies, collect_predicates_with_used_bodies};
@@ -4,9 +4,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-//! Function simplifier that simplifies expressions.
+//! Simplifier that simplifies the expressions of functions and methods.
 
-use super::super::super::ast::{self, ExprFolder};
+use super::super::ast::{self, ExprFolder, StmtFolder};
+use super::super::cfg;
+use std::mem;
 
 pub trait Simplifier {
     /// Simplify by doing constant evaluation.
@@ -18,6 +20,8 @@ impl Simplifier for ast::Function {
     /// https://bitbucket.org/viperproject/silicon/issues/387/incompleteness-in-morecompleteexhale
     fn simplify(mut self) -> Self {
         trace!("[enter] simplify = {}", self);
+        self.pres = self.pres.into_iter().map(|e| e.simplify()).collect();
+        self.posts = self.posts.into_iter().map(|e| e.simplify()).collect();
         let new_body = self.body.map(|b| b.simplify());
         self.body = new_body;
         trace!("[exit] simplify = {}", self);
@@ -25,6 +29,27 @@ impl Simplifier for ast::Function {
     }
 }
 
+impl Simplifier for cfg::CfgMethod {
+    /// Simplify the expressions of all statements and of all branch guards.
+    fn simplify(mut self) -> Self {
+        let mut sentinel_stmt = ast::Stmt::Comment(String::from("moved out stmt"));
+        for block in &mut self.basic_blocks {
+            for stmt in &mut block.stmts {
+                mem::swap(&mut sentinel_stmt, stmt);
+                sentinel_stmt = StmtSimplifier {}.fold(sentinel_stmt);
+                mem::swap(&mut sentinel_stmt, stmt);
+            }
+            if let cfg::Successor::GotoSwitch(ref mut guarded_targets, _) = block.successor {
+                for (guard, _) in guarded_targets.iter_mut() {
+                    let simplified_guard = guard.clone().simplify();
+                    *guard = simplified_guard;
+                }
+            }
+        }
+        self
+    }
+}
+
 impl Simplifier for ast::Expr {
     fn simplify(self) -> Self {
         let mut folder = ExprSimplifier {};
@@ -32,11 +57,23 @@ impl Simplifier for ast::Expr {
     }
 }
 
+struct StmtSimplifier {}
+
+impl StmtFolder for StmtSimplifier {
+    fn fold_expr(&mut self, e: ast::Expr) -> ast::Expr {
+        e.simplify()
+    }
+}
+
 struct ExprSimplifier {}
 
 impl ExprSimplifier {
     fn apply_rules(&self, e: ast::Expr) -> ast::Expr {
         trace!("[enter] apply_rules={}", e);
+        let e = match evaluate_constant_bin_op(&e) {
+            Some(value) => ast::Expr::Const(value, e.pos().clone()),
+            None => e,
+        };
         let result = match e {
             ast::Expr::UnaryOp(
                 ast::UnaryOpKind::Not,
@@ -45,6 +82,13 @@ impl ExprSimplifier {
             ) => {
                 ast::Expr::Const(ast::Const::Bool(!b), pos)
             },
+            ast::Expr::UnaryOp(
+                ast::UnaryOpKind::Not,
+                box ast::Expr::UnaryOp(ast::UnaryOpKind::Not, box inner, _),
+                _,
+            ) => {
+                inner
+            },
             ast::Expr::UnaryOp(
                 ast::UnaryOpKind::Not,
                 box ast::Expr::BinOp(ast::BinOpKind::EqCmp, box left, box right, _),
@@ -154,6 +198,9 @@ impl ExprFolder for ExprSimplifier {
         let simplified_guard = self.fold_boxed(guard);
         let simplified_then = self.fold_boxed(then_expr);
         let simplified_else = self.fold_boxed(else_expr);
+        if let ast::Expr::Const(ast::Const::Bool(b), _) = *simplified_guard {
+            return if b { *simplified_then } else { *simplified_else };
+        }
         let result = if simplified_then.is_bool() || simplified_else.is_bool() {
             ast::Expr::BinOp(
                 ast::BinOpKind::And,
@@ -181,3 +228,69 @@ impl ExprFolder for ExprSimplifier {
         self.apply_rules(result)
     }
 }
+
+/// Evaluate a binary operation whose operands are both constants.
+fn evaluate_constant_bin_op(e: &ast::Expr) -> Option<ast::Const> {
+    let (op, left, right) = match *e {
+        ast::Expr::BinOp(
+            op,
+            box ast::Expr::Const(ref left, _),
+            box ast::Expr::Const(ref right, _),
+            _,
+        ) => (op, left, right),
+        _ => return None,
+    };
+    match (left, right) {
+        (ast::Const::Int(a), ast::Const::Int(b)) => {
+            let (a, b) = (*a, *b);
+            match op {
+                ast::BinOpKind::Add => a.checked_add(b).map(ast::Const::Int),
+                ast::BinOpKind::Sub => a.checked_sub(b).map(ast::Const::Int),
+                ast::BinOpKind::Mul => a.checked_mul(b).map(ast::Const::Int),
+                // Viper and Rust round differently the division of negative numbers.
+                ast::BinOpKind::Div if a >= 0 && b > 0 => Some(ast::Const::Int(a / b)),
+                ast::BinOpKind::Mod if a >= 0 && b > 0 => Some(ast::Const::Int(a % b)),
+                ast::BinOpKind::EqCmp => Some(ast::Const::Bool(a == b)),
+                ast::BinOpKind::NeCmp => Some(ast::Const::Bool(a != b)),
+                ast::BinOpKind::GtCmp => Some(ast::Const::Bool(a > b)),
+                ast::BinOpKind::GeCmp => Some(ast::Const::Bool(a >= b)),
+                ast::BinOpKind::LtCmp => Some(ast::Const::Bool(a < b)),
+                ast::BinOpKind::LeCmp => Some(ast::Const::Bool(a <= b)),
+                _ => None,
+            }
+        }
+        (ast::Const::Bool(a), ast::Const::Bool(b)) => match op {
+            ast::BinOpKind::EqCmp => Some(ast::Const::Bool(a == b)),
+            ast::BinOpKind::NeCmp => Some(ast::Const::Bool(a != b)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> ast::Expr {
+        ast::LocalVar::new(name, ast::Type::Bool).into()
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let expr = ast::Expr::add(1.into(), ast::Expr::mul(2.into(), 3.into()));
+        assert_eq!(expr.simplify(), 7.into());
+        let expr = ast::Expr::lt_cmp(ast::Expr::sub(1.into(), 2.into()), 0.into());
+        assert_eq!(expr.simplify(), true.into());
+        let expr = ast::Expr::div((-7).into(), 2.into());
+        assert_eq!(expr.clone().simplify(), expr);
+    }
+
+    #[test]
+    fn test_boolean_simplification() {
+        let expr = ast::Expr::and(true.into(), ast::Expr::not(ast::Expr::not(var("b"))));
+        assert_eq!(expr.simplify(), var("b"));
+        let expr = ast::Expr::ite(ast::Expr::eq_cmp(1.into(), 2.into()), 3.into(), 4.into());
+        assert_eq!(expr.simplify(), 4.into());
+    }
+}
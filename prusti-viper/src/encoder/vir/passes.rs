@@ -0,0 +1,186 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A pipeline of named VIR-to-VIR passes on the encoded methods.
+//!
+//! The passes run in the order in which they are added. For each pass, the pipeline measures
+//! its duration and the number of statements before and after it, and checks that it does
+//! not lose the positions of the statements that can fail: a position that disappears or is
+//! replaced by the default one would make the errors of the verifier impossible to report.
+//! The method can be dumped after the passes listed in `DUMP_VIR_AFTER`.
+
+use encoder::vir::{cfg, PositionId, Stmt};
+use prusti_interface::config;
+use prusti_interface::report::log;
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The name of `DUMP_VIR_AFTER` that dumps the method after all the passes.
+const ALL_PASSES: &str = "all";
+
+/// A pass of the pipeline.
+struct MethodPass<'a> {
+    name: &'static str,
+    run: Box<Fn(cfg::CfgMethod) -> cfg::CfgMethod + 'a>,
+}
+
+/// What a pass changed in a method.
+#[derive(Debug, Clone)]
+pub struct PassStatistics {
+    pub name: &'static str,
+    pub duration: Duration,
+    pub stmts_before: usize,
+    pub stmts_after: usize,
+    /// The positions of the statements that can fail that are no longer in the method.
+    pub lost_positions: Vec<PositionId>,
+    /// How many more statements that can fail have the default position.
+    pub new_default_positions: usize,
+}
+
+impl fmt::Display for PassStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}.{:03} seconds, {} -> {} statements",
+            self.name,
+            self.duration.as_secs(),
+            self.duration.subsec_millis(),
+            self.stmts_before,
+            self.stmts_after
+        )?;
+        if !self.lost_positions.is_empty() {
+            let positions: Vec<String> =
+                self.lost_positions.iter().map(|pos| pos.to_string()).collect();
+            write!(f, ", lost positions {}", positions.join(", "))?;
+        }
+        if self.new_default_positions > 0 {
+            write!(f, ", {} new default positions", self.new_default_positions)?;
+        }
+        Ok(())
+    }
+}
+
+/// A sequence of passes that rewrite a method.
+pub struct MethodPipeline<'a> {
+    passes: Vec<MethodPass<'a>>,
+}
+
+impl<'a> MethodPipeline<'a> {
+    pub fn new() -> Self {
+        MethodPipeline { passes: vec![] }
+    }
+
+    /// Add a pass, which runs after the passes added before it.
+    pub fn add_pass<F>(&mut self, name: &'static str, run: F)
+    where
+        F: Fn(cfg::CfgMethod) -> cfg::CfgMethod + 'a,
+    {
+        debug_assert!(
+            self.passes.iter().all(|pass| pass.name != name),
+            "the pass {} is added twice",
+            name
+        );
+        self.passes.push(MethodPass {
+            name,
+            run: Box::new(run),
+        });
+    }
+
+    /// Run the passes on the method. The dumps of the method are named after `dump_name`.
+    pub fn run(
+        &self,
+        mut method: cfg::CfgMethod,
+        dump_name: &str,
+    ) -> (cfg::CfgMethod, Vec<PassStatistics>) {
+        let dumped_passes = config::dump_vir_after();
+        let mut statistics = vec![];
+        for pass in &self.passes {
+            let stmts_before = count_stmts(&method);
+            let (positions_before, defaults_before) = failing_positions(&method);
+            let start = Instant::now();
+            method = (pass.run)(method);
+            let duration = start.elapsed();
+            let (positions_after, defaults_after) = failing_positions(&method);
+            let mut lost_positions: Vec<PositionId> = positions_before
+                .difference(&positions_after)
+                .cloned()
+                .collect();
+            lost_positions.sort();
+            let pass_statistics = PassStatistics {
+                name: pass.name,
+                duration,
+                stmts_before,
+                stmts_after: count_stmts(&method),
+                lost_positions,
+                new_default_positions: defaults_after.saturating_sub(defaults_before),
+            };
+            debug!("VIR pass {}", pass_statistics);
+            statistics.push(pass_statistics);
+            if dumped_passes
+                .iter()
+                .any(|dumped_pass| dumped_pass == pass.name || dumped_pass == ALL_PASSES)
+            {
+                log::report(
+                    &format!("vir_after_{}", pass.name),
+                    format!("{}.vir", dump_name),
+                    &method,
+                );
+            }
+        }
+        (method, statistics)
+    }
+}
+
+fn count_stmts(method: &cfg::CfgMethod) -> usize {
+    method
+        .basic_blocks
+        .iter()
+        .map(|block| block.stmts.len())
+        .sum()
+}
+
+/// The positions of the statements that can fail, and the number of such statements with the
+/// default position.
+fn failing_positions(method: &cfg::CfgMethod) -> (HashSet<PositionId>, usize) {
+    let mut positions = HashSet::new();
+    let mut defaults = 0;
+    for block in &method.basic_blocks {
+        collect_failing_positions(&block.stmts, &mut positions, &mut defaults);
+    }
+    (positions, defaults)
+}
+
+fn collect_failing_positions(
+    stmts: &[Stmt],
+    positions: &mut HashSet<PositionId>,
+    defaults: &mut usize,
+) {
+    for stmt in stmts {
+        let pos = match stmt {
+            Stmt::Exhale(_, pos)
+            | Stmt::Assert(_, _, pos)
+            | Stmt::Refute(_, pos)
+            | Stmt::Fold(_, _, _, _, pos)
+            | Stmt::Obtain(_, pos)
+            | Stmt::ApplyMagicWand(_, pos) => pos,
+            Stmt::PackageMagicWand(_, package_stmts, _, _, pos) => {
+                collect_failing_positions(package_stmts, positions, defaults);
+                pos
+            }
+            Stmt::If(_, then_stmts) => {
+                collect_failing_positions(then_stmts, positions, defaults);
+                continue;
+            }
+            _ => continue,
+        };
+        if pos.id() == PositionId::NONE {
+            *defaults += 1;
+        } else {
+            positions.insert(pos.id());
+        }
+    }
+}
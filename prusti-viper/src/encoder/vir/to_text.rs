@@ -0,0 +1,649 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Emit the source text of a Viper program, as an alternative to constructing it object by
+//! object with `ToViper`.
+//!
+//! The backend positions the nodes of a parsed program at their line and column in the text,
+//! so `ViperText` records the position of the VIR node that starts at each line and column.
+
+use encoder::vir::ast::*;
+use encoder::vir::borrows::borrow_id;
+use std::collections::HashMap;
+use viper;
+
+/// The source text of a Viper program, together with the positions of its nodes.
+pub struct ViperText {
+    source: String,
+    /// The line and column at which the next character will be written, starting from 1.
+    line: i32,
+    column: i32,
+    indentation: usize,
+    /// The position of the outermost node that starts at a given line and column.
+    positions: HashMap<(i32, i32), PositionId>,
+}
+
+impl ViperText {
+    pub fn new() -> Self {
+        ViperText {
+            source: String::new(),
+            line: 1,
+            column: 1,
+            indentation: 0,
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Write text that does not contain line breaks.
+    pub fn write(&mut self, text: &str) {
+        debug_assert!(!text.contains('\n'));
+        self.source.push_str(text);
+        self.column += text.chars().count() as i32;
+    }
+
+    /// Start a new line, at the current indentation.
+    pub fn newline(&mut self) {
+        self.source.push('\n');
+        self.line += 1;
+        self.column = 1;
+        let indentation = "  ".repeat(self.indentation);
+        self.write(&indentation);
+    }
+
+    pub fn indent(&mut self) {
+        self.indentation += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.indentation -= 1;
+    }
+
+    /// Record that the node with position `pos` starts at the current line and column.
+    /// Nodes that start where an enclosing node starts are not recorded.
+    pub fn mark(&mut self, pos: &Position) {
+        if !pos.is_default() {
+            self.positions
+                .entry((self.line, self.column))
+                .or_insert_with(|| pos.id());
+        }
+    }
+
+    /// Write `(`, the text written by `write_inner` and `)`. The node with position `pos`
+    /// starts both at the parenthesis and at the text inside it, because the parser might
+    /// position a parenthesized expression at either of them.
+    fn parenthesized<F: FnOnce(&mut Self)>(&mut self, pos: &Position, write_inner: F) {
+        self.mark(pos);
+        self.write("(");
+        self.mark(pos);
+        write_inner(self);
+        self.write(")");
+    }
+
+    fn write_separated<'a, I: IntoIterator<Item = &'a Expr>>(&mut self, exprs: I, separator: &str) {
+        for (index, item) in exprs.into_iter().enumerate() {
+            if index > 0 {
+                self.write(separator);
+            }
+            item.to_viper_text(self);
+        }
+    }
+
+    fn write_decls(&mut self, vars: &[LocalVar]) {
+        for (index, var) in vars.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+            var.to_viper_text_decl(self);
+        }
+    }
+
+    /// Replace the `line:column` positions of an error of the backend with the identifiers
+    /// of the positions of the nodes that start there.
+    pub fn translate_error(&self, error: viper::VerificationError) -> viper::VerificationError {
+        let translate = |pos_id: Option<String>| {
+            pos_id.and_then(|pos_id| {
+                let mut parts = pos_id.splitn(2, ':');
+                let line = parts.next().and_then(|line| line.parse().ok());
+                let column = parts.next().and_then(|column| column.parse().ok());
+                match (line, column) {
+                    (Some(line), Some(column)) => self
+                        .positions
+                        .get(&(line, column))
+                        .map(|id| id.to_string()),
+                    _ => None,
+                }
+            })
+        };
+        viper::VerificationError::new(
+            error.full_id,
+            translate(error.pos_id),
+            translate(error.reason_pos_id),
+            error.message,
+        )
+    }
+}
+
+pub trait ToViperText {
+    fn to_viper_text(&self, text: &mut ViperText);
+}
+
+impl ToViperText for Type {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        match self {
+            Type::Int => text.write("Int"),
+            Type::Bool => text.write("Bool"),
+            Type::TypedRef(..) => text.write("Ref"),
+            Type::TypedMap(ref key, ref value) => {
+                text.write("Map$[");
+                key.to_viper_text(text);
+                text.write(", ");
+                value.to_viper_text(text);
+                text.write("]");
+            }
+            Type::TypedSeq(ref elem) => {
+                text.write("Seq[");
+                elem.to_viper_text(text);
+                text.write("]");
+            }
+            Type::Domain(ref name) => text.write(name),
+        }
+    }
+}
+
+impl LocalVar {
+    fn to_viper_text_decl(&self, text: &mut ViperText) {
+        text.write(&self.name);
+        text.write(": ");
+        self.typ.to_viper_text(text);
+    }
+}
+
+impl ToViperText for Field {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.write("field ");
+        text.write(&self.name);
+        text.write(": ");
+        self.typ.to_viper_text(text);
+    }
+}
+
+impl ToViperText for PermAmount {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        match self {
+            PermAmount::Write => text.write("write"),
+            PermAmount::Read => text.write("read$()"),
+            PermAmount::Remaining => text.write("(write - read$())"),
+            PermAmount::Wildcard => text.write("wildcard"),
+            PermAmount::Frac(numerator, denominator) => {
+                text.write(&format!("({}/{})", numerator, denominator))
+            }
+        }
+    }
+}
+
+impl ToViperText for Const {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        match self {
+            Const::Bool(value) => text.write(&value.to_string()),
+            Const::Int(value) => text.write(&value.to_string()),
+            Const::BigInt(ref value) => text.write(value),
+            Const::Str(ref value) if value.is_empty() => text.write("Seq[Int]()"),
+            Const::Str(ref value) => {
+                let bytes: Vec<_> = value.bytes().map(|byte| byte.to_string()).collect();
+                text.write(&format!("Seq({})", bytes.join(", ")));
+            }
+        }
+    }
+}
+
+impl ToViperText for Trigger {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.write("{ ");
+        text.write_separated(self.elements(), ", ");
+        text.write(" }");
+    }
+}
+
+/// Write a predicate access `acc(name(args), perm)`.
+fn write_predicate_access<'a, I: IntoIterator<Item = &'a Expr>>(
+    text: &mut ViperText,
+    name: &str,
+    args: I,
+    perm: PermAmount,
+) {
+    text.write("acc(");
+    text.write(name);
+    text.write("(");
+    text.write_separated(args, ", ");
+    text.write("), ");
+    perm.to_viper_text(text);
+    text.write(")");
+}
+
+/// Write the permission to the token of a dead borrow.
+fn write_dead_borrow_token(text: &mut ViperText, borrow_id: isize) {
+    text.write(&format!("acc(DeadBorrowToken$({}), write)", borrow_id));
+}
+
+fn write_quantifier(
+    text: &mut ViperText,
+    quantifier: &str,
+    vars: &[LocalVar],
+    triggers: &[Trigger],
+    body: &Expr,
+    pos: &Position,
+) {
+    text.parenthesized(pos, |text| {
+        text.write(quantifier);
+        text.write(" ");
+        text.write_decls(vars);
+        text.write(" :: ");
+        for trigger in triggers {
+            trigger.to_viper_text(text);
+            text.write(" ");
+        }
+        body.to_viper_text(text);
+    });
+}
+
+impl ToViperText for Expr {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.mark(self.pos());
+        match self {
+            Expr::Local(ref var, _) if var.name == "__result" => text.write("result"),
+            Expr::Local(ref var, _) => text.write(&var.name),
+            Expr::Variant(ref base, ref field, _) | Expr::Field(ref base, ref field, _) => {
+                base.to_viper_text(text);
+                text.write(".");
+                text.write(&field.name);
+            }
+            Expr::AddrOf(..) => unreachable!(),
+            Expr::Const(ref value, _) => value.to_viper_text(text),
+            Expr::LabelledOld(ref label, ref expr, _) => {
+                text.write(&format!("old[{}](", label));
+                expr.to_viper_text(text);
+                text.write(")");
+            }
+            Expr::MagicWand(ref lhs, ref rhs, maybe_borrow, ref pos) => {
+                let borrow_id = maybe_borrow.map_or(-1, |borrow| borrow_id(borrow) as isize);
+                text.parenthesized(pos, |text| {
+                    text.parenthesized(pos, |text| {
+                        write_dead_borrow_token(text, borrow_id);
+                        text.write(" && ");
+                        lhs.to_viper_text(text);
+                    });
+                    text.write(" --* ");
+                    rhs.to_viper_text(text);
+                });
+            }
+            Expr::PredicateAccessPredicate(ref name, ref arg, perm, _) => {
+                write_predicate_access(text, name, Some(&**arg), *perm);
+            }
+            Expr::FieldAccessPredicate(ref loc, perm, _) => {
+                text.write("acc(");
+                loc.to_viper_text(text);
+                text.write(", ");
+                perm.to_viper_text(text);
+                text.write(")");
+            }
+            Expr::UnaryOp(op, ref arg, _) => {
+                text.write(match op {
+                    UnaryOpKind::Not => "!(",
+                    UnaryOpKind::Minus => "-(",
+                });
+                arg.to_viper_text(text);
+                text.write(")");
+            }
+            Expr::BinOp(op, ref left, ref right, ref pos) => {
+                let op = match op {
+                    BinOpKind::EqCmp => "==",
+                    BinOpKind::NeCmp => "!=",
+                    BinOpKind::GtCmp => ">",
+                    BinOpKind::GeCmp => ">=",
+                    BinOpKind::LtCmp => "<",
+                    BinOpKind::LeCmp => "<=",
+                    BinOpKind::Add => "+",
+                    BinOpKind::Sub => "-",
+                    BinOpKind::Mul => "*",
+                    BinOpKind::Div => "\\",
+                    BinOpKind::Mod => "%",
+                    BinOpKind::And => "&&",
+                    BinOpKind::Or => "||",
+                    BinOpKind::Implies => "==>",
+                };
+                text.parenthesized(pos, |text| {
+                    left.to_viper_text(text);
+                    text.write(&format!(" {} ", op));
+                    right.to_viper_text(text);
+                });
+            }
+            Expr::Unfolding(ref name, ref args, ref expr, perm, _, ref pos) => {
+                text.parenthesized(pos, |text| {
+                    text.write("unfolding ");
+                    write_predicate_access(text, name, args, *perm);
+                    text.write(" in ");
+                    expr.to_viper_text(text);
+                });
+            }
+            Expr::Cond(ref guard, ref then_expr, ref else_expr, ref pos) => {
+                text.parenthesized(pos, |text| {
+                    guard.to_viper_text(text);
+                    text.write(" ? ");
+                    then_expr.to_viper_text(text);
+                    text.write(" : ");
+                    else_expr.to_viper_text(text);
+                });
+            }
+            Expr::ForAll(ref vars, ref triggers, ref body, ref pos) => {
+                write_quantifier(text, "forall", vars, triggers, body, pos);
+            }
+            Expr::Exists(ref vars, ref triggers, ref body, ref pos) => {
+                write_quantifier(text, "exists", vars, triggers, body, pos);
+            }
+            Expr::LetExpr(ref var, ref expr, ref body, ref pos) => {
+                text.parenthesized(pos, |text| {
+                    text.write(&format!("let {} == (", var.name));
+                    expr.to_viper_text(text);
+                    text.write(") in ");
+                    body.to_viper_text(text);
+                });
+            }
+            Expr::FuncApp(ref name, ref args, ref formal_args, ref return_type, _) => {
+                text.write(&compute_identifier(name, formal_args, return_type));
+                text.write("(");
+                text.write_separated(args, ", ");
+                text.write(")");
+            }
+            Expr::DomainFuncApp(ref func, ref args, _) => {
+                text.write(&func.name);
+                text.write("(");
+                text.write_separated(args, ", ");
+                text.write(")");
+            }
+            Expr::MapLookup(ref map, ref key, _) => {
+                write_map_func_app(text, "map$lookup", &[map, key]);
+            }
+            Expr::MapContains(ref map, ref key, _) => {
+                write_map_func_app(text, "map$contains", &[map, key]);
+            }
+            Expr::MapUpdate(ref map, ref key, ref value, _) => {
+                write_map_func_app(text, "map$update", &[map, key, value]);
+            }
+            Expr::MapLen(ref map, _) => write_map_func_app(text, "map$len", &[map]),
+            Expr::SeqLen(ref seq, _) => {
+                text.write("|");
+                seq.to_viper_text(text);
+                text.write("|");
+            }
+            Expr::SeqIndex(ref seq, ref index, _) => {
+                seq.to_viper_text(text);
+                text.write("[");
+                index.to_viper_text(text);
+                text.write("]");
+            }
+        }
+    }
+}
+
+fn write_map_func_app(text: &mut ViperText, name: &str, args: &[&Expr]) {
+    text.write(name);
+    text.write("(");
+    text.write_separated(args.iter().cloned(), ", ");
+    text.write(")");
+}
+
+impl ToViperText for Stmt {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        match self {
+            Stmt::Comment(ref comment) => {
+                text.write("// ");
+                text.write(&comment.replace('\n', " "));
+            }
+            Stmt::Label(ref label) => text.write(&format!("label {}", label)),
+            Stmt::Inhale(ref expr, _) => {
+                text.mark(&Position::new(0, 0, PositionId::INHALE));
+                text.write("inhale ");
+                expr.to_viper_text(text);
+            }
+            Stmt::Exhale(ref expr, ref pos) => {
+                assert!(!pos.is_default());
+                text.mark(pos);
+                text.write("exhale ");
+                expr.to_viper_text(text);
+            }
+            Stmt::Assert(ref expr, _, ref pos) => {
+                text.mark(pos);
+                text.write("assert ");
+                expr.to_viper_text(text);
+            }
+            Stmt::Assume(ref expr, ref pos) => {
+                text.mark(pos);
+                text.write("inhale ");
+                expr.to_viper_text(text);
+            }
+            Stmt::Refute(..) => {
+                unreachable!("'{}' should have been lowered by `lower_refutations`", self)
+            }
+            Stmt::MethodCall(ref method_name, ref args, ref targets) => {
+                text.mark(&Position::new(0, 0, PositionId::METHOD_CALL));
+                if !targets.is_empty() {
+                    let targets: Vec<_> = targets.iter().map(|var| var.name.as_str()).collect();
+                    text.write(&format!("{} := ", targets.join(", ")));
+                }
+                text.write(method_name);
+                text.write("(");
+                text.write_separated(args, ", ");
+                text.write(")");
+            }
+            Stmt::Assign(ref lhs, ref rhs, _) => {
+                lhs.to_viper_text(text);
+                text.write(" := ");
+                rhs.to_viper_text(text);
+            }
+            Stmt::Fold(ref pred_name, ref args, perm, _, ref pos) => {
+                text.mark(pos);
+                text.write("fold ");
+                write_predicate_access(text, pred_name, args, *perm);
+            }
+            Stmt::Unfold(ref pred_name, ref args, perm, _) => {
+                text.write("unfold ");
+                write_predicate_access(text, pred_name, args, *perm);
+            }
+            Stmt::Obtain(..)
+            | Stmt::BeginFrame
+            | Stmt::EndFrame
+            | Stmt::TransferPerm(..)
+            | Stmt::ExpireBorrows(..) => {
+                // Skip
+                Stmt::Comment(self.to_string()).to_viper_text(text);
+            }
+            Stmt::PackageMagicWand(ref wand, ref package_stmts, _, ref vars, ref pos) => {
+                text.mark(pos);
+                text.write("package ");
+                wand.to_viper_text(text);
+                text.write(" {");
+                text.indent();
+                for var in vars {
+                    text.newline();
+                    text.write("var ");
+                    var.to_viper_text_decl(text);
+                }
+                for stmt in package_stmts {
+                    write_stmt_in_package(text, stmt);
+                }
+                text.dedent();
+                text.newline();
+                text.write("}");
+            }
+            Stmt::ApplyMagicWand(ref wand, ref pos) => {
+                let borrow = if let Expr::MagicWand(_, _, Some(borrow), _) = wand {
+                    borrow_id(*borrow) as isize
+                } else {
+                    unreachable!()
+                };
+                text.mark(pos);
+                text.write("inhale ");
+                write_dead_borrow_token(text, borrow);
+                text.newline();
+                text.mark(pos);
+                text.write("apply ");
+                wand.to_viper_text(text);
+            }
+            Stmt::If(ref guard, ref then_stmts) => {
+                write_if(text, guard, |text| {
+                    for stmt in then_stmts {
+                        text.newline();
+                        stmt.to_viper_text(text);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Write `if (guard) { ... }`, with the statements written by `write_then`.
+pub fn write_if<F: FnOnce(&mut ViperText)>(text: &mut ViperText, guard: &Expr, write_then: F) {
+    text.write("if (");
+    guard.to_viper_text(text);
+    text.write(") {");
+    text.indent();
+    write_then(text);
+    text.dedent();
+    text.newline();
+    text.write("}");
+}
+
+/// Like `Stmt::to_viper_text`, but help Silicon to show that the package has access to the
+/// needed paths (see the `ToViper` implementation of `PackageMagicWand`).
+fn write_stmt_in_package(text: &mut ViperText, stmt: &Stmt) {
+    let write_footprint_asserts = |text: &mut ViperText, expr: &Expr| {
+        for access in expr.compute_footprint(PermAmount::Read) {
+            let fake_position = Position::new(0, 0, PositionId::FOLD_ASSERT);
+            text.newline();
+            Stmt::Assert(access, FoldingBehaviour::None, fake_position).to_viper_text(text);
+        }
+    };
+    match stmt {
+        Stmt::Assign(_, ref expr, _) | Stmt::Exhale(ref expr, _) => {
+            write_footprint_asserts(text, expr);
+        }
+        Stmt::Fold(_, ref args, ..) => {
+            assert_eq!(args.len(), 1);
+            assert!(args[0].is_place());
+            write_footprint_asserts(text, &args[0]);
+        }
+        Stmt::If(ref guard, ref then_stmts) => {
+            text.newline();
+            write_if(text, guard, |text| {
+                for stmt in then_stmts {
+                    write_stmt_in_package(text, stmt);
+                }
+            });
+            return;
+        }
+        _ => {}
+    }
+    text.newline();
+    stmt.to_viper_text(text);
+}
+
+impl ToViperText for Predicate {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        let (name, this, body) = match self {
+            Predicate::Struct(p) => (&p.name, &p.this, p.body.clone()),
+            Predicate::Enum(p) => (&p.name, &p.this, Some(p.body())),
+        };
+        text.write(&format!("predicate {}(", name));
+        this.to_viper_text_decl(text);
+        text.write(")");
+        if let Some(body) = body {
+            text.write(" {");
+            text.indent();
+            text.newline();
+            body.to_viper_text(text);
+            text.dedent();
+            text.newline();
+            text.write("}");
+        }
+    }
+}
+
+impl ToViperText for BodylessMethod {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.write(&format!("method {}(", self.name));
+        text.write_decls(&self.formal_args);
+        text.write(") returns (");
+        text.write_decls(&self.formal_returns);
+        text.write(")");
+    }
+}
+
+impl ToViperText for Function {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.write(&format!("function {}(", self.get_identifier()));
+        text.write_decls(&self.formal_args);
+        text.write("): ");
+        self.return_type.to_viper_text(text);
+        text.indent();
+        for pre in &self.pres {
+            text.newline();
+            text.write("requires ");
+            pre.to_viper_text(text);
+        }
+        for post in &self.posts {
+            text.newline();
+            text.write("ensures ");
+            post.to_viper_text(text);
+        }
+        text.dedent();
+        if let Some(ref body) = self.body {
+            text.newline();
+            text.write("{");
+            text.indent();
+            text.newline();
+            body.to_viper_text(text);
+            text.dedent();
+            text.newline();
+            text.write("}");
+        }
+    }
+}
+
+impl ToViperText for Domain {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        text.write(&format!("domain {} {{", self.name));
+        text.indent();
+        for function in &self.functions {
+            text.newline();
+            function.to_viper_text(text);
+        }
+        for axiom in &self.axioms {
+            text.newline();
+            text.write(&format!("axiom {} {{ ", axiom.name));
+            axiom.expr.to_viper_text(text);
+            text.write(" }");
+        }
+        text.dedent();
+        text.newline();
+        text.write("}");
+    }
+}
+
+impl ToViperText for DomainFunc {
+    fn to_viper_text(&self, text: &mut ViperText) {
+        if self.unique {
+            text.write("unique ");
+        }
+        text.write(&format!("function {}(", self.name));
+        text.write_decls(&self.formal_args);
+        text.write("): ");
+        self.return_type.to_viper_text(text);
+    }
+}
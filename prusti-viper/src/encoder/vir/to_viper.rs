@@ -6,6 +6,7 @@
 
 use encoder::vir::ast::*;
 use encoder::vir::borrows::borrow_id;
+use encoder::vir::map_domain::{map_func_app, map_type, MapFunc};
 use prusti_interface::config;
 use viper;
 use viper::AstFactory;
@@ -20,7 +21,7 @@ pub trait ToViperDecl<'v, T> {
 
 impl<'v> ToViper<'v, viper::Position<'v>> for Position {
     fn to_viper(&self, ast: &AstFactory<'v>) -> viper::Position<'v> {
-        ast.identifier_position(self.line(), self.column(), self.id())
+        ast.identifier_position(self.line(), self.column(), self.id().to_string())
     }
 }
 
@@ -30,7 +31,12 @@ impl<'v> ToViper<'v, viper::Type<'v>> for Type {
             &Type::Int => ast.int_type(),
             &Type::Bool => ast.bool_type(),
             //&Type::Ref |
-            &Type::TypedRef(_) => ast.ref_type(),
+            &Type::TypedRef(..) => ast.ref_type(),
+            &Type::TypedMap(ref key, ref value) => {
+                map_type(ast, key.to_viper(ast), value.to_viper(ast))
+            }
+            &Type::TypedSeq(ref elem) => ast.seq_type(elem.to_viper(ast)),
+            &Type::Domain(ref name) => ast.domain_type(name, &[], &[]),
         }
     }
 }
@@ -63,7 +69,7 @@ impl<'v> ToViper<'v, viper::Stmt<'v>> for Stmt {
             &Stmt::Comment(ref comment) => ast.comment(&comment),
             &Stmt::Label(ref label) => ast.label(&label, &[]),
             &Stmt::Inhale(ref expr, _) => {
-                let fake_position = Position::new(0, 0, "inhale".to_string());
+                let fake_position = Position::new(0, 0, PositionId::INHALE);
                 ast.inhale(expr.to_viper(ast), fake_position.to_viper(ast))
             }
             &Stmt::Exhale(ref expr, ref pos) => {
@@ -73,8 +79,14 @@ impl<'v> ToViper<'v, viper::Stmt<'v>> for Stmt {
             &Stmt::Assert(ref expr, _, ref pos) => {
                 ast.assert(expr.to_viper(ast), pos.to_viper(ast))
             },
+            &Stmt::Assume(ref expr, ref pos) => {
+                ast.inhale(expr.to_viper(ast), pos.to_viper(ast))
+            }
+            &Stmt::Refute(..) => {
+                unreachable!("'{}' should have been lowered by `lower_refutations`", self)
+            }
             &Stmt::MethodCall(ref method_name, ref args, ref targets) => {
-                let fake_position = Position::new(0, 0, "method_call".to_string());
+                let fake_position = Position::new(0, 0, PositionId::METHOD_CALL);
                 ast.method_call(
                     &method_name,
                     &args.to_viper(ast),
@@ -129,7 +141,7 @@ impl<'v> ToViper<'v, viper::Stmt<'v>> for Stmt {
                         expr.compute_footprint(perm)
                             .into_iter()
                             .map(|access| {
-                                let fake_position = Position::new(0, 0, "fold_assert".to_string());
+                                let fake_position = Position::new(0, 0, PositionId::FOLD_ASSERT);
                                 let assert = Stmt::Assert(
                                     access, FoldingBehaviour::None, fake_position);
                                 assert.to_viper(ast)
@@ -209,7 +221,8 @@ impl<'v> ToViper<'v, viper::Stmt<'v>> for Stmt {
                 } else {
                     unreachable!()
                 };
-                let position = ast.identifier_position(pos.line(), pos.column(), &pos.id());
+                let position =
+                    ast.identifier_position(pos.line(), pos.column(), pos.id().to_string());
                 let apply = ast.apply(wand.to_viper(ast), position);
                 ast.seqn(&[inhale, apply], &[])
             }
@@ -235,6 +248,11 @@ impl<'v> ToViper<'v, viper::Expr<'v>> for PermAmount {
                 PermAmount::Write.to_viper(ast),
                 PermAmount::Read.to_viper(ast),
             ),
+            PermAmount::Wildcard => ast.wildcard_perm(),
+            PermAmount::Frac(numerator, denominator) => ast.fractional_perm(
+                ast.int_lit(i64::from(*numerator)),
+                ast.int_lit(i64::from(*denominator)),
+            ),
         }
     }
 }
@@ -364,6 +382,12 @@ impl<'v> ToViper<'v, viper::Expr<'v>> for Expr {
                 body.to_viper(ast),
                 pos.to_viper(ast),
             ),
+            &Expr::Exists(ref vars, ref triggers, ref body, ref pos) => ast.exists_with_pos(
+                &vars.to_viper_decl(ast)[..],
+                &(triggers, pos).to_viper(ast),
+                body.to_viper(ast),
+                pos.to_viper(ast),
+            ),
             &Expr::LetExpr(ref var, ref expr, ref body, ref pos) => ast.let_expr_with_pos(
                 var.to_viper_decl(ast),
                 expr.to_viper(ast),
@@ -385,6 +409,23 @@ impl<'v> ToViper<'v, viper::Expr<'v>> for Expr {
                     pos.to_viper(ast),
                 )
             }
+            &Expr::DomainFuncApp(ref func, ref args, _) => {
+                ast.domain_func_app(func.to_viper(ast), &args.to_viper(ast), &[])
+            }
+            &Expr::MapLookup(ref map, ref key, _) => {
+                map_expr_to_viper(ast, MapFunc::Lookup, map, &[map, key])
+            }
+            &Expr::MapContains(ref map, ref key, _) => {
+                map_expr_to_viper(ast, MapFunc::Contains, map, &[map, key])
+            }
+            &Expr::MapUpdate(ref map, ref key, ref value, _) => {
+                map_expr_to_viper(ast, MapFunc::Update, map, &[map, key, value])
+            }
+            &Expr::MapLen(ref map, _) => map_expr_to_viper(ast, MapFunc::Len, map, &[map]),
+            &Expr::SeqLen(ref seq, _) => ast.seq_length(seq.to_viper(ast)),
+            &Expr::SeqIndex(ref seq, ref index, _) => {
+                ast.seq_index(seq.to_viper(ast), index.to_viper(ast))
+            }
         };
         if config::simplify_encoding() {
             ast.simplified_expression(expr)
@@ -394,6 +435,24 @@ impl<'v> ToViper<'v, viper::Expr<'v>> for Expr {
     }
 }
 
+/// Encode the application of a function of the map domain to `args`, which operate on `map`.
+fn map_expr_to_viper<'v>(
+    ast: &AstFactory<'v>,
+    func: MapFunc,
+    map: &Expr,
+    args: &[&Expr],
+) -> viper::Expr<'v> {
+    let (key_type, value_type) = map.get_map_types();
+    let args: Vec<_> = args.iter().map(|arg| arg.to_viper(ast)).collect();
+    map_func_app(
+        ast,
+        func,
+        &args,
+        key_type.to_viper(ast),
+        value_type.to_viper(ast),
+    )
+}
+
 impl<'v, 'a, 'b> ToViper<'v, viper::Trigger<'v>> for (&'a Trigger, &'b Position) {
     fn to_viper(&self, ast: &AstFactory<'v>) -> viper::Trigger<'v> {
         ast.trigger_with_pos(&self.0.elements().to_viper(ast)[..], self.1.to_viper(ast))
@@ -407,6 +466,11 @@ impl<'v, 'a, 'b> ToViper<'v, viper::Expr<'v>> for (&'a Const, &'b Position) {
             &Const::Bool(false) => ast.false_lit_with_pos(self.1.to_viper(ast)),
             &Const::Int(x) => ast.int_lit_with_pos(x, self.1.to_viper(ast)),
             &Const::BigInt(ref x) => ast.int_lit_from_ref_with_pos(x, self.1.to_viper(ast)),
+            &Const::Str(ref x) if x.is_empty() => ast.empty_seq(ast.int_type()),
+            &Const::Str(ref x) => {
+                let bytes: Vec<_> = x.bytes().map(|byte| ast.int_lit(byte.into())).collect();
+                ast.explicit_seq(&bytes)
+            }
         }
     }
 }
@@ -479,6 +543,32 @@ impl<'a, 'v> ToViper<'v, viper::Function<'v>> for &'a Function {
     }
 }
 
+impl<'v> ToViper<'v, viper::Domain<'v>> for Domain {
+    fn to_viper(&self, ast: &AstFactory<'v>) -> viper::Domain<'v> {
+        let functions: Vec<_> = self.functions.iter().map(|f| f.to_viper(ast)).collect();
+        let axioms: Vec<_> = self.axioms.iter().map(|a| a.to_viper(ast)).collect();
+        ast.domain(&self.name, &functions, &axioms, &[])
+    }
+}
+
+impl<'v> ToViper<'v, viper::DomainFunc<'v>> for DomainFunc {
+    fn to_viper(&self, ast: &AstFactory<'v>) -> viper::DomainFunc<'v> {
+        ast.domain_func(
+            &self.name,
+            &self.formal_args.to_viper_decl(ast),
+            self.return_type.to_viper(ast),
+            self.unique,
+            &self.domain_name,
+        )
+    }
+}
+
+impl<'v> ToViper<'v, viper::NamedDomainAxiom<'v>> for DomainAxiom {
+    fn to_viper(&self, ast: &AstFactory<'v>) -> viper::NamedDomainAxiom<'v> {
+        ast.named_domain_axiom(&self.name, self.expr.to_viper(ast), &self.domain_name)
+    }
+}
+
 // Vectors
 
 impl<'v> ToViper<'v, Vec<viper::Field<'v>>> for Vec<Field> {
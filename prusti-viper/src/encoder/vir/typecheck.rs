@@ -0,0 +1,610 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A type checker of the VIR items that are sent to Viper.
+//!
+//! A bug in the encoder usually produces an ill-typed Viper program, which Viper rejects with
+//! an error about the generated code. The type checker reports the offending VIR construct
+//! instead, with the position of the Rust code from which it has been encoded.
+//!
+//! Types are compared as Viper compares them: the predicate of a `Ref` type is not part of it.
+
+use encoder::vir::ast::*;
+use encoder::vir::cfg::{CfgMethod, Successor};
+use std::collections::HashMap;
+use std::fmt;
+
+/// An ill-typed construct of a VIR item.
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    /// The name of the function, predicate or method that contains the construct.
+    pub item: String,
+    /// The offending construct.
+    pub construct: String,
+    pub message: String,
+    /// The position of the construct, or of the closest enclosing construct that has one.
+    pub position: Position,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in `{}` of `{}`", self.message, self.construct, self.item)
+    }
+}
+
+/// Type check the functions, the predicates and the methods of a program.
+pub fn check_program(
+    functions: &[Function],
+    predicates: &[Predicate],
+    methods: &[CfgMethod],
+) -> Vec<TypeError> {
+    let mut checker = TypeChecker {
+        functions: functions
+            .iter()
+            .map(|function| (function.get_identifier(), function))
+            .collect(),
+        predicates: predicates
+            .iter()
+            .map(|predicate| {
+                let name = predicate.name();
+                let arg_type = if Predicate::is_user_predicate_name(name) {
+                    predicate.self_place().get_type().clone()
+                } else {
+                    Type::typed_ref(name)
+                };
+                (name, arg_type)
+            })
+            .collect(),
+        item: String::new(),
+        scope: vec![],
+        position: Position::default(),
+        errors: vec![],
+    };
+    for function in functions {
+        checker.check_function(function);
+    }
+    for predicate in predicates {
+        checker.check_predicate(predicate);
+    }
+    for method in methods {
+        checker.check_method(method);
+    }
+    checker.errors
+}
+
+/// Are the two types the same Viper type?
+fn same_type(left: &Type, right: &Type) -> bool {
+    match (left, right) {
+        (Type::TypedMap(left_key, left_value), Type::TypedMap(right_key, right_value)) => {
+            same_type(left_key, right_key) && same_type(left_value, right_value)
+        }
+        (Type::TypedSeq(left_elem), Type::TypedSeq(right_elem)) => {
+            same_type(left_elem, right_elem)
+        }
+        (Type::Domain(left_name), Type::Domain(right_name)) => left_name == right_name,
+        _ => left == right,
+    }
+}
+
+struct TypeChecker<'a> {
+    /// The declared functions, by identifier.
+    functions: HashMap<String, &'a Function>,
+    /// The declared predicates, by name, with the type of their argument.
+    predicates: HashMap<&'a str, Type>,
+    /// The item that is being checked.
+    item: String,
+    /// The variables in scope, the innermost last.
+    scope: Vec<LocalVar>,
+    /// The position of the closest enclosing construct that has one.
+    position: Position,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn error<C: fmt::Display, S: ToString>(&mut self, construct: &C, message: S) {
+        self.errors.push(TypeError {
+            item: self.item.clone(),
+            construct: construct.to_string(),
+            message: message.to_string(),
+            position: self.position.clone(),
+        });
+    }
+
+    /// Check the items of `self.item`, starting with the variables in `scope`.
+    fn begin_item<S: ToString>(&mut self, item: S, scope: Vec<LocalVar>) {
+        self.item = item.to_string();
+        self.scope = scope;
+        self.position = Position::default();
+    }
+
+    fn check_function(&mut self, function: &Function) {
+        self.begin_item(&function.name, function.formal_args.clone());
+        for pre in &function.pres {
+            self.check_bool(pre);
+        }
+        if let Some(ref body) = function.body {
+            if let Some(typ) = self.check_expr(body) {
+                if !same_type(&typ, &function.return_type) {
+                    self.error(
+                        body,
+                        format!(
+                            "the body has type {}, but the function returns {}",
+                            typ, function.return_type
+                        ),
+                    );
+                }
+            }
+        }
+        self.scope.push(LocalVar::new("__result", function.return_type.clone()));
+        for post in &function.posts {
+            self.check_bool(post);
+        }
+    }
+
+    fn check_predicate(&mut self, predicate: &Predicate) {
+        let (this, body) = match predicate {
+            Predicate::Struct(p) => (p.this.clone(), p.body.clone()),
+            Predicate::Enum(p) => (p.this.clone(), Some(p.body())),
+        };
+        self.begin_item(predicate.name(), vec![this]);
+        if let Some(ref body) = body {
+            self.check_bool(body);
+        }
+    }
+
+    fn check_method(&mut self, method: &CfgMethod) {
+        self.begin_item(method.name(), method.get_all_vars());
+        method.walk_invariants(|inv| self.check_bool(inv));
+        method.walk_statements(|stmt| self.check_stmt(stmt));
+        method.walk_successors(|successor| {
+            if let Successor::GotoSwitch(ref guarded_targets, _) = successor {
+                for (guard, _) in guarded_targets {
+                    self.check_bool(guard);
+                }
+            }
+        });
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        let outer_position = self.position.clone();
+        if let Some(pos) = stmt.pos() {
+            if !pos.is_default() {
+                self.position = pos.clone();
+            }
+        }
+        match stmt {
+            Stmt::Comment(_)
+            | Stmt::Label(_)
+            | Stmt::BeginFrame
+            | Stmt::EndFrame
+            | Stmt::ExpireBorrows(_) => {}
+            Stmt::Inhale(expr, _)
+            | Stmt::Exhale(expr, _)
+            | Stmt::Assert(expr, _, _)
+            | Stmt::Assume(expr, _)
+            | Stmt::Refute(expr, _)
+            | Stmt::Obtain(expr, _)
+            | Stmt::ApplyMagicWand(expr, _) => self.check_bool(expr),
+            Stmt::MethodCall(_, args, targets) => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                for target in targets {
+                    self.check_local(target, stmt);
+                }
+            }
+            Stmt::Assign(lhs, rhs, _) => {
+                let lhs_type = self.check_expr(lhs);
+                let rhs_type = self.check_expr(rhs);
+                if let (Some(lhs_type), Some(rhs_type)) = (lhs_type, rhs_type) {
+                    if !same_type(&lhs_type, &rhs_type) {
+                        self.error(
+                            stmt,
+                            format!(
+                                "cannot assign a value of type {} to a place of type {}",
+                                rhs_type, lhs_type
+                            ),
+                        );
+                    }
+                }
+            }
+            Stmt::Fold(predicate_name, args, ..) | Stmt::Unfold(predicate_name, args, ..) => {
+                self.check_predicate_args(predicate_name, args, stmt);
+            }
+            Stmt::TransferPerm(lhs, rhs, _) => {
+                self.check_expr(lhs);
+                self.check_expr(rhs);
+            }
+            Stmt::PackageMagicWand(wand, package_stmts, _, vars, _) => {
+                let scope_len = self.scope.len();
+                self.scope.extend(vars.iter().cloned());
+                self.check_bool(wand);
+                for package_stmt in package_stmts {
+                    self.check_stmt(package_stmt);
+                }
+                self.scope.truncate(scope_len);
+            }
+            Stmt::If(guard, then_stmts) => {
+                self.check_bool(guard);
+                for then_stmt in then_stmts {
+                    self.check_stmt(then_stmt);
+                }
+            }
+        }
+        self.position = outer_position;
+    }
+
+    /// Check that `var` is in scope, with the same type.
+    fn check_local<C: fmt::Display>(&mut self, var: &LocalVar, construct: &C) {
+        let declared_type = self
+            .scope
+            .iter()
+            .rev()
+            .find(|declared| declared.name == var.name)
+            .map(|declared| declared.typ.clone());
+        match declared_type {
+            None => self.error(
+                construct,
+                format!("the variable `{}` is not in scope", var.name),
+            ),
+            Some(ref typ) if !same_type(typ, &var.typ) => self.error(
+                construct,
+                format!(
+                    "the variable `{}` is declared with type {}, but used with type {}",
+                    var.name, typ, var.typ
+                ),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    /// Check that `predicate_name` is declared, and that `args` are its (single) argument.
+    fn check_predicate_args<C: fmt::Display>(
+        &mut self,
+        predicate_name: &str,
+        args: &[Expr],
+        construct: &C,
+    ) {
+        let arg_type = match self.predicates.get(predicate_name) {
+            Some(arg_type) => arg_type.clone(),
+            None => {
+                self.error(
+                    construct,
+                    format!("the predicate `{}` is not declared", predicate_name),
+                );
+                Type::typed_ref(predicate_name)
+            }
+        };
+        if args.len() != 1 {
+            self.error(
+                construct,
+                format!(
+                    "the predicate `{}` takes 1 argument, but {} are given",
+                    predicate_name,
+                    args.len()
+                ),
+            );
+        }
+        for arg in args {
+            self.check_expected(arg, &arg_type);
+        }
+    }
+
+    fn check_bool(&mut self, expr: &Expr) {
+        self.check_expected(expr, &Type::Bool);
+    }
+
+    fn check_int(&mut self, expr: &Expr) {
+        self.check_expected(expr, &Type::Int);
+    }
+
+    /// Check that `expr` has the type `expected`.
+    fn check_expected(&mut self, expr: &Expr, expected: &Type) {
+        if let Some(typ) = self.check_expr(expr) {
+            if !same_type(&typ, expected) {
+                self.error(expr, format!("expected type {}, found {}", expected, typ));
+            }
+        }
+    }
+
+    /// Check `expr`, returning its type or `None` if it is ill-typed.
+    fn check_expr(&mut self, expr: &Expr) -> Option<Type> {
+        let outer_position = self.position.clone();
+        if !expr.pos().is_default() {
+            self.position = expr.pos().clone();
+        }
+        let errors_before = self.errors.len();
+        let typ = self.check_expr_kind(expr);
+        self.position = outer_position;
+        if self.errors.len() > errors_before {
+            None
+        } else {
+            typ
+        }
+    }
+
+    fn check_expr_kind(&mut self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Local(var, _) => {
+                self.check_local(var, expr);
+                Some(var.typ.clone())
+            }
+            Expr::Variant(base, field, _) | Expr::Field(base, field, _) => {
+                let base_type = self.check_expr(base)?;
+                if !base_type.is_ref() {
+                    self.error(
+                        expr,
+                        format!(
+                            "the field `{}` is accessed on a value of type {}",
+                            field.name, base_type
+                        ),
+                    );
+                }
+                Some(field.typ.clone())
+            }
+            Expr::AddrOf(..) => {
+                self.error(expr, "address-of expressions cannot be encoded in Viper");
+                None
+            }
+            Expr::LabelledOld(_, body, _) => self.check_expr(body),
+            Expr::Const(Const::Bool(_), _) => Some(Type::Bool),
+            Expr::Const(Const::Int(_), _) | Expr::Const(Const::BigInt(_), _) => Some(Type::Int),
+            Expr::Const(Const::Str(_), _) => Some(Type::TypedSeq(box Type::Int)),
+            Expr::MagicWand(lhs, rhs, ..) => {
+                self.check_bool(lhs);
+                self.check_bool(rhs);
+                Some(Type::Bool)
+            }
+            Expr::PredicateAccessPredicate(predicate_name, arg, ..) => {
+                self.check_predicate_args(predicate_name, &[(**arg).clone()], expr);
+                Some(Type::Bool)
+            }
+            Expr::FieldAccessPredicate(place, ..) => {
+                match **place {
+                    Expr::Field(..) | Expr::Variant(..) => {
+                        self.check_expr(place);
+                    }
+                    _ => self.error(expr, "the permission is not about a field"),
+                }
+                Some(Type::Bool)
+            }
+            Expr::UnaryOp(UnaryOpKind::Not, arg, _) => {
+                self.check_bool(arg);
+                Some(Type::Bool)
+            }
+            Expr::UnaryOp(UnaryOpKind::Minus, arg, _) => {
+                self.check_int(arg);
+                Some(Type::Int)
+            }
+            Expr::BinOp(op, left, right, _) => self.check_bin_op(expr, *op, left, right),
+            Expr::Unfolding(predicate_name, args, body, ..) => {
+                self.check_predicate_args(predicate_name, args, expr);
+                self.check_expr(body)
+            }
+            Expr::Cond(guard, then_expr, else_expr, _) => {
+                self.check_bool(guard);
+                let then_type = self.check_expr(then_expr)?;
+                let else_type = self.check_expr(else_expr)?;
+                if !same_type(&then_type, &else_type) {
+                    self.error(
+                        expr,
+                        format!("the branches have types {} and {}", then_type, else_type),
+                    );
+                }
+                Some(then_type)
+            }
+            Expr::ForAll(vars, triggers, body, _) | Expr::Exists(vars, triggers, body, _) => {
+                let scope_len = self.scope.len();
+                self.scope.extend(vars.iter().cloned());
+                for trigger in triggers {
+                    for trigger_expr in trigger.elements() {
+                        self.check_expr(trigger_expr);
+                    }
+                }
+                self.check_bool(body);
+                self.scope.truncate(scope_len);
+                Some(Type::Bool)
+            }
+            Expr::LetExpr(var, def, body, _) => {
+                self.check_expected(def, &var.typ);
+                self.scope.push(var.clone());
+                let body_type = self.check_expr(body);
+                self.scope.pop();
+                body_type
+            }
+            Expr::FuncApp(function_name, args, formal_args, return_type, _) => {
+                if args.len() != formal_args.len() {
+                    self.error(
+                        expr,
+                        format!(
+                            "the function `{}` takes {} arguments, but {} are given",
+                            function_name,
+                            formal_args.len(),
+                            args.len()
+                        ),
+                    );
+                }
+                let identifier = compute_identifier(function_name, formal_args, return_type);
+                let declared_return_type = self
+                    .functions
+                    .get(&identifier)
+                    .map(|function| function.return_type.clone());
+                if let Some(ref declared_return_type) = declared_return_type {
+                    if !same_type(declared_return_type, return_type) {
+                        self.error(
+                            expr,
+                            format!(
+                                "the function `{}` returns {}, but it is used as returning {}",
+                                function_name, declared_return_type, return_type
+                            ),
+                        );
+                    }
+                }
+                for (arg, formal_arg) in args.iter().zip(formal_args.iter()) {
+                    self.check_expected(arg, &formal_arg.typ);
+                }
+                Some(return_type.clone())
+            }
+            Expr::DomainFuncApp(func, args, _) => {
+                if args.len() != func.formal_args.len() {
+                    self.error(
+                        expr,
+                        format!(
+                            "the function `{}` of the domain `{}` takes {} arguments, \
+                             but {} are given",
+                            func.name,
+                            func.domain_name,
+                            func.formal_args.len(),
+                            args.len()
+                        ),
+                    );
+                }
+                for (arg, formal_arg) in args.iter().zip(func.formal_args.iter()) {
+                    self.check_expected(arg, &formal_arg.typ);
+                }
+                Some(func.return_type.clone())
+            }
+            Expr::MapLookup(map, key, _) => {
+                let (key_type, value_type) = self.check_map(expr, map)?;
+                self.check_expected(key, &key_type);
+                Some(value_type)
+            }
+            Expr::MapContains(map, key, _) => {
+                let (key_type, _) = self.check_map(expr, map)?;
+                self.check_expected(key, &key_type);
+                Some(Type::Bool)
+            }
+            Expr::MapUpdate(map, key, value, _) => {
+                let (key_type, value_type) = self.check_map(expr, map)?;
+                self.check_expected(key, &key_type);
+                self.check_expected(value, &value_type);
+                Some(Type::TypedMap(box key_type, box value_type))
+            }
+            Expr::MapLen(map, _) => {
+                self.check_map(expr, map)?;
+                Some(Type::Int)
+            }
+            Expr::SeqLen(seq, _) => {
+                match self.check_expr(seq)? {
+                    Type::TypedSeq(_) => {}
+                    typ => self.error(expr, format!("expected a sequence, found {}", typ)),
+                }
+                Some(Type::Int)
+            }
+            Expr::SeqIndex(seq, index, _) => {
+                self.check_expected(index, &Type::Int);
+                match self.check_expr(seq)? {
+                    Type::TypedSeq(box elem_type) => Some(elem_type),
+                    typ => {
+                        self.error(expr, format!("expected a sequence, found {}", typ));
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_bin_op(
+        &mut self,
+        expr: &Expr,
+        op: BinOpKind,
+        left: &Expr,
+        right: &Expr,
+    ) -> Option<Type> {
+        match op {
+            BinOpKind::EqCmp | BinOpKind::NeCmp => {
+                let left_type = self.check_expr(left)?;
+                let right_type = self.check_expr(right)?;
+                if !same_type(&left_type, &right_type) {
+                    self.error(
+                        expr,
+                        format!(
+                            "cannot compare a value of type {} with one of type {}",
+                            left_type, right_type
+                        ),
+                    );
+                }
+                Some(Type::Bool)
+            }
+            BinOpKind::GtCmp | BinOpKind::GeCmp | BinOpKind::LtCmp | BinOpKind::LeCmp => {
+                self.check_int(left);
+                self.check_int(right);
+                Some(Type::Bool)
+            }
+            BinOpKind::Add | BinOpKind::Sub | BinOpKind::Mul | BinOpKind::Div | BinOpKind::Mod => {
+                self.check_int(left);
+                self.check_int(right);
+                Some(Type::Int)
+            }
+            BinOpKind::And | BinOpKind::Or | BinOpKind::Implies => {
+                self.check_bool(left);
+                self.check_bool(right);
+                Some(Type::Bool)
+            }
+        }
+    }
+
+    /// Check that `map` is a map, returning the types of its keys and of its values.
+    fn check_map(&mut self, expr: &Expr, map: &Expr) -> Option<(Type, Type)> {
+        match self.check_expr(map)? {
+            Type::TypedMap(box key_type, box value_type) => Some((key_type, value_type)),
+            typ => {
+                self.error(expr, format!("expected a map, found {}", typ));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(formal_args: Vec<LocalVar>, body: Expr) -> Function {
+        Function {
+            name: "f".to_string(),
+            formal_args,
+            return_type: Type::Int,
+            pres: vec![],
+            posts: vec![],
+            body: Some(body),
+        }
+    }
+
+    #[test]
+    fn well_typed_function() {
+        let x = LocalVar::new("x", Type::Int);
+        let mut f = function(vec![x.clone()], Expr::add(x.clone().into(), 1.into()));
+        f.posts.push(Expr::gt_cmp(
+            LocalVar::new("__result", Type::Int).into(),
+            x.into(),
+        ));
+        assert!(check_program(&[f], &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn ill_typed_bin_op() {
+        let b = LocalVar::new("b", Type::Bool);
+        let f = function(vec![b.clone()], Expr::add(b.into(), 1.into()));
+        let errors = check_program(&[f], &[], &[]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expected type Int, found Bool");
+        assert_eq!(errors[0].construct, "b");
+    }
+
+    #[test]
+    fn quantified_variable_out_of_scope() {
+        let i = LocalVar::new("i", Type::Int);
+        let mut f = function(vec![], 0.into());
+        f.pres.push(Expr::and(
+            Expr::forall(vec![i.clone()], vec![], Expr::ge_cmp(i.clone().into(), 0.into())),
+            Expr::ge_cmp(i.into(), 0.into()),
+        ));
+        let errors = check_program(&[f], &[], &[]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "the variable `i` is not in scope");
+    }
+}
@@ -23,6 +23,7 @@ extern crate num_rational;
 extern crate num_traits;
 extern crate prusti_filter;
 extern crate prusti_interface;
+extern crate prusti_std_models;
 extern crate regex;
 extern crate rustc;
 extern crate rustc_data_structures;
@@ -38,6 +39,9 @@ extern crate lazy_static;
 #[macro_use]
 extern crate pretty_assertions;
 
+pub mod demangle;
 mod encoder;
+mod resource_limits;
+pub mod smt;
 mod utils;
 pub mod verifier;
@@ -0,0 +1,105 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The limits of the resources that Viper and Z3 can use to verify a method.
+//!
+//! The limits are set globally by the configuration, and can be changed for a single procedure
+//! with the attributes `#[assert_timeout="<ms>"]`, `#[z3_random_seed="<seed>"]` and
+//! `#[quantifier_instantiation_bound="<instances>"]`. A method with its own limits is verified
+//! by a verifier of its own, so that the limits of the other methods are not changed.
+
+use prusti_interface::config;
+use prusti_interface::data::ProcedureDefId;
+use prusti_interface::environment::Environment;
+
+/// The attribute that sets the timeout of each assertion of a procedure, in milliseconds.
+pub const ASSERT_TIMEOUT_ATTR: &str = "assert_timeout";
+
+/// The attribute that sets the random seed of Z3 for a procedure.
+pub const Z3_RANDOM_SEED_ATTR: &str = "z3_random_seed";
+
+/// The attribute that bounds the instances of each quantifier for a procedure.
+pub const QUANTIFIER_INSTANTIATION_BOUND_ATTR: &str = "quantifier_instantiation_bound";
+
+/// The limits of the resources that the verifier can use for a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// The timeout of each assertion, in milliseconds. Only Silicon supports it.
+    pub assert_timeout: u64,
+    /// The random seed of Z3, or `0` for the default seed.
+    pub z3_random_seed: u64,
+    /// The maximum number of instances of each quantifier, or `0` for no bound. Only Silicon
+    /// supports it.
+    pub quantifier_instantiation_bound: u64,
+}
+
+impl ResourceLimits {
+    /// The limits set by the configuration.
+    pub fn from_config() -> Self {
+        ResourceLimits {
+            assert_timeout: config::assert_timeout(),
+            z3_random_seed: config::z3_random_seed(),
+            quantifier_instantiation_bound: config::quantifier_instantiation_bound(),
+        }
+    }
+
+    /// The limits set by the configuration, changed by the attributes of the procedure.
+    /// Report an error for each attribute whose value is not a number.
+    pub fn for_procedure(env: &Environment, proc_def_id: ProcedureDefId) -> Self {
+        let parse_attr = |attr_name: &str| -> Option<u64> {
+            let value = env.get_attr(proc_def_id, attr_name)?;
+            let parsed = value.trim().parse().ok();
+            if parsed.is_none() {
+                env.span_err(
+                    env.get_item_span(proc_def_id),
+                    &format!(
+                        "[Prusti] the value of `{}` must be a non-negative integer, found `{}`",
+                        attr_name, value
+                    ),
+                );
+            }
+            parsed
+        };
+        let mut limits = ResourceLimits::from_config();
+        if let Some(assert_timeout) = parse_attr(ASSERT_TIMEOUT_ATTR) {
+            limits.assert_timeout = assert_timeout;
+        }
+        if let Some(z3_random_seed) = parse_attr(Z3_RANDOM_SEED_ATTR) {
+            limits.z3_random_seed = z3_random_seed;
+        }
+        if let Some(bound) = parse_attr(QUANTIFIER_INSTANTIATION_BOUND_ATTR) {
+            limits.quantifier_instantiation_bound = bound;
+        }
+        limits
+    }
+
+    /// The arguments that set the limits in Silicon.
+    pub fn silicon_args(&self) -> Vec<String> {
+        let mut args = vec!["--assertTimeout".to_string(), self.assert_timeout.to_string()];
+        let mut z3_args = vec![];
+        if self.z3_random_seed != 0 {
+            z3_args.push(format!("smt.random_seed={}", self.z3_random_seed));
+        }
+        if self.quantifier_instantiation_bound != 0 {
+            z3_args.push(format!("smt.qi.max_instances={}", self.quantifier_instantiation_bound));
+        }
+        if !z3_args.is_empty() {
+            // Silicon expects the arguments of Z3 between quotation marks.
+            args.push("--z3Args".to_string());
+            args.push(format!("\"{}\"", z3_args.join(" ")));
+        }
+        args
+    }
+
+    /// The options of Boogie that set the limits in Carbon.
+    pub fn boogie_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if self.z3_random_seed != 0 {
+            options.push(format!("/z3opt:smt.random_seed={}", self.z3_random_seed));
+        }
+        options
+    }
+}
@@ -0,0 +1,356 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Lowering of VIR expressions to SMT-LIB2 terms.
+
+use encoder::vir::{self, WithIdentifier};
+use std::collections::HashSet;
+
+/// The reason why a VIR item cannot be expressed in SMT-LIB2.
+pub type Unsupported = String;
+
+/// Quote a VIR identifier, so that it is a valid SMT-LIB2 symbol.
+pub fn quote(name: &str) -> String {
+    format!("|{}|", name)
+}
+
+pub fn lower_type(typ: &vir::Type) -> Result<&'static str, Unsupported> {
+    match typ {
+        vir::Type::Int => Ok("Int"),
+        vir::Type::Bool => Ok("Bool"),
+        vir::Type::TypedRef(ref name, _) => Err(format!("values of type {} are in the heap", name)),
+        vir::Type::TypedMap(..) => Err(format!("maps of type {} are not supported", typ)),
+        vir::Type::TypedSeq(..) => Err(format!("sequences of type {} are not supported", typ)),
+        vir::Type::Domain(..) => Err(format!("values of the domain {} are not supported", typ)),
+    }
+}
+
+/// Lower a list of variables to an SMT-LIB2 list of sorted variables.
+pub fn lower_sorted_vars(vars: &[vir::LocalVar]) -> Result<String, Unsupported> {
+    let sorted_vars = vars
+        .iter()
+        .map(|var| Ok(format!("({} {})", quote(&var.name), lower_type(&var.typ)?)))
+        .collect::<Result<Vec<_>, Unsupported>>()?;
+    Ok(format!("({})", sorted_vars.join(" ")))
+}
+
+/// Lowers a place of a method body, given the label of the enclosing `old` expression, if any.
+pub type PlaceLowering<'a> = &'a Fn(&vir::Expr, Option<&str>) -> Result<String, Unsupported>;
+
+/// The context of a subexpression.
+#[derive(Clone, Default)]
+struct Scope<'e> {
+    /// The label of the enclosing `old` expression, if any.
+    label: Option<&'e str>,
+    /// The variables bound by the enclosing quantifiers and `let` expressions.
+    bound_vars: HashSet<&'e str>,
+}
+
+impl<'e> Scope<'e> {
+    fn bind<I: IntoIterator<Item = &'e vir::LocalVar>>(&self, vars: I) -> Self {
+        let mut scope = self.clone();
+        scope.bound_vars.extend(vars.into_iter().map(|var| var.name.as_str()));
+        scope
+    }
+}
+
+/// Lowers expressions that may only call the functions in `functions`.
+pub struct ExprLowering<'a> {
+    /// The identifiers of the functions declared in the SMT-LIB2 script.
+    functions: &'a HashSet<String>,
+    /// How to lower the places of a method body. Expressions of pure functions have no places.
+    places: Option<PlaceLowering<'a>>,
+}
+
+impl<'a> ExprLowering<'a> {
+    pub fn new(functions: &'a HashSet<String>) -> Self {
+        ExprLowering {
+            functions,
+            places: None,
+        }
+    }
+
+    /// Lowers expressions of a method body, whose integer and boolean places are lowered by
+    /// `places`. Permissions are not checked: accessibility predicates are lowered to `true`.
+    pub fn with_places(functions: &'a HashSet<String>, places: PlaceLowering<'a>) -> Self {
+        ExprLowering {
+            functions,
+            places: Some(places),
+        }
+    }
+
+    /// Lower the conjunction of `exprs`.
+    pub fn lower_conjunction(&self, exprs: &[vir::Expr]) -> Result<String, Unsupported> {
+        let terms = exprs
+            .iter()
+            .map(|expr| self.lower_expr(expr))
+            .collect::<Result<Vec<_>, Unsupported>>()?;
+        Ok(match terms.len() {
+            0 => "true".to_string(),
+            1 => terms[0].clone(),
+            _ => format!("(and {})", terms.join(" ")),
+        })
+    }
+
+    pub fn lower_expr(&self, expr: &vir::Expr) -> Result<String, Unsupported> {
+        self.lower_in_scope(expr, &Scope::default())
+    }
+
+    fn lower_in_scope<'e>(
+        &self,
+        expr: &'e vir::Expr,
+        scope: &Scope<'e>,
+    ) -> Result<String, Unsupported> {
+        Ok(match expr {
+            vir::Expr::Local(ref var, _) if scope.bound_vars.contains(var.name.as_str()) => {
+                quote(&var.name)
+            }
+            vir::Expr::Local(..) | vir::Expr::Field(..) | vir::Expr::Variant(..)
+                if self.places.is_some() =>
+            {
+                lower_type(expr.get_type())?;
+                self.places.unwrap()(expr, scope.label)?
+            }
+            vir::Expr::Local(ref var, _) => {
+                lower_type(&var.typ)?;
+                quote(&var.name)
+            }
+            vir::Expr::Const(vir::Const::Bool(value), _) => value.to_string(),
+            vir::Expr::Const(vir::Const::Int(value), _) => {
+                if *value < 0 {
+                    format!("(- {})", -(*value as i128))
+                } else {
+                    value.to_string()
+                }
+            }
+            vir::Expr::Const(vir::Const::BigInt(ref value), _) => {
+                if value.starts_with('-') {
+                    format!("(- {})", &value[1..])
+                } else {
+                    value.clone()
+                }
+            }
+            vir::Expr::UnaryOp(op, box ref arg, _) => {
+                let op = match op {
+                    vir::UnaryOpKind::Not => "not",
+                    vir::UnaryOpKind::Minus => "-",
+                };
+                format!("({} {})", op, self.lower_in_scope(arg, scope)?)
+            }
+            vir::Expr::BinOp(op, box ref left, box ref right, _) => {
+                let left = self.lower_in_scope(left, scope)?;
+                let right = self.lower_in_scope(right, scope)?;
+                match op {
+                    vir::BinOpKind::NeCmp => format!("(not (= {} {}))", left, right),
+                    // Rust rounds the quotient towards zero, while SMT-LIB2 rounds it so that
+                    // the remainder is not negative. `Mod` is the remainder of SMT-LIB2 and of
+                    // Viper, from which `vir::Expr::rem` builds the remainder of Rust.
+                    vir::BinOpKind::Div => format!(
+                        "(let ((|__left| {}) (|__right| {})) \
+                         (ite (= (>= |__left| 0) (>= |__right| 0)) \
+                         (div (abs |__left|) (abs |__right|)) \
+                         (- (div (abs |__left|) (abs |__right|)))))",
+                        left, right
+                    ),
+                    _ => {
+                        let op = match op {
+                            vir::BinOpKind::EqCmp => "=",
+                            vir::BinOpKind::NeCmp | vir::BinOpKind::Div => unreachable!(),
+                            vir::BinOpKind::GtCmp => ">",
+                            vir::BinOpKind::GeCmp => ">=",
+                            vir::BinOpKind::LtCmp => "<",
+                            vir::BinOpKind::LeCmp => "<=",
+                            vir::BinOpKind::Add => "+",
+                            vir::BinOpKind::Sub => "-",
+                            vir::BinOpKind::Mul => "*",
+                            vir::BinOpKind::Mod => "mod",
+                            vir::BinOpKind::And => "and",
+                            vir::BinOpKind::Or => "or",
+                            vir::BinOpKind::Implies => "=>",
+                        };
+                        format!("({} {} {})", op, left, right)
+                    }
+                }
+            }
+            vir::Expr::Cond(box ref guard, box ref then_expr, box ref else_expr, _) => format!(
+                "(ite {} {} {})",
+                self.lower_in_scope(guard, scope)?,
+                self.lower_in_scope(then_expr, scope)?,
+                self.lower_in_scope(else_expr, scope)?
+            ),
+            vir::Expr::LetExpr(ref var, box ref def, box ref body, _) => {
+                lower_type(&var.typ)?;
+                format!(
+                    "(let (({} {})) {})",
+                    quote(&var.name),
+                    self.lower_in_scope(def, scope)?,
+                    self.lower_in_scope(body, &scope.bind(Some(var)))?
+                )
+            }
+            vir::Expr::ForAll(ref vars, _, box ref body, _) => format!(
+                "(forall {} {})",
+                lower_sorted_vars(vars)?,
+                self.lower_in_scope(body, &scope.bind(vars))?
+            ),
+            vir::Expr::Exists(ref vars, _, box ref body, _) => format!(
+                "(exists {} {})",
+                lower_sorted_vars(vars)?,
+                self.lower_in_scope(body, &scope.bind(vars))?
+            ),
+            vir::Expr::FuncApp(ref name, ref args, ref formal_args, ref return_type, _) => {
+                let identifier = vir::compute_identifier(name, formal_args, return_type);
+                if !self.functions.contains(&identifier) {
+                    return Err(format!("the function {} cannot be expressed", name));
+                }
+                if args.is_empty() {
+                    quote(&identifier)
+                } else {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.lower_in_scope(arg, scope))
+                        .collect::<Result<Vec<_>, Unsupported>>()?;
+                    format!("({} {})", quote(&identifier), args.join(" "))
+                }
+            }
+            vir::Expr::DomainFuncApp(..) => {
+                return Err(format!("the domain expression {} is not supported", expr));
+            }
+            vir::Expr::Variant(..) |
+            vir::Expr::Field(..) |
+            vir::Expr::AddrOf(..) => {
+                return Err(format!("the expression {} depends on the heap", expr));
+            }
+            vir::Expr::LabelledOld(ref label, box ref arg, _) if self.places.is_some() => {
+                let mut old_scope = scope.clone();
+                old_scope.label = Some(label.as_str());
+                self.lower_in_scope(arg, &old_scope)?
+            }
+            vir::Expr::LabelledOld(..) => {
+                return Err(format!("the old expression {} is not supported", expr));
+            }
+            vir::Expr::MapLookup(..) |
+            vir::Expr::MapContains(..) |
+            vir::Expr::MapUpdate(..) |
+            vir::Expr::MapLen(..) => {
+                return Err(format!("the map expression {} is not supported", expr));
+            }
+            vir::Expr::Const(vir::Const::Str(..), _) |
+            vir::Expr::SeqLen(..) |
+            vir::Expr::SeqIndex(..) => {
+                return Err(format!("the sequence expression {} is not supported", expr));
+            }
+            vir::Expr::PredicateAccessPredicate(..) |
+            vir::Expr::FieldAccessPredicate(..) if self.places.is_some() => "true".to_string(),
+            vir::Expr::Unfolding(_, _, box ref body, ..) if self.places.is_some() => {
+                self.lower_in_scope(body, scope)?
+            }
+            vir::Expr::MagicWand(..) |
+            vir::Expr::PredicateAccessPredicate(..) |
+            vir::Expr::FieldAccessPredicate(..) |
+            vir::Expr::Unfolding(..) => {
+                return Err(format!("the expression {} uses permissions", expr));
+            }
+        })
+    }
+}
+
+/// Split the top-level conjunctions of `expr`.
+pub fn split_conjuncts(expr: &vir::Expr) -> Vec<&vir::Expr> {
+    match expr {
+        vir::Expr::BinOp(vir::BinOpKind::And, box left, box right, _) => {
+            let mut conjuncts = split_conjuncts(left);
+            conjuncts.extend(split_conjuncts(right));
+            conjuncts
+        }
+        vir::Expr::Const(vir::Const::Bool(true), _) => vec![],
+        _ => vec![expr],
+    }
+}
+
+/// Lower the declaration of a function, if its signature can be expressed.
+pub fn lower_function_declaration(function: &vir::Function) -> Result<String, Unsupported> {
+    let arg_sorts = function
+        .formal_args
+        .iter()
+        .map(|arg| lower_type(&arg.typ))
+        .collect::<Result<Vec<_>, Unsupported>>()?;
+    let return_sort = lower_type(&function.return_type)?;
+    Ok(format!(
+        "(declare-fun {} ({}) {})",
+        quote(&function.get_identifier()),
+        arg_sorts.join(" "),
+        return_sort
+    ))
+}
+
+/// Lower the application of `function` to its own formal arguments.
+pub fn lower_function_self_application(function: &vir::Function) -> String {
+    if function.formal_args.is_empty() {
+        quote(&function.get_identifier())
+    } else {
+        let args: Vec<_> = function
+            .formal_args
+            .iter()
+            .map(|arg| quote(&arg.name))
+            .collect();
+        format!("({} {})", quote(&function.get_identifier()), args.join(" "))
+    }
+}
+
+/// Universally quantify `body` over the formal arguments of `function`.
+pub fn quantify_over_arguments(function: &vir::Function, body: String) -> String {
+    if function.formal_args.is_empty() {
+        body
+    } else {
+        // The signature has already been checked by `lower_function_declaration`.
+        format!(
+            "(forall {} {})",
+            lower_sorted_vars(&function.formal_args).unwrap(),
+            body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_the_quotient_towards_zero() {
+        let functions = HashSet::new();
+        let x = vir::LocalVar::new("x", vir::Type::Int);
+        let quotient = vir::Expr::div(x.into(), (-2).into());
+        assert_eq!(
+            ExprLowering::new(&functions).lower_expr(&quotient).unwrap(),
+            "(let ((|__left| |x|) (|__right| (- 2))) \
+             (ite (= (>= |__left| 0) (>= |__right| 0)) \
+             (div (abs |__left|) (abs |__right|)) \
+             (- (div (abs |__left|) (abs |__right|)))))"
+        );
+    }
+
+    #[test]
+    fn lowers_the_places_of_method_bodies() {
+        let functions = HashSet::new();
+        let x = vir::LocalVar::new("x", vir::Type::Int);
+        let bound = vir::LocalVar::new("i", vir::Type::Int);
+        let expr = vir::Expr::forall(
+            vec![bound.clone()],
+            vec![],
+            vir::Expr::gt_cmp(bound.into(), vir::Expr::labelled_old("pre", x.clone().into())),
+        );
+        let places = |place: &vir::Expr, label: Option<&str>| {
+            Ok(format!("{}@{}", place, label.unwrap_or("now")))
+        };
+        assert_eq!(
+            ExprLowering::with_places(&functions, &places).lower_expr(&expr).unwrap(),
+            "(forall ((|i| Int)) (> |i| x@pre))"
+        );
+        assert!(ExprLowering::new(&functions)
+            .lower_expr(&vir::Expr::labelled_old("pre", x.into()))
+            .is_err());
+    }
+}
@@ -0,0 +1,22 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! An experimental verifier that does not need a JVM: it serializes the pure
+//! functions of the VIR program to SMT-LIB2 and checks their postconditions
+//! with a locally installed SMT solver. The assertions of the methods are
+//! checked by a symbolic execution of their bodies.
+//!
+//! Only the heap-independent subset of the VIR can be expressed, i.e. pure
+//! functions whose arguments and result are integers or booleans, and loop-free
+//! methods that do not call other methods. The items that cannot be expressed
+//! are reported to the user as not verified, and the verification fails.
+
+mod lowering;
+mod solver;
+mod symbolic_execution;
+mod verifier;
+
+pub use self::verifier::SmtVerifier;
@@ -0,0 +1,52 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Invocation of an SMT solver that reads SMT-LIB2 from its standard input.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The answer of the solver to a `(check-sat)` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckSatResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+/// Run the solver `solver_path` on `script`, returning the answers to the
+/// `(check-sat)` commands of the script, in order.
+pub fn run_solver(solver_path: &str, script: &str) -> Result<Vec<CheckSatResult>, String> {
+    let mut child = Command::new(solver_path)
+        .args(&["-smt2", "-in"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start the SMT solver '{}': {}", solver_path, e))?;
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .map_err(|e| format!("Failed to send the script to the SMT solver: {}", e))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to read the output of the SMT solver: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    debug!("SMT solver output:\n{}", stdout);
+
+    let mut results = vec![];
+    for line in stdout.lines() {
+        match line.trim() {
+            "sat" => results.push(CheckSatResult::Sat),
+            "unsat" => results.push(CheckSatResult::Unsat),
+            "unknown" | "timeout" => results.push(CheckSatResult::Unknown),
+            "" => {}
+            other => return Err(format!("Unexpected output of the SMT solver: {}", other)),
+        }
+    }
+    Ok(results)
+}
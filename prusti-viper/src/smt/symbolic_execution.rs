@@ -0,0 +1,395 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Symbolic execution of method bodies, which checks their assertions along each execution path.
+//!
+//! The integer and boolean places of a method body are modelled as SMT-LIB2 constants, one for
+//! each value that a place takes on a path. This relies on the ownership of Rust, which ensures
+//! that a place is not modified through another place. Permissions are not checked, and the
+//! methods that assign references, call other methods, contain loops or use magic wands are
+//! not supported.
+
+use encoder::vir;
+use smt::lowering::{self, ExprLowering, Unsupported};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The maximum number of execution paths of a supported method.
+const MAX_PATHS: usize = 256;
+
+/// An assertion of a method body, checked by a `(check-sat)` command.
+pub struct AssertionCheck {
+    /// The position of the statement.
+    pub pos: vir::Position,
+    /// The position of the conjunct that is checked.
+    pub reason_pos: vir::Position,
+}
+
+/// The SMT-LIB2 commands that check the assertions of a method, in order.
+pub struct MethodScript {
+    pub commands: Vec<String>,
+    pub checks: Vec<AssertionCheck>,
+}
+
+/// The values of the places at a point of an execution path.
+#[derive(Clone, Default)]
+struct Store {
+    /// The SMT-LIB2 constant that holds the value of each place that has been read or assigned.
+    values: HashMap<String, String>,
+    /// The latest havoc of each reference, after which the places below it have fresh values.
+    havocs: HashMap<String, u32>,
+}
+
+/// The state of an execution path.
+#[derive(Clone, Default)]
+struct PathState {
+    store: Store,
+    /// The store at each label, for the `old` expressions.
+    labels: HashMap<String, Store>,
+    /// The assertions that hold on this path.
+    facts: Vec<String>,
+}
+
+struct SymbolicExecutor<'a> {
+    /// The identifiers of the functions declared in the SMT-LIB2 script.
+    functions: &'a HashSet<String>,
+    /// The constants used by the paths, with their sort.
+    constants: RefCell<BTreeMap<String, &'static str>>,
+    fresh_index: Cell<u32>,
+    /// The checks, each with the facts of its path and the negated assertion.
+    checks: Vec<(Vec<String>, String, AssertionCheck)>,
+}
+
+/// Check the assertions of `method`, which may only call the functions in `functions`.
+pub fn check_method(
+    method: &vir::CfgMethod,
+    functions: &HashSet<String>,
+) -> Result<MethodScript, Unsupported> {
+    let mut executor = SymbolicExecutor {
+        functions,
+        constants: RefCell::new(BTreeMap::new()),
+        fresh_index: Cell::new(0),
+        checks: vec![],
+    };
+    if let Some(start) = method.start_block() {
+        executor.execute_block(method, start, PathState::default(), &mut 0, &mut vec![])?;
+    }
+
+    let mut commands: Vec<_> = executor
+        .constants
+        .into_inner()
+        .into_iter()
+        .map(|(name, sort)| format!("(declare-const {} {})", lowering::quote(&name), sort))
+        .collect();
+    let mut checks = vec![];
+    for (facts, negated_assertion, check) in executor.checks {
+        commands.push("(push 1)".to_string());
+        commands.extend(facts.into_iter().map(|fact| format!("(assert {})", fact)));
+        commands.push(format!("(assert {})", negated_assertion));
+        commands.push("(check-sat)".to_string());
+        commands.push("(pop 1)".to_string());
+        checks.push(check);
+    }
+    Ok(MethodScript { commands, checks })
+}
+
+/// The places that are a prefix of `place`, e.g. `_1` and `_1.val_ref` for `_1.val_ref.val_int`.
+fn place_prefixes(place: &str) -> Vec<&str> {
+    place
+        .match_indices('.')
+        .map(|(index, _)| &place[..index])
+        .chain(Some(place))
+        .collect()
+}
+
+impl<'a> SymbolicExecutor<'a> {
+    fn fresh_index(&self) -> u32 {
+        let index = self.fresh_index.get() + 1;
+        self.fresh_index.set(index);
+        index
+    }
+
+    fn declare(&self, name: String, typ: &vir::Type) -> Result<String, Unsupported> {
+        let sort = lowering::lower_type(typ)?;
+        let term = lowering::quote(&name);
+        self.constants.borrow_mut().insert(name, sort);
+        Ok(term)
+    }
+
+    /// Lower a place, read in `store`.
+    fn lower_place(&self, store: &Store, place: &vir::Expr) -> Result<String, Unsupported> {
+        let key = place.to_string();
+        if let Some(term) = store.values.get(&key) {
+            return Ok(term.clone());
+        }
+        // The place has not been assigned since the latest havoc of one of its prefixes, so its
+        // value is the same in all the stores with the same havocs.
+        let havoc = place_prefixes(&key)
+            .into_iter()
+            .filter_map(|prefix| store.havocs.get(prefix))
+            .max()
+            .cloned()
+            .unwrap_or(0);
+        self.declare(format!("{}@{}", key, havoc), place.get_type())
+    }
+
+    fn lower_expr(&self, state: &PathState, expr: &vir::Expr) -> Result<String, Unsupported> {
+        let places = |place: &vir::Expr, label: Option<&str>| match label {
+            None => self.lower_place(&state.store, place),
+            Some(label) => match state.labels.get(label) {
+                Some(store) => self.lower_place(store, place),
+                None => Err(format!("the label {} is not defined before {}", label, place)),
+            },
+        };
+        ExprLowering::with_places(self.functions, &places).lower_expr(expr)
+    }
+
+    fn havoc(&self, state: &mut PathState, var: &vir::LocalVar) -> Result<(), Unsupported> {
+        if var.typ.is_ref() {
+            let prefix = format!("{}.", var.name);
+            state.store.values.retain(|key, _| key != &var.name && !key.starts_with(&prefix));
+            let index = self.fresh_index();
+            state.store.havocs.insert(var.name.clone(), index);
+        } else {
+            let index = self.fresh_index();
+            let term = self.declare(format!("{}#{}", var.name, index), &var.typ)?;
+            state.store.values.insert(var.name.clone(), term);
+        }
+        Ok(())
+    }
+
+    /// Check each conjunct of `expr` on the path of `state`, then assume it.
+    fn check(
+        &mut self,
+        state: &mut PathState,
+        expr: &vir::Expr,
+        pos: &vir::Position,
+    ) -> Result<(), Unsupported> {
+        for conjunct in lowering::split_conjuncts(expr) {
+            let term = self.lower_expr(state, conjunct)?;
+            if term == "true" {
+                // An accessibility predicate, whose permission is not checked
+                continue;
+            }
+            let reason_pos = if conjunct.pos().is_default() {
+                pos.clone()
+            } else {
+                conjunct.pos().clone()
+            };
+            self.checks.push((
+                state.facts.clone(),
+                format!("(not {})", term),
+                AssertionCheck {
+                    pos: pos.clone(),
+                    reason_pos,
+                },
+            ));
+            state.facts.push(term);
+        }
+        Ok(())
+    }
+
+    /// Execute `stmt` on the path of `state`, returning the states of the paths that follow.
+    fn execute_stmt(
+        &mut self,
+        stmt: &vir::Stmt,
+        mut state: PathState,
+    ) -> Result<Vec<PathState>, Unsupported> {
+        match *stmt {
+            vir::Stmt::Comment(_) |
+            vir::Stmt::Fold(..) |
+            vir::Stmt::Unfold(..) |
+            vir::Stmt::Obtain(..) |
+            vir::Stmt::BeginFrame |
+            vir::Stmt::EndFrame |
+            vir::Stmt::TransferPerm(..) => {}
+
+            vir::Stmt::Label(ref label) => {
+                let store = state.store.clone();
+                state.labels.insert(label.clone(), store);
+            }
+
+            vir::Stmt::Inhale(ref expr, _) | vir::Stmt::Assume(ref expr, _) => {
+                let term = self.lower_expr(&state, expr)?;
+                state.facts.push(term);
+            }
+
+            vir::Stmt::Assert(ref expr, _, ref pos) | vir::Stmt::Exhale(ref expr, ref pos) => {
+                self.check(&mut state, expr, pos)?;
+            }
+
+            vir::Stmt::MethodCall(ref method_name, _, ref targets)
+                if method_name.starts_with("builtin$havoc") =>
+            {
+                for target in targets {
+                    self.havoc(&mut state, target)?;
+                }
+            }
+
+            vir::Stmt::Assign(ref target, ref value, _) if !target.get_type().is_ref() => {
+                let term = self.lower_expr(&state, value)?;
+                let index = self.fresh_index();
+                let key = target.to_string();
+                let constant = self.declare(format!("{}#{}", key, index), target.get_type())?;
+                state.facts.push(format!("(= {} {})", constant, term));
+                state.store.values.insert(key, constant);
+            }
+
+            vir::Stmt::If(ref guard, ref then_stmts) => {
+                let guard = self.lower_expr(&state, guard)?;
+                let mut then_state = state.clone();
+                then_state.facts.push(guard.clone());
+                state.facts.push(format!("(not {})", guard));
+                let mut states = self.execute_stmts(then_stmts, vec![then_state])?;
+                states.push(state);
+                return Ok(states);
+            }
+
+            vir::Stmt::Refute(..) => {
+                return Err("its refutations have not been lowered to assertions".to_string());
+            }
+            vir::Stmt::MethodCall(ref method_name, ..) => {
+                return Err(format!("it calls the method {}", method_name));
+            }
+            vir::Stmt::Assign(..) => {
+                return Err(format!("the assignment {} moves or borrows a reference", stmt));
+            }
+            vir::Stmt::PackageMagicWand(..) |
+            vir::Stmt::ApplyMagicWand(..) |
+            vir::Stmt::ExpireBorrows(..) => {
+                return Err(format!("the statement {} uses magic wands", stmt));
+            }
+        }
+        Ok(vec![state])
+    }
+
+    fn execute_stmts(
+        &mut self,
+        stmts: &[vir::Stmt],
+        mut states: Vec<PathState>,
+    ) -> Result<Vec<PathState>, Unsupported> {
+        for stmt in stmts {
+            let mut next_states = vec![];
+            for state in states {
+                next_states.extend(self.execute_stmt(stmt, state)?);
+            }
+            if next_states.len() > MAX_PATHS {
+                return Err(format!("it has more than {} execution paths", MAX_PATHS));
+            }
+            states = next_states;
+        }
+        Ok(states)
+    }
+
+    /// Execute the paths that start at the block `index`, given the blocks of the current path.
+    fn execute_block(
+        &mut self,
+        method: &vir::CfgMethod,
+        index: vir::CfgBlockIndex,
+        state: PathState,
+        num_paths: &mut usize,
+        path: &mut Vec<vir::CfgBlockIndex>,
+    ) -> Result<(), Unsupported> {
+        if path.contains(&index) {
+            return Err("it contains a loop".to_string());
+        }
+        if !method.get_block_invariants(index).is_empty() {
+            return Err("it has loop invariants".to_string());
+        }
+        path.push(index);
+        let states = self.execute_stmts(method.get_block_stmts(index), vec![state])?;
+        for state in states {
+            match *method.get_block_successor(index) {
+                vir::Successor::Return => {
+                    *num_paths += 1;
+                    if *num_paths > MAX_PATHS {
+                        return Err(format!("it has more than {} execution paths", MAX_PATHS));
+                    }
+                }
+                vir::Successor::Goto(target) => {
+                    self.execute_block(method, target, state, num_paths, path)?;
+                }
+                vir::Successor::GotoSwitch(ref guarded_targets, default_target) => {
+                    let mut other_guards = vec![];
+                    for (guard, target) in guarded_targets {
+                        let guard = self.lower_expr(&state, guard)?;
+                        let mut target_state = state.clone();
+                        target_state.facts.extend(other_guards.clone());
+                        target_state.facts.push(guard.clone());
+                        self.execute_block(method, *target, target_state, num_paths, path)?;
+                        other_guards.push(format!("(not {})", guard));
+                    }
+                    let mut default_state = state;
+                    default_state.facts.extend(other_guards);
+                    self.execute_block(method, default_target, default_state, num_paths, path)?;
+                }
+                vir::Successor::BackEdge(_) => return Err("it contains a loop".to_string()),
+                vir::Successor::Undefined => {
+                    return Err("its control flow graph is incomplete".to_string());
+                }
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assertion(expr: vir::Expr, id: u32) -> vir::Stmt {
+        let pos = vir::Position::new(1, 1, vir::PositionId::new(id));
+        vir::Stmt::Assert(expr, vir::FoldingBehaviour::None, pos)
+    }
+
+    #[test]
+    fn checks_each_assertion_on_each_path() {
+        let x = vir::LocalVar::new("x", vir::Type::Int);
+        let y = vir::LocalVar::new("y", vir::Type::Int);
+        let mut method = vir::CfgMethod::new("m".to_string(), 0, vec![], vec![], vec![]);
+        let assign = vir::Stmt::Assign(
+            y.clone().into(),
+            vir::Expr::div(x.clone().into(), 2.into()),
+            vir::AssignKind::Copy,
+        );
+        let guard = vir::Expr::gt_cmp(x.clone().into(), 0.into());
+        let start = method.add_block("start", vec![], vec![assign]);
+        let positive = method.add_block(
+            "positive",
+            vec![],
+            vec![assertion(vir::Expr::ge_cmp(y.clone().into(), 0.into()), 1)],
+        );
+        let other = method.add_block(
+            "other",
+            vec![],
+            vec![assertion(vir::Expr::le_cmp(y.clone().into(), 0.into()), 2)],
+        );
+        method.set_successor(start, vir::Successor::GotoSwitch(vec![(guard, positive)], other));
+        method.set_successor(positive, vir::Successor::Return);
+        method.set_successor(other, vir::Successor::Return);
+
+        let script = check_method(&method, &HashSet::new()).unwrap();
+        let check_ids: Vec<_> = script.checks.iter().map(|check| check.pos.id()).collect();
+        assert_eq!(check_ids, vec![vir::PositionId::new(1), vir::PositionId::new(2)]);
+        assert_eq!(
+            script.commands.iter().filter(|command| *command == "(check-sat)").count(),
+            2
+        );
+        assert!(script.commands.contains(&"(declare-const |x@0| Int)".to_string()));
+        assert!(script.commands.contains(&"(assert (not (<= |y#1| 0)))".to_string()));
+    }
+
+    #[test]
+    fn rejects_loops() {
+        let mut method = vir::CfgMethod::new("m".to_string(), 0, vec![], vec![], vec![]);
+        let start = method.add_block("start", vec![], vec![]);
+        let head = method.add_block("head", vec![], vec![]);
+        method.set_successor(start, vir::Successor::Goto(head));
+        method.set_successor(head, vir::Successor::BackEdge(head));
+        assert!(check_method(&method, &HashSet::new()).is_err());
+    }
+}
@@ -0,0 +1,484 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use encoder::vir::{self, ExprWalker, WithIdentifier};
+use encoder::Encoder;
+use prusti_interface::config;
+use prusti_interface::data::VerificationResult;
+use prusti_interface::data::VerificationTask;
+use prusti_interface::environment::Environment;
+use prusti_interface::report::{json, log};
+use prusti_interface::specifications::TypedSpecificationMap;
+use smt::lowering::{self, ExprLowering, Unsupported};
+use smt::solver::{self, CheckSatResult};
+use smt::symbolic_execution;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use viper::VerificationError;
+
+/// An assertion checked by a `(check-sat)` command.
+struct Check {
+    /// The name of the function or method that contains the assertion.
+    item_name: String,
+    /// The identifier of the verification error reported if the assertion might not hold.
+    error_id: &'static str,
+    pos: vir::Position,
+    reason_pos: vir::Position,
+}
+
+/// An SMT-LIB2 script that checks the postconditions of pure functions and the assertions of
+/// methods.
+struct SmtScript {
+    commands: Vec<String>,
+    checks: Vec<Check>,
+    /// The items that could not be expressed, with the reason. They are not verified.
+    unsupported: Vec<(String, Unsupported)>,
+    /// The positions of the assertions of the methods that are not verified.
+    unchecked_assertions: Vec<vir::Position>,
+}
+
+/// Collect the positions of the assertions in `stmts`.
+fn collect_assertion_positions(stmts: &[vir::Stmt], positions: &mut Vec<vir::Position>) {
+    for stmt in stmts {
+        match *stmt {
+            vir::Stmt::Assert(_, _, ref pos) => positions.push(pos.clone()),
+            vir::Stmt::If(_, ref then_stmts) => collect_assertion_positions(then_stmts, positions),
+            _ => {}
+        }
+    }
+}
+
+/// The identifiers of the functions applied in the contract or in the body of `function`.
+fn applied_functions(function: &vir::Function) -> HashSet<String> {
+    struct FuncAppCollector {
+        identifiers: HashSet<String>,
+    }
+    impl ExprWalker for FuncAppCollector {
+        fn walk_func_app(
+            &mut self,
+            name: &str,
+            args: &Vec<vir::Expr>,
+            formal_args: &Vec<vir::LocalVar>,
+            return_type: &vir::Type,
+            _pos: &vir::Position,
+        ) {
+            for arg in args {
+                self.walk(arg);
+            }
+            self.identifiers.insert(vir::compute_identifier(name, formal_args, return_type));
+        }
+    }
+    let mut collector = FuncAppCollector {
+        identifiers: HashSet::new(),
+    };
+    for expr in function.pres.iter().chain(&function.posts).chain(&function.body) {
+        collector.walk(expr);
+    }
+    collector.identifiers
+}
+
+/// The identifiers of the functions that each function depends on, directly or transitively,
+/// through the applications in contracts and bodies. A function depends on itself if it is
+/// recursive, also through the postcondition of its limited version.
+fn function_dependencies(functions: &[&vir::Function]) -> HashMap<String, HashSet<String>> {
+    let applications: HashMap<_, _> = functions
+        .iter()
+        .map(|function| (function.get_identifier(), applied_functions(function)))
+        .collect();
+    applications
+        .keys()
+        .map(|identifier| {
+            let mut dependencies = HashSet::new();
+            let mut to_visit: Vec<_> = applications[identifier].iter().collect();
+            while let Some(dependency) = to_visit.pop() {
+                if dependencies.insert(dependency.clone()) {
+                    if let Some(applied) = applications.get(dependency) {
+                        to_visit.extend(applied);
+                    }
+                }
+            }
+            (identifier.clone(), dependencies)
+        })
+        .collect()
+}
+
+impl SmtScript {
+    fn new(functions: &[vir::Function], methods: &[vir::CfgMethod]) -> Self {
+        let mut script = SmtScript {
+            commands: vec![],
+            checks: vec![],
+            unsupported: vec![],
+            unchecked_assertions: vec![],
+        };
+
+        // Declare the functions whose signature can be expressed.
+        let mut declared = HashSet::new();
+        for function in functions {
+            match lowering::lower_function_declaration(function) {
+                Ok(declaration) => {
+                    declared.insert(function.get_identifier());
+                    script.commands.push(declaration);
+                }
+                Err(reason) => script.unsupported.push((function.name.clone(), reason)),
+            }
+        }
+        let expr_lowering = ExprLowering::new(&declared);
+        let declared_functions: Vec<_> = functions
+            .iter()
+            .filter(|function| declared.contains(&function.get_identifier()))
+            .collect();
+
+        // A definitional axiom of a recursive function might be inconsistent, because there are
+        // no limited functions to restrict its unrolling, and its contract could be proven by
+        // assuming itself. Recursive functions are therefore only declared.
+        let dependencies = function_dependencies(&declared_functions);
+        let declared_functions: Vec<_> = declared_functions
+            .into_iter()
+            .filter(|function| {
+                let identifier = function.get_identifier();
+                if dependencies[&identifier].contains(&identifier) {
+                    script.unsupported.push((
+                        function.name.clone(),
+                        "it is recursive, which needs limited functions".to_string(),
+                    ));
+                    false
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        // The postcondition axioms, which are only assumed when checking the functions that depend
+        // on them.
+        let mut post_axioms = vec![];
+        for &function in &declared_functions {
+            let axiom = expr_lowering.lower_conjunction(&function.pres).and_then(|pre| {
+                let post = expr_lowering.lower_conjunction(&function.posts)?;
+                Ok(format!(
+                    "(assert {})",
+                    lowering::quantify_over_arguments(
+                        function,
+                        format!(
+                            "(=> {} (let ((|__result| {})) {}))",
+                            pre,
+                            lowering::lower_function_self_application(function),
+                            post
+                        )
+                    )
+                ))
+            });
+            match axiom {
+                Ok(axiom) => post_axioms.push((function.get_identifier(), axiom)),
+                Err(reason) => script.unsupported.push((
+                    function.name.clone(),
+                    format!("its contract cannot be assumed: {}", reason),
+                )),
+            }
+        }
+
+        // The definitional axioms of the function bodies.
+        for &function in &declared_functions {
+            if let Some(ref body) = function.body {
+                let axiom = expr_lowering.lower_conjunction(&function.pres).and_then(|pre| {
+                    Ok(format!(
+                        "(assert {})",
+                        lowering::quantify_over_arguments(
+                            function,
+                            format!(
+                                "(=> {} (= {} {}))",
+                                pre,
+                                lowering::lower_function_self_application(function),
+                                expr_lowering.lower_expr(body)?
+                            )
+                        )
+                    ))
+                });
+                match axiom {
+                    Ok(axiom) => script.commands.push(axiom),
+                    Err(reason) => script.unsupported.push((
+                        function.name.clone(),
+                        format!("its body cannot be expressed: {}", reason),
+                    )),
+                }
+            }
+        }
+
+        // Check each conjunct of the postconditions against the body.
+        for &function in &declared_functions {
+            let body = match function.body {
+                Some(ref body) => body,
+                None => continue,
+            };
+            let (pre, body) = match expr_lowering
+                .lower_conjunction(&function.pres)
+                .and_then(|pre| Ok((pre, expr_lowering.lower_expr(body)?)))
+            {
+                Ok(lowered) => lowered,
+                // Already reported as unsupported
+                Err(_) => continue,
+            };
+            let identifier = function.get_identifier();
+            for post in &function.posts {
+                for conjunct in lowering::split_conjuncts(post) {
+                    let conjunct_term = match expr_lowering.lower_expr(conjunct) {
+                        Ok(term) => term,
+                        Err(reason) => {
+                            script.unsupported.push((
+                                function.name.clone(),
+                                format!(
+                                    "its postcondition {} cannot be checked: {}",
+                                    conjunct, reason
+                                ),
+                            ));
+                            continue;
+                        }
+                    };
+                    script.commands.push("(push 1)".to_string());
+                    for (other_identifier, axiom) in &post_axioms {
+                        if dependencies[&identifier].contains(other_identifier) {
+                            script.commands.push(axiom.clone());
+                        }
+                    }
+                    for arg in &function.formal_args {
+                        // The signature has already been checked by `lower_function_declaration`.
+                        script.commands.push(format!(
+                            "(declare-const {} {})",
+                            lowering::quote(&arg.name),
+                            lowering::lower_type(&arg.typ).unwrap()
+                        ));
+                    }
+                    script.commands.push(format!("(assert {})", pre));
+                    script.commands.push(format!(
+                        "(assert (not (let ((|__result| {})) {})))",
+                        body, conjunct_term
+                    ));
+                    script.commands.push("(check-sat)".to_string());
+                    script.commands.push("(pop 1)".to_string());
+                    script.checks.push(Check {
+                        item_name: function.name.clone(),
+                        error_id: "postcondition.violated:assertion.false",
+                        pos: conjunct.pos().clone(),
+                        reason_pos: conjunct.pos().clone(),
+                    });
+                }
+            }
+        }
+
+        // Check the assertions of each method, assuming the postconditions of all the functions
+        // that are not recursive.
+        for method in methods {
+            match symbolic_execution::check_method(method, &declared) {
+                Ok(method_script) => {
+                    script.commands.push("(push 1)".to_string());
+                    for (_, axiom) in &post_axioms {
+                        script.commands.push(axiom.clone());
+                    }
+                    script.commands.extend(method_script.commands);
+                    script.commands.push("(pop 1)".to_string());
+                    script.checks.extend(method_script.checks.into_iter().map(|check| Check {
+                        item_name: method.name(),
+                        error_id: "assert.failed:assertion.false",
+                        pos: check.pos,
+                        reason_pos: check.reason_pos,
+                    }));
+                }
+                Err(reason) => {
+                    method.walk_statements(|stmt| {
+                        collect_assertion_positions(
+                            ::std::slice::from_ref(stmt),
+                            &mut script.unchecked_assertions,
+                        )
+                    });
+                    script.unsupported.push((method.name(), reason));
+                }
+            }
+        }
+
+        script
+    }
+
+    fn to_smtlib(&self) -> String {
+        let mut text = self.commands.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+/// A verifier that checks the pure functions of a crate with an SMT solver,
+/// without starting a JVM.
+pub struct SmtVerifier<'v, 'r, 'a, 'tcx>
+where
+    'r: 'v,
+    'a: 'r,
+    'tcx: 'a,
+{
+    env: &'v Environment<'r, 'a, 'tcx>,
+    encoder: Encoder<'v, 'r, 'a, 'tcx>,
+}
+
+impl<'v, 'r, 'a, 'tcx> SmtVerifier<'v, 'r, 'a, 'tcx> {
+    pub fn new(env: &'v Environment<'r, 'a, 'tcx>, spec: &'v TypedSpecificationMap) -> Self {
+        SmtVerifier {
+            env,
+            encoder: Encoder::new(env, spec),
+        }
+    }
+
+    /// Verify the items of `task`. The items encoded by a previous verification are reused, so
+    /// `invalidate_all` has to be called first if the crate changed in the meantime.
+    pub fn verify(&mut self, task: &VerificationTask) -> VerificationResult {
+        let start = Instant::now();
+
+        for &proc_id in task.procedures.iter().rev() {
+            self.encoder.queue_procedure_encoding(proc_id);
+        }
+        self.encoder.process_encoding_queue();
+
+        let functions = self.encoder.get_used_viper_functions();
+        let methods = self.encoder.get_used_viper_methods();
+        let script = SmtScript::new(&functions, &methods);
+        let smtlib = script.to_smtlib();
+
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        if config::dump_debug_info() {
+            log::report("smt", format!("{}.smt2", source_filename), &smtlib);
+        }
+
+        // The items that are not verified are reported, at the procedure that they encode.
+        let item_spans: HashMap<_, _> = task
+            .procedures
+            .iter()
+            .map(|&proc_id| {
+                (
+                    self.encoder.encode_item_name(proc_id),
+                    self.env.get_item_span(proc_id),
+                )
+            })
+            .collect();
+        for (name, reason) in &script.unsupported {
+            let message = format!(
+                "[Prusti] {} is not verified in SMT mode, because {}",
+                name, reason
+            );
+            match item_spans.get(name) {
+                Some(&span) => self.env.span_warn(span, &message),
+                None => self.env.warn(&message),
+            }
+        }
+
+        let duration = start.elapsed();
+        info!(
+            "Encoding to SMT-LIB2 successful ({}.{} seconds)",
+            duration.as_secs(),
+            duration.subsec_millis() / 10
+        );
+        json::timing("encoding", duration);
+        let start = Instant::now();
+
+        let results = match solver::run_solver(&config::smt_solver_path(), &smtlib) {
+            Ok(ref results) if results.len() == script.checks.len() => results.clone(),
+            Ok(results) => {
+                self.env.err(&format!(
+                    "[Prusti] the SMT solver answered {} of the {} checks",
+                    results.len(),
+                    script.checks.len()
+                ));
+                return VerificationResult::Failure;
+            }
+            Err(message) => {
+                self.env.err(&format!("[Prusti] {}", message));
+                return VerificationResult::Failure;
+            }
+        };
+
+        let duration = start.elapsed();
+        info!(
+            "Verification complete ({}.{} seconds)",
+            duration.as_secs(),
+            duration.subsec_millis() / 10
+        );
+        json::timing("verification", duration);
+
+        let verification_errors: Vec<_> = script
+            .checks
+            .iter()
+            .zip(results)
+            .filter(|(_, result)| *result != CheckSatResult::Unsat)
+            .map(|(check, result)| {
+                VerificationError::new(
+                    check.error_id.to_string(),
+                    Some(check.pos.id().to_string()),
+                    Some(check.reason_pos.id().to_string()),
+                    format!(
+                        "An assertion of {} might not hold (the SMT solver answered {:?}).",
+                        check.item_name, result
+                    ),
+                )
+            })
+            .collect();
+
+        let error_manager = self.encoder.error_manager();
+
+        // The assertions of `prusti_refute!` statements and of reachability checks are
+        // expected to fail. Those of the methods that are not verified are not reported.
+        let (mut expected_failures, verification_errors): (Vec<_>, Vec<_>) = verification_errors
+            .into_iter()
+            .partition(|verification_error| error_manager.is_expected_failure(verification_error));
+        expected_failures.extend(script.unchecked_assertions.iter().map(|pos| {
+            VerificationError::new(
+                "assert.failed:assertion.false".to_string(),
+                Some(pos.id().to_string()),
+                None,
+                "The assertion has not been checked.".to_string(),
+            )
+        }));
+
+        if config::check_unreachability() {
+            for warning in error_manager.unreachability_warnings(&expected_failures) {
+                self.env.span_warn(warning.span, &format!("[Prusti] {}", warning.message));
+            }
+        }
+
+        let mut compilation_errors: Vec<_> = verification_errors
+            .iter()
+            .map(|verification_error| {
+                debug!("Verification error: {:?}", verification_error);
+                error_manager.translate(verification_error)
+            })
+            .collect();
+        compilation_errors.extend(error_manager.unrefuted_errors(&expected_failures));
+
+        for compilation_error in &compilation_errors {
+            compilation_error.report_json(self.env.codemap());
+            self.env.span_err_with_help_and_note(
+                compilation_error.span.clone(),
+                &format!("[Prusti] {}", compilation_error.message),
+                &compilation_error.help,
+                &compilation_error.note,
+                &compilation_error.suggestion,
+            );
+        }
+
+        if !script.unsupported.is_empty() {
+            self.env.err(&format!(
+                "[Prusti] {} item(s) could not be verified in SMT mode",
+                script.unsupported.len()
+            ));
+        }
+
+        if compilation_errors.is_empty() && script.unsupported.is_empty() {
+            VerificationResult::Success
+        } else {
+            VerificationResult::Failure
+        }
+    }
+
+    /// Forget everything that has been encoded, e.g. because the crate changed.
+    pub fn invalidate_all(&mut self) {
+        self.encoder.reset();
+    }
+}
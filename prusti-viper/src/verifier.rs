@@ -4,20 +4,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use encoder::vir::{self, optimisations, ToViper, ToViperDecl};
-use encoder::Encoder;
+use encoder::vir::{self, optimisations, ToViper, ToViperDecl, ToViperText, WithIdentifier};
+use encoder::{Encoder, VerificationPhase};
 use prusti_filter::validators::Validator;
 use prusti_interface::config;
 use prusti_interface::data::VerificationResult;
 use prusti_interface::data::VerificationTask;
 use prusti_interface::environment::Environment;
+use prusti_interface::report::json;
 use prusti_interface::report::log;
+use prusti_interface::report::user;
 use prusti_interface::specifications::TypedSpecificationMap;
+use resource_limits::ResourceLimits;
 use std::time::Instant;
 use viper::{self, VerificationBackend, Viper};
-use std::path::PathBuf;
-use std::fs::{create_dir_all, canonicalize};
+use std::path::{Path, PathBuf};
+use std::fs::{self, create_dir_all, canonicalize};
 use std::ffi::OsString;
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 /// A verifier builder is an object that lives entire program's
 /// lifetime, has no mutable state, and is responsible for constructing
@@ -26,22 +33,22 @@ use std::ffi::OsString;
 /// The main motivation for having a builder is to be able to cache the JVM
 /// initialization.
 pub struct VerifierBuilder {
-    viper: Viper,
+    viper: Arc<Viper>,
 }
 
 impl VerifierBuilder {
     pub fn new() -> Self {
         VerifierBuilder {
-            viper: Viper::new_with_args(
+            viper: Arc::new(Viper::new_with_args(
                 config::extra_jvm_args(),
                 VerificationBackend::from_str(&config::viper_backend())
-            ),
+            )),
         }
     }
 
     pub fn new_verification_context(&self) -> VerificationContext {
         let verification_ctx = self.viper.new_verification_context();
-        VerificationContext::new(verification_ctx)
+        VerificationContext::new(verification_ctx, self.viper.clone())
     }
 }
 
@@ -57,6 +64,8 @@ impl Default for VerifierBuilder {
 /// thread from the JVM when the verification context goes out of scope.
 pub struct VerificationContext<'v> {
     verification_ctx: viper::VerificationContext<'v>,
+    /// Used to attach additional threads to the JVM, for parallel verification.
+    viper: Arc<Viper>,
 }
 
 impl<'v, 'r, 'a, 'tcx> VerificationContext<'v>
@@ -65,8 +74,8 @@ impl<'v, 'r, 'a, 'tcx> VerificationContext<'v>
         'a: 'r,
         'tcx: 'a,
 {
-    fn new(verification_ctx: viper::VerificationContext<'v>) -> Self {
-        VerificationContext { verification_ctx }
+    fn new(verification_ctx: viper::VerificationContext<'v>, viper: Arc<Viper>) -> Self {
+        VerificationContext { verification_ctx, viper }
     }
 
     pub fn new_verifier(
@@ -75,53 +84,236 @@ impl<'v, 'r, 'a, 'tcx> VerificationContext<'v>
         spec: &'v TypedSpecificationMap,
     ) -> Verifier<'v, 'r, 'a, 'tcx> {
         let backend = VerificationBackend::from_str(&config::viper_backend());
-
-        let mut verifier_args: Vec<String> = vec![];
         let log_path: PathBuf = PathBuf::from(config::log_dir()).join("viper_tmp");
         create_dir_all(&log_path).unwrap();
         let report_path: PathBuf = log_path.join("report.csv");
-        let log_dir_str = log_path.to_str().unwrap();
+        let verifier_args = build_verifier_args(backend, &log_path, &ResourceLimits::from_config());
+        Verifier::new(
+            self.verification_ctx.new_ast_utils(),
+            self.verification_ctx.new_ast_factory(),
+            self.verification_ctx
+                .new_verifier_with_args(backend, verifier_args.clone(), Some(report_path)),
+            env,
+            spec,
+            ParallelVerifierConfig {
+                viper: self.viper.clone(),
+                backend,
+                verifier_args,
+                log_path,
+            },
+        )
+    }
+}
+
+/// The arguments of a verifier that uses `log_path` for its temporary files and whose resources
+/// are limited by `limits`.
+fn build_verifier_args(
+    backend: VerificationBackend,
+    log_path: &Path,
+    limits: &ResourceLimits,
+) -> Vec<String> {
+    let mut verifier_args: Vec<String> = vec![];
+    let log_dir_str = log_path.to_str().unwrap();
+    if let VerificationBackend::Silicon = backend {
+        if config::use_more_complete_exhale() {
+            verifier_args.push("--enableMoreCompleteExhale".to_string()); // Buggy :(
+        }
+        verifier_args.extend(limits.silicon_args());
+        verifier_args.extend(vec![
+            "--tempDirectory".to_string(),
+            log_dir_str.to_string(),
+            //"--logLevel".to_string(), "WARN".to_string(),
+        ]);
+    } else {
+        let mut boogie_options = vec![format!("/logPrefix {}", log_dir_str)];
+        boogie_options.extend(limits.boogie_options());
+        verifier_args.extend(vec![
+            "--disableAllocEncoding".to_string(),
+            "--boogieOpt".to_string(),
+            boogie_options.join(" "),
+        ]);
+    }
+    if config::dump_debug_info() {
         if let VerificationBackend::Silicon = backend {
-            if config::use_more_complete_exhale() {
-                verifier_args.push("--enableMoreCompleteExhale".to_string()); // Buggy :(
-            }
             verifier_args.extend(vec![
-                "--assertTimeout".to_string(),
-                config::assert_timeout().to_string(),
-                "--tempDirectory".to_string(),
-                log_dir_str.to_string(),
-                //"--logLevel".to_string(), "WARN".to_string(),
+                "--printMethodCFGs".to_string(),
+                "--logLevel".to_string(),
+                "INFO".to_string(),
+                //"--printTranslatedProgram".to_string(),
             ]);
         } else {
-            verifier_args.extend(vec![
-                "--disableAllocEncoding".to_string(),
-                "--boogieOpt".to_string(),
-                format!("/logPrefix {}", log_dir_str),
+            verifier_args.extend::<Vec<_>>(vec![
+                //"--print".to_string(), "./log/boogie_program/program.bpl".to_string(),
             ]);
         }
-        if config::dump_debug_info() {
-            if let VerificationBackend::Silicon = backend {
-                verifier_args.extend(vec![
-                    "--printMethodCFGs".to_string(),
-                    "--logLevel".to_string(),
-                    "INFO".to_string(),
-                    //"--printTranslatedProgram".to_string(),
-                ]);
-            } else {
-                verifier_args.extend::<Vec<_>>(vec![
-                    //"--print".to_string(), "./log/boogie_program/program.bpl".to_string(),
-                ]);
+    }
+    verifier_args.extend(config::extra_verifier_args());
+    verifier_args
+}
+
+/// Is `method_name` the name of the method `original_name`, or of one of its obligations?
+fn is_method_or_obligation(method_name: &str, original_name: &str) -> bool {
+    method_name == original_name
+        || method_name.starts_with(&format!("{}$obligation$", original_name))
+}
+
+/// What is needed to start additional verifiers on other threads.
+struct ParallelVerifierConfig {
+    viper: Arc<Viper>,
+    backend: VerificationBackend,
+    verifier_args: Vec<String>,
+    log_path: PathBuf,
+}
+
+/// The encoded items that are shared by all the methods of a program.
+struct ProgramPreamble {
+    domains: Vec<vir::Domain>,
+    fields: Vec<vir::Field>,
+    functions: Vec<vir::Function>,
+    predicates: Vec<vir::Predicate>,
+    builtin_methods: Vec<vir::BodylessMethod>,
+    /// Whether the program needs the Viper map domain.
+    uses_maps: bool,
+}
+
+impl ProgramPreamble {
+    /// Build a Viper program that contains the preamble and the given methods.
+    fn to_viper_program<'v>(
+        &self,
+        ast: &viper::AstFactory<'v>,
+        methods: &[vir::CfgMethod],
+    ) -> viper::Program<'v> {
+        let fields = self.fields.to_viper(ast);
+        let mut viper_functions: Vec<_> = self.functions.iter().map(|f| f.to_viper(ast)).collect();
+        let mut viper_methods: Vec<_> = methods.iter().map(|m| m.to_viper(ast)).collect();
+        viper_methods.extend(self.builtin_methods.iter().map(|m| m.to_viper(ast)));
+        let mut predicates = self.predicates.to_viper(ast);
+        let mut domains: Vec<_> = self.domains.iter().map(|d| d.to_viper(ast)).collect();
+        if self.uses_maps {
+            domains.push(vir::map_domain(ast));
+        }
+
+        debug!(
+            "Viper program uses {} domains, {} fields, {} functions, {} predicates, {} methods",
+            domains.len(), fields.len(), viper_functions.len(), predicates.len(),
+            viper_methods.len()
+        );
+
+        // Add a function that represents the symbolic read permission amount.
+        viper_functions.push(ast.function(
+            "read$",
+            &[],
+            ast.perm_type(),
+            &[],
+            &[
+                ast.lt_cmp(ast.no_perm(), ast.result(ast.perm_type())),
+                ast.lt_cmp(ast.result(ast.perm_type()), ast.full_perm()),
+            ],
+            ast.no_position(),
+            None,
+        ));
+
+        // Add a predicate that represents the dead loan token.
+        predicates.push(
+            ast.predicate(
+                "DeadBorrowToken$",
+                &[vir::LocalVar {
+                    name: "borrow".to_string(),
+                    typ: vir::Type::Int,
+                }
+                    .to_viper_decl(ast)],
+                None,
+            ),
+        );
+
+        ast.program(&domains, &fields, &viper_functions, &predicates, &viper_methods)
+    }
+
+    /// Write the source text of the program built by `to_viper_program`.
+    fn to_viper_text(&self, methods: &[vir::CfgMethod]) -> vir::ViperText {
+        let mut text = vir::ViperText::new();
+        if self.uses_maps {
+            for line in vir::map_domain_text().lines() {
+                text.write(line);
+                text.newline();
             }
+            text.newline();
         }
-        verifier_args.extend(config::extra_verifier_args());
-        Verifier::new(
-            self.verification_ctx.new_ast_utils(),
-            self.verification_ctx.new_ast_factory(),
-            self.verification_ctx
-                .new_verifier_with_args(backend, verifier_args, Some(report_path)),
-            env,
-            spec,
-        )
+        for domain in &self.domains {
+            domain.to_viper_text(&mut text);
+            text.newline();
+            text.newline();
+        }
+        for field in &self.fields {
+            field.to_viper_text(&mut text);
+            text.newline();
+        }
+        text.newline();
+        for function in &self.functions {
+            function.to_viper_text(&mut text);
+            text.newline();
+            text.newline();
+        }
+        text.write("function read$(): Perm");
+        text.indent();
+        text.newline();
+        text.write("ensures none < result");
+        text.newline();
+        text.write("ensures result < write");
+        text.dedent();
+        text.newline();
+        text.newline();
+        for predicate in &self.predicates {
+            predicate.to_viper_text(&mut text);
+            text.newline();
+            text.newline();
+        }
+        text.write("predicate DeadBorrowToken$(borrow: Int)");
+        text.newline();
+        for method in methods {
+            text.newline();
+            method.to_viper_text(&mut text);
+            text.newline();
+        }
+        for method in &self.builtin_methods {
+            text.newline();
+            method.to_viper_text(&mut text);
+            text.newline();
+        }
+        text
+    }
+
+    /// Build the Viper program that contains the preamble and the given methods. With
+    /// `EMIT_VIPER_TEXT`, the backend parses the source text of the program, which is returned
+    /// to translate the positions of the verification errors. Parsing fails only if the text
+    /// emission is wrong.
+    fn build_viper_program<'v>(
+        &self,
+        ast: &viper::AstFactory<'v>,
+        ast_utils: &viper::AstUtils<'v>,
+        methods: &[vir::CfgMethod],
+    ) -> Result<(viper::Program<'v>, Option<vir::ViperText>), viper::ParseError> {
+        if config::emit_viper_text() {
+            let text = self.to_viper_text(methods);
+            let program = ast_utils.parse_program(text.source())?;
+            Ok((program, Some(text)))
+        } else {
+            Ok((self.to_viper_program(ast, methods), None))
+        }
+    }
+}
+
+/// Translate the positions of the errors reported on a program parsed from `viper_text`.
+fn translate_error_positions(
+    errors: Vec<viper::VerificationError>,
+    viper_text: &Option<vir::ViperText>,
+) -> Vec<viper::VerificationError> {
+    match viper_text {
+        Some(text) => errors
+            .into_iter()
+            .map(|error| text.translate_error(error))
+            .collect(),
+        None => errors,
     }
 }
 
@@ -138,6 +330,7 @@ where
     verifier: viper::Verifier<'v, viper::state::Started>,
     env: &'v Environment<'r, 'a, 'tcx>,
     encoder: Encoder<'v, 'r, 'a, 'tcx>,
+    parallel_config: ParallelVerifierConfig,
 }
 
 impl<'v, 'r, 'a, 'tcx> Verifier<'v, 'r, 'a, 'tcx> {
@@ -147,6 +340,7 @@ impl<'v, 'r, 'a, 'tcx> Verifier<'v, 'r, 'a, 'tcx> {
         verifier: viper::Verifier<'v, viper::state::Started>,
         env: &'v Environment<'r, 'a, 'tcx>,
         spec: &'v TypedSpecificationMap,
+        parallel_config: ParallelVerifierConfig,
     ) -> Self {
         Verifier {
             ast_utils,
@@ -154,9 +348,12 @@ impl<'v, 'r, 'a, 'tcx> Verifier<'v, 'r, 'a, 'tcx> {
             verifier,
             env,
             encoder: Encoder::new(env, spec),
+            parallel_config,
         }
     }
 
+    /// Verify the items of `task`. The items encoded by a previous verification are reused, so
+    /// `invalidate_all` has to be called first if the crate changed in the meantime.
     pub fn verify(&mut self, task: &VerificationTask) -> VerificationResult {
         let start = Instant::now();
 
@@ -178,7 +375,7 @@ impl<'v, 'r, 'a, 'tcx> Verifier<'v, 'r, 'a, 'tcx> {
         if config::report_support_status() {
             for &proc_id in &task.procedures {
                 // Do some checks
-                let is_pure_function = self.env.has_attribute_name(proc_id, "pure");
+                let is_pure_function = self.encoder.is_pure(proc_id);
 
                 let support_status = if is_pure_function {
                     validator.pure_function_support_status(proc_id)
@@ -195,162 +392,665 @@ impl<'v, 'r, 'a, 'tcx> Verifier<'v, 'r, 'a, 'tcx> {
         }
         self.encoder.process_encoding_queue();
 
+        if config::check_repeated_encoding() && !self.check_repeated_encoding(task) {
+            return VerificationResult::Failure;
+        }
+
+        // The methods whose resources are limited by their own attributes.
+        let default_limits = ResourceLimits::from_config();
+        let mut method_limits: HashMap<String, ResourceLimits> = task
+            .procedures
+            .iter()
+            .map(|&proc_id| {
+                let limits = ResourceLimits::for_procedure(self.env, proc_id);
+                (self.encoder.encode_item_name(proc_id), limits)
+            })
+            .filter(|(_, limits)| *limits != default_limits)
+            .collect();
+
+        if config::report_trusted_surface() {
+            self.report_trusted_surface();
+        }
+
+        if config::infer_purity() {
+            self.report_inferred_purity();
+        }
+
         let duration = start.elapsed();
         info!(
             "Encoding to Viper successful ({}.{} seconds)",
             duration.as_secs(),
             duration.subsec_millis() / 10
         );
+        json::timing("encoding", duration);
         let start = Instant::now();
 
-        let program = {
-            let ast = &self.ast_factory;
-
-            let domains = self.encoder.get_used_viper_domains();
-            let fields = self.encoder.get_used_viper_fields().to_viper(ast);
-            let builtin_methods = self.encoder.get_used_builtin_methods();
-            let mut methods = self.encoder.get_used_viper_methods();
-            let mut functions = self.encoder.get_used_viper_functions();
-            if config::simplify_encoding() {
-                let (new_methods, new_functions) = optimisations::functions::inline_constant_functions(
-                    methods, functions);
-                methods = new_methods
-                    .into_iter()
-                    .map(|m| {
-                        let purified = optimisations::methods::purify_vars(m);
-                        optimisations::folding::FoldingOptimiser::optimise(purified)
-                    })
-                    .collect();
-                functions = new_functions
-                    .into_iter()
-                    .map(|f| {
-                        optimisations::folding::FoldingOptimiser::optimise(f)
-                    })
-                    .collect();
-            }
-            let mut viper_functions: Vec<_> = functions.into_iter().map(|f| f.to_viper(ast)).collect();
-            let mut viper_methods: Vec<_> = methods.into_iter().map(|m| m.to_viper(ast)).collect();
-            viper_methods.extend(builtin_methods.into_iter().map(|m| m.to_viper(ast)));
-            let mut predicates = self.encoder.get_used_viper_predicates().to_viper(ast);
-            if config::verify_only_preamble() {
-                viper_methods = Vec::new();
+        let domains = self.encoder.get_used_viper_domains();
+        let fields = self.encoder.get_used_viper_fields();
+        let builtin_methods = self.encoder.get_used_builtin_methods();
+        let mut methods = self.encoder.get_used_viper_methods();
+        let mut functions = self.encoder.get_used_viper_functions();
+        if config::simplify_encoding() {
+            let (new_methods, new_functions) = optimisations::functions::inline_constant_functions(
+                methods, functions);
+            methods = new_methods
+                .into_iter()
+                .map(|m| {
+                    let purified = optimisations::methods::purify_vars(m);
+                    optimisations::folding::FoldingOptimiser::optimise(purified)
+                })
+                .collect();
+            functions = new_functions
+                .into_iter()
+                .map(|f| {
+                    optimisations::folding::FoldingOptimiser::optimise(f)
+                })
+                .collect();
+        }
+        if config::verify_only_preamble() {
+            methods = Vec::new();
+        }
+        if config::split_obligations() {
+            methods = methods
+                .into_iter()
+                .flat_map(|method| self.split_obligations(method))
+                .collect();
+        }
+        let mut predicates = self.encoder.get_used_viper_predicates();
+        if config::lazy_predicate_bodies() {
+            if config::audit_lazy_predicate_bodies() {
+                self.audit_lazy_predicate_bodies(&predicates, &methods, &functions);
+            } else {
+                let (new_predicates, abstracted) = optimisations::predicates::abstract_unused_bodies(
+                    predicates, &methods, &functions);
+                info!("Emitting {} predicates without body", abstracted.len());
+                predicates = new_predicates;
             }
+        }
+
+        info!(
+            "Viper encoding uses {} domains, {} fields, {} functions, {} predicates, {} methods",
+            domains.len(), fields.len(), functions.len(), predicates.len(),
+            methods.len() + builtin_methods.len()
+        );
+
+        let preamble = ProgramPreamble {
+            domains,
+            fields,
+            functions,
+            predicates,
+            builtin_methods,
+            uses_maps: self.encoder.uses_viper_maps(),
+        };
+
+        if config::check_vir_types() && !self.check_vir_types(&preamble, &methods) {
+            return VerificationResult::Failure;
+        }
+
+        if let Some(dump_dir) = config::dump_viper_dir() {
+            self.dump_viper_programs_per_item(&PathBuf::from(dump_dir), &preamble, &methods);
+        }
+
+        // Pure functions are not verified separately, so their limits are ignored. The limits
+        // of a method apply to each of its obligations.
+        method_limits = methods
+            .iter()
+            .flat_map(|method| {
+                let method_name = method.name();
+                method_limits
+                    .iter()
+                    .find(|(limited_name, _)| is_method_or_obligation(&method_name, limited_name))
+                    .map(|(_, &limits)| (method_name, limits))
+            })
+            .collect();
+
+        let num_threads = cmp::min(config::num_threads() as usize, methods.len());
+        // Measuring the verification time of each method, or verifying it with its own
+        // resource limits, requires a Viper program per method.
+        let verify_per_method = num_threads > 1
+            || config::report_verification_times()
+            || config::split_obligations()
+            || !method_limits.is_empty();
 
+        let verification_errors = if verify_per_method {
+            if config::dump_viper_program() {
+                let program = preamble.to_viper_program(&self.ast_factory, &methods);
+                self.dump_viper_program(program);
+            }
+            let start = Instant::now();
+            let verification_errors =
+                match self.verify_in_parallel(num_threads, preamble, methods, method_limits) {
+                    Ok(verification_errors) => verification_errors,
+                    Err(()) => return VerificationResult::Failure,
+                };
+            let duration = start.elapsed();
             info!(
-                "Viper encoding uses {} domains, {} fields, {} functions, {} predicates, {} methods",
-                domains.len(), fields.len(), viper_functions.len(), predicates.len(),
-                viper_methods.len()
+                "Parallel verification complete ({}.{} seconds)",
+                duration.as_secs(),
+                duration.subsec_millis() / 10
             );
+            json::timing("parallel_verification", duration);
+            verification_errors
+        } else {
+            let (program, viper_text) = match preamble.build_viper_program(
+                &self.ast_factory,
+                &self.ast_utils,
+                &methods,
+            ) {
+                Ok(result) => result,
+                Err(parse_error) => {
+                    self.report_parse_errors(&[parse_error]);
+                    return VerificationResult::Failure;
+                }
+            };
 
-            // Add a function that represents the symbolic read permission amount.
-            viper_functions.push(ast.function(
-                "read$",
-                &[],
-                ast.perm_type(),
-                &[],
-                &[
-                    ast.lt_cmp(ast.no_perm(), ast.result(ast.perm_type())),
-                    ast.lt_cmp(ast.result(ast.perm_type()), ast.full_perm()),
-                ],
-                ast.no_position(),
-                None,
-            ));
+            if config::dump_viper_program() {
+                self.dump_viper_program(program);
+            }
 
-            // Add a predicate that represents the dead loan token.
-            predicates.push(
-                ast.predicate(
-                    "DeadBorrowToken$",
-                    &[vir::LocalVar {
-                        name: "borrow".to_string(),
-                        typ: vir::Type::Int,
-                    }
-                        .to_viper_decl(ast)],
-                    None,
-                ),
+            let duration = start.elapsed();
+            info!(
+                "Construction of JVM objects successful ({}.{} seconds)",
+                duration.as_secs(),
+                duration.subsec_millis() / 10
+            );
+            json::timing("jvm_objects", duration);
+
+            let start = Instant::now();
+            let verification_result: viper::VerificationResult = self.verifier.verify(program);
+            let duration = start.elapsed();
+            info!(
+                "Verification complete ({}.{} seconds)",
+                duration.as_secs(),
+                duration.subsec_millis() / 10
             );
+            json::timing("verification", duration);
 
-            ast.program(&domains, &fields, &viper_functions, &predicates, &viper_methods)
+            match verification_result {
+                viper::VerificationResult::Failure(errors) => {
+                    translate_error_positions(errors, &viper_text)
+                }
+                _ => vec![],
+            }
         };
 
-        if config::dump_viper_program() {
-            // Dump Viper program
-            let source_path = self.env.source_path();
-            let source_filename = source_path.file_name().unwrap().to_str().unwrap();
-            let mut dump_path = PathBuf::from("viper_program");
-            let num_parents = config::num_parents_for_dumps();
-            if num_parents > 0 {
-                // Take `num_parents` parent folders and add them to `dump_path`
-                let mut components = vec![];
-                if let Some(abs_parent_path) = canonicalize(&source_path).ok().and_then(
-                    |full_path| full_path.parent().map(|parent| parent.to_path_buf())
-                ) {
-                    components.extend(
-                        abs_parent_path.ancestors()
-                            .flat_map(|path| path.file_name())
-                            .take(num_parents as usize)
-                            .map(|x| x.to_os_string())
-                            .collect::<Vec<_>>()
-                            .into_iter()
-                            .rev()
+        if config::report_verification_times() {
+            self.report_verification_times();
+        }
+
+        let error_manager = self.encoder.error_manager();
+
+        // The assertions of `prusti_refute!` statements and of reachability checks are
+        // expected to fail.
+        let (expected_failures, verification_errors): (Vec<_>, Vec<_>) = verification_errors
+            .into_iter()
+            .partition(|verification_error| error_manager.is_expected_failure(verification_error));
+
+        if config::check_unreachability() {
+            for warning in error_manager.unreachability_warnings(&expected_failures) {
+                self.env.span_warn(warning.span, &format!("[Prusti] {}", warning.message));
+            }
+        }
+
+        let mut compilation_errors: Vec<_> = verification_errors
+            .iter()
+            .map(|verification_error| {
+                debug!("Verification error: {:?}", verification_error);
+                let compilation_error = error_manager.translate(verification_error);
+                debug!("Compilation error: {:?}", compilation_error);
+                compilation_error
+            })
+            .collect();
+        compilation_errors.extend(error_manager.unrefuted_errors(&expected_failures));
+
+        if compilation_errors.is_empty() {
+            VerificationResult::Success
+        } else {
+            if verify_per_method {
+                // The order in which the verifiers report errors is not deterministic.
+                compilation_errors.sort_by_key(|error| {
+                    let position = error.span.primary_span().map(|span| (span.lo(), span.hi()));
+                    (position, error.message.clone())
+                });
+            }
+
+            // Tell apart the errors of the verifications under different spec profiles.
+            let message_prefix = match config::selected_verification_profile() {
+                Some(profile) => format!("[Prusti] [{} profile]", profile),
+                None => "[Prusti]".to_string(),
+            };
+
+            for compilation_error in compilation_errors {
+                compilation_error.report_json(self.env.codemap());
+                self.env.span_err_with_help_and_note(
+                    compilation_error.span,
+                    &format!("{} {}", message_prefix, compilation_error.message),
+                    &compilation_error.help,
+                    &compilation_error.note,
+                    &compilation_error.suggestion,
+                );
+            }
+            VerificationResult::Failure
+        }
+    }
+
+    /// Split the assertions of the method into separate methods, registering the new methods in
+    /// the timing report.
+    fn split_obligations(&self, method: vir::CfgMethod) -> Vec<vir::CfgMethod> {
+        let method_name = method.name();
+        let positions = optimisations::methods::obligation_positions(&method);
+        let obligation_methods = optimisations::methods::split_obligations(method);
+        if obligation_methods.len() > 1 {
+            info!(
+                "Verifying the {} assertions of {} separately",
+                obligation_methods.len(),
+                method_name
+            );
+            if config::report_verification_times() {
+                let mut timing_report = self.encoder.timing_report();
+                for (obligation_method, position) in obligation_methods.iter().zip(&positions) {
+                    timing_report.register_obligation(
+                        &method_name,
+                        obligation_method.name(),
+                        position.line(),
                     );
-                } else {
-                    components.push(OsString::from("io_error"))
-                }
-                for component in components {
-                    dump_path.push(component);
                 }
             }
-            info!("Dumping Viper program to '{:?}'", dump_path);
-            log::report(
-                dump_path.to_str().unwrap(),
-                format!("{}.vpr", source_filename),
-                self.ast_utils.pretty_print(program),
+        }
+        obligation_methods
+    }
+
+    /// Type check the program before sending it to Viper, reporting the ill-typed constructs
+    /// as internal errors. Returns whether the program is well-typed.
+    fn check_vir_types(&self, preamble: &ProgramPreamble, methods: &[vir::CfgMethod]) -> bool {
+        let start = Instant::now();
+        let type_errors =
+            vir::typecheck::check_program(&preamble.functions, &preamble.predicates, methods);
+        json::timing("vir_type_check", start.elapsed());
+        for type_error in &type_errors {
+            debug!("VIR type error: {:?}", type_error);
+            let compilation_error = self
+                .encoder
+                .error_manager()
+                .encoding_error(
+                    &type_error.position,
+                    format!("[Prusti internal error] ill-typed encoding: {}", type_error),
+                )
+                .set_help(
+                    "This is a bug of Prusti. The encoding of the code would be rejected by \
+                     Viper. Set CHECK_VIR_TYPES to false to send it to Viper nonetheless.",
+                );
+            compilation_error.report_json(self.env.codemap());
+            self.env.span_err_with_help_and_note(
+                compilation_error.span,
+                &compilation_error.message,
+                &compilation_error.help,
+                &compilation_error.note,
+                &compilation_error.suggestion,
             );
         }
+        type_errors.is_empty()
+    }
 
-        let duration = start.elapsed();
+    /// Encode the items of `task` again in a new session, and report an internal error if the
+    /// program differs from the one encoded so far. Returns whether the programs are equal.
+    fn check_repeated_encoding(&mut self, task: &VerificationTask) -> bool {
+        let first_program = self.encoded_program_text();
+        self.invalidate_all();
+        for &proc_id in task.procedures.iter().rev() {
+            self.encoder.queue_procedure_encoding(proc_id);
+        }
+        self.encoder.process_encoding_queue();
+        let second_program = self.encoded_program_text();
+        if first_program != second_program {
+            debug!("First encoding:\n{}", first_program);
+            debug!("Second encoding:\n{}", second_program);
+            self.env.err(
+                "[Prusti internal error] encoding the crate again in a new session produced a \
+                 different program",
+            );
+            return false;
+        }
+        true
+    }
+
+    /// The text of the program encoded so far, with the items sorted by name.
+    fn encoded_program_text(&self) -> String {
+        let mut functions = self.encoder.get_used_viper_functions();
+        functions.sort_by_key(|function| function.get_identifier());
+        let mut builtin_methods = self.encoder.get_used_builtin_methods();
+        builtin_methods.sort_by_key(|method| method.get_identifier());
+        let mut methods = self.encoder.get_used_viper_methods();
+        methods.sort_by_key(|method| method.name());
+        let preamble = ProgramPreamble {
+            domains: self.encoder.get_used_viper_domains(),
+            fields: self.encoder.get_used_viper_fields(),
+            functions,
+            predicates: self.encoder.get_used_viper_predicates(),
+            builtin_methods,
+            uses_maps: self.encoder.uses_viper_maps(),
+        };
+        preamble.to_viper_text(&methods).source().to_string()
+    }
+
+    /// Report the predicates whose body would be dropped by `LAZY_PREDICATE_BODIES`,
+    /// without dropping it. Comparing the verification results with and without the
+    /// audit detects unexpected interactions with the fold-unfold algorithm.
+    fn audit_lazy_predicate_bodies(
+        &self,
+        predicates: &[vir::Predicate],
+        methods: &[vir::CfgMethod],
+        functions: &[vir::Function],
+    ) {
+        let used_bodies =
+            optimisations::predicates::collect_predicates_with_used_bodies(methods, functions);
+        let mut unused_bodies: Vec<_> = predicates
+            .iter()
+            .filter(|predicate| {
+                !predicate.is_abstract() && !used_bodies.contains(predicate.name())
+            })
+            .map(|predicate| predicate.name().to_string())
+            .collect();
+        unused_bodies.sort();
         info!(
-            "Construction of JVM objects successful ({}.{} seconds)",
-            duration.as_secs(),
-            duration.subsec_millis() / 10
+            "Audit: {} predicates would be emitted without body",
+            unused_bodies.len()
         );
-        let start = Instant::now();
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        log::report(
+            "lazy_predicate_bodies",
+            format!("{}.txt", source_filename),
+            unused_bodies.join("\n"),
+        );
+    }
 
-        let verification_result: viper::VerificationResult = self.verifier.verify(program);
+    /// Report the items whose correctness is assumed by the verification.
+    fn report_trusted_surface(&self) {
+        let mut trusted_surface = self.encoder.trusted_surface();
+        trusted_surface.register_configuration();
+        let report = trusted_surface.to_report(self.env.codemap());
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        log::report(
+            "trusted_surface",
+            format!("{}.txt", source_filename),
+            &report,
+        );
+        user::message(format!(
+            "Trusted surface: {} item(s) assumed without verification",
+            trusted_surface.len()
+        ));
+        if !report.is_empty() {
+            user::message(report);
+        }
+    }
 
-        let duration = start.elapsed();
+    fn report_inferred_purity(&self) {
+        let mut inferred_pure_functions: Vec<_> = self
+            .encoder
+            .get_inferred_pure_functions()
+            .into_iter()
+            .map(|def_id| {
+                let span = self.env.get_item_span(def_id);
+                format!(
+                    "{} ({})",
+                    self.env.get_absolute_item_name(def_id),
+                    self.env.codemap().span_to_string(span)
+                )
+            })
+            .collect();
+        inferred_pure_functions.sort();
+        let report = inferred_pure_functions.join("\n");
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        log::report(
+            "inferred_purity",
+            format!("{}.txt", source_filename),
+            &report,
+        );
+        user::message(format!(
+            "Inferred purity: {} procedure(s) encoded as pure functions",
+            inferred_pure_functions.len()
+        ));
+        if !report.is_empty() {
+            user::message(report);
+        }
+    }
+
+    /// Report the time spent to encode and verify each procedure, slowest first.
+    fn report_verification_times(&self) {
+        let timing_report = self.encoder.timing_report();
+        let report = timing_report.to_report(self.env.codemap());
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        log::report(
+            "verification_times",
+            format!("{}.txt", source_filename),
+            &report,
+        );
+        user::message(format!(
+            "Verification times of {} procedure(s):",
+            timing_report.len()
+        ));
+        user::message(report);
+    }
+
+    /// Verify each method in a separate Viper program, using `num_threads`
+    /// verifiers that take the methods from a shared work queue.
+    ///
+    /// The methods in `method_limits` are verified by verifiers of their own, which use the
+    /// given resource limits. The time spent by Viper on each method is recorded in the timing
+    /// report.
+    ///
+    /// Viper verifies all the members of a program, and the program of each method contains the
+    /// whole preamble, which the method may use. Thus the functions and the predicates of the
+    /// preamble are verified again with each method: only the duplicated errors are dropped, not
+    /// the duplicated work. This makes the parallel verification pay off only when the methods
+    /// dominate the verification time.
+    ///
+    /// Fails after reporting the programs whose emitted text could not be parsed, if any.
+    fn verify_in_parallel(
+        &self,
+        num_threads: usize,
+        preamble: ProgramPreamble,
+        mut methods: Vec<vir::CfgMethod>,
+        method_limits: HashMap<String, ResourceLimits>,
+    ) -> Result<Vec<viper::VerificationError>, ()> {
+        // Sort the methods by name, so that the work queue is deterministic.
+        methods.sort_by_key(|m| m.name());
         info!(
-            "Verification complete ({}.{} seconds)",
-            duration.as_secs(),
-            duration.subsec_millis() / 10
+            "Verifying {} methods using {} parallel verifiers",
+            methods.len(),
+            num_threads
         );
+        let preamble = Arc::new(preamble);
+        let method_limits = Arc::new(method_limits);
+        let queue: Arc<Mutex<VecDeque<vir::CfgMethod>>> =
+            Arc::new(Mutex::new(methods.into_iter().collect()));
 
-        let verification_errors = match verification_result {
-            viper::VerificationResult::Failure(errors) => errors,
-            _ => vec![],
-        };
+        let handles: Vec<_> = (0..num_threads)
+            .map(|thread_index| {
+                let preamble = preamble.clone();
+                let method_limits = method_limits.clone();
+                let queue = queue.clone();
+                let viper = self.parallel_config.viper.clone();
+                let backend = self.parallel_config.backend;
+                let verifier_args = self.parallel_config.verifier_args.clone();
+                let log_path = self.parallel_config.log_path.clone();
+                let report_path = self
+                    .parallel_config
+                    .log_path
+                    .join(format!("report_{}.csv", thread_index));
+                thread::Builder::new()
+                    .name(format!("prusti-verifier-{}", thread_index))
+                    .spawn(move || {
+                        let verification_ctx = viper.new_verification_context();
+                        let ast = verification_ctx.new_ast_factory();
+                        let ast_utils = verification_ctx.new_ast_utils();
+                        let verifier = verification_ctx.new_verifier_with_args(
+                            backend,
+                            verifier_args,
+                            Some(report_path.clone()),
+                        );
+                        let mut errors = vec![];
+                        let mut durations = vec![];
+                        let mut parse_errors = vec![];
+                        loop {
+                            let next_method = queue.lock().unwrap().pop_front();
+                            let method = match next_method {
+                                Some(method) => method,
+                                None => break,
+                            };
+                            let method_name = method.name();
+                            debug!("Verifier {} verifies method {}", thread_index, method_name);
+                            let (program, viper_text) =
+                                match preamble.build_viper_program(&ast, &ast_utils, &[method]) {
+                                    Ok(result) => result,
+                                    Err(parse_error) => {
+                                        parse_errors.push(parse_error);
+                                        continue;
+                                    }
+                                };
+                            let start = Instant::now();
+                            let verification_result = match method_limits.get(&method_name) {
+                                Some(limits) => {
+                                    let limited_verifier = verification_ctx
+                                        .new_verifier_with_args(
+                                            backend,
+                                            build_verifier_args(backend, &log_path, limits),
+                                            Some(report_path.clone()),
+                                        );
+                                    limited_verifier.verify(program)
+                                }
+                                None => verifier.verify(program),
+                            };
+                            durations.push((method_name, start.elapsed()));
+                            if let viper::VerificationResult::Failure(method_errors) =
+                                verification_result
+                            {
+                                errors.extend(translate_error_positions(
+                                    method_errors,
+                                    &viper_text,
+                                ));
+                            }
+                        }
+                        (errors, durations, parse_errors)
+                    })
+                    .unwrap()
+            })
+            .collect();
 
-        if verification_errors.is_empty() {
-            VerificationResult::Success
+        // The preamble is verified together with each method, so errors that are
+        // not about a method may be reported more than once.
+        let mut seen_errors = HashSet::new();
+        let mut verification_errors = vec![];
+        let mut all_parse_errors = vec![];
+        for handle in handles {
+            let (errors, durations, parse_errors) = handle.join().unwrap();
+            for error in errors {
+                if seen_errors.insert(error.clone()) {
+                    verification_errors.push(error);
+                }
+            }
+            all_parse_errors.extend(parse_errors);
+            let mut timing_report = self.encoder.timing_report();
+            for (method_name, duration) in durations {
+                timing_report.record(method_name, VerificationPhase::Viper, duration);
+            }
+        }
+        if all_parse_errors.is_empty() {
+            Ok(verification_errors)
         } else {
-            let error_manager = self.encoder.error_manager();
+            self.report_parse_errors(&all_parse_errors);
+            Err(())
+        }
+    }
 
-            for verification_error in verification_errors {
-                debug!("Verification error: {:?}", verification_error);
-                let compilation_error = error_manager.translate(&verification_error);
-                debug!("Compilation error: {:?}", compilation_error);
-                self.env.span_err_with_help_and_note(
-                    compilation_error.span,
-                    &format!("[Prusti] {}", compilation_error.message),
-                    &compilation_error.help,
-                    &compilation_error.note,
+    /// Report the failures of the backend to parse the emitted Viper text as internal errors.
+    fn report_parse_errors(&self, parse_errors: &[viper::ParseError]) {
+        let mut messages: Vec<_> = parse_errors.iter().map(|error| error.to_string()).collect();
+        messages.sort();
+        messages.dedup();
+        for message in messages {
+            self.env.err(&format!(
+                "[Prusti internal error] the emitted Viper program could not be parsed: {}. \
+                 This is a bug of Prusti. Set EMIT_VIPER_TEXT to false to build the program \
+                 through JNI instead.",
+                message
+            ));
+        }
+    }
+
+    fn dump_viper_program(&self, program: viper::Program) {
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        let mut dump_path = PathBuf::from("viper_program");
+        let num_parents = config::num_parents_for_dumps();
+        if num_parents > 0 {
+            // Take `num_parents` parent folders and add them to `dump_path`
+            let mut components = vec![];
+            if let Some(abs_parent_path) = canonicalize(&source_path).ok().and_then(
+                |full_path| full_path.parent().map(|parent| parent.to_path_buf())
+            ) {
+                components.extend(
+                    abs_parent_path.ancestors()
+                        .flat_map(|path| path.file_name())
+                        .take(num_parents as usize)
+                        .map(|x| x.to_os_string())
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .rev()
                 );
+            } else {
+                components.push(OsString::from("io_error"))
+            }
+            for component in components {
+                dump_path.push(component);
             }
-            VerificationResult::Failure
         }
+        info!("Dumping Viper program to '{:?}'", dump_path);
+        log::report(
+            dump_path.to_str().unwrap(),
+            format!("{}.vpr", source_filename),
+            self.with_symbol_legend(self.ast_utils.pretty_print(program)),
+        );
+    }
+
+    /// Dump a self-contained Viper program for each method, so that the
+    /// verification of a single item can be replayed with the Viper tools.
+    ///
+    /// The files are stored in `<dump_dir>/<source file>/<method name>.vpr`.
+    fn dump_viper_programs_per_item(
+        &self,
+        dump_dir: &PathBuf,
+        preamble: &ProgramPreamble,
+        methods: &[vir::CfgMethod],
+    ) {
+        let source_path = self.env.source_path();
+        let source_filename = source_path.file_name().unwrap().to_str().unwrap();
+        let dump_path = dump_dir.join(source_filename);
+        create_dir_all(&dump_path).unwrap();
+        info!("Dumping Viper programs to '{:?}'", dump_path);
+        for method in methods {
+            let program = preamble.to_viper_program(&self.ast_factory, &[method.clone()]);
+            let file_path = dump_path.join(format!("{}.vpr", method.name()));
+            let program_text = self.with_symbol_legend(self.ast_utils.pretty_print(program));
+            fs::write(&file_path, program_text).unwrap_or_else(|e| {
+                panic!("Failed to write Viper program to {:?}: {}", file_path, e)
+            });
+        }
+    }
+
+    /// Prefix the text of a dumped Viper program with the Rust items and types encoded by its
+    /// identifiers.
+    fn with_symbol_legend(&self, program_text: String) -> String {
+        let legend = self.encoder.symbol_table().to_legend(&program_text);
+        format!("{}\n{}", legend, program_text)
     }
 
+    /// Forget everything that has been encoded, e.g. because the crate changed.
     pub fn invalidate_all(&mut self) {
-        unimplemented!()
+        self.encoder.reset();
     }
 }
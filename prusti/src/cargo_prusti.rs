@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::process::Command;
 
 fn main(){
@@ -17,13 +18,37 @@ where
         prusti_rustc_path.set_extension("exe");
     }
 
-    let exit_status = Command::new("cargo".to_string())
-        .arg("check")
+    // The `--message-format` and `--dump-viper` flags configure Prusti, not Cargo.
+    let mut message_format = None;
+    let mut dump_viper_dir = None;
+    let args: Vec<String> = args
+        .filter(|arg| {
+            if arg.starts_with("--message-format=") {
+                message_format = Some(arg["--message-format=".len()..].to_string());
+                false
+            } else if arg.starts_with("--dump-viper=") {
+                dump_viper_dir = Some(PathBuf::from(&arg["--dump-viper=".len()..]));
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+
+    let mut cmd = Command::new("cargo".to_string());
+    cmd.arg("check")
         .args(args)
         .env("PRUSTI_FULL_COMPILATION", "true")
-        .env("RUSTC_WRAPPER", prusti_rustc_path)
-        .status()
-        .expect("could not run cargo");
+        .env("RUSTC_WRAPPER", prusti_rustc_path);
+    if let Some(format) = message_format {
+        cmd.env("PRUSTI_MESSAGE_FORMAT", format);
+    }
+    if let Some(dir) = dump_viper_dir {
+        // Cargo does not run the compiler of each crate in the current directory.
+        let current_dir = std::env::current_dir().expect("current directory invalid");
+        cmd.env("PRUSTI_DUMP_VIPER_DIR", current_dir.join(dir));
+    }
+    let exit_status = cmd.status().expect("could not run cargo");
 
     if exit_status.success() {
         Ok(())
@@ -0,0 +1,224 @@
+// © 2019, ETH Zurich
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Verify a single Rust file, for online playgrounds and end-to-end snapshot tests.
+//!
+//! The request is a JSON object read from *stdin*:
+//!
+//! ```json
+//! { "source": "extern crate prusti_contracts; fn main() {}", "config": { "CHECK_PANICS": false } }
+//! ```
+//!
+//! Only the configuration flags in `ALLOWED_FLAGS` can be set. The response is a JSON object
+//! printed to *stdout*, containing the JSON messages of Prusti (see `report::json`), the
+//! output of the compiler, and whether the verification succeeded.
+
+#![feature(nll)]
+
+extern crate serde_json;
+
+use serde_json::{Map, Value};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The configuration flags that a request can set.
+const ALLOWED_FLAGS: &[&str] = &[
+    "CHECK_BINARY_OPERATIONS",
+    "CHECK_PANICS",
+    "ENCODE_UNSIGNED_NUM_CONSTRAINT",
+    "SIMPLIFY_ENCODING",
+    "INFER_PURITY",
+    "VIPER_BACKEND",
+];
+
+/// The maximum size of the verified source, in bytes.
+const MAX_SOURCE_LEN: usize = 100_000;
+
+/// The maximum time taken by the verification, unless `PRUSTI_PLAYGROUND_TIMEOUT` is set.
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// The timeout of each assertion checked by the backend, in milliseconds.
+const ASSERT_TIMEOUT_MS: u64 = 10_000;
+
+/// The maximum heap of the JVM running the backend.
+const MAX_JVM_HEAP: &str = "-Xmx1g";
+
+fn main() {
+    let response = match read_request() {
+        Ok((source, config)) => verify(&source, &config),
+        Err(message) => error_response(&message),
+    };
+    println!("{}", Value::Object(response));
+}
+
+fn error_response(message: &str) -> Map<String, Value> {
+    let mut response = Map::new();
+    response.insert("success".to_string(), Value::Bool(false));
+    response.insert("error".to_string(), Value::String(message.to_string()));
+    response
+}
+
+/// Read the source and the configuration flags of the request.
+fn read_request() -> Result<(String, Vec<(String, String)>), String> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| format!("failed to read the request: {}", e))?;
+    let request: Value =
+        serde_json::from_str(&input).map_err(|e| format!("invalid request: {}", e))?;
+
+    let source = request["source"]
+        .as_str()
+        .ok_or_else(|| "the request has no \"source\" string".to_string())?;
+    if source.len() > MAX_SOURCE_LEN {
+        return Err(format!("the source is longer than {} bytes", MAX_SOURCE_LEN));
+    }
+
+    let mut config = vec![];
+    if let Some(flags) = request["config"].as_object() {
+        for (flag, value) in flags {
+            if !ALLOWED_FLAGS.contains(&flag.as_str()) {
+                return Err(format!("the configuration flag {:?} cannot be set", flag));
+            }
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Bool(_) | Value::Number(_) => value.to_string(),
+                _ => return Err(format!("invalid value of the configuration flag {:?}", flag)),
+            };
+            config.push((flag.clone(), value));
+        }
+    }
+
+    Ok((source.to_string(), config))
+}
+
+/// Run `prusti-rustc` on the source, collecting its output.
+fn verify(source: &str, config: &[(String, String)]) -> Map<String, Value> {
+    let work_dir = env::temp_dir().join(format!("prusti-playground-{}", process::id()));
+    let source_path = work_dir.join("main.rs");
+    // Lists cannot be set with environment variables, so the JVM arguments are set in the
+    // `Prusti.toml` of the working directory.
+    let prusti_toml = format!("EXTRA_JVM_ARGS = [\"{}\"]\n", MAX_JVM_HEAP);
+    let written = fs::create_dir_all(&work_dir)
+        .and_then(|_| fs::write(&source_path, source))
+        .and_then(|_| fs::write(work_dir.join("Prusti.toml"), prusti_toml));
+    if let Err(e) = written {
+        return error_response(&format!("failed to write the source: {}", e));
+    }
+
+    let mut prusti_rustc_path = env::current_exe()
+        .expect("current executable path invalid")
+        .with_file_name("prusti-rustc");
+    if cfg!(windows) {
+        prusti_rustc_path.set_extension("exe");
+    }
+
+    let mut cmd = Command::new(&prusti_rustc_path);
+    cmd.arg(&source_path)
+        .arg("--color=never")
+        .arg("--out-dir")
+        .arg(&work_dir)
+        .current_dir(&work_dir)
+        .env("PRUSTI_MESSAGE_FORMAT", "json")
+        .env("PRUSTI_QUIET", "true")
+        .env("PRUSTI_NUM_THREADS", "1")
+        .env("PRUSTI_ASSERT_TIMEOUT", ASSERT_TIMEOUT_MS.to_string())
+        .env_remove("PRUSTI_CONFIG")
+        .env("PRUSTI_LOG_DIR", work_dir.join("log"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for (flag, value) in config {
+        cmd.env(format!("PRUSTI_{}", flag), value);
+    }
+
+    let start = Instant::now();
+    let response = match cmd.spawn() {
+        Ok(child) => wait_with_timeout(child, timeout(), start),
+        Err(e) => error_response(&format!("failed to run prusti-rustc: {}", e)),
+    };
+    fs::remove_dir_all(&work_dir).ok();
+    response
+}
+
+fn timeout() -> Duration {
+    let seconds = env::var("PRUSTI_PLAYGROUND_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    Duration::from_secs(seconds)
+}
+
+/// Wait for `prusti-rustc`, killing it after `timeout`, and build the response.
+fn wait_with_timeout(
+    mut child: process::Child,
+    timeout: Duration,
+    start: Instant,
+) -> Map<String, Value> {
+    let mut stdout = child.stdout.take().unwrap();
+    let mut stderr = child.stderr.take().unwrap();
+    let stdout_reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).ok();
+        output
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut output = String::new();
+        stderr.read_to_string(&mut output).ok();
+        output
+    });
+
+    let mut timed_out = false;
+    let exit_status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(100)),
+            Ok(None) => {
+                timed_out = true;
+                child.kill().ok();
+                child.wait().ok();
+                break None;
+            }
+            Err(_) => break None,
+        }
+    };
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+    // The JSON messages are on their own lines. Other lines, if any, are ignored.
+    let messages: Vec<Value> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message.is_object())
+        .collect();
+
+    let mut response = Map::new();
+    response.insert(
+        "success".to_string(),
+        Value::Bool(exit_status.map_or(false, |status| status.success())),
+    );
+    response.insert("timed_out".to_string(), Value::Bool(timed_out));
+    response.insert(
+        "exit_code".to_string(),
+        exit_status
+            .and_then(|status| status.code())
+            .map_or(Value::Null, |code| Value::from(code)),
+    );
+    response.insert(
+        "seconds".to_string(),
+        Value::from(duration_secs(start.elapsed())),
+    );
+    response.insert("messages".to_string(), Value::Array(messages));
+    response.insert("compiler_output".to_string(), Value::String(stderr));
+    response
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_millis()) / 1000.0
+}
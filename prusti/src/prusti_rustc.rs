@@ -14,7 +14,7 @@ fn main(){
     }
 }
 
-fn process(args: Vec<String>) -> Result<(), i32> {
+fn process(mut args: Vec<String>) -> Result<(), i32> {
     let mut prusti_driver_path = std::env::current_exe()
         .expect("current executable path invalid")
         .with_file_name("prusti-driver");
@@ -43,7 +43,43 @@ fn process(args: Vec<String>) -> Result<(), i32> {
         "Failed to find prusti_contracts library in Prusti's home"
     );
 
+    // Prusti-specific flags are removed from the arguments of the compiler
+    // and passed to the driver as configuration flags.
+    let mut dump_viper_dir = None;
+    let mut dump_cfg_dir = None;
+    let mut dump_vir_after = None;
+    let mut message_format = None;
+    args.retain(|arg| {
+        if arg.starts_with("--dump-viper=") {
+            dump_viper_dir = Some(arg["--dump-viper=".len()..].to_string());
+            false
+        } else if arg.starts_with("--dump-cfg=") {
+            dump_cfg_dir = Some(arg["--dump-cfg=".len()..].to_string());
+            false
+        } else if arg.starts_with("--dump-vir-after=") {
+            dump_vir_after = Some(arg["--dump-vir-after=".len()..].to_string());
+            false
+        } else if arg.starts_with("--message-format=") {
+            message_format = Some(arg["--message-format=".len()..].to_string());
+            false
+        } else {
+            true
+        }
+    });
+
     let mut cmd = Command::new(&prusti_driver_path);
+    if let Some(dir) = dump_viper_dir {
+        cmd.env("PRUSTI_DUMP_VIPER_DIR", dir);
+    }
+    if let Some(dir) = dump_cfg_dir {
+        cmd.env("PRUSTI_DUMP_CFG_DIR", dir);
+    }
+    if let Some(passes) = dump_vir_after {
+        cmd.env("PRUSTI_DUMP_VIR_AFTER", passes);
+    }
+    if let Some(format) = message_format {
+        cmd.env("PRUSTI_MESSAGE_FORMAT", format);
+    }
     let has_no_color_arg = args.iter().find(|&x| x == "--color" || x.starts_with("--color=")).is_none();
     cmd.args(args);
     if has_no_color_arg {
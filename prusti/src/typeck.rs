@@ -104,6 +104,25 @@ fn type_assertion(
                     type_trigger_set(trigger_set, typed_expressions),
                     type_assertion(assertion, typed_expressions, typed_forallargs),
                 ),
+                AssertionKind::Exists(vars, trigger_set, assertion) => AssertionKind::Exists(
+                    ForAllVars {
+                        id: vars.id,
+                        vars: typed_forallargs[&vars.id].clone(),
+                    },
+                    type_trigger_set(trigger_set, typed_expressions),
+                    type_assertion(assertion, typed_expressions, typed_forallargs),
+                ),
+                AssertionKind::Let(vars, value, assertion) => AssertionKind::Let(
+                    ForAllVars {
+                        id: vars.id,
+                        vars: typed_forallargs[&vars.id].clone(),
+                    },
+                    Expression {
+                        id: value.id,
+                        expr: typed_expressions[&value.id].clone(),
+                    },
+                    type_assertion(assertion, typed_expressions, typed_forallargs),
+                ),
                 AssertionKind::Pledge(Some(reference), lhs, rhs) => AssertionKind::Pledge(
                     Some(Expression {
                         id: reference.id,
@@ -155,6 +174,20 @@ fn convert_to_typed(
             SpecificationSet::Struct(invariants) => {
                 (id, SpecificationSet::Struct(convert(invariants)))
             }
+            SpecificationSet::Assumption(assumptions) => {
+                (id, SpecificationSet::Assumption(convert(assumptions)))
+            }
+            SpecificationSet::Assertion(assertions) => {
+                (id, SpecificationSet::Assertion(convert(assertions)))
+            }
+            SpecificationSet::Refutation(refutations) => {
+                (id, SpecificationSet::Refutation(convert(refutations)))
+            }
+            SpecificationSet::Fold(folds) => (id, SpecificationSet::Fold(convert(folds))),
+            SpecificationSet::Unfold(unfolds) => (id, SpecificationSet::Unfold(convert(unfolds))),
+            SpecificationSet::Termination(measures) => {
+                (id, SpecificationSet::Termination(convert(measures)))
+            }
         })
         .collect()
 }
@@ -6,11 +6,14 @@
 
 //! A module that invokes the verifier `prusti-viper`
 
+use prusti_interface::config;
 use prusti_interface::data::VerificationResult;
 use prusti_interface::data::VerificationTask;
 use prusti_interface::environment::Environment;
+use prusti_interface::report::json;
 use prusti_interface::report::user;
 use prusti_interface::specifications::TypedSpecificationMap;
+use prusti_viper::smt::SmtVerifier;
 use prusti_viper::verifier::VerifierBuilder;
 use rustc_driver::driver;
 use std::time::Instant;
@@ -47,31 +50,48 @@ pub fn verify<'r, 'a: 'r, 'tcx: 'a>(
             debug!("Dump borrow checker info...");
             env.dump_borrowck_info(&verification_task.procedures);
 
-            debug!("Prepare verifier...");
-            let jvm_start = Instant::now();
-            let verifier_builder = VerifierBuilder::new();
-            let verification_context = verifier_builder.new_verification_context();
-            let jvm_duration = jvm_start.elapsed();
-            info!(
-                "JVM startup ({}.{} seconds)",
-                jvm_duration.as_secs(),
-                jvm_duration.subsec_millis() / 10
-            );
-
-            let verifier_start = Instant::now();
-            let mut verifier = verification_context.new_verifier(&env, &spec);
-            let verifier_duration = verifier_start.elapsed();
-            info!(
-                "Verifier startup ({}.{} seconds)",
-                verifier_duration.as_secs(),
-                verifier_duration.subsec_millis() / 10
-            );
-
-            debug!("Run verifier...");
-            let verification_result = verifier.verify(&verification_task);
-            debug!("Verifier returned {:?}", verification_result);
-
-            verification_result
+            let profiles = config::verification_profiles();
+
+            if config::smt_export() {
+                verify_under_profiles(&profiles, || {
+                    debug!("Run SMT verifier...");
+                    let mut verifier = SmtVerifier::new(&env, &spec);
+                    let verification_result = verifier.verify(&verification_task);
+                    debug!("SMT verifier returned {:?}", verification_result);
+                    verification_result
+                })
+            } else {
+                debug!("Prepare verifier...");
+                let jvm_start = Instant::now();
+                let verifier_builder = VerifierBuilder::new();
+                let verification_context = verifier_builder.new_verification_context();
+                let jvm_duration = jvm_start.elapsed();
+                info!(
+                    "JVM startup ({}.{} seconds)",
+                    jvm_duration.as_secs(),
+                    jvm_duration.subsec_millis() / 10
+                );
+                json::timing("jvm_startup", jvm_duration);
+
+                // The JVM can be started only once, so all profiles share it.
+                verify_under_profiles(&profiles, || {
+                    let verifier_start = Instant::now();
+                    let mut verifier = verification_context.new_verifier(&env, &spec);
+                    let verifier_duration = verifier_start.elapsed();
+                    info!(
+                        "Verifier startup ({}.{} seconds)",
+                        verifier_duration.as_secs(),
+                        verifier_duration.subsec_millis() / 10
+                    );
+                    json::timing("verifier_startup", verifier_duration);
+
+                    debug!("Run verifier...");
+                    let verification_result = verifier.verify(&verification_task);
+                    debug!("Verifier returned {:?}", verification_result);
+
+                    verification_result
+                })
+            }
         };
 
         match verification_result {
@@ -90,3 +110,31 @@ pub fn verify<'r, 'a: 'r, 'tcx: 'a>(
 
     trace!("[verify] exit");
 }
+
+/// Run `verify` once under each spec profile, or once with the configuration as it is
+/// when no profile is given. The verification fails if it fails under any profile.
+fn verify_under_profiles<F>(profiles: &[String], mut verify: F) -> VerificationResult
+where
+    F: FnMut() -> VerificationResult,
+{
+    if profiles.is_empty() {
+        return verify();
+    }
+    let mut verification_result = VerificationResult::Success;
+    for profile in profiles {
+        config::select_verification_profile(profile);
+        user::message(format!("Verification under the {} profile...", profile));
+        let profile_result = verify();
+        let success = profile_result == VerificationResult::Success;
+        json::profile_result(profile, success);
+        user::message(format!(
+            "Verification under the {} profile {}",
+            profile,
+            if success { "succeeded" } else { "failed" }
+        ));
+        if !success {
+            verification_result = VerificationResult::Failure;
+        }
+    }
+    verification_result
+}
@@ -0,0 +1,26 @@
+extern crate prusti_contracts;
+
+struct T {
+    f: u32,
+}
+
+struct U {
+    a: T,
+    b: T,
+}
+
+#[ensures="result.a.f == old(x.a.f)"]
+fn branches(x: U, c1: bool, c2: bool) -> U {
+    let mut y = x;
+    if c1 {
+        y.b.f = 1;
+    } else if c2 {
+        y.b = T { f: 2 };
+    } else {
+        let z = y.b.f;
+        y.b.f = z;
+    }
+    y
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+extern crate prusti_contracts;
+
+enum E {
+    A(u32),
+    B { f: u32 },
+    C,
+}
+
+fn get(e: &E) -> u32 {
+    match e {
+        E::A(x) => *x,
+        E::B { f } => *f,
+        E::C => 0,
+    }
+}
+
+struct Counter {
+    value: u32,
+}
+
+#[requires="n <= 100"]
+fn count(c: &mut Counter, n: u32) {
+    let mut i = 0;
+    #[invariant="i <= n"]
+    while i < n {
+        c.value = i;
+        i += 1;
+    }
+}
+
+fn main() {}
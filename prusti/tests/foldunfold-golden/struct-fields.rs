@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+struct T {
+    f: u32,
+    g: u32,
+}
+
+struct U {
+    a: T,
+    b: T,
+}
+
+#[ensures="result == x.a.f"]
+fn read_nested(x: &U) -> u32 {
+    x.a.f
+}
+
+fn write_nested(x: &mut U) {
+    x.a.f = 1;
+    x.b = T { f: 2, g: 3 };
+}
+
+fn move_field(x: U) -> T {
+    let y = x.b;
+    y
+}
+
+fn main() {}
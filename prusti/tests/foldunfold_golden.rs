@@ -0,0 +1,136 @@
+//! Golden tests of the fold-unfold algorithm.
+//!
+//! Each `*.rs` file of `tests/foldunfold-golden` is verified with `PRUSTI_DUMP_FOLD_UNFOLD_ACTIONS`
+//! and the fold and unfold statements generated in each basic block are compared with the
+//! `*.actions` file next to it. Missing golden files are created. After an intended change of
+//! the generated statements, run the tests with `PRUSTI_BLESS=true` to overwrite the golden
+//! files, and review their diff.
+
+use std::env::{self, var};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn get_prusti_rustc_path() -> PathBuf {
+    let local_prusti_rustc_path: PathBuf = if cfg!(windows) {
+        ["target", "debug", "prusti-rustc.exe"].iter().collect()
+    } else {
+        ["target", "debug", "prusti-rustc"].iter().collect()
+    };
+    let workspace_prusti_rustc_path: PathBuf = if cfg!(windows) {
+        ["..", "target", "debug", "prusti-rustc.exe"].iter().collect()
+    } else {
+        ["..", "target", "debug", "prusti-rustc"].iter().collect()
+    };
+    if local_prusti_rustc_path.exists() {
+        return local_prusti_rustc_path;
+    }
+    if workspace_prusti_rustc_path.exists() {
+        return workspace_prusti_rustc_path;
+    }
+    panic!("Could not find the prusti-rustc binary to be used in tests");
+}
+
+/// Verify `source`, returning the dumped fold-unfold statements of all its methods.
+fn dump_actions(prusti_rustc_path: &Path, source: &Path, work_dir: &Path) -> String {
+    let log_dir = work_dir.join("log");
+    let output = Command::new(prusti_rustc_path)
+        .arg(source)
+        .arg("--color=never")
+        .arg("-A")
+        .arg("warnings")
+        .arg("--out-dir")
+        .arg(work_dir)
+        .env_remove("PRUSTI_CONFIG")
+        .env("PRUSTI_FULL_COMPILATION", "true")
+        .env("PRUSTI_QUIET", "true")
+        .env("PRUSTI_CHECK_BINARY_OPERATIONS", "false")
+        .env("PRUSTI_SIMPLIFY_ENCODING", "true")
+        .env("PRUSTI_DUMP_FOLD_UNFOLD_ACTIONS", "true")
+        .env("PRUSTI_LOG_DIR", &log_dir)
+        .output()
+        .expect("failed to run prusti-rustc");
+
+    let actions_dir = log_dir.join("fold_unfold_actions");
+    let mut paths: Vec<PathBuf> = match fs::read_dir(&actions_dir) {
+        Ok(entries) => entries.map(|entry| entry.unwrap().path()).collect(),
+        Err(_) => panic!(
+            "No fold-unfold statements were dumped for {}. Compiler output:\n{}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+    };
+    // The methods are encoded in an arbitrary order.
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Describe the first line that differs between the expected and the actual statements.
+fn describe_difference(expected: &str, actual: &str) -> String {
+    let expected_lines: Vec<_> = expected.lines().collect();
+    let actual_lines: Vec<_> = actual.lines().collect();
+    let line = expected_lines
+        .iter()
+        .zip(actual_lines.iter())
+        .take_while(|(e, a)| e == a)
+        .count();
+    format!(
+        "first difference at line {}:\n  expected: {}\n  actual:   {}",
+        line + 1,
+        expected_lines.get(line).unwrap_or(&"<end of file>"),
+        actual_lines.get(line).unwrap_or(&"<end of file>"),
+    )
+}
+
+#[test]
+fn foldunfold_golden() {
+    let prusti_rustc_path = get_prusti_rustc_path();
+    let bless = var("PRUSTI_BLESS") == Ok(String::from("true"));
+    let filter = var("TESTNAME").ok();
+    let golden_dir: PathBuf = ["tests", "foldunfold-golden"].iter().collect();
+    let work_dir = env::temp_dir().join(format!("prusti-foldunfold-golden-{}", std::process::id()));
+
+    let mut sources: Vec<PathBuf> = fs::read_dir(&golden_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .collect();
+    sources.sort();
+
+    let mut failures = vec![];
+    for source in &sources {
+        let name = source.file_stem().unwrap().to_string_lossy().to_string();
+        if filter.as_ref().map_or(false, |filter| !name.contains(filter.as_str())) {
+            continue;
+        }
+        let source_work_dir = work_dir.join(&name);
+        fs::create_dir_all(&source_work_dir).unwrap();
+        let actual = dump_actions(&prusti_rustc_path, source, &source_work_dir);
+
+        let golden_path = source.with_extension("actions");
+        match fs::read_to_string(&golden_path) {
+            Ok(ref expected) if *expected == actual => {}
+            Ok(ref expected) if !bless => failures.push(format!(
+                "{}: {}",
+                golden_path.display(),
+                describe_difference(expected, &actual)
+            )),
+            _ => {
+                eprintln!("Writing {}", golden_path.display());
+                fs::write(&golden_path, &actual).unwrap();
+            }
+        }
+    }
+    fs::remove_dir_all(&work_dir).ok();
+
+    assert!(
+        failures.is_empty(),
+        "The fold-unfold statements differ from the golden files \
+         (run with PRUSTI_BLESS=true to update them):\n{}",
+        failures.join("\n")
+    );
+}
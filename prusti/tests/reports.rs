@@ -0,0 +1,334 @@
+//! Tests of the reports and of the dumps that Prusti produces besides the verification errors.
+//!
+//! Each test runs `prusti-rustc` (or `cargo-prusti`) on a file of `tests/reports` and checks its
+//! output.
+
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn get_prusti_binary_path(name: &str) -> PathBuf {
+    let binary_name = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+    let local_path: PathBuf = ["target", "debug", &binary_name].iter().collect();
+    let workspace_path: PathBuf = ["..", "target", "debug", &binary_name].iter().collect();
+    if local_path.exists() {
+        return local_path;
+    }
+    if workspace_path.exists() {
+        return workspace_path;
+    }
+    panic!("Could not find the {} binary to be used in tests", name);
+}
+
+/// A fresh directory for the outputs of a test.
+fn work_dir(test_name: &str) -> PathBuf {
+    let work_dir = env::temp_dir().join(format!(
+        "prusti-reports-{}-{}",
+        test_name,
+        std::process::id()
+    ));
+    fs::remove_dir_all(&work_dir).ok();
+    fs::create_dir_all(&work_dir).unwrap();
+    work_dir
+}
+
+/// Verify the file `tests/reports/<name>.rs` with the given arguments and configuration.
+fn run_prusti_rustc(name: &str, work_dir: &Path, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    let source: PathBuf = ["tests", "reports", &format!("{}.rs", name)].iter().collect();
+    let mut cmd = Command::new(get_prusti_binary_path("prusti-rustc"));
+    cmd.arg(&source)
+        .arg("--color=never")
+        .arg("-A")
+        .arg("warnings")
+        .arg("--out-dir")
+        .arg(work_dir)
+        .args(args)
+        .env_remove("PRUSTI_CONFIG")
+        .env("PRUSTI_FULL_COMPILATION", "true")
+        .env("PRUSTI_QUIET", "false")
+        .env("PRUSTI_CHECK_BINARY_OPERATIONS", "false")
+        .env("PRUSTI_LOG_DIR", work_dir.join("log"));
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+    cmd.output().expect("failed to run prusti-rustc")
+}
+
+fn stderr_of(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// The names of the files of a directory, sorted.
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read {:?}: {}", dir, e))
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn trusted_surface_report() {
+    let work_dir = work_dir("trusted-surface");
+    let output = run_prusti_rustc(
+        "trusted-surface",
+        &work_dir,
+        &[],
+        &[("PRUSTI_REPORT_TRUSTED_SURFACE", "true")],
+    );
+    let stderr = stderr_of(&output);
+    assert!(output.status.success(), "verification failed:\n{}", stderr);
+    let report_line = |kind: &str| {
+        stderr
+            .lines()
+            .find(|line| line.starts_with(&format!("[{}]", kind)))
+            .unwrap_or_else(|| panic!("no {} in the report:\n{}", kind, stderr))
+            .to_string()
+    };
+    // The `#[trusted]` procedure is registered where it is defined and where it is called.
+    let trusted_procedure = report_line("trusted procedure");
+    assert!(trusted_procedure.contains("trusted_increment"), "{}", trusted_procedure);
+    assert!(trusted_procedure.contains("trusted-surface.rs:"), "{}", trusted_procedure);
+    assert!(trusted_procedure.ends_with("the body is not verified"), "{}", trusted_procedure);
+    let call = report_line("call to trusted procedure");
+    assert!(call.contains("trusted_increment"), "{}", call);
+    let assumption = report_line("assumption");
+    assert!(assumption.contains("trusted-surface.rs:"), "{}", assumption);
+    // The test configuration does not check the overflows.
+    let configuration = report_line("configuration");
+    assert!(configuration.contains("CHECK_BINARY_OPERATIONS=false"), "{}", configuration);
+    assert!(stderr.contains("item(s) assumed without verification"), "{}", stderr);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn unsupported_fallback_report() {
+    let work_dir = work_dir("unsupported-fallback");
+    // The warnings that are disabled by `run_prusti_rustc` are enabled again.
+    let output = run_prusti_rustc(
+        "unsupported-fallback",
+        &work_dir,
+        &["-W", "warnings"],
+        &[("PRUSTI_REPORT_TRUSTED_SURFACE", "true")],
+    );
+    let stderr = stderr_of(&output);
+    assert!(output.status.success(), "verification failed:\n{}", stderr);
+    // The whole body is trusted, and each unsupported feature is reported as a warning.
+    assert!(
+        stderr.contains("read` is not verified and its contract is assumed"),
+        "{}",
+        stderr
+    );
+    let report_line = stderr
+        .lines()
+        .find(|line| line.starts_with("[unsupported procedure]"))
+        .unwrap_or_else(|| panic!("no unsupported procedure in the report:\n{}", stderr));
+    assert!(report_line.contains("read"), "{}", report_line);
+    assert!(report_line.contains("unsupported-fallback.rs:"), "{}", report_line);
+    assert!(report_line.contains("the body is not verified, because it"), "{}", report_line);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn dump_viper_per_item() {
+    let work_dir = work_dir("dump-viper");
+    let dump_dir = work_dir.join("viper");
+    let output = run_prusti_rustc(
+        "dump-viper",
+        &work_dir,
+        &[&format!("--dump-viper={}", dump_dir.display())],
+        &[],
+    );
+    assert!(output.status.success(), "verification failed:\n{}", stderr_of(&output));
+    let dumped = file_names(&dump_dir.join("dump-viper.rs"));
+    // One program for each verified procedure, `main` included.
+    assert_eq!(dumped.len(), 3, "{:?}", dumped);
+    for (item, file_name) in ["first", "main", "max"].iter().zip(&dumped) {
+        assert!(file_name.contains(item), "{:?}", dumped);
+        assert!(file_name.ends_with(".vpr"), "{:?}", dumped);
+    }
+    let max_program = fs::read_to_string(
+        dump_dir
+            .join("dump-viper.rs")
+            .join(&dumped[2]),
+    )
+    .unwrap();
+    // Each program contains only its own method.
+    assert!(!max_program.contains("$$first"), "{}", max_program);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn cargo_prusti_dump_viper() {
+    let work_dir = work_dir("cargo-dump-viper");
+    let crate_dir = work_dir.join("dumped");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"dumped\"\nversion = \"0.1.0\"\nauthors = []\n",
+    )
+    .unwrap();
+    fs::write(
+        crate_dir.join("src").join("lib.rs"),
+        "pub fn max(a: u32, b: u32) -> u32 {\n    if a > b { a } else { b }\n}\n",
+    )
+    .unwrap();
+    let cargo_prusti_path = env::current_dir()
+        .unwrap()
+        .join(get_prusti_binary_path("cargo-prusti"));
+    let output = Command::new(cargo_prusti_path)
+        .arg("--dump-viper=viper")
+        .current_dir(&crate_dir)
+        .env_remove("PRUSTI_CONFIG")
+        .env("CARGO_TARGET_DIR", work_dir.join("target"))
+        .output()
+        .expect("failed to run cargo-prusti");
+    assert!(output.status.success(), "verification failed:\n{}", stderr_of(&output));
+    // The relative directory is resolved against the directory where cargo-prusti runs.
+    let dumped = file_names(&crate_dir.join("viper").join("lib.rs"));
+    assert_eq!(dumped.len(), 1, "{:?}", dumped);
+    assert!(dumped[0].contains("max"), "{:?}", dumped);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn lazy_predicate_bodies() {
+    let work_dir = work_dir("lazy-predicate-bodies");
+    let dump_dir = work_dir.join("viper");
+    let output = run_prusti_rustc(
+        "lazy-predicate-bodies",
+        &work_dir,
+        &[&format!("--dump-viper={}", dump_dir.display())],
+        &[("PRUSTI_LAZY_PREDICATE_BODIES", "true")],
+    );
+    assert!(output.status.success(), "verification failed:\n{}", stderr_of(&output));
+    let program_dir = dump_dir.join("lazy-predicate-bodies.rs");
+    let increment = file_names(&program_dir)
+        .into_iter()
+        .find(|file_name| file_name.contains("increment"))
+        .expect("no program for increment");
+    let program = fs::read_to_string(program_dir.join(increment)).unwrap();
+    let predicate_line = |name: &str| {
+        program
+            .lines()
+            .find(|line| line.starts_with("predicate ") && line.contains(name))
+            .unwrap_or_else(|| panic!("no predicate for {}:\n{}", name, program))
+            .to_string()
+    };
+    // `Opaque` is only moved, so its predicate is emitted without body.
+    let opaque = predicate_line("Opaque");
+    assert!(!opaque.contains('{'), "{}", opaque);
+    // The field of `Transparent` is read, so its predicate is unfolded.
+    let transparent = predicate_line("Transparent");
+    assert!(transparent.contains('{'), "{}", transparent);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn smt_unsupported_items() {
+    let z3_path = match env::var("Z3_EXE") {
+        Ok(ref path) if Path::new(path).exists() => path.clone(),
+        _ => {
+            eprintln!("Skipping the test of the SMT mode, because Z3_EXE is not set");
+            return;
+        }
+    };
+    let work_dir = work_dir("smt-unsupported");
+    let output = run_prusti_rustc(
+        "smt-unsupported",
+        &work_dir,
+        &[],
+        &[("PRUSTI_SMT_EXPORT", "true"), ("PRUSTI_SMT_SOLVER_PATH", &z3_path)],
+    );
+    let stderr = stderr_of(&output);
+    // The loop of `count` cannot be expressed, so its assertion is not verified.
+    assert!(!output.status.success(), "verification succeeded:\n{}", stderr);
+    assert!(stderr.contains("could not be verified in SMT mode"), "{}", stderr);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn json_message_format_of_a_failure() {
+    let work_dir = work_dir("json-failure");
+    let output = run_prusti_rustc("json-failure", &work_dir, &["--message-format=json"], &[]);
+    assert!(!output.status.success(), "verification succeeded:\n{}", stderr_of(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let messages: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line))
+        })
+        .collect();
+    let errors: Vec<_> = messages
+        .iter()
+        .filter(|message| message["type"] == "error")
+        .collect();
+    assert_eq!(errors.len(), 1, "{}", stdout);
+    let error = errors[0];
+    assert_eq!(error["kind"], "assert.failed:assertion.false", "{}", error);
+    assert_eq!(error["message"], "postcondition might not hold.", "{}", error);
+    assert!(error["pos_id"].is_string(), "{}", error);
+    let span = &error["span"];
+    assert!(
+        span["file_name"].as_str().unwrap().ends_with("json-failure.rs"),
+        "{}",
+        error
+    );
+    assert_eq!(span["line_start"], 3, "{}", error);
+    // The timing of the stages is reported too.
+    assert!(
+        messages.iter().any(|message| message["type"] == "timing"),
+        "{}",
+        stdout
+    );
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn smt_recursive_functions() {
+    let z3_path = match env::var("Z3_EXE") {
+        Ok(ref path) if Path::new(path).exists() => path.clone(),
+        _ => {
+            eprintln!("Skipping the test of the SMT mode, because Z3_EXE is not set");
+            return;
+        }
+    };
+    let work_dir = work_dir("smt-recursive");
+    let output = run_prusti_rustc(
+        "smt-recursive",
+        &work_dir,
+        &[],
+        &[("PRUSTI_SMT_EXPORT", "true"), ("PRUSTI_SMT_SOLVER_PATH", &z3_path)],
+    );
+    let stderr = stderr_of(&output);
+    // The false postconditions cannot be proven from the definitions of the recursive functions,
+    // nor from the contracts of each other, so the functions are reported as not verified.
+    assert!(!output.status.success(), "verification succeeded:\n{}", stderr);
+    assert!(stderr.contains("3 item(s) could not be verified in SMT mode"), "{}", stderr);
+    fs::remove_dir_all(&work_dir).ok();
+}
+
+#[test]
+fn repeated_encoding_sessions() {
+    let work_dir = work_dir("repeated-encoding");
+    // The crate is encoded twice in the same process, and the verification fails with an
+    // internal error if the second encoding session produces a different program.
+    let output = run_prusti_rustc(
+        "repeated-encoding",
+        &work_dir,
+        &[],
+        &[("PRUSTI_CHECK_REPEATED_ENCODING", "true")],
+    );
+    let stderr = stderr_of(&output);
+    assert!(output.status.success(), "verification failed:\n{}", stderr);
+    assert!(!stderr.contains("produced a different program"), "{}", stderr);
+    fs::remove_dir_all(&work_dir).ok();
+}
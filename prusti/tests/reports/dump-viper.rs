@@ -0,0 +1,13 @@
+extern crate prusti_contracts;
+
+#[ensures="result >= a && result >= b"]
+fn max(a: u32, b: u32) -> u32 {
+    if a > b { a } else { b }
+}
+
+#[ensures="result == a"]
+fn first(a: u32, _b: u32) -> u32 {
+    a
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+extern crate prusti_contracts;
+
+#[ensures="result > 0"]
+fn zero() -> u32 {
+    0
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+extern crate prusti_contracts;
+
+struct Inner {
+    value: u32,
+}
+
+struct Opaque {
+    inner: Inner,
+    other: Inner,
+}
+
+struct Transparent {
+    value: u32,
+}
+
+fn forward(x: Opaque) -> Opaque {
+    x
+}
+
+fn swap(x: Opaque, y: Opaque) -> (Opaque, Opaque) {
+    (y, x)
+}
+
+#[ensures="result.value == old(t.value) + 1"]
+fn increment(t: Transparent, o: Opaque) -> Transparent {
+    let _o = forward(o);
+    Transparent { value: t.value + 1 }
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+#[pure]
+fn is_small(counter: &Counter) -> bool {
+    counter.value < 100
+}
+
+#[requires="is_small(counter)"]
+#[ensures="counter.value == old(counter.value) + 1"]
+fn increment(counter: &mut Counter) {
+    counter.value += 1;
+}
+
+#[ensures="result == n"]
+fn count(n: u32) -> u32 {
+    let mut i = 0;
+    #[invariant="i <= n"]
+    while i < n {
+        i += 1;
+    }
+    i
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[ensures="false"]
+fn diverge(x: i32) -> i32 {
+    diverge(x) + 1
+}
+
+#[pure]
+#[ensures="false"]
+fn is_even(x: u32) -> bool {
+    if x == 0 { true } else { is_odd(x - 1) }
+}
+
+#[pure]
+#[ensures="false"]
+fn is_odd(x: u32) -> bool {
+    if x == 0 { false } else { is_even(x - 1) }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate prusti_contracts;
+
+#[pure]
+fn is_positive(x: i32) -> bool {
+    x > 0
+}
+
+fn count(n: u32) -> u32 {
+    let mut i = 0;
+    while i < n {
+        i += 1;
+    }
+    assert!(i == n);
+    i
+}
+
+fn main() {}
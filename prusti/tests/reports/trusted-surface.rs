@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+#[trusted]
+#[ensures="result >= x"]
+fn trusted_increment(x: u32) -> u32 {
+    x + 1
+}
+
+#[ensures="result >= x"]
+fn call_trusted(x: u32) -> u32 {
+    prusti_assume!(x < 100);
+    trusted_increment(x)
+}
+
+fn main() {}
@@ -24,6 +24,19 @@ fn get_prusti_rustc_path() -> PathBuf {
     panic!("Could not find the prusti-rustc binary to be used in tests");
 }
 
+/// The tests that use Carbon only run if Boogie is installed.
+fn boogie_installed() -> bool {
+    vec![var("BOOGIE_PATH").ok(), var("BOOGIE_EXE").ok()]
+        .into_iter()
+        .filter_map(|path| path)
+        .any(|path| PathBuf::from(path).exists())
+}
+
+/// The SMT solver used by the tests of the SMT mode, if Z3 is installed.
+fn z3_path() -> Option<String> {
+    var("Z3_EXE").ok().filter(|path| PathBuf::from(path).exists())
+}
+
 fn run_no_verification(group_name: &str) {
     set_var("PRUSTI_FULL_COMPILATION", "true");
 
@@ -120,6 +133,70 @@ fn run_verification(group_name: &str) {
         set_var("PRUSTI_CHECK_BINARY_OPERATIONS", "false");
     }
 
+    let path: PathBuf = ["tests", group_name, "pass-release"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_VERIFICATION_PROFILE", "release");
+        run_tests(&config);
+        remove_var("PRUSTI_VERIFICATION_PROFILE");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-split-obligations"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_SPLIT_OBLIGATIONS", "true");
+        run_tests(&config);
+        set_var("PRUSTI_SPLIT_OBLIGATIONS", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-parallel"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_NUM_THREADS", "2");
+        run_tests(&config);
+        set_var("PRUSTI_NUM_THREADS", "1");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-carbon"].iter().collect();
+    if path.exists() && boogie_installed() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_VIPER_BACKEND", "Carbon");
+        run_tests(&config);
+        remove_var("PRUSTI_VIPER_BACKEND");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-smt"].iter().collect();
+    if let (true, Some(z3_path)) = (path.exists(), z3_path()) {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_SMT_EXPORT", "true");
+        set_var("PRUSTI_SMT_SOLVER_PATH", z3_path);
+        run_tests(&config);
+        set_var("PRUSTI_SMT_EXPORT", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-infer-purity"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_INFER_PURITY", "true");
+        run_tests(&config);
+        set_var("PRUSTI_INFER_PURITY", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "pass-lookup-triggers"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::RunPass;
+        config.src_base = path;
+        set_var("PRUSTI_USE_LOOKUP_TRIGGERS", "true");
+        run_tests(&config);
+        set_var("PRUSTI_USE_LOOKUP_TRIGGERS", "false");
+    }
+
     let path: PathBuf = ["tests", group_name, "fail"].iter().collect();
     if path.exists() {
         config.mode = common::Mode::CompileFail;
@@ -127,6 +204,33 @@ fn run_verification(group_name: &str) {
         run_tests(&config);
     }
 
+    let path: PathBuf = ["tests", group_name, "fail-infer-purity"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_INFER_PURITY", "true");
+        run_tests(&config);
+        set_var("PRUSTI_INFER_PURITY", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "fail-split-obligations"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_SPLIT_OBLIGATIONS", "true");
+        run_tests(&config);
+        set_var("PRUSTI_SPLIT_OBLIGATIONS", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "fail-parallel"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_NUM_THREADS", "2");
+        run_tests(&config);
+        set_var("PRUSTI_NUM_THREADS", "1");
+    }
+
     let path: PathBuf = ["tests", group_name, "fail-overflow"].iter().collect();
     if path.exists() {
         config.mode = common::Mode::CompileFail;
@@ -135,6 +239,34 @@ fn run_verification(group_name: &str) {
         run_tests(&config);
         set_var("PRUSTI_CHECK_BINARY_OPERATIONS", "false");
     }
+
+    let path: PathBuf = ["tests", group_name, "fail-unreachability"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_CHECK_UNREACHABILITY", "true");
+        run_tests(&config);
+        set_var("PRUSTI_CHECK_UNREACHABILITY", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "fail-smt"].iter().collect();
+    if let (true, Some(z3_path)) = (path.exists(), z3_path()) {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_SMT_EXPORT", "true");
+        set_var("PRUSTI_SMT_SOLVER_PATH", z3_path);
+        run_tests(&config);
+        set_var("PRUSTI_SMT_EXPORT", "false");
+    }
+
+    let path: PathBuf = ["tests", group_name, "fail-both-profiles"].iter().collect();
+    if path.exists() {
+        config.mode = common::Mode::CompileFail;
+        config.src_base = path;
+        set_var("PRUSTI_VERIFICATION_PROFILE", "both");
+        run_tests(&config);
+        remove_var("PRUSTI_VERIFICATION_PROFILE");
+    }
 }
 
 #[test]
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+/// Overflows are errors only under the debug profile.
+fn add(x: u32, y: u32) -> u32 {
+    x + y //~ ERROR [debug profile] assertion might fail with "attempt to add with overflow"
+}
+
+/// Failing `debug_assert!`s are errors only under the debug profile.
+fn check(x: u32) {
+    debug_assert!(x > 10); //~ ERROR [debug profile] the asserted expression might not hold
+}
+
+/// Failing `assert!`s are errors under both profiles.
+fn check_always(x: u32) {
+    assert!(x > 10); //~ ERROR [debug profile] the asserted expression might not hold
+    //~^ ERROR [release profile] the asserted expression might not hold
+}
+
+fn main() {}
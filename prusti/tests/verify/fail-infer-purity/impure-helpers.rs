@@ -0,0 +1,25 @@
+extern crate prusti_contracts;
+
+fn double(a: u32) -> u32 {
+    a + a
+}
+
+/// Opting out of the purity inference.
+#[impure]
+fn triple(a: u32) -> u32 {
+    a + a + a
+}
+
+#[requires="a < 1000"]
+#[ensures="result == double(a) + 1"] //~ ERROR postcondition might not hold
+fn test_double(a: u32) -> u32 {
+    double(a)
+}
+
+#[requires="a < 1000"]
+#[ensures="result >= a"] //~ ERROR postcondition might not hold
+fn test_triple(a: u32) -> u32 {
+    triple(a)
+}
+
+fn main() {}
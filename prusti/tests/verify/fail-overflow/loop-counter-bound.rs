@@ -0,0 +1,13 @@
+extern crate prusti_contracts;
+
+fn count(n: u32) -> u32 {
+    let mut i = 0;
+    let mut j = 0;
+    while j < n { //~ HELP consider bounding `i` with a loop invariant
+        i += 1; //~ ERROR assertion might fail with "attempt to add with overflow"
+        j += 1;
+    }
+    i
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+// The errors of the methods verified by different verifiers are reported in the order of their
+// positions.
+
+#[pure]
+fn max(a: u32, b: u32) -> u32 {
+    if a > b { a } else { b }
+}
+
+#[ensures="result > a"] //~ ERROR postcondition might not hold
+fn call_max(a: u32, b: u32) -> u32 {
+    max(a, b)
+}
+
+fn first_assertion(x: u32) {
+    assert!(x > 10); //~ ERROR assertion might fail
+}
+
+#[ensures="result == x"] //~ ERROR postcondition might not hold
+fn increment(x: u32) -> u32 {
+    if x < 100 { x + 1 } else { x }
+}
+
+fn second_assertion(x: u32) {
+    assert!(x > 20); //~ ERROR assertion might fail
+}
+
+fn main() {}
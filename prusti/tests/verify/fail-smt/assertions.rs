@@ -0,0 +1,35 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[ensures="result > 0"] //~ ERROR postcondition might not hold
+fn abs(x: i32) -> i32 {
+    if x >= 0 {
+        x
+    } else {
+        -x
+    }
+}
+
+#[pure]
+#[ensures="result == 5"] //~ ERROR postcondition might not hold
+fn three() -> i32 {
+    3
+}
+
+// The contract of a caller is not assumed when checking the callee.
+#[pure]
+#[ensures="result == 5"]
+fn three_via_call() -> i32 {
+    three()
+}
+
+#[requires="x < 0 && x > -10"]
+fn negative_division(x: i32) {
+    assert!(x / 2 < -4); //~ ERROR the asserted expression might not hold
+}
+
+fn unknown_argument(x: i32) {
+    assert!(x > 0); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
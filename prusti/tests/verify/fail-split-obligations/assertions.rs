@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+/// Both failing assertions are reported, because each one is verified separately.
+fn two_failures(x: u32) {
+    assert!(x > 10); //~ ERROR assertion might fail
+    assert!(x > 20); //~ ERROR assertion might fail
+    assert!(x > 5);
+}
+
+#[ensures="result > x"] //~ ERROR postcondition might not hold
+#[ensures="result >= x"]
+fn wrong_postcondition(x: u32) -> u32 {
+    assert!(x == x);
+    x
+}
+
+/// Both failing postconditions are reported, because each conjunct is verified separately.
+#[ensures="result > x"] //~ ERROR postcondition might not hold
+#[ensures="result < x"] //~ ERROR postcondition might not hold
+fn two_wrong_postconditions(x: u32) -> u32 {
+    x
+}
+
+fn main() {}
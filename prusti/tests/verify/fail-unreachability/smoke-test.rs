@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+#[requires="x > 10 && x < 5"]
+fn contradictory_precondition(x: u32) -> u32 { x } //~ WARN the precondition of the procedure is unsatisfiable
+//~| WARN the end of the procedure is unreachable
+
+fn unreachable_branch(x: u32) -> u32 {
+    if x > 10 { if x < 5 { 1 } else { 2 } } else { 3 } //~ WARN this branch is unreachable
+}
+
+fn reachable_branches(x: u32) -> u32 {
+    if x > 10 {
+        1
+    } else {
+        2
+    }
+}
+
+fn diverging() -> ! {
+    panic!() //~ ERROR panic!(..) statement might panic
+}
+
+#[trusted]
+fn main() {}
@@ -0,0 +1,14 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+fn assumption_is_not_stronger(x: u32) {
+    prusti_assume!(x > 10);
+    assert!(x > 20); //~ ERROR the asserted expression might not hold
+}
+
+fn assumption_holds_only_after(x: u32) {
+    assert!(x > 10); //~ ERROR the asserted expression might not hold
+    prusti_assume!(x > 10);
+}
+
+fn main() {}
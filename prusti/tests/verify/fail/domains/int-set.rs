@@ -0,0 +1,39 @@
+#![allow(unused_variables)]
+
+extern crate prusti_contracts;
+
+#[derive(Clone, Copy)]
+#[domain]
+struct IntSet;
+
+impl IntSet {
+    #[pure]
+    #[ensures="forall x: i32 :: !result.contains(x)"]
+    fn empty() -> IntSet {
+        unimplemented!()
+    }
+
+    #[pure]
+    fn contains(self, x: i32) -> bool {
+        unimplemented!()
+    }
+
+    #[pure]
+    #[ensures="result.contains(x)"]
+    fn insert(self, x: i32) -> IntSet {
+        unimplemented!()
+    }
+}
+
+fn lost_element(x: i32, y: i32) {
+    let set = IntSet::empty().insert(x).insert(y);
+    // The axiom of `insert` does not state that the other elements are kept.
+    assert!(set.contains(x)); //~ ERROR the asserted expression might not hold
+}
+
+#[ensures="!result.contains(x)"] //~ ERROR postcondition might not hold
+fn wrong_post(x: i32) -> IntSet {
+    IntSet::empty().insert(x)
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+#[invariant="self.level <= 100"]
+struct Gauge {
+    level: u32,
+}
+
+impl Drop for Gauge {
+    #[ensures="self.level == 1"] //~ ERROR postcondition might not hold
+    fn drop(&mut self) {
+        self.level = 0;
+    }
+}
+
+fn fill(level: u32) {
+    let _gauge = Gauge { level };
+} //~ ERROR the type invariant might not hold when the value is dropped
+
+fn main() {}
@@ -0,0 +1,32 @@
+//! `let ... else` is not available in this version of Rust, so these tests
+//! use its desugaring: `let x = if let Pattern(x) = e { x } else { diverge };`
+
+extern crate prusti_contracts;
+
+pub enum Token {
+    Number(u32),
+    Plus,
+    Eof,
+}
+
+#[pure]
+pub fn is_number(token: &Token) -> bool {
+    match token {
+        Token::Number(_) => true,
+        _ => false,
+    }
+}
+
+#[ensures="is_number(token)"] //~ ERROR postcondition might not hold
+pub fn number_or_zero(token: &Token) -> u32 {
+    let n = if let Token::Number(n) = *token { n } else { return 0; };
+    n
+}
+
+pub fn wrong_branch(token: &Token) -> u32 {
+    let n = if let Token::Plus = *token { 1 } else { return 0; };
+    assert!(is_number(token)); //~ ERROR the asserted expression might not hold
+    n
+}
+
+fn main() {}
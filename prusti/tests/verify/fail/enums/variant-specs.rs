@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+pub enum Shape {
+    Circle(u32),
+    Square(u32),
+    Empty,
+}
+
+#[ensures="matches!(result, Shape::Circle(_))"] //~ ERROR postcondition might not hold
+pub fn circle(side: u32) -> Shape {
+    Shape::Square(side)
+}
+
+#[requires="is_variant!(shape, Shape::Circle)"]
+pub fn take_circle(shape: Shape) {}
+
+pub fn test(side: u32) {
+    take_circle(Shape::Empty); //~ ERROR precondition might not hold
+}
+
+fn main() {}
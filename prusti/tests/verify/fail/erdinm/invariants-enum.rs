@@ -0,0 +1,23 @@
+extern crate prusti_contracts;
+
+// postcondition (result) assert of the fields of the active variant
+
+#[invariant="self.value <= 100"]
+struct Percentage {
+    value: u8,
+}
+
+enum Reading {
+    Missing,
+    Measured(Percentage),
+}
+
+impl Reading {
+    fn new(value: u8) -> Self { //~ ERROR type invariants
+        Reading::Measured(Percentage {
+            value: value,
+        })
+    }
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+extern crate prusti_contracts;
+
+#[requires="0 <= n"]
+#[ensures="result == n + 1"] //~ ERROR postcondition might not hold
+fn find(n: i32) -> i32 {
+    let mut i = 0;
+    let res = loop {
+        if i == n {
+            break i;
+        }
+        i += 1;
+    };
+    res
+}
+
+fn count_down(n: u32) -> u32 {
+    let mut opt = Some(n);
+    let mut steps = 0;
+    while let Some(k) = opt {
+        steps += 1;
+        opt = if k > 1 { Some(k - 1) } else { None };
+    }
+    match opt {
+        Some(_) => {}
+        None => unreachable!() //~ ERROR unreachable!(..) statement might be reachable
+    }
+    steps
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+#![feature(nll)]
+
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+impl Point {
+    fn x_mut(&mut self) -> &mut u32 {
+        &mut self.x
+    }
+
+    fn x_mut_via_call(&mut self) -> &mut u32 {
+        self.x_mut()
+    }
+}
+
+fn set_x(p: &mut Point) {
+    *p.x_mut() = 5;
+    assert!(p.x == 6);  //~ ERROR the asserted expression might not hold
+}
+
+fn set_x_via_call(p: &mut Point) {
+    let old_y = p.y;
+    *p.x_mut_via_call() = 5;
+    assert!(p.y == old_y);
+    assert!(p.x == 6);  //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
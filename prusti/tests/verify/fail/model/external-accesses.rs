@@ -0,0 +1,30 @@
+extern crate prusti_contracts;
+
+mod counter {
+    #[invariant="self.count == self.value"]
+    pub struct Counter {
+        value: u32,
+        #[model]
+        pub count: u32,
+    }
+
+    impl Counter {
+        #[ensures="result.count == 0"]
+        pub fn new() -> Self {
+            Counter { value: 0, count: 0 }
+        }
+    }
+}
+
+use counter::Counter;
+
+#[ensures="result == counter.count"]
+fn read(counter: &Counter) -> u32 {
+    counter.count //~ ERROR can only be read in specifications outside of its module
+}
+
+fn reset(counter: &mut Counter) {
+    counter.count = 0; //~ ERROR can only be updated in its module
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+extern crate prusti_contracts;
+
+mod counter {
+    #[invariant="self.count == self.value"]
+    pub struct Counter {
+        value: u32,
+        #[model]
+        pub count: u32,
+    }
+
+    impl Counter {
+        #[requires="self.count < 1000"]
+        #[ensures="self.count == old(self.count) + 1"]
+        pub fn increment(&mut self) { //~ ERROR type invariants
+            self.count += 1;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[ensures="result.x == old(q.x)"] //~ ERROR postcondition might not hold
+fn move_out_and_refill(mut p: Box<Point>, q: Point) -> Point {
+    let old_point = *p;
+    *p = q;
+    old_point
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+#![no_std]
+
+extern crate prusti_contracts;
+
+fn test(x: u32) {
+    if x == 0 {
+        panic!();  //~ ERROR panic!(..) statement might panic
+    }
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+fn divide(x: u32, y: u32) -> u32 {
+    assert!(y != 0, "the divisor is zero"); //~ ERROR the asserted expression might not hold: the divisor is zero
+    x / y
+}
+
+fn check(x: u32) {
+    if x > 10 {
+        panic!("x is too large"); //~ ERROR panic!(..) statement might panic: x is too large
+    }
+}
+
+fn classify(x: u32) -> u32 {
+    match x {
+        0 => 1,
+        _ => unreachable!("x is not zero"), //~ ERROR unreachable!(..) statement might be reachable: x is not zero
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+use std::cell::RefCell;
+
+fn mutable_while_shared(cell: &RefCell<u32>) {
+    let _reader = cell.borrow();
+    let _writer = cell.borrow_mut();  //~ ERROR the RefCell might already be borrowed
+}
+
+fn shared_while_mutable(cell: &RefCell<u32>) {
+    let _writer = cell.borrow_mut();
+    let _reader = cell.borrow();  //~ ERROR the RefCell might already be borrowed
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate prusti_contracts;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    x + x
+}
+
+#[ensures="forall(|i: i32| 0 <= i ==> double(i) > i)"] //~ ERROR postcondition
+fn test_forall() {}
+
+#[requires="exists(|i: i32| x == double(i))"]
+#[ensures="result != 2"] //~ ERROR postcondition
+fn test_exists(x: i32) -> i32 {
+    x
+}
+
+fn main() {}
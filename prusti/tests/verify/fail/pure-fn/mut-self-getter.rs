@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    #[pure]
+    fn reset_and_get(&mut self) -> u32 {
+        self.value = 0; //~ ERROR must not modify the memory behind a reference
+        self.value
+    }
+}
+
+#[pure]
+fn overwrite(target: &mut u32, value: u32) -> u32 {
+    *target = value; //~ ERROR must not modify the memory behind a reference
+    value
+}
+
+fn main() {}
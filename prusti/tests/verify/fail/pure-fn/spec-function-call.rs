@@ -0,0 +1,13 @@
+extern crate prusti_contracts;
+
+#[pure_spec]
+#[ensures="result >= 0"]
+fn non_negative(x: i32) -> i32 {
+    unimplemented!()
+}
+
+fn test(x: i32) -> i32 {
+    non_negative(x) //~ ERROR can only be used in specifications
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate prusti_contracts;
+
+#[pure_spec]
+#[ensures="result >= 0"]
+fn non_negative(x: i32) -> i32 {
+    unimplemented!()
+}
+
+#[ensures="non_negative(x) > 0"] //~ ERROR postcondition might not hold
+fn test(x: i32) {}
+
+fn main() {}
@@ -0,0 +1,16 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[requires="n >= 0"]
+#[decreases="n"]
+fn count_up(n: i32) -> i32 {
+    if n > 100 { n } else { count_up(n + 1) } //~ ERROR termination measure might not decrease
+}
+
+#[pure]
+#[decreases="n"]
+fn negative(n: i32) -> i32 {
+    if n == 0 { 0 } else { negative(n - 1) } //~ ERROR termination measure might not decrease
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+extern crate prusti_contracts;
+
+/// A model of the integer square root. The implementation is not verified.
+#[trusted]
+#[pure]
+#[requires="x >= 0"]
+#[ensures="result >= 0 && result <= x"]
+fn isqrt(x: i64) -> i64 {
+    let mut r = 0;
+    while (r + 1) * (r + 1) <= x {
+        r += 1;
+    }
+    r
+}
+
+#[requires="n >= 0"]
+#[ensures="result < n"] //~ ERROR postcondition might not hold
+fn bounded(n: i64) -> i64 {
+    isqrt(n)
+}
+
+fn negative() -> i64 {
+    isqrt(-1) //~ ERROR precondition of pure function call might not hold
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[ensures="result.0 < result.1"] //~ ERROR postcondition
+fn min_max(a: i32, b: i32) -> (i32, i32) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[ensures="min_max(3, 1).0 == 3"] //~ ERROR postcondition
+fn test_specs() {}
+
+fn test_body(a: i32, b: i32) {
+    let (min, max) = min_max(a, b);
+    assert!(min < max); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec_frac(p, len, 1, 2) && 0 < len"]
+#[ensures="deref_spec_frac(p, len, 1, 2)"]
+fn set_first(p: *mut u32, len: usize, value: u32) {
+    unsafe {
+        *p = value; //~ ERROR the access through the raw pointer might not be in a memory region owned by a `deref_spec`
+    }
+}
+
+#[requires="deref_spec_frac(p, len, 1, 2)"]
+#[ensures="deref_spec(p, len)"] //~ ERROR postcondition might not hold
+fn widen(p: *const u32, len: usize) {}
+
+fn main() {}
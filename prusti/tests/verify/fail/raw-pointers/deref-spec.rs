@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec(p, len)"]
+#[ensures="deref_spec(p, len)"]
+fn get_last(p: *const u32, len: usize) -> u32 {
+    unsafe { *p.add(len) } //~ ERROR the access through the raw pointer might not be in a memory region owned by a `deref_spec`
+}
+
+#[requires="deref_spec(p, len) && 0 < len"]
+#[ensures="deref_spec(p, len)"]
+fn set_first(p: *mut u32, len: usize, value: u32) {
+    unsafe {
+        *p = value;
+    }
+}
+
+#[requires="0 < len"]
+fn set_unowned(p: *mut u32, len: usize, value: u32) {
+    unsafe {
+        *p = value; //~ ERROR the access through the raw pointer might not be in a memory region owned by a `deref_spec`
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec(p, len) && i < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="deref_value(p, i) == old(deref_value(p, i))"] //~ ERROR postcondition might not hold
+fn set(p: *mut u32, len: usize, i: usize, value: u32) {
+    unsafe {
+        *p.add(i) = value;
+    }
+}
+
+#[requires="deref_spec(p, len) && 0 < len"]
+#[ensures="deref_spec(p, len)"]
+fn check_first(p: *const u32, len: usize) {
+    let first = unsafe { *p };
+    assert!(first == 0); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+#[predicate]
+fn pair(p: *mut u32) -> bool {
+    deref_spec(p, 2)
+}
+
+/// The predicate is never unfolded automatically.
+#[requires="pair(p)"]
+#[ensures="pair(p)"]
+fn set_without_unfold(p: *mut u32) {
+    unsafe {
+        *p = 0; //~ ERROR the access through the raw pointer might not be in a memory region owned by a `deref_spec`
+    }
+}
+
+#[requires="deref_spec(p, 1)"]
+fn fold_too_small(p: *mut u32) {
+    fold!(pair(p)); //~ ERROR the body of the folded predicate might not hold
+}
+
+fn unfold_unheld(p: *mut u32) {
+    unfold!(pair(p)); //~ ERROR the unfolded predicate might not be held
+}
+
+/// The predicate is never folded automatically.
+#[requires="pair(p)"]
+#[ensures="pair(p)"] //~ ERROR postcondition might not hold
+fn missing_fold(p: *mut u32) {
+    unfold!(pair(p));
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+mod counter {
+    pub struct Counter {
+        #[readonly]
+        pub value: u32,
+    }
+}
+
+use counter::Counter;
+
+fn reset(counter: &mut Counter) {
+    counter.value = 0; //~ ERROR is read-only outside of its module
+}
+
+fn borrow_value(counter: &mut Counter) {
+    let value = &mut counter.value; //~ ERROR is read-only outside of its module
+    *value = 1;
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate prusti_contracts;
+
+#[assert_timeout="soon"]
+fn invalid_timeout() {} //~ ERROR the value of `assert_timeout` must be a non-negative integer
+
+#[z3_random_seed="7"]
+#[ensures="result > x"] //~ ERROR postcondition might not hold
+fn wrong_postcondition(x: u32) -> u32 {
+    x
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+// A custom equality, which `===` ignores.
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x
+    }
+}
+
+#[ensures="result === *p"] //~ ERROR postcondition might not hold
+fn project(p: &Point) -> Point {
+    Point { x: p.x, y: 0 }
+}
+
+#[requires="a.x == b.x"]
+#[ensures="a === b"] //~ ERROR postcondition might not hold
+fn same_first_coordinate(a: &Point, b: &Point) {}
+
+#[requires="a.x == b.x"]
+#[ensures="(*a === *b && a.x > 0) || a.x == 0"] //~ ERROR postcondition might not hold
+fn same_and_positive(a: &Point, b: &Point) {}
+
+fn main() {}
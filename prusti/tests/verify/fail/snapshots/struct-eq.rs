@@ -0,0 +1,49 @@
+#![allow(unused_variables)]
+
+extern crate prusti_contracts;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[pure]
+fn same_point(a: &Point, b: &Point) -> bool {
+    a == b
+}
+
+#[requires="a.x == b.x"]
+#[ensures="result"] //~ ERROR postcondition might not hold
+fn equal_first_coordinate(a: &Point, b: &Point) -> bool {
+    same_point(a, b)
+}
+
+#[ensures="result == *p"] //~ ERROR postcondition might not hold
+fn swap_coordinates(p: &Point) -> Point {
+    Point { x: p.y, y: p.x }
+}
+
+/// A domain whose `PartialEq` is not the equality of its values.
+#[derive(Clone, Copy)]
+#[domain]
+struct Parity;
+
+impl PartialEq for Parity {
+    fn eq(&self, other: &Parity) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Tagged {
+    tag: Parity,
+    value: u32,
+}
+
+#[ensures="result"] //~ ERROR postcondition might not hold
+fn custom_domain_eq(t: &Tagged) -> bool {
+    *t == *t
+}
+
+fn main() {}
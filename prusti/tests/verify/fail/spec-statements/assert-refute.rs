@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+fn assertion_might_not_hold(x: u32) {
+    prusti_assert!(x > 10); //~ ERROR the asserted expression might not hold
+}
+
+fn assertion_holds_only_after(x: u32) {
+    prusti_assert!(x == 3); //~ ERROR the asserted expression might not hold
+    prusti_assume!(x == 3);
+}
+
+fn refutation_holds(x: u32) {
+    if x > 10 {
+        prusti_refute!(x > 5); //~ ERROR the refuted expression holds in all the executions that reach this point
+    }
+}
+
+#[requires="x > 10 && x < 5"]
+fn contradictory_precondition(x: u32) {
+    prusti_refute!(false); //~ ERROR the refuted expression holds in all the executions that reach this point
+}
+
+fn refutation_does_not_hide_errors(x: u32) {
+    prusti_refute!(x == 0);
+    assert!(x > 0); //~ ERROR the asserted expression might not hold
+}
+
+fn refutation_holds_in_loop(n: u32) {
+    let mut i = 0;
+    #[invariant="i <= n"]
+    while i < n {
+        prusti_refute!(i < n); //~ ERROR the refuted expression holds in all the executions that reach this point
+        i += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+#[predicate]
+fn pair(p: *mut u32) -> bool {
+    deref_spec(p, 2)
+}
+
+fn test_executable(p: *mut u32) -> bool {
+    pair(p) //~ ERROR can only be used in specifications
+}
+
+#[requires="x > 0"]
+fn test_not_predicate(x: u32) {
+    fold!(x > 0); //~ ERROR the argument of `fold!` must be an application of a `#[predicate]` function
+}
+
+fn main() {}
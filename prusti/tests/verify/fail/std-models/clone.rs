@@ -0,0 +1,17 @@
+extern crate prusti_contracts;
+
+#[derive(Clone)]
+struct View<'a> {
+    value: &'a u32,
+    count: u32,
+}
+
+fn clone_view(value: &u32) {
+    let view = View { value, count: 0 };
+    let copy = view.clone();
+    assert!(*copy.value == *value);
+    // The owned fields of the clone are fresh.
+    assert!(copy.count == 0); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
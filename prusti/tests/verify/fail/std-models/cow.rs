@@ -0,0 +1,25 @@
+extern crate prusti_contracts;
+
+use std::borrow::Cow;
+
+#[pure]
+fn is_owned(c: &Cow<u32>) -> bool {
+    match *c {
+        Cow::Borrowed(_) => false,
+        Cow::Owned(_) => true,
+    }
+}
+
+#[ensures="!is_owned(c)"] //~ ERROR postcondition might not hold
+fn make_owned(c: &mut Cow<u32>) {
+    let content = c.to_mut();
+    *content = 42;
+}
+
+fn copy_on_write(value: &u32) {
+    let mut c = Cow::Borrowed(value);
+    c.to_mut();
+    assert!(!is_owned(&c)); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
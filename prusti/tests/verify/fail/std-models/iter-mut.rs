@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+fn first_exists(v: &mut Vec<u32>) {
+    let mut it = v.iter_mut();
+    assert!(it.next().is_some()); //~ ERROR the asserted expression might not hold
+}
+
+fn sorted_pair(v: &mut Vec<u32>) {
+    let mut it = v.iter_mut();
+    if let Some(a) = it.next() {
+        if let Some(b) = it.next() {
+            assert!(*a <= *b); //~ ERROR the asserted expression might not hold
+        }
+    }
+}
+
+#[ensures="v.len() == old(v.len()) + 1"] //~ ERROR postcondition might not hold
+fn reset(v: &mut Vec<u32>) {
+    for x in v.iter_mut() {
+        *x = 0;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+extern crate prusti_contracts;
+
+use std::sync::{Mutex, RwLock};
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    #[pure]
+    #[lock_invariant]
+    fn is_even(&self) -> bool {
+        self.value % 2 == 0
+    }
+}
+
+fn new_counter() -> Mutex<Counter> {
+    Mutex::new(Counter { value: 1 }) //~ ERROR precondition might not hold
+}
+
+fn increment(counter: &Mutex<Counter>) {
+    let mut guard = counter.lock().unwrap();
+    guard.value += 1; //~ ERROR obligation might not hold on borrow expiry
+}
+
+#[ensures="result == 0"] //~ ERROR postcondition might not hold
+fn read(counter: &RwLock<Counter>) -> u32 {
+    let guard = counter.read().unwrap();
+    guard.value
+}
+
+fn main() {}
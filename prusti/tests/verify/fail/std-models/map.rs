@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+use std::collections::HashMap;
+
+#[ensures="result.len() == 2"] //~ ERROR postcondition might not hold
+fn same_key() -> HashMap<u32, u32> {
+    let mut map = HashMap::new();
+    map.insert(1, 10);
+    map.insert(1, 20);
+    map
+}
+
+fn missing_key(map: &HashMap<u32, u32>) -> u32 {
+    map[&1] //~ ERROR precondition might not hold
+}
+
+fn overwritten() {
+    let mut map = HashMap::new();
+    map.insert(1, 10);
+    map.insert(1, 20);
+    assert!(map[&1] == 10); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate prusti_contracts;
+
+fn get(x: Option<u32>) -> u32 {
+    x.unwrap() //~ ERROR precondition might not hold
+}
+
+#[requires="x.is_err()"]
+fn get_ok(x: Result<u32, bool>) -> u32 {
+    x.unwrap() //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+fn some_any(v: &Vec<u32>) {
+    assert!(v.iter().any(|x| *x > 3)); //~ ERROR the asserted expression might not hold
+}
+
+fn first_zero(v: &Vec<u32>) {
+    if let Some(p) = v.iter().position(|x| *x == 0) {
+        assert!(v[p] == 1); //~ ERROR the asserted expression might not hold
+    }
+}
+
+fn find_large(v: &Vec<u32>) {
+    if let Some(x) = v.iter().find(|x| **x > 3) {
+        assert!(*x > 4); //~ ERROR the asserted expression might not hold
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+extern crate prusti_contracts;
+
+#[ensures="result == b.len()"] //~ ERROR postcondition might not hold
+fn different_len(a: &String, b: &String) -> usize {
+    a.len()
+}
+
+fn literal() {
+    let s = "abc";
+    assert!(s.len() == 2); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
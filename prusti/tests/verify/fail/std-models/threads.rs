@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+use std::thread;
+
+/// The result of a thread is unknown, since closures have no specifications.
+#[ensures="result == 2"] //~ ERROR postcondition might not hold
+fn join_result() -> u32 {
+    let handle = thread::spawn(move || 2);
+    match handle.join() {
+        Ok(value) => value,
+        Err(_) => 2,
+    }
+}
+
+/// The thread might have panicked.
+fn unwrap_join() -> u32 {
+    let handle = thread::spawn(move || 2);
+    handle.join().unwrap() //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+extern crate prusti_contracts;
+
+struct ParseError {
+    value: u32,
+}
+
+#[ensures="result.is_ok() == (x < 100)"]
+fn check_small(x: u32) -> Result<u32, ParseError> {
+    if x < 100 {
+        Ok(x)
+    } else {
+        Err(ParseError { value: x })
+    }
+}
+
+#[ensures="result.is_ok()"] //~ ERROR postcondition might not hold
+fn check_both(x: u32, y: u32) -> Result<u32, ParseError> {
+    let a = check_small(x)?;
+    let b = check_small(y)?;
+    Ok(a + b)
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+extern crate prusti_contracts;
+
+fn swap_out_of_bounds() {
+    let mut v = Vec::new();
+    v.push(1u32);
+    v.swap(0, 1); //~ ERROR precondition might not hold
+}
+
+fn search_unsorted(v: &Vec<u32>, x: u32) -> bool {
+    v.binary_search(&x).is_ok() //~ ERROR precondition might not hold
+}
+
+#[ensures="v.len() == old(v.len()) + 1"] //~ ERROR postcondition might not hold
+fn sort(v: &mut Vec<i32>) {
+    v.sort();
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+#[ensures="result.len() == 1"] //~ ERROR postcondition might not hold
+fn two_elements() -> Vec<u32> {
+    let mut v = Vec::new();
+    v.push(1);
+    v.push(2);
+    v
+}
+
+fn remove_last(v: &mut Vec<u32>) -> u32 {
+    v.pop().unwrap() //~ ERROR precondition might not hold
+}
+
+fn main() {}
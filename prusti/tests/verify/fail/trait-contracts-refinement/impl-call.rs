@@ -0,0 +1,42 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[requires="x < 100"]
+    #[ensures="result >= x"]
+    fn next(&self, x: u32) -> u32;
+}
+
+struct Exact;
+
+struct Inherited;
+
+impl Counter for Exact {
+    #[requires="x < 1000"]
+    #[ensures="result == x + 1"]
+    fn next(&self, x: u32) -> u32 {
+        x + 1
+    }
+}
+
+impl Counter for Inherited {
+    fn next(&self, x: u32) -> u32 {
+        x
+    }
+}
+
+fn violate_refined_precondition() {
+    let c = Exact;
+    c.next(1000); //~ ERROR precondition might not hold
+}
+
+fn violate_inherited_precondition() {
+    let c = Inherited;
+    c.next(100); //~ ERROR precondition might not hold
+}
+
+fn use_trait_contract<T: Counter>(c: &T) {
+    let r = c.next(10);
+    assert!(r == 11); //~ ERROR the asserted expression might not hold
+}
+
+fn main() {}
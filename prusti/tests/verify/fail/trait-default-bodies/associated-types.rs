@@ -0,0 +1,17 @@
+extern crate prusti_contracts;
+
+trait Container {
+    type Item;
+
+    #[pure]
+    fn len(&self) -> usize;
+
+    #[requires="self.len() > 0"]
+    fn first(&self) -> &Self::Item;
+
+    fn peek(&self) -> &Self::Item {
+        self.first() //~ ERROR precondition might not hold
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[pure]
+    fn count(&self) -> usize;
+
+    #[ensures="result"] //~ ERROR postcondition might not hold
+    fn is_zero(&self) -> bool {
+        self.count() == 0
+    }
+}
+
+fn main() {}
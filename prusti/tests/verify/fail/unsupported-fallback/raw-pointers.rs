@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+#[trusted_if_unsupported]
+#[ensures="result == *x"]
+fn read(x: &u32) -> u32 {
+    let pointer = x as *const u32;
+    unsafe { *pointer }
+}
+
+#[ensures="result == 2 * *x"] //~ ERROR postcondition might not hold
+fn read_twice(x: &u32) -> u32 {
+    read(x) + read(x) + 1
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+use std::ops::{Add, Sub};
+
+#[arithmetic_newtype]
+struct Count(u32);
+
+impl Add for Count {
+    type Output = Count;
+
+    fn add(self, other: Count) -> Count { //~ ERROR postcondition might not hold
+        Count(self.0 + other.0 + 1)
+    }
+}
+
+impl Sub for Count {
+    type Output = Count;
+
+    fn sub(self, other: Count) -> Count {
+        Count(self.0 - other.0)
+    }
+}
+
+fn difference(a: Count, b: Count) -> Count {
+    a - b //~ ERROR precondition might not hold
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[requires="n > 0"]
+fn divides_hundred(n: u32) -> bool {
+    100 % n == 0
+}
+
+#[requires="divides_hundred(n)"] //~ ERROR precondition of pure function call might not hold
+#[requires="n > 0"]
+fn divisor(n: u32) -> u32 {
+    n
+}
+
+fn main() {}
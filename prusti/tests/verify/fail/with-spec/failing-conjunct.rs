@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+#[requires="x < 100"]
+#[ensures="result > x &&
+           result < 1000 &&
+           result == x"] //~ ERROR postcondition might not hold
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+#[requires="x < 100 &&
+            x > 10"]
+fn foo(x: u32) {}
+
+fn test() {
+    foo(5); //~ ERROR precondition might not hold
+}
+
+fn main() {}
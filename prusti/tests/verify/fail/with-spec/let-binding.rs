@@ -0,0 +1,16 @@
+extern crate prusti_contracts;
+
+#[requires="x < 100"]
+#[ensures="let doubled = 2 * x in result == doubled"] //~ ERROR postcondition might not hold
+fn double(x: u32) -> u32 {
+    x + x + 1
+}
+
+#[requires="let sum = a + b in sum < 10"]
+fn bounded(a: u32, b: u32) {}
+
+fn test() {
+    bounded(5, 6); //~ ERROR precondition might not hold
+}
+
+fn main() {}
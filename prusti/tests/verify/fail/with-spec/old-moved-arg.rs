@@ -0,0 +1,20 @@
+extern crate prusti_contracts;
+
+struct IntBox {
+    value: i32,
+}
+
+#[requires="a.value < 1000"]
+#[ensures="result.value == old(a.value)"] //~ ERROR postcondition might not hold
+fn increment(mut a: IntBox) -> IntBox {
+    a.value += 1;
+    a
+}
+
+#[requires="b.value < 1000"]
+#[ensures="result == old(b.value) + 1"] //~ ERROR postcondition might not hold
+fn unbox(b: Box<IntBox>) -> i32 {
+    b.value
+}
+
+fn main() {}
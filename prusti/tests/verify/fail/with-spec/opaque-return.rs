@@ -0,0 +1,53 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[pure]
+    fn value(&self) -> u32;
+
+    #[requires="self.value() < 100"]
+    #[ensures="self.value() == old(self.value()) + 1"]
+    fn increment(&mut self);
+}
+
+struct Simple {
+    value: u32,
+}
+
+impl Counter for Simple {
+    #[pure]
+    #[trusted]
+    fn value(&self) -> u32 {
+        self.value
+    }
+
+    #[trusted]
+    fn increment(&mut self) {
+        self.value += 1;
+    }
+}
+
+#[trusted]
+fn make() -> impl Counter {
+    Simple { value: 0 }
+}
+
+#[trusted]
+fn make_boxed() -> Box<dyn Counter> {
+    Box::new(Simple { value: 0 })
+}
+
+fn use_opaque() {
+    let mut c = make();
+    // Only the contract of the trait is known, not the value of the concrete counter.
+    c.increment(); //~ ERROR precondition might not hold
+}
+
+fn use_trait_object() {
+    let mut c = make_boxed();
+    if c.value() < 100 {
+        c.increment();
+        assert!(c.value() == 1); //~ ERROR the asserted expression might not hold
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+extern crate prusti_contracts;
+
+#[ensures="result.0 <= result.1"]
+#[ensures="result.0 == a"] //~ ERROR postcondition
+//~| HELP The failing part of the postcondition is about `result.0`.
+fn min_max(a: u32, b: u32) -> (u32, u32) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+extern crate prusti_contracts;
+
+/// The postcondition has to hold for any value of the `unsafe_spec` expression.
+#[ensures="unsafe_spec(result & 1 == 0) || result > 10"] //~ ERROR postcondition might not hold
+fn identity(x: u32) -> u32 {
+    x
+}
+
+/// The callers cannot establish the `unsafe_spec` expression of a precondition.
+#[requires="unsafe_spec(x & 1 == 0)"]
+fn even(x: u32) -> u32 {
+    x
+}
+
+fn test() {
+    even(2); //~ ERROR precondition might not hold
+}
+
+fn main() {}
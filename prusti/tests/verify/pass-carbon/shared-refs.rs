@@ -0,0 +1,34 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[pure]
+fn sum(p: &Point) -> u32 {
+    p.x + p.y
+}
+
+#[requires="p.x < 100 && p.y < 100"]
+#[ensures="result == sum(p) + p.x"]
+fn read_after_call(p: &Point) -> u32 {
+    let s = sum(p);
+    s + p.x
+}
+
+#[requires="p.x < 100 && p.y < 100"]
+fn call_twice(p: &Point) {
+    let q = &*p;
+    assert!(sum(p) == sum(q));
+}
+
+#[requires="p.x < 100 && p.y < 100"]
+#[ensures="p.x == old(p.x) + 1"]
+fn write_after_call(p: &mut Point) {
+    let s = sum(p);
+    assert!(s < 200);
+    p.x += 1;
+}
+
+fn main() {}
@@ -0,0 +1,35 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn abs(a: i32) -> i32 {
+    if a < 0 { -a } else { a }
+}
+
+fn max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+fn manhattan(p: &Point) -> i32 {
+    abs(p.x) + abs(p.y)
+}
+
+#[requires="a > -1000 && a < 1000 && b > -1000 && b < 1000"]
+#[ensures="result == max(a, b)"]
+#[ensures="result >= abs(a) || result >= a"]
+fn test_max(a: i32, b: i32) -> i32 {
+    if a > b { a } else { b }
+}
+
+#[requires="p.x > -1000 && p.x < 1000 && p.y > -1000 && p.y < 1000"]
+#[ensures="result == manhattan(p)"]
+fn test_manhattan(p: &Point) -> i32 {
+    let x = if p.x < 0 { -p.x } else { p.x };
+    let y = if p.y < 0 { -p.y } else { p.y };
+    x + y
+}
+
+fn main() {}
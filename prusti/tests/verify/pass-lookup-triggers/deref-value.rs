@@ -0,0 +1,23 @@
+extern crate prusti_contracts;
+
+/// The quantifiers are triggered by `deref_value(p, k)`, which stays stable across the write.
+#[requires="deref_spec(p, len) && 1 < len"]
+#[requires="forall k: usize :: k < len ==> deref_value(p, k) > 0"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="forall k: usize :: (0 < k && k < len) ==> deref_value(p, k) > 0"]
+#[ensures="deref_value(p, 0) == 0"]
+fn reset_first(p: *mut u32, len: usize) {
+    unsafe {
+        *p = 0;
+    }
+}
+
+#[requires="deref_spec(p, len) && 1 < len"]
+#[requires="forall k: usize :: k < len ==> deref_value(p, k) > 0"]
+#[ensures="deref_spec(p, len)"]
+fn read_second(p: *const u32, len: usize) {
+    let second = unsafe { *p.add(1) };
+    assert!(second > 0);
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+extern crate prusti_contracts;
+
+#[pure]
+fn max(a: u32, b: u32) -> u32 {
+    if a > b { a } else { b }
+}
+
+#[ensures="result >= a && result >= b"]
+fn call_max(a: u32, b: u32) -> u32 {
+    max(a, b)
+}
+
+#[requires="x < 100"]
+#[ensures="result == x + 1"]
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+#[ensures="result == 3"]
+fn three() -> u32 {
+    increment(increment(increment(0)))
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate prusti_contracts;
+
+fn add(x: u32, y: u32) -> u32 {
+    x + y
+}
+
+fn check(x: u32) {
+    debug_assert!(x > 10);
+    debug_assert_eq!(x, 42);
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[ensures="result >= 0"]
+fn abs(x: i32) -> i32 {
+    if x >= 0 {
+        x
+    } else {
+        -x
+    }
+}
+
+#[requires="x > 0"]
+fn positive(x: i32) {
+    assert!(abs(x) == x);
+}
+
+#[requires="x < 0 && x > -10"]
+fn negative_division(x: i32) {
+    // Rust rounds the quotient towards zero.
+    assert!(x / 2 >= -4);
+    assert!(x % 2 <= 0);
+}
+
+fn branches(x: i32) {
+    let y = if x > 0 { 1 } else { 0 };
+    assert!(y >= 0);
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[requires="0 <= p.x && p.x < 100 && 0 <= p.y && p.y < 100"]
+#[ensures="p.x == old(p.x) + 1"]
+#[ensures="p.y == old(p.y) + 2"]
+#[ensures="result == p.x + p.y"]
+fn shift(p: &mut Point) -> i32 {
+    p.x += 1;
+    assert!(p.x > 0);
+    p.y += 2;
+    assert!(p.y > 1);
+    p.x + p.y
+}
+
+#[ensures="result >= 0"]
+fn abs(x: i32) -> i32 {
+    if x < 0 {
+        assert!(x < 0);
+        -x
+    } else {
+        x
+    }
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+#[requires="x < 100"]
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+fn assume_precondition(x: u32) -> u32 {
+    prusti_assume!(x < 100);
+    increment(x)
+}
+
+fn assume_field(counter: &mut Counter) {
+    prusti_assume!(counter.value < 10);
+    counter.value += 1;
+    assert!(counter.value <= 10);
+}
+
+fn assume_in_loop(n: u32) {
+    let mut i = 0;
+    while i < n {
+        prusti_assume!(i < 1000 && n <= 1000);
+        i += 1;
+        assert!(i <= 1000);
+    }
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+#![allow(unused_variables)]
+
+extern crate prusti_contracts;
+
+/// A model of the sets of integers, whose functions are axiomatized by their postconditions.
+#[derive(Clone, Copy)]
+#[domain]
+struct IntSet;
+
+impl IntSet {
+    #[pure]
+    #[ensures="forall x: i32 :: !result.contains(x)"]
+    fn empty() -> IntSet {
+        unimplemented!()
+    }
+
+    #[pure]
+    fn contains(self, x: i32) -> bool {
+        unimplemented!()
+    }
+
+    #[pure]
+    #[ensures="result.contains(x)"]
+    #[ensures="forall y: i32 :: y != x ==> result.contains(y) == self.contains(y)"]
+    fn insert(self, x: i32) -> IntSet {
+        unimplemented!()
+    }
+}
+
+#[pure]
+#[ensures="result.contains(a) && result.contains(b)"]
+fn pair(a: i32, b: i32) -> IntSet {
+    IntSet::empty().insert(a).insert(b)
+}
+
+#[requires="x != y"]
+#[ensures="result.contains(x) && !result.contains(y)"]
+fn singleton(x: i32, y: i32) -> IntSet {
+    let set = IntSet::empty().insert(x);
+    assert!(set.contains(x));
+    set
+}
+
+fn test(x: i32, y: i32) {
+    let set = pair(x, y);
+    assert!(set.contains(x));
+    let copy = set;
+    assert!(copy.contains(y));
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+#[invariant="self.level <= 100"]
+struct Gauge {
+    level: u32,
+}
+
+impl Drop for Gauge {
+    #[ensures="self.level == 0"]
+    fn drop(&mut self) {
+        self.level = 0;
+    }
+}
+
+fn fill(level: u32) {
+    if level <= 100 {
+        let _gauge = Gauge { level };
+    }
+}
+
+#[requires="level <= 50"]
+fn replace(level: u32) {
+    let mut gauge = Gauge { level };
+    gauge = Gauge { level: level * 2 };
+    let _moved = gauge;
+}
+
+fn main() {}
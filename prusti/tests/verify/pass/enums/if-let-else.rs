@@ -0,0 +1,44 @@
+//! `let ... else` is not available in this version of Rust, so these tests
+//! use its desugaring: `let x = if let Pattern(x) = e { x } else { diverge };`
+
+extern crate prusti_contracts;
+
+pub enum Token {
+    Number(u32),
+    Plus,
+    Eof,
+}
+
+#[pure]
+pub fn is_number(token: &Token) -> bool {
+    match token {
+        Token::Number(_) => true,
+        _ => false,
+    }
+}
+
+#[ensures="result == 0 || is_number(token)"]
+pub fn number_or_zero(token: &Token) -> u32 {
+    let n = if let Token::Number(n) = *token { n } else { return 0; };
+    assert!(is_number(token));
+    n
+}
+
+#[requires="is_number(first)"]
+#[ensures="result >= 1"]
+pub fn sum(first: &Token, second: &Token) -> u32 {
+    let a = if let Token::Number(a) = *first { a } else { unreachable!() };
+    let b = if let Token::Number(b) = *second { b } else { return 1; };
+    if a < 1000 && b < 1000 { a + b + 1 } else { 1 }
+}
+
+pub fn chained(first: Token, second: Token) -> u32 {
+    if let Token::Number(a) = first {
+        if let Token::Plus = second {
+            return a;
+        }
+    }
+    0
+}
+
+fn main() {}
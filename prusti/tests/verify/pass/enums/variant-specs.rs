@@ -0,0 +1,61 @@
+extern crate prusti_contracts;
+
+pub enum Shape {
+    Circle(u32),
+    Square(u32),
+    Empty,
+}
+
+pub enum Slot {
+    Full(Shape),
+    Free,
+}
+
+#[ensures="matches!(result, Shape::Circle(_))"]
+pub fn circle(radius: u32) -> Shape {
+    Shape::Circle(radius)
+}
+
+#[ensures="is_variant!(result, Shape::Square)"]
+pub fn square(side: u32) -> Shape {
+    Shape::Square(side)
+}
+
+#[requires="matches!(shape, Shape::Circle(_) | Shape::Square(_))"]
+#[ensures="matches!(result, Slot::Full(Shape::Circle(_)) | Slot::Full(Shape::Square(_)))"]
+pub fn store(shape: Shape) -> Slot {
+    Slot::Full(shape)
+}
+
+#[requires="!is_variant!(slot, Slot::Free)"]
+#[ensures="is_variant!(result, Slot::Free)"]
+pub fn clear(slot: Slot) -> Slot {
+    match slot {
+        Slot::Full(_) => Slot::Free,
+        Slot::Free => unreachable!(),
+    }
+}
+
+#[requires="matches!(*shape, Shape::Circle(_))"]
+pub fn radius(shape: &Shape) -> u32 {
+    match *shape {
+        Shape::Circle(r) => r,
+        _ => unreachable!(),
+    }
+}
+
+#[invariant="!matches!(self.shape, Shape::Empty)"]
+pub struct Tile {
+    shape: Shape,
+}
+
+impl Tile {
+    pub fn check(&self) {
+        match self.shape {
+            Shape::Empty => unreachable!(),
+            _ => {}
+        }
+    }
+}
+
+fn main() {}
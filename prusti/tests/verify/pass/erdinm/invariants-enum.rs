@@ -0,0 +1,40 @@
+extern crate prusti_contracts;
+
+// invariants of the fields of the active variant
+
+#[invariant="self.value <= 100"]
+struct Percentage {
+    value: u8,
+}
+
+enum Reading {
+    Missing,
+    Measured(Percentage),
+}
+
+impl Reading {
+    fn check(&self) {
+        match *self {
+            Reading::Missing => {}
+            Reading::Measured(ref p) => assert!(p.value <= 100),
+        }
+    }
+}
+
+#[invariant="self.discriminant() <= 1"]
+enum Level {
+    Low,
+    High,
+}
+
+impl Level {
+    #[pure]
+    fn discriminant(&self) -> u8 {
+        match *self {
+            Level::Low => 0,
+            Level::High => 1,
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,50 @@
+extern crate prusti_contracts;
+
+#[pure]
+fn both_even(a: u32, b: u32) -> bool {
+    a % 2 == 0 && b % 2 == 0
+}
+
+struct Pair {
+    a: u32,
+    b: u32,
+}
+
+impl Pair {
+    #[pure]
+    fn valid(&self) -> bool {
+        both_even(self.a, self.b)
+    }
+
+    #[requires="self.valid()"]
+    #[ensures="*result == old(self.a)"]
+    #[ensures="assert_on_expiry(
+        both_even(*result, 0),
+        self.valid()
+    )"]
+    fn a_mut(&mut self) -> &mut u32 {
+        &mut self.a
+    }
+
+    #[requires="self.valid()"]
+    #[ensures="*result == old(self.b)"]
+    #[ensures="assert_on_expiry(both_even(*result, 2))"]
+    fn b_mut(&mut self) -> &mut u32 {
+        &mut self.b
+    }
+}
+
+#[requires="arg.valid()"]
+#[ensures="arg.valid()"]
+fn test(arg: &mut Pair) {
+    let a = arg.a_mut();
+    *a = 4;
+}
+
+#[requires="arg.valid()"]
+fn test_b(arg: &mut Pair) {
+    let b = arg.b_mut();
+    *b = 6;
+}
+
+fn main() {}
@@ -0,0 +1,72 @@
+//! Nested matches on enums that are unfolded with different variants in
+//! different branches, so that every join has to fold them back.
+
+extern crate prusti_contracts;
+
+enum Shape {
+    Point,
+    Circle(u32),
+    Rect(u32, u32),
+}
+
+struct Scene {
+    a: Shape,
+    b: Shape,
+    c: Shape,
+}
+
+fn weight(s: &Scene, c1: bool, c2: bool) -> u32 {
+    let mut w = 0;
+    match s.a {
+        Shape::Point => {
+            if c1 {
+                match s.b {
+                    Shape::Circle(r) => w = r,
+                    Shape::Rect(x, _) => w = x,
+                    Shape::Point => {}
+                }
+            }
+        }
+        Shape::Circle(r) => {
+            if c2 {
+                match s.c {
+                    Shape::Rect(_, y) => w = y,
+                    _ => w = r,
+                }
+            }
+        }
+        Shape::Rect(x, y) => {
+            if c1 && c2 {
+                w = x;
+            } else if c1 {
+                w = y;
+            } else {
+                match s.b {
+                    Shape::Point => {}
+                    _ => w = 1,
+                }
+            }
+        }
+    }
+    match s.c {
+        Shape::Point => {
+            if c2 {
+                match s.a {
+                    Shape::Circle(_) => w = 2,
+                    _ => {}
+                }
+            }
+        }
+        _ => {
+            if c1 {
+                match s.b {
+                    Shape::Rect(_, _) => w = 3,
+                    _ => {}
+                }
+            }
+        }
+    }
+    w
+}
+
+fn main() {}
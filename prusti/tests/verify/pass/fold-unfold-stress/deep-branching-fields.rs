@@ -0,0 +1,89 @@
+//! Many nested conditionals that access different fields of a struct, so that
+//! the fold/unfold states of the branches differ at every join.
+
+extern crate prusti_contracts;
+
+struct Leaf {
+    value: u32,
+}
+
+struct Pair {
+    left: Leaf,
+    right: Leaf,
+}
+
+struct Tree {
+    first: Pair,
+    second: Pair,
+    third: Pair,
+}
+
+fn touch(t: &mut Tree, c1: bool, c2: bool, c3: bool, c4: bool) {
+    if c1 {
+        if c2 {
+            t.first.left.value = 1;
+        } else {
+            t.first.right.value = 2;
+        }
+        if c3 {
+            t.second.left.value = 3;
+        } else if c4 {
+            let leaf = Leaf { value: 4 };
+            t.second.right = leaf;
+        }
+    } else {
+        if c3 {
+            if c4 {
+                t.third.left.value = 5;
+            } else {
+                t.third.right.value = 6;
+            }
+        } else {
+            let pair = Pair {
+                left: Leaf { value: 7 },
+                right: Leaf { value: 8 },
+            };
+            t.third = pair;
+        }
+        if c2 {
+            t.first.left.value = 9;
+        }
+    }
+    if c4 {
+        if c1 {
+            t.second.left.value = 10;
+        } else {
+            t.first.right.value = 11;
+        }
+    } else if c2 {
+        if c3 {
+            t.third.right.value = 12;
+        } else {
+            t.second.right.value = 13;
+        }
+    }
+}
+
+#[ensures="result.first.left.value == old(t.first.left.value)"]
+fn read_some(t: Tree, c1: bool, c2: bool, c3: bool) -> Tree {
+    let mut sum = 0;
+    if c1 {
+        if c2 {
+            sum += t.second.left.value;
+        } else if c3 {
+            sum += t.second.right.value;
+        }
+    } else if c2 {
+        if c3 {
+            sum += t.third.left.value;
+        } else {
+            sum += t.third.right.value;
+        }
+    }
+    if sum > 100 {
+        let _ = t.first.right.value;
+    }
+    t
+}
+
+fn main() {}
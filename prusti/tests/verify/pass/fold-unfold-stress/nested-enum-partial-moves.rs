@@ -0,0 +1,58 @@
+//! Nested enums that are moved out partially, and differently in each branch.
+//! After each branch, the fold of the enclosing enums fails for several
+//! requirements of the join. Only the first failure searches the nested
+//! predicates; the others are answered by the cache of failed folds.
+
+extern crate prusti_contracts;
+
+struct Payload {
+    value: u32,
+}
+
+enum Inner {
+    Empty,
+    One(Payload),
+    Two(Payload, Payload),
+}
+
+enum Middle {
+    Leaf(Inner),
+    Pair(Inner, Inner),
+}
+
+enum Outer {
+    Single(Middle),
+    Double(Middle, Middle),
+}
+
+fn consume(p: Payload) -> u32 {
+    p.value
+}
+
+fn drain(o: Outer, c: bool) -> u32 {
+    match o {
+        Outer::Single(Middle::Leaf(Inner::Two(a, b))) => {
+            if c {
+                consume(a)
+            } else {
+                consume(b)
+            }
+        }
+        Outer::Double(Middle::Pair(Inner::One(a), Inner::Two(b, d)), Middle::Leaf(inner)) => {
+            let first = if c { consume(a) } else { consume(b) };
+            let second = match inner {
+                Inner::One(e) => consume(e),
+                Inner::Two(e, _) if c => consume(e),
+                _ => consume(d),
+            };
+            if first > second {
+                first
+            } else {
+                second
+            }
+        }
+        _ => 0,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,39 @@
+extern crate prusti_contracts;
+
+struct T {
+    f: u32,
+}
+
+struct U {
+    a: T,
+    b: T,
+}
+
+#[ensures="result.a.f == old(x.a.f)"]
+fn nested_branches(x: U, c1: bool, c2: bool, c3: bool, c4: bool) -> U {
+    let mut y = x;
+    if c1 {
+        if c2 {
+            y.b.f = 1;
+        } else {
+            if c3 {
+                y.b = T { f: 2 };
+            } else {
+                let z = y.b;
+                y.b = z;
+            }
+        }
+    } else {
+        if c4 {
+            if c3 {
+                y.b.f = 3;
+            }
+        } else {
+            let z = y.b.f;
+            y.b.f = z;
+        }
+    }
+    y
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+//! The permission of a shared reference is still available after passing the
+//! reference to a pure function.
+
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[pure]
+fn get_x(p: &Point) -> u32 {
+    p.x
+}
+
+#[requires="p.y < 100"]
+#[ensures="result == get_x(p) + p.y"]
+fn read_after_call(p: &Point) -> u32 {
+    let x = get_x(p);
+    let y = p.y;
+    x + y
+}
+
+fn read_nested_after_call(p: &(Point, Point)) -> u32 {
+    let x = get_x(&p.0);
+    assert!(x == p.0.x);
+    p.1.y
+}
+
+fn main() {}
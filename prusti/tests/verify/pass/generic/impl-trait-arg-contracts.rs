@@ -0,0 +1,46 @@
+//! The specifications of a function with `impl Trait` arguments use the
+//! contracts of the bounds, like for named type parameters.
+
+extern crate prusti_contracts;
+
+trait Counter {
+    #[requires="n < 1000"]
+    #[ensures="result > n"]
+    fn next(&self, n: u32) -> u32;
+}
+
+trait Shape {
+    #[pure]
+    fn sides(&self) -> u32;
+}
+
+#[requires="n < 1000"]
+#[ensures="result > n"]
+fn next_of(counter: &impl Counter, n: u32) -> u32 {
+    counter.next(n)
+}
+
+#[requires="n < 1000"]
+#[ensures="result > n"]
+fn next_of_both<T: Counter>(first: &T, second: &impl Counter, n: u32) -> u32 {
+    first.next(n);
+    second.next(n)
+}
+
+#[requires="shape.sides() < 100"]
+#[ensures="result == shape.sides() + 1"]
+fn more_sides(shape: &impl Shape) -> u32 {
+    shape.sides() + 1
+}
+
+struct Wrapper(u32);
+
+impl Wrapper {
+    #[requires="self.0 < 1000"]
+    #[ensures="result > self.0"]
+    fn next_with(&self, counter: &impl Counter) -> u32 {
+        counter.next(self.0)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,35 @@
+extern crate prusti_contracts;
+
+trait Shape {
+    fn sides(&self) -> u32;
+}
+
+struct Square;
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+#[requires="n < 1000"]
+#[ensures="result == n + 1"]
+fn next_with(_shape: impl Shape, n: u32) -> u32 {
+    n + 1
+}
+
+#[requires="n < 1000"]
+#[ensures="result == n + 1"]
+fn next_with_ref(shape: &impl Shape, n: u32) -> u32 {
+    shape.sides();
+    n + 1
+}
+
+fn test() {
+    let r = next_with(Square, 3);
+    assert!(r == 4);
+    let s = next_with_ref(&Square, r);
+    assert!(s == 5);
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+//! The contract of a generic function is instantiated with type arguments whose predicate
+//! names are ambiguous, e.g. a tuple whose first element is itself a generic type.
+
+extern crate prusti_contracts;
+
+struct Counted<T> {
+    value: T,
+    count: u32,
+}
+
+#[requires="counted.count < 1000"]
+#[ensures="counted.count == old(counted.count) + 1"]
+fn increment<A, B>(counted: &mut Counted<(A, B)>) {
+    counted.count += 1;
+}
+
+#[requires="counted.count == 3"]
+#[ensures="counted.count == 4"]
+fn increment_nested(counted: &mut Counted<(Counted<(u8, u16)>, u32)>) {
+    increment(counted);
+}
+
+fn main() {}
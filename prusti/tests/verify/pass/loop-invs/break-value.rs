@@ -0,0 +1,46 @@
+extern crate prusti_contracts;
+
+#[requires="0 <= n"]
+#[ensures="result == n"]
+fn find(n: i32) -> i32 {
+    let mut i = 0;
+    let res = loop {
+        if i == n {
+            break i;
+        }
+        i += 1;
+    };
+    res
+}
+
+#[requires="0 <= n"]
+#[ensures="result == n"]
+fn find_with_invariant(n: i32) -> i32 {
+    let mut i = 0;
+    #[invariant="0 <= i && i <= n"]
+    loop {
+        if i == n {
+            break;
+        }
+        i += 1;
+    }
+    i
+}
+
+#[ensures="result > 10"]
+fn nested_break(n: i32) -> i32 {
+    let mut i = n;
+    loop {
+        loop {
+            if i > 10 {
+                return i;
+            }
+            i += 1;
+            if i % 2 == 0 {
+                break;
+            }
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+extern crate prusti_contracts;
+
+fn count_down(n: u32) -> u32 {
+    let mut opt = if n > 0 { Some(n) } else { None };
+    let mut steps = 0;
+    while let Some(k) = opt {
+        steps += 1;
+        opt = if k > 1 { Some(k - 1) } else { None };
+    }
+    match opt {
+        Some(_) => unreachable!(),
+        None => {}
+    }
+    steps
+}
+
+#[requires="n >= 0"]
+#[ensures="result >= 0"]
+fn sum_while_let(n: i32) -> i32 {
+    let mut opt = Some(n);
+    let mut sum = 0;
+    #[invariant="sum >= 0"]
+    while let Some(k) = opt {
+        if k > 0 {
+            sum += k;
+            opt = Some(k - 1);
+        } else {
+            opt = None;
+        }
+    }
+    sum
+}
+
+fn main() {}
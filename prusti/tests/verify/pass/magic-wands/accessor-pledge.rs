@@ -0,0 +1,61 @@
+#![feature(nll)]
+
+extern crate prusti_contracts;
+
+struct Inner {
+    a: u32,
+    b: u32,
+}
+
+struct Point {
+    x: u32,
+    y: u32,
+    inner: Inner,
+}
+
+impl Point {
+    fn x_mut(&mut self) -> &mut u32 {
+        &mut self.x
+    }
+
+    fn inner_a_mut(&mut self) -> &mut u32 {
+        &mut self.inner.a
+    }
+
+    fn inner_b_mut(&mut self) -> &mut u32 {
+        self.inner.b_mut()
+    }
+}
+
+impl Inner {
+    fn b_mut(&mut self) -> &mut u32 {
+        &mut self.b
+    }
+}
+
+fn set_x(p: &mut Point) {
+    let old_y = p.y;
+    *p.x_mut() = 5;
+    assert!(p.x == 5);
+    assert!(p.y == old_y);
+}
+
+fn set_inner_a(p: &mut Point) {
+    let old_x = p.x;
+    let old_b = p.inner.b;
+    *p.inner_a_mut() = 7;
+    assert!(p.inner.a == 7);
+    assert!(p.x == old_x);
+    assert!(p.inner.b == old_b);
+}
+
+fn set_inner_b(p: &mut Point) {
+    let old_y = p.y;
+    let old_a = p.inner.a;
+    *p.inner_b_mut() = 9;
+    assert!(p.inner.b == 9);
+    assert!(p.y == old_y);
+    assert!(p.inner.a == old_a);
+}
+
+fn main() {}
@@ -0,0 +1,53 @@
+extern crate prusti_contracts;
+
+mod ring_buffer {
+    /// A ring buffer of capacity 16, whose number of elements is modelled by `len`.
+    #[invariant="self.head < 16 && self.tail < 16"]
+    #[invariant="self.len <= 16"]
+    #[invariant="self.len == (self.tail + 16 - self.head) % 16 || (self.len == 16 && self.head == self.tail)"]
+    pub struct RingBuffer {
+        head: usize,
+        tail: usize,
+        #[model]
+        pub len: usize,
+    }
+
+    impl RingBuffer {
+        #[ensures="result.len == 0"]
+        pub fn new() -> Self {
+            RingBuffer {
+                head: 0,
+                tail: 0,
+                len: 0,
+            }
+        }
+
+        #[requires="self.len < 16"]
+        #[ensures="self.len == old(self.len) + 1"]
+        pub fn push(&mut self) {
+            self.tail = (self.tail + 1) % 16;
+            self.len = self.len + 1;
+        }
+
+        #[requires="self.len > 0"]
+        #[ensures="self.len == old(self.len) - 1"]
+        pub fn pop(&mut self) {
+            self.head = (self.head + 1) % 16;
+            self.len -= 1;
+        }
+    }
+}
+
+use ring_buffer::RingBuffer;
+
+#[ensures="result.len == 2"]
+fn two_elements() -> RingBuffer {
+    let mut buffer = RingBuffer::new();
+    buffer.push();
+    buffer.push();
+    buffer.pop();
+    buffer.push();
+    buffer
+}
+
+fn main() {}
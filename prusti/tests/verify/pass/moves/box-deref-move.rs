@@ -0,0 +1,46 @@
+#![feature(box_patterns)]
+
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+enum List {
+    Nil,
+    Cons(Box<Node>),
+}
+
+struct Node {
+    value: u32,
+    next: List,
+}
+
+#[ensures="result.x == old(p.x) && result.y == old(p.y)"]
+fn unbox(p: Box<Point>) -> Point {
+    let point = *p;
+    point
+}
+
+#[ensures="result.x == old(q.x) && result.y == old(q.y)"]
+fn move_out_and_refill(mut p: Box<Point>, q: Point) -> Point {
+    let _old_point = *p;
+    *p = q;
+    let new_point = *p;
+    new_point
+}
+
+fn head(list: List) -> u32 {
+    match list {
+        List::Nil => 0,
+        List::Cons(box node) => node.value,
+    }
+}
+
+fn unbox_pattern(p: Box<Point>) -> u32 {
+    let box Point { x, y } = p;
+    x + y
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+extern crate prusti_contracts;
+
+#[bit_precise]
+fn low_nibble(b: u8) -> u8 {
+    let r = b & 0x0f;
+    assert!(r < 16);
+    assert!(r <= b);
+    r
+}
+
+#[bit_precise]
+fn high_nibble(b: u8) -> u8 {
+    let r = b >> 4;
+    assert!(r < 16);
+    r
+}
+
+#[bit_precise]
+fn combine(high: u8, low: u8) -> u8 {
+    let r = (high << 4) | (low & 0x0f);
+    assert!(r >= low & 0x0f);
+    r
+}
+
+#[bit_precise]
+fn decode_u16(b0: u8, b1: u8) -> u16 {
+    let r = ((b0 as u16) << 8) | (b1 as u16);
+    assert!(r >= b1 as u16);
+    r
+}
+
+fn main() {}
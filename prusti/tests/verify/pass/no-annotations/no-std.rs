@@ -0,0 +1,21 @@
+#![no_std]
+
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+fn increment(counter: &mut Counter) {
+    if counter.value < 100 {
+        counter.value += 1;
+    }
+}
+
+fn test(x: u32) {
+    let mut counter = Counter { value: x % 100 };
+    increment(&mut counter);
+    assert!(counter.value <= 100);
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+extern crate prusti_contracts;
+
+use std::cell::RefCell;
+
+fn shared_borrows(cell: &RefCell<u32>) {
+    let _first = cell.borrow();
+    let _second = cell.borrow();
+}
+
+fn sequential_borrows(cell: &RefCell<u32>) {
+    {
+        let _guard = cell.borrow_mut();
+    }
+    {
+        let _guard = cell.borrow();
+    }
+    let _guard = cell.borrow_mut();
+}
+
+fn main() {}
@@ -0,0 +1,72 @@
+extern crate prusti_contracts;
+
+use std::ops::Deref;
+
+struct Inner {
+    len: usize,
+}
+
+impl Inner {
+    #[pure]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+trait Size {
+    #[pure]
+    fn size(&self) -> usize;
+}
+
+impl Size for Inner {
+    #[pure]
+    fn size(&self) -> usize {
+        self.len
+    }
+}
+
+/// A wrapper with an overloaded dereference, which autoderef calls in the specifications.
+struct Wrapper {
+    inner: Inner,
+}
+
+impl Deref for Wrapper {
+    type Target = Inner;
+
+    fn deref(&self) -> &Inner {
+        &self.inner
+    }
+}
+
+struct Outer {
+    items: Box<Inner>,
+}
+
+/// `w.len()` dereferences `w` with the `deref` of the `impl` resolved by rustc.
+#[requires="w.len() >= 1"]
+#[ensures="result == w.len() - 1"]
+fn wrapper_len_minus_one(w: &Wrapper) -> usize {
+    w.inner.len - 1
+}
+
+/// `x.size()` is the method of the `impl` resolved by rustc, whose body is known.
+#[ensures="result == x.size()"]
+fn size_of_inner(x: &Inner) -> usize {
+    x.len
+}
+
+impl Outer {
+    #[requires="self.items.deref().len() >= 1"]
+    #[ensures="result == self.items.deref().len()"]
+    fn explicit_deref_len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+#[requires="(&x).len() >= 1"]
+#[ensures="result == x.len()"]
+fn double_ref_len(x: &&Inner) -> usize {
+    x.len()
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+#[pure]
+fn double(x: i32) -> i32 {
+    x + x
+}
+
+#[ensures="forall(|i: i32| 0 <= i ==> double(i) >= i)"]
+#[ensures="forall(|i: i32| {double(i)} double(i) == i + i)"]
+fn test_forall() {}
+
+#[requires="exists(|i: i32| x == double(i))"]
+#[ensures="result != 1"]
+fn test_exists(x: i32) -> i32 {
+    x
+}
+
+#[requires="x > 0 ==> exists(|i: i32| i < x && x == i + 1)"]
+fn test_exists_implication(x: i32) {}
+
+fn main() {}
@@ -0,0 +1,38 @@
+#![feature(box_patterns)]
+
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+    next: Option<Box<Counter>>,
+}
+
+impl Counter {
+    #[pure]
+    fn get(&mut self) -> u32 {
+        self.value
+    }
+
+    #[pure]
+    fn is_zero(&mut self) -> bool {
+        let value = self.get();
+        value == 0
+    }
+
+    #[pure]
+    fn next_is_zero(&mut self) -> bool {
+        match self.next {
+            Some(box ref mut next) => next.is_zero(),
+            None => false,
+        }
+    }
+}
+
+#[requires="c.get() > 0"]
+fn test(c: &mut Counter) {
+    let before = c.get();
+    assert!(!c.is_zero());
+    assert!(c.get() == before);
+}
+
+fn main() {}
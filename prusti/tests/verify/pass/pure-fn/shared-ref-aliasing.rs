@@ -0,0 +1,29 @@
+//! Pure functions require only a wildcard permission of their shared
+//! references, so a shared reference can be passed to them several times.
+
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[pure]
+fn sum(a: &Point, b: &Point) -> u32 {
+    a.x + b.y
+}
+
+#[pure]
+fn double_sum(p: &Point) -> u32 {
+    sum(p, p)
+}
+
+#[requires="p.x == 1 && p.y == 2"]
+#[ensures="result == 3"]
+fn test(p: &Point) -> u32 {
+    let s = double_sum(p);
+    assert!(s == sum(p, p));
+    s
+}
+
+fn main() {}
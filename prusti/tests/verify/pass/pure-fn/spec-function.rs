@@ -0,0 +1,31 @@
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    /// An abstraction of the counter, without implementation.
+    #[pure_spec]
+    #[ensures="result == self.value"]
+    fn model(&self) -> u32 {
+        unimplemented!()
+    }
+
+    #[requires="self.model() < 100"]
+    #[ensures="self.model() == old(self.model()) + 1"]
+    fn increment(&mut self) {
+        self.value += 1;
+    }
+}
+
+#[pure_spec]
+#[ensures="result >= 0"]
+fn non_negative(x: i32) -> i32 {
+    unimplemented!()
+}
+
+#[ensures="non_negative(x) >= 0"]
+fn test(x: i32) {}
+
+fn main() {}
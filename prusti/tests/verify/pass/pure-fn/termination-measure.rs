@@ -0,0 +1,35 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[requires="n >= 0"]
+#[decreases="n"]
+#[ensures="result == n * (n + 1) / 2"]
+fn sum(n: i32) -> i32 {
+    if n == 0 { 0 } else { n + sum(n - 1) }
+}
+
+#[pure]
+#[requires="a >= 0 && b >= 0"]
+#[decreases="a + b"]
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else if a == 0 {
+        b
+    } else if a > b {
+        gcd(a - b, b)
+    } else {
+        gcd(a, b - a)
+    }
+}
+
+#[requires="n >= 0 && n < 1000"]
+#[ensures="result == sum(n) + n + 1"]
+fn next_sum(n: i32) -> i32 {
+    sum(n + 1)
+}
+
+fn main() {
+    assert!(sum(3) == 6);
+    assert!(gcd(4, 0) == 4);
+}
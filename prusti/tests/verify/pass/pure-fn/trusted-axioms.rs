@@ -0,0 +1,34 @@
+extern crate prusti_contracts;
+
+/// A model of the integer square root. The implementation is not verified.
+#[trusted]
+#[pure]
+#[requires="x >= 0"]
+#[ensures="result >= 0 && result <= x"]
+fn isqrt(x: i64) -> i64 {
+    let mut r = 0;
+    while (r + 1) * (r + 1) <= x {
+        r += 1;
+    }
+    r
+}
+
+#[requires="n >= 0"]
+#[ensures="result <= n"]
+fn bounded(n: i64) -> i64 {
+    isqrt(n)
+}
+
+#[requires="n >= 0"]
+#[ensures="isqrt(n) >= 0"]
+fn non_negative(n: i64) {
+    let r = isqrt(n);
+    assert!(r == isqrt(n));
+}
+
+/// The axiom of the model of `isqrt` holds for all the arguments that satisfy its precondition.
+#[requires="n >= 0"]
+#[ensures="forall i: i64 :: (0 <= i && i <= n) ==> isqrt(i) <= i"]
+fn all_bounded(n: i64) {}
+
+fn main() {}
@@ -0,0 +1,51 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[ensures="result.0 <= result.1"]
+fn min_max(a: i32, b: i32) -> (i32, i32) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[pure]
+fn checked_pred(n: u32) -> (bool, u32, u32) {
+    if n == 0 {
+        (false, 0, n)
+    } else {
+        (true, n - 1, n)
+    }
+}
+
+#[pure]
+fn spread(a: i32, b: i32) -> i32 {
+    let (min, max) = min_max(a, b);
+    max - min
+}
+
+#[ensures="min_max(3, 1).0 == 1"]
+#[ensures="min_max(3, 1).1 == 3"]
+#[ensures="spread(2, 7) == 5"]
+#[ensures="!checked_pred(0).0 && checked_pred(7).1 == 6"]
+fn test_specs() {}
+
+fn test_body(a: i32, b: i32) {
+    let (min, max) = min_max(a, b);
+    assert!(min <= max);
+    let pair = min_max(a, b);
+    assert!(pair.0 == min);
+}
+
+#[ensures="result.0.1 == x && (result.1).0 == y"]
+fn nest(x: i32, y: i32) -> ((i32, i32), (i32, i32)) {
+    ((y, x), (y, x))
+}
+
+fn test_nested() {
+    let ((a, b), _) = nest(1, 2);
+    assert!(a == 2 && b == 1);
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec_frac(p, len, 1, 2) && i < len"]
+#[ensures="deref_spec_frac(p, len, 1, 2)"]
+#[ensures="result == deref_value(p, i)"]
+fn get(p: *const u32, len: usize, i: usize) -> u32 {
+    unsafe { *p.add(i) }
+}
+
+/// The values that are read through a fraction of the region keep their value.
+#[requires="deref_spec(p, len) && 0 < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="deref_value(p, 0) == old(deref_value(p, 0))"]
+fn read_twice(p: *const u32, len: usize) {
+    let first = get(p, len, 0);
+    let again = get(p, len, 0);
+    assert!(first == again);
+}
+
+/// Two halves make a full permission, which allows writing.
+#[requires="deref_spec_frac(p, len, 1, 2) && deref_spec_frac(p, len, 1, 2) && 0 < len"]
+#[ensures="deref_spec(p, len)"]
+fn join_halves(p: *mut u32, len: usize) {
+    unsafe {
+        *p = 0;
+    }
+}
+
+fn main() {}
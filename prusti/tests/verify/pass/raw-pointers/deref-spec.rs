@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec(p, len) && i < len"]
+#[ensures="deref_spec(p, len)"]
+fn get(p: *const u32, len: usize, i: usize) -> u32 {
+    unsafe { *p.add(i) }
+}
+
+#[requires="deref_spec(p, len) && i < len"]
+#[ensures="deref_spec(p, len)"]
+fn set(p: *mut u32, len: usize, i: usize, value: u32) {
+    unsafe {
+        *p.add(i) = value;
+    }
+}
+
+#[requires="deref_spec(p, len) && 1 < len"]
+#[ensures="deref_spec(p, len)"]
+fn swap_first(p: *mut u32, len: usize) {
+    unsafe {
+        let second = p.add(1);
+        let tmp = *p;
+        *p = *second;
+        *second = tmp;
+    }
+}
+
+fn main() {}
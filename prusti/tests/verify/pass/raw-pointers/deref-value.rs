@@ -0,0 +1,43 @@
+extern crate prusti_contracts;
+
+#[requires="deref_spec(p, len) && i < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="result == deref_value(p, i)"]
+fn get(p: *const u32, len: usize, i: usize) -> u32 {
+    unsafe { *p.add(i) }
+}
+
+#[requires="deref_spec(p, len) && i < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="deref_value(p, i) == value"]
+fn set(p: *mut u32, len: usize, i: usize, value: u32) {
+    unsafe {
+        *p.add(i) = value;
+    }
+}
+
+/// The values that are not written keep their value.
+#[requires="deref_spec(p, len) && 1 < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="deref_value(p, 0) == 0"]
+#[ensures="deref_value(p, 1) == old(deref_value(p, 1))"]
+fn reset_first(p: *mut u32, len: usize) {
+    unsafe {
+        *p = 0;
+    }
+}
+
+#[requires="deref_spec(p, len) && 1 < len"]
+#[ensures="deref_spec(p, len)"]
+#[ensures="deref_value(p, 0) == old(deref_value(p, 1))"]
+#[ensures="deref_value(p, 1) == old(deref_value(p, 0))"]
+fn swap_first(p: *mut u32, len: usize) {
+    unsafe {
+        let second = p.add(1);
+        let tmp = *p;
+        *p = *second;
+        *second = tmp;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,46 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+#[predicate]
+fn pair(p: *mut u32) -> bool {
+    deref_spec(p, 2)
+}
+
+#[requires="pair(p)"]
+#[ensures="pair(p)"]
+fn swap(p: *mut u32) {
+    unfold!(pair(p));
+    unsafe {
+        let second = p.add(1);
+        let tmp = *p;
+        *p = *second;
+        *second = tmp;
+    }
+    fold!(pair(p));
+}
+
+#[requires="pair(p)"]
+#[ensures="pair(p)"]
+fn swap_twice(p: *mut u32) {
+    swap(p);
+    swap(p);
+}
+
+#[predicate]
+fn positive_first(p: *mut u32) -> bool {
+    deref_spec(p, 1) && deref_value(p, 0) > 0
+}
+
+#[requires="positive_first(p)"]
+#[ensures="positive_first(p)"]
+fn increment(p: *mut u32) {
+    unfold!(positive_first(p));
+    unsafe {
+        if *p < 100 {
+            *p += 1;
+        }
+    }
+    fold!(positive_first(p));
+}
+
+fn main() {}
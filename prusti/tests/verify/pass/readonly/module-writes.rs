@@ -0,0 +1,42 @@
+extern crate prusti_contracts;
+
+mod counter {
+    pub struct Counter {
+        #[readonly]
+        pub value: u32,
+        pub step: u32,
+    }
+
+    impl Counter {
+        pub fn new() -> Self {
+            Counter { value: 0, step: 1 }
+        }
+
+        #[requires="self.value < 1000 && self.step < 1000"]
+        pub fn increment(&mut self) {
+            self.value = self.value + self.step;
+        }
+
+        pub fn reset(&mut self) {
+            let value = &mut self.value;
+            *value = 0;
+        }
+    }
+}
+
+use counter::Counter;
+
+fn read(counter: &Counter) -> u32 {
+    counter.value
+}
+
+fn set_step(counter: &mut Counter) {
+    counter.step = 2;
+}
+
+fn main() {
+    let mut counter = Counter::new();
+    set_step(&mut counter);
+    counter.reset();
+    read(&counter);
+}
@@ -0,0 +1,31 @@
+extern crate prusti_contracts;
+
+#[requires="0 <= n && n < 100"]
+#[ensures="result == 2 * n"]
+#[assert_timeout="60000"]
+#[z3_random_seed="42"]
+fn double(n: i32) -> i32 {
+    let mut sum = 0;
+    let mut i = 0;
+    #[invariant="0 <= i && i <= n"]
+    #[invariant="sum == 2 * i"]
+    while i < n {
+        i += 1;
+        sum += 2;
+    }
+    sum
+}
+
+#[requires="x < 1000"]
+#[ensures="result == x + 1"]
+#[quantifier_instantiation_bound="100"]
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+#[ensures="result == 6"]
+fn main_default_limits() -> i32 {
+    double(3)
+}
+
+fn main() {}
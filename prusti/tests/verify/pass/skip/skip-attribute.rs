@@ -0,0 +1,27 @@
+extern crate prusti_contracts;
+
+#[prusti::skip]
+#[ensures="result > x"]
+fn not_verified(x: u32) -> u32 {
+    assert!(x > 100);
+    x
+}
+
+fn caller() {
+    let y = not_verified(3);
+    assert!(y > 3);
+}
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    #[prusti::skip]
+    fn reset(&mut self) {
+        self.value = 0;
+        unreachable!();
+    }
+}
+
+fn main() {}
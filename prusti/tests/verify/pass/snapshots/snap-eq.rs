@@ -0,0 +1,67 @@
+extern crate prusti_contracts;
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+// A custom equality, which `===` ignores.
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x
+    }
+}
+
+struct Segment {
+    start: Point,
+    end: Point,
+}
+
+#[ensures="result === *p"]
+fn copy(p: &Point) -> Point {
+    Point { x: p.x, y: p.y }
+}
+
+#[requires="*a === *b"]
+#[ensures="a.x == b.x && a.y == b.y"]
+fn same_coordinates(a: &Point, b: &Point) {}
+
+#[requires="a.x == b.x && a.y == b.y"]
+#[ensures="a === b"]
+fn same_points(a: &Point, b: &Point) {}
+
+#[ensures="result.start === result.end"]
+#[ensures="result.start === *p"]
+fn empty_segment(p: &Point) -> Segment {
+    Segment {
+        start: Point { x: p.x, y: p.y },
+        end: Point { x: p.x, y: p.y },
+    }
+}
+
+#[ensures="result === x"]
+fn identity(x: u32) -> u32 {
+    x
+}
+
+// `===` binds tighter than `&&` and `||`.
+#[requires="*a === *b || a.x == 0"]
+#[ensures="a.x == b.x || a.x == 0"]
+fn same_or_zero(a: &Point, b: &Point) {}
+
+#[requires="a.x == b.x && a.y == b.y"]
+#[ensures="(*a === *b && b.x == a.x) || a.x == 0"]
+#[ensures="!(*a === *b) ==> false"]
+fn same_in_parentheses(a: &Point, b: &Point) {}
+
+// The operators in character literals are not operators of the specification.
+#[ensures="result === '|' || result === '='"]
+fn symbol(pipe: bool) -> char {
+    if pipe {
+        '|'
+    } else {
+        '='
+    }
+}
+
+fn main() {}
@@ -0,0 +1,47 @@
+extern crate prusti_contracts;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    start: Point,
+    end: Point,
+}
+
+#[pure]
+fn same_point(a: &Point, b: &Point) -> bool {
+    a == b
+}
+
+#[pure]
+fn is_empty(s: &Segment) -> bool {
+    s.start == s.end
+}
+
+#[requires="a.x == b.x && a.y == b.y"]
+#[ensures="result"]
+fn equal_coordinates(a: &Point, b: &Point) -> bool {
+    same_point(a, b)
+}
+
+#[requires="a.x != b.x"]
+#[ensures="!result"]
+fn different_coordinates(a: &Point, b: &Point) -> bool {
+    same_point(a, b)
+}
+
+#[ensures="result.start == *p && result.end == *p"]
+#[ensures="is_empty(&result)"]
+fn empty_segment(p: &Point) -> Segment {
+    Segment { start: *p, end: *p }
+}
+
+#[requires="s.start != s.end"]
+#[ensures="!is_empty(s)"]
+fn non_empty(s: &Segment) {}
+
+fn main() {}
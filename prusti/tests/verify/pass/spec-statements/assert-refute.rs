@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate prusti_contracts;
+
+struct Counter {
+    value: u32,
+}
+
+#[requires="x < 100"]
+fn assert_precondition(x: u32) -> u32 {
+    prusti_assert!(x < 100);
+    let y = x + 1;
+    prusti_assert!(y <= 100 && y == x + 1);
+    y
+}
+
+fn assert_field(counter: &mut Counter) {
+    counter.value = 5;
+    prusti_assert!(counter.value == 5);
+    prusti_refute!(counter.value == 6);
+}
+
+fn refute_reachable(x: u32) {
+    prusti_refute!(false);
+    if x > 10 {
+        prusti_refute!(false);
+        prusti_refute!(x > 20);
+    }
+}
+
+#[ensures="result >= x"]
+fn refute_then_check(x: u32) -> u32 {
+    prusti_refute!(x == 0);
+    if x < 100 {
+        x + 1
+    } else {
+        x
+    }
+}
+
+fn refute_in_loop(n: u32) {
+    let mut i = 0;
+    #[invariant="i <= n"]
+    while i < n {
+        prusti_refute!(i + 1 == n);
+        i += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+extern crate prusti_contracts;
+
+#[derive(Clone)]
+struct View<'a> {
+    value: &'a u32,
+    count: u32,
+}
+
+fn clone_reference(value: &u32) {
+    let copy = Clone::clone(&value);
+    assert!(*copy == *value);
+}
+
+fn clone_view(value: &u32) {
+    let view = View { value, count: 0 };
+    let copy = view.clone();
+    assert!(*copy.value == *view.value);
+    assert!(*copy.value == *value);
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+extern crate prusti_contracts;
+
+use std::borrow::Cow;
+
+#[pure]
+fn is_owned(c: &Cow<u32>) -> bool {
+    match *c {
+        Cow::Borrowed(_) => false,
+        Cow::Owned(_) => true,
+    }
+}
+
+#[ensures="is_owned(c)"]
+fn make_owned(c: &mut Cow<u32>) {
+    let content = c.to_mut();
+    *content = 42;
+}
+
+#[ensures="is_owned(&result)"]
+fn owned(value: u32) -> Cow<'static, u32> {
+    Cow::Owned(value)
+}
+
+fn copy_on_write(value: &u32) {
+    let mut c = Cow::Borrowed(value);
+    assert!(!is_owned(&c));
+    make_owned(&mut c);
+    assert!(is_owned(&c));
+}
+
+fn test() {
+    let mut c = owned(1);
+    make_owned(&mut c);
+    assert!(is_owned(&c));
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+extern crate prusti_contracts;
+
+#[ensures="v.len() == old(v.len())"]
+fn reset(v: &mut Vec<u32>) {
+    for x in v.iter_mut() {
+        *x = 0;
+    }
+}
+
+fn saturate(v: &mut Vec<i32>) {
+    for x in v.iter_mut() {
+        if *x > 10 {
+            *x = 10;
+        }
+        assert!(*x <= 10);
+    }
+}
+
+#[ensures="v.len() == old(v.len())"]
+fn negate(v: &mut Vec<bool>) {
+    for x in v.iter_mut() {
+        *x = !*x;
+    }
+}
+
+#[requires="v.len() > 0"]
+fn first_exists(v: &mut Vec<u32>) {
+    let mut it = v.iter_mut();
+    assert!(it.next().is_some());
+}
+
+/// The iterator yields the elements of the slice in order.
+#[requires="v.len() >= 2"]
+fn sorted_pair(v: &mut Vec<u32>) {
+    v.sort();
+    let mut it = v.iter_mut();
+    if let Some(a) = it.next() {
+        if let Some(b) = it.next() {
+            assert!(*a <= *b);
+        }
+    }
+}
+
+#[requires="v.len() == 1"]
+fn single(v: &mut Vec<u32>) {
+    let mut it = v.iter_mut();
+    it.next();
+    assert!(it.next().is_none());
+}
+
+fn main() {}
@@ -0,0 +1,47 @@
+extern crate prusti_contracts;
+
+use std::sync::{Mutex, RwLock};
+
+struct Counter {
+    value: u32,
+}
+
+impl Counter {
+    #[pure]
+    #[lock_invariant]
+    fn is_even(&self) -> bool {
+        self.value % 2 == 0
+    }
+}
+
+fn new_counter() -> Mutex<Counter> {
+    Mutex::new(Counter { value: 0 })
+}
+
+#[requires="value % 2 == 0"]
+fn new_shared_counter(value: u32) -> RwLock<Counter> {
+    RwLock::new(Counter { value })
+}
+
+fn increment(counter: &Mutex<Counter>) {
+    let mut guard = counter.lock().unwrap();
+    guard.value += 2;
+}
+
+fn reset(counter: &RwLock<Counter>) {
+    let mut guard = counter.write().unwrap();
+    guard.value = 0;
+}
+
+#[ensures="result % 2 == 0"]
+fn read(counter: &RwLock<Counter>) -> u32 {
+    let guard = counter.read().unwrap();
+    guard.value
+}
+
+fn without_invariant(lock: &Mutex<u32>) {
+    let mut guard = lock.lock().unwrap();
+    *guard = 3;
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+extern crate prusti_contracts;
+
+use std::collections::{BTreeMap, HashMap};
+
+#[ensures="result.len() == 2"]
+fn two_entries() -> HashMap<u32, u32> {
+    let mut map = HashMap::new();
+    map.insert(1, 10);
+    map.insert(2, 20);
+    map
+}
+
+#[requires="map.contains_key(&key)"]
+#[ensures="map.len() == old(map.len())"]
+#[ensures="map.contains_key(&key)"]
+fn overwrite(map: &mut HashMap<u32, u32>, key: u32, value: u32) {
+    let previous = map.insert(key, value);
+    assert!(previous.is_some());
+}
+
+fn lookup() {
+    let mut map = HashMap::new();
+    map.insert(1, 10);
+    map.insert(2, 20);
+    map.insert(3, 30);
+    assert!(map.contains_key(&1));
+    assert!(map[&1] == 10);
+    assert!(map[&3] == 30);
+    overwrite(&mut map, 2, 25);
+    assert!(map.len() == 3);
+}
+
+fn ordered() {
+    let mut map = BTreeMap::new();
+    assert!(map.is_empty());
+    map.insert(1u32, true);
+    assert!(!map.is_empty());
+    assert!(map[&1]);
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+#[requires="x.is_some()"]
+fn get(x: Option<u32>) -> u32 {
+    x.unwrap()
+}
+
+#[requires="x.is_ok()"]
+fn get_ok(x: Result<u32, bool>) -> u32 {
+    x.unwrap()
+}
+
+#[ensures="result.is_some()"]
+fn some() -> Option<u32> {
+    Some(5)
+}
+
+#[ensures="result.is_err()"]
+fn err() -> Result<u32, bool> {
+    Err(false)
+}
+
+fn test() {
+    let x = some();
+    assert!(!x.is_none());
+    get(x);
+}
+
+fn main() {}
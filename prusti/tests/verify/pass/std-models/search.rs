@@ -0,0 +1,48 @@
+extern crate prusti_contracts;
+
+#[requires="v.len() == 1"]
+fn single_any(v: &Vec<u32>) {
+    if v[0] > 3 {
+        assert!(v.iter().any(|x| *x > 3));
+    }
+}
+
+#[requires="v.len() == 1"]
+fn single_all(v: &Vec<i32>) {
+    if v[0] == 0 {
+        assert!(v.iter().all(|x| *x == 0));
+    }
+}
+
+fn empty_all(v: &Vec<u32>) {
+    if v.len() == 0 {
+        assert!(v.iter().all(|x| *x > 3));
+        assert!(!v.iter().any(|x| *x > 3));
+    }
+}
+
+/// The offset returned by `position` is the one of the first element that satisfies the closure.
+fn first_zero(v: &Vec<u32>) {
+    if let Some(p) = v.iter().position(|x| *x == 0) {
+        assert!(p < v.len());
+        assert!(v[p] == 0);
+    }
+}
+
+fn find_large(v: &Vec<u32>) {
+    if let Some(x) = v.iter().find(|x| **x > 3) {
+        assert!(*x > 3);
+    }
+}
+
+/// A search resumes from the ghost index of the iterator.
+#[requires="v.len() == 2"]
+fn resumed_search(v: &Vec<u32>) {
+    if v[1] > 3 {
+        let mut it = v.iter();
+        it.next();
+        assert!(it.any(|x| *x > 3));
+    }
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+#[requires="*a == *b"]
+#[ensures="result == b.len()"]
+fn same_len(a: &String, b: &String) -> usize {
+    a.len()
+}
+
+#[requires="!s.is_empty()"]
+#[ensures="result > 0"]
+fn non_empty(s: &str) -> usize {
+    s.len()
+}
+
+fn literal() {
+    let s = "abc";
+    assert!(s.len() == 3);
+    assert!(!s.is_empty());
+    assert!(non_empty(s) > 0);
+    let empty = "";
+    assert!(empty.is_empty());
+}
+
+fn unicode() {
+    let s = "é";
+    assert!(s.len() == 2);
+}
+
+fn main() {}
@@ -0,0 +1,49 @@
+extern crate prusti_contracts;
+
+use std::thread;
+
+struct Counter {
+    value: u32,
+}
+
+/// The spawned closure owns the captured vector, while the copied integer stays available.
+fn spawn_and_join() {
+    let x = 5;
+    let mut v: Vec<u32> = Vec::new();
+    let handle = thread::spawn(move || {
+        v.push(x);
+        v.len()
+    });
+    assert!(x == 5);
+    match handle.join() {
+        Ok(len) => {
+            let _ = len;
+        }
+        Err(_) => {}
+    }
+}
+
+/// The result of the thread is owned by the caller after the join.
+fn fork_join(c: Counter) -> Counter {
+    let handle = thread::spawn(move || Counter {
+        value: c.value / 2,
+    });
+    match handle.join() {
+        Ok(result) => result,
+        Err(_) => Counter { value: 0 },
+    }
+}
+
+/// Two threads run in parallel on disjoint data.
+#[ensures="result.value == 3"]
+fn parallel(left: Counter, right: Counter) -> Counter {
+    let mut local = Counter { value: 3 };
+    let first = thread::spawn(move || left.value);
+    let second = thread::spawn(move || right.value);
+    local.value += 0;
+    let _ = first.join();
+    let _ = second.join();
+    local
+}
+
+fn main() {}
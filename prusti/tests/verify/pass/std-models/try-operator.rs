@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+struct ParseError {
+    value: u32,
+}
+
+#[ensures="result.is_ok() == (x < 100)"]
+fn check_small(x: u32) -> Result<u32, ParseError> {
+    if x < 100 {
+        Ok(x)
+    } else {
+        Err(ParseError { value: x })
+    }
+}
+
+#[ensures="result.is_ok() == (x < 100 && y < 100)"]
+fn check_both(x: u32, y: u32) -> Result<u32, ParseError> {
+    let a = check_small(x)?;
+    let b = check_small(y)?;
+    Ok(a + b)
+}
+
+#[ensures="x >= 100 ==> result.is_err()"]
+fn check_first(x: u32, y: u32) -> Result<u32, ParseError> {
+    check_small(x)?;
+    Ok(y)
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+extern crate prusti_contracts;
+
+#[requires="v.len() == 2"]
+#[ensures="v.len() == 2"]
+fn swap_ends(v: &mut Vec<u32>) {
+    v.swap(0, 1);
+}
+
+#[ensures="v.len() == old(v.len())"]
+fn sort(v: &mut Vec<i32>) {
+    v.sort_unstable();
+}
+
+fn contains(v: &mut Vec<u32>, x: u32) -> bool {
+    v.sort();
+    v.binary_search(&x).is_ok()
+}
+
+/// Sorting again permutes the same elements, so a found value is found again.
+fn search_twice(v: &mut Vec<u32>, x: u32) {
+    v.sort();
+    let found = v.binary_search(&x).is_ok();
+    v.sort();
+    let found_again = v.binary_search(&x).is_ok();
+    assert!(!found || found_again);
+}
+
+fn test() {
+    let mut v = Vec::new();
+    v.push(3);
+    v.push(1);
+    swap_ends(&mut v);
+    v.sort();
+    assert!(v.len() == 2);
+    v.swap(0, 1);
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+extern crate prusti_contracts;
+
+#[ensures="result.len() == 2"]
+fn two_elements() -> Vec<u32> {
+    let mut v = Vec::new();
+    v.push(1);
+    v.push(2);
+    v
+}
+
+#[requires="!v.is_empty()"]
+#[ensures="v.len() == old(v.len()) - 1"]
+fn remove_last(v: &mut Vec<u32>) -> u32 {
+    v.pop().unwrap()
+}
+
+#[ensures="v.is_empty()"]
+fn drain(v: &mut Vec<u32>) {
+    v.clear();
+}
+
+fn test() {
+    let mut v = two_elements();
+    remove_last(&mut v);
+    remove_last(&mut v);
+    assert!(v.is_empty());
+}
+
+fn main() {}
@@ -0,0 +1,44 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[requires="x < 100"]
+    #[ensures="result >= x"]
+    fn next(&self, x: u32) -> u32;
+}
+
+struct Exact;
+
+struct Inherited;
+
+impl Counter for Exact {
+    #[requires="x < 1000"]
+    #[ensures="result == x + 1"]
+    fn next(&self, x: u32) -> u32 {
+        x + 1
+    }
+}
+
+impl Counter for Inherited {
+    fn next(&self, x: u32) -> u32 {
+        x
+    }
+}
+
+fn use_refined_contract() {
+    let c = Exact;
+    let r = c.next(500);
+    assert!(r == 501);
+}
+
+fn use_inherited_contract() {
+    let c = Inherited;
+    let r = c.next(10);
+    assert!(r >= 10);
+}
+
+fn use_trait_contract<T: Counter>(c: &T) {
+    let r = c.next(10);
+    assert!(r >= 10);
+}
+
+fn main() {}
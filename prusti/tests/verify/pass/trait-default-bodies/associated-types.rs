@@ -0,0 +1,18 @@
+extern crate prusti_contracts;
+
+trait Container {
+    type Item;
+
+    #[pure]
+    fn len(&self) -> usize;
+
+    #[requires="self.len() > 0"]
+    fn first(&self) -> &Self::Item;
+
+    #[requires="self.len() > 0"]
+    fn peek(&self) -> &Self::Item {
+        self.first()
+    }
+}
+
+fn main() {}
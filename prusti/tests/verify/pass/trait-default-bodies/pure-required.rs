@@ -0,0 +1,36 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[pure]
+    fn count(&self) -> usize;
+
+    #[ensures="result == (self.count() == 0)"]
+    fn is_zero(&self) -> bool {
+        self.count() == 0
+    }
+
+    #[requires="!self.is_zero_hint()"]
+    #[ensures="!result"]
+    fn is_zero_if_hinted(&self) -> bool {
+        self.is_zero_hint()
+    }
+
+    #[pure]
+    fn is_zero_hint(&self) -> bool;
+}
+
+struct Zero;
+
+impl Counter for Zero {
+    #[pure]
+    fn count(&self) -> usize {
+        0
+    }
+
+    #[pure]
+    fn is_zero_hint(&self) -> bool {
+        true
+    }
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+extern crate prusti_contracts;
+
+use std::ops::{Add, Sub};
+
+#[arithmetic_newtype]
+struct Count(u32);
+
+impl Add for Count {
+    type Output = Count;
+
+    fn add(self, other: Count) -> Count {
+        Count(self.0 + other.0)
+    }
+}
+
+impl Sub for Count {
+    type Output = Count;
+
+    fn sub(self, other: Count) -> Count {
+        Count(self.0 - other.0)
+    }
+}
+
+#[requires="a.0 == 3 && b.0 == 4"]
+#[ensures="result.0 == 7"]
+fn sum(a: Count, b: Count) -> Count {
+    a + b
+}
+
+#[requires="a.0 >= b.0"]
+#[ensures="result.0 <= old(a.0)"]
+fn difference(a: Count, b: Count) -> Count {
+    a - b
+}
+
+fn main() {}
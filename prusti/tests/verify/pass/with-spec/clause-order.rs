@@ -0,0 +1,35 @@
+extern crate prusti_contracts;
+
+#[pure]
+#[requires="n > 0"]
+fn divides_hundred(n: u32) -> bool {
+    100 % n == 0
+}
+
+#[requires="n > 0"]
+#[requires="100 % n == 0"]
+#[requires="divides_hundred(n)"]
+#[ensures="result > 0"]
+#[ensures="100 % result == 0"]
+#[ensures="divides_hundred(result)"]
+fn divisor(n: u32) -> u32 {
+    n
+}
+
+#[pure]
+#[requires="n > 0"]
+#[requires="divides_hundred(n)"]
+#[ensures="result > 0"]
+#[ensures="divides_hundred(result)"]
+fn same_divisor(n: u32) -> u32 {
+    n
+}
+
+fn test() {
+    let d = divisor(5);
+    assert!(100 % d == 0);
+    let e = same_divisor(d);
+    assert!(100 % e == 0);
+}
+
+fn main() {}
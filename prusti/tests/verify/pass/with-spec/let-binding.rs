@@ -0,0 +1,28 @@
+extern crate prusti_contracts;
+
+#[requires="let sum = a + b in sum > 0 && sum < 100"]
+#[ensures="let doubled = 2 * (a + b) in result == doubled"]
+fn double_sum(a: i32, b: i32) -> i32 {
+    2 * (a + b)
+}
+
+#[requires="x < 10"]
+#[ensures="let small = x < 5 in small ==> result == 0"]
+fn threshold(x: u32) -> u32 {
+    if x < 5 { 0 } else { 1 }
+}
+
+#[requires="0 < len && len < 100"]
+#[ensures="let last = len - 1 in forall(|i: usize| i < len ==> i <= last)"]
+fn quantified(len: usize) {}
+
+#[requires="x < 100"]
+#[ensures="x > 0 ==> let previous = x - 1 in result == previous + 1"]
+fn identity(x: u32) -> u32 {
+    x
+}
+
+fn main() {
+    let r = double_sum(1, 2);
+    assert!(r == 6);
+}
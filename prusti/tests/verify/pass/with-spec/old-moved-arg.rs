@@ -0,0 +1,31 @@
+extern crate prusti_contracts;
+
+struct IntBox {
+    value: i32,
+}
+
+struct Pair {
+    first: IntBox,
+    second: Box<IntBox>,
+}
+
+#[ensures="result == old(my_box.value)"]
+fn unbox(my_box: IntBox) -> i32 {
+    let IntBox { value } = my_box;
+    value
+}
+
+#[ensures="result.value == old(pair.second.value)"]
+#[ensures="result.value + old(pair.first.value) == old(pair.first.value) + old(pair.second.value)"]
+fn take_second(pair: Pair) -> IntBox {
+    *pair.second
+}
+
+#[requires="a.value < 1000"]
+#[ensures="result.value == old(a.value) + 1"]
+fn increment(mut a: IntBox) -> IntBox {
+    a.value += 1;
+    a
+}
+
+fn main() {}
@@ -0,0 +1,55 @@
+extern crate prusti_contracts;
+
+trait Counter {
+    #[pure]
+    fn value(&self) -> u32;
+
+    #[requires="self.value() < 100"]
+    #[ensures="self.value() == old(self.value()) + 1"]
+    fn increment(&mut self);
+}
+
+struct Simple {
+    value: u32,
+}
+
+impl Counter for Simple {
+    #[pure]
+    #[trusted]
+    fn value(&self) -> u32 {
+        self.value
+    }
+
+    #[trusted]
+    fn increment(&mut self) {
+        self.value += 1;
+    }
+}
+
+#[trusted]
+#[ensures="result.value() == 0"]
+fn make() -> impl Counter {
+    Simple { value: 0 }
+}
+
+#[trusted]
+fn make_boxed() -> Box<dyn Counter> {
+    Box::new(Simple { value: 0 })
+}
+
+fn use_opaque() {
+    let mut c = make();
+    c.increment();
+    assert!(c.value() == 1);
+}
+
+fn use_trait_object() {
+    let mut c = make_boxed();
+    if c.value() < 100 {
+        let before = c.value();
+        c.increment();
+        assert!(c.value() == before + 1);
+    }
+}
+
+fn main() {}
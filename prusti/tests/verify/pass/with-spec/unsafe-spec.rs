@@ -0,0 +1,18 @@
+extern crate prusti_contracts;
+
+#[ensures="unsafe_spec(result & 1 == 0) || result > 10"]
+fn big(x: u32) -> u32 {
+    if x > 10 {
+        x
+    } else {
+        20
+    }
+}
+
+#[requires="unsafe_spec(x & 1 == 0) && x < 100"]
+#[ensures="result < 101"]
+fn increment(x: u32) -> u32 {
+    x + 1
+}
+
+fn main() {}
@@ -97,6 +97,7 @@ fn main() {
             // Scala
             java_class!("scala.Some", vec![
                 constructor!(),
+                method!("get"),
             ]),
             java_class!("scala.None$", vec![
                 object_getter!(),
@@ -127,13 +128,17 @@ fn main() {
                 object_getter!(),
                 method!("apply"),
             ]),
+            // Fastparse
+            java_class!("fastparse.core.Parsed$Success", vec![
+                method!("value"),
+            ]),
             // Silicon
             java_class!("viper.silicon.Silicon", vec![
                 constructor!("(Lviper/silver/plugin/PluginAwareReporter;Lscala/collection/Seq;)V"),
             ]),
             // Carbon
             java_class!("viper.carbon.CarbonVerifier", vec![
-                constructor!("()V"),
+                constructor!("(Lviper/silver/plugin/PluginAwareReporter;Lscala/collection/Seq;)V"),
             ]),
             // Silver
             java_class!("viper.silver.plugin.PluginAwareReporter", vec![
@@ -157,6 +162,18 @@ fn main() {
                 object_getter!(),
                 method!("pretty", "(Lviper/silver/ast/Node;)Ljava/lang/String;")
             ]),
+            java_class!("viper.silver.parser.FastParser$", vec![
+                object_getter!(),
+                method!("parse", "(Ljava/lang/String;Ljava/nio/file/Path;Lscala/Option;)Lfastparse/core/Parsed;"),
+            ]),
+            java_class!("viper.silver.parser.Resolver", vec![
+                constructor!(),
+                method!("run"),
+            ]),
+            java_class!("viper.silver.parser.Translator", vec![
+                constructor!(),
+                method!("translate"),
+            ]),
             java_class!("viper.silver.ast.AbstractAssign$", vec![
                 object_getter!(),
                 method!("apply"),
@@ -326,6 +343,10 @@ fn main() {
             java_class!("viper.silver.ast.HasIdentifier", vec![
                 method!("id"),
             ]),
+            java_class!("viper.silver.ast.HasLineColumn", vec![
+                method!("line"),
+                method!("column"),
+            ]),
             java_class!("viper.silver.ast.IdentifierPosition", vec![
                 constructor!(),
             ]),
@@ -602,6 +623,8 @@ fn main() {
                 method!("errors"),
             ]),
             java_class!("viper.silver.verifier.AbstractError", vec![
+                method!("fullId"),
+                method!("readableMessage", "()Ljava/lang/String;"),
             ]),
             java_class!("viper.silver.verifier.VerificationError", vec![
                 method!("id"),
@@ -12,7 +12,12 @@ lazy_static! {
 }
 
 benchmark_main!(ast_factory);
-benchmark_group!(ast_factory, bench_build_program, bench_verify_program);
+benchmark_group!(
+    ast_factory,
+    bench_build_program,
+    bench_parse_program,
+    bench_verify_program
+);
 
 fn bench_build_program(bench: &mut Bencher) {
     let verification_context: VerificationContext = VIPER.new_verification_context();
@@ -21,6 +26,15 @@ fn bench_build_program(bench: &mut Bencher) {
     bench.iter(|| build_program(&ast_factory));
 }
 
+fn bench_parse_program(bench: &mut Bencher) {
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast_factory = verification_context.new_ast_factory();
+    let ast_utils = verification_context.new_ast_utils();
+    let source = ast_utils.pretty_print(build_program(&ast_factory));
+
+    bench.iter(|| ast_utils.parse_program(&source).unwrap());
+}
+
 fn bench_verify_program(bench: &mut Bencher) {
     let verification_context: VerificationContext = VIPER.new_verification_context();
     let ast_factory = verification_context.new_ast_factory();
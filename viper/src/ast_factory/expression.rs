@@ -683,7 +683,7 @@ impl<'a> AstFactory<'a> {
             self.jni.new_seq(&map_to_jobjects!(variables)),
             self.jni.new_seq(&map_to_jobjects!(triggers)),
             expr.to_jobject(),
-            self.no_position().to_jobject()
+            pos.to_jobject()
         )
     }
 
@@ -9,6 +9,29 @@ use jni::objects::JObject;
 use jni::JNIEnv;
 use jni_utils::JniUtils;
 use viper_sys::wrappers::viper::*;
+use std::fmt;
+use viper_sys::wrappers::{fastparse, java, scala};
+
+/// The reason why the source text of a Viper program could not be turned into a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The text is not syntactically valid. Contains the failure reported by the parser.
+    Syntax(String),
+    /// The program is ill-typed or uses undeclared identifiers.
+    Resolution,
+    /// The resolved program could not be translated to an AST.
+    Translation,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Syntax(failure) => write!(f, "syntax error ({})", failure),
+            ParseError::Resolution => write!(f, "the program could not be type-checked"),
+            ParseError::Translation => write!(f, "the program could not be translated"),
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct AstUtils<'a> {
@@ -42,6 +65,54 @@ impl<'a> AstUtils<'a> {
         )
     }
 
+    /// Parse, type-check and translate the source text of a Viper program. The nodes of the
+    /// resulting program are positioned at their line and column in `source`.
+    pub fn parse_program(&self, source: &str) -> Result<Program<'a>, ParseError> {
+        let parser_wrapper = silver::parser::FastParser_object::with(self.env);
+        let parse_result = self.jni.unwrap_result(parser_wrapper.call_parse(
+            self.jni.unwrap_result(parser_wrapper.singleton()),
+            self.jni.new_string(source),
+            self.jni.unwrap_result(
+                java::nio::file::Paths::with(self.env)
+                    .call_get(self.jni.new_string(""), self.jni.new_object_array(0)),
+            ),
+            self.jni.new_option(None),
+        ));
+        if !self
+            .jni
+            .is_instance_of(parse_result, "fastparse/core/Parsed$Success")
+        {
+            return Err(ParseError::Syntax(self.jni.to_string(parse_result)));
+        }
+        let parse_tree = self.jni.unwrap_result(
+            fastparse::core::Parsed_objectSuccess::with(self.env).call_value(parse_result),
+        );
+
+        let resolver_wrapper = silver::parser::Resolver::with(self.env);
+        let resolved_parse_tree = self.jni.unwrap_result(resolver_wrapper.call_run(
+            self.jni.unwrap_result(resolver_wrapper.new(parse_tree)),
+        ));
+        let resolved_parse_tree = self
+            .get_option(resolved_parse_tree)
+            .ok_or(ParseError::Resolution)?;
+
+        let translator_wrapper = silver::parser::Translator::with(self.env);
+        let program = self.jni.unwrap_result(translator_wrapper.call_translate(
+            self.jni.unwrap_result(translator_wrapper.new(resolved_parse_tree)),
+        ));
+        let program = self.get_option(program).ok_or(ParseError::Translation)?;
+        Ok(Program::new(program))
+    }
+
+    /// Converts a Scala Option to a Rust Option<JObject>
+    fn get_option(&self, option: JObject<'a>) -> Option<JObject<'a>> {
+        if self.jni.is_instance_of(option, "scala/Some") {
+            Some(self.jni.unwrap_result(scala::Some::with(self.env).call_get(option)))
+        } else {
+            None
+        }
+    }
+
     pub fn to_string(&self, program: Program) -> String {
         self.jni.to_string(program.to_jobject())
     }
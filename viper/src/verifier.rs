@@ -41,30 +41,31 @@ impl<'a, VerifierState> Verifier<'a, VerifierState> {
     ) -> Verifier<'a, state::Uninitialized> {
         let jni = JniUtils::new(env);
         let verifier_wrapper = silver::verifier::Verifier::with(env);
+        let reporter = if let Some(real_report_path) = report_path {
+            jni.unwrap_result(
+                silver::reporter::CSVReporter::with(env).new(
+                    jni.new_string("csv_reporter"),
+                    jni.new_string(real_report_path.to_str().unwrap()),
+                )
+            )
+        } else {
+            jni.unwrap_result(
+                silver::reporter::NoopReporter_object::with(env).singleton()
+            )
+        };
+        let plugin_aware_reporter = jni.unwrap_result(
+            silver::plugin::PluginAwareReporter::with(&env).new(
+                reporter
+            )
+        );
+        let debug_info = jni.new_seq(&[]);
         let verifier_instance = jni.unwrap_result(match backend {
             VerificationBackend::Silicon => {
-                let reporter = if let Some(real_report_path) = report_path {
-                    jni.unwrap_result(
-                        silver::reporter::CSVReporter::with(env).new(
-                            jni.new_string("csv_reporter"),
-                            jni.new_string(real_report_path.to_str().unwrap()),
-                        )
-                    )
-                } else {
-                    jni.unwrap_result(
-                        silver::reporter::NoopReporter_object::with(env).singleton()
-                    )
-                };
-                let plugin_aware_reporter = jni.unwrap_result(
-                    silver::plugin::PluginAwareReporter::with(&env).new(
-                        reporter
-                    )
-                );
-                let utils = JniUtils::new(env);
-                let debug_info = utils.new_seq(&[]);
                 silicon::Silicon::with(env).new(plugin_aware_reporter, debug_info)
             }
-            VerificationBackend::Carbon => carbon::CarbonVerifier::with(env).new(),
+            VerificationBackend::Carbon => {
+                carbon::CarbonVerifier::with(env).new(plugin_aware_reporter, debug_info)
+            }
         });
 
         let name = jni.to_string(jni.unwrap_result(verifier_wrapper.call_name(verifier_instance)));
@@ -180,8 +181,6 @@ impl<'a> Verifier<'a, state::Started> {
 
             let verification_error_wrapper = silver::verifier::VerificationError::with(self.env);
 
-            let has_identifier_wrapper = silver::ast::HasIdentifier::with(self.env);
-
             let error_reason_wrapper = silver::verifier::ErrorReason::with(self.env);
 
             for viper_error in viper_errors {
@@ -189,6 +188,24 @@ impl<'a> Verifier<'a, state::Started> {
                     .jni
                     .is_instance_of(viper_error, "viper/silver/verifier/VerificationError");
 
+                let is_timeout = self
+                    .jni
+                    .is_instance_of(viper_error, "viper/silver/verifier/TimeoutOccurred");
+
+                if is_timeout {
+                    // Reported by Carbon, it has no position
+                    let abstract_error_wrapper = silver::verifier::AbstractError::with(self.env);
+                    let error_full_id = self.jni.get_string(
+                        self.jni
+                            .unwrap_result(abstract_error_wrapper.call_fullId(viper_error)),
+                    );
+                    let message = self.jni.to_string(self.jni.unwrap_result(
+                        abstract_error_wrapper.call_readableMessage(viper_error),
+                    ));
+                    errors.push(VerificationError::new(error_full_id, None, None, message));
+                    continue;
+                }
+
                 if !is_verification_error {
                     let is_aborted_exceptionally = self
                         .jni
@@ -223,23 +240,13 @@ impl<'a> Verifier<'a, state::Started> {
                     .jni
                     .unwrap_result(error_reason_wrapper.call_pos(reason));
 
-                let reason_pos_id = if self
-                    .jni
-                    .is_instance_of(reason_pos, "viper/silver/ast/HasIdentifier")
-                {
-                    Some(
-                        self.jni.get_string(
-                            self.jni
-                                .unwrap_result(has_identifier_wrapper.call_id(reason_pos)),
-                        ),
-                    )
-                } else {
+                let reason_pos_id = self.position_id(reason_pos);
+                if reason_pos_id.is_none() {
                     debug!(
                         "The verifier returned an error whose offending node position has no identifier: {:?}",
                         self.jni.to_string(viper_error)
                     );
-                    None
-                };
+                }
 
                 let error_full_id = self.jni.get_string(
                     self.jni
@@ -255,21 +262,13 @@ impl<'a> Verifier<'a, state::Started> {
                         verification_error_wrapper.call_readableMessage(viper_error),
                     ));
 
-                let pos_id =
-                    if self
-                        .jni
-                        .is_instance_of(pos, "viper/silver/ast/HasIdentifier")
-                    {
-                        Some(self.jni.get_string(
-                            self.jni.unwrap_result(has_identifier_wrapper.call_id(pos)),
-                        ))
-                    } else {
-                        debug!(
-                            "The verifier returned an error whose position has no identifier: {:?}",
-                            self.jni.to_string(viper_error)
-                        );
-                        None
-                    };
+                let pos_id = self.position_id(pos);
+                if pos_id.is_none() {
+                    debug!(
+                        "The verifier returned an error whose position has no identifier: {:?}",
+                        self.jni.to_string(viper_error)
+                    );
+                }
 
                 errors.push(VerificationError::new(
                     error_full_id,
@@ -284,4 +283,21 @@ impl<'a> Verifier<'a, state::Started> {
             VerificationResult::Success()
         }
     }
+
+    /// The identifier of a position: the id of the positions built by `AstFactory`, or
+    /// `line:column` for the positions of the programs built by `AstUtils::parse_program`.
+    fn position_id(&self, pos: JObject) -> Option<String> {
+        if self.jni.is_instance_of(pos, "viper/silver/ast/HasIdentifier") {
+            Some(self.jni.get_string(self.jni.unwrap_result(
+                silver::ast::HasIdentifier::with(self.env).call_id(pos),
+            )))
+        } else if self.jni.is_instance_of(pos, "viper/silver/ast/HasLineColumn") {
+            let has_line_column_wrapper = silver::ast::HasLineColumn::with(self.env);
+            let line = self.jni.unwrap_result(has_line_column_wrapper.call_line(pos));
+            let column = self.jni.unwrap_result(has_line_column_wrapper.call_column(pos));
+            Some(format!("{}:{}", line, column))
+        } else {
+            None
+        }
+    }
 }
@@ -0,0 +1,91 @@
+extern crate env_logger;
+extern crate error_chain;
+#[macro_use]
+extern crate lazy_static;
+extern crate viper;
+
+use std::env;
+use std::path::Path;
+use std::sync::{Once, ONCE_INIT};
+use viper::*;
+
+static INIT: Once = ONCE_INIT;
+
+lazy_static! {
+    static ref VIPER: Viper = Viper::new_with_args(vec![], VerificationBackend::Carbon);
+}
+
+/// Setup function that is only run once, even if called multiple times.
+fn setup() {
+    INIT.call_once(|| {
+        env_logger::init();
+    });
+}
+
+/// Carbon needs Boogie, which is looked up in the same way as the verification context does.
+fn boogie_installed() -> bool {
+    vec![env::var("BOOGIE_PATH").ok(), env::var("BOOGIE_EXE").ok()]
+        .into_iter()
+        .filter_map(|path| path)
+        .any(|path| Path::new(&path).exists())
+}
+
+#[test]
+fn carbon_success_with_empty_program() {
+    if !boogie_installed() {
+        eprintln!("Skipped because Boogie is not installed; set BOOGIE_EXE to run it");
+        return;
+    }
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+
+    let ast = verification_context.new_ast_factory();
+
+    let program = ast.program(&[], &[], &[], &[], &[]);
+
+    let verifier = verification_context.new_verifier(viper::VerificationBackend::Carbon, None);
+
+    let verification_result = verifier.verify(program);
+
+    assert_eq!(verification_result, VerificationResult::Success());
+}
+
+#[test]
+fn carbon_failure_with_assert_false() {
+    if !boogie_installed() {
+        eprintln!("Skipped because Boogie is not installed; set BOOGIE_EXE to run it");
+        return;
+    }
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast = verification_context.new_ast_factory();
+
+    let false_lit = ast.false_lit();
+
+    let pos = ast.identifier_position(0, 0, "pos-id:123");
+
+    let assertion = ast.assert(false_lit, pos);
+
+    let body = ast.seqn(&[assertion], &[]);
+
+    let method = ast.method("foo", &[], &[], &[], &[], Some(body));
+
+    let program = ast.program(&[], &[], &[], &[], &[method]);
+
+    let verifier = verification_context.new_verifier(viper::VerificationBackend::Carbon, None);
+
+    let verification_result = verifier.verify(program);
+
+    if let VerificationResult::Failure(errors) = verification_result {
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].full_id,
+            "assert.failed:assertion.false".to_string()
+        );
+        assert_eq!(errors[0].pos_id, Some("pos-id:123".to_string()));
+    } else {
+        assert!(false)
+    }
+}
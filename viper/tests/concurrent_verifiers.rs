@@ -14,7 +14,7 @@ lazy_static! {
 
 /// Regression test for the following bug:
 /// <https://bitbucket.org/viperproject/silicon/issues/315/exception-while-building-silicon-instances>
-#[ignore] // Ignored because Prusti doesn't need to concurrently verify programs
+#[ignore] // Ignored because it is slow; run it when changing the parallel verification
 #[test]
 fn concurrent_verifier_initialization() {
     env_logger::init();
@@ -0,0 +1,91 @@
+extern crate env_logger;
+extern crate error_chain;
+#[macro_use]
+extern crate lazy_static;
+extern crate viper;
+
+use std::sync::{Once, ONCE_INIT};
+use viper::*;
+
+static INIT: Once = ONCE_INIT;
+
+lazy_static! {
+    static ref VIPER: Viper = Viper::new();
+}
+
+/// Setup function that is only run once, even if called multiple times.
+fn setup() {
+    INIT.call_once(|| {
+        env_logger::init();
+    });
+}
+
+#[test]
+fn success_with_parsed_program() {
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast_utils = verification_context.new_ast_utils();
+
+    let program = ast_utils.parse_program(
+        "method foo(x: Int) returns (y: Int)\n  ensures y > x\n{\n  y := x + 1\n}\n",
+    ).unwrap();
+
+    let verifier = verification_context.new_verifier(viper::VerificationBackend::Silicon, None);
+
+    let verification_result = verifier.verify(program);
+
+    assert_eq!(verification_result, VerificationResult::Success());
+}
+
+#[test]
+fn failure_with_parsed_assert_false() {
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast_utils = verification_context.new_ast_utils();
+
+    let program = ast_utils
+        .parse_program("method foo()\n{\n  assert false\n}\n")
+        .unwrap();
+
+    let verifier = verification_context.new_verifier(viper::VerificationBackend::Silicon, None);
+
+    let verification_result = verifier.verify(program);
+
+    if let VerificationResult::Failure(errors) = verification_result {
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].full_id,
+            "assert.failed:assertion.false".to_string()
+        );
+        assert_eq!(errors[0].pos_id, Some("3:3".to_string()));
+    } else {
+        assert!(false)
+    }
+}
+
+#[test]
+fn syntax_error_in_parsed_program() {
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast_utils = verification_context.new_ast_utils();
+
+    match ast_utils.parse_program("method foo(\n") {
+        Err(ParseError::Syntax(_)) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn resolution_error_in_parsed_program() {
+    setup();
+
+    let verification_context: VerificationContext = VIPER.new_verification_context();
+    let ast_utils = verification_context.new_ast_utils();
+
+    let result = ast_utils.parse_program("method foo()\n{\n  x := 1\n}\n");
+
+    assert_eq!(result.err(), Some(ParseError::Resolution));
+}